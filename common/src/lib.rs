@@ -1,2 +1,3 @@
 pub mod printer;
 pub mod protocol;
+pub mod scale;
@@ -5,6 +5,8 @@ use encoding::EncoderTrap;
 use glob::glob;
 use recibo::{Alignment, Encoder, GraphicSize, Printer, FileDriver};
 
+use crate::protocol::{format_amount, GiftCardSummary, PrintReceiptJob, TaxSummary};
+
 // ESC/POS "select character code table" page for Windows-1252 (WPC1252).
 // Thermal printers don't understand UTF-8: they map each byte through a
 // single-byte code page. We encode text as Windows-1252 (which contains the
@@ -117,8 +119,8 @@ pub fn print_credentials(
     printer.bold(false)?;
     printer.feed(1)?;
     printer.align(Alignment::Left)?;
-    printer.text(&format!("  Username: {}\n", username))?;
-    printer.text(&format!("  PIN:      {}\n", pin))?;
+    printer.text(format!("  Username: {}\n", username))?;
+    printer.text(format!("  PIN:      {}\n", pin))?;
     printer.feed(1)?;
     printer.align(Alignment::Center)?;
     printer.text("Please change your PIN\n")?;
@@ -134,17 +136,27 @@ fn fit_left(text: &str, width: usize) -> String {
     format!("{:<width$}", s, width = width)
 }
 
-/// Print a sales breakdown for a period: per-item quantity sold and total value.
+/// The figures of a sales report to print. Amounts are in minor units with
+/// `decimals` decimal places; items are `(name, quantity sold, total value)`.
+pub struct SalesReportSummary {
+    pub period: String,
+    pub currency: String,
+    pub decimals: u32,
+    pub items: Vec<(String, u32, i64)>,
+    pub total_items_sold: u32,
+    pub total_revenue: i64,
+    pub taxes: Vec<TaxSummary>,
+}
+
+/// Print a sales breakdown for a period: per-item quantity sold and total value,
+/// followed by net, tax and gross per tax rate.
 pub fn print_sales_report(
     printer: &mut Printer,
-    period: &str,
-    currency: &str,
-    items: Vec<(String, u32, f32)>,
-    total_items_sold: u32,
-    total_revenue: f32,
+    report: &SalesReportSummary,
     datetime: DateTime<Local>,
     logo_path: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
+    let decimals = report.decimals;
     printer.init()?;
     select_codepage(printer)?;
     // Open the cash drawer when the sales report is printed (end-of-day cash count).
@@ -160,15 +172,15 @@ pub fn print_sales_report(
     printer.bold(true)?;
     printer.text("Sales Report\n")?;
     printer.bold(false)?;
-    printer.text(&format!("{}\n", period))?;
+    printer.text(format!("{}\n", report.period))?;
     printer.text("------------------------------------------------\n")?;
 
     printer.align(Alignment::Left)?;
     // Columns: name (26) | qty (6) | total (16) = 48 chars
-    printer.text(&format!("{}{:>6}{:>16}\n", fit_left("Item", 26), "Qty", "Total"))?;
+    printer.text(format!("{}{:>6}{:>16}\n", fit_left("Item", 26), "Qty", "Total"))?;
     printer.text("------------------------------------------------\n")?;
-    for (name, qty, revenue) in &items {
-        let line = format!("{}{:>6}{:>16.2}\n", fit_left(name, 26), qty, revenue);
+    for (name, qty, revenue) in &report.items {
+        let line = format!("{}{:>6}{:>16}\n", fit_left(name, 26), qty, format_amount(*revenue, decimals));
         printer.text(&line)?;
     }
 
@@ -176,11 +188,14 @@ pub fn print_sales_report(
     printer.text("------------------------------------------------\n")?;
     printer.align(Alignment::Left)?;
     printer.bold(true)?;
-    printer.text(&format!("Items sold: {}\n", total_items_sold))?;
-    printer.text(&format!("TOTAL {}: {:.2}\n", currency, total_revenue))?;
+    printer.text(format!("Items sold: {}\n", report.total_items_sold))?;
+    printer.text(format!("TOTAL {}: {}\n", report.currency, format_amount(report.total_revenue, decimals)))?;
     printer.bold(false)?;
     printer.feed(1)?;
-    printer.text(&format!(
+    if !report.taxes.is_empty() {
+        print_tax_table(printer, &report.taxes, decimals)?;
+    }
+    printer.text(format!(
         "Printed: {}\n",
         datetime.format("%Y-%m-%d %H:%M:%S")
     ))?;
@@ -189,11 +204,186 @@ pub fn print_sales_report(
     Ok(())
 }
 
+/// The figures of a Z-closing or X-report to print. Amounts are in minor
+/// units with `decimals` decimal places; tenders and users are
+/// `(name, transactions, amount)`.
+pub struct ClosingSummary {
+    pub title: String,
+    pub period: String,
+    pub closed_by: Option<String>,
+    pub currency: String,
+    pub decimals: u32,
+    pub revenue: i64,
+    pub transaction_count: i64,
+    pub refund_count: i64,
+    pub refunds: i64,
+    pub cancellation_count: i64,
+    pub cancellations: i64,
+    pub tips: i64,
+    pub rounding: i64,
+    pub tenders: Vec<(String, i64, i64)>,
+    pub taxes: Vec<TaxSummary>,
+    pub users: Vec<(String, i64, i64)>,
+}
+
+/// Print a Z-closing or X-report: revenue and counts, then the totals per
+/// tender, per tax rate and per user. With `open_drawer` the cash drawer
+/// opens too, for counting.
+pub fn print_closing(
+    printer: &mut Printer,
+    closing: &ClosingSummary,
+    open_drawer: bool,
+    datetime: DateTime<Local>,
+    logo_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let amount = |value: i64| format_amount(value, closing.decimals);
+    // Columns: label (26) | count (6) | amount (16) = 48 chars
+    let row = |label: &str, count: i64, value: i64| {
+        format!("{}{:>6}{:>16}\n", fit_left(label, 26), count, amount(value))
+    };
+
+    printer.init()?;
+    select_codepage(printer)?;
+    if open_drawer {
+        open_cash_drawer(printer)?;
+    }
+    printer.align(Alignment::Center)?;
+    printer.linespacing(1)?;
+    if let Some(logo) = logo_path {
+        let logo_owned = logo.to_string();
+        printer.graphic(move |builder| {
+            builder.path(&logo_owned).size(GraphicSize::Normal)
+        })?;
+    }
+    printer.bold(true)?;
+    printer.text(format!("{}\n", closing.title))?;
+    printer.bold(false)?;
+    printer.text(format!("{}\n", closing.period))?;
+    if let Some(user) = &closing.closed_by {
+        printer.text(format!("Closed by {}\n", user))?;
+    }
+    printer.text("------------------------------------------------\n")?;
+
+    printer.align(Alignment::Left)?;
+    printer.text(row("Sales", closing.transaction_count, closing.revenue - closing.refunds))?;
+    printer.text(row("Refunds", closing.refund_count, closing.refunds))?;
+    printer.bold(true)?;
+    printer.text(format!("TOTAL {}: {}\n", closing.currency, amount(closing.revenue)))?;
+    printer.bold(false)?;
+    if closing.tips != 0 {
+        printer.text(format!("Tips: {:>36}\n", amount(closing.tips)))?;
+    }
+    if closing.rounding != 0 {
+        printer.text(format!("Rounding: {:>32}\n", amount(closing.rounding)))?;
+    }
+    printer.text(row("Cancelled sales", closing.cancellation_count, closing.cancellations))?;
+    printer.feed(1)?;
+
+    if !closing.tenders.is_empty() {
+        printer.text(format!("{}{:>6}{:>16}\n", fit_left("Tender", 26), "Count", "Amount"))?;
+        for (tender, count, value) in &closing.tenders {
+            printer.text(row(tender_label(tender), *count, *value))?;
+        }
+        printer.feed(1)?;
+    }
+    if !closing.taxes.is_empty() {
+        print_tax_table(printer, &closing.taxes, closing.decimals)?;
+    }
+    if !closing.users.is_empty() {
+        printer.text(format!("{}{:>6}{:>16}\n", fit_left("User", 26), "Count", "Amount"))?;
+        for (user, count, value) in &closing.users {
+            let user = if user.is_empty() { "-" } else { user.as_str() };
+            printer.text(row(user, *count, *value))?;
+        }
+        printer.feed(1)?;
+    }
+    printer.text(format!(
+        "Printed: {}\n",
+        datetime.format("%Y-%m-%d %H:%M:%S")
+    ))?;
+    printer.feed(6)?;
+    printer.cut()?;
+    Ok(())
+}
+
+/// Receipt label for a tender type as stored on payments ("cash", "card", ...).
+fn tender_label(tender: &str) -> &str {
+    match tender {
+        "cash" => "Cash",
+        "card" => "Card",
+        "voucher" => "Voucher",
+        "other" => "Other",
+        "gift_card" => "Gift card",
+        "account" => "On account",
+        other => other,
+    }
+}
+
+/// Print the subtotal and the tax added per rate, for sales where tax is
+/// added on top of the item prices. Returns the total including tax.
+fn print_added_tax(
+    printer: &mut Printer,
+    job: &PrintReceiptJob,
+    subtotal: i64,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    printer.text(format!("Subtotal: {:>32}\n", format_amount(subtotal, job.decimals)))?;
+    let mut total = subtotal;
+    for t in &job.taxes {
+        let label = format!("Tax {}%:", t.rate);
+        printer.text(format!("{:<12}{:>36}\n", label, format_amount(t.tax, job.decimals)))?;
+        total += t.tax;
+    }
+    Ok(total)
+}
+
+/// Print the tip and the cash rounding under the (bold) total, if there are
+/// any, followed by what is paid with them under `due_label`.
+fn print_tip_and_rounding(
+    printer: &mut Printer,
+    job: &PrintReceiptJob,
+    total: i64,
+    due_label: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if job.tip == 0 && job.rounding == 0 {
+        return Ok(());
+    }
+    printer.bold(false)?;
+    if job.tip != 0 {
+        printer.text(format!("Tip: {:>37}\n", format_amount(job.tip, job.decimals)))?;
+    }
+    if job.rounding != 0 {
+        printer.text(format!("Rounding: {:>32}\n", format_amount(job.rounding, job.decimals)))?;
+    }
+    printer.bold(true)?;
+    let label = format!("{}:", due_label);
+    let due = format_amount(total + job.tip + job.rounding, job.decimals);
+    printer.text(format!("{:<12}{:>30}\n", label, due))?;
+    Ok(())
+}
+
+/// Print a net/tax/gross table, one row per tax rate.
+fn print_tax_table(
+    printer: &mut Printer,
+    taxes: &[TaxSummary],
+    decimals: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    printer.text(format!("{:<12}{:>12}{:>12}{:>12}\n", "Rate", "Net", "Tax", "Gross"))?;
+    for t in taxes {
+        printer.text(format!(
+            "{:<12}{:>12}{:>12}{:>12}\n",
+            format!("{}%", t.rate),
+            format_amount(t.net, decimals),
+            format_amount(t.tax, decimals),
+            format_amount(t.gross, decimals)
+        ))?;
+    }
+    printer.feed(1)?;
+    Ok(())
+}
+
 pub fn print_receipt(
     printer: &mut Printer,
-    items: Vec<(String, u32, f32)>,
-    paid_amount: f32,
-    change: f32,
+    job: &PrintReceiptJob,
     datetime: DateTime<Local>,
     logo_path: Option<&str>,
 ) -> Result<(), Box<dyn std::error::Error>> {
@@ -214,38 +404,229 @@ pub fn print_receipt(
     printer.text("------------------------------------------------\n")?;
 
     printer.align(Alignment::Left)?;
-    let mut total = 0.0;
-    for (name, qty, price) in &items {
-        let line = format!("{:<20} {:>2} x {:>18.2}\n", name, qty, price);
+    let mut total = 0;
+    for (i, (name, qty, price)) in job.items.iter().enumerate() {
+        let line = format!("{:<20} {:>2} x {:>18}\n", name, qty, format_amount(*price, job.decimals));
         printer.text(&line)?;
-        total += (*qty as f32) * price;
+        print_item_modifiers(printer, job, i)?;
+        total += *qty as i64 * price;
     }
+    total += print_discounts(printer, job)?;
 
     printer.align(Alignment::Center)?;
     printer.text("------------------------------------------------\n")?;
     printer.align(Alignment::Left)?;
+    if job.tax_exclusive {
+        total = print_added_tax(printer, job, total)?;
+    }
     printer.bold(true)?;
-    printer.text(&format!("TOTAL: {:>35.2}\n", total))?;
+    printer.text(format!("TOTAL: {:>35}\n", format_amount(total, job.decimals)))?;
+    print_tip_and_rounding(printer, job, total, "AMOUNT DUE")?;
     printer.text("------------------------------------------------\n")?;
     printer.feed(1)?;
     printer.bold(false)?;
-    printer.text(&format!(
+    if !job.taxes.is_empty() {
+        print_tax_table(printer, &job.taxes, job.decimals)?;
+    }
+    printer.text(format!(
         "Date: {}\n",
         datetime.format("%Y-%m-%d %H:%M:%S")
     ))?;
-    printer.text(&format!("Cash: {:.2}\n", paid_amount))?;
-    printer.text(&format!("Change: {:.2}\n", change))?;
+    print_journal_reference(printer, job)?;
+    if job.payments.is_empty() {
+        printer.text(format!("Cash: {}\n", format_amount(job.paid_amount, job.decimals)))?;
+    } else {
+        for (tender, amount) in &job.payments {
+            printer.text(format!("{}: {}\n", tender_label(tender), format_amount(*amount, job.decimals)))?;
+        }
+    }
+    printer.text(format!("Change: {}\n", format_amount(job.change, job.decimals)))?;
     printer.feed(1)?;
+    print_gift_cards(printer, &job.gift_cards, job.decimals)?;
+    if let Some((earned, balance)) = job.loyalty_points {
+        printer.align(Alignment::Left)?;
+        printer.text(format!("{}{:>18}\n", fit_left("Loyalty points earned", 30), earned))?;
+        printer.text(format!("{}{:>18}\n", fit_left("Loyalty points balance", 30), balance))?;
+        printer.feed(1)?;
+    }
+    if !print_fiscal_signature(printer, job)? {
+        printer.align(Alignment::Center)?;
+        printer.qr(|builder| {
+            builder.size(200).text(format!(
+                "{}|Total:{}|Given:{}|Change:{}",
+                datetime.format("%Y-%m-%d %H:%M:%S"),
+                format_amount(total, job.decimals),
+                format_amount(job.paid_amount, job.decimals),
+                format_amount(job.change, job.decimals)
+            ))
+        })?;
+    }
+    printer.feed(6)?;
+    printer.cut()?;
+    Ok(())
+}
+
+/// Print the receipt number and short journal hash of the sale, if any.
+fn print_journal_reference(
+    printer: &mut Printer,
+    job: &PrintReceiptJob,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some((number, hash)) = &job.journal {
+        printer.text(format!("Receipt no: {}\n", number))?;
+        printer.text(format!("Journal: {}\n", hash))?;
+    }
+    Ok(())
+}
+
+/// Print the fiscal signature counter and QR code of the sale, or why it
+/// could not be signed. Returns whether a QR code was printed.
+fn print_fiscal_signature(
+    printer: &mut Printer,
+    job: &PrintReceiptJob,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    if let Some(error) = &job.fiscal_error {
+        printer.align(Alignment::Center)?;
+        printer.bold(true)?;
+        printer.text("NOT SIGNED\n")?;
+        printer.bold(false)?;
+        printer.text(format!("{}\n", error))?;
+        printer.feed(1)?;
+    }
+    let Some(fiscal) = &job.fiscal else {
+        return Ok(false);
+    };
+    printer.align(Alignment::Left)?;
+    printer.text(format!("Signature counter: {}\n", fiscal.counter))?;
     printer.align(Alignment::Center)?;
-    printer.qr(|builder| {
-        builder.size(200).text(&format!(
-            "{}|Total:{}|Given:{}|Change:{}",
-            datetime.format("%Y-%m-%d %H:%M:%S"),
-            total,
-            paid_amount,
-            change
-        ))
-    })?;
+    let qr = fiscal.qr.clone();
+    printer.qr(|builder| builder.size(200).text(&qr))?;
+    Ok(true)
+}
+
+/// Print the weight and modifiers of the `index`th item, indented under it.
+fn print_item_modifiers(
+    printer: &mut Printer,
+    job: &PrintReceiptJob,
+    index: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(Some((kg, unit, unit_price))) = job.weights.get(index) {
+        printer.text(format!("  {:.3} kg x {}/{}\n", kg, format_amount(*unit_price, job.decimals), unit))?;
+    }
+    for label in job.modifiers.get(index).into_iter().flatten() {
+        printer.text(format!("  + {}\n", label))?;
+    }
+    Ok(())
+}
+
+/// Print the discount lines under the items. Returns their sum.
+fn print_discounts(
+    printer: &mut Printer,
+    job: &PrintReceiptJob,
+) -> Result<i64, Box<dyn std::error::Error>> {
+    let mut sum = 0;
+    for (label, amount) in &job.discounts {
+        printer.text(format!("{}{:>18}\n", fit_left(label, 26), format_amount(*amount, job.decimals)))?;
+        sum += amount;
+    }
+    Ok(sum)
+}
+
+/// Print the balance of each gift card of a sale. Cards that were topped up
+/// also get their code as a QR code to scan when redeeming it.
+fn print_gift_cards(
+    printer: &mut Printer,
+    cards: &[GiftCardSummary],
+    decimals: u32,
+) -> Result<(), Box<dyn std::error::Error>> {
+    for card in cards {
+        printer.align(Alignment::Left)?;
+        printer.text(format!(
+            "{}{:>18}\n",
+            fit_left(&format!("Gift card {} balance", mask_card_code(&card.code)), 30),
+            format_amount(card.balance, decimals)
+        ))?;
+        if card.print_code {
+            printer.align(Alignment::Center)?;
+            let code = card.code.clone();
+            printer.qr(|builder| builder.size(200).text(&code))?;
+            printer.text(format!("{}\n", card.code))?;
+        }
+        printer.feed(1)?;
+    }
+    Ok(())
+}
+
+/// A gift card code with all but its last four characters hidden.
+fn mask_card_code(code: &str) -> String {
+    let chars: Vec<char> = code.chars().collect();
+    let shown: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    format!("****{}", shown)
+}
+
+/// Print a refund receipt for returned lines of an earlier sale.
+pub fn print_refund_receipt(
+    printer: &mut Printer,
+    job: &PrintReceiptJob,
+    datetime: DateTime<Local>,
+    logo_path: Option<&str>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    printer.init()?;
+    select_codepage(printer)?;
+    // Cash refunds are paid out of the drawer.
+    open_cash_drawer(printer)?;
+    printer.align(Alignment::Center)?;
+    printer.linespacing(1)?;
+    if let Some(logo) = logo_path {
+        let logo_owned = logo.to_string();
+        printer.graphic(move |builder| {
+            builder
+                .path(&logo_owned)
+                .size(GraphicSize::Normal)
+        })?;
+    }
+    printer.bold(true)?;
+    printer.text("REFUND\n")?;
+    printer.bold(false)?;
+    if let Some(original) = &job.refund_of {
+        printer.text(format!("Original sale: {}\n", original))?;
+    }
+    printer.text("------------------------------------------------\n")?;
+
+    printer.align(Alignment::Left)?;
+    let mut total = 0;
+    for (i, (name, qty, price)) in job.items.iter().enumerate() {
+        let line = format!("{:<20} {:>2} x {:>18}\n", name, qty, format_amount(-price, job.decimals));
+        printer.text(&line)?;
+        print_item_modifiers(printer, job, i)?;
+        total -= *qty as i64 * price;
+    }
+    total += print_discounts(printer, job)?;
+
+    printer.align(Alignment::Center)?;
+    printer.text("------------------------------------------------\n")?;
+    printer.align(Alignment::Left)?;
+    if job.tax_exclusive {
+        total = print_added_tax(printer, job, total)?;
+    }
+    printer.bold(true)?;
+    printer.text(format!("REFUND TOTAL: {:>28}\n", format_amount(total, job.decimals)))?;
+    print_tip_and_rounding(printer, job, total, "PAID OUT")?;
+    printer.text("------------------------------------------------\n")?;
+    printer.feed(1)?;
+    printer.bold(false)?;
+    if !job.taxes.is_empty() {
+        print_tax_table(printer, &job.taxes, job.decimals)?;
+    }
+    printer.text(format!(
+        "Date: {}\n",
+        datetime.format("%Y-%m-%d %H:%M:%S")
+    ))?;
+    print_journal_reference(printer, job)?;
+    for (tender, amount) in &job.payments {
+        printer.text(format!("Refunded ({}): {}\n", tender_label(tender), format_amount(-amount, job.decimals)))?;
+    }
+    printer.feed(1)?;
+    print_fiscal_signature(printer, job)?;
     printer.feed(6)?;
     printer.cut()?;
     Ok(())
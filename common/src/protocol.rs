@@ -1,11 +1,117 @@
 use serde::{Deserialize, Serialize};
 
+/// Formats an amount given in minor units (e.g. cents) with `decimals`
+/// decimal places, e.g. `format_amount(-1205, 2)` is "-12.05".
+pub fn format_amount(minor: i64, decimals: u32) -> String {
+    let sign = if minor < 0 { "-" } else { "" };
+    let abs = minor.unsigned_abs();
+    if decimals == 0 {
+        return format!("{}{}", sign, abs);
+    }
+    let unit = 10_u64.pow(decimals);
+    format!("{}{}.{:0width$}", sign, abs / unit, abs % unit, width = decimals as usize)
+}
+
+/// A receipt to print. All amounts are integers in minor units of the
+/// currency (e.g. cents), so the receipt adds up exactly; `decimals` says
+/// how many decimal places to print them with.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PrintReceiptJob {
-    pub items: Vec<(String, u32, f32)>,
-    pub paid_amount: f32,
-    pub change: f32,
+    pub items: Vec<(String, u32, i64)>,
+    /// Modifier labels per entry of `items`, e.g. `["Oat milk +0.50"]`,
+    /// printed under the item. The item price already includes them.
+    #[serde(default)]
+    pub modifiers: Vec<Vec<String>>,
+    /// Weight details per entry of `items` for goods sold by weight, as
+    /// `(weight in kg, price unit, price per unit)`, e.g. `(0.535, "kg", 299)`,
+    /// printed under the item. `None` for items sold per piece.
+    #[serde(default)]
+    pub weights: Vec<Option<(f32, String, i64)>>,
+    /// Discount lines as `(label, amount)`, e.g. `("Happy hour", -200)`,
+    /// printed after the items. The amounts are signed and added to the total:
+    /// negative on a sale, positive on a refund giving a discount back.
+    #[serde(default)]
+    pub discounts: Vec<(String, i64)>,
+    /// Tenders that settled the sale as `(tender, amount)`, e.g. `("card", 1250)`.
+    /// Empty for jobs from servers that predate split payments, in which case
+    /// the receipt shows `paid_amount` as a single cash line.
+    #[serde(default)]
+    pub payments: Vec<(String, i64)>,
+    pub paid_amount: i64,
+    pub change: i64,
+    /// The tip paid on top of the total. It is part of `paid_amount` and is
+    /// printed after the total; zero means no tip line.
+    #[serde(default)]
+    pub tip: i64,
+    /// What cash rounding added to (negative: took off) the amount paid,
+    /// printed after the total; zero means no rounding line.
+    #[serde(default)]
+    pub rounding: i64,
+    /// Decimal places of the currency the amounts are in, e.g. 2.
+    pub decimals: u32,
     pub datetime: String,
+    /// Set for refunds: a reference to the original sale (its date and short
+    /// id). The job is then printed as a refund receipt, with `items` holding
+    /// the returned quantities and `payments` the (negative) amounts paid back.
+    #[serde(default)]
+    pub refund_of: Option<String>,
+    /// Net, tax and gross per tax rate. Empty when no rates are configured,
+    /// in which case no tax section is printed.
+    #[serde(default)]
+    pub taxes: Vec<TaxSummary>,
+    /// Whether tax is added on top of the item prices (printed between the
+    /// subtotal and the total) rather than included in them.
+    #[serde(default)]
+    pub tax_exclusive: bool,
+    /// Gift cards topped up or paid with in the sale, printed after the
+    /// payments with their new balance.
+    #[serde(default)]
+    pub gift_cards: Vec<GiftCardSummary>,
+    /// For sales to a registered customer: the loyalty points earned and the
+    /// customer's balance after the sale.
+    #[serde(default)]
+    pub loyalty_points: Option<(i64, i64)>,
+    /// The receipt number and short hash of the sale's journal entry,
+    /// printed under the date. `None` for slips that are not journalled.
+    #[serde(default)]
+    pub journal: Option<(i64, String)>,
+    /// The fiscal signature of the sale, printed as a QR code in place of the
+    /// totals one. `None` when sales are not signed.
+    #[serde(default)]
+    pub fiscal: Option<FiscalReceipt>,
+    /// Why the sale could not be signed, printed so the receipt is not
+    /// mistaken for a signed one.
+    #[serde(default)]
+    pub fiscal_error: Option<String>,
+}
+
+/// The fiscal signature of a sale on a receipt: the signer's signature
+/// counter and the content of the QR code to check the signature with.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FiscalReceipt {
+    pub counter: i64,
+    pub qr: String,
+}
+
+/// A gift card on a receipt and its balance after the sale. When
+/// `print_code` is set (the card was topped up) its full code is printed as
+/// a QR code, so the receipt can be scanned to redeem it; otherwise only the
+/// last digits are shown.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftCardSummary {
+    pub code: String,
+    pub balance: i64,
+    pub print_code: bool,
+}
+
+/// The amounts of one tax rate on a receipt, in minor units. `rate` is in
+/// percent.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TaxSummary {
+    pub rate: f32,
+    pub net: i64,
+    pub tax: i64,
+    pub gross: i64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -32,5 +138,19 @@ pub enum ServerMessage {
         data: Option<String>,
     },
     #[serde(rename = "print_receipt")]
-    PrintReceipt(PrintReceiptJob),
+    PrintReceipt(Box<PrintReceiptJob>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_minor_units() {
+        assert_eq!(format_amount(1205, 2), "12.05");
+        assert_eq!(format_amount(-5, 2), "-0.05");
+        assert_eq!(format_amount(0, 2), "0.00");
+        assert_eq!(format_amount(1500, 0), "1500");
+        assert_eq!(format_amount(-1234567, 3), "-1234.567");
+    }
 }
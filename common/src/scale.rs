@@ -0,0 +1,87 @@
+use std::io::{Read, Write};
+use std::time::Duration;
+
+// Retail scales are read with the widespread "8217" style protocol (Mettler
+// Toledo 8217, and emulated by most Bizerba, CAS and Dibal POS scales): the
+// host sends a single `W`, the scale answers with STX, the weight in kg and CR,
+// e.g. `\x02 0.535\r`. While the load is moving or out of range it answers
+// with `?` and a status byte instead of a weight. The line runs at 9600 baud,
+// 7 data bits, even parity, 1 stop bit.
+const BAUD_RATE: u32 = 9600;
+const TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// Ports a scale could be attached to, for the admin settings page. Unlike the
+/// printer, the scale is never probed automatically: sending `W` to a printer
+/// would print it.
+pub fn list_ports() -> Vec<String> {
+    serialport::available_ports()
+        .map(|ports| ports.into_iter().map(|p| p.port_name).collect())
+        .unwrap_or_default()
+}
+
+/// Ask the scale on `port` for the current weight, in kg.
+pub fn read_weight(port: &str) -> Result<f64, Box<dyn std::error::Error>> {
+    let path = if cfg!(windows) && !port.starts_with("\\\\.\\") {
+        format!("\\\\.\\{}", port)
+    } else {
+        port.to_string()
+    };
+    let mut serial = serialport::new(path, BAUD_RATE)
+        .data_bits(serialport::DataBits::Seven)
+        .parity(serialport::Parity::Even)
+        .stop_bits(serialport::StopBits::One)
+        .timeout(TIMEOUT)
+        .open()?;
+    serial.write_all(b"W")?;
+    serial.flush()?;
+
+    let mut reply = Vec::new();
+    let mut byte = [0u8; 1];
+    while reply.len() < 32 {
+        serial.read_exact(&mut byte)?;
+        if byte[0] == b'\r' {
+            break;
+        }
+        reply.push(byte[0]);
+    }
+    parse_weight(&String::from_utf8_lossy(&reply))
+}
+
+/// Parse a scale reply (without the trailing CR) into kg. Accepts an optional
+/// `kg` or `g` unit after the number, as sent by scales in verbose mode.
+pub fn parse_weight(reply: &str) -> Result<f64, Box<dyn std::error::Error>> {
+    let reply = reply.trim_matches(|c: char| c == '\x02' || c.is_whitespace());
+    if reply.starts_with('?') {
+        return Err("Scale is not stable".into());
+    }
+    let (number, grams) = if let Some(n) = reply.strip_suffix("kg") {
+        (n, false)
+    } else if let Some(n) = reply.strip_suffix('g') {
+        (n, true)
+    } else {
+        (reply, false)
+    };
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("Unexpected scale reply: {:?}", reply))?;
+    if value < 0.0 {
+        return Err("Scale reports a negative weight".into());
+    }
+    Ok(if grams { value / 1000.0 } else { value })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scale_replies() {
+        assert_eq!(parse_weight("\x0200.535").unwrap(), 0.535);
+        assert_eq!(parse_weight(" 1.250 kg").unwrap(), 1.25);
+        assert_eq!(parse_weight("535g").unwrap(), 0.535);
+        assert!(parse_weight("\x02?D").is_err());
+        assert!(parse_weight("-0.010").is_err());
+        assert!(parse_weight("").is_err());
+    }
+}
@@ -88,8 +88,16 @@ pub fn App() -> impl IntoView {
                     <Route path=StaticSegment("transactions") view=TransactionsPage/>
                     <Route path=StaticSegment("items") view=ItemsPage/>
                     <Route path=StaticSegment("categories") view=CategoriesPage/>
+                    <Route path=StaticSegment("modifiers") view=ModifiersPage/>
+                    <Route path=StaticSegment("promotions") view=PromotionsPage/>
+                    <Route path=StaticSegment("discount-reasons") view=DiscountReasonsPage/>
                     <Route path=StaticSegment("customer-groups") view=CustomerGroupsPage/>
+                    <Route path=StaticSegment("customers") view=CustomersPage/>
+                    <Route path=StaticSegment("accounts") view=AccountsPage/>
+                    <Route path=StaticSegment("floor-plan") view=FloorPlanPage/>
                     <Route path=StaticSegment("reports") view=ReportsPage/>
+                    <Route path=StaticSegment("closings") view=ClosingsPage/>
+                    <Route path=StaticSegment("cash-drawer") view=CashDrawerPage/>
                     <Route path=StaticSegment("kitchen") view=KitchenPage/>
                     <Route path=StaticSegment("login") view=LoginPage/>
                     <Route path=StaticSegment("admin") view=AdminPage/>
@@ -159,6 +167,14 @@ fn AppNavbar(
                                         class=move || if location.pathname.get() == "/" && active_sale_view.get() == "kitchen" { "active" } else { "" }
                                         on:click=move |_| { active_sale_view.set("kitchen".to_string()); set_menu_open.set(false); }
                                     >{i18n.get().t("sale.kitchen")}</a>
+                                    <a href="/"
+                                        class=move || if location.pathname.get() == "/" && active_sale_view.get() == "tables" { "active" } else { "" }
+                                        on:click=move |_| { active_sale_view.set("tables".to_string()); set_menu_open.set(false); }
+                                    >{i18n.get().t("sale.tables")}</a>
+                                    <a href="/cash-drawer"
+                                        class=move || if location.pathname.get().starts_with("/cash-drawer") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.cash_drawer")}</a>
                                 </Show>
                                 <Show when=move || is_admin fallback=|| ()>
                                     <a href="/transactions"
@@ -173,14 +189,42 @@ fn AppNavbar(
                                         class=move || if location.pathname.get().starts_with("/categories") { "active" } else { "" }
                                         on:click=move |_| set_menu_open.set(false)
                                     >{i18n.get().t("nav.categories")}</a>
+                                    <a href="/modifiers"
+                                        class=move || if location.pathname.get().starts_with("/modifiers") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.modifiers")}</a>
+                                    <a href="/promotions"
+                                        class=move || if location.pathname.get().starts_with("/promotions") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.promotions")}</a>
+                                    <a href="/discount-reasons"
+                                        class=move || if location.pathname.get().starts_with("/discount-reasons") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.discount_reasons")}</a>
                                     <a href="/customer-groups"
                                         class=move || if location.pathname.get().starts_with("/customer-groups") { "active" } else { "" }
                                         on:click=move |_| set_menu_open.set(false)
                                     >{i18n.get().t("nav.customer_groups")}</a>
+                                    <a href="/customers"
+                                        class=move || if location.pathname.get().starts_with("/customers") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.customers")}</a>
+                                    <a href="/accounts"
+                                        class=move || if location.pathname.get().starts_with("/accounts") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.accounts")}</a>
+                                    <a href="/floor-plan"
+                                        class=move || if location.pathname.get().starts_with("/floor-plan") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.floor_plan")}</a>
                                     <a href="/reports"
                                         class=move || if location.pathname.get().starts_with("/reports") { "active" } else { "" }
                                         on:click=move |_| set_menu_open.set(false)
                                     >{i18n.get().t("nav.reports")}</a>
+                                    <a href="/closings"
+                                        class=move || if location.pathname.get().starts_with("/closings") { "active" } else { "" }
+                                        on:click=move |_| set_menu_open.set(false)
+                                    >{i18n.get().t("nav.closings")}</a>
                                     <a href="/admin"
                                         class=move || if location.pathname.get().starts_with("/admin") { "active" } else { "" }
                                         on:click=move |_| set_menu_open.set(false)
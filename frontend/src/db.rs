@@ -0,0 +1,1080 @@
+use sqlx::SqlitePool;
+
+/// Creates any missing tables, columns and indexes, and brings data written
+/// by older versions up to date. Safe to run on every start.
+pub async fn migrate(db: &SqlitePool) {
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS categories (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create categories table");
+
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS items (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            description TEXT,
+            price INTEGER NOT NULL,
+            category_id TEXT NOT NULL,
+            sku TEXT,
+            in_stock BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (category_id) REFERENCES categories(id)
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create items table");
+
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS transactions (
+            id TEXT PRIMARY KEY,
+            customer_name TEXT,
+            status TEXT NOT NULL CHECK (status IN ('open', 'closed', 'cancelled')),
+            total INTEGER NOT NULL DEFAULT 0,
+            paid_amount INTEGER,
+            change_amount INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            closed_at TEXT
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create transactions table");
+
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS transaction_items (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            unit_price INTEGER NOT NULL,
+            total_price INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+            FOREIGN KEY (item_id) REFERENCES items(id)
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create transaction_items table");
+
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS customer_groups (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create customer_groups table");
+
+    // Amounts were once stored as REAL; they are whole minor units now. This
+    // runs before the backfills below copy amounts out of the old columns.
+    migrate_money_to_minor_units(db)
+        .await
+        .expect("Failed to convert amounts to minor units");
+
+    // Migrations for new columns
+    sqlx::query("ALTER TABLE categories ADD COLUMN main_course BOOLEAN NOT NULL DEFAULT 0").execute(db).await.ok();
+    // NULL customer_group_id means the sale belongs to "regular customers".
+    sqlx::query("ALTER TABLE transactions ADD COLUMN customer_group_id TEXT REFERENCES customer_groups(id)").execute(db).await.ok();
+    sqlx::query("ALTER TABLE categories ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0").execute(db).await.ok();
+    // One-time backfill: when no category has an assigned order yet (every row
+    // still at the default 0), seed a stable initial order alphabetically by
+    // name. New/assigned categories always have sort_order >= 1, so this runs
+    // only once and never clobbers a user-defined order.
+    let categories_unordered: i64 =
+        sqlx::query_scalar("SELECT COUNT(*) FROM categories WHERE sort_order != 0")
+            .fetch_one(db)
+            .await
+            .unwrap_or(0);
+    if categories_unordered == 0 {
+        sqlx::query(
+            r#"UPDATE categories SET sort_order = (
+                SELECT COUNT(*) FROM categories c2
+                WHERE c2.name < categories.name
+                   OR (c2.name = categories.name AND c2.id <= categories.id)
+            )"#,
+        )
+        .execute(db)
+        .await
+        .ok();
+    }
+    // Refunds are closed transactions linked to the sale they reverse, with
+    // each negative line linked to the original line it returns.
+    sqlx::query("ALTER TABLE transactions ADD COLUMN refund_of TEXT REFERENCES transactions(id)").execute(db).await.ok();
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN refund_of_item_id TEXT REFERENCES transaction_items(id)").execute(db).await.ok();
+    sqlx::query("ALTER TABLE items ADD COLUMN image_path TEXT").execute(db).await.ok();
+    sqlx::query("ALTER TABLE items ADD COLUMN stock_quantity INTEGER").execute(db).await.ok();
+    sqlx::query("ALTER TABLE items ADD COLUMN kitchen_item BOOLEAN NOT NULL DEFAULT 0").execute(db).await.ok();
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS kitchen_order_items (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            transaction_item_id TEXT NOT NULL,
+            item_id TEXT NOT NULL,
+            item_name TEXT NOT NULL,
+            quantity INTEGER NOT NULL,
+            customer_name TEXT,
+            completed BOOLEAN NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            completed_at TEXT,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+        )"#,
+    ).execute(db).await.ok();
+
+    // Split payments: one row per tender used to settle a sale.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS payments (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            tender TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create payments table");
+    // One-time backfill: sales closed before split payments were cash-only, so
+    // record their paid amount as a single cash payment.
+    sqlx::query(
+        r#"INSERT INTO payments (id, transaction_id, tender, amount, created_at)
+           SELECT randomblob(16), t.id, 'cash', t.paid_amount, COALESCE(t.closed_at, t.updated_at)
+           FROM transactions t
+           WHERE t.status = 'closed' AND t.paid_amount IS NOT NULL
+             AND NOT EXISTS (SELECT 1 FROM payments p WHERE p.transaction_id = t.id)"#,
+    )
+    .execute(db)
+    .await
+    .ok();
+
+    // Tax rates, assigned per category with optional per-item overrides.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS tax_rates (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            rate REAL NOT NULL,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create tax_rates table");
+    sqlx::query("ALTER TABLE categories ADD COLUMN tax_rate_id TEXT REFERENCES tax_rates(id)").execute(db).await.ok();
+    // NULL on an item means it inherits its category's rate.
+    sqlx::query("ALTER TABLE items ADD COLUMN tax_rate_id TEXT REFERENCES tax_rates(id)").execute(db).await.ok();
+    // The rate in percent as it was when the line was sold; NULL means untaxed.
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN tax_rate REAL").execute(db).await.ok();
+    // Whether line prices include tax (VAT) or tax is added on top at
+    // checkout. Fixed per sale when it is started, so changing the setting
+    // never alters open or past sales.
+    sqlx::query("ALTER TABLE transactions ADD COLUMN tax_inclusive BOOLEAN NOT NULL DEFAULT 1").execute(db).await.ok();
+
+    // Modifier groups (e.g. "Milk", "Size") with their options, attached to
+    // items or whole categories. A group is required when min_picks > 0;
+    // NULL max_picks means no upper limit.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS modifier_groups (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            min_picks INTEGER NOT NULL DEFAULT 0,
+            max_picks INTEGER,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create modifier_groups table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS modifiers (
+            id TEXT PRIMARY KEY,
+            group_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            price_delta INTEGER NOT NULL DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (group_id) REFERENCES modifier_groups(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create modifiers table");
+    // Exactly one of item_id / category_id is set per row.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS modifier_group_links (
+            group_id TEXT NOT NULL,
+            item_id TEXT,
+            category_id TEXT,
+            FOREIGN KEY (group_id) REFERENCES modifier_groups(id) ON DELETE CASCADE,
+            FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create modifier_group_links table");
+    // Modifiers chosen for a sale line, copied so later edits to the options
+    // don't change past sales.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS transaction_item_modifiers (
+            id TEXT PRIMARY KEY,
+            transaction_item_id TEXT NOT NULL,
+            modifier_id TEXT NOT NULL,
+            group_name TEXT NOT NULL,
+            name TEXT NOT NULL,
+            price_delta INTEGER NOT NULL,
+            FOREIGN KEY (transaction_item_id) REFERENCES transaction_items(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create transaction_item_modifiers table");
+    // Sorted ids of a line's modifiers; lines of the same item only merge
+    // when this matches.
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN modifier_key TEXT NOT NULL DEFAULT ''").execute(db).await.ok();
+    sqlx::query("ALTER TABLE kitchen_order_items ADD COLUMN modifiers TEXT").execute(db).await.ok();
+    // Free-text note per sale line (e.g. "no onions"), copied to the kitchen.
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN note TEXT").execute(db).await.ok();
+    sqlx::query("ALTER TABLE kitchen_order_items ADD COLUMN note TEXT").execute(db).await.ok();
+
+    // Floor plan: areas (e.g. "Terrace") holding tables with seat counts.
+    // Open sales can be assigned to a table.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS floor_areas (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create floor_areas table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS floor_tables (
+            id TEXT PRIMARY KEY,
+            area_id TEXT NOT NULL,
+            name TEXT NOT NULL,
+            seats INTEGER NOT NULL DEFAULT 0,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (area_id) REFERENCES floor_areas(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create floor_tables table");
+    sqlx::query("ALTER TABLE transactions ADD COLUMN table_id TEXT REFERENCES floor_tables(id)").execute(db).await.ok();
+    // Table name as it was when the order was sent, shown instead of the customer name.
+    sqlx::query("ALTER TABLE kitchen_order_items ADD COLUMN table_name TEXT").execute(db).await.ok();
+
+    // Goods sold by weight: the item's price is per kg or 100 g, and a
+    // weighed line has the weight as its quantity, "kg" as its unit and the
+    // price of a kg as its unit price.
+    sqlx::query("ALTER TABLE items ADD COLUMN price_unit TEXT NOT NULL DEFAULT 'each'").execute(db).await.ok();
+    // Line quantities are kept in thousandths, so weights are exact to the
+    // gram. The unit column came with that change: when it still has to be
+    // added, the quantities written so far are whole units and are scaled.
+    let mut tx = db.begin().await.expect("Failed to convert quantities to thousandths");
+    if sqlx::query("ALTER TABLE transaction_items ADD COLUMN unit TEXT").execute(&mut *tx).await.is_ok() {
+        for table in ["transaction_items", "kitchen_order_items"] {
+            sqlx::query(&format!("UPDATE {table} SET quantity = quantity * 1000"))
+                .execute(&mut *tx)
+                .await
+                .expect("Failed to convert quantities to thousandths");
+        }
+    }
+    tx.commit().await.expect("Failed to convert quantities to thousandths");
+    // Lines priced by an in-store barcode keep that price when repricing.
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN price_embedded BOOLEAN NOT NULL DEFAULT 0").execute(db).await.ok();
+
+    // Bundle (combo) items: fixed component items, or a choice from a category.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS bundle_components (
+            id TEXT PRIMARY KEY,
+            bundle_item_id TEXT NOT NULL,
+            item_id TEXT,
+            category_id TEXT,
+            quantity INTEGER NOT NULL DEFAULT 1,
+            sort_order INTEGER NOT NULL DEFAULT 0,
+            FOREIGN KEY (bundle_item_id) REFERENCES items(id) ON DELETE CASCADE,
+            FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create bundle_components table");
+    // A sold bundle is a line of its own plus one line per component, which
+    // carries its share of the bundle price and goes away with the bundle line.
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN is_bundle BOOLEAN NOT NULL DEFAULT 0").execute(db).await.ok();
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN bundle_id TEXT REFERENCES transaction_items(id) ON DELETE CASCADE").execute(db).await.ok();
+
+    // List price of each item over time; the open row has no valid_to.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS price_history (
+            id TEXT PRIMARY KEY,
+            item_id TEXT NOT NULL,
+            price INTEGER NOT NULL,
+            valid_from TEXT NOT NULL,
+            valid_to TEXT,
+            FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create price_history table");
+    // Items from before price history was kept start with their current price.
+    sqlx::query(
+        "INSERT INTO price_history (id, item_id, price, valid_from)
+         SELECT randomblob(16), id, price, created_at FROM items
+         WHERE id NOT IN (SELECT item_id FROM price_history)",
+    )
+    .execute(db)
+    .await
+    .expect("Failed to seed price_history");
+
+    // Promotions: automatic discount rules, and the discounts given on sale
+    // lines. Promotion discounts carry their promotion (kept after it is
+    // deleted, for reports) and are recomputed while the sale is open.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS promotions (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            active BOOLEAN NOT NULL DEFAULT 1,
+            item_id TEXT,
+            category_id TEXT,
+            trigger_item_id TEXT,
+            trigger_category_id TEXT,
+            buy_quantity INTEGER NOT NULL DEFAULT 1,
+            get_quantity INTEGER NOT NULL DEFAULT 1,
+            discount_type TEXT NOT NULL,
+            discount_value REAL NOT NULL,
+            customer_group_id TEXT,
+            weekdays TEXT NOT NULL DEFAULT '',
+            start_time TEXT,
+            end_time TEXT,
+            valid_from TEXT,
+            valid_to TEXT,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL,
+            FOREIGN KEY (item_id) REFERENCES items(id) ON DELETE CASCADE,
+            FOREIGN KEY (category_id) REFERENCES categories(id) ON DELETE CASCADE,
+            FOREIGN KEY (trigger_item_id) REFERENCES items(id) ON DELETE CASCADE,
+            FOREIGN KEY (trigger_category_id) REFERENCES categories(id) ON DELETE CASCADE,
+            FOREIGN KEY (customer_group_id) REFERENCES customer_groups(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create promotions table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS transaction_discounts (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            transaction_item_id TEXT NOT NULL,
+            promotion_id TEXT,
+            name TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+            FOREIGN KEY (transaction_item_id) REFERENCES transaction_items(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create transaction_discounts table");
+
+    // Manual discounts: the reason codes cashiers pick from, and the
+    // discounts given by hand on a line or a whole sale. Their shares per
+    // line are kept in transaction_discounts like promotion discounts.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS discount_reasons (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create discount_reasons table");
+    let reason_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM discount_reasons")
+        .fetch_one(db)
+        .await
+        .unwrap_or(0);
+    if reason_count == 0 {
+        let now = chrono::Utc::now();
+        for name in ["Damaged", "Staff", "Goodwill", "Complimentary"] {
+            sqlx::query("INSERT INTO discount_reasons (id, name, created_at, updated_at) VALUES (?, ?, ?, ?)")
+                .bind(uuid::Uuid::new_v4())
+                .bind(name)
+                .bind(now)
+                .bind(now)
+                .execute(db)
+                .await
+                .expect("Failed to seed discount_reasons");
+        }
+    }
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS manual_discounts (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            transaction_item_id TEXT,
+            discount_type TEXT NOT NULL,
+            discount_value REAL NOT NULL,
+            reason TEXT NOT NULL,
+            user_name TEXT,
+            approved_by TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+            FOREIGN KEY (transaction_item_id) REFERENCES transaction_items(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create manual_discounts table");
+    sqlx::query("ALTER TABLE transaction_discounts ADD COLUMN manual_discount_id TEXT REFERENCES manual_discounts(id) ON DELETE CASCADE").execute(db).await.ok();
+    sqlx::query("ALTER TABLE transaction_discounts ADD COLUMN reason TEXT").execute(db).await.ok();
+    sqlx::query("ALTER TABLE transaction_discounts ADD COLUMN user_name TEXT").execute(db).await.ok();
+
+    // Gift cards: the balance is the sum of the card's ledger entries.
+    // Top-ups rung up on an open sale wait in gift_card_loads until it closes.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS gift_cards (
+            id TEXT PRIMARY KEY,
+            code TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create gift_cards table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS gift_card_entries (
+            id TEXT PRIMARY KEY,
+            gift_card_id TEXT NOT NULL,
+            transaction_id TEXT,
+            kind TEXT NOT NULL CHECK (kind IN ('top_up', 'redeem', 'refund')),
+            amount INTEGER NOT NULL,
+            user_name TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (gift_card_id) REFERENCES gift_cards(id) ON DELETE CASCADE,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id)
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create gift_card_entries table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS gift_card_loads (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            gift_card_id TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
+            FOREIGN KEY (gift_card_id) REFERENCES gift_cards(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create gift_card_loads table");
+
+    // Registered customers and their loyalty points. The points balance is
+    // the sum of the customer's loyalty entries.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS customers (
+            id TEXT PRIMARY KEY,
+            name TEXT NOT NULL,
+            phone TEXT,
+            email TEXT,
+            notes TEXT,
+            card_code TEXT UNIQUE,
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create customers table");
+    sqlx::query("ALTER TABLE transactions ADD COLUMN customer_id TEXT REFERENCES customers(id) ON DELETE SET NULL").execute(db).await.ok();
+    sqlx::query("ALTER TABLE manual_discounts ADD COLUMN loyalty_points INTEGER").execute(db).await.ok();
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS loyalty_entries (
+            id TEXT PRIMARY KEY,
+            customer_id TEXT NOT NULL,
+            transaction_id TEXT,
+            points INTEGER NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (customer_id) REFERENCES customers(id) ON DELETE CASCADE,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id)
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create loyalty_entries table");
+
+    // House accounts: on-account sales charged to a customer or a customer
+    // group, and the settlement payments that clear them.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS account_settlements (
+            id TEXT PRIMARY KEY,
+            customer_id TEXT,
+            customer_group_id TEXT,
+            tender TEXT NOT NULL,
+            amount INTEGER NOT NULL,
+            user_name TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (customer_id) REFERENCES customers(id) ON DELETE CASCADE,
+            FOREIGN KEY (customer_group_id) REFERENCES customer_groups(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create account_settlements table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS account_charges (
+            id TEXT PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            customer_id TEXT,
+            customer_group_id TEXT,
+            amount INTEGER NOT NULL,
+            settlement_id TEXT,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (transaction_id) REFERENCES transactions(id),
+            FOREIGN KEY (customer_id) REFERENCES customers(id) ON DELETE CASCADE,
+            FOREIGN KEY (customer_group_id) REFERENCES customer_groups(id) ON DELETE CASCADE,
+            FOREIGN KEY (settlement_id) REFERENCES account_settlements(id)
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create account_charges table");
+
+    // Deposit (Pfand) items: an item can name the deposit item charged with
+    // it. Deposit lines are flagged so reports keep them out of revenue; the
+    // ones added with an item point at its line and go away with it.
+    sqlx::query("ALTER TABLE items ADD COLUMN is_deposit BOOLEAN NOT NULL DEFAULT 0").execute(db).await.ok();
+    sqlx::query("ALTER TABLE items ADD COLUMN deposit_item_id TEXT REFERENCES items(id) ON DELETE SET NULL").execute(db).await.ok();
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN is_deposit BOOLEAN NOT NULL DEFAULT 0").execute(db).await.ok();
+    sqlx::query("ALTER TABLE transaction_items ADD COLUMN deposit_for TEXT REFERENCES transaction_items(id) ON DELETE CASCADE").execute(db).await.ok();
+
+    // Tips are kept apart from the sale total, and credited to the user who
+    // closed the sale.
+    sqlx::query("ALTER TABLE transactions ADD COLUMN tip_amount INTEGER NOT NULL DEFAULT 0").execute(db).await.ok();
+    sqlx::query("ALTER TABLE transactions ADD COLUMN closed_by TEXT").execute(db).await.ok();
+
+    // What cash rounding added to or took off the amount paid for a sale.
+    sqlx::query("ALTER TABLE transactions ADD COLUMN rounding_amount INTEGER NOT NULL DEFAULT 0").execute(db).await.ok();
+
+    // Fiscal signature of finished sales, or why signing failed.
+    for column in [
+        "fiscal_signer TEXT",
+        "fiscal_counter INTEGER",
+        "fiscal_signature TEXT",
+        "fiscal_started_at TEXT",
+        "fiscal_signed_at TEXT",
+        "fiscal_error TEXT",
+    ] {
+        sqlx::query(&format!("ALTER TABLE transactions ADD COLUMN {}", column)).execute(db).await.ok();
+    }
+
+    // User accounts and sessions
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS users (
+            id TEXT PRIMARY KEY,
+            username TEXT NOT NULL UNIQUE,
+            pin_hash TEXT NOT NULL,
+            role TEXT NOT NULL CHECK (role IN ('admin', 'cashier', 'cook')),
+            created_at TEXT NOT NULL,
+            updated_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create users table");
+
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS sessions (
+            id TEXT PRIMARY KEY,
+            user_id TEXT NOT NULL,
+            token TEXT NOT NULL UNIQUE,
+            created_at TEXT NOT NULL,
+            expires_at TEXT NOT NULL,
+            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create sessions table");
+
+    // Configuration table
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS config (
+            key TEXT PRIMARY KEY,
+            value TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create config table");
+
+    // Tamper-evident journal of finished sales: gapless receipt numbers, each
+    // entry hashed together with the hash of the one before. Append-only.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS journal (
+            seq INTEGER PRIMARY KEY,
+            transaction_id TEXT NOT NULL,
+            event TEXT NOT NULL CHECK (event IN ('sale', 'cancel', 'refund')),
+            payload TEXT NOT NULL,
+            prev_hash TEXT NOT NULL,
+            hash TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create journal table");
+
+    for (name, action) in [("journal_no_update", "UPDATE"), ("journal_no_delete", "DELETE")] {
+        sqlx::query(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {name} BEFORE {action} ON journal
+             BEGIN SELECT RAISE(ABORT, 'The journal is append-only'); END"
+        ))
+        .execute(db)
+        .await
+        .expect("Failed to create journal trigger");
+    }
+
+    // Z-closings: the totals since the closing before, frozen as JSON under
+    // gapless numbers. Append-only like the journal.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS z_closings (
+            number INTEGER PRIMARY KEY,
+            closed_by TEXT NOT NULL,
+            totals TEXT NOT NULL,
+            created_at TEXT NOT NULL
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create z_closings table");
+
+    for (name, action) in [("z_closings_no_update", "UPDATE"), ("z_closings_no_delete", "DELETE")] {
+        sqlx::query(&format!(
+            "CREATE TRIGGER IF NOT EXISTS {name} BEFORE {action} ON z_closings
+             BEGIN SELECT RAISE(ABORT, 'Z-closings cannot be changed'); END"
+        ))
+        .execute(db)
+        .await
+        .expect("Failed to create z_closings trigger");
+    }
+
+    // Cash drawer sessions, from the opening float to the count, with the
+    // cash put in and taken out in between and the count per denomination.
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS drawer_sessions (
+            id TEXT PRIMARY KEY,
+            opened_by TEXT NOT NULL,
+            opened_at TEXT NOT NULL,
+            closed_by TEXT,
+            closed_at TEXT,
+            expected INTEGER,
+            counted INTEGER,
+            variance INTEGER
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create drawer_sessions table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS cash_movements (
+            id TEXT PRIMARY KEY,
+            session_id TEXT NOT NULL,
+            kind TEXT NOT NULL CHECK (kind IN ('float', 'pay_in', 'pay_out', 'safe_drop')),
+            amount INTEGER NOT NULL,
+            reason TEXT,
+            user_name TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            FOREIGN KEY (session_id) REFERENCES drawer_sessions(id)
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create cash_movements table");
+    sqlx::query(
+        r#"CREATE TABLE IF NOT EXISTS drawer_counts (
+            session_id TEXT NOT NULL,
+            denomination INTEGER NOT NULL,
+            quantity INTEGER NOT NULL,
+            PRIMARY KEY (session_id, denomination),
+            FOREIGN KEY (session_id) REFERENCES drawer_sessions(id)
+        )"#,
+    )
+    .execute(db)
+    .await
+    .expect("Failed to create drawer_counts table");
+
+    // Create indexes
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_items_category_id ON items(category_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_items_transaction_id ON transaction_items(transaction_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_transaction_items_item_id ON transaction_items(item_id)",
+    )
+    .execute(db)
+    .await
+    .ok();
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_transactions_status ON transactions(status)",
+    )
+    .execute(db)
+    .await
+    .ok();
+    sqlx::query(
+        "CREATE INDEX IF NOT EXISTS idx_transactions_customer_name ON transactions(customer_name)",
+    )
+    .execute(db)
+    .await
+    .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_items_refund_of_item_id ON transaction_items(refund_of_item_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_payments_transaction_id ON payments(transaction_id)")
+        .execute(db)
+        .await
+        .ok();
+    // Fails (and is skipped) on databases that already hold duplicate SKUs;
+    // create_item/update_item reject new duplicates either way.
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_items_sku ON items(sku) WHERE sku IS NOT NULL")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_price_history_item_id ON price_history(item_id, valid_from)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transactions_table_id ON transactions(table_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_item_modifiers_transaction_item_id ON transaction_item_modifiers(transaction_item_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_items_bundle_id ON transaction_items(bundle_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_bundle_components_bundle_item_id ON bundle_components(bundle_item_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_discounts_transaction_id ON transaction_discounts(transaction_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_discounts_transaction_item_id ON transaction_discounts(transaction_item_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_manual_discounts_transaction_id ON manual_discounts(transaction_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_gift_card_entries_gift_card_id ON gift_card_entries(gift_card_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_gift_card_loads_transaction_id ON gift_card_loads(transaction_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transactions_customer_id ON transactions(customer_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_loyalty_entries_customer_id ON loyalty_entries(customer_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_account_charges_customer_id ON account_charges(customer_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_account_charges_customer_group_id ON account_charges(customer_group_id)")
+        .execute(db)
+        .await
+        .ok();
+    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_items_deposit_for ON transaction_items(deposit_for)")
+        .execute(db)
+        .await
+        .ok();
+
+    // A sale is journalled once.
+    sqlx::query("CREATE UNIQUE INDEX IF NOT EXISTS idx_journal_transaction_id ON journal(transaction_id)")
+        .execute(db)
+        .await
+        .ok();
+}
+
+/// Converts money columns still declared REAL to INTEGER minor units of the
+/// configured currency. SQLite cannot change a column's type, so each such
+/// table is rebuilt from its own definition with those columns retyped, and
+/// the amounts are converted while copying the rows over.
+async fn migrate_money_to_minor_units(db: &sqlx::SqlitePool) -> Result<(), sqlx::Error> {
+    use crate::models::currency_decimals;
+    use crate::server_fns::MONEY_COLUMNS;
+    use sqlx::{Connection, Row};
+
+    // A new database has no config table yet, and no amounts to convert.
+    let currency: Option<String> = sqlx::query_scalar("SELECT value FROM config WHERE key = 'currency'")
+        .fetch_optional(db)
+        .await
+        .unwrap_or_default();
+    let factor = 10_i64.pow(currency_decimals(&currency.unwrap_or_default()));
+
+    let mut conn = db.acquire().await?;
+    // Dropping a table others reference must not be refused or cascade.
+    // The pragma has no effect inside a transaction, so it is set first.
+    sqlx::query("PRAGMA foreign_keys = OFF").execute(&mut *conn).await?;
+    let mut tx = conn.begin().await?;
+    // A table listed twice would be rebuilt twice and its amounts scaled twice.
+    let mut tables: Vec<&str> = MONEY_COLUMNS.iter().map(|(table, _)| *table).collect();
+    tables.sort_unstable();
+    tables.dedup();
+    for table in tables {
+        let columns: Vec<(String, String)> = sqlx::query(&format!("PRAGMA table_info({table})"))
+            .fetch_all(&mut *tx)
+            .await?
+            .iter()
+            .map(|row| (row.get("name"), row.get("type")))
+            .collect();
+        let to_convert: Vec<&str> = columns
+            .iter()
+            .filter(|(name, ty)| {
+                ty.eq_ignore_ascii_case("REAL") && MONEY_COLUMNS.contains(&(table, name.as_str()))
+            })
+            .map(|(name, _)| name.as_str())
+            .collect();
+        if to_convert.is_empty() {
+            continue;
+        }
+
+        let sql: String = sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE type = 'table' AND name = ?")
+            .bind(table)
+            .fetch_one(&mut *tx)
+            .await?;
+        let mut definition = format!("CREATE TABLE {table}_minor {}", &sql[sql.find('(').unwrap_or(0)..]);
+        for column in &to_convert {
+            definition = retype_real_column(&definition, column);
+        }
+        let select = columns
+            .iter()
+            .map(|(name, _)| {
+                if to_convert.contains(&name.as_str()) {
+                    format!("CAST(ROUND({name} * {factor}) AS INTEGER)")
+                } else {
+                    name.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        sqlx::query(&definition).execute(&mut *tx).await?;
+        sqlx::query(&format!("INSERT INTO {table}_minor SELECT {select} FROM {table}"))
+            .execute(&mut *tx)
+            .await?;
+        sqlx::query(&format!("DROP TABLE {table}")).execute(&mut *tx).await?;
+        sqlx::query(&format!("ALTER TABLE {table}_minor RENAME TO {table}"))
+            .execute(&mut *tx)
+            .await?;
+        println!("Converted {} in {} to minor units", to_convert.join(", "), table);
+    }
+    tx.commit().await?;
+    sqlx::query("PRAGMA foreign_keys = ON").execute(&mut *conn).await?;
+    Ok(())
+}
+
+/// Changes the declared type of `column` in a CREATE TABLE statement from
+/// REAL to INTEGER, leaving columns that merely end in the same name alone.
+fn retype_real_column(definition: &str, column: &str) -> String {
+    let pattern = format!("{column} REAL");
+    let mut out = String::with_capacity(definition.len());
+    let mut rest = definition;
+    while let Some(pos) = rest.find(&pattern) {
+        let (before, after) = rest.split_at(pos);
+        out.push_str(before);
+        if out.ends_with(|c: char| c.is_alphanumeric() || c == '_') {
+            out.push_str(&pattern);
+        } else {
+            out.push_str(&format!("{column} INTEGER"));
+        }
+        rest = &after[pattern.len()..];
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[test]
+    fn retypes_only_the_named_column() {
+        let definition = "CREATE TABLE t (id TEXT, price REAL NOT NULL, unit_price REAL)";
+        assert_eq!(
+            retype_real_column(definition, "price"),
+            "CREATE TABLE t (id TEXT, price INTEGER NOT NULL, unit_price REAL)"
+        );
+    }
+
+    async fn memory_pool() -> SqlitePool {
+        SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn migrate_twice() {
+        let db = memory_pool().await;
+        migrate(&db).await;
+        migrate(&db).await;
+        let reasons: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM discount_reasons")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(reasons, 4);
+    }
+
+    #[tokio::test]
+    async fn money_migration_runs_once() {
+        let db = memory_pool().await;
+        sqlx::query("CREATE TABLE config (key TEXT PRIMARY KEY, value TEXT NOT NULL)")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO config (key, value) VALUES ('currency', '€')")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("CREATE TABLE transactions (id TEXT PRIMARY KEY, total REAL, paid_amount REAL, change_amount REAL)")
+            .execute(&db)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO transactions (id, total, paid_amount, change_amount) VALUES ('a', 12.5, 20.0, 7.5)")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        migrate_money_to_minor_units(&db).await.unwrap();
+        migrate_money_to_minor_units(&db).await.unwrap();
+
+        let row: (i64, i64, i64) = sqlx::query_as("SELECT total, paid_amount, change_amount FROM transactions")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(row, (1250, 2000, 750));
+    }
+
+    /// The tables of the last release that stored amounts as REAL.
+    const BASELINE_SCHEMA: &str = r#"
+        CREATE TABLE categories (id TEXT PRIMARY KEY, name TEXT NOT NULL, description TEXT,
+            created_at TEXT NOT NULL, updated_at TEXT NOT NULL);
+        CREATE TABLE items (id TEXT PRIMARY KEY, name TEXT NOT NULL, description TEXT, price REAL NOT NULL,
+            category_id TEXT NOT NULL, sku TEXT, in_stock BOOLEAN NOT NULL DEFAULT 1,
+            created_at TEXT NOT NULL, updated_at TEXT NOT NULL);
+        CREATE TABLE transactions (id TEXT PRIMARY KEY, customer_name TEXT,
+            status TEXT NOT NULL CHECK (status IN ('open', 'closed', 'cancelled')),
+            total REAL NOT NULL DEFAULT 0, paid_amount REAL, change_amount REAL,
+            created_at TEXT NOT NULL, updated_at TEXT NOT NULL, closed_at TEXT);
+        CREATE TABLE transaction_items (id TEXT PRIMARY KEY, transaction_id TEXT NOT NULL, item_id TEXT NOT NULL,
+            quantity INTEGER NOT NULL, unit_price REAL NOT NULL, total_price REAL NOT NULL, created_at TEXT NOT NULL);
+        CREATE TABLE config (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+        INSERT INTO config (key, value) VALUES ('currency', '€');
+        INSERT INTO categories VALUES ('c', 'Drinks', NULL, '2025-01-01', '2025-01-01');
+        INSERT INTO items VALUES ('i', 'Coffee', NULL, 3.5, 'c', NULL, 1, '2025-01-01', '2025-01-01');
+        INSERT INTO transactions VALUES ('t', NULL, 'closed', 7.0, 10.0, 3.0, '2025-01-02', '2025-01-02', '2025-01-02');
+        INSERT INTO transaction_items VALUES ('l', 't', 'i', 2, 3.5, 7.0, '2025-01-02');
+    "#;
+
+    #[tokio::test]
+    async fn amounts_from_the_baseline_schema_become_minor_units() {
+        let db = memory_pool().await;
+        sqlx::raw_sql(BASELINE_SCHEMA).execute(&db).await.unwrap();
+
+        migrate(&db).await;
+
+        let item: i64 = sqlx::query_scalar("SELECT price FROM items").fetch_one(&db).await.unwrap();
+        assert_eq!(item, 350);
+        let sale: (i64, i64, i64) = sqlx::query_as("SELECT total, paid_amount, change_amount FROM transactions")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(sale, (700, 1000, 300));
+        let line: (i64, i64, i64) = sqlx::query_as("SELECT quantity, unit_price, total_price FROM transaction_items")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(line, (2000, 350, 700));
+        let payment: (String, i64) = sqlx::query_as("SELECT tender, amount FROM payments")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(payment, ("cash".to_string(), 1000));
+        let listed: i64 = sqlx::query_scalar("SELECT price FROM price_history").fetch_one(&db).await.unwrap();
+        assert_eq!(listed, 350);
+    }
+
+    #[tokio::test]
+    async fn quantities_are_scaled_once() {
+        let db = memory_pool().await;
+        sqlx::query(
+            "CREATE TABLE transaction_items (id TEXT PRIMARY KEY, transaction_id TEXT NOT NULL, item_id TEXT NOT NULL,
+             quantity INTEGER NOT NULL, unit_price INTEGER NOT NULL, total_price INTEGER NOT NULL, created_at TEXT NOT NULL)",
+        )
+        .execute(&db)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO transaction_items VALUES ('a', 't', 'i', 3, 250, 750, '2025-01-01')")
+            .execute(&db)
+            .await
+            .unwrap();
+
+        migrate(&db).await;
+        migrate(&db).await;
+
+        let quantity: i64 = sqlx::query_scalar("SELECT quantity FROM transaction_items")
+            .fetch_one(&db)
+            .await
+            .unwrap();
+        assert_eq!(quantity, 3000);
+    }
+}
@@ -0,0 +1,1170 @@
+//! The DSFinV-K export tax auditors in Germany ask for: a ZIP of CSV files,
+//! one per table of the standard, and an `index.xml` describing them in the
+//! GDPdU format their tools import.
+//!
+//! The export covers the Z-closings, their receipts
+//! with lines, payments and tax, the tax rates, the register and the fiscal
+//! signer. Tables of the standard this register has no data for (pay agents,
+//! subitems, price details, allocation groups, slave registers) are left out.
+//!
+//! Amounts are written with a decimal point and the currency's decimal
+//! places, times in the server's local time. Each receipt is split into the
+//! positions the standard expects: items, each discount, gift cards sold,
+//! the tip and the cash rounding on lines of their own.
+
+use crate::fiscal::SignerInfo;
+use crate::models::{
+    currency_decimals, mask_card_code, tax_breakdown, BusinessDetails, GiftCardLoad, Money, Payment, Quantity,
+    Transaction, TransactionItemDetail,
+};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use std::collections::BTreeMap;
+use std::io::Write;
+use uuid::Uuid;
+
+/// The version of the DSFinV-K taxonomy the export follows.
+pub const TAXONOMY_VERSION: &str = "2.3";
+
+/// Everything exported for a period.
+pub struct Export {
+    pub business: BusinessDetails,
+    /// The currency as set on the admin page, e.g. "€" or "EUR".
+    pub currency: String,
+    pub software_version: String,
+    /// The signers that signed the exported receipts.
+    pub signers: Vec<SignerInfo>,
+    pub closings: Vec<Closing>,
+}
+
+/// The receipts of one Z-closing.
+pub struct Closing {
+    /// The number of the Z-closing.
+    pub number: i64,
+    /// The day of the last receipt in the closing.
+    pub business_day: NaiveDate,
+    /// When the Z-closing was made.
+    pub created_at: DateTime<Utc>,
+    /// In receipt number order.
+    pub receipts: Vec<Receipt>,
+}
+
+/// A finished sale: closed, cancelled or a refund.
+pub struct Receipt {
+    /// The receipt number from the journal.
+    pub number: i64,
+    pub transaction: Transaction,
+    pub lines: Vec<TransactionItemDetail>,
+    pub payments: Vec<Payment>,
+    pub gift_card_loads: Vec<GiftCardLoad>,
+    /// For refunds, the sale refunded.
+    pub refund_of: Option<Reference>,
+}
+
+/// Another receipt of this register, and the closing it is in.
+#[derive(Debug, Clone)]
+pub struct Reference {
+    pub closing: i64,
+    pub transaction_id: Uuid,
+    pub finished_at: DateTime<Utc>,
+}
+
+/// How a column is typed in `index.xml`, and whether it is quoted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Kind {
+    Text,
+    Integer,
+    /// An amount with the currency's decimal places.
+    Amount,
+    Decimal(u32),
+    Date,
+    Timestamp,
+}
+
+use Kind::*;
+
+/// One CSV file of the export. The first `keys` columns make up the
+/// primary key.
+struct Table {
+    file: &'static str,
+    name: &'static str,
+    keys: usize,
+    columns: &'static [(&'static str, Kind)],
+    rows: Vec<Vec<String>>,
+}
+
+const CASHPOINT_CLOSING: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("Z_BUCHUNGSTAG", Date),
+    ("TAXONOMIE_VERSION", Text),
+    ("Z_START_ID", Text),
+    ("Z_ENDE_ID", Text),
+    ("NAME", Text),
+    ("STRASSE", Text),
+    ("PLZ", Text),
+    ("ORT", Text),
+    ("LAND", Text),
+    ("STNR", Text),
+    ("USTID", Text),
+    ("Z_SE_ZAHLUNGEN", Amount),
+    ("Z_SE_BARZAHLUNGEN", Amount),
+];
+
+const LOCATION: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("LOC_NAME", Text),
+    ("LOC_STRASSE", Text),
+    ("LOC_PLZ", Text),
+    ("LOC_ORT", Text),
+    ("LOC_LAND", Text),
+    ("LOC_USTID", Text),
+];
+
+const CASH_REGISTER: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("KASSE_BRAND", Text),
+    ("KASSE_MODELL", Text),
+    ("KASSE_SERIENNR", Text),
+    ("KASSE_SW_BRAND", Text),
+    ("KASSE_SW_VERSION", Text),
+    ("KASSE_BASISWAEH_CODE", Text),
+    ("KEINE_UST_ZUORDNUNG", Text),
+];
+
+const VAT: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("UST_SCHLUESSEL", Integer),
+    ("UST_SATZ", Decimal(2)),
+    ("UST_BESCHR", Text),
+];
+
+const TSE: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("TSE_ID", Integer),
+    ("TSE_SERIAL", Text),
+    ("TSE_SIG_ALGO", Text),
+    ("TSE_ZEITFORMAT", Text),
+    ("TSE_PD_ENCODING", Text),
+    ("TSE_PUBLIC_KEY", Text),
+    ("TSE_ZERTIFIKAT_I", Text),
+    ("TSE_ZERTIFIKAT_II", Text),
+];
+
+const TRANSACTIONS: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("BON_ID", Text),
+    ("BON_NR", Integer),
+    ("BON_TYP", Text),
+    ("BON_NAME", Text),
+    ("TERMINAL_ID", Text),
+    ("BON_STORNO", Integer),
+    ("BON_START", Timestamp),
+    ("BON_ENDE", Timestamp),
+    ("BEDIENER_ID", Text),
+    ("BEDIENER_NAME", Text),
+    ("UMS_BRUTTO", Amount),
+    ("KUNDE_NAME", Text),
+    ("KUNDE_ID", Text),
+    ("KUNDE_TYP", Text),
+    ("KUNDE_STRASSE", Text),
+    ("KUNDE_PLZ", Text),
+    ("KUNDE_ORT", Text),
+    ("KUNDE_LAND", Text),
+    ("KUNDE_USTID", Text),
+    ("BON_NOTIZ", Text),
+];
+
+const TRANSACTIONS_VAT: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("BON_ID", Text),
+    ("UST_SCHLUESSEL", Integer),
+    ("BON_BRUTTO", Amount),
+    ("BON_NETTO", Amount),
+    ("BON_UST", Amount),
+];
+
+const DATAPAYMENT: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("BON_ID", Text),
+    ("ZAHLART_TYP", Text),
+    ("ZAHLART_NAME", Text),
+    ("ZAHLWAEH_CODE", Text),
+    ("ZAHLWAEH_BETRAG", Amount),
+    ("BASISWAEH_BETRAG", Amount),
+];
+
+const REFERENCES: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("BON_ID", Text),
+    ("POS_ZEILE", Text),
+    ("REF_TYP", Text),
+    ("REF_NAME", Text),
+    ("REF_DATUM", Timestamp),
+    ("REF_KASSE_ID", Text),
+    ("REF_Z_NR", Integer),
+    ("REF_BON_ID", Text),
+];
+
+const TRANSACTIONS_TSE: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("BON_ID", Text),
+    ("TSE_ID", Integer),
+    ("TSE_TANR", Integer),
+    ("TSE_TA_START", Timestamp),
+    ("TSE_TA_ENDE", Timestamp),
+    ("TSE_TA_VORGANGSART", Text),
+    ("TSE_TA_SIGZ", Integer),
+    ("TSE_TA_SIG", Text),
+    ("TSE_TA_FEHLER", Text),
+];
+
+const LINES: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("BON_ID", Text),
+    ("POS_ZEILE", Text),
+    ("GUTSCHEIN_NR", Text),
+    ("ARTIKELTEXT", Text),
+    ("POS_TERMINAL_ID", Text),
+    ("GV_TYP", Text),
+    ("GV_NAME", Text),
+    ("INHAUS", Integer),
+    ("P_STORNO", Integer),
+    ("AGENTUR_ID", Integer),
+    ("ART_NR", Text),
+    ("GTIN", Text),
+    ("WARENGR_ID", Text),
+    ("WARENGR", Text),
+    ("MENGE", Decimal(3)),
+    ("FAKTOR", Decimal(3)),
+    ("EINHEIT", Text),
+    ("STK_BR", Amount),
+];
+
+const LINES_VAT: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("BON_ID", Text),
+    ("POS_ZEILE", Text),
+    ("UST_SCHLUESSEL", Integer),
+    ("POS_BRUTTO", Amount),
+    ("POS_NETTO", Amount),
+    ("POS_UST", Amount),
+];
+
+const BUSINESS_CASES: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("GV_TYP", Text),
+    ("GV_NAME", Text),
+    ("AGENTUR_ID", Integer),
+    ("UST_SCHLUESSEL", Integer),
+    ("Z_UMS_BRUTTO", Amount),
+    ("Z_UMS_NETTO", Amount),
+    ("Z_UST", Amount),
+];
+
+const PAYMENT: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("ZAHLART_TYP", Text),
+    ("ZAHLART_NAME", Text),
+    ("Z_ZAHLART_BETRAG", Amount),
+];
+
+const CASH_PER_CURRENCY: &[(&str, Kind)] = &[
+    ("Z_KASSE_ID", Text),
+    ("Z_ERSTELLUNG", Timestamp),
+    ("Z_NR", Integer),
+    ("ZAHLART_WAEH", Text),
+    ("ZAHLART_BETRAG_WAEH", Amount),
+];
+
+/// The DSFinV-K key of a tax rate: the standard's keys for the German rates
+/// and for untaxed lines, and 1000 plus the rate in hundredths of a percent
+/// for any other rate.
+pub fn vat_key(rate: Option<f64>) -> i64 {
+    let Some(rate) = rate else {
+        return 5;
+    };
+    match (rate * 100.0).round() as i64 {
+        1900 => 1,
+        700 => 2,
+        1070 => 3,
+        550 => 4,
+        0 => 6,
+        basis_points => 1000 + basis_points,
+    }
+}
+
+fn vat_description(key: i64) -> &'static str {
+    match key {
+        1 => "Regelsteuersatz",
+        2 => "Ermäßigter Steuersatz",
+        3 => "Durchschnittsatz (§ 24 Abs. 1 Nr. 3 UStG)",
+        4 => "Durchschnittsatz (§ 24 Abs. 1 Nr. 1 UStG)",
+        5 => "Nicht Steuerbar",
+        6 => "Umsatzsteuerfrei",
+        _ => "Individueller Steuersatz",
+    }
+}
+
+/// The ISO 4217 code of the currency set on the admin page.
+pub fn currency_code(currency: &str) -> String {
+    match currency {
+        "€" => "EUR",
+        "$" => "USD",
+        "£" => "GBP",
+        "Fr." => "CHF",
+        "zł" => "PLN",
+        "Kč" => "CZK",
+        "Ft" => "HUF",
+        other => other,
+    }
+    .to_string()
+}
+
+/// The DSFinV-K payment type of a tender.
+fn payment_type(tender: &str) -> &'static str {
+    match tender {
+        "cash" => "Bar",
+        "gift_card" => "GuthabenKarte",
+        "account" => "Keine",
+        _ => "Unbar",
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local).format("%Y-%m-%dT%H:%M:%S").to_string()
+}
+
+/// A line of a receipt as DSFinV-K has it.
+struct Position {
+    text: String,
+    /// The business case (`GV_TYP`), e.g. "Umsatz" or "Rabatt".
+    case: &'static str,
+    voucher: String,
+    item_id: String,
+    quantity: f64,
+    unit: String,
+    /// Like `amount`, gross or net as the sale's prices are.
+    unit_price: Money,
+    rate: Option<f64>,
+    /// Gross when the sale's prices include tax, otherwise net.
+    amount: Money,
+}
+
+impl Position {
+    fn other(text: &str, case: &'static str, amount: Money) -> Self {
+        Position {
+            text: text.to_string(),
+            case,
+            voucher: String::new(),
+            item_id: String::new(),
+            quantity: 1.0,
+            unit: String::new(),
+            unit_price: amount,
+            rate: None,
+            amount,
+        }
+    }
+}
+
+/// The positions of a receipt: the items with their discounts after each,
+/// then gift cards sold, the tip and the cash rounding. Bundle lines are left
+/// out, their components carry the price.
+fn positions(receipt: &Receipt) -> Vec<Position> {
+    let t = &receipt.transaction;
+    let mut out = Vec::new();
+    for line in receipt.lines.iter().filter(|l| !l.is_bundle) {
+        let case = match (line.is_deposit, line.quantity.is_negative()) {
+            (true, true) => "PfandRueckzahlung",
+            (true, false) => "Pfand",
+            _ => "Umsatz",
+        };
+        out.push(Position {
+            text: line.item_name.clone(),
+            case,
+            voucher: String::new(),
+            item_id: line.item_id.to_string(),
+            quantity: line.quantity.0 as f64 / Quantity::ONE.0 as f64,
+            unit: line.unit.clone().unwrap_or_default(),
+            unit_price: line.unit_price,
+            rate: line.tax_rate,
+            amount: line.total_price,
+        });
+        for discount in &line.discounts {
+            out.push(Position { rate: line.tax_rate, ..Position::other(&discount.name, "Rabatt", discount.amount) });
+        }
+    }
+    for load in &receipt.gift_card_loads {
+        let mut position = Position::other("Gift card", "MehrzweckgutscheinKauf", load.amount);
+        position.voucher = mask_card_code(&load.code);
+        out.push(position);
+    }
+    if !t.tip_amount.is_zero() {
+        out.push(Position::other("Tip", "TrinkgeldAN", t.tip_amount));
+    }
+    if !t.rounding_amount.is_zero() {
+        let case = if t.rounding_amount.is_negative() { "Rabatt" } else { "Aufschlag" };
+        out.push(Position::other("Cash rounding", case, t.rounding_amount));
+    }
+    out
+}
+
+/// Gross, net and tax of `amount` at `rate`. The amount is gross when the
+/// sale's prices include tax, otherwise net.
+fn split_tax(amount: Money, rate: Option<f64>, inclusive: bool) -> (Money, Money, Money) {
+    match tax_breakdown(&[(rate, amount)], inclusive).first() {
+        Some(t) => (t.gross, t.net, t.tax),
+        None => (amount, amount, Money::ZERO),
+    }
+}
+
+/// Gross, net and tax per tax key of `positions`, with the tax worked out
+/// once per rate as on the receipt.
+fn vat_totals<'a>(
+    positions: impl Iterator<Item = &'a Position>,
+    inclusive: bool,
+) -> BTreeMap<i64, (Money, Money, Money)> {
+    let mut sums: BTreeMap<i64, (Option<f64>, Money)> = BTreeMap::new();
+    for p in positions {
+        sums.entry(vat_key(p.rate)).or_insert((p.rate, Money::ZERO)).1 += p.amount;
+    }
+    sums.into_iter().map(|(key, (rate, amount))| (key, split_tax(amount, rate, inclusive))).collect()
+}
+
+/// The receipt's payments with the change handed back taken off the cash
+/// tendered, so they add up to what was kept.
+fn net_payments(receipt: &Receipt) -> Vec<(String, Money)> {
+    let mut change = receipt.transaction.change_amount.unwrap_or_default();
+    receipt
+        .payments
+        .iter()
+        .map(|p| {
+            let mut amount = p.amount;
+            if p.tender == "cash" {
+                amount -= change;
+                change = Money::ZERO;
+            }
+            (p.tender.clone(), amount)
+        })
+        .collect()
+}
+
+/// The rows of every table for `export`.
+fn tables(export: &Export) -> Vec<Table> {
+    let business = &export.business;
+    let currency = currency_code(&export.currency);
+    let amount = |m: Money| m.format(&export.currency);
+    let register = if business.register_id.is_empty() { "1" } else { business.register_id.as_str() };
+
+    let mut cashpoint_closing = Vec::new();
+    let mut location = Vec::new();
+    let mut cash_register = Vec::new();
+    let mut vat = Vec::new();
+    let mut tse = Vec::new();
+    let mut transactions = Vec::new();
+    let mut transactions_vat = Vec::new();
+    let mut datapayment = Vec::new();
+    let mut references = Vec::new();
+    let mut transactions_tse = Vec::new();
+    let mut lines = Vec::new();
+    let mut lines_vat = Vec::new();
+    let mut business_cases = Vec::new();
+    let mut payment = Vec::new();
+    let mut cash_per_currency = Vec::new();
+
+    for closing in &export.closings {
+        let z = vec![register.to_string(), timestamp(closing.created_at), closing.number.to_string()];
+        let row = |fields: Vec<String>| [z.clone(), fields].concat();
+
+        let mut keys_used = std::collections::BTreeSet::new();
+        let mut cases: BTreeMap<(&'static str, i64), (Option<f64>, Money, bool)> = BTreeMap::new();
+        let mut payments: BTreeMap<(&'static str, String), Money> = BTreeMap::new();
+        for receipt in &closing.receipts {
+            let t = &receipt.transaction;
+            let bon = t.id.to_string();
+            let cancelled = t.status == "cancelled";
+            let positions = positions(receipt);
+            let receipt_vat = vat_totals(positions.iter(), t.tax_inclusive);
+            let gross: Money = receipt_vat.values().map(|(gross, _, _)| *gross).sum();
+            let user = t.closed_by.clone().unwrap_or_default();
+
+            transactions.push(row(vec![
+                bon.clone(),
+                receipt.number.to_string(),
+                if cancelled { "AVBelegabbruch" } else { "Beleg" }.to_string(),
+                String::new(),
+                String::new(),
+                "0".to_string(),
+                timestamp(t.created_at),
+                timestamp(t.closed_at.unwrap_or(t.updated_at)),
+                user.clone(),
+                user,
+                amount(gross),
+                t.customer_name.clone().unwrap_or_default(),
+                t.customer_id.map(|id| id.to_string()).unwrap_or_default(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+                String::new(),
+            ]));
+            for (key, (gross, net, tax)) in &receipt_vat {
+                keys_used.insert(*key);
+                transactions_vat.push(row(vec![bon.clone(), key.to_string(), amount(*gross), amount(*net), amount(*tax)]));
+            }
+
+            for (i, p) in positions.iter().enumerate() {
+                let line = (i + 1).to_string();
+                let (gross, net, tax) = split_tax(p.amount, p.rate, t.tax_inclusive);
+                let key = vat_key(p.rate);
+                keys_used.insert(key);
+                lines.push(row(vec![
+                    bon.clone(),
+                    line.clone(),
+                    p.voucher.clone(),
+                    p.text.clone(),
+                    String::new(),
+                    p.case.to_string(),
+                    String::new(),
+                    if t.table_id.is_some() { "1" } else { "0" }.to_string(),
+                    "0".to_string(),
+                    "0".to_string(),
+                    p.item_id.clone(),
+                    String::new(),
+                    String::new(),
+                    String::new(),
+                    format!("{:.3}", p.quantity),
+                    format!("{:.3}", 1.0),
+                    p.unit.clone(),
+                    // STK_BR is the gross price, whichever way the sale was taxed.
+                    amount(split_tax(p.unit_price, p.rate, t.tax_inclusive).0),
+                ]));
+                lines_vat.push(row(vec![bon.clone(), line, key.to_string(), amount(gross), amount(net), amount(tax)]));
+                if !cancelled {
+                    let case = cases.entry((p.case, key)).or_insert((p.rate, Money::ZERO, t.tax_inclusive));
+                    case.1 += p.amount;
+                }
+            }
+
+            if !cancelled {
+                for (tender, paid) in net_payments(receipt) {
+                    datapayment.push(row(vec![
+                        bon.clone(),
+                        payment_type(&tender).to_string(),
+                        tender.clone(),
+                        String::new(),
+                        String::new(),
+                        amount(paid),
+                    ]));
+                    *payments.entry((payment_type(&tender), tender)).or_default() += paid;
+                }
+            }
+
+            if let Some(original) = &receipt.refund_of {
+                references.push(row(vec![
+                    bon.clone(),
+                    String::new(),
+                    "Transaktion".to_string(),
+                    String::new(),
+                    timestamp(original.finished_at),
+                    register.to_string(),
+                    original.closing.to_string(),
+                    original.transaction_id.to_string(),
+                ]));
+            }
+
+            if t.fiscal_counter.is_some() || t.fiscal_error.is_some() {
+                let signer = t
+                    .fiscal_signer
+                    .as_ref()
+                    .and_then(|serial| export.signers.iter().position(|s| &s.serial == serial))
+                    .map(|i| (i + 1).to_string())
+                    .unwrap_or_default();
+                let counter = t.fiscal_counter.map(|c| c.to_string()).unwrap_or_default();
+                transactions_tse.push(row(vec![
+                    bon.clone(),
+                    signer,
+                    counter.clone(),
+                    t.fiscal_started_at.map(timestamp).unwrap_or_default(),
+                    t.fiscal_signed_at.map(timestamp).unwrap_or_default(),
+                    "Kassenbeleg-V1".to_string(),
+                    counter,
+                    t.fiscal_signature.clone().unwrap_or_default(),
+                    t.fiscal_error.clone().unwrap_or_default(),
+                ]));
+            }
+        }
+
+        let paid: Money = payments.values().copied().sum();
+        let cash: Money = payments.iter().filter(|((kind, _), _)| *kind == "Bar").map(|(_, m)| *m).sum();
+        let bon_id = |r: Option<&Receipt>| r.map(|r| r.transaction.id.to_string()).unwrap_or_default();
+        cashpoint_closing.push(row(vec![
+            closing.business_day.format("%Y-%m-%d").to_string(),
+            TAXONOMY_VERSION.to_string(),
+            bon_id(closing.receipts.first()),
+            bon_id(closing.receipts.last()),
+            business.name.clone(),
+            business.street.clone(),
+            business.postcode.clone(),
+            business.city.clone(),
+            business.country.clone(),
+            business.tax_number.clone(),
+            business.vat_id.clone(),
+            amount(paid),
+            amount(cash),
+        ]));
+        location.push(row(vec![
+            business.name.clone(),
+            business.street.clone(),
+            business.postcode.clone(),
+            business.city.clone(),
+            business.country.clone(),
+            business.vat_id.clone(),
+        ]));
+        cash_register.push(row(vec![
+            "RustPOS".to_string(),
+            "RustPOS".to_string(),
+            register.to_string(),
+            "RustPOS".to_string(),
+            export.software_version.clone(),
+            currency.clone(),
+            String::new(),
+        ]));
+        for key in keys_used {
+            let rate = match key {
+                1 => 19.0,
+                2 => 7.0,
+                3 => 10.7,
+                4 => 5.5,
+                5 | 6 => 0.0,
+                _ => (key - 1000) as f64 / 100.0,
+            };
+            vat.push(row(vec![key.to_string(), format!("{:.2}", rate), vat_description(key).to_string()]));
+        }
+        for (i, signer) in export.signers.iter().enumerate() {
+            tse.push(row(vec![
+                (i + 1).to_string(),
+                signer.serial.clone(),
+                signer.algorithm.clone(),
+                "utcTime".to_string(),
+                "UTF-8".to_string(),
+                signer.public_key.clone(),
+                String::new(),
+                String::new(),
+            ]));
+        }
+        for ((case, key), (rate, sum, inclusive)) in cases {
+            let (gross, net, tax) = split_tax(sum, rate, inclusive);
+            business_cases.push(row(vec![
+                case.to_string(),
+                String::new(),
+                "0".to_string(),
+                key.to_string(),
+                amount(gross),
+                amount(net),
+                amount(tax),
+            ]));
+        }
+        for ((kind, tender), paid) in payments {
+            payment.push(row(vec![kind.to_string(), tender, amount(paid)]));
+        }
+        cash_per_currency.push(row(vec![currency.clone(), amount(cash)]));
+    }
+
+    let table = |file, name, keys, columns, rows| Table { file, name, keys, columns, rows };
+    vec![
+        table("cashpointclosing.csv", "Stamm_Abschluss", 3, CASHPOINT_CLOSING, cashpoint_closing),
+        table("location.csv", "Stamm_Orte", 3, LOCATION, location),
+        table("cashregister.csv", "Stamm_Kassen", 3, CASH_REGISTER, cash_register),
+        table("vat.csv", "Stamm_USt", 4, VAT, vat),
+        table("tse.csv", "Stamm_TSE", 4, TSE, tse),
+        table("transactions.csv", "Bonkopf", 4, TRANSACTIONS, transactions),
+        table("transactions_vat.csv", "Bonkopf_USt", 5, TRANSACTIONS_VAT, transactions_vat),
+        table("datapayment.csv", "Bonkopf_Zahlarten", 6, DATAPAYMENT, datapayment),
+        table("references.csv", "Bon_Referenzen", 6, REFERENCES, references),
+        table("transactions_tse.csv", "TSE_Transaktionen", 6, TRANSACTIONS_TSE, transactions_tse),
+        table("lines.csv", "Bonpos", 5, LINES, lines),
+        table("lines_vat.csv", "Bonpos_USt", 6, LINES_VAT, lines_vat),
+        table("businesscases.csv", "Z_GV_Typ", 7, BUSINESS_CASES, business_cases),
+        table("payment.csv", "Z_Zahlart", 5, PAYMENT, payment),
+        table("cash_per_currency.csv", "Z_Waehrungen", 4, CASH_PER_CURRENCY, cash_per_currency),
+    ]
+}
+
+fn csv(table: &Table) -> String {
+    let mut out = table.columns.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(";");
+    out.push_str("\r\n");
+    for row in &table.rows {
+        let fields: Vec<String> = row
+            .iter()
+            .zip(table.columns)
+            .map(|(value, (_, kind))| match kind {
+                Text => format!("\"{}\"", value.replace('"', "\"\"")),
+                _ => value.clone(),
+            })
+            .collect();
+        out.push_str(&fields.join(";"));
+        out.push_str("\r\n");
+    }
+    out
+}
+
+fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;").replace('"', "&quot;")
+}
+
+/// The GDPdU description of the tables. Every file starts with a header
+/// line, which the description skips.
+fn index_xml(export: &Export, tables: &[Table]) -> String {
+    let decimals = currency_decimals(&export.currency);
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE DataSet SYSTEM \"gdpdu-01-09-2004.dtd\">\n<DataSet>\n",
+    );
+    xml.push_str("  <Version>1.0</Version>\n");
+    xml.push_str(&format!(
+        "  <DataSupplier>\n    <Name>{}</Name>\n    <Location>{}</Location>\n    <Comment>DSFinV-K {}</Comment>\n  </DataSupplier>\n",
+        xml_escape(&export.business.name),
+        xml_escape(&export.business.city),
+        TAXONOMY_VERSION,
+    ));
+    xml.push_str("  <Media>\n    <Name>DSFinV-K</Name>\n");
+    for table in tables {
+        xml.push_str("    <Table>\n");
+        xml.push_str(&format!("      <URL>{}</URL>\n", table.file));
+        xml.push_str(&format!("      <Name>{}</Name>\n", table.name));
+        xml.push_str(&format!("      <Description>{}</Description>\n", table.name));
+        xml.push_str("      <UTF8/>\n");
+        xml.push_str("      <DecimalSymbol>.</DecimalSymbol>\n      <DigitGroupingSymbol>,</DigitGroupingSymbol>\n");
+        xml.push_str("      <Range>\n        <From>2</From>\n      </Range>\n");
+        xml.push_str("      <VariableLength>\n");
+        xml.push_str("        <ColumnDelimiter>;</ColumnDelimiter>\n");
+        xml.push_str("        <RecordDelimiter>&#13;&#10;</RecordDelimiter>\n");
+        xml.push_str("        <TextEncapsulator>&quot;</TextEncapsulator>\n");
+        for (i, (name, kind)) in table.columns.iter().enumerate() {
+            let element = if i < table.keys { "VariablePrimaryKey" } else { "VariableColumn" };
+            let kind = match kind {
+                Text => "<AlphaNumeric/>".to_string(),
+                Integer => "<Numeric/>".to_string(),
+                Amount => format!("<Numeric><Accuracy>{}</Accuracy></Numeric>", decimals),
+                Decimal(accuracy) => format!("<Numeric><Accuracy>{}</Accuracy></Numeric>", accuracy),
+                Date => "<Date><Format>YYYY-MM-DD</Format></Date>".to_string(),
+                Timestamp => "<Date><Format>YYYY-MM-DD&quot;T&quot;hh:mm:ss</Format></Date>".to_string(),
+            };
+            xml.push_str(&format!("        <{0}><Name>{1}</Name>{2}</{0}>\n", element, name, kind));
+        }
+        xml.push_str("      </VariableLength>\n    </Table>\n");
+    }
+    xml.push_str("  </Media>\n</DataSet>\n");
+    xml
+}
+
+/// The export as a ZIP archive of `index.xml` and the CSV files.
+pub fn build_archive(export: &Export) -> Result<Vec<u8>, String> {
+    use zip::write::SimpleFileOptions;
+
+    let tables = tables(export);
+    let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+    let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+    let mut add = |name: &str, content: String| -> Result<(), String> {
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(content.as_bytes()).map_err(|e| e.to_string())
+    };
+    add("index.xml", index_xml(export, &tables))?;
+    for table in &tables {
+        add(table.file, csv(table))?;
+    }
+    Ok(zip.finish().map_err(|e| e.to_string())?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::TransactionDiscount;
+    use chrono::TimeZone;
+    use std::io::Read;
+
+    fn transaction(status: &str, total: i64, at: DateTime<Utc>) -> Transaction {
+        Transaction {
+            id: Uuid::new_v4(),
+            customer_name: None,
+            status: status.to_string(),
+            total: Money(total),
+            paid_amount: None,
+            change_amount: None,
+            customer_group_id: None,
+            created_at: at - chrono::Duration::minutes(5),
+            updated_at: at,
+            closed_at: (status == "closed").then_some(at),
+            refund_of: None,
+            tax_inclusive: true,
+            table_id: None,
+            customer_id: None,
+            tip_amount: Money::ZERO,
+            closed_by: Some("anna".to_string()),
+            rounding_amount: Money::ZERO,
+            fiscal_signer: None,
+            fiscal_counter: None,
+            fiscal_signature: None,
+            fiscal_started_at: None,
+            fiscal_signed_at: None,
+            fiscal_error: None,
+        }
+    }
+
+    fn line(name: &str, quantity: i32, unit_price: i64, tax_rate: f64) -> TransactionItemDetail {
+        TransactionItemDetail {
+            id: Uuid::new_v4(),
+            item_id: Uuid::new_v4(),
+            item_name: name.to_string(),
+            quantity: Quantity::units(quantity as i64),
+            unit_price: Money(unit_price),
+            total_price: Money(unit_price * quantity as i64),
+            tax_rate: Some(tax_rate),
+            note: None,
+            unit: None,
+            is_bundle: false,
+            bundle_id: None,
+            is_deposit: false,
+            deposit_for: None,
+            modifiers: Vec::new(),
+            components: Vec::new(),
+            discounts: Vec::new(),
+        }
+    }
+
+    fn payment(transaction_id: Uuid, tender: &str, amount: i64) -> Payment {
+        Payment {
+            id: Uuid::new_v4(),
+            transaction_id,
+            tender: tender.to_string(),
+            amount: Money(amount),
+            created_at: Utc::now(),
+        }
+    }
+
+    /// One day: a sale of two coffees (19%) and a discounted cake (7%) with a
+    /// tip, paid in cash; a cancelled sale; and a signed refund of one coffee.
+    fn fixture() -> Export {
+        let at = Utc.with_ymd_and_hms(2025, 3, 1, 11, 0, 0).unwrap();
+
+        let mut sale = transaction("closed", 850, at);
+        sale.tip_amount = Money(100);
+        sale.paid_amount = Some(Money(1000));
+        sale.change_amount = Some(Money(50));
+        let mut cake = line("Cake \"Deluxe\"; large", 1, 400, 7.0);
+        cake.discounts.push(TransactionDiscount {
+            transaction_item_id: cake.id,
+            promotion_id: None,
+            manual_discount_id: None,
+            reason: None,
+            user_name: None,
+            name: "Happy hour".to_string(),
+            amount: Money(-50),
+        });
+        let sale_payments = vec![payment(sale.id, "cash", 1000)];
+
+        let cancelled = transaction("cancelled", 250, at + chrono::Duration::minutes(10));
+
+        let mut refund = transaction("closed", -250, at + chrono::Duration::minutes(20));
+        refund.refund_of = Some(sale.id);
+        refund.fiscal_signer = Some("ab12cd34".to_string());
+        refund.fiscal_counter = Some(7);
+        refund.fiscal_signature = Some("c2lnbmF0dXJl".to_string());
+        refund.fiscal_started_at = Some(refund.created_at);
+        refund.fiscal_signed_at = refund.closed_at;
+        let refund_payments = vec![payment(refund.id, "card", -250)];
+
+        let original = Reference { closing: 1, transaction_id: sale.id, finished_at: at };
+        Export {
+            business: BusinessDetails {
+                name: "Café & Bar".to_string(),
+                city: "Berlin".to_string(),
+                country: "DEU".to_string(),
+                ..Default::default()
+            },
+            currency: "€".to_string(),
+            software_version: "1.0.0".to_string(),
+            signers: vec![SignerInfo {
+                serial: "ab12cd34".to_string(),
+                algorithm: "Ed25519".to_string(),
+                public_key: "cHVibGlj".to_string(),
+            }],
+            closings: vec![Closing {
+                number: 1,
+                business_day: at.with_timezone(&Local).date_naive(),
+                created_at: at + chrono::Duration::minutes(20),
+                receipts: vec![
+                    Receipt {
+                        number: 1,
+                        lines: vec![line("Coffee", 2, 250, 19.0), cake],
+                        payments: sale_payments,
+                        gift_card_loads: Vec::new(),
+                        refund_of: None,
+                        transaction: sale,
+                    },
+                    Receipt {
+                        number: 2,
+                        lines: vec![line("Coffee", 1, 250, 19.0)],
+                        payments: Vec::new(),
+                        gift_card_loads: Vec::new(),
+                        refund_of: None,
+                        transaction: cancelled,
+                    },
+                    Receipt {
+                        number: 3,
+                        lines: vec![line("Coffee", -1, 250, 19.0)],
+                        payments: refund_payments,
+                        gift_card_loads: Vec::new(),
+                        refund_of: Some(original),
+                        transaction: refund,
+                    },
+                ],
+            }],
+        }
+    }
+
+    /// Splits a CSV record, honouring quoted fields.
+    fn fields(record: &str) -> Vec<String> {
+        let mut out = vec![String::new()];
+        let mut quoted = false;
+        let mut chars = record.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '"' if quoted && chars.peek() == Some(&'"') => {
+                    chars.next();
+                    out.last_mut().unwrap().push('"');
+                }
+                '"' => quoted = !quoted,
+                ';' if !quoted => out.push(String::new()),
+                c => out.last_mut().unwrap().push(c),
+            }
+        }
+        out
+    }
+
+    /// The records of a table by column name, header left out.
+    fn records(table: &Table) -> Vec<BTreeMap<&'static str, String>> {
+        let text = csv(table);
+        let mut lines = text.split("\r\n").filter(|l| !l.is_empty());
+        let header = lines.next().unwrap();
+        assert_eq!(fields(header).len(), table.columns.len(), "{}", table.file);
+        lines
+            .map(|l| {
+                let values = fields(l);
+                assert_eq!(values.len(), table.columns.len(), "{}: {}", table.file, l);
+                table.columns.iter().map(|(name, _)| *name).zip(values).collect()
+            })
+            .collect()
+    }
+
+    fn table<'a>(tables: &'a [Table], file: &str) -> &'a Table {
+        tables.iter().find(|t| t.file == file).unwrap()
+    }
+
+    #[test]
+    fn archive_holds_the_index_and_every_table() {
+        let export = fixture();
+        let bytes = build_archive(&export).unwrap();
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(bytes)).unwrap();
+        let mut index = String::new();
+        archive.by_name("index.xml").unwrap().read_to_string(&mut index).unwrap();
+        assert!(index.contains("<Name>Café &amp; Bar</Name>"));
+
+        for table in tables(&export) {
+            let mut content = String::new();
+            archive.by_name(table.file).unwrap().read_to_string(&mut content).unwrap();
+            assert_eq!(content, csv(&table));
+            // The index describes each file with its columns in order.
+            let start = index.find(&format!("<URL>{}</URL>", table.file)).unwrap();
+            let described = &index[start..start + index[start..].find("</Table>").unwrap()];
+            let names: Vec<&str> = described
+                .split("<Name>")
+                .skip(2)
+                .map(|s| &s[..s.find("</Name>").unwrap()])
+                .collect();
+            let columns: Vec<&str> = table.columns.iter().map(|(name, _)| *name).collect();
+            assert_eq!(names, columns, "{}", table.file);
+            assert_eq!(described.matches("<VariablePrimaryKey>").count(), table.keys, "{}", table.file);
+            // Every record has a value for every column.
+            records(&table);
+        }
+    }
+
+    #[test]
+    fn receipts_lines_and_payments() {
+        let export = fixture();
+        let tables = tables(&export);
+        let sale = export.closings[0].receipts[0].transaction.id.to_string();
+        let refund = export.closings[0].receipts[2].transaction.id.to_string();
+
+        let receipts = records(table(&tables, "transactions.csv"));
+        let kinds: Vec<(&str, &str, &str)> = receipts
+            .iter()
+            .map(|r| (r["BON_NR"].as_str(), r["BON_TYP"].as_str(), r["UMS_BRUTTO"].as_str()))
+            .collect();
+        assert_eq!(kinds, vec![("1", "Beleg", "9.50"), ("2", "AVBelegabbruch", "2.50"), ("3", "Beleg", "-2.50")]);
+        assert_eq!(receipts[0]["Z_ERSTELLUNG"], timestamp(export.closings[0].created_at));
+
+        // Coffee, cake, its discount and the tip, with a quoted item name.
+        let lines: Vec<_> = records(table(&tables, "lines.csv")).into_iter().filter(|l| l["BON_ID"] == sale).collect();
+        let cases: Vec<(&str, &str, &str, &str)> = lines
+            .iter()
+            .map(|l| (l["GV_TYP"].as_str(), l["ARTIKELTEXT"].as_str(), l["MENGE"].as_str(), l["STK_BR"].as_str()))
+            .collect();
+        assert_eq!(
+            cases,
+            vec![
+                ("Umsatz", "Coffee", "2.000", "2.50"),
+                ("Umsatz", "Cake \"Deluxe\"; large", "1.000", "4.00"),
+                ("Rabatt", "Happy hour", "1.000", "-0.50"),
+                ("TrinkgeldAN", "Tip", "1.000", "1.00"),
+            ]
+        );
+
+        // Tax per key on the sale: 5.00 at 19%, 3.50 at 7%, the tip untaxed.
+        let vat: Vec<(String, String, String, String)> = records(table(&tables, "transactions_vat.csv"))
+            .into_iter()
+            .filter(|v| v["BON_ID"] == sale)
+            .map(|v| {
+                (v["UST_SCHLUESSEL"].clone(), v["BON_BRUTTO"].clone(), v["BON_NETTO"].clone(), v["BON_UST"].clone())
+            })
+            .collect();
+        let expected = [("1", "5.00", "4.20", "0.80"), ("2", "3.50", "3.27", "0.23"), ("5", "1.00", "1.00", "0.00")];
+        let expected: Vec<(String, String, String, String)> = expected
+            .iter()
+            .map(|(a, b, c, d)| (a.to_string(), b.to_string(), c.to_string(), d.to_string()))
+            .collect();
+        assert_eq!(vat, expected);
+
+        // The change is taken off the cash tendered.
+        let paid: Vec<(String, String, String)> = records(table(&tables, "datapayment.csv"))
+            .into_iter()
+            .map(|p| (p["BON_ID"].clone(), p["ZAHLART_TYP"].clone(), p["BASISWAEH_BETRAG"].clone()))
+            .collect();
+        assert_eq!(
+            paid,
+            vec![
+                (sale.clone(), "Bar".to_string(), "9.50".to_string()),
+                (refund.clone(), "Unbar".to_string(), "-2.50".to_string()),
+            ]
+        );
+
+        let references = records(table(&tables, "references.csv"));
+        assert_eq!(references.len(), 1);
+        assert_eq!(references[0]["BON_ID"], refund);
+        assert_eq!(references[0]["REF_Z_NR"], "1");
+        assert_eq!(references[0]["REF_BON_ID"], sale);
+
+        let signed = records(table(&tables, "transactions_tse.csv"));
+        assert_eq!(signed.len(), 1);
+        assert_eq!(signed[0]["BON_ID"], refund);
+        assert_eq!((signed[0]["TSE_ID"].as_str(), signed[0]["TSE_TANR"].as_str()), ("1", "7"));
+    }
+
+    #[test]
+    fn unit_prices_are_gross_when_the_sale_adds_tax() {
+        let mut export = fixture();
+        let sale = &mut export.closings[0].receipts[0];
+        sale.transaction.tax_inclusive = false;
+        let sale = sale.transaction.id.to_string();
+        let tables = tables(&export);
+
+        // Net 2.50 at 19% and net 4.00 at 7%, less 0.50 net off the cake.
+        let lines: Vec<(String, String)> = records(table(&tables, "lines.csv"))
+            .into_iter()
+            .filter(|l| l["BON_ID"] == sale)
+            .map(|l| (l["ARTIKELTEXT"].clone(), l["STK_BR"].clone()))
+            .collect();
+        let prices: Vec<(&str, &str)> = lines.iter().map(|(text, price)| (text.as_str(), price.as_str())).collect();
+        assert_eq!(
+            prices,
+            vec![("Coffee", "2.98"), ("Cake \"Deluxe\"; large", "4.28"), ("Happy hour", "-0.54"), ("Tip", "1.00")]
+        );
+
+        let vat: Vec<(String, String)> = records(table(&tables, "lines_vat.csv"))
+            .into_iter()
+            .filter(|v| v["BON_ID"] == sale)
+            .map(|v| (v["UST_SCHLUESSEL"].clone(), v["POS_BRUTTO"].clone()))
+            .collect();
+        assert_eq!(vat[0], ("1".to_string(), "5.95".to_string()));
+    }
+
+    #[test]
+    fn closing_totals_leave_out_cancelled_sales() {
+        let export = fixture();
+        let tables = tables(&export);
+
+        let closing = &records(table(&tables, "cashpointclosing.csv"))[0];
+        assert_eq!(closing["Z_SE_ZAHLUNGEN"], "7.00");
+        assert_eq!(closing["Z_SE_BARZAHLUNGEN"], "9.50");
+        assert_eq!(closing["Z_START_ID"], export.closings[0].receipts[0].transaction.id.to_string());
+
+        // Two coffees sold and one refunded; the cancelled one is not revenue.
+        let cases: Vec<(String, String, String)> = records(table(&tables, "businesscases.csv"))
+            .into_iter()
+            .map(|c| (c["GV_TYP"].clone(), c["UST_SCHLUESSEL"].clone(), c["Z_UMS_BRUTTO"].clone()))
+            .collect();
+        let expected = [
+            ("Rabatt", "2", "-0.50"),
+            ("TrinkgeldAN", "5", "1.00"),
+            ("Umsatz", "1", "2.50"),
+            ("Umsatz", "2", "4.00"),
+        ];
+        let expected: Vec<(String, String, String)> =
+            expected.iter().map(|(a, b, c)| (a.to_string(), b.to_string(), c.to_string())).collect();
+        assert_eq!(cases, expected);
+
+        let payments: Vec<(String, String)> = records(table(&tables, "payment.csv"))
+            .into_iter()
+            .map(|p| (p["ZAHLART_NAME"].clone(), p["Z_ZAHLART_BETRAG"].clone()))
+            .collect();
+        assert_eq!(payments, vec![("cash".to_string(), "9.50".to_string()), ("card".to_string(), "-2.50".to_string())]);
+
+        let rates: Vec<(String, String)> = records(table(&tables, "vat.csv"))
+            .into_iter()
+            .map(|v| (v["UST_SCHLUESSEL"].clone(), v["UST_SATZ"].clone()))
+            .collect();
+        let expected = [("1", "19.00"), ("2", "7.00"), ("5", "0.00")];
+        let expected: Vec<(String, String)> = expected.iter().map(|(a, b)| (a.to_string(), b.to_string())).collect();
+        assert_eq!(rates, expected);
+    }
+
+    #[test]
+    fn vat_keys() {
+        assert_eq!(vat_key(Some(19.0)), 1);
+        assert_eq!(vat_key(Some(7.0)), 2);
+        assert_eq!(vat_key(Some(0.0)), 6);
+        assert_eq!(vat_key(None), 5);
+        assert_eq!(vat_key(Some(8.1)), 1810);
+        assert_eq!(currency_code("€"), "EUR");
+        assert_eq!(currency_code("CHF"), "CHF");
+    }
+}
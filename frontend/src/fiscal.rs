@@ -0,0 +1,242 @@
+//! Fiscal signing of finished sales, as a TSE (Germany) or RKSV signature
+//! device (Austria) does it.
+//!
+//! Every sale that is closed, cancelled or refunded is signed by the
+//! [`FiscalSigner`] set up at start, over the hash of its journal entry (see
+//! `journal`), which covers the sale and every entry before it. The signer's
+//! counter, the signature and the start and signing times are stored with the
+//! transaction and printed on the receipt as a QR code. When signing fails the
+//! sale is refused, unless a signer outage has been declared; then it goes
+//! through with the error stored with it, printed on the receipt and shown to
+//! the cashier. Signing is the last step before the sale is committed, so a
+//! signature is never used up by a sale that is then rolled back.
+//!
+//! [`SoftwareSigner`] signs with a local Ed25519 key. It stands in for a
+//! certified device in testing, without any of its protection of the key.
+
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use ed25519_dalek::{Signer, SigningKey};
+use sha2::{Digest, Sha256};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use uuid::Uuid;
+
+/// A finished sale to sign.
+#[derive(Debug, Clone)]
+pub struct SignRequest {
+    pub transaction_id: Uuid,
+    /// The journal event, "sale", "cancel" or "refund".
+    pub process_type: String,
+    /// The hash of the sale's journal entry.
+    pub process_data: String,
+    /// When the sale was started.
+    pub started_at: DateTime<Utc>,
+}
+
+/// A signature returned by a [`FiscalSigner`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FiscalSignature {
+    /// The serial number of the signer.
+    pub signer: String,
+    pub algorithm: String,
+    /// The signer's signature counter, one up for every signature it made.
+    pub counter: i64,
+    pub started_at: DateTime<Utc>,
+    pub signed_at: DateTime<Utc>,
+    /// The signature, base64 encoded.
+    pub signature: String,
+}
+
+/// What a signer tells about itself, for tax audit exports.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SignerInfo {
+    pub serial: String,
+    pub algorithm: String,
+    /// The public key to check signatures with, base64 encoded.
+    pub public_key: String,
+}
+
+/// A signature device. Signing may block, e.g. on a device attached by USB.
+pub trait FiscalSigner: Send + Sync {
+    /// Signs a finished sale. The error says what went wrong, for the cashier.
+    fn sign(&self, request: &SignRequest) -> Result<FiscalSignature, String>;
+
+    fn info(&self) -> SignerInfo;
+}
+
+/// The signer provided as context to server functions. Without one, sales
+/// are not signed.
+#[derive(Clone)]
+pub struct FiscalSignerHandle(pub Arc<dyn FiscalSigner>);
+
+/// The message a signature is made over:
+/// `serial;process type;process data;counter;start;end`, times in RFC 3339.
+pub fn signed_message(signer: &str, counter: i64, request: &SignRequest, signed_at: DateTime<Utc>) -> String {
+    format!(
+        "{};{};{};{};{};{}",
+        signer,
+        request.process_type,
+        request.process_data,
+        counter,
+        request.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        signed_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+    )
+}
+
+/// The content of the receipt's QR code:
+/// `V0;serial;process type;process data;counter;start;end;algorithm;signature`.
+pub fn qr_code(signature: &FiscalSignature, process_type: &str, process_data: &str) -> String {
+    format!(
+        "V0;{};{};{};{};{};{};{};{}",
+        signature.signer,
+        process_type,
+        process_data,
+        signature.counter,
+        signature.started_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        signature.signed_at.to_rfc3339_opts(SecondsFormat::Millis, true),
+        signature.algorithm,
+        signature.signature,
+    )
+}
+
+/// Signs with an Ed25519 key kept in a file, for testing without a device.
+/// The signature counter is kept next to the key.
+pub struct SoftwareSigner {
+    key: SigningKey,
+    serial: String,
+    counter: Mutex<i64>,
+    counter_path: PathBuf,
+}
+
+impl SoftwareSigner {
+    pub const ALGORITHM: &'static str = "Ed25519";
+
+    /// Opens the key at `key_path`, creating a new random one if there is
+    /// none yet. The counter is kept in the same place with a `.counter`
+    /// extension.
+    pub fn open(key_path: &Path) -> std::io::Result<Self> {
+        let bytes = match std::fs::read(key_path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let bytes: [u8; 32] = rand::random();
+                std::fs::write(key_path, bytes)?;
+                bytes.to_vec()
+            }
+            Err(e) => return Err(e),
+        };
+        let bytes: [u8; 32] = bytes
+            .try_into()
+            .map_err(|_| Error::new(ErrorKind::InvalidData, "The signing key must be 32 bytes"))?;
+        let key = SigningKey::from_bytes(&bytes);
+
+        let counter_path = key_path.with_extension("counter");
+        let counter = match std::fs::read_to_string(&counter_path) {
+            Ok(text) => text
+                .trim()
+                .parse()
+                .map_err(|_| Error::new(ErrorKind::InvalidData, "The signature counter is not a number"))?,
+            Err(e) if e.kind() == ErrorKind::NotFound => 0,
+            Err(e) => return Err(e),
+        };
+
+        // Like a device's serial number: the start of its public key's hash.
+        let digest = Sha256::digest(key.verifying_key().to_bytes());
+        let serial = digest[..8].iter().map(|b| format!("{:02x}", b)).collect();
+        Ok(Self { key, serial, counter: Mutex::new(counter), counter_path })
+    }
+
+    pub fn serial(&self) -> &str {
+        &self.serial
+    }
+
+    /// The public key to check signatures with, base64 encoded.
+    pub fn public_key(&self) -> String {
+        public_key_base64(&self.key)
+    }
+}
+
+fn public_key_base64(key: &SigningKey) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key.verifying_key().to_bytes())
+}
+
+impl FiscalSigner for SoftwareSigner {
+    fn sign(&self, request: &SignRequest) -> Result<FiscalSignature, String> {
+        let mut counter = self.counter.lock().map_err(|_| "The signature counter is unavailable".to_string())?;
+        let next = *counter + 1;
+        // Stored before signing, so a counter value is never used twice.
+        std::fs::write(&self.counter_path, next.to_string())
+            .map_err(|e| format!("Cannot store the signature counter: {}", e))?;
+        *counter = next;
+
+        let signed_at = Utc::now();
+        let message = signed_message(&self.serial, next, request, signed_at);
+        let signature = self.key.sign(message.as_bytes());
+        Ok(FiscalSignature {
+            signer: self.serial.clone(),
+            algorithm: Self::ALGORITHM.to_string(),
+            counter: next,
+            started_at: request.started_at,
+            signed_at,
+            signature: base64::engine::general_purpose::STANDARD.encode(signature.to_bytes()),
+        })
+    }
+
+    fn info(&self) -> SignerInfo {
+        SignerInfo {
+            serial: self.serial.clone(),
+            algorithm: Self::ALGORITHM.to_string(),
+            public_key: self.public_key(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+
+    fn request() -> SignRequest {
+        SignRequest {
+            transaction_id: Uuid::new_v4(),
+            process_type: "sale".to_string(),
+            process_data: "ab12".to_string(),
+            started_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn software_signer_signs_and_counts() {
+        let dir = std::env::temp_dir().join(format!("rustpos-signer-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let key_path = dir.join("signer.key");
+
+        let signer = SoftwareSigner::open(&key_path).unwrap();
+        let request = request();
+        let first = signer.sign(&request).unwrap();
+        let second = signer.sign(&request).unwrap();
+        assert_eq!((first.counter, second.counter), (1, 2));
+        assert_eq!(first.signer, signer.serial());
+
+        // The signature checks out against the public key.
+        let b64 = base64::engine::general_purpose::STANDARD;
+        let public: [u8; 32] = b64.decode(signer.public_key()).unwrap().try_into().unwrap();
+        let bytes: [u8; 64] = b64.decode(&second.signature).unwrap().try_into().unwrap();
+        let message = signed_message(&second.signer, 2, &request, second.signed_at);
+        let key = VerifyingKey::from_bytes(&public).unwrap();
+        assert!(key.verify(message.as_bytes(), &Signature::from_bytes(&bytes)).is_ok());
+        assert!(key.verify(b"something else", &Signature::from_bytes(&bytes)).is_err());
+
+        // Reopening keeps the key and continues the counter.
+        let reopened = SoftwareSigner::open(&key_path).unwrap();
+        assert_eq!(reopened.public_key(), signer.public_key());
+        assert_eq!(reopened.sign(&request).unwrap().counter, 3);
+
+        let qr = qr_code(&first, "sale", "ab12");
+        assert!(qr.starts_with(&format!("V0;{};sale;ab12;1;", signer.serial())));
+        assert!(qr.ends_with(&format!(";Ed25519;{}", first.signature)));
+
+        std::fs::remove_dir_all(dir).ok();
+    }
+}
@@ -0,0 +1,120 @@
+//! The hash chain of the tamper-evident journal.
+//!
+//! Each [`JournalEntry`] is hashed over its receipt number, the hash of the
+//! entry before it and its payload, so editing, inserting or deleting an
+//! entry changes every hash after it. The last receipt number and hash are
+//! also kept in the config (`journal_head`), so entries cut off the end are
+//! noticed too. Whether the sales still match their payloads is checked by
+//! the caller, which has the database.
+
+use crate::models::{JournalEntry, JournalProblem};
+use sha2::{Digest, Sha256};
+
+/// The `prev_hash` of the first entry.
+pub const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+
+/// SHA-256 over the receipt number, the previous hash and the payload, as hex.
+pub fn entry_hash(seq: i64, prev_hash: &str, payload: &str) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}\n{}\n{}", seq, prev_hash, payload).as_bytes());
+    hasher.finalize().iter().fold(String::with_capacity(64), |mut s, b| {
+        use std::fmt::Write;
+        let _ = write!(s, "{:02x}", b);
+        s
+    })
+}
+
+/// Parses the `journal_head` config value, `"<seq>:<hash>"`.
+pub fn parse_head(value: &str) -> Option<(i64, String)> {
+    let (seq, hash) = value.split_once(':')?;
+    Some((seq.parse().ok()?, hash.to_string()))
+}
+
+/// Checks that `entries`, in receipt number order, are numbered from 1
+/// without gaps, match their hashes, are chained to each other and end at
+/// `head`.
+pub fn check_chain(entries: &[JournalEntry], head: Option<&(i64, String)>) -> Vec<JournalProblem> {
+    let mut problems = Vec::new();
+    let mut prev_seq = 0;
+    let mut prev_hash = GENESIS_HASH;
+    for entry in entries {
+        if entry.hash != entry_hash(entry.seq, &entry.prev_hash, &entry.payload) {
+            problems.push(JournalProblem::BadHash { seq: entry.seq });
+        }
+        if entry.seq != prev_seq + 1 {
+            // The entries in between are gone, so the chain is broken too.
+            problems.push(JournalProblem::Gap { after: prev_seq, next: entry.seq });
+        } else if entry.prev_hash != prev_hash {
+            problems.push(JournalProblem::BrokenChain { seq: entry.seq });
+        }
+        prev_seq = entry.seq;
+        prev_hash = entry.hash.as_str();
+    }
+    let last = entries.last().map(|e| (e.seq, e.hash.as_str()));
+    if last != head.map(|(seq, hash)| (*seq, hash.as_str())) {
+        problems.push(JournalProblem::BadHead { seq: prev_seq });
+    }
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use uuid::Uuid;
+
+    fn chain(payloads: &[&str]) -> Vec<JournalEntry> {
+        let mut prev_hash = GENESIS_HASH.to_string();
+        payloads
+            .iter()
+            .enumerate()
+            .map(|(i, payload)| {
+                let seq = i as i64 + 1;
+                let hash = entry_hash(seq, &prev_hash, payload);
+                JournalEntry {
+                    seq,
+                    transaction_id: Uuid::new_v4(),
+                    event: "sale".to_string(),
+                    payload: payload.to_string(),
+                    prev_hash: std::mem::replace(&mut prev_hash, hash.clone()),
+                    hash,
+                    created_at: Utc::now(),
+                }
+            })
+            .collect()
+    }
+
+    fn head(entries: &[JournalEntry]) -> Option<(i64, String)> {
+        entries.last().map(|e| (e.seq, e.hash.clone()))
+    }
+
+    #[test]
+    fn intact_chain_passes() {
+        let entries = chain(&["a", "b", "c"]);
+        assert!(check_chain(&entries, head(&entries).as_ref()).is_empty());
+        assert!(check_chain(&[], None).is_empty());
+        assert_eq!(parse_head(&format!("3:{}", entries[2].hash)), head(&entries));
+    }
+
+    #[test]
+    fn edits_and_deletions_are_found() {
+        let entries = chain(&["a", "b", "c", "d"]);
+        let head = head(&entries);
+
+        let mut edited = entries.clone();
+        edited[1].payload = "B".to_string();
+        assert_eq!(check_chain(&edited, head.as_ref()), vec![JournalProblem::BadHash { seq: 2 }]);
+
+        // Rehashing the edited entry breaks the link to the next one.
+        edited[1].hash = entry_hash(2, &edited[1].prev_hash, "B");
+        assert_eq!(check_chain(&edited, head.as_ref()), vec![JournalProblem::BrokenChain { seq: 3 }]);
+
+        let mut gap = entries.clone();
+        gap.remove(1);
+        assert_eq!(check_chain(&gap, head.as_ref()), vec![JournalProblem::Gap { after: 1, next: 3 }]);
+
+        let cut = &entries[..3];
+        assert_eq!(check_chain(cut, head.as_ref()), vec![JournalProblem::BadHead { seq: 3 }]);
+        assert_eq!(check_chain(&[], head.as_ref()), vec![JournalProblem::BadHead { seq: 0 }]);
+    }
+}
@@ -1,12 +1,22 @@
 #![recursion_limit = "512"]
 
 pub mod app;
+#[cfg(feature = "ssr")]
+pub mod db;
+#[cfg(feature = "ssr")]
+pub mod dsfinvk;
+#[cfg(feature = "ssr")]
+pub mod fiscal;
 pub mod i18n;
 #[cfg(feature = "ssr")]
+pub mod journal;
+#[cfg(feature = "ssr")]
 pub mod menu_pdf;
 pub mod models;
 pub mod pages;
 pub mod server_fns;
+#[cfg(feature = "ssr")]
+pub mod statement_pdf;
 
 #[cfg(feature = "ssr")]
 pub use rustpos_common::printer;
@@ -36,168 +36,8 @@ async fn main() {
         .await
         .expect("Failed to connect to database");
 
-    // Run migrations inline
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS categories (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            description TEXT,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create categories table");
-
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS items (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            description TEXT,
-            price REAL NOT NULL,
-            category_id TEXT NOT NULL,
-            sku TEXT,
-            in_stock BOOLEAN NOT NULL DEFAULT 1,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            FOREIGN KEY (category_id) REFERENCES categories(id)
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create items table");
-
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS transactions (
-            id TEXT PRIMARY KEY,
-            customer_name TEXT,
-            status TEXT NOT NULL CHECK (status IN ('open', 'closed', 'cancelled')),
-            total REAL NOT NULL DEFAULT 0,
-            paid_amount REAL,
-            change_amount REAL,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL,
-            closed_at TEXT
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create transactions table");
-
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS transaction_items (
-            id TEXT PRIMARY KEY,
-            transaction_id TEXT NOT NULL,
-            item_id TEXT NOT NULL,
-            quantity INTEGER NOT NULL,
-            unit_price REAL NOT NULL,
-            total_price REAL NOT NULL,
-            created_at TEXT NOT NULL,
-            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE,
-            FOREIGN KEY (item_id) REFERENCES items(id)
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create transaction_items table");
-
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS customer_groups (
-            id TEXT PRIMARY KEY,
-            name TEXT NOT NULL,
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create customer_groups table");
-
-    // Migrations for new columns
-    sqlx::query("ALTER TABLE categories ADD COLUMN main_course BOOLEAN NOT NULL DEFAULT 0").execute(&db).await.ok();
-    // NULL customer_group_id means the sale belongs to "regular customers".
-    sqlx::query("ALTER TABLE transactions ADD COLUMN customer_group_id TEXT REFERENCES customer_groups(id)").execute(&db).await.ok();
-    sqlx::query("ALTER TABLE categories ADD COLUMN sort_order INTEGER NOT NULL DEFAULT 0").execute(&db).await.ok();
-    // One-time backfill: when no category has an assigned order yet (every row
-    // still at the default 0), seed a stable initial order alphabetically by
-    // name. New/assigned categories always have sort_order >= 1, so this runs
-    // only once and never clobbers a user-defined order.
-    let categories_unordered: i64 =
-        sqlx::query_scalar("SELECT COUNT(*) FROM categories WHERE sort_order != 0")
-            .fetch_one(&db)
-            .await
-            .unwrap_or(0);
-    if categories_unordered == 0 {
-        sqlx::query(
-            r#"UPDATE categories SET sort_order = (
-                SELECT COUNT(*) FROM categories c2
-                WHERE c2.name < categories.name
-                   OR (c2.name = categories.name AND c2.id <= categories.id)
-            )"#,
-        )
-        .execute(&db)
-        .await
-        .ok();
-    }
-    sqlx::query("ALTER TABLE items ADD COLUMN image_path TEXT").execute(&db).await.ok();
-    sqlx::query("ALTER TABLE items ADD COLUMN stock_quantity INTEGER").execute(&db).await.ok();
-    sqlx::query("ALTER TABLE items ADD COLUMN kitchen_item BOOLEAN NOT NULL DEFAULT 0").execute(&db).await.ok();
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS kitchen_order_items (
-            id TEXT PRIMARY KEY,
-            transaction_id TEXT NOT NULL,
-            transaction_item_id TEXT NOT NULL,
-            item_id TEXT NOT NULL,
-            item_name TEXT NOT NULL,
-            quantity INTEGER NOT NULL,
-            customer_name TEXT,
-            completed BOOLEAN NOT NULL DEFAULT 0,
-            created_at TEXT NOT NULL,
-            completed_at TEXT,
-            FOREIGN KEY (transaction_id) REFERENCES transactions(id) ON DELETE CASCADE
-        )"#,
-    ).execute(&db).await.ok();
-
-    // User accounts and sessions
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS users (
-            id TEXT PRIMARY KEY,
-            username TEXT NOT NULL UNIQUE,
-            pin_hash TEXT NOT NULL,
-            role TEXT NOT NULL CHECK (role IN ('admin', 'cashier', 'cook')),
-            created_at TEXT NOT NULL,
-            updated_at TEXT NOT NULL
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create users table");
-
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS sessions (
-            id TEXT PRIMARY KEY,
-            user_id TEXT NOT NULL,
-            token TEXT NOT NULL UNIQUE,
-            created_at TEXT NOT NULL,
-            expires_at TEXT NOT NULL,
-            FOREIGN KEY (user_id) REFERENCES users(id) ON DELETE CASCADE
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create sessions table");
-
-    // Configuration table
-    sqlx::query(
-        r#"CREATE TABLE IF NOT EXISTS config (
-            key TEXT PRIMARY KEY,
-            value TEXT NOT NULL
-        )"#,
-    )
-    .execute(&db)
-    .await
-    .expect("Failed to create config table");
+    // Create or update the schema
+    rustpos::db::migrate(&db).await;
 
     // Apply the configured ESC/POS printer code page (default 16 = WPC1252).
     if let Ok(Some(value)) =
@@ -210,36 +50,43 @@ async fn main() {
         }
     }
 
-    // Create indexes
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_items_category_id ON items(category_id)")
-        .execute(&db)
+    // Sales finished before the journal existed are journalled once, in the
+    // order they were finished.
+    rustpos::server_fns::start_journal_db(&db)
         .await
-        .ok();
-    sqlx::query("CREATE INDEX IF NOT EXISTS idx_transaction_items_transaction_id ON transaction_items(transaction_id)")
-        .execute(&db)
-        .await
-        .ok();
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_transaction_items_item_id ON transaction_items(item_id)",
-    )
-    .execute(&db)
-    .await
-    .ok();
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_transactions_status ON transactions(status)",
-    )
-    .execute(&db)
-    .await
-    .ok();
-    sqlx::query(
-        "CREATE INDEX IF NOT EXISTS idx_transactions_customer_name ON transactions(customer_name)",
-    )
-    .execute(&db)
-    .await
-    .ok();
+        .expect("Failed to start the journal");
+
+    // `rustpos --verify-journal` checks the journal and exits.
+    if env::args().any(|arg| arg == "--verify-journal") {
+        let verification = rustpos::server_fns::verify_journal_db(&db)
+            .await
+            .expect("Failed to read the journal");
+        for problem in &verification.problems {
+            println!("{}", problem.message());
+        }
+        if verification.is_ok() {
+            println!("Journal intact: {} entries verified", verification.entries);
+            return;
+        }
+        println!("Journal verification failed: {} problem(s) in {} entries", verification.problems.len(), verification.entries);
+        std::process::exit(1);
+    }
 
     println!("Database initialized successfully!");
 
+    // `RUSTPOS_FISCAL_SIGNER=software` signs finished sales with a local key,
+    // see `rustpos::fiscal`. Without it sales are not signed.
+    let fiscal_signer = match env::var("RUSTPOS_FISCAL_SIGNER").ok().as_deref() {
+        None | Some("") | Some("none") => None,
+        Some("software") => {
+            let signer = rustpos::fiscal::SoftwareSigner::open(std::path::Path::new("data/signer.key"))
+                .expect("Failed to open the fiscal signing key data/signer.key");
+            println!("Signing sales with software signer {} (public key {})", signer.serial(), signer.public_key());
+            Some(rustpos::fiscal::FiscalSignerHandle(std::sync::Arc::new(signer)))
+        }
+        Some(other) => panic!("Unknown RUSTPOS_FISCAL_SIGNER {:?}, expected \"software\"", other),
+    };
+
     let conf = get_configuration(None).expect("Failed to get Leptos configuration");
     let mut leptos_options = conf.leptos_options;
     // Ensure site_root is relative to cwd so the binary is portable
@@ -273,6 +120,9 @@ async fn main() {
                     provide_context(printer_tx.clone());
                     provide_context(display_tx.clone());
                     provide_context(sale_broadcast.clone());
+                    if let Some(signer) = &fiscal_signer {
+                        provide_context(signer.clone());
+                    }
                 }
             },
             {
@@ -479,7 +329,7 @@ async fn printer_ws_handler(
                     match result {
                         Ok(job) => {
                             let msg = serde_json::to_string(
-                                &ServerMessage::PrintReceipt(job)
+                                &ServerMessage::PrintReceipt(Box::new(job))
                             ).unwrap();
                             if socket.send(Message::Text(msg.into())).await.is_err() {
                                 break;
@@ -11,7 +11,11 @@
 //! flow onto new pages when they would overflow the bottom margin. printpdf
 //! places everything from the bottom-left corner, so coordinates are converted
 //! on the way out.
+//!
+//! The page primitives ([`Pdf`], fonts, colours and A4 geometry) are shared
+//! with the house account statement in `statement_pdf`.
 
+use crate::models::Money;
 use printpdf::path::PaintMode;
 use printpdf::*;
 use std::io::Cursor;
@@ -33,7 +37,9 @@ fn metrics_face() -> &'static ttf_parser::Face<'static> {
 /// A single available item shown on the menu.
 pub struct MenuItem {
     pub name: String,
-    pub price: f64,
+    pub price: Money,
+    /// Printed after the price, e.g. "/kg" for goods sold by weight.
+    pub price_suffix: String,
     /// Optional description, shown under the name for main-course items.
     pub description: Option<String>,
     /// Absolute/relative filesystem path to the item image, if any
@@ -49,12 +55,12 @@ pub struct MenuSection {
 }
 
 // A4 portrait, all units in millimetres.
-const PAGE_W: f32 = 210.0;
-const PAGE_H: f32 = 297.0;
-const MARGIN: f32 = 18.0;
-const CONTENT_W: f32 = PAGE_W - 2.0 * MARGIN;
+pub(crate) const PAGE_W: f32 = 210.0;
+pub(crate) const PAGE_H: f32 = 297.0;
+pub(crate) const MARGIN: f32 = 18.0;
+pub(crate) const CONTENT_W: f32 = PAGE_W - 2.0 * MARGIN;
 /// Largest `y` (distance from top) at which content may still be drawn.
-const BOTTOM_LIMIT: f32 = PAGE_H - MARGIN;
+pub(crate) const BOTTOM_LIMIT: f32 = PAGE_H - MARGIN;
 
 // Two-column layout: the content area below the header is split into two equal
 // columns separated by a gutter, with a vertical divider drawn down its middle.
@@ -65,12 +71,12 @@ const COL_RIGHT_X: f32 = MARGIN + COL_W + GUTTER;
 const COL_SEP_X: f32 = MARGIN + COL_W + GUTTER / 2.0;
 
 /// Points to millimetres (1pt = 1/72 inch).
-const PT_TO_MM: f32 = 25.4 / 72.0;
+pub(crate) const PT_TO_MM: f32 = 25.4 / 72.0;
 
-fn black() -> Color {
+pub(crate) fn black() -> Color {
     Color::Rgb(Rgb::new(0.1, 0.1, 0.1, None))
 }
-fn gray() -> Color {
+pub(crate) fn gray() -> Color {
     Color::Rgb(Rgb::new(0.55, 0.55, 0.55, None))
 }
 /// Section-header band gradient endpoints (R, G, B): dark on the left (where
@@ -86,7 +92,7 @@ fn band_text() -> Color {
 /// dimensions. Any alpha channel is composited over white so the embedded
 /// (alpha-less) RGB image still looks right. Returns `None` if the file is
 /// missing or can't be decoded.
-fn load_image_rgb(path: &str) -> Option<(Vec<u8>, u32, u32)> {
+pub(crate) fn load_image_rgb(path: &str) -> Option<(Vec<u8>, u32, u32)> {
     let bytes = std::fs::read(path).ok()?;
     // Auto-detects the format from the magic bytes (png / jpeg / webp).
     // Absolute path because `use printpdf::*` brings printpdf's own `image`
@@ -109,7 +115,7 @@ fn load_image_rgb(path: &str) -> Option<(Vec<u8>, u32, u32)> {
 /// Truncates `s` with a trailing ellipsis so it fits within `max_width` mm at
 /// the given font size; returns it unchanged when it already fits. Prevents a
 /// long name from running into the right-aligned price in a narrow column.
-fn ellipsize(s: &str, size_pt: f32, max_width: f32) -> String {
+pub(crate) fn ellipsize(s: &str, size_pt: f32, max_width: f32) -> String {
     if Pdf::text_width(s, size_pt) <= max_width {
         return s.to_string();
     }
@@ -124,19 +130,38 @@ fn ellipsize(s: &str, size_pt: f32, max_width: f32) -> String {
     "…".to_string()
 }
 
-struct Pdf {
+pub(crate) struct Pdf {
     doc: PdfDocumentReference,
     layer: PdfLayerReference,
     font: IndirectFontRef,
     font_bold: IndirectFontRef,
     /// Distance from the top of the current page to the drawing cursor, in mm.
-    y: f32,
+    pub(crate) y: f32,
 }
 
 impl Pdf {
+    /// Starts an A4 document titled `title` with the bundled fonts embedded
+    /// (subsetted to the glyphs actually used, so the PDF stays small) and
+    /// the cursor at the top margin of its first page.
+    pub(crate) fn new(title: &str) -> Result<Self, String> {
+        let (doc, page1, layer1) = PdfDocument::new(title, Mm(PAGE_W), Mm(PAGE_H), "Layer 1");
+        let font = doc
+            .add_external_font_with_subsetting(Cursor::new(FONT_REGULAR), true)
+            .map_err(|e| e.to_string())?;
+        let font_bold = doc
+            .add_external_font_with_subsetting(Cursor::new(FONT_BOLD), true)
+            .map_err(|e| e.to_string())?;
+        let layer = doc.get_page(page1).get_layer(layer1);
+        Ok(Self { doc, layer, font, font_bold, y: MARGIN })
+    }
+
+    pub(crate) fn save(self) -> Result<Vec<u8>, String> {
+        self.doc.save_to_bytes().map_err(|e| e.to_string())
+    }
+
     /// Width of a string in mm, summed from the real glyph advances of the
     /// bundled font so centring, right-alignment and leader dots line up.
-    fn text_width(s: &str, size_pt: f32) -> f32 {
+    pub(crate) fn text_width(s: &str, size_pt: f32) -> f32 {
         let face = metrics_face();
         let upm = face.units_per_em() as f32;
         let units: f32 = s
@@ -153,7 +178,7 @@ impl Pdf {
     /// Greedily wraps `s` into lines no wider than `max_width` mm (using the
     /// same width estimate as `text_width`). A word longer than `max_width` is
     /// left on its own line rather than split.
-    fn wrap_text(s: &str, size_pt: f32, max_width: f32) -> Vec<String> {
+    pub(crate) fn wrap_text(s: &str, size_pt: f32, max_width: f32) -> Vec<String> {
         let mut lines = Vec::new();
         let mut current = String::new();
         for word in s.split_whitespace() {
@@ -174,7 +199,7 @@ impl Pdf {
         lines
     }
 
-    fn new_page(&mut self) {
+    pub(crate) fn new_page(&mut self) {
         let (page, layer) = self.doc.add_page(Mm(PAGE_W), Mm(PAGE_H), "Layer 1");
         self.layer = self.doc.get_page(page).get_layer(layer);
         self.y = MARGIN;
@@ -182,14 +207,14 @@ impl Pdf {
 
     /// Starts a new page if `needed` mm of vertical space won't fit below the
     /// cursor on the current page.
-    fn ensure(&mut self, needed: f32) {
+    pub(crate) fn ensure(&mut self, needed: f32) {
         if self.y + needed > BOTTOM_LIMIT {
             self.new_page();
         }
     }
 
     /// Draws a line of text with its visual top at `y_top` mm from the page top.
-    fn text(&self, s: &str, size_pt: f32, x: f32, y_top: f32, bold: bool, color: Color) {
+    pub(crate) fn text(&self, s: &str, size_pt: f32, x: f32, y_top: f32, bold: bool, color: Color) {
         let font = if bold { &self.font_bold } else { &self.font };
         // Approximate cap height above the baseline (~0.7em).
         let ascent = size_pt * 0.7 * PT_TO_MM;
@@ -265,6 +290,20 @@ impl Pdf {
         }
     }
 
+    /// Draws a horizontal rule at `y_top` mm spanning the given `x` range.
+    pub(crate) fn hline(&self, x_start: f32, x_end: f32, y_top: f32) {
+        self.layer.set_outline_color(gray());
+        self.layer.set_outline_thickness(0.4);
+        let line = Line {
+            points: vec![
+                (Point::new(Mm(x_start), Mm(PAGE_H - y_top)), false),
+                (Point::new(Mm(x_end), Mm(PAGE_H - y_top)), false),
+            ],
+            is_closed: false,
+        };
+        self.layer.add_line(line);
+    }
+
     /// Draws a vertical rule at `x` mm spanning the given `y_top` range.
     fn vline(&self, x: f32, y_top_start: f32, y_top_end: f32) {
         self.layer.set_outline_color(gray());
@@ -282,7 +321,7 @@ impl Pdf {
     /// Draws an already-decoded RGB image fitted into the `box_w` x `box_h` box
     /// whose top-left corner is at (`x`, `y_top`) mm, preserving aspect ratio
     /// and centring it within the box.
-    fn image(&self, rgb: Vec<u8>, px_w: u32, px_h: u32, x: f32, y_top: f32, box_w: f32, box_h: f32) {
+    pub(crate) fn image(&self, rgb: Vec<u8>, px_w: u32, px_h: u32, x: f32, y_top: f32, box_w: f32, box_h: f32) {
         let aspect = px_w as f32 / px_h as f32;
         let (mut w, mut h) = (box_w, box_w / aspect);
         if h > box_h {
@@ -402,7 +441,7 @@ struct RawSection {
 }
 
 impl RawItem {
-    fn new(item: &MenuItem, main: bool, price_str: &impl Fn(f64) -> String) -> Self {
+    fn new(item: &MenuItem, main: bool, price_str: &impl Fn(Money) -> String) -> Self {
         let thumb = if main {
             item.image_path.as_deref().and_then(load_image_rgb)
         } else {
@@ -417,7 +456,7 @@ impl RawItem {
         } else {
             None
         };
-        Self { name: item.name.clone(), price: price_str(item.price), desc, thumb, main }
+        Self { name: item.name.clone(), price: format!("{}{}", price_str(item.price), item.price_suffix), desc, thumb, main }
     }
 }
 
@@ -561,26 +600,9 @@ pub fn build_menu_pdf(
     logo_path: &str,
     sections: &[MenuSection],
 ) -> Result<Vec<u8>, String> {
-    let (doc, page1, layer1) = PdfDocument::new(title, Mm(PAGE_W), Mm(PAGE_H), "Layer 1");
-    // Embed the bundled Noto Sans, subsetted to the glyphs actually used so the
-    // PDF stays small.
-    let font = doc
-        .add_external_font_with_subsetting(Cursor::new(FONT_REGULAR), true)
-        .map_err(|e| e.to_string())?;
-    let font_bold = doc
-        .add_external_font_with_subsetting(Cursor::new(FONT_BOLD), true)
-        .map_err(|e| e.to_string())?;
-    let layer = doc.get_page(page1).get_layer(layer1);
-
-    let mut pdf = Pdf {
-        doc,
-        layer,
-        font,
-        font_bold,
-        y: MARGIN,
-    };
-
-    let price_str = |price: f64| format!("{} {:.2}", currency, price);
+    let mut pdf = Pdf::new(title)?;
+
+    let price_str = |price: Money| format!("{} {}", currency, price.format(currency));
 
     // Decode the logo once and note the height it will occupy.
     let logo = load_image_rgb(logo_path);
@@ -637,7 +659,7 @@ pub fn build_menu_pdf(
         render_section(&mut pdf, section, &st);
     }
 
-    pdf.doc.save_to_bytes().map_err(|e| e.to_string())
+    pdf.save()
 }
 
 #[cfg(test)]
@@ -653,20 +675,21 @@ mod tests {
                 items: vec![
                     MenuItem {
                         name: "Cheeseburger".into(),
-                        price: 9.5,
+                        price: Money(950),
+                        price_suffix: String::new(),
                         description: Some(
                             "Beef patty, cheddar, lettuce, tomato and our house sauce on a brioche bun"
                                 .into(),
                         ),
                         image_path: None,
                     },
-                    MenuItem { name: "Veggie Burger".into(), price: 8.0, description: None, image_path: None },
+                    MenuItem { name: "Veggie Burger".into(), price: Money(800), price_suffix: String::new(), description: None, image_path: None },
                 ],
             },
             MenuSection {
                 name: "Drinks".into(),
                 main_course: false,
-                items: vec![MenuItem { name: "Cola".into(), price: 2.5, description: None, image_path: None }],
+                items: vec![MenuItem { name: "Cola".into(), price: Money(250), price_suffix: String::new(), description: None, image_path: None }],
             },
         ];
         // Use a logo path that does not exist to exercise the missing-image path.
@@ -679,7 +702,7 @@ mod tests {
     fn paginates_long_menus() {
         // Enough items to overflow a single page and force `new_page()`.
         let items: Vec<MenuItem> = (0..120)
-            .map(|i| MenuItem { name: format!("Item {i}"), price: i as f64, description: None, image_path: None })
+            .map(|i| MenuItem { name: format!("Item {i}"), price: Money(i * 100), price_suffix: String::new(), description: None, image_path: None })
             .collect();
         let sections = vec![MenuSection { name: "Many".into(), main_course: false, items }];
         let bytes = build_menu_pdf("Menu", "$", "does/not/exist.png", &sections).unwrap();
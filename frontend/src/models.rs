@@ -1,7 +1,270 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::iter::Sum;
+use std::ops::{Add, AddAssign, Mul, Neg, Sub, SubAssign};
 use uuid::Uuid;
 
+/// An amount of money in minor units of the configured currency, e.g. cents.
+/// Amounts are stored, summed and sent around as whole minor units so totals
+/// always add up exactly; the currency's decimal places (see
+/// [`currency_decimals`]) only come in when an amount is shown, typed in, or
+/// worked out from a setting given in major units.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(transparent))]
+pub struct Money(pub i64);
+
+/// How many decimal places amounts in `currency` (a symbol or code as set on
+/// the admin page) have. Currencies without minor units in use have none.
+pub fn currency_decimals(currency: &str) -> u32 {
+    match currency {
+        "CL$" | "CLP" | "CFA" | "XOF" | "XAF" | "\u{a5}" | "JPY" | "\u{20a9}" | "KRW" | "ISK" | "\u{20ab}" | "VND" => 0,
+        _ => 2,
+    }
+}
+
+impl Money {
+    pub const ZERO: Money = Money(0);
+
+    /// Minor units per major unit of `currency`, e.g. 100.
+    pub fn unit(currency: &str) -> i64 {
+        10_i64.pow(currency_decimals(currency))
+    }
+
+    /// The nearest amount to `value` major units, e.g. 0.05 → 5 cents.
+    pub fn from_major(value: f64, currency: &str) -> Money {
+        Money((value * Self::unit(currency) as f64).round() as i64)
+    }
+
+    pub fn to_major(self, currency: &str) -> f64 {
+        self.0 as f64 / Self::unit(currency) as f64
+    }
+
+    /// Reads a typed amount such as "12.5" or "12,50" exactly. Amounts with
+    /// more decimal places than the currency has are rejected.
+    pub fn parse(s: &str, currency: &str) -> Option<Money> {
+        let s = s.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let (whole, frac) = s.split_once(['.', ',']).unwrap_or((s, ""));
+        let decimals = currency_decimals(currency) as usize;
+        if (whole.is_empty() && frac.is_empty())
+            || frac.len() > decimals
+            || !whole.chars().chain(frac.chars()).all(|c| c.is_ascii_digit())
+        {
+            return None;
+        }
+        let whole: i64 = if whole.is_empty() { 0 } else { whole.parse().ok()? };
+        let frac: i64 = if decimals == 0 { 0 } else { format!("{:0<width$}", frac, width = decimals).parse().ok()? };
+        let minor = whole.checked_mul(Self::unit(currency))?.checked_add(frac)?;
+        Some(Money(if negative { -minor } else { minor }))
+    }
+
+    /// The amount with the currency's decimal places, e.g. "12.50", without
+    /// the currency symbol.
+    pub fn format(self, currency: &str) -> String {
+        let decimals = currency_decimals(currency) as usize;
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        if decimals == 0 {
+            return format!("{}{}", sign, abs);
+        }
+        let unit = Self::unit(currency) as u64;
+        format!("{}{}.{:0width$}", sign, abs / unit, abs % unit, width = decimals)
+    }
+
+    /// `self * num / den`, rounded half away from zero to a whole minor unit.
+    pub fn mul_div(self, num: i64, den: i64) -> Money {
+        let n = self.0 as i128 * num as i128;
+        let d = den as i128;
+        let q = (2 * n.abs() + d.abs()) / (2 * d.abs());
+        Money((if (n < 0) != (d < 0) { -q } else { q }) as i64)
+    }
+
+    /// `percent` percent of the amount, rounded to a whole minor unit.
+    /// Percentages are taken to two decimal places, e.g. 7.75%.
+    pub fn percent(self, percent: f64) -> Money {
+        self.mul_div((percent * 100.0).round() as i64, 10_000)
+    }
+
+    pub fn abs(self) -> Money {
+        Money(self.0.abs())
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+}
+
+impl Add for Money {
+    type Output = Money;
+    fn add(self, rhs: Money) -> Money {
+        Money(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Money {
+    type Output = Money;
+    fn sub(self, rhs: Money) -> Money {
+        Money(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Money {
+    type Output = Money;
+    fn neg(self) -> Money {
+        Money(-self.0)
+    }
+}
+
+impl AddAssign for Money {
+    fn add_assign(&mut self, rhs: Money) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Money {
+    fn sub_assign(&mut self, rhs: Money) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Mul<i64> for Money {
+    type Output = Money;
+    fn mul(self, rhs: i64) -> Money {
+        Money(self.0 * rhs)
+    }
+}
+
+/// A unit price times a quantity, rounded half away from zero to a whole
+/// minor unit.
+impl Mul<Quantity> for Money {
+    type Output = Money;
+    fn mul(self, rhs: Quantity) -> Money {
+        self.mul_div(rhs.0, Quantity::ONE.0)
+    }
+}
+
+impl Sum for Money {
+    fn sum<I: Iterator<Item = Money>>(iter: I) -> Money {
+        Money(iter.map(|m| m.0).sum())
+    }
+}
+
+impl<'a> Sum<&'a Money> for Money {
+    fn sum<I: Iterator<Item = &'a Money>>(iter: I) -> Money {
+        iter.copied().sum()
+    }
+}
+
+/// How much of an item a sale line holds, in thousandths. Items sold by the
+/// piece come in whole units; goods sold by weight are measured in kg, to
+/// the gram. Like [`Money`], quantities are stored and summed as integers so
+/// they add up exactly.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+#[cfg_attr(feature = "ssr", derive(sqlx::Type), sqlx(transparent))]
+pub struct Quantity(pub i64);
+
+impl Quantity {
+    pub const ZERO: Quantity = Quantity(0);
+    pub const ONE: Quantity = Quantity(1000);
+
+    /// `count` whole units.
+    pub fn units(count: i64) -> Quantity {
+        Quantity(count * Self::ONE.0)
+    }
+
+    /// The nearest quantity to `value`, e.g. a weight in kg read from a scale.
+    pub fn from_decimal(value: f64) -> Quantity {
+        Quantity((value * Self::ONE.0 as f64).round() as i64)
+    }
+
+    /// The whole units in the quantity, rounded toward zero.
+    pub fn whole_units(self) -> i64 {
+        self.0 / Self::ONE.0
+    }
+
+    pub fn is_whole(self) -> bool {
+        self.0 % Self::ONE.0 == 0
+    }
+
+    pub fn is_zero(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn is_negative(self) -> bool {
+        self.0 < 0
+    }
+
+    pub fn is_positive(self) -> bool {
+        self.0 > 0
+    }
+}
+
+/// Shown as "2" for whole units, "0.535" otherwise.
+impl fmt::Display for Quantity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_whole() {
+            return write!(f, "{}", self.whole_units());
+        }
+        let sign = if self.0 < 0 { "-" } else { "" };
+        let abs = self.0.unsigned_abs();
+        let unit = Self::ONE.0 as u64;
+        write!(f, "{}{}.{:03}", sign, abs / unit, abs % unit)
+    }
+}
+
+impl Add for Quantity {
+    type Output = Quantity;
+    fn add(self, rhs: Quantity) -> Quantity {
+        Quantity(self.0 + rhs.0)
+    }
+}
+
+impl Sub for Quantity {
+    type Output = Quantity;
+    fn sub(self, rhs: Quantity) -> Quantity {
+        Quantity(self.0 - rhs.0)
+    }
+}
+
+impl Neg for Quantity {
+    type Output = Quantity;
+    fn neg(self) -> Quantity {
+        Quantity(-self.0)
+    }
+}
+
+impl AddAssign for Quantity {
+    fn add_assign(&mut self, rhs: Quantity) {
+        self.0 += rhs.0;
+    }
+}
+
+impl SubAssign for Quantity {
+    fn sub_assign(&mut self, rhs: Quantity) {
+        self.0 -= rhs.0;
+    }
+}
+
+impl Sum for Quantity {
+    fn sum<I: Iterator<Item = Quantity>>(iter: I) -> Quantity {
+        Quantity(iter.map(|q| q.0).sum())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
 pub struct Category {
@@ -16,86 +279,1343 @@ pub struct Category {
     pub sort_order: i64,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Tax rate applied to the category's items unless an item overrides it.
+    pub tax_rate_id: Option<Uuid>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Item {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub price: Money,
+    pub category_id: Uuid,
+    pub sku: Option<String>,
+    pub in_stock: bool,
+    pub image_path: Option<String>,
+    pub stock_quantity: Option<i32>,
+    pub kitchen_item: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Overrides the category's tax rate. `None` inherits it.
+    pub tax_rate_id: Option<Uuid>,
+    /// What `price` is for: one of [`PRICE_UNITS`]. Items priced per `kg` or
+    /// `100g` are sold by weight.
+    pub price_unit: String,
+    /// Marks a deposit (Pfand) item, e.g. a bottle deposit. Its lines are
+    /// money held for the customer, not revenue.
+    pub is_deposit: bool,
+    /// The deposit item rung up alongside each unit of this item.
+    pub deposit_item_id: Option<Uuid>,
+}
+
+impl Item {
+    pub fn is_weighed(&self) -> bool {
+        self.price_unit != "each"
+    }
+
+    /// Shown after the price: "/kg" or "/100 g" for goods sold by weight.
+    pub fn price_suffix(&self) -> String {
+        if self.is_weighed() { format!("/{}", price_unit_label(&self.price_unit)) } else { String::new() }
+    }
+}
+
+/// The price units an item can have: per piece, per kilogram or per 100 g.
+pub const PRICE_UNITS: [&str; 3] = ["each", "kg", "100g"];
+
+/// How a weighed price unit is printed after a price, e.g. "2.99/kg".
+pub fn price_unit_label(price_unit: &str) -> &str {
+    match price_unit {
+        "100g" => "100 g",
+        other => other,
+    }
+}
+
+/// The price of a kilogram of an item priced `price` per `price_unit`. A
+/// weighed sale line has this as its unit price and the weight in kg as its
+/// quantity.
+pub fn kg_price(price: Money, price_unit: &str) -> Money {
+    match price_unit {
+        "100g" => price * 10,
+        _ => price,
+    }
+}
+
+/// An EAN-13 from the in-store range (prefixes 20–29) that carries a weight
+/// or price, laid out as `PP IIIII VVVVV C`: prefix, item reference, value
+/// and check digit. The item is found by `item_code` (prefix and reference)
+/// as its SKU; `value` is the weight in grams for items sold by weight and
+/// the price in minor units of the currency (e.g. cents) for others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InStoreCode {
+    pub item_code: String,
+    pub value: u32,
+}
+
+impl InStoreCode {
+    pub fn decode(code: &str) -> Option<Self> {
+        let digits: Vec<u32> = code.chars().map(|c| c.to_digit(10)).collect::<Option<_>>()?;
+        if digits.len() != 13 || digits[0] != 2 {
+            return None;
+        }
+        let sum: u32 = digits[..12]
+            .iter()
+            .enumerate()
+            .map(|(i, d)| if i % 2 == 1 { d * 3 } else { *d })
+            .sum();
+        if (10 - sum % 10) % 10 != digits[12] {
+            return None;
+        }
+        Some(Self {
+            item_code: code[..7].to_string(),
+            value: code[7..12].parse().ok()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Transaction {
+    pub id: Uuid,
+    pub customer_name: Option<String>,
+    pub status: String,
+    pub total: Money,
+    pub paid_amount: Option<Money>,
+    pub change_amount: Option<Money>,
+    /// The customer group this sale is tabulated under. `None` means the sale
+    /// belongs to "regular customers". See [`CustomerGroup`].
+    pub customer_group_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub closed_at: Option<DateTime<Utc>>,
+    /// Set on refunds: the closed sale this transaction reverses. Refunds are
+    /// closed transactions with negative quantities and totals, so they net
+    /// out of revenue in the reports.
+    pub refund_of: Option<Uuid>,
+    /// Whether line prices include tax (VAT) or tax is added on top of them.
+    /// Taken from the `tax_mode` setting when the sale is started.
+    pub tax_inclusive: bool,
+    /// The table the sale is served at, if any. See [`FloorTable`].
+    pub table_id: Option<Uuid>,
+    /// The registered customer the sale is for, if any. See [`Customer`].
+    pub customer_id: Option<Uuid>,
+    /// The tip given on top of the total at checkout. It is part of
+    /// `paid_amount` but not of `total`, so it stays out of revenue.
+    pub tip_amount: Money,
+    /// The user who closed the sale, whom its tip is counted for.
+    pub closed_by: Option<String>,
+    /// What cash rounding added to (or, if negative, took off) the amount
+    /// paid, see [`CashRounding`]. Like the tip it is outside `total`.
+    pub rounding_amount: Money,
+    /// The fiscal signature of the finished sale: the signer's serial number,
+    /// its signature counter and the signature (base64). `None` when no signer
+    /// is set up or signing failed.
+    pub fiscal_signer: Option<String>,
+    pub fiscal_counter: Option<i64>,
+    pub fiscal_signature: Option<String>,
+    /// When the sale was started and when it was signed.
+    pub fiscal_started_at: Option<DateTime<Utc>>,
+    pub fiscal_signed_at: Option<DateTime<Utc>>,
+    /// Why signing the finished sale failed, if it did.
+    pub fiscal_error: Option<String>,
+}
+
+/// What a barcode scan found. `added` is false when the item has modifier
+/// groups or is a bundle with choices, so its options must be picked before
+/// it is added.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScanResult {
+    pub item: Item,
+    pub added: bool,
+}
+
+/// Picks barcodes out of keyboard input. Keyboard-wedge scanners "type" a
+/// code much faster than a person and finish it with Enter; keys with longer
+/// gaps between them start over.
+#[derive(Debug, Clone, Default)]
+pub struct ScanBuffer {
+    code: String,
+    last_key_ms: i64,
+}
+
+impl ScanBuffer {
+    pub const MAX_KEY_GAP_MS: i64 = 50;
+    pub const MIN_CODE_LEN: usize = 4;
+
+    /// Feeds one `KeyboardEvent.key` pressed at `at_ms`. Returns the code
+    /// when Enter completes a burst of at least [`Self::MIN_CODE_LEN`] keys.
+    pub fn push(&mut self, key: &str, at_ms: i64) -> Option<String> {
+        if at_ms - self.last_key_ms > Self::MAX_KEY_GAP_MS {
+            self.code.clear();
+        }
+        self.last_key_ms = at_ms;
+        match key {
+            "Enter" => {
+                let code = std::mem::take(&mut self.code);
+                (code.chars().count() >= Self::MIN_CODE_LEN).then_some(code)
+            }
+            // Printable keys; names like "Shift" are ignored.
+            k if k.chars().count() == 1 => {
+                self.code.push_str(k);
+                None
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A part of the floor plan, e.g. "Terrace" or "Bar".
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct FloorArea {
+    pub id: Uuid,
+    pub name: String,
+    pub sort_order: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A table in a [`FloorArea`] that open sales can be assigned to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct FloorTable {
+    pub id: Uuid,
+    pub area_id: Uuid,
+    pub name: String,
+    pub seats: i32,
+    pub sort_order: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An area with its tables, in display order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FloorAreaDetail {
+    pub area: FloorArea,
+    pub tables: Vec<FloorTable>,
+}
+
+/// Open sales at an occupied table. Tables without a status are free.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct TableStatus {
+    pub table_id: Uuid,
+    pub open_count: i64,
+    pub open_total: Money,
+    /// When the first item was ordered, or the first sale was started if
+    /// nothing has been ordered yet.
+    pub first_order_at: DateTime<Utc>,
+}
+
+/// A named group whose sales are tabulated separately in the statistics
+/// (e.g. "Staff", "VIP"). Managed by admins; sales can be closed against any
+/// group by cashiers. Deleting a group reassigns its sales to regular customers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct CustomerGroup {
+    pub id: Uuid,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A registered customer. Sales linked to a customer earn loyalty points,
+/// which can be redeemed as a discount on later sales. `points` is the
+/// current balance, summed from the customer's loyalty entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Customer {
+    pub id: Uuid,
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+    /// The code on the customer's loyalty card, scanned to link a sale.
+    pub card_code: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    #[cfg_attr(feature = "ssr", sqlx(default))]
+    #[serde(default)]
+    pub points: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerInput {
+    pub name: String,
+    pub phone: Option<String>,
+    pub email: Option<String>,
+    pub notes: Option<String>,
+    pub card_code: Option<String>,
+}
+
+/// A customer with their visit history: closed sales, newest first, and what
+/// they spent over all of them, net of refunds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CustomerDetail {
+    pub customer: Customer,
+    pub visit_count: i64,
+    pub lifetime_spend: Money,
+    pub visits: Vec<Transaction>,
+}
+
+/// How loyalty points are earned and what they are worth: `points_per_unit`
+/// points per currency unit spent, and `point_value` off a sale for each
+/// point redeemed.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LoyaltySettings {
+    pub points_per_unit: f64,
+    pub point_value: f64,
+}
+
+impl Default for LoyaltySettings {
+    fn default() -> Self {
+        Self { points_per_unit: 1.0, point_value: 0.01 }
+    }
+}
+
+impl LoyaltySettings {
+    /// Points earned on a sale of `amount`, in whole points. Refunds (a
+    /// negative amount) take back what the same amount earned.
+    pub fn points_for(&self, amount: Money, currency: &str) -> i64 {
+        // Allow for f64 error just below a whole point, e.g. 19.99 * 100.
+        let points = (amount.abs().to_major(currency) * self.points_per_unit + 1e-6).floor() as i64;
+        if amount.is_negative() { -points } else { points }
+    }
+
+    /// What redeeming `points` takes off a sale.
+    pub fn redemption_value(&self, points: i64, currency: &str) -> Money {
+        Money::from_major(points as f64 * self.point_value, currency)
+    }
+}
+
+/// Rounding of cash amounts to the smallest coin in use, e.g. to 0.05 in
+/// Switzerland or to whole units where there is no small change. Only the
+/// part of a sale settled in cash is rounded. `increment` 0 means no
+/// rounding; `mode` is one of [`ROUNDING_MODES`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CashRounding {
+    pub increment: Money,
+    pub mode: String,
+}
+
+/// "nearest" rounds half-way amounts up; "up" and "down" always round away
+/// from or towards zero.
+pub const ROUNDING_MODES: [&str; 3] = ["nearest", "up", "down"];
+
+impl Default for CashRounding {
+    fn default() -> Self {
+        Self { increment: Money::ZERO, mode: "nearest".to_string() }
+    }
+}
+
+impl CashRounding {
+    /// `amount` rounded to the increment. Payouts (negative amounts) are
+    /// rounded like the same amount taken in.
+    pub fn round(&self, amount: Money) -> Money {
+        let increment = self.increment.0;
+        if increment <= 0 {
+            return amount;
+        }
+        let (steps, rest) = (amount.0.abs() / increment, amount.0.abs() % increment);
+        let round_up = match self.mode.as_str() {
+            "up" => rest > 0,
+            "down" => false,
+            _ => 2 * rest >= increment,
+        };
+        let steps = if round_up { steps + 1 } else { steps };
+        Money(steps * increment * amount.0.signum())
+    }
+}
+
+/// Selects which sales the statistics aggregate over.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum GroupFilter {
+    /// Every closed sale, regardless of customer group.
+    All,
+    /// Only sales not assigned to any customer group ("regular customers").
+    Regular,
+    /// Only sales assigned to the given customer group.
+    Group(Uuid),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct TransactionItemDetail {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub item_name: String,
+    pub quantity: Quantity,
+    pub unit_price: Money,
+    pub total_price: Money,
+    /// Tax rate in percent at the time of sale; `None` if untaxed.
+    pub tax_rate: Option<f64>,
+    /// Free-text note for the kitchen, e.g. "no onions".
+    pub note: Option<String>,
+    /// "kg" for goods sold by weight: `quantity` is the weight and
+    /// `unit_price` the price of a kg. `None` for items sold by the piece.
+    pub unit: Option<String>,
+    /// Set on the line of a bundle (combo) item. It has no price of its own:
+    /// the bundle price is shared out over its component lines.
+    pub is_bundle: bool,
+    /// For a component of a bundle, the bundle's line.
+    pub bundle_id: Option<Uuid>,
+    /// Set on lines of deposit items: the deposit charged with an item, or a
+    /// deposit return, which has a negative quantity.
+    pub is_deposit: bool,
+    /// For a deposit added automatically, the line it is charged with. It
+    /// follows that line's quantity and goes away with it.
+    pub deposit_for: Option<Uuid>,
+    /// Modifiers chosen for this line. Their price deltas are included in
+    /// `unit_price`. Loaded separately from the line row.
+    #[cfg_attr(feature = "ssr", sqlx(skip))]
+    pub modifiers: Vec<LineModifier>,
+    /// Component labels of a bundle line, e.g. "2× Fries", filled in by
+    /// [`group_bundles`].
+    #[cfg_attr(feature = "ssr", sqlx(skip))]
+    pub components: Vec<String>,
+    /// Discounts on this line, e.g. from promotions. Kept apart from
+    /// `total_price`, which stays the undiscounted amount. Loaded separately
+    /// from the line row.
+    #[cfg_attr(feature = "ssr", sqlx(skip))]
+    pub discounts: Vec<TransactionDiscount>,
+}
+
+impl TransactionItemDetail {
+    /// The line's modifier names joined for display, e.g. "Oat milk, Large".
+    pub fn modifier_names(&self) -> Option<String> {
+        if self.modifiers.is_empty() {
+            return None;
+        }
+        Some(self.modifiers.iter().map(|m| m.name.as_str()).collect::<Vec<_>>().join(", "))
+    }
+
+    /// The units the line counts as when a tab is split evenly. A weighed
+    /// line goes to one tab whole, so it counts as one.
+    pub fn split_units(&self) -> i32 {
+        match self.unit {
+            Some(_) => 1,
+            None => self.quantity.whole_units() as i32,
+        }
+    }
+
+    /// The weight and price per unit of a weighed line, e.g. "0.535 kg × 2.99/kg".
+    pub fn weight_label(&self, currency: &str) -> Option<String> {
+        let unit = self.unit.as_deref()?;
+        Some(format!("{} {} × {}/{}", self.quantity, unit, self.unit_price.format(currency), unit))
+    }
+
+    /// The sum of the line's discounts (negative, or zero without any).
+    pub fn discount(&self) -> Money {
+        self.discounts.iter().map(|d| d.amount).sum()
+    }
+
+    /// What the line comes to after its discounts.
+    pub fn net_total(&self) -> Money {
+        self.total_price + self.discount()
+    }
+
+    /// The discounts given back when `quantity` more is returned after
+    /// `returned` earlier: each discount's share for what is returned, as
+    /// positive amounts. The shares of all of the line add up to the discount.
+    pub fn refund_discounts(&self, returned: Quantity, quantity: Quantity) -> Vec<TransactionDiscount> {
+        let sold = self.quantity.0.max(1);
+        self.discounts
+            .iter()
+            .map(|d| TransactionDiscount {
+                amount: (-d.amount).mul_div((returned + quantity).0, sold) - (-d.amount).mul_div(returned.0, sold),
+                ..d.clone()
+            })
+            .filter(|d| !d.amount.is_zero())
+            .collect()
+    }
+}
+
+/// A discount on one sale line. `promotion_id` is set for discounts worked
+/// out by a promotion, `manual_discount_id` for the line's share of a
+/// discount given by hand; both are recomputed whenever the sale's lines
+/// change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct TransactionDiscount {
+    pub transaction_item_id: Uuid,
+    pub promotion_id: Option<Uuid>,
+    pub manual_discount_id: Option<Uuid>,
+    /// Reason code and cashier of a manual discount, kept on refunds too so
+    /// they net out in the reports.
+    pub reason: Option<String>,
+    pub user_name: Option<String>,
+    pub name: String,
+    /// Negative on a sale, positive on a refund giving the discount back.
+    pub amount: Money,
+}
+
+/// Folds the component lines of bundles into their bundle line for display:
+/// the bundle line gets the components' total as its price and their names
+/// as `components`, and the component lines are left out.
+pub fn group_bundles(lines: &[TransactionItemDetail]) -> Vec<TransactionItemDetail> {
+    lines
+        .iter()
+        .filter(|l| l.bundle_id.is_none())
+        .map(|l| {
+            let mut line = l.clone();
+            if line.is_bundle {
+                let parts: Vec<&TransactionItemDetail> =
+                    lines.iter().filter(|c| c.bundle_id == Some(l.id)).collect();
+                line.total_price = parts.iter().map(|c| c.total_price).sum();
+                line.unit_price = line.total_price.mul_div(1, line.quantity.whole_units().max(1));
+                line.discounts = parts.iter().flat_map(|c| c.discounts.iter().cloned()).collect();
+                line.components = parts
+                    .iter()
+                    .map(|c| match c.quantity {
+                        Quantity::ONE => c.item_name.clone(),
+                        n => format!("{}× {}", n, c.item_name),
+                    })
+                    .collect();
+            }
+            line
+        })
+        .collect()
+}
+
+/// One part of a bundle (combo) item: a fixed item, or one item the customer
+/// picks from `category_id`. Exactly one of `item_id` and `category_id` is set.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct BundleComponent {
+    pub id: Uuid,
+    pub bundle_item_id: Uuid,
+    pub item_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub quantity: i32,
+    pub sort_order: i64,
+}
+
+/// A bundle component as entered on the Items page.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BundleComponentInput {
+    pub item_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub quantity: i32,
+}
+
+/// Shares a bundle price out over its component units in proportion to their
+/// list prices (evenly if they are all free), to the minor unit: the units
+/// left over by rounding down go to the shares with the largest remainders.
+/// The shares always add up to `total`.
+pub fn allocate_bundle_price(total: Money, unit_prices: &[Money]) -> Vec<Money> {
+    if unit_prices.is_empty() {
+        return Vec::new();
+    }
+    let weights: Vec<i128> = if unit_prices.iter().sum::<Money>().is_positive() {
+        unit_prices.iter().map(|p| p.0 as i128).collect()
+    } else {
+        vec![1; unit_prices.len()]
+    };
+    let weight_sum: i128 = weights.iter().sum();
+    let exact: Vec<i128> = weights.iter().map(|w| total.0 as i128 * w).collect();
+    let mut shares: Vec<i64> = exact.iter().map(|e| e.div_euclid(weight_sum) as i64).collect();
+    let mut order: Vec<usize> = (0..exact.len()).collect();
+    order.sort_by_key(|&i| std::cmp::Reverse(exact[i].rem_euclid(weight_sum)));
+    let left = total.0 - shares.iter().sum::<i64>();
+    for &i in order.iter().cycle().take(left.max(0) as usize) {
+        shares[i] += 1;
+    }
+    shares.into_iter().map(Money).collect()
+}
+
+/// A set of options offered when an item is sold, e.g. "Milk" (oat, soy) or
+/// "Size" (S, M, L). Attached to single items or to whole categories.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct ModifierGroup {
+    pub id: Uuid,
+    pub name: String,
+    /// Fewest options that must be picked; the group is required when > 0.
+    pub min_picks: i32,
+    /// Most options that may be picked; `None` for no limit.
+    pub max_picks: Option<i32>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One option of a [`ModifierGroup`]. `price_delta` is added to the item price.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Modifier {
+    pub id: Uuid,
+    pub group_id: Uuid,
+    pub name: String,
+    pub price_delta: Money,
+    pub sort_order: i64,
+}
+
+/// A modifier group with its options and the items and categories it is
+/// attached to.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModifierGroupDetail {
+    pub group: ModifierGroup,
+    pub modifiers: Vec<Modifier>,
+    pub item_ids: Vec<Uuid>,
+    pub category_ids: Vec<Uuid>,
+}
+
+impl ModifierGroupDetail {
+    /// Whether the group is offered for `item`, directly or via its category.
+    pub fn applies_to(&self, item: &Item) -> bool {
+        self.item_ids.contains(&item.id) || self.category_ids.contains(&item.category_id)
+    }
+}
+
+/// Checks the modifiers picked for an item against the groups offered for it:
+/// every pick must be an option of one of the groups, none may be picked
+/// twice, and each group's min/max picks must be met.
+pub fn check_modifier_picks(groups: &[&ModifierGroupDetail], picks: &[Uuid]) -> Result<(), String> {
+    for (i, pick) in picks.iter().enumerate() {
+        if picks[..i].contains(pick) {
+            return Err("A modifier was picked twice".to_string());
+        }
+        if !groups.iter().any(|g| g.modifiers.iter().any(|m| m.id == *pick)) {
+            return Err("Modifier not available for this item".to_string());
+        }
+    }
+    for g in groups {
+        let count = g.modifiers.iter().filter(|m| picks.contains(&m.id)).count() as i32;
+        if count < g.group.min_picks {
+            return Err(format!("Pick at least {} from {}", g.group.min_picks, g.group.name));
+        }
+        if let Some(max) = g.group.max_picks
+            && count > max
+        {
+            return Err(format!("Pick at most {} from {}", max, g.group.name));
+        }
+    }
+    Ok(())
+}
+
+/// A modifier group as entered in the admin form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModifierGroupInput {
+    pub name: String,
+    pub min_picks: i32,
+    pub max_picks: Option<i32>,
+    pub modifiers: Vec<ModifierInput>,
+    pub item_ids: Vec<Uuid>,
+    pub category_ids: Vec<Uuid>,
+}
+
+/// An option in the modifier group form; `id` is `None` for new options.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ModifierInput {
+    pub id: Option<Uuid>,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+/// A modifier chosen for a sale line, with the names and price as they were
+/// when it was sold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct LineModifier {
+    pub transaction_item_id: Uuid,
+    pub modifier_id: Uuid,
+    pub group_name: String,
+    pub name: String,
+    pub price_delta: Money,
+}
+
+/// A named tax rate (e.g. "Standard 20%"), assigned to categories and
+/// optionally overridden per item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct TaxRate {
+    pub id: Uuid,
+    pub name: String,
+    /// Rate in percent, e.g. `20.0`.
+    pub rate: f64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Net, tax and gross amounts for one tax rate of a sale or report.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxLine {
+    pub rate: f64,
+    pub net: Money,
+    pub tax: Money,
+    pub gross: Money,
+}
+
+/// Groups line totals `(tax rate, line total)` by rate and works out net, tax
+/// and gross for each, ordered by rate. Untaxed lines are listed at 0%, and
+/// nothing is returned if no line has a rate, so installations without tax
+/// rates show no tax section at all.
+///
+/// With `inclusive` the line totals are gross and the tax is extracted from
+/// them; otherwise they are net and the tax is added on top. Tax is rounded
+/// once per rate rather than per line, as on the printed receipt. Lines
+/// without an amount, such as bundle lines, are left out.
+pub fn tax_breakdown(lines: &[(Option<f64>, Money)], inclusive: bool) -> Vec<TaxLine> {
+    let lines: Vec<&(Option<f64>, Money)> = lines.iter().filter(|(_, amount)| !amount.is_zero()).collect();
+    if lines.iter().all(|(rate, _)| rate.is_none()) {
+        return Vec::new();
+    }
+    let mut groups: Vec<(f64, Money)> = Vec::new();
+    for (rate, amount) in lines {
+        let rate = rate.unwrap_or(0.0);
+        match groups.iter_mut().find(|(r, _)| *r == rate) {
+            Some((_, sum)) => *sum += *amount,
+            None => groups.push((rate, *amount)),
+        }
+    }
+    groups.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+    groups
+        .into_iter()
+        .map(|(rate, sum)| {
+            // The rate in hundredths of a percent, so tax is exact integer math.
+            let rate_bp = (rate * 100.0).round() as i64;
+            if inclusive {
+                let tax = sum.mul_div(rate_bp, 10_000 + rate_bp);
+                TaxLine { rate, net: sum - tax, tax, gross: sum }
+            } else {
+                let tax = sum.mul_div(rate_bp, 10_000);
+                TaxLine { rate, net: sum, tax, gross: sum + tax }
+            }
+        })
+        .collect()
+}
+
+/// Tax to add on top of the line totals: the sum of all rates' tax when tax
+/// is exclusive, nothing when prices already include it.
+pub fn added_tax(taxes: &[TaxLine], inclusive: bool) -> Money {
+    if inclusive {
+        Money::ZERO
+    } else {
+        taxes.iter().map(|t| t.tax).sum()
+    }
+}
+
+/// Tender types a sale can be settled with. Change is only ever given from cash.
+/// A "gift_card" payment is taken off the balance of the card it names; an
+/// "account" payment is charged to the sale's house account, see [`AccountRef`].
+pub const TENDERS: [&str; 6] = ["cash", "card", "voucher", "gift_card", "account", "other"];
+
+/// One tender line of a closed sale. A sale may be split across several
+/// payments (e.g. part card, part cash); the amounts are as tendered, so the
+/// cash line includes any change handed back.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Payment {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub tender: String,
+    pub amount: Money,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Tip percentages offered at checkout, of the sale total.
+pub const TIP_PRESETS: [u32; 4] = [5, 10, 15, 20];
+
+/// A tip of `percent` percent on `total`, in whole minor units. Payouts get
+/// none.
+pub fn tip_for_percent(total: Money, percent: u32) -> Money {
+    total.max(Money::ZERO).mul_div(percent as i64, 100)
+}
+
+/// A payment entered at checkout, before the sale is closed.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentInput {
+    pub tender: String,
+    pub amount: Money,
+    /// The code of the card a "gift_card" payment is redeemed from.
+    #[serde(default)]
+    pub card_code: Option<String>,
+}
+
+/// A prepaid gift card. Its balance is the sum of its ledger entries, see
+/// [`GiftCardEntry`]. The code is what is printed on the card or receipt and
+/// scanned to redeem it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct GiftCard {
+    pub id: Uuid,
+    pub code: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Kinds of gift card ledger entries: money put on the card when a sale with
+/// a top-up closes, taken off when it pays for a sale, and paid out when the
+/// remaining balance is refunded.
+pub const GIFT_CARD_ENTRY_KINDS: [&str; 3] = ["top_up", "redeem", "refund"];
+
+/// One movement on a gift card. `amount` is signed: positive for top-ups,
+/// negative for redemptions and refunds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct GiftCardEntry {
+    pub id: Uuid,
+    pub gift_card_id: Uuid,
+    pub transaction_id: Option<Uuid>,
+    /// One of [`GIFT_CARD_ENTRY_KINDS`].
+    pub kind: String,
+    pub amount: Money,
+    pub user_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A gift card with its current balance and ledger, newest entry first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GiftCardDetail {
+    pub card: GiftCard,
+    pub balance: Money,
+    pub entries: Vec<GiftCardEntry>,
+}
+
+/// Money to put on a gift card with an open sale. It is charged with the sale
+/// but is not item revenue, and is only credited to the card when the sale
+/// closes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct GiftCardLoad {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub gift_card_id: Uuid,
+    pub code: String,
+    pub amount: Money,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A gift card code with all but its last four characters hidden, as shown
+/// on sale lines and receipts.
+pub fn mask_card_code(code: &str) -> String {
+    let chars: Vec<char> = code.chars().collect();
+    let shown: String = chars[chars.len().saturating_sub(4)..].iter().collect();
+    format!("****{}", shown)
+}
+
+/// Tidies a typed or scanned gift or loyalty card code: surrounding
+/// whitespace goes and letters are upper-cased. Codes are 4 to 32 letters or
+/// digits.
+pub fn normalize_card_code(code: &str) -> Option<String> {
+    let code = code.trim().to_uppercase();
+    (code.len() >= 4 && code.len() <= 32 && code.chars().all(|c| c.is_ascii_alphanumeric()))
+        .then_some(code)
+}
+
+/// A house account that on-account sales are charged to and that is invoiced
+/// and settled later: a registered customer's or a customer group's.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum AccountRef {
+    Customer(Uuid),
+    Group(Uuid),
+}
+
+impl AccountRef {
+    /// The account a sale is charged to: its customer's if it is linked to
+    /// one, otherwise its customer group's.
+    pub fn of(customer_id: Option<Uuid>, customer_group_id: Option<Uuid>) -> Option<Self> {
+        customer_id.map(AccountRef::Customer).or(customer_group_id.map(AccountRef::Group))
+    }
+
+    /// The `(customer_id, customer_group_id)` columns the account is stored as.
+    pub fn ids(&self) -> (Option<Uuid>, Option<Uuid>) {
+        match *self {
+            AccountRef::Customer(id) => (Some(id), None),
+            AccountRef::Group(id) => (None, Some(id)),
+        }
+    }
+}
+
+/// What an on-account sale put on a house account, or, negative, what a
+/// refund credited back. Unpaid until a settlement clears it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct AccountCharge {
+    pub id: Uuid,
+    pub transaction_id: Uuid,
+    pub customer_id: Option<Uuid>,
+    pub customer_group_id: Option<Uuid>,
+    pub amount: Money,
+    pub settlement_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    /// Name on the sale, e.g. which member of a group ran it up.
+    #[cfg_attr(feature = "ssr", sqlx(default))]
+    #[serde(default)]
+    pub customer_name: Option<String>,
+}
+
+/// A payment that cleared charges on a house account.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct AccountSettlement {
+    pub id: Uuid,
+    pub customer_id: Option<Uuid>,
+    pub customer_group_id: Option<Uuid>,
+    pub tender: String,
+    pub amount: Money,
+    pub user_name: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A house account and what is still owed on it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountBalance {
+    pub account: AccountRef,
+    pub name: String,
+    pub outstanding: Money,
+}
+
+/// A house account's statement for a period: the charges made in it, paid
+/// or not, the settlements received in it, what of the period is still
+/// unpaid and what is owed on the account overall.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AccountStatement {
+    pub account: AccountRef,
+    pub name: String,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub charges: Vec<AccountCharge>,
+    pub settlements: Vec<AccountSettlement>,
+    pub unpaid: Money,
+    pub outstanding: Money,
+}
+
+/// The wording of a printed statement, in the language of whoever prints it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StatementLabels {
+    pub title: String,
+    pub period: String,
+    pub date: String,
+    pub description: String,
+    pub amount: String,
+    pub status: String,
+    pub paid: String,
+    pub open: String,
+    pub settlements: String,
+    pub unpaid: String,
+    pub outstanding: String,
+    /// Tender codes and their names, for the settlements.
+    pub tenders: Vec<(String, String)>,
+}
+
+impl AccountStatement {
+    /// Sums the charges not yet settled.
+    pub fn unpaid_of(charges: &[AccountCharge]) -> Money {
+        charges.iter().filter(|c| c.settlement_id.is_none()).map(|c| c.amount).sum()
+    }
+}
+
+/// A line of a closed sale and a quantity of it, used both to select what
+/// to refund and to report how much of each line is still refundable.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RefundLine {
+    pub transaction_item_id: Uuid,
+    pub quantity: Quantity,
+}
+
+/// A quantity of a line of an open sale to move to another open sale.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LineMove {
+    pub transaction_item_id: Uuid,
+    pub quantity: Quantity,
+}
+
+/// Shares out the units of a sale's lines, given as `(line id, quantity,
+/// unit price)`, between `parts` tabs so their totals are as even as whole
+/// units allow: most expensive units first, each to the tab with the lowest
+/// total so far. Returns the `(line id, quantity)` per tab; the first tab is
+/// the one that keeps the sale.
+pub fn split_units(lines: &[(Uuid, i32, Money)], parts: usize) -> Vec<Vec<(Uuid, i32)>> {
+    let mut units: Vec<(Uuid, Money)> = lines
+        .iter()
+        .flat_map(|&(id, qty, price)| std::iter::repeat_n((id, price), qty.max(0) as usize))
+        .collect();
+    units.sort_by_key(|u| std::cmp::Reverse(u.1));
+
+    let mut tabs: Vec<Vec<(Uuid, i32)>> = vec![Vec::new(); parts];
+    let mut totals = vec![Money::ZERO; parts];
+    for (id, price) in units {
+        let Some(tab) = (0..parts).min_by_key(|&t| totals[t]) else {
+            break;
+        };
+        totals[tab] += price;
+        match tabs[tab].iter_mut().find(|(line, _)| *line == id) {
+            Some((_, qty)) => *qty += 1,
+            None => tabs[tab].push((id, 1)),
+        }
+    }
+    tabs
+}
+
+/// An automatic discount rule, e.g. "happy hour: beer -1.00" or "3 for 2 on
+/// pastries".
+///
+/// The rule discounts units of its target (an item, a category, or every
+/// item when neither is set). Without a trigger, every `buy_quantity` target
+/// units ordered together get `get_quantity` of them discounted, the cheapest
+/// of each group. With a trigger item or category, every `buy_quantity`
+/// trigger units let `get_quantity` target units be discounted, cheapest
+/// first ("buy a main, get a dessert at half price"). Units only count while
+/// the rule applies at the time they were rung up (weekdays, time window and
+/// validity dates, in local time).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct Promotion {
+    pub id: Uuid,
+    pub name: String,
+    pub active: bool,
+    pub item_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub trigger_item_id: Option<Uuid>,
+    pub trigger_category_id: Option<Uuid>,
+    pub buy_quantity: i32,
+    pub get_quantity: i32,
+    /// "percent" or "amount" (off each discounted unit, at most its price).
+    /// Amounts are in major units, e.g. 1.5.
+    pub discount_type: String,
+    pub discount_value: f64,
+    /// Only sales tabulated under this customer group get the discount.
+    pub customer_group_id: Option<Uuid>,
+    /// ISO weekday numbers the rule runs on, e.g. "12345" for Monday to
+    /// Friday. Empty for every day.
+    pub weekdays: String,
+    /// Daily window as "HH:MM", end exclusive. A window ending before it
+    /// starts runs past midnight.
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    /// First and last day the rule runs, as "YYYY-MM-DD".
+    pub valid_from: Option<String>,
+    pub valid_to: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The discount types a promotion can have.
+pub const DISCOUNT_TYPES: [&str; 2] = ["percent", "amount"];
+
+impl Promotion {
+    /// Whether the rule runs at local time `at`.
+    pub fn applies_at(&self, at: NaiveDateTime) -> bool {
+        let weekday = at.weekday().number_from_monday().to_string();
+        if !self.weekdays.is_empty() && !self.weekdays.contains(weekday.as_str()) {
+            return false;
+        }
+        let date = at.format("%Y-%m-%d").to_string();
+        if self.valid_from.as_ref().is_some_and(|from| date < *from)
+            || self.valid_to.as_ref().is_some_and(|to| date > *to)
+        {
+            return false;
+        }
+        let time = at.format("%H:%M").to_string();
+        match (&self.start_time, &self.end_time) {
+            (Some(start), Some(end)) if start <= end => time >= *start && time < *end,
+            (Some(start), Some(end)) => time >= *start || time < *end,
+            (Some(start), None) => time >= *start,
+            (None, Some(end)) => time < *end,
+            (None, None) => true,
+        }
+    }
+
+    fn in_scope(item: Option<Uuid>, category: Option<Uuid>, line: &PromotionLine) -> bool {
+        match (item, category) {
+            (Some(item), _) => line.item_id == item,
+            (None, Some(category)) => line.category_id == category,
+            (None, None) => true,
+        }
+    }
+
+    fn has_trigger(&self) -> bool {
+        self.trigger_item_id.is_some() || self.trigger_category_id.is_some()
+    }
+
+    /// The discount on one unit at `unit_price`, as a negative amount.
+    fn unit_discount(&self, unit_price: Money, currency: &str) -> Money {
+        let off = match self.discount_type.as_str() {
+            "percent" => unit_price.percent(self.discount_value.clamp(0.0, 100.0)),
+            _ => Money::from_major(self.discount_value, currency).clamp(Money::ZERO, unit_price.max(Money::ZERO)),
+        };
+        -off
+    }
 }
 
+/// What the promotion form sends to create or update a promotion.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
-pub struct Item {
-    pub id: Uuid,
+pub struct PromotionInput {
     pub name: String,
-    pub description: Option<String>,
-    pub price: f64,
+    pub active: bool,
+    pub item_id: Option<Uuid>,
+    pub category_id: Option<Uuid>,
+    pub trigger_item_id: Option<Uuid>,
+    pub trigger_category_id: Option<Uuid>,
+    pub buy_quantity: i32,
+    pub get_quantity: i32,
+    pub discount_type: String,
+    pub discount_value: f64,
+    pub customer_group_id: Option<Uuid>,
+    pub weekdays: String,
+    pub start_time: Option<String>,
+    pub end_time: Option<String>,
+    pub valid_from: Option<String>,
+    pub valid_to: Option<String>,
+}
+
+/// A sale line as seen by [`apply_promotions`]: its item and category, the
+/// units and their price, and the local time it was rung up.
+#[derive(Debug, Clone)]
+pub struct PromotionLine {
+    pub line_id: Uuid,
+    pub item_id: Uuid,
     pub category_id: Uuid,
-    pub sku: Option<String>,
-    pub in_stock: bool,
-    pub image_path: Option<String>,
-    pub stock_quantity: Option<i32>,
-    pub kitchen_item: bool,
-    pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
+    pub quantity: i32,
+    pub unit_price: Money,
+    pub ordered_at: NaiveDateTime,
+}
+
+/// The discount a promotion gives on one sale line, as a negative amount.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineDiscount {
+    pub promotion_id: Uuid,
+    pub line_id: Uuid,
+    pub amount: Money,
+}
+
+/// Works out the promotions a sale gets. Promotions are tried in the given
+/// order and each unit gets at most one discount; `customer_group_id` is the
+/// sale's group and `currency` what amount discounts are given in. Inactive
+/// and misconfigured rules are skipped.
+pub fn apply_promotions(
+    promotions: &[Promotion],
+    lines: &[PromotionLine],
+    customer_group_id: Option<Uuid>,
+    currency: &str,
+) -> Vec<LineDiscount> {
+    // Units of each line already discounted by an earlier promotion.
+    let mut used: Vec<i32> = vec![0; lines.len()];
+    let mut discounts: Vec<LineDiscount> = Vec::new();
+
+    for promo in promotions {
+        if !promo.active
+            || promo.buy_quantity < 1
+            || promo.get_quantity < 1
+            || (!promo.has_trigger() && promo.get_quantity > promo.buy_quantity)
+            || promo.customer_group_id.is_some_and(|g| Some(g) != customer_group_id)
+        {
+            continue;
+        }
+        // Free target units as (line index, unit price), most expensive first.
+        let mut units: Vec<(usize, Money)> = lines
+            .iter()
+            .enumerate()
+            .filter(|(_, l)| Promotion::in_scope(promo.item_id, promo.category_id, l))
+            .filter(|(_, l)| promo.applies_at(l.ordered_at))
+            .flat_map(|(i, l)| std::iter::repeat_n((i, l.unit_price), (l.quantity - used[i]).max(0) as usize))
+            .collect();
+        units.sort_by_key(|u| std::cmp::Reverse(u.1));
+
+        let (buy, get) = (promo.buy_quantity as usize, promo.get_quantity as usize);
+        let discounted: Vec<(usize, Money)> = if promo.has_trigger() {
+            let triggers: i32 = lines
+                .iter()
+                .filter(|l| Promotion::in_scope(promo.trigger_item_id, promo.trigger_category_id, l))
+                .filter(|l| promo.applies_at(l.ordered_at))
+                .map(|l| l.quantity)
+                .sum();
+            let count = (triggers.max(0) as usize / buy) * get;
+            units.iter().rev().take(count).copied().collect()
+        } else {
+            units.chunks_exact(buy).flat_map(|group| group[buy - get..].iter().copied()).collect()
+        };
+
+        for (i, unit_price) in discounted {
+            let amount = promo.unit_discount(unit_price, currency);
+            if amount.is_zero() {
+                continue;
+            }
+            used[i] += 1;
+            let line_id = lines[i].line_id;
+            match discounts.iter_mut().find(|d| d.promotion_id == promo.id && d.line_id == line_id) {
+                Some(d) => d.amount += amount,
+                None => discounts.push(LineDiscount { promotion_id: promo.id, line_id, amount }),
+            }
+        }
+    }
+    discounts
+}
+
+/// A discount on a sale, summed per name for display and receipts.
+/// `amount` is negative, or positive on a refund giving the discount back.
+/// `manual_discount_id` is set for a discount given by hand, which can be
+/// taken off again while the sale is open.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscountLine {
+    pub name: String,
+    pub amount: Money,
+    pub manual_discount_id: Option<Uuid>,
+}
+
+/// Sums the discounts of a sale's lines per name, in the order they first
+/// appear. Manual discounts are kept apart from each other.
+pub fn discount_lines(lines: &[TransactionItemDetail]) -> Vec<DiscountLine> {
+    let mut out: Vec<DiscountLine> = Vec::new();
+    for d in lines.iter().flat_map(|l| l.discounts.iter()) {
+        match out.iter_mut().find(|o| o.name == d.name && o.manual_discount_id == d.manual_discount_id) {
+            Some(o) => o.amount += d.amount,
+            None => out.push(DiscountLine {
+                name: d.name.clone(),
+                amount: d.amount,
+                manual_discount_id: d.manual_discount_id,
+            }),
+        }
+    }
+    out
 }
 
+/// A reason code a cashier must pick when giving a discount by hand, e.g.
+/// "Damaged" or "Staff". Managed by admins.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
-pub struct Transaction {
+pub struct DiscountReason {
     pub id: Uuid,
-    pub customer_name: Option<String>,
-    pub status: String,
-    pub total: f64,
-    pub paid_amount: Option<f64>,
-    pub change_amount: Option<f64>,
-    /// The customer group this sale is tabulated under. `None` means the sale
-    /// belongs to "regular customers". See [`CustomerGroup`].
-    pub customer_group_id: Option<Uuid>,
+    pub name: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
-    pub closed_at: Option<DateTime<Utc>>,
 }
 
-/// A named group whose sales are tabulated separately in the statistics
-/// (e.g. "Staff", "VIP"). Managed by admins; sales can be closed against any
-/// group by cashiers. Deleting a group reassigns its sales to regular customers.
+/// A discount given by hand on one line (`transaction_item_id`) or on the
+/// whole sale. It is shared out over the lines it covers as
+/// [`TransactionDiscount`]s, see [`apply_manual_discounts`].
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
-pub struct CustomerGroup {
+pub struct ManualDiscount {
     pub id: Uuid,
-    pub name: String,
+    pub transaction_id: Uuid,
+    pub transaction_item_id: Option<Uuid>,
+    /// "percent" or "amount", as for promotions (see [`DISCOUNT_TYPES`]).
+    /// Amounts are in major units.
+    pub discount_type: String,
+    pub discount_value: f64,
+    /// The reason code's name when the discount was given.
+    pub reason: String,
+    pub user_name: Option<String>,
+    /// The admin who approved a discount above the cashier limit.
+    pub approved_by: Option<String>,
+    /// Set when the discount redeems the linked customer's loyalty points:
+    /// how many, taken off their balance when the sale closes.
+    pub loyalty_points: Option<i64>,
     pub created_at: DateTime<Utc>,
-    pub updated_at: DateTime<Utc>,
 }
 
-/// Selects which sales the statistics aggregate over.
-#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub enum GroupFilter {
-    /// Every closed sale, regardless of customer group.
-    All,
-    /// Only sales not assigned to any customer group ("regular customers").
-    Regular,
-    /// Only sales assigned to the given customer group.
-    Group(Uuid),
+impl ManualDiscount {
+    /// The label shown on the sale and the receipt, e.g. "Staff 10%" or
+    /// "Loyalty points (150)".
+    pub fn label(&self) -> String {
+        match (self.loyalty_points, self.discount_type.as_str()) {
+            (Some(points), _) => format!("{} ({})", self.reason, points),
+            (None, "percent") => format!("{} {}%", self.reason, self.discount_value),
+            _ => self.reason.clone(),
+        }
+    }
 }
 
+/// What the discount dialog sends to give a discount by hand. A discount
+/// above the cashier limit needs an admin's PIN in `manager_pin`, unless an
+/// admin gives it.
 #[derive(Debug, Clone, Serialize, Deserialize)]
-#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
-pub struct TransactionItemDetail {
-    pub id: Uuid,
-    pub item_id: Uuid,
-    pub item_name: String,
-    pub quantity: i32,
-    pub unit_price: f64,
-    pub total_price: f64,
+pub struct ManualDiscountInput {
+    pub transaction_item_id: Option<Uuid>,
+    pub discount_type: String,
+    pub discount_value: f64,
+    pub reason_id: Uuid,
+    pub manager_pin: Option<String>,
+}
+
+/// How much of `base` a discount takes off, in percent. Used to check a
+/// discount against the cashier limit.
+pub fn discount_percent(discount_type: &str, value: f64, base: f64) -> f64 {
+    match discount_type {
+        "percent" => value,
+        _ if base > 0.0 => value / base * 100.0,
+        _ => 100.0,
+    }
+}
+
+/// A sale line as seen by [`apply_manual_discounts`]: what it comes to after
+/// its promotions. Bundle components carry their bundle's line.
+#[derive(Debug, Clone)]
+pub struct DiscountableLine {
+    pub line_id: Uuid,
+    pub bundle_id: Option<Uuid>,
+    pub net: Money,
+}
+
+/// A line's share of a manual discount, as a negative amount.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManualLineDiscount {
+    pub manual_discount_id: Uuid,
+    pub line_id: Uuid,
+    pub amount: Money,
+}
+
+/// Shares manual discounts out over the lines they cover, to the minor unit
+/// and in proportion to what each line comes to. Line discounts (a bundle's
+/// covers its components) are taken first, then discounts on the whole sale,
+/// each from what is left after the ones before it; no line goes below zero.
+/// Amount discounts are in major units of `currency`.
+pub fn apply_manual_discounts(
+    discounts: &[ManualDiscount],
+    lines: &[DiscountableLine],
+    currency: &str,
+) -> Vec<ManualLineDiscount> {
+    let mut nets: Vec<Money> = lines.iter().map(|l| l.net.max(Money::ZERO)).collect();
+    let mut out = Vec::new();
+    let ordered = discounts
+        .iter()
+        .filter(|d| d.transaction_item_id.is_some())
+        .chain(discounts.iter().filter(|d| d.transaction_item_id.is_none()));
+    for d in ordered {
+        let covered: Vec<usize> = match d.transaction_item_id {
+            Some(target) => (0..lines.len())
+                .filter(|&i| lines[i].line_id == target || lines[i].bundle_id == Some(target))
+                .collect(),
+            None => (0..lines.len()).collect(),
+        };
+        let base: Money = covered.iter().map(|&i| nets[i]).sum();
+        let off = match d.discount_type.as_str() {
+            "percent" => base.percent(d.discount_value.clamp(0.0, 100.0)),
+            _ => Money::from_major(d.discount_value, currency).clamp(Money::ZERO, base),
+        };
+        if !off.is_positive() {
+            continue;
+        }
+        let weights: Vec<Money> = covered.iter().map(|&i| nets[i]).collect();
+        for (&i, share) in covered.iter().zip(allocate_bundle_price(off, &weights)) {
+            if share.is_zero() {
+                continue;
+            }
+            nets[i] -= share;
+            out.push(ManualLineDiscount { manual_discount_id: d.id, line_id: lines[i].line_id, amount: -share });
+        }
+    }
+    out
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TransactionDetailsResponse {
     pub transaction: Transaction,
     pub items: Vec<TransactionItemDetail>,
+    pub payments: Vec<Payment>,
+    pub taxes: Vec<TaxLine>,
+    pub gift_card_loads: Vec<GiftCardLoad>,
+    /// The customer linked to the sale, with their current points.
+    pub customer: Option<Customer>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CloseTransactionResponse {
     pub transaction: Transaction,
-    pub change_amount: f64,
+    pub change_amount: Money,
+    pub tip_amount: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -104,28 +1624,182 @@ pub struct ItemSalesReport {
     pub item_id: Uuid,
     pub item_name: String,
     pub category_name: String,
-    pub quantity_sold: i64,
-    pub total_revenue: f64,
-    pub average_price: f64,
+    /// Units sold, or kg for goods sold by weight.
+    pub quantity_sold: Quantity,
+    pub total_revenue: Money,
+    /// Per unit, or per kg for goods sold by weight.
+    pub average_price: Money,
     pub transaction_count: i64,
+    /// "kg" for goods sold by weight, `None` for items sold by the piece.
+    pub unit: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReportSummary {
-    pub total_revenue: f64,
+    pub total_revenue: Money,
     pub total_items_sold: i64,
     pub total_transactions: i64,
-    pub average_transaction_value: f64,
+    pub average_transaction_value: Money,
     pub top_selling_item: Option<String>,
     pub top_revenue_item: Option<String>,
 }
 
+/// How often a modifier was sold in a period and the revenue from its price
+/// delta.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct ModifierSalesReport {
+    pub group_name: String,
+    pub modifier_name: String,
+    pub quantity_sold: i64,
+    pub total_revenue: Money,
+}
+
+/// Units of an item sold while a given list price was in effect, from the
+/// `price_history` table. Revenue is what the lines were actually rung up
+/// at, modifiers included.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct ItemPriceReport {
+    pub item_name: String,
+    pub list_price: Money,
+    pub valid_from: DateTime<Utc>,
+    pub valid_to: Option<DateTime<Utc>>,
+    pub quantity_sold: Quantity,
+    pub total_revenue: Money,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SalesReport {
     pub start_date: DateTime<Utc>,
     pub end_date: DateTime<Utc>,
     pub items: Vec<ItemSalesReport>,
     pub summary: ReportSummary,
+    /// Net, tax and gross per rate, summed from each sale's own breakdown so
+    /// the figures match the printed receipts.
+    pub taxes: Vec<TaxLine>,
+    pub modifiers: Vec<ModifierSalesReport>,
+    pub prices: Vec<ItemPriceReport>,
+    pub promotions: Vec<PromotionSalesReport>,
+    /// Manual discounts per reason code and per cashier.
+    pub discount_reasons: Vec<DiscountSalesReport>,
+    pub discount_users: Vec<DiscountSalesReport>,
+    /// Gift card money taken in and paid out. Top-ups are not part of
+    /// `summary.total_revenue`; the goods are counted when a card is redeemed.
+    pub gift_cards: GiftCardReport,
+    /// Deposits charged and paid back, which are not part of
+    /// `summary.total_revenue` either.
+    pub deposits: DepositReport,
+    /// Tips taken, per day and cashier, for handing them out. Not revenue.
+    pub tips: TipReport,
+}
+
+/// Gift card movements in a period, and what all cards still hold.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct GiftCardReport {
+    pub topped_up: Money,
+    pub redeemed: Money,
+    pub refunded: Money,
+    /// The balance left on all cards now, regardless of the period.
+    pub outstanding: Money,
+}
+
+/// Deposit money in a period per deposit item. Charged deposits are lines
+/// with a positive amount, returns (and refunded deposits) negative ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct DepositItemReport {
+    pub item_name: String,
+    pub charged_quantity: i64,
+    pub charged: Money,
+    pub returned_quantity: i64,
+    pub returned: Money,
+}
+
+/// Deposits charged and paid back in a period. `net_liability` is what was
+/// taken in and not yet paid back: money owed for containers still out.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DepositReport {
+    pub items: Vec<DepositItemReport>,
+    pub charged: Money,
+    pub returned: Money,
+    pub net_liability: Money,
+}
+
+impl DepositReport {
+    pub fn from_items(items: Vec<DepositItemReport>) -> Self {
+        let charged = items.iter().map(|i| i.charged).sum();
+        let returned = items.iter().map(|i| i.returned).sum();
+        DepositReport { items, charged, returned, net_liability: charged - returned }
+    }
+}
+
+/// The tips one cashier took on one day. `day` is the local date
+/// (YYYY-MM-DD); it is empty on the per-cashier totals of a [`TipReport`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TipShare {
+    pub day: String,
+    pub user_name: String,
+    pub transaction_count: i64,
+    pub amount: Money,
+}
+
+/// Tips taken in a period, per day and cashier and per cashier overall.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TipReport {
+    pub days: Vec<TipShare>,
+    pub users: Vec<TipShare>,
+    pub total: Money,
+}
+
+impl TipReport {
+    /// Sums tips given as `(day, cashier, amount)`, one per sale.
+    pub fn from_tips(tips: &[(String, String, Money)]) -> Self {
+        fn add(shares: &mut Vec<TipShare>, day: &str, user_name: &str, amount: Money) {
+            match shares.iter_mut().find(|s| s.day == day && s.user_name == user_name) {
+                Some(share) => {
+                    share.transaction_count += 1;
+                    share.amount += amount;
+                }
+                None => shares.push(TipShare {
+                    day: day.to_string(),
+                    user_name: user_name.to_string(),
+                    transaction_count: 1,
+                    amount,
+                }),
+            }
+        }
+        let mut report = TipReport::default();
+        for (day, user_name, amount) in tips {
+            add(&mut report.days, day, user_name, *amount);
+            add(&mut report.users, "", user_name, *amount);
+        }
+        report.days.sort_by(|a, b| a.day.cmp(&b.day).then_with(|| a.user_name.cmp(&b.user_name)));
+        report.users.sort_by(|a, b| a.user_name.cmp(&b.user_name));
+        report.total = tips.iter().map(|(_, _, amount)| amount).sum();
+        report
+    }
+}
+
+/// How often a promotion was given in a period and what it took off.
+/// `total_discount` is negative, net of discounts given back on refunds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct PromotionSalesReport {
+    pub name: String,
+    pub transaction_count: i64,
+    pub total_discount: Money,
+}
+
+/// Manual discounts given in a period under one reason code or by one
+/// cashier. `total_discount` is negative, net of discounts given back on
+/// refunds.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct DiscountSalesReport {
+    pub name: String,
+    pub transaction_count: i64,
+    pub total_discount: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -147,14 +1821,14 @@ pub struct ItemSalesTimeseries {
 pub struct RevenueBucket {
     pub bucket_start: DateTime<Utc>,
     pub label: String,
-    pub revenue: f64,
+    pub revenue: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RevenueTimeseries {
     pub buckets: Vec<RevenueBucket>,
     pub bucket_unit: String,
-    pub total_revenue: f64,
+    pub total_revenue: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -176,14 +1850,26 @@ pub struct ChangeBucket {
     pub count: i64,
 }
 
+/// Revenue taken with one tender type. For cash, `amount` is net of change.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TenderTotal {
+    pub tender: String,
+    pub transaction_count: i64,
+    pub amount: Money,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PaymentAnalysis {
     pub transaction_count: i64,
-    pub total_paid: f64,
-    pub total_change: f64,
-    pub average_change: f64,
+    pub total_paid: Money,
+    pub total_change: Money,
+    pub average_change: Money,
     pub exact_payment_count: i64,
     pub change_distribution: Vec<ChangeBucket>,
+    pub tenders: Vec<TenderTotal>,
+    /// The sum of the cash rounding differences, which the cash tender
+    /// includes on top of revenue.
+    pub cash_rounding: Money,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -192,12 +1878,375 @@ pub struct UnderperformingItem {
     pub item_id: Uuid,
     pub item_name: String,
     pub category_name: String,
-    pub price: f64,
+    pub price: Money,
     pub quantity_sold: i64,
-    pub revenue: f64,
+    pub revenue: Money,
+    pub created_at: DateTime<Utc>,
+}
+
+// Journal models
+
+/// What put a sale into the journal.
+pub const JOURNAL_EVENTS: [&str; 3] = ["sale", "cancel", "refund"];
+
+/// An entry of the tamper-evident journal. Every sale that is closed,
+/// cancelled or refunded gets the next receipt number (`seq`, without gaps)
+/// and a hash over its content chained to the hash of the entry before, so a
+/// journalled sale cannot be altered or deleted without breaking the chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct JournalEntry {
+    pub seq: i64,
+    pub transaction_id: Uuid,
+    /// One of [`JOURNAL_EVENTS`].
+    pub event: String,
+    /// The [`JournalRecord`] as JSON, exactly as it was hashed.
+    pub payload: String,
+    pub prev_hash: String,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl JournalEntry {
+    /// The start of the hash, printed on receipts.
+    pub fn short_hash(&self) -> &str {
+        &self.hash[..self.hash.len().min(8)]
+    }
+}
+
+/// The content of a sale that the journal hashes: the fields that must not
+/// change once it is finished. Names, customers and tables are left out, as
+/// renaming an item or deleting a table legitimately changes those later.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JournalRecord {
+    pub transaction_id: Uuid,
+    pub event: String,
+    pub status: String,
+    pub refund_of: Option<Uuid>,
+    pub total: Money,
+    pub paid_amount: Option<Money>,
+    pub change_amount: Option<Money>,
+    pub tip_amount: Money,
+    pub rounding_amount: Money,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub lines: Vec<JournalLine>,
+    /// `(tender, amount)` per payment.
+    pub payments: Vec<(String, Money)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct JournalLine {
+    pub id: Uuid,
+    pub item_id: Uuid,
+    pub quantity: Quantity,
+    pub unit_price: Money,
+    pub total_price: Money,
+}
+
+/// Something wrong found by verifying the journal.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum JournalProblem {
+    /// Receipt numbers skip from `after` to `next`: entries were deleted.
+    Gap { after: i64, next: i64 },
+    /// The entry's hash does not match its content: the entry was edited.
+    BadHash { seq: i64 },
+    /// The entry does not point to the hash of the entry before it.
+    BrokenChain { seq: i64 },
+    /// The last entry is not the one the journal last recorded: entries were
+    /// deleted from the end.
+    BadHead { seq: i64 },
+    /// The sale no longer matches what was journalled.
+    Altered { seq: i64, transaction_id: Uuid },
+    /// The journalled sale was deleted.
+    Missing { seq: i64, transaction_id: Uuid },
+    /// A finished sale that has no journal entry.
+    NotJournaled { transaction_id: Uuid },
+}
+
+impl JournalProblem {
+    pub fn message(&self) -> String {
+        match self {
+            JournalProblem::Gap { after, next } => {
+                format!("Receipt numbers jump from {} to {}", after, next)
+            }
+            JournalProblem::BadHash { seq } => format!("Entry {} does not match its hash", seq),
+            JournalProblem::BrokenChain { seq } => {
+                format!("Entry {} is not chained to the entry before it", seq)
+            }
+            JournalProblem::BadHead { seq } => {
+                format!("Entry {} is not the last entry the journal recorded", seq)
+            }
+            JournalProblem::Altered { seq, transaction_id } => {
+                format!("Sale {} of entry {} was altered", transaction_id, seq)
+            }
+            JournalProblem::Missing { seq, transaction_id } => {
+                format!("Sale {} of entry {} was deleted", transaction_id, seq)
+            }
+            JournalProblem::NotJournaled { transaction_id } => {
+                format!("Sale {} has no journal entry", transaction_id)
+            }
+        }
+    }
+}
+
+/// The outcome of verifying the journal: how many entries were checked and
+/// what was wrong, if anything.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalVerification {
+    pub entries: i64,
+    pub problems: Vec<JournalProblem>,
+}
+
+impl JournalVerification {
+    pub fn is_ok(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+// Business details
+
+/// The business and register as tax audit exports name them. Fields left
+/// empty are exported empty.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BusinessDetails {
+    pub name: String,
+    pub street: String,
+    pub postcode: String,
+    pub city: String,
+    /// ISO 3166 alpha-3 country code, e.g. "DEU".
+    pub country: String,
+    pub tax_number: String,
+    pub vat_id: String,
+    /// Identifies this register among the business's registers, e.g. "1".
+    pub register_id: String,
+}
+
+// Closing models
+
+/// A sale finished in a closing period, as far as its totals count it.
+/// Cancelled sales count by the time they were cancelled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct ClosingSale {
+    pub status: String,
+    pub refund_of: Option<Uuid>,
+    pub closed_by: Option<String>,
+    pub total: Money,
+    pub tip_amount: Money,
+    pub rounding_amount: Money,
+}
+
+/// The sales one user closed in a closing period, refunds included.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UserTotal {
+    pub user_name: String,
+    pub transaction_count: i64,
+    pub amount: Money,
+}
+
+/// The totals of the sales finished in a period, as a Z-closing freezes
+/// them and an X-report shows them so far.
+///
+/// `revenue` is what the sales and refunds came to, gift card top-ups and
+/// deposits included, so that with the tips and the cash rounding it adds
+/// up to the tenders. `refunds` (negative) are part of it; cancelled sales
+/// are not.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClosingTotals {
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub revenue: Money,
+    /// Closed sales, refunds not counted.
+    pub transaction_count: i64,
+    pub refund_count: i64,
+    pub refunds: Money,
+    pub cancellation_count: i64,
+    /// What the cancelled sales had come to when they were cancelled.
+    pub cancellations: Money,
+    pub tips: Money,
+    pub rounding: Money,
+    pub tenders: Vec<TenderTotal>,
+    pub taxes: Vec<TaxLine>,
+    pub users: Vec<UserTotal>,
+}
+
+impl ClosingTotals {
+    /// Sums the finished sales of a period. Tenders and taxes are worked out
+    /// from the payments and lines and passed in. Users are in name order;
+    /// sales closed before users were recorded count under an empty name.
+    pub fn from_sales(
+        period_start: DateTime<Utc>,
+        period_end: DateTime<Utc>,
+        sales: &[ClosingSale],
+        tenders: Vec<TenderTotal>,
+        taxes: Vec<TaxLine>,
+    ) -> Self {
+        let mut totals = ClosingTotals {
+            period_start,
+            period_end,
+            revenue: Money::ZERO,
+            transaction_count: 0,
+            refund_count: 0,
+            refunds: Money::ZERO,
+            cancellation_count: 0,
+            cancellations: Money::ZERO,
+            tips: Money::ZERO,
+            rounding: Money::ZERO,
+            tenders,
+            taxes,
+            users: Vec::new(),
+        };
+        for sale in sales {
+            if sale.status == "cancelled" {
+                totals.cancellation_count += 1;
+                totals.cancellations += sale.total;
+                continue;
+            }
+            if sale.refund_of.is_some() {
+                totals.refund_count += 1;
+                totals.refunds += sale.total;
+            } else {
+                totals.transaction_count += 1;
+            }
+            totals.revenue += sale.total;
+            totals.tips += sale.tip_amount;
+            totals.rounding += sale.rounding_amount;
+
+            let user_name = sale.closed_by.clone().unwrap_or_default();
+            match totals.users.iter_mut().find(|u| u.user_name == user_name) {
+                Some(user) => {
+                    user.transaction_count += 1;
+                    user.amount += sale.total;
+                }
+                None => totals.users.push(UserTotal { user_name, transaction_count: 1, amount: sale.total }),
+            }
+        }
+        totals.users.sort_by(|a, b| a.user_name.cmp(&b.user_name));
+        totals
+    }
+}
+
+/// A Z-closing: the totals since the closing before, frozen under the next
+/// number. Closings are never changed or deleted.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ZClosing {
+    pub number: i64,
+    pub closed_by: String,
+    pub created_at: DateTime<Utc>,
+    pub totals: ClosingTotals,
+}
+
+// Cash drawer models
+
+/// Kinds of [`CashMovement`]: the opening float, money put in (pay-in),
+/// money taken out for expenses (pay-out) and money taken to the safe.
+pub const CASH_MOVEMENT_KINDS: [&str; 4] = ["float", "pay_in", "pay_out", "safe_drop"];
+
+/// Cash put into or taken out of the drawer other than by a sale. `amount`
+/// is always positive; [`CashMovement::signed_amount`] says which way it
+/// went.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct CashMovement {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    /// One of [`CASH_MOVEMENT_KINDS`].
+    pub kind: String,
+    pub amount: Money,
+    /// What the cash was for, e.g. "milk for the coffee machine".
+    pub reason: Option<String>,
+    pub user_name: String,
     pub created_at: DateTime<Utc>,
 }
 
+impl CashMovement {
+    /// The amount as it changes the cash in the drawer: floats and pay-ins
+    /// add to it, pay-outs and safe drops take from it.
+    pub fn signed_amount(&self) -> Money {
+        match self.kind.as_str() {
+            "pay_out" | "safe_drop" => -self.amount,
+            _ => self.amount,
+        }
+    }
+}
+
+/// A drawer session runs from putting in the opening float to counting the
+/// drawer. Counting it records what it should have held, what was counted
+/// and the difference (negative when cash is missing).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct DrawerSession {
+    pub id: Uuid,
+    pub opened_by: String,
+    pub opened_at: DateTime<Utc>,
+    pub closed_by: Option<String>,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub expected: Option<Money>,
+    pub counted: Option<Money>,
+    pub variance: Option<Money>,
+}
+
+/// How many coins or notes of one denomination were counted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "ssr", derive(sqlx::FromRow))]
+pub struct DenominationCount {
+    pub denomination: Money,
+    pub quantity: i64,
+}
+
+/// The coins and notes a drawer is counted in, largest first: the 1-2-5
+/// series from the smallest unit up to 500, or up to 500 000 for currencies
+/// without minor units, whose notes go that high (e.g. the dong).
+/// Denominations a currency lacks are simply left at zero.
+pub fn cash_denominations(currency: &str) -> Vec<Money> {
+    let top = if currency_decimals(currency) == 0 { 500_000 } else { 500 * Money::unit(currency) };
+    let mut denominations = Vec::new();
+    let mut decade = 1;
+    while decade <= top {
+        denominations.extend([1, 2, 5].iter().map(|step| decade * step).filter(|d| *d <= top).map(Money));
+        decade *= 10;
+    }
+    denominations.reverse();
+    denominations
+}
+
+/// The total of a count.
+pub fn counted_total(counts: &[DenominationCount]) -> Money {
+    counts.iter().map(|c| c.denomination * c.quantity).sum()
+}
+
+/// A drawer session with what went into and out of the drawer so far.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DrawerSummary {
+    pub session: DrawerSession,
+    pub movements: Vec<CashMovement>,
+    /// Cash taken with sales and paid back on refunds, net of change.
+    pub cash_sales: Money,
+    /// House account settlements paid in cash.
+    pub cash_settlements: Money,
+    /// Gift card balances paid out in cash (negative).
+    pub gift_card_payouts: Money,
+    /// The denominations counted, once the session is closed.
+    pub counts: Vec<DenominationCount>,
+}
+
+impl DrawerSummary {
+    /// The movements of one kind, summed.
+    pub fn movement_total(&self, kind: &str) -> Money {
+        self.movements.iter().filter(|m| m.kind == kind).map(|m| m.amount).sum()
+    }
+
+    /// What the drawer should hold: the float and every movement, plus the
+    /// cash taken and paid out since the session was opened.
+    pub fn expected(&self) -> Money {
+        self.movements.iter().map(CashMovement::signed_amount).sum::<Money>()
+            + self.cash_sales
+            + self.cash_settlements
+            + self.gift_card_payouts
+    }
+}
+
 // User / Auth models
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -230,14 +2279,631 @@ pub struct InitialCredentials {
 pub struct KitchenOrderItem {
     pub transaction_item_id: Uuid,
     pub item_name: String,
-    pub quantity: i32,
+    pub quantity: Quantity,
     pub completed: bool,
+    /// Names of the line's modifiers, comma separated.
+    pub modifiers: Option<String>,
+    /// The cashier's note for the line, highlighted on the kitchen display.
+    pub note: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KitchenOrder {
     pub transaction_id: Uuid,
     pub customer_name: Option<String>,
+    /// The table the order is served at; shown instead of the customer name.
+    pub table_name: Option<String>,
     pub created_at: DateTime<Utc>,
     pub items: Vec<KitchenOrderItem>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bundle_price_is_shared_to_the_cent() {
+        let shares = allocate_bundle_price(Money(990), &[Money(500), Money(300), Money(250)]);
+        assert_eq!(shares, vec![Money(471), Money(283), Money(236)]);
+        assert_eq!(shares.iter().sum::<Money>(), Money(990));
+
+        // Free components share evenly; a third of a cent goes to the first.
+        assert_eq!(allocate_bundle_price(Money(100), &[Money::ZERO; 3]), vec![Money(34), Money(33), Money(33)]);
+        assert!(allocate_bundle_price(Money(500), &[]).is_empty());
+    }
+
+    #[test]
+    fn in_store_codes_carry_weight_or_price() {
+        assert_eq!(
+            InStoreCode::decode("2812345005354"),
+            Some(InStoreCode { item_code: "2812345".to_string(), value: 535 })
+        );
+        assert_eq!(InStoreCode::decode("2212345012992").map(|c| c.value), Some(1299));
+        // Wrong check digit, regular EAN and wrong length.
+        assert_eq!(InStoreCode::decode("2812345005355"), None);
+        assert_eq!(InStoreCode::decode("4006381333931"), None);
+        assert_eq!(InStoreCode::decode("281234500535"), None);
+
+        assert_eq!(kg_price(Money(299), "kg") * Quantity(535), Money(160));
+        assert_eq!(kg_price(Money(149), "100g") * Quantity(250), Money(373));
+    }
+
+    #[test]
+    fn quantities() {
+        assert_eq!(Quantity::units(3).to_string(), "3");
+        assert_eq!(Quantity(535).to_string(), "0.535");
+        assert_eq!(Quantity(-1250).to_string(), "-1.250");
+        assert_eq!(Quantity::from_decimal(0.535), Quantity(535));
+        assert_eq!(Quantity(2500).whole_units(), 2);
+        assert!(!Quantity(2500).is_whole());
+        // A unit price times a quantity is rounded half away from zero.
+        assert_eq!(Money(250) * Quantity::units(2), Money(500));
+        assert_eq!(Money(999) * Quantity(500), Money(500));
+        assert_eq!(Money(-999) * Quantity(500), Money(-500));
+    }
+
+    #[test]
+    fn inclusive_tax_is_extracted_per_rate() {
+        let lines = [(Some(20.0), Money(1200)), (Some(10.0), Money(550)), (Some(20.0), Money(600)), (None, Money(100))];
+        let taxes = tax_breakdown(&lines, true);
+        assert_eq!(
+            taxes,
+            vec![
+                TaxLine { rate: 0.0, net: Money(100), tax: Money(0), gross: Money(100) },
+                TaxLine { rate: 10.0, net: Money(500), tax: Money(50), gross: Money(550) },
+                TaxLine { rate: 20.0, net: Money(1500), tax: Money(300), gross: Money(1800) },
+            ]
+        );
+        assert_eq!(added_tax(&taxes, true), Money::ZERO);
+    }
+
+    #[test]
+    fn exclusive_tax_is_added_on_top() {
+        let lines = [(Some(19.0), Money(999)), (Some(7.0), Money(350))];
+        let taxes = tax_breakdown(&lines, false);
+        assert_eq!(taxes[0], TaxLine { rate: 7.0, net: Money(350), tax: Money(25), gross: Money(375) });
+        assert_eq!(taxes[1], TaxLine { rate: 19.0, net: Money(999), tax: Money(190), gross: Money(1189) });
+        assert_eq!(added_tax(&taxes, false), Money(215));
+    }
+
+    #[test]
+    fn untaxed_sales_have_no_breakdown() {
+        assert!(tax_breakdown(&[(None, Money(400)), (None, Money(250))], true).is_empty());
+    }
+
+    fn group(min_picks: i32, max_picks: Option<i32>, options: &[Uuid]) -> ModifierGroupDetail {
+        let id = Uuid::new_v4();
+        ModifierGroupDetail {
+            group: ModifierGroup {
+                id,
+                name: "Milk".to_string(),
+                min_picks,
+                max_picks,
+                created_at: Utc::now(),
+                updated_at: Utc::now(),
+            },
+            modifiers: options
+                .iter()
+                .map(|&m| Modifier { id: m, group_id: id, name: String::new(), price_delta: Money::ZERO, sort_order: 0 })
+                .collect(),
+            item_ids: vec![],
+            category_ids: vec![],
+        }
+    }
+
+    #[test]
+    fn split_units_evens_out_totals() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let tabs = split_units(&[(a, 4, Money(250)), (b, 2, Money(100))], 2);
+        assert_eq!(tabs, vec![vec![(a, 2), (b, 1)], vec![(a, 2), (b, 1)]]);
+
+        let tabs = split_units(&[(a, 1, Money(900)), (b, 3, Money(300))], 2);
+        assert_eq!(tabs, vec![vec![(a, 1)], vec![(b, 3)]]);
+    }
+
+    #[test]
+    fn modifier_picks_respect_min_and_max() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let required = group(1, Some(1), &[a, b]);
+        let extras = group(0, None, &[c]);
+        let groups = [&required, &extras];
+        assert!(check_modifier_picks(&groups, &[a]).is_ok());
+        assert!(check_modifier_picks(&groups, &[b, c]).is_ok());
+        assert!(check_modifier_picks(&groups, &[c]).is_err());
+        assert!(check_modifier_picks(&groups, &[a, b]).is_err());
+        assert!(check_modifier_picks(&groups, &[a, a]).is_err());
+        assert!(check_modifier_picks(&groups, &[a, Uuid::new_v4()]).is_err());
+    }
+
+    #[test]
+    fn scan_buffer_takes_fast_bursts_only() {
+        fn feed(scan: &mut ScanBuffer, keys: &[&str], start: i64, gap: i64) -> Option<String> {
+            keys.iter().enumerate().map(|(i, k)| scan.push(k, start + i as i64 * gap)).last().flatten()
+        }
+        let mut scan = ScanBuffer::default();
+        let code = ["4", "0", "0", "6", "Shift", "A", "Enter"];
+        assert_eq!(feed(&mut scan, &code, 1_000, 10), Some("4006A".to_string()));
+        // Typed by hand: too slow.
+        assert_eq!(feed(&mut scan, &code, 5_000, 200), None);
+        // Too short, e.g. a stray Enter after a digit.
+        assert_eq!(feed(&mut scan, &["1", "Enter"], 10_000, 5), None);
+        // A slow key before the burst is dropped.
+        assert_eq!(scan.push("7", 20_000), None);
+        assert_eq!(feed(&mut scan, &["1", "2", "3", "4", "Enter"], 20_500, 5), Some("1234".to_string()));
+    }
+
+    fn promo(buy: i32, get: i32, discount_type: &str, value: f64) -> Promotion {
+        Promotion {
+            id: Uuid::new_v4(),
+            name: "Deal".to_string(),
+            active: true,
+            item_id: None,
+            category_id: None,
+            trigger_item_id: None,
+            trigger_category_id: None,
+            buy_quantity: buy,
+            get_quantity: get,
+            discount_type: discount_type.to_string(),
+            discount_value: value,
+            customer_group_id: None,
+            weekdays: String::new(),
+            start_time: None,
+            end_time: None,
+            valid_from: None,
+            valid_to: None,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn promo_line(category_id: Uuid, quantity: i32, unit_price: i64) -> PromotionLine {
+        PromotionLine {
+            line_id: Uuid::new_v4(),
+            item_id: Uuid::new_v4(),
+            category_id,
+            quantity,
+            unit_price: Money(unit_price),
+            ordered_at: at("2026-10-16 12:00"),
+        }
+    }
+
+    #[test]
+    fn promotions_apply_quantity_deals_and_triggers() {
+        let (pastry, main, dessert) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+
+        // 3 for 2 on pastries: of 7 units, the cheapest of each full group of
+        // three (most expensive first) is free.
+        let mut three_for_two = promo(3, 1, "percent", 100.0);
+        three_for_two.category_id = Some(pastry);
+        let croissant = promo_line(pastry, 4, 250);
+        let muffin = promo_line(pastry, 3, 200);
+        let found = apply_promotions(&[three_for_two.clone()], &[croissant.clone(), muffin.clone()], None, "€");
+        assert_eq!(
+            found,
+            vec![
+                LineDiscount { promotion_id: three_for_two.id, line_id: croissant.line_id, amount: Money(-250) },
+                LineDiscount { promotion_id: three_for_two.id, line_id: muffin.line_id, amount: Money(-200) },
+            ]
+        );
+
+        // Buy a main, get a dessert at half price: two mains, three desserts.
+        let mut meal_deal = promo(1, 1, "percent", 50.0);
+        meal_deal.trigger_category_id = Some(main);
+        meal_deal.category_id = Some(dessert);
+        let burger = promo_line(main, 2, 1200);
+        let cake = promo_line(dessert, 1, 450);
+        let ice = promo_line(dessert, 2, 300);
+        let found = apply_promotions(&[meal_deal.clone()], &[burger, cake.clone(), ice.clone()], None, "€");
+        assert_eq!(found, vec![LineDiscount { promotion_id: meal_deal.id, line_id: ice.line_id, amount: Money(-300) }]);
+
+        // A unit gets one discount only, and the amount never exceeds the price.
+        let mut off = promo(1, 1, "amount", 5.0);
+        off.category_id = Some(pastry);
+        let found = apply_promotions(&[three_for_two.clone(), off.clone()], std::slice::from_ref(&croissant), None, "€");
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[1], LineDiscount { promotion_id: off.id, line_id: croissant.line_id, amount: Money(-750) });
+
+        // Group-only and inactive rules.
+        let mut members = promo(1, 1, "amount", 1.0);
+        members.customer_group_id = Some(Uuid::new_v4());
+        assert!(apply_promotions(&[members.clone()], std::slice::from_ref(&croissant), None, "€").is_empty());
+        assert_eq!(apply_promotions(&[members.clone()], std::slice::from_ref(&croissant), members.customer_group_id, "€").len(), 1);
+        off.active = false;
+        assert!(apply_promotions(&[off], &[croissant], None, "€").is_empty());
+    }
+
+    #[test]
+    fn promotion_time_windows() {
+        let mut happy_hour = promo(1, 1, "amount", 1.0);
+        happy_hour.weekdays = "5".to_string();
+        happy_hour.start_time = Some("17:00".to_string());
+        happy_hour.end_time = Some("19:00".to_string());
+        // 2026-10-16 is a Friday.
+        assert!(happy_hour.applies_at(at("2026-10-16 17:00")));
+        assert!(!happy_hour.applies_at(at("2026-10-16 19:00")));
+        assert!(!happy_hour.applies_at(at("2026-10-15 18:00")));
+
+        let mut late = promo(1, 1, "amount", 1.0);
+        late.start_time = Some("22:00".to_string());
+        late.end_time = Some("02:00".to_string());
+        late.valid_from = Some("2026-10-01".to_string());
+        late.valid_to = Some("2026-10-31".to_string());
+        assert!(late.applies_at(at("2026-10-16 23:30")));
+        assert!(late.applies_at(at("2026-10-17 01:59")));
+        assert!(!late.applies_at(at("2026-10-16 12:00")));
+        assert!(!late.applies_at(at("2026-11-01 23:00")));
+    }
+
+    #[test]
+    fn refunds_give_discounts_back_to_the_cent() {
+        let mut line = TransactionItemDetail {
+            id: Uuid::new_v4(),
+            item_id: Uuid::new_v4(),
+            item_name: "Beer".to_string(),
+            quantity: Quantity::units(3),
+            unit_price: Money(400),
+            total_price: Money(1200),
+            tax_rate: None,
+            note: None,
+            unit: None,
+            is_bundle: false,
+            bundle_id: None,
+            is_deposit: false,
+            deposit_for: None,
+            modifiers: Vec::new(),
+            components: Vec::new(),
+            discounts: Vec::new(),
+        };
+        line.discounts.push(TransactionDiscount {
+            transaction_item_id: line.id,
+            promotion_id: None,
+            manual_discount_id: None,
+            reason: None,
+            user_name: None,
+            name: "Happy hour".to_string(),
+            amount: Money(-100),
+        });
+        assert_eq!(line.net_total(), Money(1100));
+        let first = line.refund_discounts(Quantity::ZERO, Quantity::units(1));
+        let rest = line.refund_discounts(Quantity::units(1), Quantity::units(2));
+        assert_eq!(first[0].amount, Money(33));
+        assert_eq!(rest[0].amount, Money(67));
+        assert_eq!(discount_lines(&[line])[0].amount, Money(-100));
+    }
+
+    fn manual(target: Option<Uuid>, discount_type: &str, value: f64) -> ManualDiscount {
+        ManualDiscount {
+            id: Uuid::new_v4(),
+            transaction_id: Uuid::new_v4(),
+            transaction_item_id: target,
+            discount_type: discount_type.to_string(),
+            discount_value: value,
+            reason: "Goodwill".to_string(),
+            user_name: None,
+            approved_by: None,
+            loyalty_points: None,
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn manual_discounts_share_out_to_the_cent() {
+        let (burger, menu) = (Uuid::new_v4(), Uuid::new_v4());
+        let (fries, drink) = (Uuid::new_v4(), Uuid::new_v4());
+        let lines = vec![
+            DiscountableLine { line_id: burger, bundle_id: None, net: Money(1000) },
+            DiscountableLine { line_id: menu, bundle_id: None, net: Money::ZERO },
+            DiscountableLine { line_id: fries, bundle_id: Some(menu), net: Money(300) },
+            DiscountableLine { line_id: drink, bundle_id: Some(menu), net: Money(200) },
+        ];
+        // The sale discount comes after the line discount, whatever the order.
+        let sale = manual(None, "percent", 10.0);
+        let on_menu = manual(Some(menu), "amount", 1.0);
+        let shares = apply_manual_discounts(&[sale.clone(), on_menu.clone()], &lines, "€");
+
+        let menu_shares: Vec<Money> =
+            shares.iter().filter(|s| s.manual_discount_id == on_menu.id).map(|s| s.amount).collect();
+        assert_eq!(menu_shares, vec![Money(-60), Money(-40)]);
+        let sale_total: Money = shares.iter().filter(|s| s.manual_discount_id == sale.id).map(|s| s.amount).sum();
+        assert_eq!(sale_total, Money(-140));
+
+        // A fixed amount never takes a line below zero.
+        let too_much = manual(Some(burger), "amount", 25.0);
+        assert_eq!(apply_manual_discounts(&[too_much], &lines, "€")[0].amount, Money(-1000));
+
+        assert_eq!(discount_percent("amount", 1.5, 10.0), 15.0);
+        assert_eq!(discount_percent("percent", 20.0, 10.0), 20.0);
+    }
+
+    #[test]
+    fn gift_card_codes_are_tidied_and_masked() {
+        assert_eq!(normalize_card_code(" ab12cd34 \n"), Some("AB12CD34".to_string()));
+        assert_eq!(normalize_card_code("12-34"), None);
+        assert_eq!(normalize_card_code("123"), None);
+        assert_eq!(mask_card_code("4000123412341234"), "****1234");
+        assert_eq!(mask_card_code("12"), "****12");
+    }
+
+    #[test]
+    fn house_accounts_prefer_the_customer_and_sum_what_is_unpaid() {
+        let (customer, group) = (Uuid::new_v4(), Uuid::new_v4());
+        assert_eq!(AccountRef::of(Some(customer), Some(group)), Some(AccountRef::Customer(customer)));
+        assert_eq!(AccountRef::of(None, Some(group)), Some(AccountRef::Group(group)));
+        assert_eq!(AccountRef::of(None, None), None);
+        assert_eq!(AccountRef::Group(group).ids(), (None, Some(group)));
+
+        let charge = |amount: i64, settled: bool| AccountCharge {
+            id: Uuid::new_v4(),
+            transaction_id: Uuid::new_v4(),
+            customer_id: None,
+            customer_group_id: Some(group),
+            amount: Money(amount),
+            settlement_id: settled.then(Uuid::new_v4),
+            created_at: Utc::now(),
+            customer_name: None,
+        };
+        let charges = vec![charge(1210, false), charge(20, false), charge(-210, false), charge(5000, true)];
+        assert_eq!(AccountStatement::unpaid_of(&charges), Money(1020));
+    }
+
+    #[test]
+    fn loyalty_points_are_whole_and_refunds_take_them_back() {
+        let loyalty = LoyaltySettings { points_per_unit: 1.0, point_value: 0.05 };
+        assert_eq!(loyalty.points_for(Money(1999), "€"), 19);
+        assert_eq!(loyalty.points_for(Money(-1999), "€"), -19);
+        assert_eq!(loyalty.points_for(Money(99), "€"), 0);
+        let per_cent = LoyaltySettings { points_per_unit: 100.0, point_value: 0.01 };
+        assert_eq!(per_cent.points_for(Money(1999), "€"), 1999);
+        assert_eq!(loyalty.redemption_value(150, "€"), Money(750));
+        // Whole units only: a point per 100 units of a currency without cents.
+        let pesos = LoyaltySettings { points_per_unit: 0.01, point_value: 1.0 };
+        assert_eq!(pesos.points_for(Money(2599), "CL$"), 25);
+        assert_eq!(pesos.redemption_value(25, "CL$"), Money(25));
+    }
+
+    #[test]
+    fn deposit_liability_nets_returns_against_charges() {
+        let row = |name: &str, charged_quantity, charged, returned_quantity, returned| DepositItemReport {
+            item_name: name.to_string(),
+            charged_quantity,
+            charged: Money(charged),
+            returned_quantity,
+            returned: Money(returned),
+        };
+        let report = DepositReport::from_items(vec![
+            row("Bottle 0.25", 7, 175, 3, 75),
+            row("Crate", 1, 150, 2, 300),
+            row("Can", 30, 750, 0, 0),
+        ]);
+        assert_eq!(report.charged, Money(1075));
+        assert_eq!(report.returned, Money(375));
+        assert_eq!(report.net_liability, Money(700));
+        assert_eq!(DepositReport::from_items(Vec::new()).net_liability, Money::ZERO);
+    }
+
+    #[test]
+    fn tips_are_split_per_day_and_cashier() {
+        let tip = |day: &str, user: &str, amount| (day.to_string(), user.to_string(), Money(amount));
+        let report = TipReport::from_tips(&[
+            tip("2026-05-02", "sam", 110),
+            tip("2026-05-01", "sam", 200),
+            tip("2026-05-01", "alex", 50),
+            tip("2026-05-01", "sam", 20),
+        ]);
+        let days: Vec<(&str, &str, i64, i64)> = report
+            .days
+            .iter()
+            .map(|s| (s.day.as_str(), s.user_name.as_str(), s.transaction_count, s.amount.0))
+            .collect();
+        assert_eq!(
+            days,
+            vec![("2026-05-01", "alex", 1, 50), ("2026-05-01", "sam", 2, 220), ("2026-05-02", "sam", 1, 110)]
+        );
+        let users: Vec<(&str, i64, i64)> =
+            report.users.iter().map(|s| (s.user_name.as_str(), s.transaction_count, s.amount.0)).collect();
+        assert_eq!(users, vec![("alex", 1, 50), ("sam", 3, 330)]);
+        assert_eq!(report.total, Money(380));
+    }
+
+    #[test]
+    fn closings_sum_sales_refunds_and_cancellations() {
+        let sale = |status: &str, refund: bool, user: Option<&str>, total, tip, rounding| ClosingSale {
+            status: status.to_string(),
+            refund_of: refund.then(Uuid::new_v4),
+            closed_by: user.map(str::to_string),
+            total: Money(total),
+            tip_amount: Money(tip),
+            rounding_amount: Money(rounding),
+        };
+        let now = Utc::now();
+        let totals = ClosingTotals::from_sales(
+            now,
+            now,
+            &[
+                sale("closed", false, Some("sam"), 1250, 100, -2),
+                sale("closed", false, Some("alex"), 800, 0, 0),
+                sale("cancelled", false, None, 430, 0, 0),
+                sale("closed", true, Some("sam"), -250, 0, 0),
+                sale("closed", false, None, 300, 0, 0),
+            ],
+            Vec::new(),
+            Vec::new(),
+        );
+        assert_eq!(totals.revenue, Money(2100));
+        assert_eq!((totals.transaction_count, totals.refund_count, totals.refunds), (3, 1, Money(-250)));
+        assert_eq!((totals.cancellation_count, totals.cancellations), (1, Money(430)));
+        assert_eq!((totals.tips, totals.rounding), (Money(100), Money(-2)));
+        let users: Vec<(&str, i64, i64)> =
+            totals.users.iter().map(|u| (u.user_name.as_str(), u.transaction_count, u.amount.0)).collect();
+        assert_eq!(users, vec![("", 1, 300), ("alex", 1, 800), ("sam", 2, 1000)]);
+    }
+
+    #[test]
+    fn drawer_expects_float_movements_and_cash_taken() {
+        let movement = |kind: &str, amount| CashMovement {
+            id: Uuid::new_v4(),
+            session_id: Uuid::nil(),
+            kind: kind.to_string(),
+            amount: Money(amount),
+            reason: None,
+            user_name: "sam".to_string(),
+            created_at: Utc::now(),
+        };
+        let summary = DrawerSummary {
+            session: DrawerSession {
+                id: Uuid::nil(),
+                opened_by: "sam".to_string(),
+                opened_at: Utc::now(),
+                closed_by: None,
+                closed_at: None,
+                expected: None,
+                counted: None,
+                variance: None,
+            },
+            movements: vec![
+                movement("float", 15000),
+                movement("pay_out", 249),
+                movement("pay_in", 1000),
+                movement("safe_drop", 10000),
+                movement("pay_out", 151),
+            ],
+            cash_sales: Money(8420),
+            cash_settlements: Money(500),
+            gift_card_payouts: Money(-1200),
+            counts: Vec::new(),
+        };
+        assert_eq!(summary.movement_total("pay_out"), Money(400));
+        assert_eq!(summary.expected(), Money(15000 - 400 + 1000 - 10000 + 8420 + 500 - 1200));
+    }
+
+    #[test]
+    fn cash_is_counted_by_denomination() {
+        let euro = cash_denominations("€");
+        assert_eq!(euro.len(), 15);
+        assert_eq!((euro[0], euro[14]), (Money(50000), Money(1)));
+        let yen = cash_denominations("JPY");
+        assert_eq!((yen[0], yen[yen.len() - 1], yen.len()), (Money(500_000), Money(1), 18));
+
+        let count = |denomination, quantity| DenominationCount { denomination: Money(denomination), quantity };
+        assert_eq!(counted_total(&[count(2000, 3), count(50, 7), count(1, 0)]), Money(6350));
+        assert_eq!(counted_total(&[]), Money::ZERO);
+    }
+
+    #[test]
+    fn cash_rounding_follows_increment_and_mode() {
+        let rule = |increment, mode: &str| CashRounding { increment: Money(increment), mode: mode.to_string() };
+        let swiss = rule(5, "nearest");
+        assert_eq!(swiss.round(Money(1232)), Money(1230));
+        assert_eq!(swiss.round(Money(1233)), Money(1235));
+        assert_eq!(swiss.round(Money(1235)), Money(1235));
+        assert_eq!(swiss.round(Money(-238)), Money(-240));
+        assert_eq!(rule(10, "up").round(Money(401)), Money(410));
+        assert_eq!(rule(10, "up").round(Money(410)), Money(410));
+        assert_eq!(rule(10, "down").round(Money(419)), Money(410));
+        assert_eq!(rule(500, "nearest").round(Money(123_200)), Money(123_000));
+        assert_eq!(rule(100, "nearest").round(Money(9950)), Money(10_000));
+        assert_eq!(CashRounding::default().round(Money(1233)), Money(1233));
+    }
+
+    #[test]
+    fn tip_presets_are_whole_cents_of_the_total() {
+        assert_eq!(tip_for_percent(Money(2345), 10), Money(235));
+        assert_eq!(tip_for_percent(Money(1999), 15), Money(300));
+        assert_eq!(tip_for_percent(Money(-400), 10), Money::ZERO);
+    }
+
+    #[test]
+    fn money_is_read_and_shown_with_the_currency_decimals() {
+        assert_eq!(Money::parse("12.5", "€"), Some(Money(1250)));
+        assert_eq!(Money::parse(" 12,05 ", "€"), Some(Money(1205)));
+        assert_eq!(Money::parse("-0.3", "€"), Some(Money(-30)));
+        assert_eq!(Money::parse(".99", "$"), Some(Money(99)));
+        assert_eq!(Money::parse("1.999", "€"), None);
+        assert_eq!(Money::parse("abc", "€"), None);
+        assert_eq!(Money::parse("", "€"), None);
+        assert_eq!(Money::parse("1500", "CL$"), Some(Money(1500)));
+        assert_eq!(Money::parse("1500.5", "CL$"), None);
+
+        assert_eq!(Money(1205).format("€"), "12.05");
+        assert_eq!(Money(-5).format("€"), "-0.05");
+        assert_eq!(Money(1500).format("CL$"), "1500");
+        assert_eq!(Money::from_major(0.1 + 0.2, "€"), Money(30));
+        assert_eq!(Money(1999).to_major("€"), 19.99);
+    }
+
+    #[test]
+    fn money_rounds_half_away_from_zero() {
+        assert_eq!(Money(5).mul_div(1, 2), Money(3));
+        assert_eq!(Money(-5).mul_div(1, 2), Money(-3));
+        assert_eq!(Money(1000).mul_div(1, 3), Money(333));
+        assert_eq!(Money(1999).percent(7.5), Money(150));
+        assert_eq!(Money(-1999).percent(7.5), Money(-150));
+    }
+
+    #[test]
+    fn totals_reconcile_exactly() {
+        // Ten cent items summed the way f64 got wrong: 0.1 * 3 != 0.3.
+        let lines = [Money(10); 3];
+        assert_eq!(lines.iter().sum::<Money>(), Money(30));
+        assert_eq!(Money(10) * 3, Money(30));
+
+        // A thousand sales of 19.99 with 19% tax added: the per-sale taxes
+        // add up to the tax on the report, and net plus tax to gross.
+        let sales: Vec<Vec<TaxLine>> =
+            (0..1000).map(|_| tax_breakdown(&[(Some(19.0), Money(1999))], false)).collect();
+        let net: Money = sales.iter().flatten().map(|t| t.net).sum();
+        let tax: Money = sales.iter().flatten().map(|t| t.tax).sum();
+        let gross: Money = sales.iter().flatten().map(|t| t.gross).sum();
+        assert_eq!(net, Money(1_999_000));
+        assert_eq!(tax, Money(380_000));
+        assert_eq!(net + tax, gross);
+
+        // Extracted tax and net always add back up to the gross.
+        for cents in 1..2000 {
+            for rate in [5.5, 7.0, 10.0, 19.0, 20.0, 21.0] {
+                let t = &tax_breakdown(&[(Some(rate), Money(cents))], true)[0];
+                assert_eq!(t.net + t.tax, Money(cents));
+            }
+        }
+
+        // Bundle shares and manual discount shares add up to what is shared.
+        for total in 1..500 {
+            let shares = allocate_bundle_price(Money(total), &[Money(333), Money(333), Money(334)]);
+            assert_eq!(shares.iter().sum::<Money>(), Money(total));
+        }
+
+        // Refunding a discounted line unit by unit gives the whole discount back.
+        let line = TransactionItemDetail {
+            id: Uuid::new_v4(),
+            item_id: Uuid::new_v4(),
+            item_name: "Tea".to_string(),
+            quantity: Quantity::units(7),
+            unit_price: Money(330),
+            total_price: Money(2310),
+            tax_rate: None,
+            note: None,
+            unit: None,
+            is_bundle: false,
+            bundle_id: None,
+            is_deposit: false,
+            deposit_for: None,
+            modifiers: Vec::new(),
+            components: Vec::new(),
+            discounts: vec![TransactionDiscount {
+                transaction_item_id: Uuid::nil(),
+                promotion_id: None,
+                manual_discount_id: None,
+                reason: None,
+                user_name: None,
+                name: "Staff".to_string(),
+                amount: Money(-231),
+            }],
+        };
+        let given_back: Money = (0..7).flat_map(|n| line.refund_discounts(Quantity::units(n), Quantity::ONE)).map(|d| d.amount).sum();
+        assert_eq!(given_back, Money(231));
+    }
+}
@@ -0,0 +1,302 @@
+use chrono::{DateTime, Datelike, Utc};
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::server_fns::*;
+
+#[cfg(not(target_arch = "wasm32"))]
+fn trigger_pdf_download(_pdf_b64: &str, _filename: &str) {}
+
+#[cfg(target_arch = "wasm32")]
+fn trigger_pdf_download(pdf_b64: &str, filename: &str) {
+    use wasm_bindgen::prelude::*;
+    let doc = leptos::prelude::document();
+    let a: web_sys::HtmlAnchorElement = doc.create_element("a").unwrap().unchecked_into();
+    let href = format!("data:application/pdf;base64,{}", pdf_b64);
+    a.set_href(&href);
+    a.set_download(filename);
+    a.click();
+}
+
+/// The statement period for the picked dates: from the start of the first
+/// day to the end of the last.
+fn period_bounds(start: &str, end: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let start = start.parse::<chrono::NaiveDate>().ok()?.and_hms_opt(0, 0, 0)?;
+    let end = end.parse::<chrono::NaiveDate>().ok()?.succ_opt()?.and_hms_opt(0, 0, 0)?;
+    Some((
+        DateTime::<Utc>::from_naive_utc_and_offset(start, Utc),
+        DateTime::<Utc>::from_naive_utc_and_offset(end, Utc),
+    ))
+}
+
+fn statement_labels(i: &I18n) -> StatementLabels {
+    StatementLabels {
+        title: i.t("accounts.statement"),
+        period: i.t("accounts.period"),
+        date: i.t("accounts.date"),
+        description: i.t("accounts.sale"),
+        amount: i.t("accounts.amount"),
+        status: i.t("accounts.status"),
+        paid: i.t("accounts.paid"),
+        open: i.t("accounts.open"),
+        settlements: i.t("accounts.settlements"),
+        unpaid: i.t("accounts.unpaid"),
+        outstanding: i.t("accounts.outstanding"),
+        tenders: TENDERS.iter().map(|t| (t.to_string(), i.t(&format!("sale.tender_{}", t)))).collect(),
+    }
+}
+
+#[component]
+pub fn AccountsPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let (authorized, set_authorized) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" => set_authorized.set(true),
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let (accounts, set_accounts) = signal(Vec::<AccountBalance>::new());
+    let (selected, set_selected) = signal(Option::<AccountRef>::None);
+    let (statement, set_statement) = signal(Option::<AccountStatement>::None);
+    let (start_date, set_start_date) = signal(String::new());
+    let (end_date, set_end_date) = signal(String::new());
+    let (settle_tender, set_settle_tender) = signal("cash".to_string());
+    let (confirming_settle, set_confirming_settle) = signal(false);
+    let (error, set_error) = signal(Option::<String>::None);
+    let (generating_pdf, set_generating_pdf) = signal(false);
+
+    // The period defaults to the current month so far.
+    Effect::new(move || {
+        let today = Utc::now().date_naive();
+        set_start_date.set(today.with_day(1).unwrap_or(today).format("%Y-%m-%d").to_string());
+        set_end_date.set(today.format("%Y-%m-%d").to_string());
+    });
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            if let Ok(a) = fetch_accounts().await { set_accounts.set(a); }
+        });
+    });
+
+    let load_statement = move || {
+        let Some(account) = selected.get_untracked() else { return };
+        let Some((start, end)) = period_bounds(&start_date.get_untracked(), &end_date.get_untracked()) else {
+            set_error.set(Some(i18n.get_untracked().t("accounts.invalid_period")));
+            return;
+        };
+        leptos::task::spawn_local(async move {
+            match fetch_account_statement(account, start, end).await {
+                Ok(s) => {
+                    set_statement.set(Some(s));
+                    set_error.set(None);
+                }
+                Err(e) => set_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let open_account = move |account: AccountRef| {
+        set_selected.set(Some(account));
+        set_confirming_settle.set(false);
+        load_statement();
+    };
+
+    let settle = move |_| {
+        let Some(s) = statement.get() else { return };
+        let charge_ids: Vec<Uuid> = s.charges.iter().filter(|c| c.settlement_id.is_none()).map(|c| c.id).collect();
+        let tender = settle_tender.get();
+        set_confirming_settle.set(false);
+        leptos::task::spawn_local(async move {
+            match settle_account(s.account, charge_ids, tender).await {
+                Ok(_) => {
+                    set_reload.update(|v| *v += 1);
+                    load_statement();
+                }
+                Err(e) => set_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let download_pdf = move |_| {
+        let Some(s) = statement.get() else { return };
+        let labels = statement_labels(&i18n.get());
+        let filename = format!("statement-{}-{}.pdf", s.name.replace(' ', "_"), s.start_date.format("%Y-%m-%d"));
+        set_generating_pdf.set(true);
+        leptos::task::spawn_local(async move {
+            match generate_account_statement_pdf(s.account, s.start_date, s.end_date, labels).await {
+                Ok(pdf_b64) => trigger_pdf_download(&pdf_b64, &filename),
+                Err(e) => set_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+            set_generating_pdf.set(false);
+        });
+    };
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div>
+            <div class="page-header">
+                <h2>{move || i18n.get().t("accounts.title")}</h2>
+            </div>
+            <p class="text-muted">{move || i18n.get().t("accounts.hint")}</p>
+
+            <Show when=move || accounts.get().is_empty() fallback=|| ()>
+                <p class="text-muted">{move || i18n.get().t("accounts.none")}</p>
+            </Show>
+
+            <table class="data-table">
+                <thead><tr>
+                    <th>{move || i18n.get().t("accounts.account")}</th>
+                    <th>{move || i18n.get().t("accounts.outstanding")}</th>
+                    <th></th>
+                </tr></thead>
+                <tbody>
+                    <For each=move || accounts.get() key=|a| (a.account, a.name.clone(), a.outstanding.0) let:a>
+                        {
+                            let account = a.account;
+                            let kind = match account {
+                                AccountRef::Customer(_) => "accounts.kind_customer",
+                                AccountRef::Group(_) => "accounts.kind_group",
+                            };
+                            view! {
+                                <tr>
+                                    <td>{a.name.clone()}" "<span class="text-muted">{move || format!("({})", i18n.get().t(kind))}</span></td>
+                                    <td>{move || currency.with(|c| format!("{} {}", c, a.outstanding.format(c)))}</td>
+                                    <td class="data-table-actions">
+                                        <button class="btn-small" on:click=move |_| open_account(account)
+                                        >{move || i18n.get().t("accounts.statement")}</button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+
+            <Show when=move || error.get().is_some() fallback=|| ()>
+                <p class="error-message">{move || error.get().unwrap_or_default()}</p>
+            </Show>
+
+            <Show when=move || selected.get().is_some() fallback=|| ()>
+                <div class="edit-form">
+                    <div class="form-grid">
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("reports.start_date")}</label>
+                            <input type="date" prop:value=move || start_date.get() on:input=move |ev| set_start_date.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("reports.end_date")}</label>
+                            <input type="date" prop:value=move || end_date.get() on:input=move |ev| set_end_date.set(event_target_value(&ev)) />
+                        </div>
+                    </div>
+                    <div class="form-actions">
+                        <button class="btn-primary" on:click=move |_| load_statement()>{move || i18n.get().t("accounts.show")}</button>
+                        <button class="btn-secondary" on:click=download_pdf
+                            disabled=move || statement.get().is_none() || generating_pdf.get()
+                        >{move || if generating_pdf.get() { i18n.get().t("categories.generating_menu") } else { i18n.get().t("accounts.download_pdf") }}</button>
+                    </div>
+
+                    {move || statement.get().map(|s| {
+                        let cur = currency.get();
+                        let i = i18n.get();
+                        let unpaid = s.unpaid;
+                        let has_settlements = !s.settlements.is_empty();
+                        view! {
+                            <h3>{format!("{}: {}", i.t("accounts.statement"), s.name)}</h3>
+                            <table class="data-table">
+                                <thead><tr>
+                                    <th>{i.t("accounts.date")}</th>
+                                    <th>{i.t("accounts.sale")}</th>
+                                    <th>{i.t("accounts.amount")}</th>
+                                    <th>{i.t("accounts.status")}</th>
+                                </tr></thead>
+                                <tbody>
+                                    {s.charges.iter().map(|c| view! {
+                                        <tr>
+                                            <td>{c.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()}</td>
+                                            <td>{c.customer_name.clone().unwrap_or_else(|| i.t("general.walkin"))}</td>
+                                            <td>{format!("{} {}", cur, c.amount.format(&cur))}</td>
+                                            <td>{if c.settlement_id.is_some() { i.t("accounts.paid") } else { i.t("accounts.open") }}</td>
+                                        </tr>
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                            <Show when=move || has_settlements fallback=|| ()>
+                                <h4>{i18n.get().t("accounts.settlements")}</h4>
+                            </Show>
+                            <table class="data-table">
+                                <tbody>
+                                    {s.settlements.iter().map(|p| view! {
+                                        <tr>
+                                            <td>{p.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()}</td>
+                                            <td>{i.t(&format!("sale.tender_{}", p.tender))}</td>
+                                            <td>{format!("{} {}", cur, p.amount.format(&cur))}</td>
+                                            <td>{p.user_name.clone().unwrap_or_default()}</td>
+                                        </tr>
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                            <div class="transaction-total">
+                                <span>{i.t("accounts.unpaid")}</span>
+                                <strong>{format!("{} {}", cur, s.unpaid.format(&cur))}</strong>
+                            </div>
+                            <div class="transaction-total">
+                                <span>{i.t("accounts.outstanding")}</span>
+                                <strong>{format!("{} {}", cur, s.outstanding.format(&cur))}</strong>
+                            </div>
+                            <Show when=move || { !unpaid.is_zero() } fallback=|| ()>
+                                <div class="admin-input-row">
+                                    <select prop:value=move || settle_tender.get()
+                                        on:change=move |ev| set_settle_tender.set(event_target_value(&ev))
+                                    >
+                                        {TENDERS.into_iter().filter(|t| *t != "account" && *t != "gift_card").map(|t| view! {
+                                            <option value=t>{move || i18n.get().t(&format!("sale.tender_{}", t))}</option>
+                                        }).collect_view()}
+                                    </select>
+                                    <button class="btn-success" on:click=move |_| set_confirming_settle.set(true)
+                                    >{move || i18n.get().t("accounts.settle")}</button>
+                                </div>
+                            </Show>
+                        }
+                    })}
+                </div>
+            </Show>
+
+            <Show when=move || confirming_settle.get() fallback=|| ()>
+                <div class="modal-overlay">
+                    <div class="confirmation-modal">
+                        <h3>{move || i18n.get().t("accounts.settle")}</h3>
+                        <p>{move || {
+                            let amount = statement.get().map(|s| s.unpaid).unwrap_or_default();
+                            i18n.get().t("accounts.confirm_settle")
+                                .replace("{amount}", &currency.with(|c| format!("{} {}", c, amount.format(c))))
+                                .replace("{tender}", &i18n.get().t(&format!("sale.tender_{}", settle_tender.get())))
+                        }}</p>
+                        <div class="modal-actions">
+                            <button class="btn-success" on:click=settle>{move || i18n.get().t("accounts.settle")}</button>
+                            <button class="btn-secondary" on:click=move |_| set_confirming_settle.set(false)>{move || i18n.get().t("general.cancel")}</button>
+                        </div>
+                    </div>
+                </div>
+            </Show>
+        </div>
+        </Show>
+    }
+}
@@ -1,4 +1,5 @@
 use leptos::prelude::*;
+use uuid::Uuid;
 
 use crate::i18n::{available_currencies, available_languages, I18n};
 use crate::models::*;
@@ -494,9 +495,24 @@ pub fn AdminPage() -> impl IntoView {
         // Currency setting section
         <CurrencySettings i18n=i18n />
 
+        // Tax rates and tax mode
+        <TaxSettings i18n=i18n />
+
+        // Rounding of cash payments
+        <CashRoundingSettings i18n=i18n />
+
+        // Verification of the tamper-evident journal
+        <JournalSettings i18n=i18n />
+
+        // Business and register details for the tax audit export
+        <BusinessSettings i18n=i18n />
+
         // Remote printer passphrase setting
         <PrinterPassphraseSettings i18n=i18n />
 
+        // Serial scale for goods sold by weight
+        <ScaleSettings i18n=i18n />
+
         // System network information
         <SystemInfoSettings i18n=i18n />
 
@@ -508,12 +524,19 @@ pub fn AdminPage() -> impl IntoView {
 fn CurrencySettings(i18n: RwSignal<I18n>) -> impl IntoView {
     let currency_ctx = expect_context::<RwSignal<String>>();
     let (custom_input, set_custom_input) = signal(String::new());
+    let (status_msg, set_status_msg) = signal(Option::<String>::None);
 
+    // Only switch once the server accepted it: the decimal places cannot
+    // change once sales are journaled.
     let set_currency = move |sym: String| {
-        let sym_clone = sym.clone();
-        currency_ctx.set(sym);
         leptos::task::spawn_local(async move {
-            let _ = set_currency_admin(sym_clone).await;
+            match set_currency_admin(sym.clone()).await {
+                Ok(()) => {
+                    currency_ctx.set(sym);
+                    set_status_msg.set(None);
+                }
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
         });
     };
 
@@ -558,6 +581,372 @@ fn CurrencySettings(i18n: RwSignal<I18n>) -> impl IntoView {
                     </button>
                 </div>
             </div>
+
+            <Show when=move || status_msg.get().is_some() fallback=|| ()>
+                <p class="error-message">{move || status_msg.get().unwrap_or_default()}</p>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+fn TaxSettings(i18n: RwSignal<I18n>) -> impl IntoView {
+    let (rates, set_rates) = signal(Vec::<TaxRate>::new());
+    let (inclusive, set_inclusive) = signal(true);
+    // Rate being edited in the form; None while adding a new one.
+    let (editing, set_editing) = signal(Option::<Uuid>::None);
+    let (name_input, set_name_input) = signal(String::new());
+    let (rate_input, set_rate_input) = signal(String::new());
+    let (status_msg, set_status_msg) = signal(Option::<String>::None);
+    let (reload, set_reload) = signal(0u32);
+
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            if let Ok(r) = fetch_tax_rates().await {
+                set_rates.set(r);
+            }
+        });
+    });
+
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            if let Ok(incl) = get_tax_inclusive().await {
+                set_inclusive.set(incl);
+            }
+        });
+    });
+
+    let change_mode = move |ev| {
+        let incl = event_target_value(&ev) == "inclusive";
+        set_inclusive.set(incl);
+        leptos::task::spawn_local(async move {
+            let _ = set_tax_inclusive(incl).await;
+        });
+    };
+
+    let reset_form = move || {
+        set_editing.set(None);
+        set_name_input.set(String::new());
+        set_rate_input.set(String::new());
+    };
+
+    let submit_rate = move |_| {
+        let name = name_input.get().trim().to_string();
+        let Ok(rate) = rate_input.get().trim().replace(',', ".").parse::<f64>() else {
+            set_status_msg.set(Some(i18n.get().t("tax.invalid_rate")));
+            return;
+        };
+        let editing_id = editing.get();
+        leptos::task::spawn_local(async move {
+            let result = match editing_id {
+                Some(id) => update_tax_rate(id, name, rate).await.map(|_| ()),
+                None => create_tax_rate(name, rate).await.map(|_| ()),
+            };
+            match result {
+                Ok(()) => {
+                    reset_form();
+                    set_status_msg.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    let start_edit = move |rate: TaxRate| {
+        set_editing.set(Some(rate.id));
+        set_name_input.set(rate.name);
+        set_rate_input.set(rate.rate.to_string());
+    };
+
+    let remove_rate = move |id: Uuid| {
+        leptos::task::spawn_local(async move {
+            match delete_tax_rate(id).await {
+                Ok(()) => set_reload.update(|v| *v += 1),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    view! {
+        <div class="admin-page" style="margin-top: 2rem;">
+            <h2>{move || i18n.get().t("tax.settings")}</h2>
+
+            <div class="currency-custom">
+                <label>{move || i18n.get().t("tax.mode")}</label>
+                <p style="margin: 0.25rem 0; color: #888; font-size: 0.85rem;">
+                    {move || i18n.get().t("tax.mode_hint")}
+                </p>
+                <select
+                    prop:value=move || if inclusive.get() { "inclusive" } else { "exclusive" }
+                    on:change=change_mode
+                >
+                    <option value="inclusive">{move || i18n.get().t("tax.mode_inclusive")}</option>
+                    <option value="exclusive">{move || i18n.get().t("tax.mode_exclusive")}</option>
+                </select>
+            </div>
+
+            <table class="data-table" style="margin-top: 1.5rem;">
+                <thead>
+                    <tr>
+                        <th>{move || i18n.get().t("tax.name")}</th>
+                        <th>{move || i18n.get().t("tax.rate")}</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <For each=move || rates.get() key=|r| (r.id, r.name.clone(), r.rate.to_bits()) let:rate>
+                        {
+                            let rate_id = rate.id;
+                            let rate_clone = rate.clone();
+                            view! {
+                                <tr>
+                                    <td>{rate.name.clone()}</td>
+                                    <td>{format!("{}%", rate.rate)}</td>
+                                    <td class="data-table-actions">
+                                        <button class="btn-small" on:click=move |_| start_edit(rate_clone.clone())>
+                                            {move || i18n.get().t("general.edit")}
+                                        </button>
+                                        <button class="btn-small btn-danger" on:click=move |_| remove_rate(rate_id)>
+                                            {move || i18n.get().t("general.delete")}
+                                        </button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+
+            <div class="currency-custom">
+                <label>{move || if editing.get().is_some() { i18n.get().t("tax.edit") } else { i18n.get().t("tax.add") }}</label>
+                <div class="currency-custom-row">
+                    <input
+                        type="text"
+                        placeholder=move || i18n.get().t("tax.name")
+                        on:input=move |ev| set_name_input.set(event_target_value(&ev))
+                        prop:value=move || name_input.get()
+                    />
+                    <input
+                        type="text"
+                        inputmode="decimal"
+                        placeholder=move || i18n.get().t("tax.rate_placeholder")
+                        on:input=move |ev| set_rate_input.set(event_target_value(&ev))
+                        prop:value=move || rate_input.get()
+                    />
+                    <button class="btn-primary" on:click=submit_rate>
+                        {move || i18n.get().t("general.save")}
+                    </button>
+                    <Show when=move || editing.get().is_some() fallback=|| ()>
+                        <button class="btn-secondary" on:click=move |_| reset_form()>
+                            {move || i18n.get().t("general.cancel")}
+                        </button>
+                    </Show>
+                </div>
+            </div>
+
+            <Show when=move || status_msg.get().is_some() fallback=|| ()>
+                <p class="error-message">{move || status_msg.get().unwrap_or_default()}</p>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+fn CashRoundingSettings(i18n: RwSignal<I18n>) -> impl IntoView {
+    let currency = expect_context::<RwSignal<String>>();
+    let (increment_input, set_increment_input) = signal(String::new());
+    let (mode, set_mode) = signal("nearest".to_string());
+    let (status_msg, set_status_msg) = signal(Option::<String>::None);
+
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            if let Ok(rounding) = get_cash_rounding().await {
+                if rounding.increment.is_positive() {
+                    set_increment_input.set(rounding.increment.format(&currency.get_untracked()));
+                }
+                set_mode.set(rounding.mode);
+            }
+        });
+    });
+
+    let save = move |_| {
+        let text = increment_input.get();
+        let increment = if text.trim().is_empty() { Some(Money::ZERO) } else { Money::parse(&text, &currency.get_untracked()) };
+        let Some(increment) = increment else {
+            set_status_msg.set(Some(i18n.get().t("rounding.invalid")));
+            return;
+        };
+        let rounding = CashRounding { increment, mode: mode.get() };
+        leptos::task::spawn_local(async move {
+            match set_cash_rounding(rounding).await {
+                Ok(()) => set_status_msg.set(Some(i18n.get().t("rounding.saved"))),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    view! {
+        <div class="admin-page" style="margin-top: 2rem;">
+            <h2>{move || i18n.get().t("rounding.settings")}</h2>
+            <p style="margin: 0.25rem 0; color: #888; font-size: 0.85rem;">
+                {move || i18n.get().t("rounding.hint")}
+            </p>
+            <div class="currency-custom-row">
+                <input
+                    type="text"
+                    inputmode="decimal"
+                    placeholder=move || i18n.get().t("rounding.increment_placeholder")
+                    on:input=move |ev| set_increment_input.set(event_target_value(&ev))
+                    prop:value=move || increment_input.get()
+                />
+                <select
+                    prop:value=move || mode.get()
+                    on:change=move |ev| set_mode.set(event_target_value(&ev))
+                >
+                    {ROUNDING_MODES.into_iter().map(|m| view! {
+                        <option value=m>{move || i18n.get().t(&format!("rounding.mode_{}", m))}</option>
+                    }).collect_view()}
+                </select>
+                <button class="btn-primary" on:click=save>
+                    {move || i18n.get().t("general.save")}
+                </button>
+            </div>
+
+            <Show when=move || status_msg.get().is_some() fallback=|| ()>
+                <p class="text-muted">{move || status_msg.get().unwrap_or_default()}</p>
+            </Show>
+        </div>
+    }
+}
+
+#[component]
+fn JournalSettings(i18n: RwSignal<I18n>) -> impl IntoView {
+    let (result, set_result) = signal(Option::<Result<JournalVerification, String>>::None);
+    let (verifying, set_verifying) = signal(false);
+    let (outage, set_outage) = signal(false);
+
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            if let Ok(outage) = get_fiscal_outage().await {
+                set_outage.set(outage);
+            }
+        });
+    });
+
+    let toggle_outage = move |ev| {
+        let outage = event_target_checked(&ev);
+        set_outage.set(outage);
+        leptos::task::spawn_local(async move {
+            let _ = set_fiscal_outage(outage).await;
+        });
+    };
+
+    let verify = move |_| {
+        set_verifying.set(true);
+        leptos::task::spawn_local(async move {
+            set_result.set(Some(verify_journal().await.map_err(|e| e.to_string())));
+            set_verifying.set(false);
+        });
+    };
+
+    view! {
+        <div class="admin-page" style="margin-top: 2rem;">
+            <h2>{move || i18n.get().t("journal.settings")}</h2>
+            <p style="margin: 0.25rem 0; color: #888; font-size: 0.85rem;">
+                {move || i18n.get().t("journal.hint")}
+            </p>
+            <button class="btn-primary" on:click=verify disabled=move || verifying.get()>
+                {move || i18n.get().t("journal.verify")}
+            </button>
+            {move || result.get().map(|result| match result {
+                Ok(v) if v.is_ok() => view! {
+                    <p class="text-muted">{i18n.get().t("journal.intact").replace("{count}", &v.entries.to_string())}</p>
+                }.into_any(),
+                Ok(v) => view! {
+                    <p class="error-message">{i18n.get().t("journal.problems").replace("{count}", &v.problems.len().to_string())}</p>
+                    <ul>
+                        {v.problems.iter().map(|p| view! { <li>{p.message()}</li> }).collect_view()}
+                    </ul>
+                }.into_any(),
+                Err(e) => view! { <p class="error-message">{e}</p> }.into_any(),
+            })}
+
+            <div class="currency-custom" style="margin-top: 1.5rem;">
+                <label>
+                    <input type="checkbox"
+                        prop:checked=move || outage.get()
+                        on:change=toggle_outage
+                    />
+                    " " {move || i18n.get().t("fiscal.outage")}
+                </label>
+                <p style="margin: 0.25rem 0; color: #888; font-size: 0.85rem;">
+                    {move || i18n.get().t("fiscal.outage_hint")}
+                </p>
+            </div>
+        </div>
+    }
+}
+
+#[component]
+fn BusinessSettings(i18n: RwSignal<I18n>) -> impl IntoView {
+    let details = RwSignal::new(BusinessDetails::default());
+    let (status_msg, set_status_msg) = signal(Option::<String>::None);
+
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            if let Ok(loaded) = get_business_details().await {
+                details.set(loaded);
+            }
+        });
+    });
+
+    let save = move |_| {
+        let current = details.get();
+        leptos::task::spawn_local(async move {
+            match set_business_details(current).await {
+                Ok(()) => set_status_msg.set(Some(i18n.get().t("business.saved"))),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    let field = move |label: &'static str, get: fn(&BusinessDetails) -> String, set: fn(&mut BusinessDetails, String)| {
+        view! {
+            <div class="admin-form-field">
+                <label>{move || i18n.get().t(label)}</label>
+                <input type="text"
+                    prop:value=move || details.with(get)
+                    on:input=move |ev| details.update(|d| set(d, event_target_value(&ev)))
+                />
+            </div>
+        }
+    };
+
+    view! {
+        <div class="admin-page" style="margin-top: 2rem;">
+            <h2>{move || i18n.get().t("business.settings")}</h2>
+            <p style="margin: 0.25rem 0; color: #888; font-size: 0.85rem;">
+                {move || i18n.get().t("business.hint")}
+            </p>
+            <div class="admin-form">
+                {field("business.name", |d| d.name.clone(), |d, v| d.name = v)}
+                {field("business.street", |d| d.street.clone(), |d, v| d.street = v)}
+                {field("business.postcode", |d| d.postcode.clone(), |d, v| d.postcode = v)}
+                {field("business.city", |d| d.city.clone(), |d, v| d.city = v)}
+                {field("business.country", |d| d.country.clone(), |d, v| d.country = v)}
+                {field("business.tax_number", |d| d.tax_number.clone(), |d, v| d.tax_number = v)}
+                {field("business.vat_id", |d| d.vat_id.clone(), |d, v| d.vat_id = v)}
+                {field("business.register_id", |d| d.register_id.clone(), |d, v| d.register_id = v)}
+                <button class="btn-primary" on:click=save>
+                    {move || i18n.get().t("general.save")}
+                </button>
+            </div>
+
+            <Show when=move || status_msg.get().is_some() fallback=|| ()>
+                <p class="text-muted">{move || status_msg.get().unwrap_or_default()}</p>
+            </Show>
         </div>
     }
 }
@@ -734,6 +1123,83 @@ fn PrinterPassphraseSettings(i18n: RwSignal<I18n>) -> impl IntoView {
     }
 }
 
+#[component]
+fn ScaleSettings(i18n: RwSignal<I18n>) -> impl IntoView {
+    let (port, set_port) = signal(String::new());
+    let (ports, set_ports) = signal(Vec::<String>::new());
+    let (status_msg, set_status_msg) = signal(Option::<String>::None);
+
+    // Load the configured port and the serial ports found on the server
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            if let Ok(p) = get_scale_port().await {
+                set_port.set(p.unwrap_or_default());
+            }
+            if let Ok(list) = list_serial_ports().await {
+                set_ports.set(list);
+            }
+        });
+    });
+
+    let save_port = move |_| {
+        let value = port.get();
+        leptos::task::spawn_local(async move {
+            match set_scale_port(value).await {
+                Ok(()) => set_status_msg.set(Some(i18n.get().t("admin.scale_port_updated"))),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    let test_scale = move |_| {
+        set_status_msg.set(None);
+        leptos::task::spawn_local(async move {
+            match read_scale_weight().await {
+                Ok(kg) => set_status_msg.set(Some(format!("{:.3} kg", kg))),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    view! {
+        <div class="admin-page" style="margin-top: 2rem;">
+            <h2>{move || i18n.get().t("admin.scale_settings")}</h2>
+            <p style="margin: 0.25rem 0; color: #888; font-size: 0.85rem;">
+                {move || i18n.get().t("admin.scale_port_hint")}
+            </p>
+            <div class="currency-custom-row">
+                <select
+                    prop:value=move || port.get()
+                    on:change=move |ev| set_port.set(event_target_value(&ev))
+                >
+                    <option value="">{move || i18n.get().t("admin.scale_none")}</option>
+                    // Keep a configured port selectable while it is unplugged.
+                    {move || {
+                        let mut list = ports.get();
+                        let current = port.get();
+                        if !current.is_empty() && !list.contains(&current) {
+                            list.push(current);
+                        }
+                        list.into_iter().map(|p| view! { <option value=p.clone()>{p.clone()}</option> }).collect_view()
+                    }}
+                </select>
+                <button class="btn-primary" on:click=save_port>
+                    {move || i18n.get().t("general.save")}
+                </button>
+                <button class="btn-secondary" on:click=test_scale>
+                    {move || i18n.get().t("admin.scale_test")}
+                </button>
+            </div>
+
+            <Show when=move || status_msg.get().is_some() fallback=|| ()>
+                <p style="margin-top: 0.5rem; color: #27ae60; font-weight: bold;">
+                    {move || status_msg.get().unwrap_or_default()}
+                </p>
+            </Show>
+        </div>
+    }
+}
+
 #[component]
 fn SystemInfoSettings(i18n: RwSignal<I18n>) -> impl IntoView {
     let (addresses, set_addresses) = signal(Vec::<String>::new());
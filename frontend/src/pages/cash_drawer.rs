@@ -0,0 +1,323 @@
+use leptos::prelude::*;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::server_fns::*;
+
+fn local_time(t: chrono::DateTime<chrono::Utc>) -> String {
+    t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// The drawer session: opening float, pay-ins, pay-outs and safe drops, and
+/// the count by denomination that closes it.
+#[component]
+pub fn CashDrawerPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let (authorized, set_authorized) = signal(false);
+    let (is_admin, set_is_admin) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" || u.role == "cashier" => {
+                    set_is_admin.set(u.role == "admin");
+                    set_authorized.set(true);
+                }
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let money = move |m: Money| currency.with(|c| format!("{} {}", c, m.format(c)));
+
+    let (drawer, set_drawer) = signal(Option::<DrawerSummary>::None);
+    let (sessions, set_sessions) = signal(Vec::<DrawerSession>::new());
+    let (status_msg, set_status_msg) = signal(Option::<String>::None);
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        // The history is for admins only.
+        let admin = is_admin.get();
+        leptos::task::spawn_local(async move {
+            match fetch_drawer().await {
+                Ok(d) => set_drawer.set(d),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+            if admin && let Ok(s) = fetch_drawer_sessions().await {
+                set_sessions.set(s);
+            }
+        });
+    });
+
+    // Opening float
+    let (float_text, set_float_text) = signal(String::new());
+    let open_drawer = move |_| {
+        let text = float_text.get();
+        let float = if text.trim().is_empty() { Some(Money::ZERO) } else { Money::parse(&text, &currency.get_untracked()) };
+        let Some(float) = float else {
+            set_status_msg.set(Some(i18n.get().t("drawer.invalid_amount")));
+            return;
+        };
+        set_status_msg.set(None);
+        leptos::task::spawn_local(async move {
+            match open_drawer_session(float).await {
+                Ok(d) => {
+                    set_float_text.set(String::new());
+                    set_drawer.set(Some(d));
+                }
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    // Pay-ins, pay-outs and safe drops
+    let (kind, set_kind) = signal("pay_out".to_string());
+    let (amount_text, set_amount_text) = signal(String::new());
+    let (reason, set_reason) = signal(String::new());
+    let add_movement = move |_| {
+        let Some(amount) = Money::parse(&amount_text.get(), &currency.get_untracked()) else {
+            set_status_msg.set(Some(i18n.get().t("drawer.invalid_amount")));
+            return;
+        };
+        let k = kind.get();
+        let r = reason.get();
+        set_status_msg.set(None);
+        leptos::task::spawn_local(async move {
+            match add_cash_movement(k, amount, Some(r)).await {
+                Ok(d) => {
+                    set_amount_text.set(String::new());
+                    set_reason.set(String::new());
+                    set_drawer.set(Some(d));
+                }
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    // Count by denomination; quantities are kept per denomination as typed.
+    let denominations = Memo::new(move |_| cash_denominations(&currency.get()));
+    let (quantities, set_quantities) = signal(Vec::<(Money, String)>::new());
+    let counts = move || -> Vec<DenominationCount> {
+        quantities
+            .get()
+            .iter()
+            .filter_map(|(denomination, text)| {
+                let quantity = text.trim().parse::<i64>().ok()?;
+                Some(DenominationCount { denomination: *denomination, quantity })
+            })
+            .collect()
+    };
+    let set_quantity = move |denomination: Money, text: String| {
+        set_quantities.update(|q| match q.iter_mut().find(|(d, _)| *d == denomination) {
+            Some(entry) => entry.1 = text,
+            None => q.push((denomination, text)),
+        });
+    };
+    let counted = move || counted_total(&counts());
+    let (confirming_count, set_confirming_count) = signal(false);
+    let save_count = move |_| {
+        set_confirming_count.set(false);
+        let c = counts();
+        set_status_msg.set(None);
+        leptos::task::spawn_local(async move {
+            match count_drawer(c).await {
+                Ok(d) => {
+                    let variance = d.session.variance.unwrap_or_default();
+                    let variance = currency.with_untracked(|c| format!("{} {}", c, variance.format(c)));
+                    let msg = i18n.get_untracked().t("drawer.counted").replace("{variance}", &variance);
+                    set_status_msg.set(Some(msg));
+                    set_quantities.set(Vec::new());
+                    set_reload.update(|v| *v += 1);
+                }
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div class="reports-page">
+            <h2>{move || i18n.get().t("drawer.title")}</h2>
+            <p class="text-muted">{move || i18n.get().t("drawer.hint")}</p>
+            <Show when=move || status_msg.get().is_some() fallback=|| ()>
+                <p class="text-muted">{move || status_msg.get().unwrap_or_default()}</p>
+            </Show>
+
+            <Show when=move || drawer.get().is_none() fallback=|| ()>
+                <div class="currency-custom">
+                    <label>{move || i18n.get().t("drawer.float")}</label>
+                    <p class="text-muted">{move || i18n.get().t("drawer.float_hint")}</p>
+                    <div class="currency-custom-row">
+                        <input type="text" inputmode="decimal"
+                            prop:value=move || float_text.get()
+                            on:input=move |ev| set_float_text.set(event_target_value(&ev)) />
+                        <button class="btn-primary" on:click=open_drawer>{move || i18n.get().t("drawer.open")}</button>
+                    </div>
+                </div>
+            </Show>
+
+            {move || drawer.get().map(|d| {
+                let i = i18n.get();
+                let expected = d.expected();
+                view! {
+                    <div class="report-content">
+                        <p class="text-muted">{i.t("drawer.opened").replace("{user}", &d.session.opened_by).replace("{time}", &local_time(d.session.opened_at))}</p>
+                        <table class="data-table">
+                            <tbody>
+                                <tr><td>{i.t("drawer.kind_float")}</td><td>{money(d.movement_total("float"))}</td></tr>
+                                <tr><td>{i.t("drawer.cash_sales")}</td><td>{money(d.cash_sales)}</td></tr>
+                                {(!d.cash_settlements.is_zero()).then(|| view! {
+                                    <tr><td>{i.t("drawer.cash_settlements")}</td><td>{money(d.cash_settlements)}</td></tr>
+                                })}
+                                {(!d.gift_card_payouts.is_zero()).then(|| view! {
+                                    <tr><td>{i.t("drawer.gift_card_payouts")}</td><td>{money(d.gift_card_payouts)}</td></tr>
+                                })}
+                                <tr><td>{i.t("drawer.kind_pay_in")}</td><td>{money(d.movement_total("pay_in"))}</td></tr>
+                                <tr><td>{i.t("drawer.kind_pay_out")}</td><td>{money(-d.movement_total("pay_out"))}</td></tr>
+                                <tr><td>{i.t("drawer.kind_safe_drop")}</td><td>{money(-d.movement_total("safe_drop"))}</td></tr>
+                            </tbody>
+                            <tfoot>
+                                <tr class="table-footer"><td><strong>{i.t("drawer.expected")}</strong></td><td><strong>{money(expected)}</strong></td></tr>
+                            </tfoot>
+                        </table>
+
+                        <h3>{i.t("drawer.movements")}</h3>
+                        <table class="data-table">
+                            <thead><tr><th>{i.t("drawer.time")}</th><th>{i.t("drawer.kind")}</th><th>{i.t("drawer.amount")}</th><th>{i.t("drawer.reason")}</th><th>{i.t("drawer.user")}</th></tr></thead>
+                            <tbody>
+                                {d.movements.iter().map(|m| view! {
+                                    <tr>
+                                        <td>{local_time(m.created_at)}</td>
+                                        <td>{i.t(&format!("drawer.kind_{}", m.kind))}</td>
+                                        <td>{money(m.signed_amount())}</td>
+                                        <td>{m.reason.clone().unwrap_or_default()}</td>
+                                        <td>{m.user_name.clone()}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                    </div>
+                }
+            })}
+
+            <Show when=move || drawer.get().is_some() fallback=|| ()>
+                <div class="edit-form">
+                    <h3>{move || i18n.get().t("drawer.add_movement")}</h3>
+                    <div class="form-grid">
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("drawer.kind")}</label>
+                            <select on:change=move |ev| set_kind.set(event_target_value(&ev)) prop:value=move || kind.get()>
+                                {CASH_MOVEMENT_KINDS.iter().copied().filter(|k| *k != "float").map(|k| view! {
+                                    <option value=k>{move || i18n.get().t(&format!("drawer.kind_{}", k))}</option>
+                                }).collect_view()}
+                            </select>
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("drawer.amount")}</label>
+                            <input type="text" inputmode="decimal"
+                                prop:value=move || amount_text.get()
+                                on:input=move |ev| set_amount_text.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("drawer.reason")}</label>
+                            <input type="text"
+                                placeholder=move || i18n.get().t("drawer.reason_placeholder")
+                                prop:value=move || reason.get()
+                                on:input=move |ev| set_reason.set(event_target_value(&ev)) />
+                        </div>
+                    </div>
+                    <div class="form-actions">
+                        <button class="btn-primary" on:click=add_movement>{move || i18n.get().t("drawer.record")}</button>
+                    </div>
+                </div>
+
+                <h3>{move || i18n.get().t("drawer.count")}</h3>
+                <table class="data-table">
+                    <thead><tr><th>{move || i18n.get().t("drawer.denomination")}</th><th>{move || i18n.get().t("drawer.quantity")}</th><th>{move || i18n.get().t("drawer.amount")}</th></tr></thead>
+                    <tbody>
+                        <For each=move || denominations.get() key=|d| d.0 let:denomination>
+                            <tr>
+                                <td>{move || money(denomination)}</td>
+                                <td>
+                                    <input type="text" inputmode="numeric"
+                                        prop:value=move || quantities.get().iter().find(|(d, _)| *d == denomination).map(|(_, t)| t.clone()).unwrap_or_default()
+                                        on:input=move |ev| set_quantity(denomination, event_target_value(&ev)) />
+                                </td>
+                                <td>{move || {
+                                    let quantity = counts().iter().find(|c| c.denomination == denomination).map_or(0, |c| c.quantity);
+                                    money(denomination * quantity)
+                                }}</td>
+                            </tr>
+                        </For>
+                    </tbody>
+                    <tfoot>
+                        <tr class="table-footer">
+                            <td><strong>{move || i18n.get().t("drawer.counted_total")}</strong></td>
+                            <td></td>
+                            <td><strong>{move || money(counted())}</strong></td>
+                        </tr>
+                        <tr>
+                            <td>{move || i18n.get().t("drawer.expected")}</td>
+                            <td></td>
+                            <td>{move || money(drawer.get().map(|d| d.expected()).unwrap_or_default())}</td>
+                        </tr>
+                        <tr>
+                            <td>{move || i18n.get().t("drawer.variance")}</td>
+                            <td></td>
+                            <td>{move || money(counted() - drawer.get().map(|d| d.expected()).unwrap_or_default())}</td>
+                        </tr>
+                    </tfoot>
+                </table>
+                <div class="form-actions">
+                    <button class="btn-danger" on:click=move |_| set_confirming_count.set(true)>{move || i18n.get().t("drawer.close")}</button>
+                </div>
+            </Show>
+
+            <Show when=move || confirming_count.get() fallback=|| ()>
+                <div class="modal-overlay">
+                    <div class="confirmation-modal">
+                        <h3>{move || i18n.get().t("drawer.close")}</h3>
+                        <p>{move || i18n.get().t("drawer.confirm_close").replace("{counted}", &money(counted()))}</p>
+                        <div class="modal-actions">
+                            <button class="btn-danger" on:click=save_count>{move || i18n.get().t("drawer.close")}</button>
+                            <button class="btn-secondary" on:click=move |_| set_confirming_count.set(false)>{move || i18n.get().t("general.cancel")}</button>
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || is_admin.get() fallback=|| ()>
+                <h3>{move || i18n.get().t("drawer.history")}</h3>
+                <table class="data-table">
+                    <thead>
+                        <tr>
+                            <th>{move || i18n.get().t("drawer.opened_at")}</th>
+                            <th>{move || i18n.get().t("drawer.counted_at")}</th>
+                            <th>{move || i18n.get().t("drawer.user")}</th>
+                            <th>{move || i18n.get().t("drawer.expected")}</th>
+                            <th>{move || i18n.get().t("drawer.counted_total")}</th>
+                            <th>{move || i18n.get().t("drawer.variance")}</th>
+                        </tr>
+                    </thead>
+                    <tbody>
+                        <For each=move || sessions.get() key=|s| s.id let:session>
+                            <tr>
+                                <td>{local_time(session.opened_at)}</td>
+                                <td>{session.closed_at.map(local_time).unwrap_or_default()}</td>
+                                <td>{session.closed_by.clone().unwrap_or_default()}</td>
+                                <td>{money(session.expected.unwrap_or_default())}</td>
+                                <td>{money(session.counted.unwrap_or_default())}</td>
+                                <td>{money(session.variance.unwrap_or_default())}</td>
+                            </tr>
+                        </For>
+                    </tbody>
+                </table>
+            </Show>
+        </div>
+        </Show>
+    }
+}
@@ -45,6 +45,9 @@ pub fn CategoriesPage() -> impl IntoView {
     let (name, set_name) = signal(String::new());
     let (description, set_description) = signal(String::new());
     let (main_course, set_main_course) = signal(false);
+    // Selected tax rate id, empty for none.
+    let (tax_rate_id, set_tax_rate_id) = signal(String::new());
+    let (tax_rates, set_tax_rates) = signal(Vec::<TaxRate>::new());
 
     // On-screen keyboard target: "name" or "description" (hidden on mobile via CSS)
     let (kb_target, set_kb_target) = signal(Option::<String>::None);
@@ -76,6 +79,7 @@ pub fn CategoriesPage() -> impl IntoView {
         reload.get(); // subscribe to trigger
         leptos::task::spawn_local(async move {
             if let Ok(cats) = fetch_categories().await { set_categories.set(cats); }
+            if let Ok(rates) = fetch_tax_rates().await { set_tax_rates.set(rates); }
         });
     });
 
@@ -86,6 +90,7 @@ pub fn CategoriesPage() -> impl IntoView {
         set_name.set(category.name.clone());
         set_description.set(category.description.clone().unwrap_or_default());
         set_main_course.set(category.main_course);
+        set_tax_rate_id.set(category.tax_rate_id.map(|id| id.to_string()).unwrap_or_default());
         set_editing_category.set(Some(category));
     };
 
@@ -96,8 +101,9 @@ pub fn CategoriesPage() -> impl IntoView {
             let n = name.get();
             let d = Some(description.get()).filter(|s| !s.is_empty());
             let mc = Some(main_course.get());
+            let tr = tax_rate_id.get().parse::<Uuid>().ok();
             leptos::task::spawn_local(async move {
-                if create_category(n, d, mc).await.is_ok() {
+                if create_category(n, d, mc, tr).await.is_ok() {
                     set_creating_category.set(false);
                     set_reload.update(|v| *v += 1);
                 }
@@ -106,9 +112,10 @@ pub fn CategoriesPage() -> impl IntoView {
             let n = Some(name.get());
             let d = Some(description.get()).filter(|s| !s.is_empty());
             let mc = Some(main_course.get());
+            let tr = tax_rate_id.get().parse::<Uuid>().ok();
             let cat_id = category.id;
             leptos::task::spawn_local(async move {
-                if update_category(cat_id, n, d, mc).await.is_ok() {
+                if update_category(cat_id, n, d, mc, tr).await.is_ok() {
                     set_editing_category.set(None);
                     set_reload.update(|v| *v += 1);
                 }
@@ -133,11 +140,13 @@ pub fn CategoriesPage() -> impl IntoView {
         set_editing_category.set(None); set_creating_category.set(false);
         set_name.set(String::new()); set_description.set(String::new());
         set_main_course.set(false);
+        set_tax_rate_id.set(String::new());
     };
     let start_create = move |_| {
         set_kb_target.set(None);
         set_name.set(String::new()); set_description.set(String::new());
         set_main_course.set(false);
+        set_tax_rate_id.set(String::new());
         set_creating_category.set(true); set_editing_category.set(None);
     };
 
@@ -224,6 +233,15 @@ pub fn CategoriesPage() -> impl IntoView {
                                 " " {move || i18n.get().t("categories.main_course")}
                             </label>
                         </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("tax.rate")}</label>
+                            <select prop:value=move || tax_rate_id.get() on:change=move |ev| set_tax_rate_id.set(event_target_value(&ev))>
+                                <option value="">{move || i18n.get().t("tax.none")}</option>
+                                <For each=move || tax_rates.get() key=|r| r.id let:rate>
+                                    <option value={rate.id.to_string()}>{format!("{} ({}%)", rate.name, rate.rate)}</option>
+                                </For>
+                            </select>
+                        </div>
                     </div>
                     <Show when=move || kb_target.get().is_some() fallback=|| ()>
                         <OnScreenKeyboard on_key=on_kb_key shift=kb_shift i18n=i18n />
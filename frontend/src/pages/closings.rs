@@ -0,0 +1,237 @@
+use leptos::prelude::*;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::server_fns::*;
+
+fn local_time(t: chrono::DateTime<chrono::Utc>) -> String {
+    t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// The figures of a closing or X-report: revenue and counts, then the totals
+/// per tender, tax rate and user.
+fn closing_totals_view(i18n: RwSignal<I18n>, currency: RwSignal<String>, totals: ClosingTotals) -> impl IntoView {
+    let i = i18n.get();
+    let money = move |m: Money| currency.with(|c| format!("{} {}", c, m.format(c)));
+    view! {
+        <p class="text-muted">{local_time(totals.period_start)}" – "{local_time(totals.period_end)}</p>
+        <div class="summary-cards">
+            <div class="summary-card"><h4>{i.t("closings.revenue")}</h4><div class="summary-value">{money(totals.revenue)}</div></div>
+            <div class="summary-card"><h4>{i.t("closings.sales")}</h4><div class="summary-value">{totals.transaction_count.to_string()}</div></div>
+            <div class="summary-card"><h4>{i.t("closings.refunds")}</h4><div class="summary-value">{format!("{} ({})", money(totals.refunds), totals.refund_count)}</div></div>
+            <div class="summary-card"><h4>{i.t("closings.cancellations")}</h4><div class="summary-value">{format!("{} ({})", money(totals.cancellations), totals.cancellation_count)}</div></div>
+        </div>
+        {(!totals.tips.is_zero() || !totals.rounding.is_zero()).then(|| view! {
+            <p>
+                {i.t("reports.tips")}": "{money(totals.tips)}" · "
+                {i.t("reports.cash_rounding")}": "{money(totals.rounding)}
+            </p>
+        })}
+        {(!totals.tenders.is_empty()).then(|| view! {
+            <h4>{i.t("reports.payment_by_tender")}</h4>
+            <table class="data-table">
+                <thead><tr><th>{i.t("reports.tender")}</th><th>{i.t("reports.transactions")}</th><th>{i.t("reports.revenue")}</th></tr></thead>
+                <tbody>
+                    {totals.tenders.iter().map(|t| view! {
+                        <tr>
+                            <td>{i.t(&format!("sale.tender_{}", t.tender))}</td>
+                            <td>{t.transaction_count.to_string()}</td>
+                            <td>{money(t.amount)}</td>
+                        </tr>
+                    }).collect_view()}
+                </tbody>
+            </table>
+        })}
+        {(!totals.taxes.is_empty()).then(|| view! {
+            <h4>{i.t("reports.tax_by_rate")}</h4>
+            <table class="data-table">
+                <thead><tr><th>{i.t("tax.rate")}</th><th>{i.t("tax.net")}</th><th>{i.t("tax.tax")}</th><th>{i.t("tax.gross")}</th></tr></thead>
+                <tbody>
+                    {totals.taxes.iter().map(|t| view! {
+                        <tr>
+                            <td>{format!("{}%", t.rate)}</td>
+                            <td>{money(t.net)}</td>
+                            <td>{money(t.tax)}</td>
+                            <td>{money(t.gross)}</td>
+                        </tr>
+                    }).collect_view()}
+                </tbody>
+            </table>
+        })}
+        {(!totals.users.is_empty()).then(|| view! {
+            <h4>{i.t("closings.by_user")}</h4>
+            <table class="data-table">
+                <thead><tr><th>{i.t("closings.user")}</th><th>{i.t("reports.transactions")}</th><th>{i.t("reports.revenue")}</th></tr></thead>
+                <tbody>
+                    {totals.users.iter().map(|u| view! {
+                        <tr>
+                            <td>{if u.user_name.is_empty() { "-".to_string() } else { u.user_name.clone() }}</td>
+                            <td>{u.transaction_count.to_string()}</td>
+                            <td>{money(u.amount)}</td>
+                        </tr>
+                    }).collect_view()}
+                </tbody>
+            </table>
+        })}
+    }
+}
+
+/// End-of-day Z-closings and the mid-shift X-report.
+#[component]
+pub fn ClosingsPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let (authorized, set_authorized) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" => set_authorized.set(true),
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let (x_report, set_x_report) = signal(Option::<ClosingTotals>::None);
+    let (closings, set_closings) = signal(Vec::<ZClosing>::new());
+    let (viewing, set_viewing) = signal(Option::<i64>::None);
+    let (confirming_close, set_confirming_close) = signal(false);
+    let (status_msg, set_status_msg) = signal(Option::<String>::None);
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            match fetch_x_report().await {
+                Ok(totals) => set_x_report.set(Some(totals)),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+            if let Ok(c) = fetch_z_closings().await { set_closings.set(c); }
+        });
+    });
+
+    let print_x = move |_| {
+        set_status_msg.set(None);
+        leptos::task::spawn_local(async move {
+            match print_x_report().await {
+                Ok(()) => set_status_msg.set(Some(i18n.get_untracked().t("reports.print_sent"))),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    let close_day = move |_| {
+        set_confirming_close.set(false);
+        set_status_msg.set(None);
+        leptos::task::spawn_local(async move {
+            match create_z_closing().await {
+                Ok(closing) => {
+                    let msg = i18n.get_untracked().t("closings.closed").replace("{number}", &closing.number.to_string());
+                    // The closing stands even if the printer is out.
+                    let msg = match print_z_closing(closing.number).await {
+                        Ok(()) => msg,
+                        Err(e) => format!("{} {}", msg, e),
+                    };
+                    set_status_msg.set(Some(msg));
+                    set_viewing.set(Some(closing.number));
+                    set_reload.update(|v| *v += 1);
+                }
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    let reprint = move |number: i64| {
+        set_status_msg.set(None);
+        leptos::task::spawn_local(async move {
+            match print_z_closing(number).await {
+                Ok(()) => set_status_msg.set(Some(i18n.get_untracked().t("reports.print_sent"))),
+                Err(e) => set_status_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div class="reports-page">
+            <div class="page-header">
+                <h2>{move || i18n.get().t("closings.title")}</h2>
+                <div class="page-header-actions">
+                    <button class="btn-secondary" on:click=move |_| set_reload.update(|v| *v += 1)>{move || i18n.get().t("closings.refresh")}</button>
+                    <button class="btn-primary" on:click=print_x>{move || i18n.get().t("closings.print_x")}</button>
+                    <button class="btn-danger" on:click=move |_| set_confirming_close.set(true)>{move || i18n.get().t("closings.close_day")}</button>
+                </div>
+            </div>
+            <p class="text-muted">{move || i18n.get().t("closings.hint")}</p>
+            <Show when=move || status_msg.get().is_some() fallback=|| ()>
+                <p class="text-muted">{move || status_msg.get().unwrap_or_default()}</p>
+            </Show>
+
+            <Show when=move || confirming_close.get() fallback=|| ()>
+                <div class="modal-overlay">
+                    <div class="confirmation-modal">
+                        <h3>{move || i18n.get().t("closings.close_day")}</h3>
+                        <p>{move || i18n.get().t("closings.confirm_close")}</p>
+                        <div class="modal-actions">
+                            <button class="btn-danger" on:click=close_day>{move || i18n.get().t("closings.close_day")}</button>
+                            <button class="btn-secondary" on:click=move |_| set_confirming_close.set(false)>{move || i18n.get().t("general.cancel")}</button>
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
+            <div class="report-content">
+                <h3>{move || i18n.get().t("closings.x_report")}</h3>
+                {move || x_report.get().map(|totals| closing_totals_view(i18n, currency, totals))}
+            </div>
+
+            <h3>{move || i18n.get().t("closings.history")}</h3>
+            <Show when=move || closings.get().is_empty() fallback=|| ()>
+                <p class="text-muted">{move || i18n.get().t("closings.none")}</p>
+            </Show>
+            <table class="data-table">
+                <thead>
+                    <tr>
+                        <th>{move || i18n.get().t("closings.number")}</th>
+                        <th>{move || i18n.get().t("closings.closed_at")}</th>
+                        <th>{move || i18n.get().t("closings.closed_by")}</th>
+                        <th>{move || i18n.get().t("reports.transactions")}</th>
+                        <th>{move || i18n.get().t("closings.revenue")}</th>
+                        <th></th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <For each=move || closings.get() key=|c| c.number let:closing>
+                        {
+                            let number = closing.number;
+                            let totals = closing.totals.clone();
+                            view! {
+                                <tr>
+                                    <td>{number.to_string()}</td>
+                                    <td>{local_time(closing.created_at)}</td>
+                                    <td>{closing.closed_by.clone()}</td>
+                                    <td>{closing.totals.transaction_count.to_string()}</td>
+                                    <td>{currency.with(|c| format!("{} {}", c, closing.totals.revenue.format(c)))}</td>
+                                    <td class="data-table-actions">
+                                        <button class="btn-small" on:click=move |_| {
+                                            set_viewing.update(|v| *v = if *v == Some(number) { None } else { Some(number) });
+                                        }>{move || i18n.get().t("closings.details")}</button>
+                                        <button class="btn-small" on:click=move |_| reprint(number)>{move || i18n.get().t("closings.print")}</button>
+                                    </td>
+                                </tr>
+                                <Show when=move || viewing.get() == Some(number) fallback=|| ()>
+                                    <tr>
+                                        <td colspan="6">{closing_totals_view(i18n, currency, totals.clone())}</td>
+                                    </tr>
+                                </Show>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+        </div>
+        </Show>
+    }
+}
@@ -0,0 +1,376 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::pages::keyboard::{scroll_page_to_top, OnScreenKeyboard};
+use crate::server_fns::*;
+
+fn optional(s: String) -> Option<String> {
+    let s = s.trim().to_string();
+    if s.is_empty() { None } else { Some(s) }
+}
+
+#[component]
+pub fn CustomersPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let (authorized, set_authorized) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" => set_authorized.set(true),
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let (customers, set_customers) = signal(Vec::<Customer>::new());
+    let (search, set_search) = signal(String::new());
+    let (editing_customer, set_editing_customer) = signal(Option::<Customer>::None);
+    let (creating_customer, set_creating_customer) = signal(false);
+    let (deleting_customer, set_deleting_customer) = signal(Option::<(Uuid, String)>::None);
+    let (detail, set_detail) = signal(Option::<CustomerDetail>::None);
+    let (form_error, set_form_error) = signal(Option::<String>::None);
+
+    let (name, set_name) = signal(String::new());
+    let (phone, set_phone) = signal(String::new());
+    let (email, set_email) = signal(String::new());
+    let (notes, set_notes) = signal(String::new());
+    let (card_code, set_card_code) = signal(String::new());
+
+    // On-screen keyboard (hidden on mobile via CSS), typing into the name field.
+    let (kb_open, set_kb_open) = signal(false);
+    let (kb_shift, set_kb_shift) = signal(false);
+
+    let on_kb_key = move |key: String| {
+        match key.as_str() {
+            "Backspace" => { set_name.update(|s| { s.pop(); }); }
+            "Enter" => { set_kb_open.set(false); }
+            "Shift" => { set_kb_shift.update(|s| *s = !*s); }
+            "Space" => { set_name.update(|s| s.push(' ')); }
+            ch => {
+                let ch = if kb_shift.get() { ch.to_uppercase() } else { ch.to_lowercase() };
+                set_name.update(|s| s.push_str(&ch));
+            }
+        }
+    };
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            if let Ok(c) = fetch_customers().await { set_customers.set(c); }
+        });
+    });
+
+    // How points are earned and what they are worth when redeemed.
+    let (points_text, set_points_text) = signal(String::new());
+    let (value_text, set_value_text) = signal(String::new());
+    let (loyalty_msg, set_loyalty_msg) = signal(Option::<String>::None);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            if let Ok(settings) = get_loyalty_settings().await {
+                set_points_text.set(settings.points_per_unit.to_string());
+                set_value_text.set(settings.point_value.to_string());
+            }
+        });
+    });
+
+    let save_loyalty = move |_| {
+        let parse = |s: String| s.trim().replace(',', ".").parse::<f64>().ok();
+        let (Some(points_per_unit), Some(point_value)) = (parse(points_text.get()), parse(value_text.get())) else {
+            set_loyalty_msg.set(Some(i18n.get().t("customers.invalid_loyalty")));
+            return;
+        };
+        leptos::task::spawn_local(async move {
+            match set_loyalty_settings(LoyaltySettings { points_per_unit, point_value }).await {
+                Ok(()) => set_loyalty_msg.set(Some(i18n.get().t("customers.loyalty_saved"))),
+                Err(e) => set_loyalty_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    let filtered = move || {
+        let q = search.get().trim().to_lowercase();
+        customers.get().into_iter().filter(|c| {
+            q.is_empty()
+                || c.name.to_lowercase().contains(&q)
+                || c.phone.as_deref().is_some_and(|p| p.contains(&q))
+                || c.email.as_deref().is_some_and(|e| e.to_lowercase().contains(&q))
+                || c.card_code.as_deref().is_some_and(|k| k.to_lowercase() == q)
+        }).collect::<Vec<_>>()
+    };
+
+    let fill_form = move |c: Option<&Customer>| {
+        set_name.set(c.map(|c| c.name.clone()).unwrap_or_default());
+        set_phone.set(c.and_then(|c| c.phone.clone()).unwrap_or_default());
+        set_email.set(c.and_then(|c| c.email.clone()).unwrap_or_default());
+        set_notes.set(c.and_then(|c| c.notes.clone()).unwrap_or_default());
+        set_card_code.set(c.and_then(|c| c.card_code.clone()).unwrap_or_default());
+        set_form_error.set(None);
+    };
+
+    let start_edit = move |customer: Customer| {
+        set_kb_open.set(false);
+        scroll_page_to_top();
+        fill_form(Some(&customer));
+        set_editing_customer.set(Some(customer));
+        set_creating_customer.set(false);
+        set_detail.set(None);
+    };
+
+    let start_create = move |_| {
+        set_kb_open.set(false);
+        fill_form(None);
+        set_creating_customer.set(true);
+        set_editing_customer.set(None);
+        set_detail.set(None);
+    };
+
+    let show_detail = move |id: Uuid| {
+        leptos::task::spawn_local(async move {
+            if let Ok(d) = fetch_customer_detail(id).await {
+                scroll_page_to_top();
+                set_detail.set(Some(d));
+            }
+        });
+    };
+
+    let save_customer = move |_| {
+        let input = CustomerInput {
+            name: name.get().trim().to_string(),
+            phone: optional(phone.get()),
+            email: optional(email.get()),
+            notes: optional(notes.get()),
+            card_code: optional(card_code.get()),
+        };
+        if input.name.is_empty() { return; }
+        let editing = editing_customer.get();
+        leptos::task::spawn_local(async move {
+            let result = match editing {
+                Some(customer) => update_customer(customer.id, input).await,
+                None => create_customer(input).await,
+            };
+            match result {
+                Ok(_) => {
+                    set_creating_customer.set(false);
+                    set_editing_customer.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+                Err(e) => set_form_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let cancel_edit = move |_| {
+        set_kb_open.set(false);
+        set_editing_customer.set(None);
+        set_creating_customer.set(false);
+        fill_form(None);
+    };
+
+    let confirm_delete = move |id: Uuid, name: String| { set_deleting_customer.set(Some((id, name))); };
+    let delete_customer_handler = move |_| {
+        if let Some((id, _)) = deleting_customer.get() {
+            leptos::task::spawn_local(async move {
+                if delete_customer(id).await.is_ok() {
+                    set_deleting_customer.set(None);
+                    set_detail.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+            });
+        }
+    };
+    let cancel_delete = move |_| { set_deleting_customer.set(None); };
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div>
+            <div class="page-header">
+                <h2>{move || i18n.get().t("customers.title")}</h2>
+                <div class="page-header-actions">
+                    <button class="btn-primary" on:click=start_create
+                        disabled=move || editing_customer.get().is_some() || creating_customer.get()
+                    >{move || i18n.get().t("customers.add")}</button>
+                </div>
+            </div>
+
+            <div class="currency-custom">
+                <label>{move || i18n.get().t("customers.loyalty")}</label>
+                <p class="text-muted">{move || i18n.get().t("customers.loyalty_hint")}</p>
+                <div class="currency-custom-row">
+                    <input type="text" inputmode="decimal"
+                        prop:value=move || points_text.get()
+                        on:input=move |ev| set_points_text.set(event_target_value(&ev)) />
+                    <span>{move || format!("{} / {}", i18n.get().t("customers.points"), currency.get())}</span>
+                    <input type="text" inputmode="decimal"
+                        prop:value=move || value_text.get()
+                        on:input=move |ev| set_value_text.set(event_target_value(&ev)) />
+                    <span>{move || format!("{} / {}", currency.get(), i18n.get().t("customers.point"))}</span>
+                    <button class="btn-primary" on:click=save_loyalty>{move || i18n.get().t("general.save")}</button>
+                </div>
+                <Show when=move || loyalty_msg.get().is_some() fallback=|| ()>
+                    <p class="text-muted">{move || loyalty_msg.get().unwrap_or_default()}</p>
+                </Show>
+            </div>
+
+            <Show when=move || deleting_customer.get().is_some() fallback=|| ()>
+                {move || {
+                    deleting_customer.get().map(|(_, customer_name)| {
+                        let i = i18n.get();
+                        let confirm_msg = i.t("customers.confirm_delete").replace("{name}", &customer_name);
+                        view! {
+                            <div class="modal-overlay">
+                                <div class="confirmation-modal">
+                                    <h3>{i.t("general.confirm_delete")}</h3>
+                                    <p>{confirm_msg}</p>
+                                    <p class="warning-text">{i.t("customers.delete_warning")}</p>
+                                    <div class="modal-actions">
+                                        <button class="btn-danger" on:click=delete_customer_handler>{i.t("general.delete")}</button>
+                                        <button class="btn-secondary" on:click=cancel_delete>{i.t("general.cancel")}</button>
+                                    </div>
+                                </div>
+                            </div>
+                        }
+                    })
+                }}
+            </Show>
+
+            <Show when=move || editing_customer.get().is_some() || creating_customer.get() fallback=|| ()>
+                <div class="edit-form">
+                    <h3>{move || if creating_customer.get() { i18n.get().t("customers.create") } else { i18n.get().t("customers.edit") }}</h3>
+                    <Show when=move || form_error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || form_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="form-grid">
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("customers.name")}</label>
+                            <input type="text" prop:value=move || name.get()
+                                on:focus=move |_| { set_kb_open.set(true); set_kb_shift.set(false); }
+                                on:input=move |ev| set_name.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("customers.phone")}</label>
+                            <input type="tel" prop:value=move || phone.get()
+                                on:input=move |ev| set_phone.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("customers.email")}</label>
+                            <input type="email" prop:value=move || email.get()
+                                on:input=move |ev| set_email.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("customers.card_code")}</label>
+                            <input type="text" autocomplete="off" prop:value=move || card_code.get()
+                                on:input=move |ev| set_card_code.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("customers.notes")}</label>
+                            <input type="text" prop:value=move || notes.get()
+                                on:input=move |ev| set_notes.set(event_target_value(&ev)) />
+                        </div>
+                    </div>
+                    <Show when=move || kb_open.get() fallback=|| ()>
+                        <OnScreenKeyboard on_key=on_kb_key shift=kb_shift i18n=i18n />
+                    </Show>
+                    <div class="form-actions">
+                        <button class="btn-success" on:click=save_customer>{move || i18n.get().t("general.save")}</button>
+                        <button class="btn-secondary" on:click=cancel_edit>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </Show>
+
+            {move || detail.get().map(|d| {
+                let cur = currency.get();
+                let i = i18n.get();
+                view! {
+                    <div class="edit-form">
+                        <h3>{d.customer.name.clone()}</h3>
+                        <p class="text-muted">
+                            {[d.customer.phone.clone(), d.customer.email.clone(), d.customer.card_code.clone()]
+                                .into_iter().flatten().collect::<Vec<_>>().join(" · ")}
+                        </p>
+                        {d.customer.notes.clone().map(|n| view! { <p>{n}</p> })}
+                        <table class="data-table">
+                            <tbody>
+                                <tr><td>{i.t("customers.visits")}</td><td>{d.visit_count}</td></tr>
+                                <tr><td>{i.t("customers.lifetime_spend")}</td><td>{format!("{} {}", cur, d.lifetime_spend.format(&cur))}</td></tr>
+                                <tr><td>{i.t("customers.points")}</td><td>{d.customer.points}</td></tr>
+                            </tbody>
+                        </table>
+                        <h4>{i.t("customers.visit_history")}</h4>
+                        <table class="data-table">
+                            <thead><tr>
+                                <th>{i.t("customers.date")}</th>
+                                <th>{i.t("customers.total")}</th>
+                            </tr></thead>
+                            <tbody>
+                                {d.visits.iter().map(|t| view! {
+                                    <tr>
+                                        <td>{t.closed_at.unwrap_or(t.created_at).with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()}</td>
+                                        <td>{format!("{} {}", cur, t.total.format(&cur))}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody>
+                        </table>
+                        <div class="form-actions">
+                            <button class="btn-secondary" on:click=move |_| set_detail.set(None)>{i.t("gift_cards.close")}</button>
+                        </div>
+                    </div>
+                }
+            })}
+
+            <div class="admin-input-row">
+                <input type="text" placeholder=move || i18n.get().t("customers.search")
+                    prop:value=move || search.get()
+                    on:input=move |ev| set_search.set(event_target_value(&ev)) />
+            </div>
+
+            <Show when=move || customers.get().is_empty() fallback=|| ()>
+                <p class="text-muted">{move || i18n.get().t("customers.none")}</p>
+            </Show>
+
+            <table class="data-table">
+                <thead><tr>
+                    <th>{move || i18n.get().t("customers.name")}</th>
+                    <th>{move || i18n.get().t("customers.phone")}</th>
+                    <th>{move || i18n.get().t("customers.points")}</th>
+                    <th></th>
+                </tr></thead>
+                <tbody>
+                    <For each=filtered key=|c| (c.id, c.name.clone(), c.phone.clone(), c.points) let:customer>
+                        {
+                            let customer_clone = customer.clone();
+                            let customer_id = customer.id;
+                            let customer_name = customer.name.clone();
+                            view! {
+                                <tr>
+                                    <td>{customer.name.clone()}</td>
+                                    <td>{customer.phone.clone().unwrap_or_default()}</td>
+                                    <td>{customer.points}</td>
+                                    <td class="data-table-actions">
+                                        <button class="btn-small" on:click=move |_| show_detail(customer_id)
+                                        >{move || i18n.get().t("customers.details")}</button>
+                                        <button class="btn-small" on:click=move |_| start_edit(customer_clone.clone())
+                                            disabled=move || editing_customer.get().is_some() || creating_customer.get()
+                                        >{move || i18n.get().t("general.edit")}</button>
+                                        <button class="btn-small btn-danger" on:click=move |_| confirm_delete(customer_id, customer_name.clone())
+                                            disabled=move || editing_customer.get().is_some() || creating_customer.get()
+                                        >{move || i18n.get().t("general.delete")}</button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+        </div>
+        </Show>
+    }
+}
@@ -0,0 +1,241 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::pages::keyboard::{scroll_page_to_top, OnScreenKeyboard};
+use crate::server_fns::*;
+
+#[component]
+pub fn DiscountReasonsPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let (authorized, set_authorized) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" => set_authorized.set(true),
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let (reasons, set_reasons) = signal(Vec::<DiscountReason>::new());
+    let (editing_reason, set_editing_reason) = signal(Option::<DiscountReason>::None);
+    let (creating_reason, set_creating_reason) = signal(false);
+    let (deleting_reason, set_deleting_reason) = signal(Option::<(Uuid, String)>::None);
+
+    let (name, set_name) = signal(String::new());
+
+    // On-screen keyboard (hidden on mobile via CSS)
+    let (kb_open, set_kb_open) = signal(false);
+    let (kb_shift, set_kb_shift) = signal(false);
+
+    let on_kb_key = move |key: String| {
+        match key.as_str() {
+            "Backspace" => { set_name.update(|s| { s.pop(); }); }
+            "Enter" => { set_kb_open.set(false); }
+            "Shift" => { set_kb_shift.update(|s| *s = !*s); }
+            "Space" => { set_name.update(|s| s.push(' ')); }
+            ch => {
+                let ch = if kb_shift.get() { ch.to_uppercase() } else { ch.to_lowercase() };
+                set_name.update(|s| s.push_str(&ch));
+            }
+        }
+    };
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            if let Ok(r) = fetch_discount_reasons().await { set_reasons.set(r); }
+        });
+    });
+
+    // Largest discount in percent a cashier may give without an admin's PIN.
+    let (limit_text, set_limit_text) = signal(String::new());
+    let (limit_msg, set_limit_msg) = signal(Option::<String>::None);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            if let Ok(limit) = get_max_cashier_discount().await { set_limit_text.set(limit.to_string()); }
+        });
+    });
+
+    let save_limit = move |_| {
+        let Ok(percent) = limit_text.get().trim().replace(',', ".").parse::<f64>() else {
+            set_limit_msg.set(Some(i18n.get().t("discounts.invalid_limit")));
+            return;
+        };
+        leptos::task::spawn_local(async move {
+            match set_max_cashier_discount(percent).await {
+                Ok(()) => set_limit_msg.set(Some(i18n.get().t("discounts.limit_saved"))),
+                Err(e) => set_limit_msg.set(Some(format!("{}", e))),
+            }
+        });
+    };
+
+    let start_edit = move |reason: DiscountReason| {
+        set_kb_open.set(false);
+        scroll_page_to_top();
+        set_name.set(reason.name.clone());
+        set_editing_reason.set(Some(reason));
+        set_creating_reason.set(false);
+    };
+
+    let start_create = move |_| {
+        set_kb_open.set(false);
+        set_name.set(String::new());
+        set_creating_reason.set(true);
+        set_editing_reason.set(None);
+    };
+
+    let save_reason = move |_| {
+        let editing = editing_reason.get();
+        let creating = creating_reason.get();
+        let n = name.get();
+        if n.trim().is_empty() { return; }
+        if creating {
+            leptos::task::spawn_local(async move {
+                if create_discount_reason(n).await.is_ok() {
+                    set_creating_reason.set(false);
+                    set_reload.update(|v| *v += 1);
+                }
+            });
+        } else if let Some(reason) = editing {
+            let rid = reason.id;
+            leptos::task::spawn_local(async move {
+                if update_discount_reason(rid, n).await.is_ok() {
+                    set_editing_reason.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+            });
+        }
+    };
+
+    let cancel_edit = move |_| {
+        set_kb_open.set(false);
+        set_editing_reason.set(None);
+        set_creating_reason.set(false);
+        set_name.set(String::new());
+    };
+
+    let confirm_delete = move |id: Uuid, name: String| { set_deleting_reason.set(Some((id, name))); };
+    let delete_reason_handler = move |_| {
+        if let Some((id, _)) = deleting_reason.get() {
+            leptos::task::spawn_local(async move {
+                if delete_discount_reason(id).await.is_ok() {
+                    set_deleting_reason.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+            });
+        }
+    };
+    let cancel_delete = move |_| { set_deleting_reason.set(None); };
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div>
+            <div class="page-header">
+                <h2>{move || i18n.get().t("discounts.title")}</h2>
+                <div class="page-header-actions">
+                    <button class="btn-primary" on:click=start_create
+                        disabled=move || editing_reason.get().is_some() || creating_reason.get()
+                    >{move || i18n.get().t("discounts.add")}</button>
+                </div>
+            </div>
+
+            <div class="currency-custom">
+                <label>{move || i18n.get().t("discounts.cashier_limit")}</label>
+                <p class="text-muted">{move || i18n.get().t("discounts.cashier_limit_hint")}</p>
+                <div class="currency-custom-row">
+                    <input type="text" inputmode="decimal"
+                        prop:value=move || limit_text.get()
+                        on:input=move |ev| set_limit_text.set(event_target_value(&ev)) />
+                    <span>"%"</span>
+                    <button class="btn-primary" on:click=save_limit>{move || i18n.get().t("general.save")}</button>
+                </div>
+                <Show when=move || limit_msg.get().is_some() fallback=|| ()>
+                    <p class="text-muted">{move || limit_msg.get().unwrap_or_default()}</p>
+                </Show>
+            </div>
+
+            <Show when=move || deleting_reason.get().is_some() fallback=|| ()>
+                {move || {
+                    deleting_reason.get().map(|(_, reason_name)| {
+                        let i = i18n.get();
+                        let confirm_msg = i.t("discounts.confirm_delete").replace("{name}", &reason_name);
+                        view! {
+                            <div class="modal-overlay">
+                                <div class="confirmation-modal">
+                                    <h3>{i.t("general.confirm_delete")}</h3>
+                                    <p>{confirm_msg}</p>
+                                    <p class="warning-text">{i.t("discounts.delete_warning")}</p>
+                                    <div class="modal-actions">
+                                        <button class="btn-danger" on:click=delete_reason_handler>{i.t("general.delete")}</button>
+                                        <button class="btn-secondary" on:click=cancel_delete>{i.t("general.cancel")}</button>
+                                    </div>
+                                </div>
+                            </div>
+                        }
+                    })
+                }}
+            </Show>
+
+            <Show when=move || editing_reason.get().is_some() || creating_reason.get() fallback=|| ()>
+                <div class="edit-form">
+                    <h3>{move || if creating_reason.get() { i18n.get().t("discounts.create") } else { i18n.get().t("discounts.edit") }}</h3>
+                    <div class="form-grid">
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("discounts.reason")}</label>
+                            <div class="admin-input-row">
+                                <input type="text" value=move || name.get()
+                                    on:focus=move |_| { set_kb_open.set(true); set_kb_shift.set(false); }
+                                    on:input=move |ev| set_name.set(event_target_value(&ev)) />
+                            </div>
+                        </div>
+                    </div>
+                    <Show when=move || kb_open.get() fallback=|| ()>
+                        <OnScreenKeyboard on_key=on_kb_key shift=kb_shift i18n=i18n />
+                    </Show>
+                    <div class="form-actions">
+                        <button class="btn-success" on:click=save_reason>{move || i18n.get().t("general.save")}</button>
+                        <button class="btn-secondary" on:click=cancel_edit>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || reasons.get().is_empty() fallback=|| ()>
+                <p class="text-muted">{move || i18n.get().t("discounts.none")}</p>
+            </Show>
+
+            <table class="data-table">
+                <thead><tr><th>{move || i18n.get().t("discounts.reason")}</th><th></th></tr></thead>
+                <tbody>
+                    <For each=move || reasons.get() key=|g| (g.id, g.name.clone()) let:reason>
+                        {
+                            let reason_clone = reason.clone();
+                            let reason_id = reason.id;
+                            let reason_name = reason.name.clone();
+                            view! {
+                                <tr>
+                                    <td>{reason.name.clone()}</td>
+                                    <td class="data-table-actions">
+                                        <button class="btn-small" on:click=move |_| start_edit(reason_clone.clone())
+                                            disabled=move || editing_reason.get().is_some() || creating_reason.get()
+                                        >{move || i18n.get().t("general.edit")}</button>
+                                        <button class="btn-small btn-danger" on:click=move |_| confirm_delete(reason_id, reason_name.clone())
+                                            disabled=move || editing_reason.get().is_some() || creating_reason.get()
+                                        >{move || i18n.get().t("general.delete")}</button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+        </div>
+        </Show>
+    }
+}
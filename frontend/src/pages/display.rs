@@ -2,7 +2,7 @@ use leptos::prelude::*;
 use uuid::Uuid;
 
 use crate::i18n::I18n;
-use crate::models::TransactionItemDetail;
+use crate::models::{discount_lines, group_bundles, Money, Payment, TransactionItemDetail};
 use crate::server_fns::fetch_transaction_details;
 
 /// Looks up a label and strips the trailing `": "` that the shared sale keys
@@ -92,10 +92,11 @@ pub fn DisplayPage() -> impl IntoView {
     let currency = expect_context::<RwSignal<String>>();
     let i18n = expect_context::<RwSignal<I18n>>();
     let (items, set_items) = signal(Vec::<TransactionItemDetail>::new());
-    let (total, set_total) = signal(0.0f64);
-    // Cash tendered and change due; populated once the sale is closed.
-    let (paid, set_paid) = signal(Option::<f64>::None);
-    let (change, set_change) = signal(Option::<f64>::None);
+    let (total, set_total) = signal(Money::ZERO);
+    // Amount tendered (per payment) and change due; populated once the sale is closed.
+    let (paid, set_paid) = signal(Option::<Money>::None);
+    let (payments, set_payments) = signal(Vec::<Payment>::new());
+    let (change, set_change) = signal(Option::<Money>::None);
     let (active, set_active) = signal(false);
     let (ws_msg, set_ws_msg) = signal(String::new());
     #[allow(unused_variables)]
@@ -123,6 +124,7 @@ pub fn DisplayPage() -> impl IntoView {
                         set_total.set(details.transaction.total);
                         // Still open: no payment yet (also clears any stale values).
                         set_paid.set(details.transaction.paid_amount);
+                        set_payments.set(details.payments);
                         set_change.set(details.transaction.change_amount);
                         set_active.set(true);
                     }
@@ -136,6 +138,7 @@ pub fn DisplayPage() -> impl IntoView {
                         set_items.set(details.items);
                         set_total.set(details.transaction.total);
                         set_paid.set(details.transaction.paid_amount);
+                        set_payments.set(details.payments);
                         set_change.set(details.transaction.change_amount);
                     }
                 });
@@ -149,8 +152,9 @@ pub fn DisplayPage() -> impl IntoView {
                     let tid = set_timeout_ms(move || {
                         set_active.set(false);
                         set_items.set(vec![]);
-                        set_total.set(0.0);
+                        set_total.set(Money::ZERO);
                         set_paid.set(None);
+                        set_payments.set(vec![]);
                         set_change.set(None);
                     }, 60_000);
                     set_timer_id.set(Some(tid));
@@ -164,8 +168,9 @@ pub fn DisplayPage() -> impl IntoView {
             set_timer_id.set(None);
             set_active.set(false);
             set_items.set(vec![]);
-            set_total.set(0.0);
+            set_total.set(Money::ZERO);
             set_paid.set(None);
+            set_payments.set(vec![]);
             set_change.set(None);
         }
     });
@@ -188,28 +193,49 @@ pub fn DisplayPage() -> impl IntoView {
                 }
             >
                 <div class="display-items">
-                    <For each=move || items.get() key=|item| (item.id, item.quantity) let:item>
+                    <For each=move || group_bundles(&items.get()) key=|item| (item.id, item.quantity) let:item>
                         <div class="display-item-row">
-                            <span class="display-item-name">{item.item_name.clone()}</span>
-                            <span class="display-item-qty">{format!("{}x", item.quantity)}</span>
-                            <span class="display-item-price">{move || format!("{}{:.2}", currency.get(), item.total_price)}</span>
+                            <span class="display-item-name">
+                                {item.item_name.clone()}
+                                {currency.with(|c| item.weight_label(c)).map(|w| view! { <span class="line-weight">{w}</span> })}
+                                {item.modifier_names().map(|m| view! { <span class="line-modifiers">{m}</span> })}
+                                {(!item.components.is_empty()).then(|| view! { <span class="line-modifiers">{item.components.join(", ")}</span> })}
+                            </span>
+                            <span class="display-item-qty">{match item.unit.as_deref() {
+                                Some(unit) => format!("{} {}", item.quantity, unit),
+                                None => format!("{}x", item.quantity),
+                            }}</span>
+                            <span class="display-item-price">{move || currency.with(|c| format!("{}{}", c, item.total_price.format(c)))}</span>
+                        </div>
+                    </For>
+                    <For each=move || discount_lines(&items.get()) key=|d| (d.name.clone(), d.amount.0) let:d>
+                        <div class="display-item-row display-discount-row">
+                            <span class="display-item-name">{d.name.clone()}</span>
+                            <span class="display-item-qty"></span>
+                            <span class="display-item-price">{move || currency.with(|c| format!("{}{}", c, d.amount.format(c)))}</span>
                         </div>
                     </For>
                 </div>
                 <div class="display-total">
                     <span>{move || label(&i18n.get(), "sale.total")}</span>
-                    <span>{move || format!("{}{:.2}", currency.get(), total.get())}</span>
+                    <span>{move || currency.with(|c| format!("{}{}", c, total.get().format(c)))}</span>
                 </div>
-                <Show when=move || paid.get().is_some() fallback=|| ()>
+                <Show when=move || paid.get().is_some() && payments.get().is_empty() fallback=|| ()>
                     <div class="display-paid">
                         <span>{move || label(&i18n.get(), "sale.cash")}</span>
-                        <span>{move || format!("{}{:.2}", currency.get(), paid.get().unwrap_or(0.0))}</span>
+                        <span>{move || currency.with(|c| format!("{}{}", c, paid.get().unwrap_or_default().format(c)))}</span>
                     </div>
                 </Show>
+                <For each=move || payments.get() key=|p| p.id let:payment>
+                    <div class="display-paid">
+                        <span>{move || label(&i18n.get(), &format!("sale.tender_{}", payment.tender))}</span>
+                        <span>{move || currency.with(|c| format!("{}{}", c, payment.amount.format(c)))}</span>
+                    </div>
+                </For>
                 <Show when=move || change.get().is_some() fallback=|| ()>
                     <div class="display-change">
                         <span>{move || label(&i18n.get(), "sale.change")}</span>
-                        <span>{move || format!("{}{:.2}", currency.get(), change.get().unwrap_or(0.0))}</span>
+                        <span>{move || currency.with(|c| format!("{}{}", c, change.get().unwrap_or_default().format(c)))}</span>
                     </div>
                 </Show>
             </Show>
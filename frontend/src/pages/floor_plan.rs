@@ -0,0 +1,255 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::pages::keyboard::scroll_page_to_top;
+use crate::server_fns::*;
+
+/// What the edit form is open for. `None` ids mean a new area or table.
+#[derive(Clone, Copy, PartialEq)]
+enum FloorForm {
+    Area(Option<Uuid>),
+    Table(Option<Uuid>),
+}
+
+#[component]
+pub fn FloorPlanPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let (authorized, set_authorized) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" => set_authorized.set(true),
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let (areas, set_areas) = signal(Vec::<FloorAreaDetail>::new());
+    let (form, set_form) = signal(Option::<FloorForm>::None);
+    // Area or table waiting for delete confirmation: (is_area, id, name).
+    let (deleting, set_deleting) = signal(Option::<(bool, Uuid, String)>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+
+    let (name, set_name) = signal(String::new());
+    let (seats, set_seats) = signal(String::new());
+    let (area_id, set_area_id) = signal(Option::<Uuid>::None);
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            if let Ok(a) = fetch_floor_plan().await { set_areas.set(a); }
+        });
+    });
+
+    let open_form = move |f: FloorForm, form_name: String, form_seats: i32, form_area: Option<Uuid>| {
+        scroll_page_to_top();
+        set_error.set(None);
+        set_name.set(form_name);
+        set_seats.set(form_seats.to_string());
+        set_area_id.set(form_area);
+        set_form.set(Some(f));
+    };
+
+    let save = move |_| {
+        let Some(f) = form.get() else { return };
+        let n = name.get();
+        let Ok(s) = seats.get().trim().parse::<i32>() else {
+            set_error.set(Some(i18n.get().t("floor.invalid_seats")));
+            return;
+        };
+        let area = area_id.get();
+        set_error.set(None);
+        leptos::task::spawn_local(async move {
+            let result = match (f, area) {
+                (FloorForm::Area(None), _) => create_floor_area(n).await.map(|_| ()),
+                (FloorForm::Area(Some(id)), _) => update_floor_area(id, n).await.map(|_| ()),
+                (FloorForm::Table(None), Some(a)) => create_floor_table(a, n, s).await.map(|_| ()),
+                (FloorForm::Table(Some(id)), Some(a)) => update_floor_table(id, a, n, s).await.map(|_| ()),
+                (FloorForm::Table(_), None) => return,
+            };
+            match result {
+                Ok(()) => {
+                    set_form.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+                Err(e) => set_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let cancel_edit = move |_| {
+        set_form.set(None);
+        set_error.set(None);
+    };
+
+    let delete_handler = move |_| {
+        if let Some((is_area, id, _)) = deleting.get() {
+            leptos::task::spawn_local(async move {
+                let result = if is_area { delete_floor_area(id).await } else { delete_floor_table(id).await };
+                if result.is_ok() {
+                    set_deleting.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+            });
+        }
+    };
+    let cancel_delete = move |_| { set_deleting.set(None); };
+
+    let form_open = move || form.get().is_some();
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div>
+            <div class="page-header">
+                <h2>{move || i18n.get().t("floor.title")}</h2>
+                <div class="page-header-actions">
+                    <button class="btn-primary"
+                        on:click=move |_| open_form(FloorForm::Area(None), String::new(), 0, None)
+                        disabled=form_open
+                    >{move || i18n.get().t("floor.add_area")}</button>
+                </div>
+            </div>
+
+            <Show when=move || deleting.get().is_some() fallback=|| ()>
+                {move || {
+                    deleting.get().map(|(is_area, _, target)| {
+                        let i = i18n.get();
+                        let key = if is_area { "floor.confirm_delete_area" } else { "floor.confirm_delete_table" };
+                        let confirm_msg = i.t(key).replace("{name}", &target);
+                        view! {
+                            <div class="modal-overlay">
+                                <div class="confirmation-modal">
+                                    <h3>{i.t("general.confirm_delete")}</h3>
+                                    <p>{confirm_msg}</p>
+                                    <p class="warning-text">{i.t("floor.delete_warning")}</p>
+                                    <div class="modal-actions">
+                                        <button class="btn-danger" on:click=delete_handler>{i.t("general.delete")}</button>
+                                        <button class="btn-secondary" on:click=cancel_delete>{i.t("general.cancel")}</button>
+                                    </div>
+                                </div>
+                            </div>
+                        }
+                    })
+                }}
+            </Show>
+
+            <Show when=form_open fallback=|| ()>
+                <div class="edit-form">
+                    <h3>{move || match form.get() {
+                        Some(FloorForm::Area(None)) => i18n.get().t("floor.create_area"),
+                        Some(FloorForm::Area(Some(_))) => i18n.get().t("floor.edit_area"),
+                        Some(FloorForm::Table(None)) => i18n.get().t("floor.create_table"),
+                        _ => i18n.get().t("floor.edit_table"),
+                    }}</h3>
+                    <Show when=move || error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="form-grid">
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("floor.name")}</label>
+                            <input type="text" value=move || name.get()
+                                on:input=move |ev| set_name.set(event_target_value(&ev)) />
+                        </div>
+                        <Show when=move || matches!(form.get(), Some(FloorForm::Table(_))) fallback=|| ()>
+                            <div class="form-group">
+                                <label>{move || i18n.get().t("floor.area")}</label>
+                                <select
+                                    prop:value=move || area_id.get().map(|id| id.to_string()).unwrap_or_default()
+                                    on:change=move |ev| set_area_id.set(Uuid::parse_str(&event_target_value(&ev)).ok())
+                                >
+                                    <For each=move || areas.get() key=|a| (a.area.id, a.area.name.clone()) let:a>
+                                        <option value={a.area.id.to_string()}>{a.area.name.clone()}</option>
+                                    </For>
+                                </select>
+                            </div>
+                            <div class="form-group">
+                                <label>{move || i18n.get().t("floor.seats")}</label>
+                                <input type="number" min="0" step="1" value=move || seats.get()
+                                    on:input=move |ev| set_seats.set(event_target_value(&ev)) />
+                            </div>
+                        </Show>
+                    </div>
+                    <div class="form-actions">
+                        <button class="btn-success" on:click=save>{move || i18n.get().t("general.save")}</button>
+                        <button class="btn-secondary" on:click=cancel_edit>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || areas.get().is_empty() fallback=|| ()>
+                <p class="text-muted">{move || i18n.get().t("floor.none")}</p>
+            </Show>
+
+            <For each=move || areas.get() key=|a| (a.area.id, a.area.updated_at, a.tables.iter().map(|t| (t.id, t.updated_at)).collect::<Vec<_>>()) let:detail>
+                {
+                    let area = detail.area.clone();
+                    let area_id = area.id;
+                    let area_name = area.name.clone();
+                    let edit_name = area.name.clone();
+                    let tables = detail.tables.clone();
+                    view! {
+                        <div class="floor-area-admin">
+                            <div class="page-header">
+                                <h3>{area.name.clone()}</h3>
+                                <div class="page-header-actions">
+                                    <button class="btn-small"
+                                        on:click=move |_| open_form(FloorForm::Table(None), String::new(), 4, Some(area_id))
+                                        disabled=form_open
+                                    >{move || i18n.get().t("floor.add_table")}</button>
+                                    <button class="btn-small"
+                                        on:click=move |_| open_form(FloorForm::Area(Some(area_id)), edit_name.clone(), 0, None)
+                                        disabled=form_open
+                                    >{move || i18n.get().t("general.edit")}</button>
+                                    <button class="btn-small btn-danger"
+                                        on:click=move |_| set_deleting.set(Some((true, area_id, area_name.clone())))
+                                        disabled=form_open
+                                    >{move || i18n.get().t("general.delete")}</button>
+                                </div>
+                            </div>
+                            <table class="data-table">
+                                <thead><tr>
+                                    <th>{move || i18n.get().t("floor.table")}</th>
+                                    <th>{move || i18n.get().t("floor.seats")}</th>
+                                    <th></th>
+                                </tr></thead>
+                                <tbody>
+                                    {tables.into_iter().map(|table| {
+                                        let table_id = table.id;
+                                        let table_name = table.name.clone();
+                                        let edit_name = table.name.clone();
+                                        let table_seats = table.seats;
+                                        view! {
+                                            <tr>
+                                                <td>{table.name.clone()}</td>
+                                                <td>{table.seats}</td>
+                                                <td class="data-table-actions">
+                                                    <button class="btn-small"
+                                                        on:click=move |_| open_form(FloorForm::Table(Some(table_id)), edit_name.clone(), table_seats, Some(area_id))
+                                                        disabled=form_open
+                                                    >{move || i18n.get().t("general.edit")}</button>
+                                                    <button class="btn-small btn-danger"
+                                                        on:click=move |_| set_deleting.set(Some((false, table_id, table_name.clone())))
+                                                        disabled=form_open
+                                                    >{move || i18n.get().t("general.delete")}</button>
+                                                </td>
+                                            </tr>
+                                        }
+                                    }).collect_view()}
+                                </tbody>
+                            </table>
+                        </div>
+                    }
+                }
+            </For>
+        </div>
+        </Show>
+    }
+}
@@ -72,6 +72,15 @@ fn handle_image_file(ev: leptos::ev::Event, set_image_preview: WriteSignal<Optio
     let _ = reader.read_as_data_url(&file);
 }
 
+/// One bundle component row in the form: a stable key for `<For>`, the part
+/// as "item:<id>" or "category:<id>" and the quantity input.
+#[derive(Clone)]
+struct ComponentRow {
+    key: usize,
+    part: RwSignal<String>,
+    quantity: RwSignal<String>,
+}
+
 #[component]
 pub fn ItemsPage() -> impl IntoView {
     let i18n = expect_context::<RwSignal<I18n>>();
@@ -98,6 +107,8 @@ pub fn ItemsPage() -> impl IntoView {
     let (name, set_name) = signal(String::new());
     let (description, set_description) = signal(String::new());
     let (price, set_price) = signal(String::new());
+    // One of PRICE_UNITS: "each", or "kg"/"100g" for goods sold by weight.
+    let (price_unit, set_price_unit) = signal(String::from("each"));
     let (category_id, set_category_id) = signal(String::new());
     let (sku, set_sku) = signal(String::new());
     let (in_stock, set_in_stock) = signal(true);
@@ -105,6 +116,19 @@ pub fn ItemsPage() -> impl IntoView {
     let (track_stock, set_track_stock) = signal(false);
     let (stock_quantity, set_stock_quantity) = signal(String::new());
     let (kitchen_item, set_kitchen_item) = signal(false);
+    // Tax rate override id, empty to inherit the category's rate.
+    let (tax_rate_id, set_tax_rate_id) = signal(String::new());
+    let (tax_rates, set_tax_rates) = signal(Vec::<TaxRate>::new());
+    // Whether the item is a deposit (Pfand) item, and the deposit item id
+    // charged with it, empty for none.
+    let (is_deposit, set_is_deposit) = signal(false);
+    let (deposit_item_id, set_deposit_item_id) = signal(String::new());
+    // Why the last save failed, e.g. a SKU already used by another item.
+    let (error, set_error) = signal(Option::<String>::None);
+    // Components that make the item a bundle; empty for a plain item.
+    let (bundle_components, set_bundle_components) = signal(Vec::<BundleComponent>::new());
+    let (component_rows, set_component_rows) = signal(Vec::<ComponentRow>::new());
+    let (next_key, set_next_key) = signal(0usize);
 
     // On-screen keyboard target: "name", "description" or "sku" (hidden on mobile via CSS)
     let (kb_target, set_kb_target) = signal(Option::<String>::None);
@@ -151,17 +175,49 @@ pub fn ItemsPage() -> impl IntoView {
         leptos::task::spawn_local(async move {
             if let Ok(items_data) = fetch_items().await { set_items.set(items_data); }
             if let Ok(cats) = fetch_categories().await { set_categories.set(cats); }
+            if let Ok(rates) = fetch_tax_rates().await { set_tax_rates.set(rates); }
+            if let Ok(c) = fetch_bundle_components().await { set_bundle_components.set(c); }
         });
     });
 
+    let push_component = move |part: String, quantity: i32| {
+        let key = next_key.get_untracked();
+        set_next_key.set(key + 1);
+        set_component_rows.update(|rows| rows.push(ComponentRow {
+            key,
+            part: RwSignal::new(part),
+            quantity: RwSignal::new(quantity.to_string()),
+        }));
+    };
+
+    // The component rows as entered, or the message for the first bad one.
+    let component_inputs = move || -> Result<Vec<BundleComponentInput>, String> {
+        let mut inputs = Vec::new();
+        for row in component_rows.get() {
+            let part = row.part.get();
+            let (kind, id) = part.split_once(':').unwrap_or_default();
+            let (Ok(id), Ok(quantity)) = (id.parse::<Uuid>(), row.quantity.get().trim().parse::<i32>()) else {
+                return Err(i18n.get().t("items.invalid_component"));
+            };
+            inputs.push(BundleComponentInput {
+                item_id: Some(id).filter(|_| kind == "item"),
+                category_id: Some(id).filter(|_| kind == "category"),
+                quantity,
+            });
+        }
+        Ok(inputs)
+    };
+
     let start_edit = move |item: Item| {
         set_kb_target.set(None);
+        set_error.set(None);
         // The edit form renders above the (potentially long) item list, so bring
         // it into view.
         scroll_page_to_top();
         set_name.set(item.name.clone());
         set_description.set(item.description.clone().unwrap_or_default());
-        set_price.set(item.price.to_string());
+        set_price.set(item.price.format(&currency.get_untracked()));
+        set_price_unit.set(item.price_unit.clone());
         set_category_id.set(item.category_id.to_string());
         set_sku.set(item.sku.clone().unwrap_or_default());
         set_in_stock.set(item.in_stock);
@@ -169,17 +225,40 @@ pub fn ItemsPage() -> impl IntoView {
         set_track_stock.set(item.stock_quantity.is_some());
         set_stock_quantity.set(item.stock_quantity.map(|q| q.to_string()).unwrap_or_default());
         set_kitchen_item.set(item.kitchen_item);
+        set_tax_rate_id.set(item.tax_rate_id.map(|id| id.to_string()).unwrap_or_default());
+        set_is_deposit.set(item.is_deposit);
+        set_deposit_item_id.set(item.deposit_item_id.map(|id| id.to_string()).unwrap_or_default());
+        set_component_rows.set(vec![]);
+        for c in bundle_components.get().iter().filter(|c| c.bundle_item_id == item.id) {
+            let part = match (c.item_id, c.category_id) {
+                (Some(id), _) => format!("item:{}", id),
+                (None, Some(id)) => format!("category:{}", id),
+                (None, None) => continue,
+            };
+            push_component(part, c.quantity);
+        }
         set_editing_item.set(Some(item));
     };
 
     let save_item = move |_| {
         let editing = editing_item.get();
         let creating = creating_item.get();
-        if let Ok(price_val) = price.get().parse::<f64>() {
+        if let Some(price_val) = Money::parse(&price.get(), &currency.get_untracked()) {
             if let Ok(cat_id) = category_id.get().parse::<Uuid>() {
                 let ts = track_stock.get();
                 let sq = if ts { stock_quantity.get().parse::<i32>().ok() } else { None };
                 let ki = Some(kitchen_item.get());
+                let tr = tax_rate_id.get().parse::<Uuid>().ok();
+                let pu = Some(price_unit.get());
+                let dep = Some(is_deposit.get());
+                let dep_item = deposit_item_id.get().parse::<Uuid>().ok().filter(|_| !is_deposit.get());
+                let components = match component_inputs() {
+                    Ok(c) => c,
+                    Err(e) => {
+                        set_error.set(Some(e));
+                        return;
+                    }
+                };
 
                 if creating {
                     let n = name.get();
@@ -188,15 +267,25 @@ pub fn ItemsPage() -> impl IntoView {
                     let stock = Some(in_stock.get());
                     let img_data = image_preview.get();
                     leptos::task::spawn_local(async move {
-                        if let Ok(new_item) = create_item(n, d, price_val, cat_id, s, stock, sq, ki).await {
-                            if let Some(data) = img_data {
-                                if data.starts_with("data:") {
-                                    let _ = upload_item_image(new_item.id, data).await;
+                        match create_item(n, d, price_val, cat_id, s, stock, sq, ki, tr, pu, dep, dep_item).await {
+                            Ok(new_item) => {
+                                if let Some(data) = img_data {
+                                    if data.starts_with("data:") {
+                                        let _ = upload_item_image(new_item.id, data).await;
+                                    }
+                                }
+                                set_image_preview.set(None);
+                                set_creating_item.set(false);
+                                set_reload.update(|v| *v += 1);
+                                if let Err(e) = crate::server_fns::set_bundle_components(new_item.id, components).await {
+                                    // The item exists now; keep the form open on it to fix the bundle.
+                                    set_error.set(Some(e.to_string().replace("error running server function: ", "")));
+                                    set_editing_item.set(Some(new_item));
                                 }
                             }
-                            set_creating_item.set(false);
-                            set_image_preview.set(None);
-                            set_reload.update(|v| *v += 1);
+                            Err(e) => set_error.set(Some(
+                                e.to_string().replace("error running server function: ", ""),
+                            )),
                         }
                     });
                 } else if let Some(item) = editing {
@@ -208,7 +297,13 @@ pub fn ItemsPage() -> impl IntoView {
                     let img_data = image_preview.get();
                     let had_image = item.image_path.is_some();
                     leptos::task::spawn_local(async move {
-                        if update_item(item_id, n, d, Some(price_val), Some(cat_id), s, stock, sq, Some(ts), ki).await.is_ok() {
+                        let result = match update_item(item_id, n, d, Some(price_val), Some(cat_id), s, stock, sq, Some(ts), ki, tr, pu, dep, dep_item).await {
+                            Ok(_) => crate::server_fns::set_bundle_components(item_id, components).await,
+                            Err(e) => Err(e),
+                        };
+                        if let Err(e) = result {
+                            set_error.set(Some(e.to_string().replace("error running server function: ", "")));
+                        } else {
                             match img_data.as_deref() {
                                 Some(data) if data.starts_with("data:") => {
                                     let _ = upload_item_image(item_id, data.to_string()).await;
@@ -239,21 +334,30 @@ pub fn ItemsPage() -> impl IntoView {
     let cancel_delete = move |_| { set_deleting_item.set(None); };
     let cancel_edit = move |_| {
         set_kb_target.set(None);
+        set_error.set(None);
         set_editing_item.set(None); set_creating_item.set(false);
         set_name.set(String::new()); set_description.set(String::new());
         set_price.set(String::new()); set_category_id.set(String::new());
+        set_price_unit.set("each".to_string());
         set_sku.set(String::new()); set_in_stock.set(true);
         set_image_preview.set(None); set_track_stock.set(false);
         set_stock_quantity.set(String::new()); set_kitchen_item.set(false);
+        set_tax_rate_id.set(String::new());
+        set_is_deposit.set(false); set_deposit_item_id.set(String::new());
     };
     let start_create = move |_| {
         set_kb_target.set(None);
+        set_error.set(None);
         set_name.set(String::new()); set_description.set(String::new());
         set_price.set(String::new());
+        set_price_unit.set("each".to_string());
         set_category_id.set(if let Some(cat) = categories.get().first() { cat.id.to_string() } else { String::new() });
         set_sku.set(String::new()); set_in_stock.set(true);
         set_image_preview.set(None); set_track_stock.set(false);
         set_stock_quantity.set(String::new()); set_kitchen_item.set(false);
+        set_tax_rate_id.set(String::new());
+        set_is_deposit.set(false); set_deposit_item_id.set(String::new());
+        set_component_rows.set(vec![]);
         set_creating_item.set(true); set_editing_item.set(None);
     };
 
@@ -274,12 +378,29 @@ pub fn ItemsPage() -> impl IntoView {
         });
     };
 
+    // Open sales keep the prices they were rung up at until an admin
+    // explicitly reprices them.
+    let (confirm_reprice, set_confirm_reprice) = signal(false);
+    let (reprice_message, set_reprice_message) = signal(Option::<String>::None);
+    let reprice_handler = move |_| {
+        set_confirm_reprice.set(false);
+        leptos::task::spawn_local(async move {
+            let msg = match reprice_open_transactions().await {
+                Ok(n) => i18n.get_untracked().t("items.repriced").replace("{n}", &n.to_string()),
+                Err(e) => e.to_string().replace("error running server function: ", ""),
+            };
+            set_reprice_message.set(Some(msg));
+        });
+    };
+
     view! {
         <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
         <div>
             <div class="page-header">
                 <h2>{move || i18n.get().t("items.title")}</h2>
                 <div class="page-header-actions">
+                    <button class="btn-secondary" on:click=move |_| set_confirm_reprice.set(true)
+                    >{move || i18n.get().t("items.reprice_open")}</button>
                     <button class="btn-secondary" on:click=download_menu
                         disabled=move || generating_menu.get()
                     >{move || if generating_menu.get() { i18n.get().t("categories.generating_menu") } else { i18n.get().t("categories.print_menu") }}</button>
@@ -289,6 +410,23 @@ pub fn ItemsPage() -> impl IntoView {
                 </div>
             </div>
 
+            <Show when=move || reprice_message.get().is_some() fallback=|| ()>
+                <p class="text-muted">{move || reprice_message.get().unwrap_or_default()}</p>
+            </Show>
+
+            <Show when=move || confirm_reprice.get() fallback=|| ()>
+                <div class="modal-overlay">
+                    <div class="confirmation-modal">
+                        <h3>{move || i18n.get().t("items.reprice_open")}</h3>
+                        <p>{move || i18n.get().t("items.reprice_explain")}</p>
+                        <div class="modal-actions">
+                            <button class="btn-primary" on:click=reprice_handler>{move || i18n.get().t("items.reprice_confirm")}</button>
+                            <button class="btn-secondary" on:click=move |_| set_confirm_reprice.set(false)>{move || i18n.get().t("general.cancel")}</button>
+                        </div>
+                    </div>
+                </div>
+            </Show>
+
             <Show when=move || deleting_item.get().is_some() fallback=|| ()>
                 {move || {
                     deleting_item.get().map(|(_, item_name)| {
@@ -313,6 +451,9 @@ pub fn ItemsPage() -> impl IntoView {
             <Show when=move || editing_item.get().is_some() || creating_item.get() fallback=|| ()>
                 <div class="edit-form">
                     <h3>{move || if creating_item.get() { i18n.get().t("items.create_item") } else { i18n.get().t("items.edit_item") }}</h3>
+                    <Show when=move || error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || error.get().unwrap_or_default()}</p>
+                    </Show>
                     <div class="form-grid">
                         <div class="form-group">
                             <label>{move || i18n.get().t("general.name")}</label>
@@ -330,6 +471,14 @@ pub fn ItemsPage() -> impl IntoView {
                                     on:input=move |ev| set_price.set(event_target_value(&ev)) />
                             </div>
                         </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("items.price_unit")}</label>
+                            <select prop:value=move || price_unit.get() on:change=move |ev| set_price_unit.set(event_target_value(&ev))>
+                                <option value="each">{move || i18n.get().t("items.per_piece")}</option>
+                                <option value="kg">{move || i18n.get().t("items.per_kg")}</option>
+                                <option value="100g">{move || i18n.get().t("items.per_100g")}</option>
+                            </select>
+                        </div>
                         <div class="form-group">
                             <label>{move || i18n.get().t("items.category")}</label>
                             <select prop:value=move || category_id.get() on:change=move |ev| set_category_id.set(event_target_value(&ev))>
@@ -338,6 +487,15 @@ pub fn ItemsPage() -> impl IntoView {
                                 </For>
                             </select>
                         </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("tax.rate")}</label>
+                            <select prop:value=move || tax_rate_id.get() on:change=move |ev| set_tax_rate_id.set(event_target_value(&ev))>
+                                <option value="">{move || i18n.get().t("tax.inherit")}</option>
+                                <For each=move || tax_rates.get() key=|r| r.id let:rate>
+                                    <option value={rate.id.to_string()}>{format!("{} ({}%)", rate.name, rate.rate)}</option>
+                                </For>
+                            </select>
+                        </div>
                         <div class="form-group">
                             <label>{move || i18n.get().t("items.sku")}</label>
                             <div class="admin-input-row">
@@ -366,6 +524,23 @@ pub fn ItemsPage() -> impl IntoView {
                                 " " {move || i18n.get().t("items.kitchen_item")}
                             </label>
                         </div>
+                        <div class="form-group">
+                            <label>
+                                <input type="checkbox" checked=move || is_deposit.get() on:change=move |ev| set_is_deposit.set(event_target_checked(&ev)) />
+                                " " {move || i18n.get().t("items.is_deposit")}
+                            </label>
+                        </div>
+                        <Show when=move || !is_deposit.get() fallback=|| ()>
+                            <div class="form-group">
+                                <label>{move || i18n.get().t("items.deposit")}</label>
+                                <select prop:value=move || deposit_item_id.get() on:change=move |ev| set_deposit_item_id.set(event_target_value(&ev))>
+                                    <option value="">{move || i18n.get().t("items.no_deposit")}</option>
+                                    <For each=move || { items.get().into_iter().filter(|i| i.is_deposit).collect::<Vec<_>>() } key=|i| (i.id, i.name.clone(), i.price.0) let:dep>
+                                        <option value={dep.id.to_string()}>{currency.with(|c| format!("{} ({} {})", dep.name, c, dep.price.format(c)))}</option>
+                                    </For>
+                                </select>
+                            </div>
+                        </Show>
                         <div class="form-group">
                             <label>
                                 <input type="checkbox" checked=move || track_stock.get() on:change=move |ev| set_track_stock.set(event_target_checked(&ev)) />
@@ -390,6 +565,56 @@ pub fn ItemsPage() -> impl IntoView {
                             </Show>
                         </div>
                     </div>
+
+                    <h4>{move || i18n.get().t("items.bundle_components")}</h4>
+                    <p class="text-muted">{move || i18n.get().t("items.bundle_explain")}</p>
+                    <Show when=move || !component_rows.get().is_empty() fallback=|| ()>
+                        <table class="data-table bundle-components-table">
+                            <thead><tr>
+                                <th>{move || i18n.get().t("items.component")}</th>
+                                <th>{move || i18n.get().t("items.quantity")}</th>
+                                <th></th>
+                            </tr></thead>
+                            <tbody>
+                                <For each=move || component_rows.get() key=|row| row.key let:row>
+                                    {
+                                        let key = row.key;
+                                        let editing_id = editing_item.get_untracked().map(|i| i.id);
+                                        view! {
+                                            <tr>
+                                                <td>
+                                                    <select prop:value=move || row.part.get()
+                                                        on:change=move |ev| row.part.set(event_target_value(&ev))>
+                                                        <option value="">"-"</option>
+                                                        <optgroup label=move || i18n.get().t("items.choice_from")>
+                                                            <For each=move || categories.get() key=|c| c.id let:cat>
+                                                                <option value={format!("category:{}", cat.id)}>{cat.name.clone()}</option>
+                                                            </For>
+                                                        </optgroup>
+                                                        <optgroup label=move || i18n.get().t("items.fixed_item")>
+                                                            <For each=move || { items.get().into_iter().filter(|i| Some(i.id) != editing_id).collect::<Vec<_>>() } key=|i| i.id let:item>
+                                                                <option value={format!("item:{}", item.id)}>{item.name.clone()}</option>
+                                                            </For>
+                                                        </optgroup>
+                                                    </select>
+                                                </td>
+                                                <td><input type="number" min="1" step="1" value=move || row.quantity.get()
+                                                    on:input=move |ev| row.quantity.set(event_target_value(&ev)) /></td>
+                                                <td class="data-table-actions">
+                                                    <button class="btn-small btn-danger"
+                                                        on:click=move |_| set_component_rows.update(|rows| rows.retain(|r| r.key != key))
+                                                    >{move || i18n.get().t("general.delete")}</button>
+                                                </td>
+                                            </tr>
+                                        }
+                                    }
+                                </For>
+                            </tbody>
+                        </table>
+                    </Show>
+                    <button class="btn-small" on:click=move |_| push_component(String::new(), 1)
+                    >{move || i18n.get().t("items.add_component")}</button>
+
                     <Show when=move || matches!(kb_target.get().as_deref(), Some("price") | Some("stock")) fallback=|| ()>
                         <NumericKeyboard on_key=on_kb_key i18n=i18n />
                     </Show>
@@ -414,7 +639,7 @@ pub fn ItemsPage() -> impl IntoView {
                     <th></th>
                 </tr></thead>
                 <tbody>
-                    <For each=move || items.get() key=|i| (i.id, i.name.clone(), i.description.clone(), i.price.to_bits(), i.in_stock, i.sku.clone(), i.category_id, i.image_path.clone(), i.stock_quantity, i.kitchen_item) let:item>
+                    <For each=move || items.get() key=|i| (i.id, i.name.clone(), i.description.clone(), i.price.0, i.in_stock, i.sku.clone(), i.category_id, i.image_path.clone(), i.stock_quantity, i.kitchen_item, (i.price_unit.clone(), i.is_deposit, i.deposit_item_id)) let:item>
                         {
                             let item_clone = item.clone();
                             let item_id = item.id;
@@ -429,8 +654,14 @@ pub fn ItemsPage() -> impl IntoView {
                                     <td class="item-thumb-cell">
                                         {item.image_path.clone().map(|path| view! { <img class="item-thumb" src=path alt="" /> })}
                                     </td>
-                                    <td>{item.name.clone()}</td>
-                                    <td>{format!("{} {:.2}", &currency.get(), item.price)}</td>
+                                    <td>
+                                        {item.name.clone()}
+                                        <Show when=move || bundle_components.get().iter().any(|c| c.bundle_item_id == item_id) fallback=|| ()>
+                                            " "<span class="bundle-tag">{move || i18n.get().t("items.bundle")}</span>
+                                        </Show>
+                                        {item.is_deposit.then(|| view! { " "<span class="bundle-tag">{move || i18n.get().t("items.deposit_tag")}</span> })}
+                                    </td>
+                                    <td>{currency.with(|c| format!("{} {}{}", c, item.price.format(c), item.price_suffix()))}</td>
                                     <td>{move || categories.get().iter()
                                         .find(|c| c.id == item_category_id)
                                         .map(|c| c.name.clone())
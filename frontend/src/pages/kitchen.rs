@@ -231,7 +231,7 @@ pub fn KitchenPage() -> impl IntoView {
                                 <div class=card_class>
                                     <div class="kitchen-order-header">
                                         <span class="kitchen-customer">
-                                            {order.customer_name.clone().unwrap_or_else(|| i18n.get().t("general.walkin"))}
+                                            {order.table_name.clone().or(order.customer_name.clone()).unwrap_or_else(|| i18n.get().t("general.walkin"))}
                                         </span>
                                         <span class="kitchen-time">{move || format_elapsed(created, tick.get())}</span>
                                     </div>
@@ -243,7 +243,11 @@ pub fn KitchenPage() -> impl IntoView {
                                                 view! {
                                                     <div class=if done { "kitchen-item-row kitchen-item-done" } else { "kitchen-item-row" }>
                                                         <span class="kitchen-item-qty">{format!("{}x", item.quantity)}</span>
-                                                        <span class="kitchen-item-name">{item.item_name.clone()}</span>
+                                                        <span class="kitchen-item-name">
+                                                            {item.item_name.clone()}
+                                                            {item.modifiers.clone().map(|m| view! { <span class="kitchen-item-modifiers">{m}</span> })}
+                                                            {item.note.clone().map(|n| view! { <span class="kitchen-item-note">{n}</span> })}
+                                                        </span>
                                                         {if done {
                                                             view! { <span class="kitchen-done-check">{i18n.get().t("kitchen.done")}</span> }.into_any()
                                                         } else {
@@ -279,7 +283,7 @@ pub fn KitchenPage() -> impl IntoView {
                             <div class="kitchen-order-card kitchen-order-completed">
                                 <div class="kitchen-order-header">
                                     <span class="kitchen-customer">
-                                        {order.customer_name.clone().unwrap_or_else(|| i18n.get().t("general.walkin"))}
+                                        {order.table_name.clone().or(order.customer_name.clone()).unwrap_or_else(|| i18n.get().t("general.walkin"))}
                                     </span>
                                     <span class="kitchen-time">{order.created_at.format("%H:%M").to_string()}</span>
                                 </div>
@@ -287,7 +291,11 @@ pub fn KitchenPage() -> impl IntoView {
                                     <For each=move || order.items.clone() key=|i| i.transaction_item_id let:item>
                                         <div class="kitchen-item-row kitchen-item-done">
                                             <span class="kitchen-item-qty">{format!("{}x", item.quantity)}</span>
-                                            <span class="kitchen-item-name">{item.item_name.clone()}</span>
+                                            <span class="kitchen-item-name">
+                                                {item.item_name.clone()}
+                                                {item.modifiers.clone().map(|m| view! { <span class="kitchen-item-modifiers">{m}</span> })}
+                                                {item.note.clone().map(|n| view! { <span class="kitchen-item-note">{n}</span> })}
+                                            </span>
                                             <span class="kitchen-done-check">{i18n.get().t("kitchen.done")}</span>
                                         </div>
                                     </For>
@@ -3,7 +3,15 @@ mod transactions;
 mod items;
 mod categories;
 mod customer_groups;
+mod customers;
+mod accounts;
+mod modifiers;
+mod promotions;
+mod discount_reasons;
+mod floor_plan;
 mod reports;
+mod closings;
+mod cash_drawer;
 mod kitchen;
 mod login;
 mod admin;
@@ -16,7 +24,15 @@ pub use transactions::TransactionsPage;
 pub use items::ItemsPage;
 pub use categories::CategoriesPage;
 pub use customer_groups::CustomerGroupsPage;
+pub use customers::CustomersPage;
+pub use accounts::AccountsPage;
+pub use modifiers::ModifiersPage;
+pub use promotions::PromotionsPage;
+pub use discount_reasons::DiscountReasonsPage;
+pub use floor_plan::FloorPlanPage;
 pub use reports::ReportsPage;
+pub use closings::ClosingsPage;
+pub use cash_drawer::CashDrawerPage;
 pub use kitchen::KitchenPage;
 pub use login::LoginPage;
 pub use admin::AdminPage;
@@ -0,0 +1,366 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::pages::keyboard::scroll_page_to_top;
+use crate::server_fns::*;
+
+/// One option row in the form: a stable key for `<For>`, the id of an
+/// existing option (None for new ones) and the name and price delta inputs.
+#[derive(Clone)]
+struct OptionRow {
+    key: usize,
+    id: Option<Uuid>,
+    name: RwSignal<String>,
+    price_delta: RwSignal<String>,
+}
+
+#[component]
+pub fn ModifiersPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let (authorized, set_authorized) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" => set_authorized.set(true),
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let (groups, set_groups) = signal(Vec::<ModifierGroupDetail>::new());
+    let (categories, set_categories) = signal(Vec::<Category>::new());
+    let (items, set_items) = signal(Vec::<Item>::new());
+    let (editing_group, set_editing_group) = signal(Option::<Uuid>::None);
+    let (creating_group, set_creating_group) = signal(false);
+    let (deleting_group, set_deleting_group) = signal(Option::<(Uuid, String)>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+
+    let (name, set_name) = signal(String::new());
+    let (min_picks, set_min_picks) = signal(String::from("0"));
+    // Empty means no upper limit.
+    let (max_picks, set_max_picks) = signal(String::new());
+    let (options, set_options) = signal(Vec::<OptionRow>::new());
+    let (next_key, set_next_key) = signal(0usize);
+    let (item_ids, set_item_ids) = signal(Vec::<Uuid>::new());
+    let (category_ids, set_category_ids) = signal(Vec::<Uuid>::new());
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            if let Ok(g) = fetch_modifier_groups().await { set_groups.set(g); }
+            if let Ok(c) = fetch_categories().await { set_categories.set(c); }
+            if let Ok(i) = fetch_items().await { set_items.set(i); }
+        });
+    });
+
+    let push_option = move |id: Option<Uuid>, option_name: String, price_delta: Money| {
+        let key = next_key.get_untracked();
+        set_next_key.set(key + 1);
+        set_options.update(|list| list.push(OptionRow {
+            key,
+            id,
+            name: RwSignal::new(option_name),
+            price_delta: RwSignal::new(price_delta.format(&currency.get_untracked())),
+        }));
+    };
+
+    let start_edit = move |detail: ModifierGroupDetail| {
+        scroll_page_to_top();
+        set_error.set(None);
+        set_name.set(detail.group.name.clone());
+        set_min_picks.set(detail.group.min_picks.to_string());
+        set_max_picks.set(detail.group.max_picks.map(|m| m.to_string()).unwrap_or_default());
+        set_options.set(vec![]);
+        for m in &detail.modifiers {
+            push_option(Some(m.id), m.name.clone(), m.price_delta);
+        }
+        set_item_ids.set(detail.item_ids.clone());
+        set_category_ids.set(detail.category_ids.clone());
+        set_editing_group.set(Some(detail.group.id));
+        set_creating_group.set(false);
+    };
+
+    let start_create = move |_| {
+        set_error.set(None);
+        set_name.set(String::new());
+        set_min_picks.set("0".to_string());
+        set_max_picks.set(String::new());
+        set_options.set(vec![]);
+        push_option(None, String::new(), Money::ZERO);
+        set_item_ids.set(vec![]);
+        set_category_ids.set(vec![]);
+        set_creating_group.set(true);
+        set_editing_group.set(None);
+    };
+
+    let save_group = move |_| {
+        let editing = editing_group.get();
+        let creating = creating_group.get();
+        let Ok(min) = min_picks.get().trim().parse::<i32>() else {
+            set_error.set(Some(i18n.get().t("modifiers.invalid_picks")));
+            return;
+        };
+        let max = match max_picks.get().trim() {
+            "" => None,
+            s => match s.parse::<i32>() {
+                Ok(m) => Some(m),
+                Err(_) => {
+                    set_error.set(Some(i18n.get().t("modifiers.invalid_picks")));
+                    return;
+                }
+            },
+        };
+        let mut modifiers = Vec::new();
+        for row in options.get() {
+            let Some(price_delta) = Money::parse(&row.price_delta.get(), &currency.get_untracked()) else {
+                set_error.set(Some(i18n.get().t("modifiers.invalid_price")));
+                return;
+            };
+            modifiers.push(ModifierInput { id: row.id, name: row.name.get(), price_delta });
+        }
+        let input = ModifierGroupInput {
+            name: name.get(),
+            min_picks: min,
+            max_picks: max,
+            modifiers,
+            item_ids: item_ids.get(),
+            category_ids: category_ids.get(),
+        };
+        set_error.set(None);
+        leptos::task::spawn_local(async move {
+            let result = if creating {
+                create_modifier_group(input).await
+            } else if let Some(id) = editing {
+                update_modifier_group(id, input).await
+            } else {
+                return;
+            };
+            match result {
+                Ok(_) => {
+                    set_creating_group.set(false);
+                    set_editing_group.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+                Err(e) => set_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let cancel_edit = move |_| {
+        set_editing_group.set(None);
+        set_creating_group.set(false);
+        set_error.set(None);
+    };
+
+    let confirm_delete = move |id: Uuid, name: String| { set_deleting_group.set(Some((id, name))); };
+    let delete_group_handler = move |_| {
+        if let Some((id, _)) = deleting_group.get() {
+            leptos::task::spawn_local(async move {
+                if delete_modifier_group(id).await.is_ok() {
+                    set_deleting_group.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+            });
+        }
+    };
+    let cancel_delete = move |_| { set_deleting_group.set(None); };
+
+    let toggle = move |set: WriteSignal<Vec<Uuid>>, id: Uuid, on: bool| {
+        set.update(|ids| {
+            ids.retain(|i| *i != id);
+            if on { ids.push(id); }
+        });
+    };
+
+    let form_open = move || editing_group.get().is_some() || creating_group.get();
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div>
+            <div class="page-header">
+                <h2>{move || i18n.get().t("modifiers.title")}</h2>
+                <div class="page-header-actions">
+                    <button class="btn-primary" on:click=start_create disabled=form_open
+                    >{move || i18n.get().t("modifiers.add")}</button>
+                </div>
+            </div>
+
+            <Show when=move || deleting_group.get().is_some() fallback=|| ()>
+                {move || {
+                    deleting_group.get().map(|(_, group_name)| {
+                        let i = i18n.get();
+                        let confirm_msg = i.t("modifiers.confirm_delete").replace("{name}", &group_name);
+                        view! {
+                            <div class="modal-overlay">
+                                <div class="confirmation-modal">
+                                    <h3>{i.t("general.confirm_delete")}</h3>
+                                    <p>{confirm_msg}</p>
+                                    <div class="modal-actions">
+                                        <button class="btn-danger" on:click=delete_group_handler>{i.t("general.delete")}</button>
+                                        <button class="btn-secondary" on:click=cancel_delete>{i.t("general.cancel")}</button>
+                                    </div>
+                                </div>
+                            </div>
+                        }
+                    })
+                }}
+            </Show>
+
+            <Show when=form_open fallback=|| ()>
+                <div class="edit-form">
+                    <h3>{move || if creating_group.get() { i18n.get().t("modifiers.create") } else { i18n.get().t("modifiers.edit") }}</h3>
+                    <Show when=move || error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="form-grid">
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("modifiers.name")}</label>
+                            <input type="text" value=move || name.get()
+                                on:input=move |ev| set_name.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("modifiers.min_picks")}</label>
+                            <input type="number" min="0" step="1" value=move || min_picks.get()
+                                on:input=move |ev| set_min_picks.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("modifiers.max_picks")}</label>
+                            <input type="number" min="1" step="1" value=move || max_picks.get()
+                                placeholder=move || i18n.get().t("modifiers.no_limit")
+                                on:input=move |ev| set_max_picks.set(event_target_value(&ev)) />
+                        </div>
+                    </div>
+
+                    <h4>{move || i18n.get().t("modifiers.options")}</h4>
+                    <table class="data-table modifier-options-table">
+                        <thead><tr>
+                            <th>{move || i18n.get().t("modifiers.option")}</th>
+                            <th>{move || format!("{} ({})", i18n.get().t("modifiers.price_delta"), currency.get())}</th>
+                            <th></th>
+                        </tr></thead>
+                        <tbody>
+                            <For each=move || options.get() key=|row| row.key let:row>
+                                {
+                                    let key = row.key;
+                                    view! {
+                                        <tr>
+                                            <td><input type="text" value=move || row.name.get()
+                                                on:input=move |ev| row.name.set(event_target_value(&ev)) /></td>
+                                            <td><input type="text" inputmode="decimal" value=move || row.price_delta.get()
+                                                on:input=move |ev| row.price_delta.set(event_target_value(&ev)) /></td>
+                                            <td class="data-table-actions">
+                                                <button class="btn-small btn-danger"
+                                                    on:click=move |_| set_options.update(|list| list.retain(|r| r.key != key))
+                                                >{move || i18n.get().t("general.delete")}</button>
+                                            </td>
+                                        </tr>
+                                    }
+                                }
+                            </For>
+                        </tbody>
+                    </table>
+                    <button class="btn-small" on:click=move |_| push_option(None, String::new(), Money::ZERO)
+                    >{move || i18n.get().t("modifiers.add_option")}</button>
+
+                    <h4>{move || i18n.get().t("modifiers.categories")}</h4>
+                    <div class="modifier-links">
+                        <For each=move || categories.get() key=|c| c.id let:cat>
+                            {
+                                let id = cat.id;
+                                view! {
+                                    <label class="modifier-link">
+                                        <input type="checkbox" checked=move || category_ids.get().contains(&id)
+                                            on:change=move |ev| toggle(set_category_ids, id, event_target_checked(&ev)) />
+                                        {cat.name.clone()}
+                                    </label>
+                                }
+                            }
+                        </For>
+                    </div>
+
+                    <h4>{move || i18n.get().t("modifiers.items")}</h4>
+                    <div class="modifier-links">
+                        <For each=move || items.get() key=|i| i.id let:item>
+                            {
+                                let id = item.id;
+                                view! {
+                                    <label class="modifier-link">
+                                        <input type="checkbox" checked=move || item_ids.get().contains(&id)
+                                            on:change=move |ev| toggle(set_item_ids, id, event_target_checked(&ev)) />
+                                        {item.name.clone()}
+                                    </label>
+                                }
+                            }
+                        </For>
+                    </div>
+
+                    <div class="form-actions">
+                        <button class="btn-success" on:click=save_group>{move || i18n.get().t("general.save")}</button>
+                        <button class="btn-secondary" on:click=cancel_edit>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || groups.get().is_empty() fallback=|| ()>
+                <p class="text-muted">{move || i18n.get().t("modifiers.none")}</p>
+            </Show>
+
+            <table class="data-table">
+                <thead><tr>
+                    <th>{move || i18n.get().t("modifiers.name")}</th>
+                    <th>{move || i18n.get().t("modifiers.picks")}</th>
+                    <th>{move || i18n.get().t("modifiers.options")}</th>
+                    <th>{move || i18n.get().t("modifiers.attached_to")}</th>
+                    <th></th>
+                </tr></thead>
+                <tbody>
+                    <For each=move || groups.get() key=|g| (g.group.id, g.group.updated_at) let:detail>
+                        {
+                            let detail_clone = detail.clone();
+                            let group_id = detail.group.id;
+                            let group_name = detail.group.name.clone();
+                            let picks = match detail.group.max_picks {
+                                Some(max) => format!("{}–{}", detail.group.min_picks, max),
+                                None => format!("{}+", detail.group.min_picks),
+                            };
+                            let option_names = detail.modifiers.iter().map(|m| m.name.as_str()).collect::<Vec<_>>().join(", ");
+                            let cats = detail.category_ids.clone();
+                            let its = detail.item_ids.clone();
+                            let attached = move || {
+                                let mut names: Vec<String> = categories.get().into_iter().filter(|c| cats.contains(&c.id)).map(|c| c.name).collect();
+                                names.extend(items.get().into_iter().filter(|i| its.contains(&i.id)).map(|i| i.name));
+                                names.join(", ")
+                            };
+                            view! {
+                                <tr>
+                                    <td>{detail.group.name.clone()}</td>
+                                    <td>{picks}</td>
+                                    <td>{option_names}</td>
+                                    <td>{attached}</td>
+                                    <td class="data-table-actions">
+                                        <button class="btn-small" on:click=move |_| start_edit(detail_clone.clone())
+                                            disabled=form_open
+                                        >{move || i18n.get().t("general.edit")}</button>
+                                        <button class="btn-small btn-danger" on:click=move |_| confirm_delete(group_id, group_name.clone())
+                                            disabled=form_open
+                                        >{move || i18n.get().t("general.delete")}</button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+        </div>
+        </Show>
+    }
+}
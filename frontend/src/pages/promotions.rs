@@ -0,0 +1,476 @@
+use leptos::prelude::*;
+use uuid::Uuid;
+
+use crate::i18n::I18n;
+use crate::models::*;
+use crate::pages::keyboard::scroll_page_to_top;
+use crate::server_fns::*;
+
+/// Encodes an item-or-category choice for a `<select>`: "i:<id>", "c:<id>"
+/// or "" for neither.
+fn scope_value(item_id: Option<Uuid>, category_id: Option<Uuid>) -> String {
+    match (item_id, category_id) {
+        (Some(id), _) => format!("i:{}", id),
+        (None, Some(id)) => format!("c:{}", id),
+        (None, None) => String::new(),
+    }
+}
+
+/// The `(item, category)` of a value made by [`scope_value`].
+fn parse_scope(value: &str) -> (Option<Uuid>, Option<Uuid>) {
+    let id = |s: &str| Uuid::parse_str(s).ok();
+    match value.split_once(':') {
+        Some(("i", s)) => (id(s), None),
+        Some(("c", s)) => (None, id(s)),
+        _ => (None, None),
+    }
+}
+
+fn optional(value: String) -> Option<String> {
+    let value = value.trim().to_string();
+    (!value.is_empty()).then_some(value)
+}
+
+#[component]
+pub fn PromotionsPage() -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let (authorized, set_authorized) = signal(false);
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match get_current_user().await {
+                Ok(Some(u)) if u.role == "admin" => set_authorized.set(true),
+                _ => {
+                    #[cfg(target_arch = "wasm32")]
+                    { let _ = web_sys::window().unwrap().location().set_href("/login"); }
+                }
+            }
+        });
+    });
+
+    let (promotions, set_promotions) = signal(Vec::<Promotion>::new());
+    let (categories, set_categories) = signal(Vec::<Category>::new());
+    let (items, set_items) = signal(Vec::<Item>::new());
+    let (groups, set_groups) = signal(Vec::<CustomerGroup>::new());
+    let (editing, set_editing) = signal(Option::<Uuid>::None);
+    let (creating, set_creating) = signal(false);
+    let (deleting, set_deleting) = signal(Option::<(Uuid, String)>::None);
+    let (error, set_error) = signal(Option::<String>::None);
+
+    let (name, set_name) = signal(String::new());
+    let (active, set_active) = signal(true);
+    let (target, set_target) = signal(String::new());
+    let (trigger, set_trigger) = signal(String::new());
+    let (buy_quantity, set_buy_quantity) = signal(String::from("1"));
+    let (get_quantity, set_get_quantity) = signal(String::from("1"));
+    let (discount_type, set_discount_type) = signal(String::from("percent"));
+    let (discount_value, set_discount_value) = signal(String::new());
+    let (group_id, set_group_id) = signal(Option::<Uuid>::None);
+    let (weekdays, set_weekdays) = signal(String::new());
+    let (start_time, set_start_time) = signal(String::new());
+    let (end_time, set_end_time) = signal(String::new());
+    let (valid_from, set_valid_from) = signal(String::new());
+    let (valid_to, set_valid_to) = signal(String::new());
+
+    let (reload, set_reload) = signal(0u32);
+    Effect::new(move || {
+        reload.get();
+        leptos::task::spawn_local(async move {
+            if let Ok(p) = fetch_promotions().await { set_promotions.set(p); }
+            if let Ok(c) = fetch_categories().await { set_categories.set(c); }
+            if let Ok(i) = fetch_items().await { set_items.set(i); }
+            if let Ok(g) = fetch_customer_groups().await { set_groups.set(g); }
+        });
+    });
+
+    let start_edit = move |p: Promotion| {
+        scroll_page_to_top();
+        set_error.set(None);
+        set_name.set(p.name.clone());
+        set_active.set(p.active);
+        set_target.set(scope_value(p.item_id, p.category_id));
+        set_trigger.set(scope_value(p.trigger_item_id, p.trigger_category_id));
+        set_buy_quantity.set(p.buy_quantity.to_string());
+        set_get_quantity.set(p.get_quantity.to_string());
+        set_discount_type.set(p.discount_type.clone());
+        set_discount_value.set(format!("{:.2}", p.discount_value));
+        set_group_id.set(p.customer_group_id);
+        set_weekdays.set(p.weekdays.clone());
+        set_start_time.set(p.start_time.clone().unwrap_or_default());
+        set_end_time.set(p.end_time.clone().unwrap_or_default());
+        set_valid_from.set(p.valid_from.clone().unwrap_or_default());
+        set_valid_to.set(p.valid_to.clone().unwrap_or_default());
+        set_editing.set(Some(p.id));
+        set_creating.set(false);
+    };
+
+    let start_create = move |_| {
+        set_error.set(None);
+        set_name.set(String::new());
+        set_active.set(true);
+        set_target.set(String::new());
+        set_trigger.set(String::new());
+        set_buy_quantity.set("1".to_string());
+        set_get_quantity.set("1".to_string());
+        set_discount_type.set("percent".to_string());
+        set_discount_value.set(String::new());
+        set_group_id.set(None);
+        set_weekdays.set(String::new());
+        set_start_time.set(String::new());
+        set_end_time.set(String::new());
+        set_valid_from.set(String::new());
+        set_valid_to.set(String::new());
+        set_creating.set(true);
+        set_editing.set(None);
+    };
+
+    let save = move |_| {
+        let editing_id = editing.get();
+        let is_creating = creating.get();
+        let (Ok(buy), Ok(get)) = (
+            buy_quantity.get().trim().parse::<i32>(),
+            get_quantity.get().trim().parse::<i32>(),
+        ) else {
+            set_error.set(Some(i18n.get().t("promotions.invalid_quantity")));
+            return;
+        };
+        let Ok(value) = discount_value.get().trim().replace(',', ".").parse::<f64>() else {
+            set_error.set(Some(i18n.get().t("promotions.invalid_discount")));
+            return;
+        };
+        let (item_id, category_id) = parse_scope(&target.get());
+        let (trigger_item_id, trigger_category_id) = parse_scope(&trigger.get());
+        let input = PromotionInput {
+            name: name.get(),
+            active: active.get(),
+            item_id,
+            category_id,
+            trigger_item_id,
+            trigger_category_id,
+            buy_quantity: buy,
+            get_quantity: get,
+            discount_type: discount_type.get(),
+            discount_value: value,
+            customer_group_id: group_id.get(),
+            weekdays: weekdays.get(),
+            start_time: optional(start_time.get()),
+            end_time: optional(end_time.get()),
+            valid_from: optional(valid_from.get()),
+            valid_to: optional(valid_to.get()),
+        };
+        set_error.set(None);
+        leptos::task::spawn_local(async move {
+            let result = if is_creating {
+                create_promotion(input).await
+            } else if let Some(id) = editing_id {
+                update_promotion(id, input).await
+            } else {
+                return;
+            };
+            match result {
+                Ok(_) => {
+                    set_creating.set(false);
+                    set_editing.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+                Err(e) => set_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let cancel_edit = move |_| {
+        set_editing.set(None);
+        set_creating.set(false);
+        set_error.set(None);
+    };
+
+    let confirm_delete = move |id: Uuid, name: String| { set_deleting.set(Some((id, name))); };
+    let delete_handler = move |_| {
+        if let Some((id, _)) = deleting.get() {
+            leptos::task::spawn_local(async move {
+                if delete_promotion(id).await.is_ok() {
+                    set_deleting.set(None);
+                    set_reload.update(|v| *v += 1);
+                }
+            });
+        }
+    };
+    let cancel_delete = move |_| { set_deleting.set(None); };
+
+    let toggle_weekday = move |day: char, on: bool| {
+        set_weekdays.update(|days| {
+            let mut picked: Vec<char> = days.chars().filter(|d| *d != day).collect();
+            if on { picked.push(day); }
+            picked.sort();
+            *days = picked.into_iter().collect();
+        });
+    };
+
+    // Name of the item or category a promotion is scoped to, or `all` if none.
+    let scope_name = move |item_id: Option<Uuid>, category_id: Option<Uuid>, all: String| match (item_id, category_id) {
+        (Some(id), _) => items.get().into_iter().find(|i| i.id == id).map(|i| i.name).unwrap_or_default(),
+        (None, Some(id)) => categories.get().into_iter().find(|c| c.id == id).map(|c| c.name).unwrap_or_default(),
+        (None, None) => all,
+    };
+
+    // Options for a target or trigger `<select>`: categories, then items.
+    let scope_options = move || {
+        let i = i18n.get();
+        let mut options: Vec<(String, String)> = categories
+            .get()
+            .into_iter()
+            .map(|c| (scope_value(None, Some(c.id)), format!("{}: {}", i.t("promotions.category"), c.name)))
+            .collect();
+        options.extend(items.get().into_iter().map(|it| (scope_value(Some(it.id), None), it.name)));
+        options
+    };
+
+    let form_open = move || editing.get().is_some() || creating.get();
+
+    view! {
+        <Show when=move || authorized.get() fallback=move || view! { <div class="loading">{move || i18n.get().t("general.loading")}</div> }>
+        <div>
+            <div class="page-header">
+                <h2>{move || i18n.get().t("promotions.title")}</h2>
+                <div class="page-header-actions">
+                    <button class="btn-primary" on:click=start_create disabled=form_open
+                    >{move || i18n.get().t("promotions.add")}</button>
+                </div>
+            </div>
+
+            <Show when=move || deleting.get().is_some() fallback=|| ()>
+                {move || {
+                    deleting.get().map(|(_, promo_name)| {
+                        let i = i18n.get();
+                        let confirm_msg = i.t("promotions.confirm_delete").replace("{name}", &promo_name);
+                        view! {
+                            <div class="modal-overlay">
+                                <div class="confirmation-modal">
+                                    <h3>{i.t("general.confirm_delete")}</h3>
+                                    <p>{confirm_msg}</p>
+                                    <div class="modal-actions">
+                                        <button class="btn-danger" on:click=delete_handler>{i.t("general.delete")}</button>
+                                        <button class="btn-secondary" on:click=cancel_delete>{i.t("general.cancel")}</button>
+                                    </div>
+                                </div>
+                            </div>
+                        }
+                    })
+                }}
+            </Show>
+
+            <Show when=form_open fallback=|| ()>
+                <div class="edit-form">
+                    <h3>{move || if creating.get() { i18n.get().t("promotions.create") } else { i18n.get().t("promotions.edit") }}</h3>
+                    <Show when=move || error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <p class="muted">{move || i18n.get().t("promotions.explain")}</p>
+                    <div class="form-grid">
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.name")}</label>
+                            <input type="text" value=move || name.get()
+                                on:input=move |ev| set_name.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>
+                                <input type="checkbox" checked=move || active.get()
+                                    on:change=move |ev| set_active.set(event_target_checked(&ev)) />
+                                {move || i18n.get().t("promotions.active")}
+                            </label>
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.target")}</label>
+                            <select on:change=move |ev| set_target.set(event_target_value(&ev))>
+                                <option value="" selected=move || target.get().is_empty()>{move || i18n.get().t("promotions.all_items")}</option>
+                                {move || scope_options().into_iter().map(|(value, label)| {
+                                    let selected = target.get() == value;
+                                    view! { <option value=value selected=selected>{label}</option> }
+                                }).collect_view()}
+                            </select>
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.trigger")}</label>
+                            <select on:change=move |ev| set_trigger.set(event_target_value(&ev))>
+                                <option value="" selected=move || trigger.get().is_empty()>{move || i18n.get().t("promotions.no_trigger")}</option>
+                                {move || scope_options().into_iter().map(|(value, label)| {
+                                    let selected = trigger.get() == value;
+                                    view! { <option value=value selected=selected>{label}</option> }
+                                }).collect_view()}
+                            </select>
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.buy_quantity")}</label>
+                            <input type="number" min="1" step="1" value=move || buy_quantity.get()
+                                on:input=move |ev| set_buy_quantity.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.get_quantity")}</label>
+                            <input type="number" min="1" step="1" value=move || get_quantity.get()
+                                on:input=move |ev| set_get_quantity.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.discount_type")}</label>
+                            <select on:change=move |ev| set_discount_type.set(event_target_value(&ev))>
+                                {DISCOUNT_TYPES.iter().map(|t| {
+                                    let t = *t;
+                                    let label_key = format!("promotions.type_{}", t);
+                                    view! {
+                                        <option value=t selected=move || discount_type.get() == t>
+                                            {move || i18n.get().t(&label_key)}
+                                        </option>
+                                    }
+                                }).collect_view()}
+                            </select>
+                        </div>
+                        <div class="form-group">
+                            <label>{move || if discount_type.get() == "percent" {
+                                format!("{} (%)", i18n.get().t("promotions.discount"))
+                            } else {
+                                format!("{} ({})", i18n.get().t("promotions.discount"), currency.get())
+                            }}</label>
+                            <input type="text" inputmode="decimal" value=move || discount_value.get()
+                                on:input=move |ev| set_discount_value.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.customer_group")}</label>
+                            <select on:change=move |ev| set_group_id.set(Uuid::parse_str(&event_target_value(&ev)).ok())>
+                                <option value="" selected=move || group_id.get().is_none()>{move || i18n.get().t("groups.all")}</option>
+                                {move || groups.get().into_iter().map(|g| {
+                                    let selected = group_id.get() == Some(g.id);
+                                    view! { <option value=g.id.to_string() selected=selected>{g.name}</option> }
+                                }).collect_view()}
+                            </select>
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.start_time")}</label>
+                            <input type="time" value=move || start_time.get()
+                                on:input=move |ev| set_start_time.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.end_time")}</label>
+                            <input type="time" value=move || end_time.get()
+                                on:input=move |ev| set_end_time.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.valid_from")}</label>
+                            <input type="date" value=move || valid_from.get()
+                                on:input=move |ev| set_valid_from.set(event_target_value(&ev)) />
+                        </div>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("promotions.valid_to")}</label>
+                            <input type="date" value=move || valid_to.get()
+                                on:input=move |ev| set_valid_to.set(event_target_value(&ev)) />
+                        </div>
+                    </div>
+
+                    <h4>{move || i18n.get().t("promotions.weekdays")}</h4>
+                    <div class="modifier-links">
+                        {('1'..='7').map(|day| {
+                            let index = day as usize - '1' as usize;
+                            view! {
+                                <label class="modifier-link">
+                                    <input type="checkbox" checked=move || weekdays.get().contains(day)
+                                        on:change=move |ev| toggle_weekday(day, event_target_checked(&ev)) />
+                                    {move || i18n.get().t("promotions.weekday_names").split(',').nth(index).unwrap_or_default().to_string()}
+                                </label>
+                            }
+                        }).collect_view()}
+                    </div>
+                    <p class="muted">{move || i18n.get().t("promotions.weekdays_hint")}</p>
+
+                    <div class="form-actions">
+                        <button class="btn-success" on:click=save>{move || i18n.get().t("general.save")}</button>
+                        <button class="btn-secondary" on:click=cancel_edit>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </Show>
+
+            <Show when=move || promotions.get().is_empty() fallback=|| ()>
+                <p class="text-muted">{move || i18n.get().t("promotions.none")}</p>
+            </Show>
+
+            <table class="data-table">
+                <thead><tr>
+                    <th>{move || i18n.get().t("promotions.name")}</th>
+                    <th>{move || i18n.get().t("promotions.target")}</th>
+                    <th>{move || i18n.get().t("promotions.deal")}</th>
+                    <th>{move || i18n.get().t("promotions.discount")}</th>
+                    <th>{move || i18n.get().t("promotions.when")}</th>
+                    <th>{move || i18n.get().t("promotions.active")}</th>
+                    <th></th>
+                </tr></thead>
+                <tbody>
+                    <For each=move || promotions.get() key=|p| (p.id, p.updated_at) let:promo>
+                        {
+                            let promo_clone = promo.clone();
+                            let promo_id = promo.id;
+                            let promo_name = promo.name.clone();
+                            let p = promo.clone();
+                            let applies_to = move || {
+                                let i = i18n.get();
+                                let target = scope_name(p.item_id, p.category_id, i.t("promotions.all_items"));
+                                if p.trigger_item_id.is_none() && p.trigger_category_id.is_none() {
+                                    return target;
+                                }
+                                let trigger = scope_name(p.trigger_item_id, p.trigger_category_id, String::new());
+                                i.t("promotions.with_trigger").replace("{target}", &target).replace("{trigger}", &trigger)
+                            };
+                            let deal = move || i18n.get().t("promotions.deal_label")
+                                .replace("{buy}", &promo.buy_quantity.to_string())
+                                .replace("{get}", &promo.get_quantity.to_string());
+                            let discount = match promo.discount_type.as_str() {
+                                "percent" => format!("{}%", promo.discount_value),
+                                _ => currency.with_untracked(|c| format!("{} {}", c, Money::from_major(promo.discount_value, c).format(c))),
+                            };
+                            let when = {
+                                let mut parts = Vec::new();
+                                if !promo.weekdays.is_empty() {
+                                    let names = i18n.get_untracked().t("promotions.weekday_names");
+                                    let names: Vec<&str> = names.split(',').collect();
+                                    parts.push(promo.weekdays.chars()
+                                        .filter_map(|d| d.to_digit(10).and_then(|d| names.get(d as usize - 1)).map(|n| n.to_string()))
+                                        .collect::<Vec<_>>().join(" "));
+                                }
+                                if promo.start_time.is_some() || promo.end_time.is_some() {
+                                    parts.push(format!("{}–{}", promo.start_time.clone().unwrap_or_default(), promo.end_time.clone().unwrap_or_default()));
+                                }
+                                if promo.valid_from.is_some() || promo.valid_to.is_some() {
+                                    parts.push(format!("{} – {}", promo.valid_from.clone().unwrap_or_default(), promo.valid_to.clone().unwrap_or_default()));
+                                }
+                                parts.join(", ")
+                            };
+                            let group = promo.customer_group_id.map(|id| move || {
+                                groups.get().into_iter().find(|g| g.id == id).map(|g| g.name).unwrap_or_default()
+                            });
+                            view! {
+                                <tr>
+                                    <td>
+                                        {promo.name.clone()}
+                                        {group.map(|g| view! { <span class="line-modifiers">{g}</span> })}
+                                    </td>
+                                    <td>{applies_to}</td>
+                                    <td>{deal}</td>
+                                    <td>{discount}</td>
+                                    <td>{when}</td>
+                                    <td>{move || if promo.active { i18n.get().t("general.yes") } else { String::from("–") }}</td>
+                                    <td class="data-table-actions">
+                                        <button class="btn-small" on:click=move |_| start_edit(promo_clone.clone())
+                                            disabled=form_open
+                                        >{move || i18n.get().t("general.edit")}</button>
+                                        <button class="btn-small btn-danger" on:click=move |_| confirm_delete(promo_id, promo_name.clone())
+                                            disabled=form_open
+                                        >{move || i18n.get().t("general.delete")}</button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+        </div>
+        </Show>
+    }
+}
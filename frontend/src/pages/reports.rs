@@ -492,6 +492,20 @@ fn trigger_csv_download(csv: &str, filename: &str) {
     a.click();
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn trigger_zip_download(_zip_b64: &str, _filename: &str) {}
+
+#[cfg(target_arch = "wasm32")]
+fn trigger_zip_download(zip_b64: &str, filename: &str) {
+    use wasm_bindgen::prelude::*;
+    let doc = leptos::prelude::document();
+    let a: web_sys::HtmlAnchorElement = doc.create_element("a").unwrap().unchecked_into();
+    let href = format!("data:application/zip;base64,{}", zip_b64);
+    a.set_href(&href);
+    a.set_download(filename);
+    a.click();
+}
+
 #[component]
 pub fn ReportsPage() -> impl IntoView {
     let i18n = expect_context::<RwSignal<I18n>>();
@@ -696,6 +710,22 @@ pub fn ReportsPage() -> impl IntoView {
                             });
                         }
                     }>{move || i18n.get().t("reports.export_csv")}</button>
+                    <button class="btn-secondary" title=move || i18n.get().t("reports.export_dsfinvk_hint") on:click=move |_| {
+                        if let Some(r) = report.get() {
+                            let sd = r.start_date;
+                            let ed = r.end_date;
+                            set_print_msg.set(None);
+                            leptos::task::spawn_local(async move {
+                                match export_dsfinvk(sd, ed).await {
+                                    Ok(zip_b64) => {
+                                        let filename = format!("dsfinvk_{}_{}.zip", sd.format("%Y%m%d"), ed.format("%Y%m%d"));
+                                        trigger_zip_download(&zip_b64, &filename);
+                                    }
+                                    Err(e) => set_print_msg.set(Some(format!("{}", e))),
+                                }
+                            });
+                        }
+                    }>{move || i18n.get().t("reports.export_dsfinvk")}</button>
                     <button class="btn-primary" on:click=move |_| {
                         if let Some(r) = report.get() {
                             let sd = r.start_date;
@@ -739,10 +769,10 @@ pub fn ReportsPage() -> impl IntoView {
                                 </div>
 
                                 <div class="summary-cards">
-                                    <div class="summary-card"><h4>{i18n.get().t("reports.total_revenue")}</h4><div class="summary-value">{format!("{} {:.2}", &currency.get(), report_data.summary.total_revenue)}</div></div>
+                                    <div class="summary-card"><h4>{i18n.get().t("reports.total_revenue")}</h4><div class="summary-value">{currency.with(|c| format!("{} {}", c, report_data.summary.total_revenue.format(c)))}</div></div>
                                     <div class="summary-card"><h4>{i18n.get().t("reports.items_sold")}</h4><div class="summary-value">{report_data.summary.total_items_sold.to_string()}</div></div>
                                     <div class="summary-card"><h4>{i18n.get().t("reports.transactions")}</h4><div class="summary-value">{report_data.summary.total_transactions.to_string()}</div></div>
-                                    <div class="summary-card"><h4>{i18n.get().t("reports.avg_transaction")}</h4><div class="summary-value">{format!("{} {:.2}", &currency.get(), report_data.summary.average_transaction_value)}</div></div>
+                                    <div class="summary-card"><h4>{i18n.get().t("reports.avg_transaction")}</h4><div class="summary-value">{currency.with(|c| format!("{} {}", c, report_data.summary.average_transaction_value.format(c)))}</div></div>
                                 </div>
 
                                 <div class="report-highlights">
@@ -755,10 +785,11 @@ pub fn ReportsPage() -> impl IntoView {
                                 } else {
                                     let mut by_qty: Vec<&ItemSalesReport> = report_data.items.iter().collect();
                                     by_qty.sort_by(|a, b| b.quantity_sold.cmp(&a.quantity_sold));
-                                    let qty_slices = build_top_slices(&by_qty, 8, |it| it.quantity_sold as f64, |it| it.item_name.clone());
+                                    let qty_slices = build_top_slices(&by_qty, 8, |it| it.quantity_sold.0 as f64, |it| it.item_name.clone());
                                     // items already arrives sorted by revenue desc
                                     let by_rev: Vec<&ItemSalesReport> = report_data.items.iter().collect();
-                                    let rev_slices = build_top_slices(&by_rev, 8, |it| it.total_revenue, |it| it.item_name.clone());
+                                    // Revenue slices are sized in minor units and formatted back exactly.
+                                    let rev_slices = build_top_slices(&by_rev, 8, |it| it.total_revenue.0 as f64, |it| it.item_name.clone());
                                     let cur_for_pie = currency.get();
                                     view! {
                                         <div class="charts-row">
@@ -768,7 +799,7 @@ pub fn ReportsPage() -> impl IntoView {
                                             </div>
                                             <div class="chart-card">
                                                 <h3>{i18n.get().t("reports.chart_top_revenue")}</h3>
-                                                {pie_chart_view(rev_slices, false, None, move |v| format!("{} {:.2}", cur_for_pie, v))}
+                                                {pie_chart_view(rev_slices, false, None, move |v| format!("{} {}", cur_for_pie, Money(v as i64).format(&cur_for_pie)))}
                                             </div>
                                         </div>
                                     }.into_any()
@@ -779,7 +810,7 @@ pub fn ReportsPage() -> impl IntoView {
                                     let cat_slices: Vec<PieSlice> = if report_data.items.is_empty() {
                                         Vec::new()
                                     } else {
-                                        let mut agg: Vec<(String, f64)> = Vec::new();
+                                        let mut agg: Vec<(String, Money)> = Vec::new();
                                         for it in &report_data.items {
                                             if let Some(entry) = agg.iter_mut().find(|(n, _)| *n == it.category_name) {
                                                 entry.1 += it.total_revenue;
@@ -787,15 +818,15 @@ pub fn ReportsPage() -> impl IntoView {
                                                 agg.push((it.category_name.clone(), it.total_revenue));
                                             }
                                         }
-                                        agg.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
-                                        let refs: Vec<&(String, f64)> = agg.iter().collect();
-                                        build_top_slices(&refs, 8, |t| t.1, |t| t.0.clone())
+                                        agg.sort_by_key(|t| std::cmp::Reverse(t.1));
+                                        let refs: Vec<&(String, Money)> = agg.iter().collect();
+                                        build_top_slices(&refs, 8, |t| t.1.0 as f64, |t| t.0.clone())
                                     };
                                     let cat_total = report_data.summary.total_revenue;
                                     let cur_for_cat = currency.get();
                                     let cur_for_cat_fmt = currency.get();
-                                    let cat_center = if cat_total > 0.0 {
-                                        Some((format!("{} {:.0}", cur_for_cat, cat_total), i18n.get().t("reports.total_revenue")))
+                                    let cat_center = if cat_total.is_positive() {
+                                        Some((format!("{} {:.0}", cur_for_cat, cat_total.to_major(&cur_for_cat)), i18n.get().t("reports.total_revenue")))
                                     } else { None };
 
                                     let basket_view = move || basket_dist.get().map(|bd| {
@@ -822,7 +853,7 @@ pub fn ReportsPage() -> impl IntoView {
                                         <div class="charts-row">
                                             <div class="chart-card">
                                                 <h3>{i18n.get().t("reports.chart_category_breakdown")}</h3>
-                                                {pie_chart_view(cat_slices, true, cat_center, move |v| format!("{} {:.2}", cur_for_cat_fmt, v))}
+                                                {pie_chart_view(cat_slices, true, cat_center, move |v| format!("{} {}", cur_for_cat_fmt, Money(v as i64).format(&cur_for_cat_fmt)))}
                                             </div>
                                             {basket_view}
                                         </div>
@@ -832,12 +863,11 @@ pub fn ReportsPage() -> impl IntoView {
                                 {move || revenue_ts.get().map(|rt| {
                                     let cur = currency.get();
                                     let cur_for_y = currency.get();
-                                    let mut acc = 0.0_f64;
+                                    let mut acc = Money::ZERO;
                                     let points: Vec<(String, f64)> = rt.buckets.iter().map(|b| {
                                         acc += b.revenue;
-                                        (b.label.clone(), acc)
+                                        (b.label.clone(), acc.to_major(&cur))
                                     }).collect();
-                                    let _ = cur;
                                     view! {
                                         <div class="chart-card chart-card-wide">
                                             <h3>{i18n.get().t("reports.chart_cumulative_revenue")}</h3>
@@ -869,21 +899,50 @@ pub fn ReportsPage() -> impl IntoView {
                                             <div class="payment-stats">
                                                 <div class="payment-stat">
                                                     <div class="payment-stat-label">{i18n.get().t("reports.payment_total_paid")}</div>
-                                                    <div class="payment-stat-value">{format!("{} {:.2}", cur, p.total_paid)}</div>
+                                                    <div class="payment-stat-value">{format!("{} {}", cur, p.total_paid.format(&cur))}</div>
                                                 </div>
                                                 <div class="payment-stat">
                                                     <div class="payment-stat-label">{i18n.get().t("reports.payment_total_change")}</div>
-                                                    <div class="payment-stat-value">{format!("{} {:.2}", cur, p.total_change)}</div>
+                                                    <div class="payment-stat-value">{format!("{} {}", cur, p.total_change.format(&cur))}</div>
                                                 </div>
                                                 <div class="payment-stat">
                                                     <div class="payment-stat-label">{i18n.get().t("reports.payment_avg_change")}</div>
-                                                    <div class="payment-stat-value">{format!("{} {:.2}", cur, p.average_change)}</div>
+                                                    <div class="payment-stat-value">{format!("{} {}", cur, p.average_change.format(&cur))}</div>
                                                 </div>
                                                 <div class="payment-stat">
                                                     <div class="payment-stat-label">{i18n.get().t("reports.payment_exact")}</div>
                                                     <div class="payment-stat-value">{format!("{} ({:.0}%)", p.exact_payment_count, exact_pct)}</div>
                                                 </div>
+                                                {(!p.cash_rounding.is_zero()).then(|| view! {
+                                                    <div class="payment-stat">
+                                                        <div class="payment-stat-label">{i18n.get().t("reports.cash_rounding")}</div>
+                                                        <div class="payment-stat-value">{format!("{} {}", cur, p.cash_rounding.format(&cur))}</div>
+                                                    </div>
+                                                })}
                                             </div>
+                                            {(!p.tenders.is_empty()).then(|| view! {
+                                                <div class="payment-tenders">
+                                                    <div class="payment-histogram-title">{i18n.get().t("reports.payment_by_tender")}</div>
+                                                    <table class="data-table">
+                                                        <thead>
+                                                            <tr>
+                                                                <th>{i18n.get().t("reports.tender")}</th>
+                                                                <th>{i18n.get().t("reports.transactions")}</th>
+                                                                <th>{i18n.get().t("reports.revenue")}</th>
+                                                            </tr>
+                                                        </thead>
+                                                        <tbody>
+                                                            {p.tenders.iter().map(|t| view! {
+                                                                <tr>
+                                                                    <td>{i18n.get().t(&format!("sale.tender_{}", t.tender))}</td>
+                                                                    <td>{t.transaction_count.to_string()}</td>
+                                                                    <td>{format!("{} {}", cur, t.amount.format(&cur))}</td>
+                                                                </tr>
+                                                            }).collect_view()}
+                                                        </tbody>
+                                                    </table>
+                                                </div>
+                                            })}
                                             <div class="payment-histogram">
                                                 <div class="payment-histogram-title">{i18n.get().t("reports.payment_change_distribution")}</div>
                                                 <div class="hist-bars">
@@ -930,7 +989,7 @@ pub fn ReportsPage() -> impl IntoView {
                                                             <tr>
                                                                 <td>{it.item_name}</td>
                                                                 <td>{it.category_name}</td>
-                                                                <td>{format!("{} {:.2}", cur, it.price)}</td>
+                                                                <td>{format!("{} {}", cur, it.price.format(&cur))}</td>
                                                                 <td>{it.created_at.format("%Y-%m-%d").to_string()}</td>
                                                             </tr>
                                                         }
@@ -957,9 +1016,16 @@ pub fn ReportsPage() -> impl IntoView {
                                                     <tr>
                                                         <td>{item.item_name.clone()}</td>
                                                         <td>{item.category_name.clone()}</td>
-                                                        <td>{item.quantity_sold.to_string()}</td>
-                                                        <td>{format!("{} {:.2}", &currency.get(), item.total_revenue)}</td>
-                                                        <td>{format!("{} {:.2}", &currency.get(), item.average_price)}</td>
+                                                        <td>{match item.unit.as_deref() {
+                                                            Some(unit) => format!("{} {}", item.quantity_sold, unit),
+                                                            None => item.quantity_sold.to_string(),
+                                                        }}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, item.total_revenue.format(c)))}</td>
+                                                        <td>{match item.unit.as_deref() {
+                                                            // Goods sold by weight: average price per kg.
+                                                            Some(unit) => currency.with(|c| format!("{} {}/{}", c, item.average_price.format(c), unit)),
+                                                            None => currency.with(|c| format!("{} {}", c, item.average_price.format(c))),
+                                                        }}</td>
                                                         <td>{item.transaction_count.to_string()}</td>
                                                     </tr>
                                                 </For>
@@ -968,7 +1034,7 @@ pub fn ReportsPage() -> impl IntoView {
                                                 <tr class="table-footer">
                                                     <td colspan="2"><strong>{i18n.get().t("reports.total")}</strong></td>
                                                     <td><strong>{total_items.to_string()}</strong></td>
-                                                    <td><strong>{format!("{} {:.2}", &currency.get(), total_revenue)}</strong></td>
+                                                    <td><strong>{currency.with(|c| format!("{} {}", c, total_revenue.format(c)))}</strong></td>
                                                     <td>"-"</td>
                                                     <td><strong>{total_transactions.to_string()}</strong></td>
                                                 </tr>
@@ -976,6 +1042,220 @@ pub fn ReportsPage() -> impl IntoView {
                                         </table>
                                     }.into_any()
                                 }}
+
+                                {(!report_data.taxes.is_empty()).then(|| {
+                                    let taxes = report_data.taxes.clone();
+                                    let net: Money = taxes.iter().map(|t| t.net).sum();
+                                    let tax: Money = taxes.iter().map(|t| t.tax).sum();
+                                    let gross: Money = taxes.iter().map(|t| t.gross).sum();
+                                    view! {
+                                        <h3>{i18n.get().t("reports.tax_by_rate")}</h3>
+                                        <table class="data-table">
+                                            <thead><tr><th>{i18n.get().t("tax.rate")}</th><th>{i18n.get().t("tax.net")}</th><th>{i18n.get().t("tax.tax")}</th><th>{i18n.get().t("tax.gross")}</th></tr></thead>
+                                            <tbody>
+                                                {taxes.iter().map(|t| view! {
+                                                    <tr>
+                                                        <td>{format!("{}%", t.rate)}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, t.net.format(c)))}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, t.tax.format(c)))}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, t.gross.format(c)))}</td>
+                                                    </tr>
+                                                }).collect_view()}
+                                            </tbody>
+                                            <tfoot>
+                                                <tr class="table-footer">
+                                                    <td><strong>{i18n.get().t("reports.total")}</strong></td>
+                                                    <td><strong>{currency.with(|c| format!("{} {}", c, net.format(c)))}</strong></td>
+                                                    <td><strong>{currency.with(|c| format!("{} {}", c, tax.format(c)))}</strong></td>
+                                                    <td><strong>{currency.with(|c| format!("{} {}", c, gross.format(c)))}</strong></td>
+                                                </tr>
+                                            </tfoot>
+                                        </table>
+                                    }
+                                })}
+
+                                {(!report_data.promotions.is_empty()).then(|| {
+                                    let promotions = report_data.promotions.clone();
+                                    view! {
+                                        <h3>{i18n.get().t("reports.promotions")}</h3>
+                                        <table class="data-table">
+                                            <thead><tr><th>{i18n.get().t("promotions.name")}</th><th>{i18n.get().t("reports.transactions")}</th><th>{i18n.get().t("promotions.discount")}</th></tr></thead>
+                                            <tbody>
+                                                {promotions.iter().map(|p| view! {
+                                                    <tr>
+                                                        <td>{p.name.clone()}</td>
+                                                        <td>{p.transaction_count.to_string()}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, p.total_discount.format(c)))}</td>
+                                                    </tr>
+                                                }).collect_view()}
+                                            </tbody>
+                                        </table>
+                                    }
+                                })}
+
+                                {[
+                                    ("discounts.by_reason", "discounts.reason", report_data.discount_reasons.clone()),
+                                    ("discounts.by_user", "discounts.user", report_data.discount_users.clone()),
+                                ].into_iter().filter(|(_, _, rows)| !rows.is_empty()).map(|(title, column, rows)| view! {
+                                    <h3>{i18n.get().t(title)}</h3>
+                                    <table class="data-table">
+                                        <thead><tr><th>{i18n.get().t(column)}</th><th>{i18n.get().t("reports.transactions")}</th><th>{i18n.get().t("promotions.discount")}</th></tr></thead>
+                                        <tbody>
+                                            {rows.into_iter().map(|d| view! {
+                                                <tr>
+                                                    <td>{d.name}</td>
+                                                    <td>{d.transaction_count.to_string()}</td>
+                                                    <td>{currency.with(|c| format!("{} {}", c, d.total_discount.format(c)))}</td>
+                                                </tr>
+                                            }).collect_view()}
+                                        </tbody>
+                                    </table>
+                                }).collect_view()}
+
+                                {{
+                                    let g = report_data.gift_cards.clone();
+                                    [g.topped_up, g.redeemed, g.refunded, g.outstanding].iter().any(|a| !a.is_zero()).then(|| view! {
+                                        <h3>{i18n.get().t("reports.gift_cards")}</h3>
+                                        <table class="data-table">
+                                            <tbody>
+                                                {[
+                                                    ("gift_cards.topped_up", g.topped_up),
+                                                    ("gift_cards.redeemed", g.redeemed),
+                                                    ("gift_cards.refunded", g.refunded),
+                                                    ("gift_cards.outstanding", g.outstanding),
+                                                ].into_iter().map(|(label, amount)| view! {
+                                                    <tr>
+                                                        <td>{i18n.get().t(label)}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, amount.format(c)))}</td>
+                                                    </tr>
+                                                }).collect_view()}
+                                            </tbody>
+                                        </table>
+                                    })
+                                }}
+
+                                {(!report_data.deposits.items.is_empty()).then(|| {
+                                    let d = report_data.deposits.clone();
+                                    view! {
+                                        <h3>{i18n.get().t("reports.deposits")}</h3>
+                                        <table class="data-table">
+                                            <thead><tr>
+                                                <th>{i18n.get().t("reports.deposit_item")}</th>
+                                                <th>{i18n.get().t("reports.deposit_charged")}</th>
+                                                <th>{i18n.get().t("reports.deposit_returned")}</th>
+                                            </tr></thead>
+                                            <tbody>
+                                                {d.items.iter().map(|row| view! {
+                                                    <tr>
+                                                        <td>{row.item_name.clone()}</td>
+                                                        <td>{currency.with(|c| format!("{}x · {} {}", row.charged_quantity, c, row.charged.format(c)))}</td>
+                                                        <td>{currency.with(|c| format!("{}x · {} {}", row.returned_quantity, c, row.returned.format(c)))}</td>
+                                                    </tr>
+                                                }).collect_view()}
+                                                <tr>
+                                                    <td><strong>{i18n.get().t("reports.total")}</strong></td>
+                                                    <td>{currency.with(|c| format!("{} {}", c, d.charged.format(c)))}</td>
+                                                    <td>{currency.with(|c| format!("{} {}", c, d.returned.format(c)))}</td>
+                                                </tr>
+                                                <tr>
+                                                    <td><strong>{i18n.get().t("reports.deposit_liability")}</strong></td>
+                                                    <td colspan="2"><strong>{currency.with(|c| format!("{} {}", c, d.net_liability.format(c)))}</strong></td>
+                                                </tr>
+                                            </tbody>
+                                        </table>
+                                    }
+                                })}
+
+                                {(!report_data.tips.days.is_empty()).then(|| {
+                                    let t = report_data.tips.clone();
+                                    let user_label = move |name: String| if name.is_empty() { "—".to_string() } else { name };
+                                    view! {
+                                        <h3>{i18n.get().t("reports.tips")}</h3>
+                                        <table class="data-table">
+                                            <thead><tr>
+                                                <th>{i18n.get().t("reports.tip_day")}</th>
+                                                <th>{i18n.get().t("discounts.user")}</th>
+                                                <th>{i18n.get().t("reports.transactions")}</th>
+                                                <th>{i18n.get().t("reports.tips")}</th>
+                                            </tr></thead>
+                                            <tbody>
+                                                {t.days.into_iter().map(|share| view! {
+                                                    <tr>
+                                                        <td>{share.day}</td>
+                                                        <td>{user_label(share.user_name)}</td>
+                                                        <td>{share.transaction_count.to_string()}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, share.amount.format(c)))}</td>
+                                                    </tr>
+                                                }).collect_view()}
+                                                {t.users.into_iter().map(|share| view! {
+                                                    <tr>
+                                                        <td><strong>{i18n.get().t("reports.total")}</strong></td>
+                                                        <td><strong>{user_label(share.user_name)}</strong></td>
+                                                        <td>{share.transaction_count.to_string()}</td>
+                                                        <td><strong>{currency.with(|c| format!("{} {}", c, share.amount.format(c)))}</strong></td>
+                                                    </tr>
+                                                }).collect_view()}
+                                                <tr>
+                                                    <td colspan="3"><strong>{i18n.get().t("reports.total")}</strong></td>
+                                                    <td><strong>{currency.with(|c| format!("{} {}", c, t.total.format(c)))}</strong></td>
+                                                </tr>
+                                            </tbody>
+                                        </table>
+                                    }
+                                })}
+
+                                {(!report_data.modifiers.is_empty()).then(|| {
+                                    let modifiers = report_data.modifiers.clone();
+                                    view! {
+                                        <h3>{i18n.get().t("reports.modifier_sales")}</h3>
+                                        <table class="data-table">
+                                            <thead><tr><th>{i18n.get().t("modifiers.group")}</th><th>{i18n.get().t("modifiers.option")}</th><th>{i18n.get().t("reports.quantity_sold")}</th><th>{i18n.get().t("reports.revenue")}</th></tr></thead>
+                                            <tbody>
+                                                {modifiers.iter().map(|m| view! {
+                                                    <tr>
+                                                        <td>{m.group_name.clone()}</td>
+                                                        <td>{m.modifier_name.clone()}</td>
+                                                        <td>{m.quantity_sold.to_string()}</td>
+                                                        <td>{currency.with(|c| format!("{} {}", c, m.total_revenue.format(c)))}</td>
+                                                    </tr>
+                                                }).collect_view()}
+                                            </tbody>
+                                        </table>
+                                    }
+                                })}
+
+                                {(!report_data.prices.is_empty()).then(|| {
+                                    let prices = report_data.prices.clone();
+                                    view! {
+                                        <h3>{i18n.get().t("reports.price_history")}</h3>
+                                        <table class="data-table">
+                                            <thead><tr>
+                                                <th>{i18n.get().t("reports.item")}</th>
+                                                <th>{i18n.get().t("reports.list_price")}</th>
+                                                <th>{i18n.get().t("reports.price_valid")}</th>
+                                                <th>{i18n.get().t("reports.quantity_sold")}</th>
+                                                <th>{i18n.get().t("reports.revenue")}</th>
+                                            </tr></thead>
+                                            <tbody>
+                                                {prices.iter().map(|p| {
+                                                    let valid = match p.valid_to {
+                                                        Some(to) => format!("{} – {}", p.valid_from.format("%Y-%m-%d %H:%M"), to.format("%Y-%m-%d %H:%M")),
+                                                        None => format!("{} –", p.valid_from.format("%Y-%m-%d %H:%M")),
+                                                    };
+                                                    view! {
+                                                        <tr>
+                                                            <td>{p.item_name.clone()}</td>
+                                                            <td>{currency.with(|c| format!("{} {}", c, p.list_price.format(c)))}</td>
+                                                            <td>{valid}</td>
+                                                            <td>{p.quantity_sold.to_string()}</td>
+                                                            <td>{currency.with(|c| format!("{} {}", c, p.total_revenue.format(c)))}</td>
+                                                        </tr>
+                                                    }
+                                                }).collect_view()}
+                                            </tbody>
+                                        </table>
+                                    }
+                                })}
                             </div>
                         }
                     })
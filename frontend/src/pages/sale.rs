@@ -4,7 +4,7 @@ use uuid::Uuid;
 
 use crate::i18n::I18n;
 use crate::models::*;
-use crate::pages::keyboard::OnScreenKeyboard;
+use crate::pages::keyboard::{NumericKeyboard, OnScreenKeyboard};
 use crate::server_fns::*;
 
 
@@ -125,17 +125,45 @@ fn setup_sale_ws(_set_msg: WriteSignal<String>) {}
 #[cfg(not(target_arch = "wasm32"))]
 fn setup_tick(_set_tick: WriteSignal<u32>) {}
 
+/// Calls `on_code` for each barcode a keyboard-wedge scanner types while no
+/// input field has focus; see [`ScanBuffer`].
+#[cfg(target_arch = "wasm32")]
+fn setup_scanner(on_code: impl Fn(String) + 'static) {
+    use wasm_bindgen::JsCast;
+    let buffer = std::cell::RefCell::new(ScanBuffer::default());
+    let handle = window_event_listener(leptos::ev::keydown, move |e| {
+        let in_field = e
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+            .is_some_and(|el| matches!(el.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT"));
+        if in_field {
+            return;
+        }
+        if let Some(code) = buffer.borrow_mut().push(&e.key(), Utc::now().timestamp_millis()) {
+            // Keep the Enter from also clicking the last pressed button.
+            e.prevent_default();
+            on_code(code);
+        }
+    });
+    on_cleanup(move || handle.remove());
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn setup_scanner(_on_code: impl Fn(String) + 'static) {}
+
 #[component]
 pub fn SalePage() -> impl IntoView {
     let i18n = expect_context::<RwSignal<I18n>>();
     let currency = expect_context::<RwSignal<String>>();
     let (authorized, set_authorized) = signal(false);
+    let (is_admin, set_is_admin) = signal(false);
 
     // Auth check
     Effect::new(move || {
         leptos::task::spawn_local(async move {
             match get_current_user().await {
                 Ok(Some(u)) if u.role == "admin" || u.role == "cashier" => {
+                    set_is_admin.set(u.role == "admin");
                     set_authorized.set(true);
                 }
                 Ok(Some(u)) if u.role == "cook" => {
@@ -151,17 +179,45 @@ pub fn SalePage() -> impl IntoView {
 
     let (categories, set_categories) = signal(Vec::<Category>::new());
     let (items, set_items) = signal(Vec::<Item>::new());
+    let (modifier_groups, set_modifier_groups) = signal(Vec::<ModifierGroupDetail>::new());
+    // Item whose modifiers are being picked before it is added, and the picks so far.
+    let (picking_item, set_picking_item) = signal(Option::<Item>::None);
+    let (picked_modifiers, set_picked_modifiers) = signal(Vec::<Uuid>::new());
+    // Item sold by weight whose weight (kg) is being entered or read from the scale.
+    let (weighing_item, set_weighing_item) = signal(Option::<Item>::None);
+    let (weight_text, set_weight_text) = signal(String::new());
+    let (weight_error, set_weight_error) = signal(Option::<String>::None);
+    let (scale_available, set_scale_available) = signal(false);
+    let (bundle_components, set_bundle_components) = signal(Vec::<BundleComponent>::new());
+    // Bundle whose choices are being picked, and the item picked for each of
+    // its category components so far.
+    let (bundle_item, set_bundle_item) = signal(Option::<Item>::None);
+    let (bundle_choices, set_bundle_choices) = signal(Vec::<Option<Uuid>>::new());
+    let (bundle_error, set_bundle_error) = signal(Option::<String>::None);
     let (selected_category, set_selected_category) = signal(Option::<Uuid>::None);
     let (current_transaction, set_current_transaction) = signal(Option::<Uuid>::None);
     let (transaction_items, set_transaction_items) =
         signal(Vec::<TransactionItemDetail>::new());
+    // Whether the active order's prices include tax; when they don't, tax
+    // is added on top of the line totals.
+    let (tax_inclusive, set_tax_inclusive) = signal(true);
     let (customer_name, set_customer_name) = signal(String::new());
     // Customer group the active order is tabulated under (None = regular customers).
     let (customer_groups, set_customer_groups) = signal(Vec::<CustomerGroup>::new());
     let (selected_group, set_selected_group) = signal(Option::<Uuid>::None);
-    let (change_amount, set_change_amount) = signal(Option::<f64>::None);
+    // Floor plan for the table view and what is open at each table.
+    let (floor_plan, set_floor_plan) = signal(Vec::<FloorAreaDetail>::new());
+    let (table_status, set_table_status) = signal(Vec::<TableStatus>::new());
+    let (change_amount, set_change_amount) = signal(Option::<Money>::None);
     let (open_transactions, set_open_transactions) = signal(Vec::<Transaction>::new());
     let (payment_amount, set_payment_amount) = signal(String::new());
+    // Tender the amount in the payment field is given in, and payments already
+    // added for a split checkout (e.g. part card, rest cash).
+    let (tender, set_tender) = signal("cash".to_string());
+    let (split_payments, set_split_payments) = signal(Vec::<PaymentInput>::new());
+    // Tip paid on top of the total, entered or picked from the presets.
+    let (tip, set_tip) = signal(Money::ZERO);
+    let (cash_rounding, set_cash_rounding) = signal(CashRounding::default());
     let (canceling_transaction, set_canceling_transaction) = signal(Option::<Uuid>::None);
     let (last_closed_transaction, set_last_closed_transaction) =
         signal(Option::<Transaction>::None);
@@ -176,6 +232,57 @@ pub fn SalePage() -> impl IntoView {
     let (show_name_kb, set_show_name_kb) = signal(false);
     let (kb_shift, set_kb_shift) = signal(false);
 
+    // Tab operation in progress on the active order: "move", "merge" or "split".
+    let (tab_action, set_tab_action) = signal(Option::<&'static str>::None);
+
+    // Why the last barcode scan failed, e.g. an unknown code.
+    let (scan_error, set_scan_error) = signal(Option::<String>::None);
+    // Why the last finished sale could not be fiscally signed, until dismissed.
+    let (fiscal_warning, set_fiscal_warning) = signal(Option::<String>::None);
+
+    // Manual discount being given: on a line (Some(line)) or on the whole
+    // sale (None), with its type, value, reason code and, above the cashier
+    // limit, an admin's PIN.
+    let (discount_target, set_discount_target) = signal(Option::<Option<Uuid>>::None);
+    let (discount_type, set_discount_type) = signal("percent".to_string());
+    let (discount_value, set_discount_value) = signal(String::new());
+    let (discount_reason, set_discount_reason) = signal(Option::<Uuid>::None);
+    let (manager_pin, set_manager_pin) = signal(String::new());
+    let (discount_error, set_discount_error) = signal(Option::<String>::None);
+    let (discount_reasons, set_discount_reasons) = signal(Vec::<DiscountReason>::new());
+    let (cashier_limit, set_cashier_limit) = signal(0.0f64);
+
+    // Gift card top-ups rung up on the active order, and the gift card
+    // dialog: the card's code (empty issues a new card), the amount to put
+    // on it and the card last looked up.
+    let (gift_card_loads, set_gift_card_loads) = signal(Vec::<GiftCardLoad>::new());
+    let (show_gift_card, set_show_gift_card) = signal(false);
+    let (gift_card_code, set_gift_card_code) = signal(String::new());
+    let (gift_card_amount, set_gift_card_amount) = signal(String::new());
+    let (gift_card, set_gift_card) = signal(Option::<GiftCardDetail>::None);
+    let (gift_card_error, set_gift_card_error) = signal(Option::<String>::None);
+    // Code of the card a gift card payment is taken from, and why the last
+    // checkout failed (e.g. not enough left on the card).
+    let (payment_card_code, set_payment_card_code) = signal(String::new());
+    let (checkout_error, set_checkout_error) = signal(Option::<String>::None);
+
+    // Registered customer the active order is linked to, customers matching
+    // what is typed into the customer field, and the points redemption dialog.
+    let (linked_customer, set_linked_customer) = signal(Option::<Customer>::None);
+    let (customer_matches, set_customer_matches) = signal(Vec::<Customer>::new());
+    let (show_redeem, set_show_redeem) = signal(false);
+    let (redeem_points, set_redeem_points) = signal(String::new());
+    let (redeem_error, set_redeem_error) = signal(Option::<String>::None);
+    let (loyalty_settings, set_loyalty_settings) = signal(LoyaltySettings::default());
+
+    // The deposit return dialog, where returned containers are counted in.
+    let (show_deposit_return, set_show_deposit_return) = signal(false);
+    let (deposit_error, set_deposit_error) = signal(Option::<String>::None);
+
+    // Line whose kitchen note is being edited, and the note being typed.
+    let (editing_note, set_editing_note) = signal(Option::<Uuid>::None);
+    let (note_text, set_note_text) = signal(String::new());
+
     // Persists the active order's customer name and group to the server, and
     // mirrors them into the open-orders list signal so the tab updates at once.
     let sync_customer_name = move || {
@@ -241,7 +348,10 @@ pub fn SalePage() -> impl IntoView {
                 if current_transaction.get_untracked() == Some(id) {
                     leptos::task::spawn_local(async move {
                         if let Ok(details) = fetch_transaction_details(id).await {
+                            set_tax_inclusive.set(details.transaction.tax_inclusive);
                             set_transaction_items.set(details.items);
+                            set_gift_card_loads.set(details.gift_card_loads);
+                            set_linked_customer.set(details.customer);
                         }
                     });
                 }
@@ -258,8 +368,13 @@ pub fn SalePage() -> impl IntoView {
                 if current_transaction.get_untracked() == Some(id) {
                     set_current_transaction.set(None);
                     set_transaction_items.set(vec![]);
+                    set_gift_card_loads.set(vec![]);
+                    set_linked_customer.set(None);
+                    set_customer_matches.set(vec![]);
                     set_customer_name.set(String::new());
                     set_payment_amount.set(String::new());
+                    set_split_payments.set(vec![]);
+                    set_tip.set(Money::ZERO);
                 }
                 // Refresh open transactions and item stock
                 leptos::task::spawn_local(async move {
@@ -274,6 +389,9 @@ pub fn SalePage() -> impl IntoView {
                 if current_transaction.get_untracked() == Some(id) {
                     set_current_transaction.set(None);
                     set_transaction_items.set(vec![]);
+                    set_gift_card_loads.set(vec![]);
+                    set_linked_customer.set(None);
+                    set_customer_matches.set(vec![]);
                     set_customer_name.set(String::new());
                 }
                 leptos::task::spawn_local(async move {
@@ -290,7 +408,7 @@ pub fn SalePage() -> impl IntoView {
             if let Ok(all_transactions) = fetch_all_transactions().await {
                 let last_closed = all_transactions
                     .iter()
-                    .filter(|t| t.status == "closed" && t.change_amount.is_some())
+                    .filter(|t| t.status == "closed" && t.refund_of.is_none() && t.change_amount.is_some())
                     .max_by_key(|t| t.closed_at);
                 set_last_closed_transaction.set(last_closed.cloned());
             }
@@ -306,15 +424,61 @@ pub fn SalePage() -> impl IntoView {
             if let Ok(its) = fetch_items().await {
                 set_items.set(its);
             }
+            if let Ok(groups) = fetch_modifier_groups().await {
+                set_modifier_groups.set(groups);
+            }
+            if let Ok(scale) = has_scale().await {
+                set_scale_available.set(scale);
+            }
+            if let Ok(reasons) = fetch_discount_reasons().await {
+                set_discount_reasons.set(reasons);
+            }
+            if let Ok(limit) = get_max_cashier_discount().await {
+                set_cashier_limit.set(limit);
+            }
+            if let Ok(components) = fetch_bundle_components().await {
+                set_bundle_components.set(components);
+            }
             if let Ok(groups) = fetch_customer_groups().await {
                 set_customer_groups.set(groups);
             }
+            if let Ok(settings) = get_loyalty_settings().await {
+                set_loyalty_settings.set(settings);
+            }
+            if let Ok(rounding) = get_cash_rounding().await {
+                set_cash_rounding.set(rounding);
+            }
+            if let Ok(plan) = fetch_floor_plan().await {
+                set_floor_plan.set(plan);
+            }
             if let Ok(trans) = fetch_open_transactions().await {
                 set_open_transactions.set(trans);
             }
         });
     });
 
+    // Every change to an open order refetches the open-orders list, so the
+    // table view follows it.
+    Effect::new(move || {
+        open_transactions.track();
+        leptos::task::spawn_local(async move {
+            if let Ok(status) = fetch_table_status().await {
+                set_table_status.set(status);
+            }
+        });
+    });
+
+    let table_name = move |table_id: Option<Uuid>| {
+        let id = table_id?;
+        floor_plan.get().into_iter().flat_map(|a| a.tables).find(|t| t.id == id).map(|t| t.name)
+    };
+
+    // Table of the active order, read from the open-orders list.
+    let current_table = move || {
+        let current = current_transaction.get()?;
+        open_transactions.get().into_iter().find(|t| t.id == current)?.table_id
+    };
+
     let (reload_kitchen, set_reload_kitchen) = signal(0u32);
     Effect::new(move || { setup_kitchen_ws(set_reload_kitchen); });
     Effect::new(move || {
@@ -372,19 +536,78 @@ pub fn SalePage() -> impl IntoView {
     };
 
 
-    let transaction_total = move || {
-        transaction_items.get().iter().map(|i| i.total_price).sum::<f64>()
+    // Line totals after promotions and other discounts.
+    let subtotal = move || {
+        transaction_items.get().iter().map(|i| i.net_total()).sum::<Money>()
     };
 
-    let start_transaction = move |_| {
+    // Tax added on top of the subtotal (zero when prices include tax).
+    let added_tax_amount = move || {
+        let inclusive = tax_inclusive.get();
+        let lines: Vec<(Option<f64>, Money)> = transaction_items
+            .get()
+            .iter()
+            .map(|i| (i.tax_rate, i.net_total()))
+            .collect();
+        added_tax(&tax_breakdown(&lines, inclusive), inclusive)
+    };
+
+    // Gift card top-ups are charged on top, untaxed.
+    let top_up_total = move || gift_card_loads.get().iter().map(|l| l.amount).sum::<Money>();
+
+    let transaction_total = move || subtotal() + added_tax_amount() + top_up_total();
+
+    let split_total = move || split_payments.get().iter().map(|p| p.amount).sum::<Money>();
+
+    // What is still owed, tip included, after the payments already added to
+    // the split.
+    let remaining_due = move || (transaction_total() + tip.get() - split_total()).max(Money::ZERO);
+
+    // What is owed in the selected tender: cash is rounded to the smallest coin.
+    let due_in_tender = move || {
+        if tender.get() == "cash" { cash_rounding.get().round(remaining_due()) } else { remaining_due() }
+    };
+
+    // The payment being entered, with the card code for gift card payments.
+    let entered_payment = move || {
+        let amount = Money::parse(&payment_amount.get(), &currency.get()).filter(|a| a.is_positive())?;
+        let tender = tender.get();
+        let card_code = (tender == "gift_card").then(|| payment_card_code.get());
+        Some(PaymentInput { tender, amount, card_code })
+    };
+
+    // All payments for checkout: the added splits plus the amount being entered.
+    let pending_payments = move || {
+        let mut payments = split_payments.get();
+        payments.extend(entered_payment());
+        payments
+    };
+
+    let add_split_payment = move |_| {
+        if let Some(payment) = entered_payment() {
+            set_split_payments.update(|list| list.push(payment));
+            set_payment_amount.set(String::new());
+            set_payment_card_code.set(String::new());
+            set_tender.set("cash".to_string());
+        }
+    };
+
+    let begin_transaction = move |table_id: Option<Uuid>| {
         let name = customer_name.get();
         leptos::task::spawn_local(async move {
             let cust = if name.is_empty() { None } else { Some(name) };
-            if let Ok(transaction) = create_transaction(cust).await {
+            if let Ok(transaction) = create_transaction(cust, table_id).await {
                 set_current_transaction.set(Some(transaction.id));
+                set_tax_inclusive.set(transaction.tax_inclusive);
                 set_transaction_items.set(vec![]);
+                set_gift_card_loads.set(vec![]);
+                set_linked_customer.set(None);
+                set_customer_matches.set(vec![]);
+                set_checkout_error.set(None);
                 set_selected_group.set(None);
                 set_change_amount.set(None);
+                set_split_payments.set(vec![]);
+                set_tip.set(Money::ZERO);
                 set_mobile_panel.set("items".to_string());
                 let _ = set_display_transaction(Some(transaction.id)).await;
                 if let Ok(trans) = fetch_open_transactions().await {
@@ -398,9 +621,15 @@ pub fn SalePage() -> impl IntoView {
         leptos::task::spawn_local(async move {
             if let Ok(details) = fetch_transaction_details(trans_id).await {
                 set_current_transaction.set(Some(trans_id));
+                set_tax_inclusive.set(details.transaction.tax_inclusive);
                 set_transaction_items.set(details.items);
+                set_gift_card_loads.set(details.gift_card_loads);
+                set_linked_customer.set(details.customer);
+                set_checkout_error.set(None);
                 set_customer_name.set(details.transaction.customer_name.unwrap_or_default());
                 set_selected_group.set(details.transaction.customer_group_id);
+                set_split_payments.set(vec![]);
+                set_tip.set(Money::ZERO);
                 set_mobile_panel.set("items".to_string());
                 let _ = set_display_transaction(Some(trans_id)).await;
             }
@@ -408,25 +637,564 @@ pub fn SalePage() -> impl IntoView {
     };
 
 
-    let add_item = move |item: Item| {
+    let assign_table = move |table_id: Option<Uuid>| {
+        if let Some(trans_id) = current_transaction.get() {
+            set_open_transactions.update(|list| {
+                if let Some(t) = list.iter_mut().find(|t| t.id == trans_id) {
+                    t.table_id = table_id;
+                }
+            });
+            leptos::task::spawn_local(async move {
+                let _ = assign_transaction_table(trans_id, table_id).await;
+            });
+        }
+    };
+
+    let add_line = move |item_id: Uuid, modifier_ids: Vec<Uuid>| {
         let current_trans = current_transaction.get();
         if let Some(trans_id) = current_trans {
             leptos::task::spawn_local(async move {
-                if add_item_to_transaction(trans_id, item.id, 1).await.is_ok() {
+                if add_item_to_transaction(trans_id, item_id, 1, modifier_ids).await.is_ok() {
+                    if let Ok(details) = fetch_transaction_details(trans_id).await {
+                        set_tax_inclusive.set(details.transaction.tax_inclusive);
+                        set_transaction_items.set(details.items);
+                    }
+                }
+            });
+        }
+    };
+
+    let read_scale = move || {
+        set_weight_error.set(None);
+        leptos::task::spawn_local(async move {
+            match read_scale_weight().await {
+                Ok(kg) => set_weight_text.set(format!("{:.3}", kg)),
+                Err(e) => set_weight_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let start_weighing = move |item: Item| {
+        set_weight_text.set(String::new());
+        set_weight_error.set(None);
+        set_weighing_item.set(Some(item));
+        if scale_available.get_untracked() {
+            read_scale();
+        }
+    };
+
+    let weight_kg = move || weight_text.get().trim().replace(',', ".").parse::<f64>().ok().filter(|w| *w > 0.0);
+
+    let confirm_weight = move || {
+        let (Some(trans_id), Some(item)) = (current_transaction.get(), weighing_item.get()) else { return };
+        let Some(kg) = weight_kg() else {
+            set_weight_error.set(Some(i18n.get().t("sale.invalid_weight")));
+            return;
+        };
+        leptos::task::spawn_local(async move {
+            match add_weighed_item(trans_id, item.id, kg).await {
+                Ok(()) => {
+                    set_weighing_item.set(None);
+                    if let Ok(details) = fetch_transaction_details(trans_id).await {
+                        set_tax_inclusive.set(details.transaction.tax_inclusive);
+                        set_transaction_items.set(details.items);
+                    }
+                }
+                Err(e) => set_weight_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let on_weight_key = move |key: String| {
+        match key.as_str() {
+            "Backspace" => { set_weight_text.update(|s| { s.pop(); }); }
+            "Enter" => confirm_weight(),
+            ch => set_weight_text.update(|s| s.push_str(ch)),
+        }
+    };
+
+    let start_discount = move |target: Option<Uuid>| {
+        set_discount_type.set("percent".to_string());
+        set_discount_value.set(String::new());
+        set_discount_reason.set(None);
+        set_manager_pin.set(String::new());
+        set_discount_error.set(None);
+        set_discount_target.set(Some(target));
+    };
+
+    let discount_amount = move || discount_value.get().trim().replace(',', ".").parse::<f64>().ok().filter(|v| *v > 0.0);
+
+    // What the line or sale being discounted comes to so far.
+    let discount_base = move || {
+        let target = discount_target.get().flatten();
+        transaction_items
+            .get()
+            .iter()
+            .filter(|l| !l.is_deposit && target.is_none_or(|id| l.id == id || l.bundle_id == Some(id)))
+            .map(|l| l.net_total())
+            .sum::<Money>()
+    };
+
+    let needs_approval = move || {
+        !is_admin.get()
+            && discount_amount()
+                .is_some_and(|v| {
+                    let base = discount_base().to_major(&currency.get());
+                    discount_percent(&discount_type.get(), v, base) > cashier_limit.get()
+                })
+    };
+
+    let confirm_discount = move || {
+        let (Some(trans_id), Some(target)) = (current_transaction.get(), discount_target.get()) else { return };
+        let Some(value) = discount_amount() else {
+            set_discount_error.set(Some(i18n.get().t("promotions.invalid_discount")));
+            return;
+        };
+        let Some(reason_id) = discount_reason.get() else {
+            set_discount_error.set(Some(i18n.get().t("discounts.pick_reason")));
+            return;
+        };
+        let pin = manager_pin.get();
+        let input = ManualDiscountInput {
+            transaction_item_id: target,
+            discount_type: discount_type.get(),
+            discount_value: value,
+            reason_id,
+            manager_pin: (!pin.is_empty()).then_some(pin),
+        };
+        leptos::task::spawn_local(async move {
+            match add_manual_discount(trans_id, input).await {
+                Ok(()) => {
+                    set_discount_target.set(None);
+                    if let Ok(details) = fetch_transaction_details(trans_id).await {
+                        set_transaction_items.set(details.items);
+                    }
+                }
+                Err(e) => set_discount_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let on_discount_key = move |key: String| {
+        match key.as_str() {
+            "Backspace" => { set_discount_value.update(|s| { s.pop(); }); }
+            "Enter" => confirm_discount(),
+            ch => set_discount_value.update(|s| s.push_str(ch)),
+        }
+    };
+
+    let remove_discount = move |discount_id: Uuid| {
+        let Some(trans_id) = current_transaction.get() else { return };
+        leptos::task::spawn_local(async move {
+            if remove_manual_discount(trans_id, discount_id).await.is_ok()
+                && let Ok(details) = fetch_transaction_details(trans_id).await
+            {
+                set_transaction_items.set(details.items);
+            }
+        });
+    };
+
+    let open_gift_card = move |_| {
+        set_gift_card_code.set(String::new());
+        set_gift_card_amount.set(String::new());
+        set_gift_card.set(None);
+        set_gift_card_error.set(None);
+        set_show_gift_card.set(true);
+    };
+
+    let lookup_gift_card = move || {
+        let code = gift_card_code.get();
+        leptos::task::spawn_local(async move {
+            match fetch_gift_card(code).await {
+                Ok(card) => {
+                    set_gift_card_error.set(None);
+                    set_gift_card.set(Some(card));
+                }
+                Err(e) => {
+                    set_gift_card.set(None);
+                    set_gift_card_error.set(Some(e.to_string().replace("error running server function: ", "")));
+                }
+            }
+        });
+    };
+
+    let confirm_top_up = move || {
+        let Some(trans_id) = current_transaction.get() else { return };
+        let Some(amount) = Money::parse(&gift_card_amount.get(), &currency.get()).filter(|a| a.is_positive()) else {
+            set_gift_card_error.set(Some(i18n.get().t("gift_cards.invalid_amount")));
+            return;
+        };
+        let code = Some(gift_card_code.get()).filter(|c| !c.trim().is_empty());
+        leptos::task::spawn_local(async move {
+            match add_gift_card_top_up(trans_id, code, amount).await {
+                Ok(_) => {
+                    set_show_gift_card.set(false);
+                    if let Ok(details) = fetch_transaction_details(trans_id).await {
+                        set_gift_card_loads.set(details.gift_card_loads);
+                        set_linked_customer.set(details.customer);
+                    }
+                }
+                Err(e) => set_gift_card_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let on_gift_card_key = move |key: String| {
+        match key.as_str() {
+            "Backspace" => { set_gift_card_amount.update(|s| { s.pop(); }); }
+            "Enter" => confirm_top_up(),
+            ch => set_gift_card_amount.update(|s| s.push_str(ch)),
+        }
+    };
+
+    let remove_top_up = move |load_id: Uuid| {
+        let Some(trans_id) = current_transaction.get() else { return };
+        leptos::task::spawn_local(async move {
+            if remove_gift_card_top_up(trans_id, load_id).await.is_ok()
+                && let Ok(details) = fetch_transaction_details(trans_id).await
+            {
+                set_gift_card_loads.set(details.gift_card_loads);
+                set_linked_customer.set(details.customer);
+            }
+        });
+    };
+
+    let refund_card_balance = move |code: String| {
+        leptos::task::spawn_local(async move {
+            match refund_gift_card(code).await {
+                Ok(card) => {
+                    set_gift_card_error.set(None);
+                    set_gift_card.set(Some(card));
+                }
+                Err(e) => set_gift_card_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    // The category components of a bundle, for which an item must be picked.
+    let bundle_slots = move || -> Vec<BundleComponent> {
+        let Some(item) = bundle_item.get() else { return Vec::new() };
+        bundle_components
+            .get()
+            .into_iter()
+            .filter(|c| c.bundle_item_id == item.id && c.category_id.is_some())
+            .collect()
+    };
+
+    let add_bundle = move |item_id: Uuid, choices: Vec<Uuid>| {
+        let Some(trans_id) = current_transaction.get() else { return };
+        leptos::task::spawn_local(async move {
+            match add_bundle_to_transaction(trans_id, item_id, choices).await {
+                Ok(()) => {
+                    set_bundle_item.set(None);
+                    if let Ok(details) = fetch_transaction_details(trans_id).await {
+                        set_tax_inclusive.set(details.transaction.tax_inclusive);
+                        set_transaction_items.set(details.items);
+                    }
+                }
+                Err(e) => set_bundle_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let start_bundle = move |item: Item| {
+        let slots = bundle_components
+            .get()
+            .iter()
+            .filter(|c| c.bundle_item_id == item.id && c.category_id.is_some())
+            .count();
+        set_bundle_error.set(None);
+        if slots == 0 {
+            add_bundle(item.id, vec![]);
+        } else {
+            set_bundle_choices.set(vec![None; slots]);
+            set_bundle_item.set(Some(item));
+        }
+    };
+
+    let confirm_bundle = move |_| {
+        let Some(item) = bundle_item.get() else { return };
+        let choices: Option<Vec<Uuid>> = bundle_choices.get().into_iter().collect();
+        if let Some(choices) = choices {
+            add_bundle(item.id, choices);
+        }
+    };
+
+    // Items sold by weight ask for the weight, bundles ask for their choices
+    // and items with modifier groups open the picker first; others are added
+    // as is.
+    let add_item = move |item: Item| {
+        if item.is_weighed() {
+            start_weighing(item);
+        } else if bundle_components.get().iter().any(|c| c.bundle_item_id == item.id) {
+            start_bundle(item);
+        } else if modifier_groups.get().iter().any(|g| g.applies_to(&item)) {
+            set_picked_modifiers.set(vec![]);
+            set_picking_item.set(Some(item));
+        } else {
+            add_line(item.id, vec![]);
+        }
+    };
+
+    let on_scan = move |code: String| {
+        let Some(trans_id) = current_transaction.get_untracked() else {
+            set_scan_error.set(Some(i18n.get_untracked().t("sale.scan_no_sale")));
+            return;
+        };
+        leptos::task::spawn_local(async move {
+            match scan_item_code(trans_id, code.clone()).await {
+                Ok(result) => {
+                    set_scan_error.set(None);
+                    if result.added {
+                        if let Ok(details) = fetch_transaction_details(trans_id).await {
+                            set_tax_inclusive.set(details.transaction.tax_inclusive);
+                            set_transaction_items.set(details.items);
+                        }
+                    } else if result.item.is_weighed() {
+                        start_weighing(result.item);
+                    } else if bundle_components.get_untracked().iter().any(|c| c.bundle_item_id == result.item.id) {
+                        start_bundle(result.item);
+                    } else {
+                        set_picked_modifiers.set(vec![]);
+                        set_picking_item.set(Some(result.item));
+                    }
+                }
+                // Not an item: it may be a customer's loyalty card.
+                Err(e) => match link_customer_card(trans_id, code).await {
+                    Ok(customer) => {
+                        set_scan_error.set(None);
+                        set_customer_name.set(customer.name.clone());
+                        set_linked_customer.set(Some(customer));
+                        set_customer_matches.set(vec![]);
+                    }
+                    Err(_) => set_scan_error.set(Some(
+                        e.to_string().replace("error running server function: ", ""),
+                    )),
+                },
+            }
+        });
+    };
+    Effect::new(move || setup_scanner(on_scan));
+
+    // Looks up registered customers as a name, phone number or card code is
+    // typed into the customer field; stale answers are dropped.
+    let search_customer_field = move |query: String| {
+        if linked_customer.get_untracked().is_some() || query.trim().chars().count() < 2 {
+            set_customer_matches.set(vec![]);
+            return;
+        }
+        leptos::task::spawn_local(async move {
+            if let Ok(found) = search_customers(query.clone()).await
+                && customer_name.get_untracked() == query
+            {
+                set_customer_matches.set(found);
+            }
+        });
+    };
+
+    let link_customer = move |customer_id: Option<Uuid>| {
+        let Some(trans_id) = current_transaction.get() else { return };
+        set_customer_matches.set(vec![]);
+        leptos::task::spawn_local(async move {
+            if let Ok(customer) = set_transaction_customer(trans_id, customer_id).await {
+                if let Some(c) = &customer {
+                    set_customer_name.set(c.name.clone());
+                    set_open_transactions.update(|list| {
+                        if let Some(t) = list.iter_mut().find(|t| t.id == trans_id) {
+                            t.customer_name = Some(c.name.clone());
+                        }
+                    });
+                }
+                set_linked_customer.set(customer);
+            }
+        });
+    };
+
+    // Registers what was typed into the customer field as a new customer and
+    // links the order to them.
+    let register_customer = move |_| {
+        let name = customer_name.get().trim().to_string();
+        if name.is_empty() { return; }
+        leptos::task::spawn_local(async move {
+            let input = CustomerInput { name, phone: None, email: None, notes: None, card_code: None };
+            if let Ok(customer) = create_customer(input).await {
+                link_customer(Some(customer.id));
+            }
+        });
+    };
+
+    let confirm_redeem = move || {
+        let Some(trans_id) = current_transaction.get() else { return };
+        let Ok(points) = redeem_points.get().parse::<i64>() else {
+            set_redeem_error.set(Some(i18n.get().t("sale.redeem_invalid")));
+            return;
+        };
+        leptos::task::spawn_local(async move {
+            match redeem_loyalty_points(trans_id, points).await {
+                Ok(()) => {
+                    set_show_redeem.set(false);
                     if let Ok(details) = fetch_transaction_details(trans_id).await {
                         set_transaction_items.set(details.items);
                     }
                 }
+                Err(e) => set_redeem_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    let on_redeem_key = move |key: String| {
+        match key.as_str() {
+            "Backspace" => { set_redeem_points.update(|s| { s.pop(); }); }
+            "Enter" => confirm_redeem(),
+            "." | "," => {}
+            ch => set_redeem_points.update(|s| s.push_str(ch)),
+        }
+    };
+
+    // Pays back the deposit for one returned container.
+    let return_deposit_unit = move |item_id: Uuid| {
+        let Some(trans_id) = current_transaction.get() else { return };
+        leptos::task::spawn_local(async move {
+            match return_deposit(trans_id, item_id, 1).await {
+                Ok(()) => {
+                    set_deposit_error.set(None);
+                    if let Ok(details) = fetch_transaction_details(trans_id).await {
+                        set_transaction_items.set(details.items);
+                    }
+                }
+                Err(e) => set_deposit_error.set(Some(
+                    e.to_string().replace("error running server function: ", ""),
+                )),
+            }
+        });
+    };
+
+    // Units of a deposit item returned on the active order so far.
+    let returned_units = move |item_id: Uuid| {
+        transaction_items
+            .get()
+            .iter()
+            .filter(|l| l.item_id == item_id && l.is_deposit && l.quantity.is_negative())
+            .map(|l| (-l.quantity).whole_units())
+            .sum::<i64>()
+    };
+
+    let picker_groups = move || {
+        let groups = modifier_groups.get();
+        match picking_item.get() {
+            Some(item) => groups.into_iter().filter(|g| g.applies_to(&item)).collect(),
+            None => Vec::new(),
+        }
+    };
+
+    // Toggles an option. In single-choice groups picking replaces the
+    // previous pick; otherwise picks beyond the group's maximum are ignored.
+    let toggle_modifier = move |group: ModifierGroupDetail, modifier_id: Uuid| {
+        set_picked_modifiers.update(|picks| {
+            if let Some(pos) = picks.iter().position(|p| *p == modifier_id) {
+                picks.remove(pos);
+                return;
+            }
+            let in_group = |p: &Uuid| group.modifiers.iter().any(|m| m.id == *p);
+            match group.group.max_picks {
+                Some(1) => picks.retain(|p| !in_group(p)),
+                Some(max) if picks.iter().filter(|&p| in_group(p)).count() as i32 >= max => return,
+                _ => {}
+            }
+            picks.push(modifier_id);
+        });
+    };
+
+    let picks_valid = move || {
+        let groups = picker_groups();
+        let refs: Vec<&ModifierGroupDetail> = groups.iter().collect();
+        check_modifier_picks(&refs, &picked_modifiers.get()).is_ok()
+    };
+
+    let start_note = move |line: TransactionItemDetail| {
+        set_note_text.set(line.note.unwrap_or_default());
+        set_kb_shift.set(false);
+        set_editing_note.set(Some(line.id));
+    };
+
+    let save_note = move |note: Option<String>| {
+        let current_trans = current_transaction.get();
+        let line = editing_note.get();
+        set_editing_note.set(None);
+        if let (Some(trans_id), Some(line_id)) = (current_trans, line) {
+            leptos::task::spawn_local(async move {
+                if set_line_note(trans_id, line_id, note).await.is_ok()
+                    && let Ok(details) = fetch_transaction_details(trans_id).await
+                {
+                    set_transaction_items.set(details.items);
+                }
             });
         }
     };
 
-    let remove_item = move |item_id: Uuid| {
+    let on_note_kb_key = move |key: String| {
+        match key.as_str() {
+            "Backspace" => { set_note_text.update(|s| { s.pop(); }); }
+            "Enter" => save_note(Some(note_text.get())),
+            "Shift" => { set_kb_shift.update(|s| *s = !*s); }
+            "Space" => { set_note_text.update(|s| s.push(' ')); }
+            ch => {
+                let ch = if kb_shift.get() { ch.to_uppercase() } else { ch.to_lowercase() };
+                set_note_text.update(|s| s.push_str(&ch));
+            }
+        }
+    };
+
+    let on_tab_action_done = move |switch_to: Option<Uuid>| {
+        set_tab_action.set(None);
+        match switch_to {
+            Some(id) => resume_transaction(id),
+            None => {
+                if let Some(trans_id) = current_transaction.get_untracked() {
+                    leptos::task::spawn_local(async move {
+                        if let Ok(details) = fetch_transaction_details(trans_id).await {
+                            set_customer_name.set(details.transaction.customer_name.unwrap_or_default());
+                            set_transaction_items.set(details.items);
+                            set_gift_card_loads.set(details.gift_card_loads);
+                            set_linked_customer.set(details.customer);
+                        }
+                        if let Ok(trans) = fetch_open_transactions().await {
+                            set_open_transactions.set(trans);
+                        }
+                    });
+                }
+            }
+        }
+    };
+
+    let confirm_modifiers = move |_| {
+        if let Some(item) = picking_item.get() {
+            add_line(item.id, picked_modifiers.get());
+        }
+        set_picking_item.set(None);
+    };
+
+    let remove_item = move |line_id: Uuid| {
         let current_trans = current_transaction.get();
         if let Some(trans_id) = current_trans {
             leptos::task::spawn_local(async move {
-                if remove_item_from_transaction(trans_id, item_id).await.is_ok() {
+                if remove_item_from_transaction(trans_id, line_id).await.is_ok() {
                     if let Ok(details) = fetch_transaction_details(trans_id).await {
+                        set_tax_inclusive.set(details.transaction.tax_inclusive);
                         set_transaction_items.set(details.items);
                     }
                 }
@@ -436,23 +1204,40 @@ pub fn SalePage() -> impl IntoView {
 
     let checkout = move |_| {
         let current_trans = current_transaction.get();
-        let amount_str = payment_amount.get();
+        let payments = pending_payments();
         let fetch_last_closed = fetch_last_closed.clone();
         if let Some(trans_id) = current_trans {
-            if let Ok(amount) = amount_str.parse::<f64>() {
+            // Deposit returns alone are paid out without a payment.
+            if !payments.is_empty() || transaction_total().is_negative() {
                 leptos::task::spawn_local(async move {
-                    if let Ok(response) = close_transaction(trans_id, amount).await {
-                        set_change_amount.set(Some(response.change_amount));
-                        set_current_transaction.set(None);
-                        set_customer_name.set(String::new());
-                        set_selected_group.set(None);
-                        set_payment_amount.set(String::new());
-                        if let Ok(trans) = fetch_open_transactions().await {
-                            set_open_transactions.set(trans);
+                    match close_transaction(trans_id, payments, tip.get_untracked()).await {
+                        Ok(response) => {
+                            set_change_amount.set(Some(response.change_amount));
+                            set_fiscal_warning.set(response.transaction.fiscal_error.map(|error| {
+                                i18n.get_untracked().t("fiscal.not_signed").replace("{error}", &error)
+                            }));
+                            set_checkout_error.set(None);
+                            set_current_transaction.set(None);
+                            set_gift_card_loads.set(vec![]);
+                            set_linked_customer.set(None);
+                            set_customer_matches.set(vec![]);
+                            set_customer_name.set(String::new());
+                            set_selected_group.set(None);
+                            set_payment_amount.set(String::new());
+                            set_payment_card_code.set(String::new());
+                            set_tender.set("cash".to_string());
+                            set_split_payments.set(vec![]);
+                            set_tip.set(Money::ZERO);
+                            if let Ok(trans) = fetch_open_transactions().await {
+                                set_open_transactions.set(trans);
+                            }
+                            fetch_last_closed();
+                            set_reload_items.update(|v| *v += 1);
+                            set_reload_kitchen.update(|v| *v += 1);
                         }
-                        fetch_last_closed();
-                        set_reload_items.update(|v| *v += 1);
-                        set_reload_kitchen.update(|v| *v += 1);
+                        Err(e) => set_checkout_error.set(Some(
+                            e.to_string().replace("error running server function: ", ""),
+                        )),
                     }
                 });
             }
@@ -468,9 +1253,15 @@ pub fn SalePage() -> impl IntoView {
         let fetch_last_closed = fetch_last_closed.clone();
         if let Some(trans_id) = current_trans {
             leptos::task::spawn_local(async move {
-                if cancel_transaction(trans_id).await.is_ok() {
+                if let Ok(cancelled) = cancel_transaction(trans_id).await {
+                    set_fiscal_warning.set(cancelled.fiscal_error.map(|error| {
+                        i18n.get_untracked().t("fiscal.not_signed").replace("{error}", &error)
+                    }));
                     set_current_transaction.set(None);
                     set_transaction_items.set(vec![]);
+                    set_gift_card_loads.set(vec![]);
+                    set_linked_customer.set(None);
+                    set_customer_matches.set(vec![]);
                     set_customer_name.set(String::new());
                     set_selected_group.set(None);
                     if let Ok(trans) = fetch_open_transactions().await {
@@ -494,8 +1285,13 @@ pub fn SalePage() -> impl IntoView {
                 let _ = set_display_transaction(None).await;
                 set_current_transaction.set(None);
                 set_transaction_items.set(vec![]);
+                set_gift_card_loads.set(vec![]);
+                set_linked_customer.set(None);
+                set_customer_matches.set(vec![]);
                 set_customer_name.set(String::new());
                 set_selected_group.set(None);
+                set_split_payments.set(vec![]);
+                set_tip.set(Money::ZERO);
                 if let Ok(trans) = fetch_open_transactions().await {
                     set_open_transactions.set(trans);
                 }
@@ -526,8 +1322,365 @@ pub fn SalePage() -> impl IntoView {
             }}
         </Show>
 
+        <Show when=move || picking_item.get().is_some() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal modifier-picker">
+                    <h3>{move || picking_item.get().map(|i| i.name).unwrap_or_default()}</h3>
+                    <For each=picker_groups key=|g| g.group.id let:group>
+                        {
+                            let hint = match (group.group.min_picks, group.group.max_picks) {
+                                (0, Some(max)) => i18n.get().t("modifiers.pick_up_to").replace("{n}", &max.to_string()),
+                                (0, None) => i18n.get().t("modifiers.optional"),
+                                (min, _) => i18n.get().t("modifiers.pick_at_least").replace("{n}", &min.to_string()),
+                            };
+                            let options = group.modifiers.clone();
+                            view! {
+                                <div class="modifier-group">
+                                    <h4>{group.group.name.clone()}" "<span class="modifier-hint">{hint}</span></h4>
+                                    <div class="modifier-options">
+                                        {options.into_iter().map(|m| {
+                                            let group = group.clone();
+                                            let id = m.id;
+                                            let label = if m.price_delta.is_zero() {
+                                                m.name.clone()
+                                            } else {
+                                                let sign = if m.price_delta.is_negative() { "" } else { "+" };
+                                                currency.with(|c| format!("{} ({}{})", m.name, sign, m.price_delta.format(c)))
+                                            };
+                                            view! {
+                                                <button
+                                                    class=move || if picked_modifiers.get().contains(&id) { "modifier-option selected" } else { "modifier-option" }
+                                                    on:click=move |_| toggle_modifier(group.clone(), id)
+                                                >{label}</button>
+                                            }
+                                        }).collect_view()}
+                                    </div>
+                                </div>
+                            }
+                        }
+                    </For>
+                    <div class="modal-actions">
+                        <button class="btn-primary" on:click=confirm_modifiers disabled=move || !picks_valid()>{move || i18n.get().t("modifiers.add_to_order")}</button>
+                        <button class="btn-secondary" on:click=move |_| set_picking_item.set(None)>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
+        <Show when=move || bundle_item.get().is_some() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal modifier-picker">
+                    <h3>{move || bundle_item.get().map(|i| currency.with(|c| format!("{} ({}{})", i.name, c, i.price.format(c)))).unwrap_or_default()}</h3>
+                    <Show when=move || bundle_error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || bundle_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    {move || bundle_slots().into_iter().enumerate().map(|(slot, component)| {
+                        let category_id = component.category_id;
+                        let title = categories
+                            .get()
+                            .into_iter()
+                            .find(|c| Some(c.id) == category_id)
+                            .map(|c| c.name)
+                            .unwrap_or_default();
+                        let title = match component.quantity {
+                            1 => title,
+                            n => format!("{}× {}", n, title),
+                        };
+                        let choices: Vec<Item> = items
+                            .get()
+                            .into_iter()
+                            .filter(|i| Some(i.category_id) == category_id && i.in_stock && !i.is_weighed())
+                            .collect();
+                        view! {
+                            <div class="modifier-group">
+                                <h4>{title}</h4>
+                                <div class="modifier-options">
+                                    {choices.into_iter().map(|choice| {
+                                        let id = choice.id;
+                                        view! {
+                                            <button
+                                                class=move || if bundle_choices.get().get(slot) == Some(&Some(id)) { "modifier-option selected" } else { "modifier-option" }
+                                                on:click=move |_| set_bundle_choices.update(|picks| {
+                                                    if let Some(pick) = picks.get_mut(slot) { *pick = Some(id); }
+                                                })
+                                            >{choice.name.clone()}</button>
+                                        }
+                                    }).collect_view()}
+                                </div>
+                            </div>
+                        }
+                    }).collect_view()}
+                    <div class="modal-actions">
+                        <button class="btn-primary" on:click=confirm_bundle
+                            disabled=move || bundle_choices.get().iter().any(|c| c.is_none())
+                        >{move || i18n.get().t("modifiers.add_to_order")}</button>
+                        <button class="btn-secondary" on:click=move |_| set_bundle_item.set(None)>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
+        <Show when=move || weighing_item.get().is_some() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal weigh-modal">
+                    <h3>{move || weighing_item.get().map(|i| currency.with(|c| format!("{} ({}{}{})", i.name, c, i.price.format(c), i.price_suffix()))).unwrap_or_default()}</h3>
+                    <Show when=move || weight_error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || weight_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="admin-input-row">
+                        <input type="text" inputmode="decimal" class="weigh-input"
+                            placeholder=move || i18n.get().t("sale.weight_kg")
+                            prop:value=move || weight_text.get()
+                            on:input=move |ev| set_weight_text.set(event_target_value(&ev)) />
+                        <span>"kg"</span>
+                        <Show when=move || scale_available.get() fallback=|| ()>
+                            <button class="btn-secondary" on:click=move |_| read_scale()>{move || i18n.get().t("sale.read_scale")}</button>
+                        </Show>
+                    </div>
+                    <p class="weigh-price">{move || {
+                        match (weighing_item.get(), weight_kg()) {
+                            (Some(item), Some(kg)) => currency.with(|c| format!("{} {}", c, (kg_price(item.price, &item.price_unit) * Quantity::from_decimal(kg)).format(c))),
+                            _ => String::new(),
+                        }
+                    }}</p>
+                    <NumericKeyboard on_key=on_weight_key i18n=i18n />
+                    <div class="modal-actions">
+                        <button class="btn-primary" on:click=move |_| confirm_weight() disabled=move || weight_kg().is_none()>{move || i18n.get().t("modifiers.add_to_order")}</button>
+                        <button class="btn-secondary" on:click=move |_| set_weighing_item.set(None)>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
+        {move || tab_action.get().zip(current_transaction.get()).map(|(mode, trans_id)| {
+            let others: Vec<Transaction> = open_transactions
+                .get_untracked()
+                .into_iter()
+                .filter(|t| t.id != trans_id)
+                .collect();
+            view! {
+                <TabActionsPanel
+                    mode=mode
+                    transaction_id=trans_id
+                    items=movable_lines(&transaction_items.get_untracked())
+                    open_tabs=others
+                    on_done=on_tab_action_done
+                    on_cancel=move || set_tab_action.set(None)
+                />
+            }
+        })}
+
+        <Show when=move || discount_target.get().is_some() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal modifier-picker">
+                    <h3>{move || match discount_target.get().flatten() {
+                        Some(line_id) => {
+                            let name = transaction_items.get().into_iter().find(|l| l.id == line_id).map(|l| l.item_name).unwrap_or_default();
+                            format!("{}: {}", i18n.get().t("discounts.line_discount"), name)
+                        }
+                        None => i18n.get().t("discounts.sale_discount"),
+                    }}</h3>
+                    <Show when=move || discount_error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || discount_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="modifier-options">
+                        <For each=|| DISCOUNT_TYPES.to_vec() key=|t| *t let:t>
+                            <button
+                                class=move || if discount_type.get() == t { "modifier-option selected" } else { "modifier-option" }
+                                on:click=move |_| set_discount_type.set(t.to_string())
+                            >{move || i18n.get().t(&format!("promotions.type_{}", t))}</button>
+                        </For>
+                    </div>
+                    <div class="admin-input-row">
+                        <input type="text" inputmode="decimal" class="weigh-input"
+                            placeholder=move || i18n.get().t("promotions.discount")
+                            prop:value=move || discount_value.get()
+                            on:input=move |ev| set_discount_value.set(event_target_value(&ev)) />
+                        <span>{move || if discount_type.get() == "percent" { "%".to_string() } else { currency.get() }}</span>
+                    </div>
+                    <NumericKeyboard on_key=on_discount_key i18n=i18n />
+                    <div class="modifier-group">
+                        <h4>{move || i18n.get().t("discounts.reason")}</h4>
+                        <div class="modifier-options">
+                            <For each=move || discount_reasons.get() key=|r| r.id let:reason>
+                                <button
+                                    class=move || if discount_reason.get() == Some(reason.id) { "modifier-option selected" } else { "modifier-option" }
+                                    on:click=move |_| set_discount_reason.set(Some(reason.id))
+                                >{reason.name.clone()}</button>
+                            </For>
+                        </div>
+                    </div>
+                    <Show when=needs_approval fallback=|| ()>
+                        <div class="form-group">
+                            <label>{move || i18n.get().t("discounts.manager_pin").replace("{limit}", &cashier_limit.get().to_string())}</label>
+                            <input type="password" inputmode="numeric" autocomplete="off"
+                                prop:value=move || manager_pin.get()
+                                on:input=move |ev| set_manager_pin.set(event_target_value(&ev)) />
+                        </div>
+                    </Show>
+                    <div class="modal-actions">
+                        <button class="btn-primary" on:click=move |_| confirm_discount()
+                            disabled=move || discount_amount().is_none() || discount_reason.get().is_none()
+                        >{move || i18n.get().t("discounts.give")}</button>
+                        <button class="btn-secondary" on:click=move |_| set_discount_target.set(None)>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
+        <Show when=move || show_gift_card.get() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal modifier-picker">
+                    <h3>{move || i18n.get().t("gift_cards.title")}</h3>
+                    <Show when=move || gift_card_error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || gift_card_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="admin-input-row">
+                        <input type="text" autocomplete="off"
+                            placeholder=move || i18n.get().t("gift_cards.code_placeholder")
+                            prop:value=move || gift_card_code.get()
+                            on:input=move |ev| {
+                                set_gift_card_code.set(event_target_value(&ev));
+                                set_gift_card.set(None);
+                            } />
+                        <button class="btn-secondary" on:click=move |_| lookup_gift_card()
+                            disabled=move || gift_card_code.get().trim().is_empty()
+                        >{move || i18n.get().t("gift_cards.check_balance")}</button>
+                    </div>
+                    {move || gift_card.get().map(|card| {
+                        let code = card.card.code.clone();
+                        let balance = card.balance;
+                        view! {
+                            <div class="transaction-total">
+                                <span>{move || i18n.get().t("gift_cards.balance")}</span>
+                                <strong>{currency.with(|c| format!("{} {}", c, balance.format(c)))}</strong>
+                            </div>
+                            <table class="data-table"><tbody>
+                                {card.entries.iter().take(5).map(|e| view! {
+                                    <tr>
+                                        <td>{e.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string()}</td>
+                                        <td>{i18n.get().t(&format!("gift_cards.kind_{}", e.kind))}</td>
+                                        <td>{currency.with(|c| e.amount.format(c))}</td>
+                                    </tr>
+                                }).collect_view()}
+                            </tbody></table>
+                            <Show when=move || is_admin.get() && balance.is_positive() fallback=|| ()>
+                                {
+                                    let code = code.clone();
+                                    view! {
+                                        <button class="btn-danger" on:click=move |_| refund_card_balance(code.clone())
+                                        >{move || i18n.get().t("gift_cards.refund_balance")}</button>
+                                    }
+                                }
+                            </Show>
+                        }
+                    })}
+                    <div class="admin-input-row">
+                        <input type="text" inputmode="decimal" class="weigh-input"
+                            placeholder=move || i18n.get().t("gift_cards.top_up_amount")
+                            prop:value=move || gift_card_amount.get()
+                            on:input=move |ev| set_gift_card_amount.set(event_target_value(&ev)) />
+                        <span>{move || currency.get()}</span>
+                    </div>
+                    <NumericKeyboard on_key=on_gift_card_key i18n=i18n />
+                    <div class="modal-actions">
+                        <button class="btn-primary" on:click=move |_| confirm_top_up()
+                            disabled=move || current_transaction.get().is_none() || gift_card_amount.get().is_empty()
+                        >{move || if gift_card_code.get().trim().is_empty() {
+                            i18n.get().t("gift_cards.issue")
+                        } else {
+                            i18n.get().t("gift_cards.top_up")
+                        }}</button>
+                        <button class="btn-secondary" on:click=move |_| set_show_gift_card.set(false)>{move || i18n.get().t("gift_cards.close")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
+        <Show when=move || show_redeem.get() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal modifier-picker">
+                    <h3>{move || i18n.get().t("sale.redeem_points")}</h3>
+                    <p>{move || {
+                        let points = linked_customer.get().map(|c| c.points).unwrap_or(0);
+                        format!("{}: {}", i18n.get().t("sale.points_available"), points)
+                    }}</p>
+                    <Show when=move || redeem_error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || redeem_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="admin-input-row">
+                        <input type="text" inputmode="numeric" class="weigh-input"
+                            prop:value=move || redeem_points.get()
+                            on:input=move |ev| set_redeem_points.set(event_target_value(&ev)) />
+                        <span>{move || {
+                            let points = redeem_points.get().parse::<i64>().unwrap_or(0);
+                            currency.with(|c| format!("= {} {}", c, loyalty_settings.get().redemption_value(points, c).format(c)))
+                        }}</span>
+                    </div>
+                    <NumericKeyboard on_key=on_redeem_key i18n=i18n />
+                    <div class="modal-actions">
+                        <button class="btn-primary" on:click=move |_| confirm_redeem()
+                            disabled=move || redeem_points.get().is_empty()
+                        >{move || i18n.get().t("sale.redeem_points")}</button>
+                        <button class="btn-secondary" on:click=move |_| set_show_redeem.set(false)>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
+        <Show when=move || show_deposit_return.get() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal modifier-picker">
+                    <h3>{move || i18n.get().t("sale.deposit_return")}</h3>
+                    <p class="text-muted">{move || i18n.get().t("sale.deposit_return_hint")}</p>
+                    <Show when=move || deposit_error.get().is_some() fallback=|| ()>
+                        <p class="error-message">{move || deposit_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <div class="modifier-options">
+                        <For each=move || { items.get().into_iter().filter(|i| i.is_deposit).collect::<Vec<_>>() } key=|i| (i.id, i.name.clone(), i.price.0) let:dep>
+                            {
+                                let dep_id = dep.id;
+                                let label = currency.with(|c| format!("{} (-{} {})", dep.name, c, dep.price.format(c)));
+                                view! {
+                                    <button
+                                        class=move || { if returned_units(dep_id) > 0 { "modifier-option selected" } else { "modifier-option" } }
+                                        on:click=move |_| return_deposit_unit(dep_id)
+                                    >{move || match returned_units(dep_id) {
+                                        0 => label.clone(),
+                                        n => format!("{} · {}x", label, n),
+                                    }}</button>
+                                }
+                            }
+                        </For>
+                    </div>
+                    <div class="modal-actions">
+                        <button class="btn-secondary" on:click=move |_| set_show_deposit_return.set(false)>{move || i18n.get().t("gift_cards.close")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
+        <Show when=move || editing_note.get().is_some() fallback=|| ()>
+            <div class="modal-overlay">
+                <div class="confirmation-modal note-editor">
+                    <h3>{move || i18n.get().t("sale.line_note")}</h3>
+                    <input type="text" maxlength="200"
+                        placeholder=move || i18n.get().t("sale.line_note_placeholder")
+                        prop:value=move || note_text.get()
+                        on:input=move |ev| set_note_text.set(event_target_value(&ev)) />
+                    <OnScreenKeyboard on_key=on_note_kb_key shift=kb_shift i18n=i18n />
+                    <div class="modal-actions">
+                        <button class="btn-primary" on:click=move |_| save_note(Some(note_text.get()))>{move || i18n.get().t("general.save")}</button>
+                        <button class="btn-danger" on:click=move |_| save_note(None)>{move || i18n.get().t("sale.clear_note")}</button>
+                        <button class="btn-secondary" on:click=move |_| set_editing_note.set(None)>{move || i18n.get().t("general.cancel")}</button>
+                    </div>
+                </div>
+            </div>
+        </Show>
+
         <div class="sale-page">
             <Show when=move || active_sale_view.get() == "sale" fallback=move || view! {
+                <Show when=move || active_sale_view.get() == "tables" fallback=move || view! {
                 // Kitchen status tab (read-only)
                 <div class="kitchen-status-panel">
                     <h3>{i18n.get().t("sale.kitchen_orders")}</h3>
@@ -543,7 +1696,7 @@ pub fn SalePage() -> impl IntoView {
                                 view! {
                             <div class=card_class>
                                 <div class="kitchen-status-header">
-                                    <strong>{order.customer_name.clone().unwrap_or_else(|| i18n.get().t("general.walkin"))}</strong>
+                                    <strong>{order.table_name.clone().or(order.customer_name.clone()).unwrap_or_else(|| i18n.get().t("general.walkin"))}</strong>
                                     {if all_done {
                                         view! { <span class="kitchen-status-time kitchen-status-complete-badge">{i18n.get().t("sale.complete")}</span> }.into_any()
                                     } else {
@@ -555,6 +1708,8 @@ pub fn SalePage() -> impl IntoView {
                                         <li class=if item.completed { "kitchen-status-item-done" } else { "" }>
                                             {if item.completed { "✓ " } else { "" }}
                                             {format!("{}x {}", item.quantity, item.item_name)}
+                                            {item.modifiers.clone().map(|m| format!(" ({})", m))}
+                                            {item.note.clone().map(|n| view! { <span class="line-note">{n}</span> })}
                                         </li>
                                     </For>
                                 </ul>
@@ -566,11 +1721,35 @@ pub fn SalePage() -> impl IntoView {
                         <p class="kitchen-empty">{i18n.get().t("sale.no_kitchen_orders")}</p>
                     </Show>
                 </div>
+                }>
+                    <TablesPanel
+                        areas=floor_plan
+                        status=table_status
+                        open_tabs=open_transactions
+                        tick=tick
+                        on_resume=move |id| {
+                            resume_transaction(id);
+                            active_sale_view.set("sale".to_string());
+                        }
+                        on_new=move |table_id| {
+                            begin_transaction(Some(table_id));
+                            active_sale_view.set("sale".to_string());
+                        }
+                    />
+                </Show>
             }>
 
             <div class="sale-grid">
                 <div class=move || if mobile_panel.get() == "checkout" { "items-section mobile-hidden" } else { "items-section" }>
                     <h2>{i18n.get().t("sale.items")}</h2>
+                    <Show when=move || scan_error.get().is_some() fallback=|| ()>
+                        <p class="error-message scan-error" on:click=move |_| set_scan_error.set(None)
+                        >{move || scan_error.get().unwrap_or_default()}</p>
+                    </Show>
+                    <Show when=move || fiscal_warning.get().is_some() fallback=|| ()>
+                        <p class="error-message scan-error" on:click=move |_| set_fiscal_warning.set(None)
+                        >{move || fiscal_warning.get().unwrap_or_default()}</p>
+                    </Show>
                     <div class="category-tabs">
                         <button
                             class=move || if selected_category.get().is_none() { "active" } else { "" }
@@ -610,7 +1789,7 @@ pub fn SalePage() -> impl IntoView {
                         </For>
                     </select>
                     <div class="items-grid">
-                        <For each=filtered_items key=|item| (item.id, item.name.clone(), item.price.to_bits(), item.in_stock, item.image_path.clone(), item.stock_quantity) let:item>
+                        <For each=filtered_items key=|item| (item.id, item.name.clone(), item.price.0, item.in_stock, item.image_path.clone(), item.stock_quantity, item.price_unit.clone()) let:item>
                             {
                                 let item_clone = item.clone();
                                 let has_image = item.image_path.is_some();
@@ -627,7 +1806,7 @@ pub fn SalePage() -> impl IntoView {
                                             <img class="item-card-img" src=path alt="" />
                                         })}
                                         <div class="item-card-overlay">
-                                            <div class="item-price-badge">{format!("{}{:.2}", &currency.get(), item.price)}</div>
+                                            <div class="item-price-badge">{currency.with(|c| format!("{}{}{}", c, item.price.format(c), item.price_suffix()))}</div>
                                             <div class="item-name-badge">{item.name.clone()}</div>
                                         </div>
                                         <Show when=move || is_out fallback=|| ()>
@@ -660,7 +1839,7 @@ pub fn SalePage() -> impl IntoView {
                                 <Show when=move || show_name_kb.get() && current_transaction.get().is_none() fallback=|| ()>
                                     <OnScreenKeyboard on_key=on_name_kb_key shift=kb_shift i18n=i18n />
                                 </Show>
-                                <button class="btn-primary" on:click=start_transaction>{move || i18n.get().t("sale.new_transaction")}</button>
+                                <button class="btn-primary" on:click=move |_| begin_transaction(None)>{move || i18n.get().t("sale.new_transaction")}</button>
 
                                 <Show when=move || last_closed_transaction.get().is_some() fallback=|| ()>
                                 {
@@ -668,7 +1847,7 @@ pub fn SalePage() -> impl IntoView {
                                         view! {
                                             <div class="last-change-display">
                                                 <strong>{i18n.get().t("sale.last_change")}</strong>
-                                                {format!("{} {:.2}", &currency.get(), t.change_amount.unwrap())}
+                                                {currency.with(|c| format!("{} {}", c, t.change_amount.unwrap().format(c)))}
                                             </div>
                                         }
                                     })
@@ -677,14 +1856,16 @@ pub fn SalePage() -> impl IntoView {
 
                                 <Show when=move || !open_transactions.get().is_empty() fallback=|| ()>
                                     <div class="open-transactions-list">
-                                        <For each=move || open_transactions.get() key=|t| (t.id, t.total.to_bits(), t.customer_name.clone()) let:trans>
+                                        <For each=move || open_transactions.get() key=|t| (t.id, t.total.0, t.customer_name.clone(), t.table_id) let:trans>
                                             {
                                                 let trans_id = trans.id;
+                                                let table_id = trans.table_id;
                                                 view! {
                                                     <div class="open-transaction-item">
                                                         <div>
+                                                            {move || table_name(table_id).map(|n| view! { <span class="open-transaction-table">{n}</span> })}
                                                             <strong>{trans.customer_name.clone().unwrap_or_else(|| i18n.get().t("general.walkin"))}</strong>
-                                                            <span>" - "{format!("{} {:.2}", &currency.get(), trans.total)}</span>
+                                                            <span>" - "{currency.with(|c| format!("{} {}", c, trans.total.format(c)))}</span>
                                                         </div>
                                                         <button class="btn-small" on:click=move |_| resume_transaction(trans_id)>{i18n.get().t("sale.resume")}</button>
                                                     </div>
@@ -703,12 +1884,53 @@ pub fn SalePage() -> impl IntoView {
                                     <input type="text" placeholder=move || i18n.get().t("general.walkin")
                                         on:focus=move |_| set_show_name_kb.set(true)
                                         on:input=move |ev| {
-                                            set_customer_name.set(event_target_value(&ev));
+                                            let name = event_target_value(&ev);
+                                            set_customer_name.set(name.clone());
                                             sync_customer_name();
+                                            search_customer_field(name);
                                         }
                                         prop:value=move || customer_name.get()
                                     />
                                 </div>
+                                <Show when=move || { linked_customer.get().is_none() && customer_name.get().trim().chars().count() >= 2 } fallback=|| ()>
+                                    <div class="customer-matches">
+                                        <For each=move || customer_matches.get() key=|c| c.id let:c>
+                                            {
+                                                let id = c.id;
+                                                let contact = c.phone.clone().or(c.email.clone()).unwrap_or_default();
+                                                view! {
+                                                    <button class="btn-secondary" on:click=move |_| link_customer(Some(id))>
+                                                        <strong>{c.name.clone()}</strong>" "<span class="item-stock">{contact}</span>
+                                                    </button>
+                                                }
+                                            }
+                                        </For>
+                                        <button class="btn-secondary" on:click=register_customer>
+                                            {move || format!("+ {} \"{}\"", i18n.get().t("sale.new_customer"), customer_name.get().trim())}
+                                        </button>
+                                    </div>
+                                </Show>
+                                {move || linked_customer.get().map(|c| {
+                                    let settings = loyalty_settings.get();
+                                    let points = c.points;
+                                    view! {
+                                        <div class="admin-input-row">
+                                            <span>
+                                                {currency.with(|cur| format!("{} · {} {} ({} {})", c.name, points, i18n.get().t("sale.loyalty_points"), cur, settings.redemption_value(points, cur).format(cur)))}
+                                            </span>
+                                            <Show when=move || { points > 0 } fallback=|| ()>
+                                                <button class="btn-secondary" on:click=move |_| {
+                                                    set_redeem_points.set(String::new());
+                                                    set_redeem_error.set(None);
+                                                    set_show_redeem.set(true);
+                                                }>{move || i18n.get().t("sale.redeem_points")}</button>
+                                            </Show>
+                                            <button class="btn-secondary" title=move || i18n.get().t("sale.unlink_customer")
+                                                on:click=move |_| link_customer(None)
+                                            >"×"</button>
+                                        </div>
+                                    }
+                                })}
                                 <Show when=move || !customer_groups.get().is_empty() fallback=|| ()>
                                     <div class="admin-input-row">
                                         <strong>{move || i18n.get().t("sale.customer_group")}</strong>
@@ -727,6 +1949,24 @@ pub fn SalePage() -> impl IntoView {
                                         </select>
                                     </div>
                                 </Show>
+                                <Show when=move || !floor_plan.get().is_empty() fallback=|| ()>
+                                    <div class="admin-input-row">
+                                        <strong>{move || i18n.get().t("sale.table")}</strong>
+                                        <select
+                                            prop:value=move || current_table().map(|id| id.to_string()).unwrap_or_default()
+                                            on:change=move |ev| assign_table(Uuid::parse_str(&event_target_value(&ev)).ok())
+                                        >
+                                            <option value="">{move || i18n.get().t("sale.no_table")}</option>
+                                            <For each=move || floor_plan.get() key=|a| (a.area.id, a.tables.len()) let:area>
+                                                <optgroup label=area.area.name.clone()>
+                                                    {area.tables.into_iter().map(|t| view! {
+                                                        <option value={t.id.to_string()}>{t.name}</option>
+                                                    }).collect_view()}
+                                                </optgroup>
+                                            </For>
+                                        </select>
+                                    </div>
+                                </Show>
                                 <Show when=move || show_name_kb.get() && current_transaction.get().is_some() fallback=|| ()>
                                     <OnScreenKeyboard on_key=on_name_kb_key shift=kb_shift i18n=i18n />
                                 </Show>
@@ -734,45 +1974,220 @@ pub fn SalePage() -> impl IntoView {
 
                             <div class="transaction-items">
                                 <table class="data-table"><tbody>
-                                    <For each=move || transaction_items.get() key=|item| (item.id, item.quantity) let:item>
+                                    <For each=move || group_bundles(&transaction_items.get()) key=|item| (item.id, item.quantity, item.note.clone()) let:item>
                                         {
-                                            let item_id = item.item_id;
+                                            let line_id = item.id;
+                                            let line = item.clone();
                                             view! {
                                                 <tr>
-                                                    <td>{item.item_name.clone()}</td>
-                                                    <td>{format!("{}x", item.quantity)}</td>
-                                                    <td>{format!("{} {:.2}", &currency.get(), item.total_price)}</td>
+                                                    <td>
+                                                        {item.item_name.clone()}
+                                                        {currency.with(|c| item.weight_label(c)).map(|w| view! { <span class="line-weight">{w}</span> })}
+                                                        {item.modifier_names().map(|m| view! { <span class="line-modifiers">{m}</span> })}
+                                                        {(!item.components.is_empty()).then(|| view! { <span class="line-modifiers">{item.components.join(", ")}</span> })}
+                                                        {item.note.clone().map(|n| view! { <span class="line-note">{n}</span> })}
+                                                    </td>
+                                                    <td>{match item.unit.as_deref() {
+                                                        Some(unit) => format!("{} {}", item.quantity, unit),
+                                                        None => format!("{}x", item.quantity),
+                                                    }}</td>
+                                                    <td>{currency.with(|c| format!("{} {}", c, item.total_price.format(c)))}</td>
                                                     <td class="data-table-actions">
-                                                        <button class="btn-remove" on:click=move |_| remove_item(item_id)>"-"</button>
+                                                        {(!item.is_deposit).then(|| view! {
+                                                            <button class="btn-note" title=move || i18n.get().t("sale.line_note")
+                                                                on:click=move |_| start_note(line.clone())>"✎"</button>
+                                                            <button class="btn-note" title=move || i18n.get().t("discounts.line_discount")
+                                                                on:click=move |_| start_discount(Some(line_id))>"%"</button>
+                                                        })}
+                                                        // A deposit charged with an item goes with the item.
+                                                        {item.deposit_for.is_none().then(|| view! {
+                                                            <button class="btn-remove" on:click=move |_| remove_item(line_id)>"-"</button>
+                                                        })}
                                                     </td>
                                                 </tr>
                                             }
                                         }
                                     </For>
+                                    <For each=move || gift_card_loads.get() key=|l| l.id let:load>
+                                        <tr>
+                                            <td>{move || format!("{} {}", i18n.get().t("gift_cards.title"), mask_card_code(&load.code))}</td>
+                                            <td>"1x"</td>
+                                            <td>{currency.with(|c| format!("{} {}", c, load.amount.format(c)))}</td>
+                                            <td class="data-table-actions">
+                                                <button class="btn-remove" on:click=move |_| remove_top_up(load.id)>"-"</button>
+                                            </td>
+                                        </tr>
+                                    </For>
                                 </tbody></table>
                             </div>
 
+                            <div class="tab-actions">
+                                <button class="btn-small" on:click=open_gift_card
+                                >{move || i18n.get().t("gift_cards.title")}</button>
+                                <Show when=move || items.get().iter().any(|i| i.is_deposit) fallback=|| ()>
+                                    <button class="btn-small"
+                                        on:click=move |_| {
+                                            set_deposit_error.set(None);
+                                            set_show_deposit_return.set(true);
+                                        }
+                                        disabled=move || current_transaction.get().is_none()
+                                    >{move || i18n.get().t("sale.deposit_return")}</button>
+                                </Show>
+                            </div>
+
+                            <Show when=move || !transaction_items.get().is_empty() fallback=|| ()>
+                                <div class="tab-actions">
+                                    <button class="btn-small" on:click=move |_| set_tab_action.set(Some("move"))
+                                        disabled=move || open_transactions.get().len() < 2
+                                    >{move || i18n.get().t("sale.move_items")}</button>
+                                    <button class="btn-small" on:click=move |_| set_tab_action.set(Some("merge"))
+                                        disabled=move || open_transactions.get().len() < 2
+                                    >{move || i18n.get().t("sale.merge_tab")}</button>
+                                    <button class="btn-small" on:click=move |_| set_tab_action.set(Some("split"))
+                                        disabled=move || { movable_lines(&transaction_items.get()).iter().map(|i| i.split_units()).sum::<i32>() < 2 }
+                                    >{move || i18n.get().t("sale.split_tab")}</button>
+                                    <button class="btn-small" on:click=move |_| start_discount(None)
+                                    >{move || i18n.get().t("discounts.sale_discount")}</button>
+                                </div>
+                            </Show>
+
+                            <For each=move || discount_lines(&transaction_items.get()) key=|d| (d.name.clone(), d.manual_discount_id, d.amount.0) let:d>
+                                <div class="transaction-discount">
+                                    <span>{d.name.clone()}</span>
+                                    <span>
+                                        {move || currency.with(|c| format!("{} {}", c, d.amount.format(c)))}
+                                        {d.manual_discount_id.map(|id| view! {
+                                            " "<button class="btn-remove" on:click=move |_| remove_discount(id)>"×"</button>
+                                        })}
+                                    </span>
+                                </div>
+                            </For>
+                            <Show when=move || !tax_inclusive.get() fallback=|| ()>
+                                <div class="transaction-tax">
+                                    <span>{move || i18n.get().t("sale.subtotal")}</span>
+                                    <span>{move || currency.with(|c| format!("{} {}", c, subtotal().format(c)))}</span>
+                                </div>
+                                <div class="transaction-tax">
+                                    <span>{move || i18n.get().t("sale.tax")}</span>
+                                    <span>{move || currency.with(|c| format!("{} {}", c, added_tax_amount().format(c)))}</span>
+                                </div>
+                            </Show>
                             <div class="transaction-total">
                                 <strong>{i18n.get().t("sale.total")}</strong>
-                                <strong>{move || format!("{} {:.2}", &currency.get(), transaction_total())}</strong>
+                                <strong>{move || currency.with(|c| format!("{} {}", c, transaction_total().format(c)))}</strong>
                             </div>
 
+                            <div class="tip-section">
+                                <span>{move || i18n.get().t("sale.tip")}</span>
+                                <div class="tip-buttons">
+                                    <For each=|| TIP_PRESETS.to_vec() key=|p| *p let:percent>
+                                        <button
+                                            class=move || {
+                                                let preset = tip_for_percent(transaction_total(), percent);
+                                                if preset.is_positive() && tip.get() == preset { "tender-btn active" } else { "tender-btn" }
+                                            }
+                                            on:click=move |_| set_tip.set(tip_for_percent(transaction_total(), percent))
+                                        >{format!("{}%", percent)}</button>
+                                    </For>
+                                    <button
+                                        class=move || if tip.get().is_zero() { "tender-btn active" } else { "tender-btn" }
+                                        on:click=move |_| set_tip.set(Money::ZERO)
+                                    >{move || i18n.get().t("sale.no_tip")}</button>
+                                </div>
+                                <input type="text" inputmode="decimal" class="tip-input" autocomplete="off"
+                                    placeholder=move || i18n.get().t("sale.tip_placeholder")
+                                    prop:value=move || if tip.get().is_positive() { currency.with(|c| tip.get().format(c)) } else { String::new() }
+                                    on:change=move |ev| {
+                                        let amount = Money::parse(&event_target_value(&ev), &currency.get_untracked()).unwrap_or_default();
+                                        set_tip.set(amount.max(Money::ZERO));
+                                    } />
+                            </div>
+
+                            <div class="tender-buttons">
+                                <For each=|| TENDERS.to_vec() key=|t| *t let:t>
+                                    <button
+                                        class=move || if tender.get() == t { "tender-btn active" } else { "tender-btn" }
+                                        on:click=move |_| set_tender.set(t.to_string())
+                                    >{move || i18n.get().t(&format!("sale.tender_{}", t))}</button>
+                                </For>
+                            </div>
+
+                            <Show when=move || !split_payments.get().is_empty() fallback=|| ()>
+                                <div class="split-payments">
+                                    <For each=move || { split_payments.get().into_iter().enumerate().collect::<Vec<_>>() } key=|(i, p)| (*i, p.tender.clone(), p.amount.0) let:entry>
+                                        {
+                                            let (idx, p) = entry;
+                                            view! {
+                                                <div class="split-payment-row">
+                                                    <span>{move || i18n.get().t(&format!("sale.tender_{}", p.tender))}</span>
+                                                    <span>{currency.with(|c| format!("{} {}", c, p.amount.format(c)))}</span>
+                                                    <button class="btn-remove" on:click=move |_| set_split_payments.update(|list| { list.remove(idx); })>"×"</button>
+                                                </div>
+                                            }
+                                        }
+                                    </For>
+                                    <div class="split-payment-row split-payment-remaining">
+                                        <strong>{move || i18n.get().t("sale.remaining")}</strong>
+                                        <strong>{move || currency.with(|c| format!("{} {}", c, remaining_due().format(c)))}</strong>
+                                    </div>
+                                </div>
+                            </Show>
+
+                            <Show when=move || due_in_tender() != remaining_due() fallback=|| ()>
+                                <p class="text-muted">
+                                    {move || currency.with(|c| format!("{}: {} {}", i18n.get().t("sale.cash_rounded"), c, due_in_tender().format(c)))}
+                                </p>
+                            </Show>
+
+                            <Show when=move || tender.get() == "account" fallback=|| ()>
+                                {move || {
+                                    // Charged to the linked customer, else to the order's group.
+                                    let holder = linked_customer.get().map(|c| c.name).or_else(|| {
+                                        let group = selected_group.get()?;
+                                        customer_groups.get().into_iter().find(|g| g.id == group).map(|g| g.name)
+                                    });
+                                    match holder {
+                                        Some(name) => view! {
+                                            <p class="text-muted">{format!("{}: {}", i18n.get().t("sale.charged_to"), name)}</p>
+                                        }.into_any(),
+                                        None => view! {
+                                            <p class="error-message">{i18n.get().t("sale.account_needs_customer")}</p>
+                                        }.into_any(),
+                                    }
+                                }}
+                            </Show>
+
+                            <Show when=move || tender.get() == "gift_card" fallback=|| ()>
+                                <div class="admin-input-row">
+                                    <input type="text" autocomplete="off"
+                                        placeholder=move || i18n.get().t("gift_cards.code_placeholder_pay")
+                                        prop:value=move || payment_card_code.get()
+                                        on:input=move |ev| set_payment_card_code.set(event_target_value(&ev)) />
+                                </div>
+                            </Show>
+
                             <div class="payment-change-wrapper">
                                 <div class="payment-section">
-                                    <strong>{i18n.get().t("sale.cash")}</strong>
+                                    <strong>{move || format!("{}: ", i18n.get().t(&format!("sale.tender_{}", tender.get())))}</strong>
                                     <input type="text" class="payment-input" placeholder="" readonly value=move || payment_amount.get() />
                                 </div>
                                 <div class="change-section">
                                     <strong>{i18n.get().t("sale.change")}</strong>
                                     <input type="text" class="change-input" placeholder="" readonly
                                         value=move || {
-                                            match payment_amount.get().parse::<f64>() {
-                                                Ok(amount) => format!("{:.2}", amount - transaction_total()),
-                                                Err(_) => String::new(),
+                                            let cur = currency.get();
+                                            match Money::parse(&payment_amount.get(), &cur) {
+                                                Some(amount) => (amount - due_in_tender()).format(&cur),
+                                                // Deposits paid back with nothing bought.
+                                                None if transaction_total().is_negative() => {
+                                                    (-cash_rounding.get().round(transaction_total())).format(&cur)
+                                                }
+                                                None => String::new(),
                                             }
                                         }
                                     />
                                 </div>
+                                <button class="btn-secondary add-payment-btn" on:click=add_split_payment>{move || i18n.get().t("sale.add_payment")}</button>
                             </div>
 
                             <div class="keypad-section">
@@ -802,11 +2217,24 @@ pub fn SalePage() -> impl IntoView {
                                             }
                                         </For>
                                         <button class="quick-cash-btn quick-cash-exact"
-                                            on:click=move |_| set_payment_amount.set(format!("{:.2}", transaction_total()))
+                                            on:click=move |_| set_payment_amount.set(currency.with(|c| due_in_tender().format(c)))
                                         >{move || i18n.get().t("sale.exact")}</button>
                                         <button class="quick-cash-btn quick-cash-clear"
                                             on:click=move |_| set_payment_amount.set(String::new())
                                         >{move || i18n.get().t("sale.clear")}</button>
+                                        // What is given over the amount due is kept as a tip.
+                                        <button class="quick-cash-btn quick-cash-tip"
+                                            disabled=move || Money::parse(&payment_amount.get(), &currency.get()).is_none_or(|a| a <= due_in_tender())
+                                            on:click=move |_| {
+                                                if let Some(given) = Money::parse(&payment_amount.get_untracked(), &currency.get_untracked()) {
+                                                    // The tip makes up the cash given exactly, so no rounding is left.
+                                                    let change = given - remaining_due();
+                                                    if change.is_positive() {
+                                                        set_tip.update(|t| *t += change);
+                                                    }
+                                                }
+                                            }
+                                        >{move || i18n.get().t("sale.keep_change")}</button>
                                     </div>
                                 }>
                                     <div class="keypad">
@@ -828,6 +2256,10 @@ pub fn SalePage() -> impl IntoView {
                                 </Show>
                             </div>
 
+                            <Show when=move || checkout_error.get().is_some() fallback=|| ()>
+                                <p class="error-message">{move || checkout_error.get().unwrap_or_default()}</p>
+                            </Show>
+
                             <div class="action-buttons">
                                 <button class="action-button cancel" on:click=move |_| confirm_cancel_sale(current_transaction.get().unwrap_or_default())>{move || i18n.get().t("sale.cancel")}</button>
                                 <button class="action-button pause" on:click=pause_sale>{move || i18n.get().t("sale.back")}</button>
@@ -836,7 +2268,7 @@ pub fn SalePage() -> impl IntoView {
 
                             <Show when=move || change_amount.get().is_some() fallback=|| ()>
                                 <div class="change-display">
-                                    <h3>{move || i18n.get().t("sale.change")}{move || format!("{} {:.2}", &currency.get(), change_amount.get().unwrap())}</h3>
+                                    <h3>{move || i18n.get().t("sale.change")}{move || currency.with(|c| format!("{} {}", c, change_amount.get().unwrap().format(c)))}</h3>
                                 </div>
                             </Show>
                         </div>
@@ -854,8 +2286,8 @@ pub fn SalePage() -> impl IntoView {
                     on:click=move |_| set_mobile_panel.set("checkout".to_string())
                 >
                     {move || i18n.get().t("sale.checkout")}
-                    <Show when=move || { current_transaction.get().is_some() && transaction_total() > 0.0 } fallback=|| ()>
-                        <span class="mobile-tab-badge">{move || format!("{}{:.2}", currency.get(), transaction_total())}</span>
+                    <Show when=move || { current_transaction.get().is_some() && transaction_total().is_positive() } fallback=|| ()>
+                        <span class="mobile-tab-badge">{move || currency.with(|c| format!("{}{}", c, transaction_total().format(c)))}</span>
                     </Show>
                 </button>
             </div>
@@ -864,3 +2296,201 @@ pub fn SalePage() -> impl IntoView {
         </Show>
     }
 }
+
+/// The lines that can be moved or split onto another tab: deposits follow
+/// their items and deposit returns stay where they are.
+fn movable_lines(lines: &[TransactionItemDetail]) -> Vec<TransactionItemDetail> {
+    group_bundles(lines).into_iter().filter(|l| l.deposit_for.is_none() && l.quantity.is_positive()).collect()
+}
+
+/// Moves lines to another open tab, merges this tab into another, or splits
+/// it evenly, depending on `mode` ("move", "merge" or "split"). `on_done`
+/// gets the tab to switch to, if the current one no longer exists.
+#[component]
+fn TabActionsPanel(
+    mode: &'static str,
+    transaction_id: Uuid,
+    items: Vec<TransactionItemDetail>,
+    open_tabs: Vec<Transaction>,
+    on_done: impl Fn(Option<Uuid>) + Copy + Send + 'static,
+    on_cancel: impl Fn() + Copy + Send + 'static,
+) -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let total_units: i32 = items.iter().map(|i| i.split_units()).sum();
+    let (items, _) = signal(items);
+    let (target, set_target) = signal(open_tabs.first().map(|t| t.id));
+    let (chosen, set_chosen) = signal(Vec::<LineMove>::new());
+    let (parts, set_parts) = signal(2i32);
+    let (error, set_error) = signal(Option::<String>::None);
+
+    let chosen_qty = move |line_id: Uuid| {
+        chosen.get().iter().find(|m| m.transaction_item_id == line_id).map(|m| m.quantity).unwrap_or_default()
+    };
+    // Steps by one unit; a weighed line moves whole, so one step takes all of it.
+    let adjust = move |line_id: Uuid, max: Quantity, delta: Quantity| {
+        set_chosen.update(|list| match list.iter_mut().find(|m| m.transaction_item_id == line_id) {
+            Some(m) => m.quantity = (m.quantity + delta).clamp(Quantity::ZERO, max),
+            None if delta.is_positive() => list.push(LineMove { transaction_item_id: line_id, quantity: delta.min(max) }),
+            None => {}
+        });
+    };
+
+    let confirm = move |_| {
+        set_error.set(None);
+        let target = target.get();
+        let moves: Vec<LineMove> = chosen.get().into_iter().filter(|m| m.quantity.is_positive()).collect();
+        let parts = parts.get();
+        leptos::task::spawn_local(async move {
+            let result = match (mode, target) {
+                ("move", Some(to)) => move_transaction_items(transaction_id, to, moves).await.map(|_| None),
+                ("merge", Some(into)) => merge_transactions(transaction_id, into).await.map(|t| Some(t.id)),
+                ("split", _) => split_transaction(transaction_id, parts).await.map(|_| None),
+                _ => return,
+            };
+            match result {
+                Ok(switch_to) => on_done(switch_to),
+                Err(e) => set_error.set(Some(e.to_string().replace("error running server function: ", ""))),
+            }
+        });
+    };
+
+    let title = match mode {
+        "move" => "sale.move_items",
+        "merge" => "sale.merge_tab",
+        _ => "sale.split_tab",
+    };
+    let tab_label = move |t: &Transaction| {
+        currency.with(|c| format!("{} - {} {}", t.customer_name.clone().unwrap_or_else(|| i18n.get().t("general.walkin")), c, t.total.format(c)))
+    };
+
+    view! {
+        <div class="modal-overlay">
+            <div class="confirmation-modal tab-actions-panel">
+                <h3>{move || i18n.get().t(title)}</h3>
+                <Show when=move || error.get().is_some() fallback=|| ()>
+                    <p class="error-message">{move || error.get().unwrap_or_default()}</p>
+                </Show>
+                {(mode != "split").then(|| view! {
+                    <div class="form-group">
+                        <label>{move || i18n.get().t(if mode == "move" { "sale.move_to" } else { "sale.merge_into" })}</label>
+                        <select on:change=move |ev| set_target.set(Uuid::parse_str(&event_target_value(&ev)).ok())>
+                            {open_tabs.iter().map(|t| view! {
+                                <option value=t.id.to_string()>{tab_label(t)}</option>
+                            }).collect_view()}
+                        </select>
+                    </div>
+                })}
+                {(mode == "move").then(|| view! {
+                    <table class="detail-items-table">
+                        <tbody>
+                            <For each=move || items.get() key=|i| i.id let:item>
+                                {
+                                    let line_id = item.id;
+                                    let max = item.quantity;
+                                    view! {
+                                        <tr>
+                                            <td>
+                                                {item.item_name.clone()}
+                                                {item.modifier_names().map(|m| view! { <span class="line-modifiers">{m}</span> })}
+                                                {(!item.components.is_empty()).then(|| view! { <span class="line-modifiers">{item.components.join(", ")}</span> })}
+                                                {item.note.clone().map(|n| view! { <span class="line-note">{n}</span> })}
+                                            </td>
+                                            <td class="refund-qty">
+                                                <button class="btn-small" on:click=move |_| adjust(line_id, max, -Quantity::ONE)>"-"</button>
+                                                <span>{move || format!("{}/{}", chosen_qty(line_id), max)}</span>
+                                                <button class="btn-small" on:click=move |_| adjust(line_id, max, Quantity::ONE)>"+"</button>
+                                            </td>
+                                        </tr>
+                                    }
+                                }
+                            </For>
+                        </tbody>
+                    </table>
+                })}
+                {(mode == "split").then(|| view! {
+                    <div class="form-group">
+                        <label>{move || i18n.get().t("sale.split_into")}</label>
+                        <div class="refund-qty">
+                            <button class="btn-small" on:click=move |_| set_parts.update(|p| *p = (*p - 1).max(2))>"-"</button>
+                            <span>{move || parts.get().to_string()}</span>
+                            <button class="btn-small" on:click=move |_| set_parts.update(|p| *p = (*p + 1).min(total_units.max(2)))>"+"</button>
+                        </div>
+                    </div>
+                })}
+                <div class="modal-actions">
+                    <button class="btn-primary" on:click=confirm
+                        disabled=move || (mode != "split" && target.get().is_none())
+                            || (mode == "move" && chosen.get().iter().all(|m| m.quantity.is_zero()))
+                    >{move || i18n.get().t(title)}</button>
+                    <button class="btn-secondary" on:click=move |_| on_cancel()>{move || i18n.get().t("general.cancel")}</button>
+                </div>
+            </div>
+        </div>
+    }
+}
+
+/// Table view: every table of the floor plan with its open orders, the
+/// amount still open and the time since the first order.
+#[component]
+fn TablesPanel(
+    areas: ReadSignal<Vec<FloorAreaDetail>>,
+    status: ReadSignal<Vec<TableStatus>>,
+    open_tabs: ReadSignal<Vec<Transaction>>,
+    tick: ReadSignal<u32>,
+    on_resume: impl Fn(Uuid) + Copy + Send + 'static,
+    on_new: impl Fn(Uuid) + Copy + Send + 'static,
+) -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+
+    view! {
+        <div class="tables-panel">
+            <Show when=move || areas.get().is_empty() fallback=|| ()>
+                <p class="kitchen-empty">{move || i18n.get().t("sale.no_tables")}</p>
+            </Show>
+            <For each=move || areas.get() key=|a| (a.area.id, a.area.updated_at, a.tables.len()) let:area>
+                <div class="tables-area">
+                    <h3>{area.area.name.clone()}</h3>
+                    <div class="tables-grid">
+                        {area.tables.into_iter().map(|table| {
+                            let table_id = table.id;
+                            let table_status = move || status.get().into_iter().find(|s| s.table_id == table_id);
+                            let tabs = move || open_tabs.get().into_iter().filter(|t| t.table_id == Some(table_id)).collect::<Vec<_>>();
+                            view! {
+                                <div class=move || if table_status().is_some() { "table-card table-card-occupied" } else { "table-card" }>
+                                    <div class="table-card-header">
+                                        <strong>{table.name.clone()}</strong>
+                                        <span class="table-card-seats">{move || i18n.get().t("sale.seats").replace("{n}", &table.seats.to_string())}</span>
+                                    </div>
+                                    {move || match table_status() {
+                                        Some(s) => view! {
+                                            <div class="table-card-status">
+                                                <span>{currency.with(|c| format!("{} {}", c, s.open_total.format(c)))}</span>
+                                                <span class="kitchen-status-time">{format_elapsed(s.first_order_at, tick.get())}</span>
+                                            </div>
+                                        }.into_any(),
+                                        None => view! {
+                                            <div class="table-card-status table-card-free">{i18n.get().t("sale.table_free")}</div>
+                                        }.into_any(),
+                                    }}
+                                    <div class="table-card-tabs">
+                                        {move || tabs().into_iter().map(|t| {
+                                            let id = t.id;
+                                            let label = t.customer_name.clone().unwrap_or_else(|| i18n.get().t("general.walkin"));
+                                            view! {
+                                                <button class="btn-small" on:click=move |_| on_resume(id)>{label}</button>
+                                            }
+                                        }).collect_view()}
+                                        <button class="btn-small btn-primary" on:click=move |_| on_new(table_id)
+                                        >{move || i18n.get().t("sale.new_at_table")}</button>
+                                    </div>
+                                </div>
+                            }
+                        }).collect_view()}
+                    </div>
+                </div>
+            </For>
+        </div>
+    }
+}
@@ -26,9 +26,15 @@ pub fn TransactionsPage() -> impl IntoView {
     let (show_all, set_show_all) = signal(false);
     let (selected, set_selected) = signal(Option::<Uuid>::None);
     let (details, set_details) = signal(Option::<TransactionDetailsResponse>::None);
+    // Closed sale whose refund panel is open.
+    let (refunding, set_refunding) = signal(Option::<Uuid>::None);
+    let (reload, set_reload) = signal(0u32);
+    // Why the last refund could not be fiscally signed, until dismissed.
+    let (fiscal_warning, set_fiscal_warning) = signal(Option::<String>::None);
 
     Effect::new(move || {
         let show_all = show_all.get();
+        reload.get();
         leptos::task::spawn_local(async move {
             let trans = if show_all {
                 fetch_all_transactions().await
@@ -41,7 +47,23 @@ pub fn TransactionsPage() -> impl IntoView {
         });
     });
 
+    let on_refund_done = move |fiscal_error: Option<String>| {
+        set_fiscal_warning.set(fiscal_error.map(|error| {
+            i18n.get_untracked().t("fiscal.not_signed").replace("{error}", &error)
+        }));
+        set_refunding.set(None);
+        set_reload.update(|v| *v += 1);
+        if let Some(id) = selected.get_untracked() {
+            leptos::task::spawn_local(async move {
+                if let Ok(d) = fetch_transaction_details(id).await {
+                    set_details.set(Some(d));
+                }
+            });
+        }
+    };
+
     let on_row_click = move |id: Uuid| {
+        set_refunding.set(None);
         if selected.get() == Some(id) {
             set_selected.set(None);
             set_details.set(None);
@@ -65,6 +87,10 @@ pub fn TransactionsPage() -> impl IntoView {
                     {move || if show_all.get() { i18n.get().t("transactions.show_open") } else { i18n.get().t("transactions.show_all") }}
                 </button>
             </div>
+            <Show when=move || fiscal_warning.get().is_some() fallback=|| ()>
+                <p class="error-message" on:click=move |_| set_fiscal_warning.set(None)
+                >{move || fiscal_warning.get().unwrap_or_default()}</p>
+            </Show>
 
             <table class="data-table">
                 <thead>
@@ -84,6 +110,7 @@ pub fn TransactionsPage() -> impl IntoView {
                                 <tr
                                     class=move || {
                                         let status = match transaction.status.as_str() {
+                                            _ if transaction.refund_of.is_some() => "status-refund",
                                             "open" => "status-open",
                                             "closed" => "status-closed",
                                             "cancelled" => "status-cancelled",
@@ -99,8 +126,8 @@ pub fn TransactionsPage() -> impl IntoView {
                                     style="cursor: pointer;"
                                 >
                                     <td>{transaction.customer_name.clone().unwrap_or_else(|| i18n.get().t("general.walkin"))}</td>
-                                    <td>{format!("{} {:.2}", &currency.get(), transaction.total)}</td>
-                                    <td>{transaction.status.clone()}</td>
+                                    <td>{currency.with(|c| format!("{} {}", c, transaction.total.format(c)))}</td>
+                                    <td>{if transaction.refund_of.is_some() { i18n.get().t("transactions.refund") } else { transaction.status.clone() }}</td>
                                     <td>{transaction.created_at.format("%Y-%m-%d %H:%M").to_string()}</td>
                                 </tr>
                                 <Show when=is_selected fallback=|| ()>
@@ -112,14 +139,28 @@ pub fn TransactionsPage() -> impl IntoView {
                                             >
                                                 {move || details.get().map(|d| {
                                                     let t = d.transaction.clone();
-                                                    let items = d.items.clone();
+                                                    let items = group_bundles(&d.items);
+                                                    let discounts = discount_lines(&d.items);
+                                                    let payments = d.payments.clone();
+                                                    let gift_card_loads = d.gift_card_loads.clone();
+                                                    let taxes = d.taxes.clone();
+                                                    let has_taxes = !taxes.is_empty();
+                                                    // Bundles are refunded through their components.
+                                                    let refund_items: Vec<TransactionItemDetail> =
+                                                        d.items.iter().filter(|i| !i.is_bundle).cloned().collect();
+                                                    let refund_items = StoredValue::new(refund_items);
+                                                    let can_refund = t.status == "closed" && t.refund_of.is_none();
+                                                    let tax_inclusive = t.tax_inclusive;
+                                                    let refund_of = t.refund_of.map(|id| id.simple().to_string()[..8].to_string());
                                                     let has_customer = t.customer_name.is_some();
                                                     let customer = t.customer_name.clone().unwrap_or_default();
-                                                    let total = format!("{} {:.2}", &currency.get(), t.total);
+                                                    let total = currency.with(|c| format!("{} {}", c, t.total.format(c)));
                                                     let has_paid = t.paid_amount.is_some();
-                                                    let paid = format!("{} {:.2}", &currency.get(), t.paid_amount.unwrap_or(0.0));
+                                                    let paid = currency.with(|c| format!("{} {}", c, t.paid_amount.unwrap_or_default().format(c)));
                                                     let has_change = t.change_amount.is_some();
-                                                    let change = format!("{} {:.2}", &currency.get(), t.change_amount.unwrap_or(0.0));
+                                                    let change = currency.with(|c| format!("{} {}", c, t.change_amount.unwrap_or_default().format(c)));
+                                                    let fiscal_counter = t.fiscal_counter;
+                                                    let fiscal_error = t.fiscal_error.clone();
                                                     view! {
                                                         <div class="transaction-detail-panel">
                                                             <Show when=move || has_customer fallback=|| ()>
@@ -129,6 +170,13 @@ pub fn TransactionsPage() -> impl IntoView {
                                                                 </div>
                                                             </Show>
 
+                                                            {refund_of.map(|r| view! {
+                                                                <div class="detail-field">
+                                                                    <strong>{i18n.get().t("transactions.refund_of")}</strong>
+                                                                    {format!("#{}", r)}
+                                                                </div>
+                                                            })}
+
                                                             <table class="detail-items-table">
                                                                 <thead>
                                                                     <tr>
@@ -145,15 +193,56 @@ pub fn TransactionsPage() -> impl IntoView {
                                                                         let:item
                                                                     >
                                                                         <tr>
-                                                                            <td>{item.item_name.clone()}</td>
+                                                                            <td>
+                                                                                {item.item_name.clone()}
+                                                                                {currency.with(|c| item.weight_label(c)).map(|w| view! { <span class="line-weight">{w}</span> })}
+                                                                                {item.modifier_names().map(|m| view! { <span class="line-modifiers">{m}</span> })}
+                                                                                {(!item.components.is_empty()).then(|| view! { <span class="line-modifiers">{item.components.join(", ")}</span> })}
+                                                                                {item.note.clone().map(|n| view! { <span class="line-note">{n}</span> })}
+                                                                            </td>
                                                                             <td>{item.quantity.to_string()}</td>
-                                                                            <td>{format!("{} {:.2}", &currency.get(), item.unit_price)}</td>
-                                                                            <td>{format!("{} {:.2}", &currency.get(), item.total_price)}</td>
+                                                                            <td>{currency.with(|c| format!("{} {}", c, item.unit_price.format(c)))}</td>
+                                                                            <td>{currency.with(|c| format!("{} {}", c, item.total_price.format(c)))}</td>
                                                                         </tr>
                                                                     </For>
+                                                                    {discounts.iter().map(|d| view! {
+                                                                        <tr class="detail-discount">
+                                                                            <td colspan="3">{d.name.clone()}</td>
+                                                                            <td>{currency.with(|c| format!("{} {}", c, d.amount.format(c)))}</td>
+                                                                        </tr>
+                                                                    }).collect_view()}
+                                                                    {gift_card_loads.iter().map(|l| view! {
+                                                                        <tr>
+                                                                            <td colspan="3">{format!("{} {}", i18n.get().t("gift_cards.title"), mask_card_code(&l.code))}</td>
+                                                                            <td>{currency.with(|c| format!("{} {}", c, l.amount.format(c)))}</td>
+                                                                        </tr>
+                                                                    }).collect_view()}
                                                                 </tbody>
                                                             </table>
 
+                                                            <Show when=move || has_taxes fallback=|| ()>
+                                                                <table class="detail-items-table">
+                                                                    <thead>
+                                                                        <tr>
+                                                                            <th>{i18n.get().t("tax.rate")}</th>
+                                                                            <th>{i18n.get().t("tax.net")}</th>
+                                                                            <th>{i18n.get().t("tax.tax")}</th>
+                                                                            <th>{i18n.get().t("tax.gross")}</th>
+                                                                        </tr>
+                                                                    </thead>
+                                                                    <tbody>
+                                                                        {taxes.iter().map(|t| view! {
+                                                                            <tr>
+                                                                                <td>{format!("{}%", t.rate)}</td>
+                                                                                <td>{currency.with(|c| format!("{} {}", c, t.net.format(c)))}</td>
+                                                                                <td>{currency.with(|c| format!("{} {}", c, t.tax.format(c)))}</td>
+                                                                                <td>{currency.with(|c| format!("{} {}", c, t.gross.format(c)))}</td>
+                                                                            </tr>
+                                                                        }).collect_view()}
+                                                                    </tbody>
+                                                                </table>
+                                                            </Show>
+
                                                             <div class="detail-summary">
                                                                 <div class="detail-field">
                                                                     <strong>{i18n.get().t("transactions.total_label")}</strong>
@@ -165,13 +254,43 @@ pub fn TransactionsPage() -> impl IntoView {
                                                                         {paid.clone()}
                                                                     </div>
                                                                 </Show>
+                                                                <For each=move || payments.clone() key=|p| p.id let:payment>
+                                                                    <div class="detail-field detail-payment">
+                                                                        <strong>{format!("{}: ", i18n.get().t(&format!("sale.tender_{}", payment.tender)))}</strong>
+                                                                        {currency.with(|c| format!("{} {}", c, payment.amount.format(c)))}
+                                                                    </div>
+                                                                </For>
                                                                 <Show when=move || has_change fallback=|| ()>
                                                                     <div class="detail-field">
                                                                         <strong>{i18n.get().t("transactions.change")}</strong>
                                                                         {change.clone()}
                                                                     </div>
                                                                 </Show>
+                                                                {fiscal_counter.map(|counter| view! {
+                                                                    <div class="detail-field">
+                                                                        <strong>{i18n.get().t("transactions.signature")}</strong>
+                                                                        {format!("#{}", counter)}
+                                                                    </div>
+                                                                })}
+                                                                {fiscal_error.map(|error| view! {
+                                                                    <p class="error-message">
+                                                                        {i18n.get().t("fiscal.not_signed").replace("{error}", &error)}
+                                                                    </p>
+                                                                })}
                                                             </div>
+
+                                                            <Show when=move || can_refund fallback=|| ()>
+                                                                <Show
+                                                                    when=move || refunding.get() == Some(tid)
+                                                                    fallback=move || view! {
+                                                                        <button class="btn-secondary" on:click=move |_| set_refunding.set(Some(tid))>
+                                                                            {i18n.get().t("transactions.refund")}
+                                                                        </button>
+                                                                    }
+                                                                >
+                                                                    <RefundPanel transaction_id=tid items=refund_items.get_value() tax_inclusive=tax_inclusive on_done=on_refund_done />
+                                                                </Show>
+                                                            </Show>
                                                         </div>
                                                     }
                                                 })}
@@ -188,3 +307,140 @@ pub fn TransactionsPage() -> impl IntoView {
         </Show>
     }
 }
+
+/// Lets an admin pick how many units of each line of a closed sale to refund,
+/// and the tender the money is paid back on.
+#[component]
+fn RefundPanel(
+    transaction_id: Uuid,
+    items: Vec<TransactionItemDetail>,
+    tax_inclusive: bool,
+    /// Called with why the refund could not be fiscally signed, if it could not.
+    on_done: impl Fn(Option<String>) + Copy + Send + 'static,
+) -> impl IntoView {
+    let i18n = expect_context::<RwSignal<I18n>>();
+    let currency = expect_context::<RwSignal<String>>();
+    let (items, _) = signal(items);
+    let (refundable, set_refundable) = signal(Vec::<RefundLine>::new());
+    let (chosen, set_chosen) = signal(Vec::<RefundLine>::new());
+    let (tender, set_tender) = signal("cash".to_string());
+    let (error, set_error) = signal(Option::<String>::None);
+
+    Effect::new(move || {
+        leptos::task::spawn_local(async move {
+            match fetch_refundable_lines(transaction_id).await {
+                Ok(lines) => {
+                    set_chosen.set(
+                        lines
+                            .iter()
+                            .map(|l| RefundLine { transaction_item_id: l.transaction_item_id, quantity: Quantity::ZERO })
+                            .collect(),
+                    );
+                    set_refundable.set(lines);
+                }
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    });
+
+    let available = move |line_id: Uuid| {
+        refundable.get().iter().find(|l| l.transaction_item_id == line_id).map(|l| l.quantity).unwrap_or_default()
+    };
+    let chosen_qty = move |line_id: Uuid| {
+        chosen.get().iter().find(|l| l.transaction_item_id == line_id).map(|l| l.quantity).unwrap_or_default()
+    };
+    // Steps by one unit; a weighed line is refunded whole, so one step takes all of it.
+    let adjust = move |line_id: Uuid, delta: Quantity| {
+        let max = available(line_id);
+        set_chosen.update(|list| {
+            if let Some(l) = list.iter_mut().find(|l| l.transaction_item_id == line_id) {
+                l.quantity = (l.quantity + delta).clamp(Quantity::ZERO, max);
+            }
+        });
+    };
+    // Discounts on the returned units are given back, so they are not paid out.
+    let refund_total = move || {
+        let lines: Vec<(Option<f64>, Money)> = items
+            .get()
+            .iter()
+            .map(|it| {
+                let qty = chosen_qty(it.id);
+                let given_back: Money = it
+                    .refund_discounts(it.quantity - available(it.id), qty)
+                    .iter()
+                    .map(|d| d.amount)
+                    .sum();
+                (it.tax_rate, it.unit_price * qty - given_back)
+            })
+            .collect();
+        let subtotal: Money = lines.iter().map(|(_, amount)| amount).sum();
+        subtotal + added_tax(&tax_breakdown(&lines, tax_inclusive), tax_inclusive)
+    };
+
+    let confirm = move |_| {
+        let lines: Vec<RefundLine> = chosen.get().into_iter().filter(|l| l.quantity.is_positive()).collect();
+        if lines.is_empty() {
+            return;
+        }
+        let tender = tender.get();
+        leptos::task::spawn_local(async move {
+            match refund_transaction(transaction_id, lines, tender).await {
+                Ok(refund) => on_done(refund.fiscal_error),
+                Err(e) => set_error.set(Some(e.to_string())),
+            }
+        });
+    };
+
+    view! {
+        <div class="refund-panel">
+            <h4>{move || i18n.get().t("transactions.refund")}</h4>
+            <table class="detail-items-table">
+                <thead>
+                    <tr>
+                        <th>{move || i18n.get().t("transactions.item")}</th>
+                        <th>{move || i18n.get().t("transactions.refundable")}</th>
+                        <th>{move || i18n.get().t("transactions.qty")}</th>
+                    </tr>
+                </thead>
+                <tbody>
+                    <For each=move || items.get() key=|i| i.id let:item>
+                        {
+                            let line_id = item.id;
+                            view! {
+                                <tr>
+                                    <td>
+                                        {item.item_name.clone()}
+                                        {currency.with(|c| item.weight_label(c)).map(|w| view! { <span class="line-weight">{w}</span> })}
+                                        {item.modifier_names().map(|m| view! { <span class="line-modifiers">{m}</span> })}
+                                    </td>
+                                    <td>{move || available(line_id).to_string()}</td>
+                                    <td class="refund-qty">
+                                        <button class="btn-small" on:click=move |_| adjust(line_id, -Quantity::ONE)>"-"</button>
+                                        <span>{move || chosen_qty(line_id).to_string()}</span>
+                                        <button class="btn-small" on:click=move |_| adjust(line_id, Quantity::ONE)>"+"</button>
+                                    </td>
+                                </tr>
+                            }
+                        }
+                    </For>
+                </tbody>
+            </table>
+            <div class="refund-actions">
+                <select
+                    prop:value=move || tender.get()
+                    on:change=move |ev| set_tender.set(event_target_value(&ev))
+                >
+                    // Refunds are not paid onto gift cards.
+                    {TENDERS.into_iter().filter(|t| *t != "gift_card").map(|t| view! {
+                        <option value=t>{move || i18n.get().t(&format!("sale.tender_{}", t))}</option>
+                    }).collect_view()}
+                </select>
+                <strong>{move || currency.with(|c| format!("{} {}", c, (-refund_total()).format(c)))}</strong>
+                <button class="btn-danger" on:click=confirm disabled=move || !refund_total().is_positive()>
+                    {move || i18n.get().t("transactions.confirm_refund")}
+                </button>
+            </div>
+            {move || error.get().map(|e| view! { <p class="error-message">{e}</p> })}
+        </div>
+    }
+}
@@ -67,1651 +67,7275 @@ async fn require_admin(pool: &sqlx::SqlitePool) -> Result<UserAccount, ServerFnE
     Ok(user)
 }
 
+/// The configured currency symbol, which sets how many decimal places
+/// amounts are kept with (see [`currency_decimals`]). Empty if not set yet.
 #[cfg(feature = "ssr")]
-async fn update_transaction_total_db(
-    pool: &sqlx::SqlitePool,
-    transaction_id: Uuid,
-) -> Result<(), ServerFnError> {
+async fn currency_db(db: impl sqlx::SqliteExecutor<'_>) -> Result<String, ServerFnError> {
+    let currency: Option<String> = sqlx::query_scalar("SELECT value FROM config WHERE key = 'currency'")
+        .fetch_optional(db)
+        .await
+        .map_err(db_err)?;
+    Ok(currency.unwrap_or_default())
+}
+
+/// Every column holding an amount of money, as `(table, column)`. They are
+/// integers in minor units of the configured currency.
+#[cfg(feature = "ssr")]
+pub const MONEY_COLUMNS: &[(&str, &str)] = &[
+    ("items", "price"),
+    ("transactions", "total"),
+    ("transactions", "paid_amount"),
+    ("transactions", "change_amount"),
+    ("transactions", "tip_amount"),
+    ("transactions", "rounding_amount"),
+    ("transaction_items", "unit_price"),
+    ("transaction_items", "total_price"),
+    ("transaction_item_modifiers", "price_delta"),
+    ("transaction_discounts", "amount"),
+    ("payments", "amount"),
+    ("modifiers", "price_delta"),
+    ("price_history", "price"),
+    ("gift_card_entries", "amount"),
+    ("gift_card_loads", "amount"),
+    ("account_charges", "amount"),
+    ("account_settlements", "amount"),
+    ("drawer_sessions", "expected"),
+    ("drawer_sessions", "counted"),
+    ("drawer_sessions", "variance"),
+    ("cash_movements", "amount"),
+    ("drawer_counts", "denomination"),
+];
+
+/// Sets the currency symbol. When the new currency has a different number of
+/// decimal places, every stored amount is rescaled so it keeps its value
+/// (going to fewer decimals rounds half away from zero).
+#[cfg(feature = "ssr")]
+async fn store_currency_db(pool: &sqlx::SqlitePool, currency: &str) -> Result<(), ServerFnError> {
+    let old = currency_decimals(&currency_db(pool).await?);
+    let new = currency_decimals(currency);
+    // Rescaling would alter journalled sales, and the totals frozen in
+    // Z-closings would no longer read right.
+    if new != old && (journal_head_db(pool).await?.is_some() || last_z_closing_db(pool).await?.is_some()) {
+        return Err(not_found("The number of decimal places cannot change once sales are journaled or closed"));
+    }
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    if new != old {
+        let factor = 10_i64.pow(new.abs_diff(old));
+        for (table, column) in MONEY_COLUMNS {
+            // SQLite's integer division truncates, so add half first.
+            let value = if new > old {
+                format!("{column} * {factor}")
+            } else {
+                format!("({column} + CASE WHEN {column} < 0 THEN -{half} ELSE {half} END) / {factor}", half = factor / 2)
+            };
+            sqlx::query(&format!("UPDATE {table} SET {column} = {value}"))
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+        }
+    }
     sqlx::query(
-        "UPDATE transactions SET total = (
-            SELECT COALESCE(SUM(total_price), 0) FROM transaction_items WHERE transaction_id = ?
-        ), updated_at = ? WHERE id = ?",
+        "INSERT INTO config (key, value) VALUES ('currency', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(currency)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    tx.commit().await.map_err(db_err)
+}
+
+/// The receipt number and hash of the last journal entry, as recorded when
+/// it was appended.
+#[cfg(feature = "ssr")]
+async fn journal_head_db(pool: &sqlx::SqlitePool) -> Result<Option<(i64, String)>, ServerFnError> {
+    let head: Option<String> = sqlx::query_scalar("SELECT value FROM config WHERE key = 'journal_head'")
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)?;
+    Ok(head.as_deref().and_then(crate::journal::parse_head))
+}
+
+/// A sale's journal record as it is stored now, or `None` if it is gone.
+#[cfg(feature = "ssr")]
+async fn journal_record_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction_id: Uuid,
+    event: &str,
+) -> Result<Option<JournalRecord>, ServerFnError> {
+    let Some(t) = sqlx::query_as::<_, Transaction>("SELECT * FROM transactions WHERE id = ?")
+        .bind(transaction_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_err)?
+    else {
+        return Ok(None);
+    };
+    let lines = sqlx::query_as::<_, JournalLine>(
+        "SELECT id, item_id, quantity, unit_price, total_price FROM transaction_items
+         WHERE transaction_id = ? ORDER BY id",
     )
     .bind(transaction_id)
-    .bind(Utc::now())
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    let payments = sqlx::query_as::<_, (String, Money)>(
+        "SELECT tender, amount FROM payments WHERE transaction_id = ? ORDER BY id",
+    )
     .bind(transaction_id)
-    .execute(pool)
+    .fetch_all(&mut *conn)
     .await
     .map_err(db_err)?;
-    Ok(())
+    Ok(Some(JournalRecord {
+        transaction_id,
+        event: event.to_string(),
+        status: t.status,
+        refund_of: t.refund_of,
+        total: t.total,
+        paid_amount: t.paid_amount,
+        change_amount: t.change_amount,
+        tip_amount: t.tip_amount,
+        rounding_amount: t.rounding_amount,
+        closed_at: t.closed_at,
+        lines,
+        payments,
+    }))
 }
 
-/// Builds the SQL fragment that restricts a query to a customer group.
+/// Journals a finished sale under the next receipt number, chained to the
+/// entry before it. `event` is one of [`JOURNAL_EVENTS`]. A sale is journalled
+/// once.
 ///
-/// `alias` is the table alias used for `transactions` in the surrounding query
-/// (e.g. `"t"` or `"transactions"`); it is a hard-coded identifier, never user
-/// input. sqlx stores `Uuid` columns as 16-byte BLOBs, so the group id is
-/// inlined as a SQLite blob literal (`X'<32 hex>'`) — a text literal would never
-/// match the stored blob. The hex form has no SQL-special characters, so this is
-/// injection-safe and avoids variadic binding.
+/// Runs in the transaction that finishes the sale, after it has written, so
+/// the sale and its entry are committed together and the database's write
+/// lock keeps receipt numbers from being taken twice.
 #[cfg(feature = "ssr")]
-fn group_filter_clause(filter: &GroupFilter, alias: &str) -> String {
-    match filter {
-        GroupFilter::All => String::new(),
-        GroupFilter::Regular => format!(" AND {alias}.customer_group_id IS NULL"),
-        GroupFilter::Group(id) => {
-            format!(" AND {alias}.customer_group_id = X'{}'", id.simple())
-        }
+async fn append_journal_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction_id: Uuid,
+    event: &str,
+) -> Result<JournalEntry, ServerFnError> {
+    use crate::journal::{entry_hash, GENESIS_HASH};
+
+    let journaled: Option<i64> = sqlx::query_scalar("SELECT seq FROM journal WHERE transaction_id = ?")
+        .bind(transaction_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    if let Some(seq) = journaled {
+        return Err(not_found(&format!("The sale is already journaled as receipt {}", seq)));
     }
+    let record = journal_record_db(conn, transaction_id, event)
+        .await?
+        .ok_or_else(|| not_found("Transaction not found"))?;
+    let payload = serde_json::to_string(&record).map_err(db_err)?;
+
+    let last: Option<(i64, String)> = sqlx::query_as("SELECT seq, hash FROM journal ORDER BY seq DESC LIMIT 1")
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    let (seq, prev_hash) = match last {
+        Some((seq, hash)) => (seq + 1, hash),
+        None => (1, GENESIS_HASH.to_string()),
+    };
+    let hash = entry_hash(seq, &prev_hash, &payload);
+    let entry = sqlx::query_as::<_, JournalEntry>(
+        "INSERT INTO journal (seq, transaction_id, event, payload, prev_hash, hash, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(seq)
+    .bind(transaction_id)
+    .bind(event)
+    .bind(&payload)
+    .bind(&prev_hash)
+    .bind(&hash)
+    .bind(Utc::now())
+    .fetch_one(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    sqlx::query(
+        "INSERT INTO config (key, value) VALUES ('journal_head', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(format!("{}:{}", seq, hash))
+    .execute(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    Ok(entry)
 }
 
+/// Signs a finished sale over its journal entry with the fiscal signer, if
+/// one is set up, and stores the signature with the sale. Runs in the
+/// transaction that finishes the sale. When signing fails the sale is refused,
+/// unless a signer outage has been declared (see [`set_fiscal_outage`]); then
+/// the error is stored instead, so it can be shown to the cashier and printed
+/// on the receipt. Returns the sale as stored and the signature for the
+/// receipt.
 #[cfg(feature = "ssr")]
-async fn generate_sales_report_db(
-    pool: &sqlx::SqlitePool,
-    start_date: DateTime<Utc>,
-    end_date: DateTime<Utc>,
-    filter: &GroupFilter,
-) -> Result<SalesReport, ServerFnError> {
-    if end_date <= start_date {
-        return Err(not_found("End date must be after start date"));
+async fn sign_transaction_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction: Transaction,
+    entry: &JournalEntry,
+) -> Result<(Transaction, Option<rustpos_common::protocol::FiscalReceipt>), ServerFnError> {
+    use crate::fiscal::{qr_code, FiscalSignerHandle, SignRequest};
+
+    let Some(FiscalSignerHandle(signer)) = use_context::<FiscalSignerHandle>() else {
+        return Ok((transaction, None));
+    };
+    let request = SignRequest {
+        transaction_id: transaction.id,
+        process_type: entry.event.clone(),
+        process_data: entry.hash.clone(),
+        started_at: transaction.created_at,
+    };
+    let signed = {
+        let request = request.clone();
+        tokio::task::spawn_blocking(move || signer.sign(&request))
+            .await
+            .unwrap_or_else(|e| Err(format!("The signer stopped: {}", e)))
+    };
+    match signed {
+        Ok(signature) => {
+            let transaction = sqlx::query_as::<_, Transaction>(
+                "UPDATE transactions SET fiscal_signer = ?, fiscal_counter = ?, fiscal_signature = ?,
+                 fiscal_started_at = ?, fiscal_signed_at = ?, fiscal_error = NULL
+                 WHERE id = ? RETURNING *",
+            )
+            .bind(&signature.signer)
+            .bind(signature.counter)
+            .bind(&signature.signature)
+            .bind(signature.started_at)
+            .bind(signature.signed_at)
+            .bind(transaction.id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(db_err)?;
+            let receipt = rustpos_common::protocol::FiscalReceipt {
+                counter: signature.counter,
+                qr: qr_code(&signature, &request.process_type, &request.process_data),
+            };
+            Ok((transaction, Some(receipt)))
+        }
+        Err(error) => {
+            if !read_fiscal_outage(conn).await {
+                return Err(not_found(&format!("The sale could not be fiscally signed: {}", error)));
+            }
+            let transaction = sqlx::query_as::<_, Transaction>(
+                "UPDATE transactions SET fiscal_error = ? WHERE id = ? RETURNING *",
+            )
+            .bind(&error)
+            .bind(transaction.id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(db_err)?;
+            Ok((transaction, None))
+        }
     }
+}
 
-    let items = sqlx::query_as::<_, ItemSalesReport>(&format!(
-        "SELECT i.id as item_id, i.name as item_name, c.name as category_name,
-         SUM(ti.quantity) as quantity_sold, SUM(ti.total_price) as total_revenue,
-         AVG(ti.unit_price) as average_price, COUNT(DISTINCT ti.transaction_id) as transaction_count
-         FROM transaction_items ti
-         JOIN items i ON ti.item_id = i.id
-         JOIN categories c ON i.category_id = c.id
-         JOIN transactions t ON ti.transaction_id = t.id
-         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
-         GROUP BY i.id, i.name, c.name ORDER BY total_revenue DESC",
-        group_filter_clause(filter, "t"),
-    ))
-    .bind(start_date)
-    .bind(end_date)
+/// Journals the sales finished before the journal was introduced, in the
+/// order they were finished. Does nothing once the journal has been started.
+#[cfg(feature = "ssr")]
+pub async fn start_journal_db(pool: &sqlx::SqlitePool) -> Result<(), ServerFnError> {
+    let entries: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM journal")
+        .fetch_one(pool)
+        .await
+        .map_err(db_err)?;
+    if entries > 0 || journal_head_db(pool).await?.is_some() {
+        return Ok(());
+    }
+    let finished = sqlx::query_as::<_, (Uuid, String, Option<Uuid>)>(
+        "SELECT id, status, refund_of FROM transactions WHERE status IN ('closed', 'cancelled')
+         ORDER BY COALESCE(closed_at, updated_at), id",
+    )
     .fetch_all(pool)
     .await
     .map_err(db_err)?;
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    for (id, status, refund_of) in finished {
+        let event = match (status.as_str(), refund_of) {
+            ("cancelled", _) => "cancel",
+            (_, Some(_)) => "refund",
+            _ => "sale",
+        };
+        append_journal_db(&mut tx, id, event).await?;
+    }
+    tx.commit().await.map_err(db_err)
+}
 
-    let total_revenue: f64 = items.iter().map(|i| i.total_revenue).sum();
-    let total_items_sold: i64 = items.iter().map(|i| i.quantity_sold).sum();
+/// Checks the journal: the hash chain and numbering (see
+/// [`crate::journal::check_chain`]), that every journalled sale still matches
+/// its entry, and that every finished sale has one.
+#[cfg(feature = "ssr")]
+pub async fn verify_journal_db(pool: &sqlx::SqlitePool) -> Result<JournalVerification, ServerFnError> {
+    let entries = sqlx::query_as::<_, JournalEntry>("SELECT * FROM journal ORDER BY seq")
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?;
+    let head = journal_head_db(pool).await?;
+    let mut problems = crate::journal::check_chain(&entries, head.as_ref());
+
+    let mut conn = pool.acquire().await.map_err(db_err)?;
+    for entry in &entries {
+        let (seq, transaction_id) = (entry.seq, entry.transaction_id);
+        match journal_record_db(&mut conn, transaction_id, &entry.event).await? {
+            None => problems.push(JournalProblem::Missing { seq, transaction_id }),
+            Some(record) => {
+                if serde_json::from_str::<JournalRecord>(&entry.payload).ok().as_ref() != Some(&record) {
+                    problems.push(JournalProblem::Altered { seq, transaction_id });
+                }
+            }
+        }
+    }
 
-    let transaction_count = sqlx::query_scalar::<_, i64>(&format!(
-        "SELECT COUNT(DISTINCT id) FROM transactions
-         WHERE status = 'closed' AND closed_at >= ? AND closed_at < ?{}",
-        group_filter_clause(filter, "transactions"),
-    ))
-    .bind(start_date)
-    .bind(end_date)
-    .fetch_one(pool)
+    let unjournaled = sqlx::query_scalar::<_, Uuid>(
+        "SELECT id FROM transactions WHERE status IN ('closed', 'cancelled')
+         AND id NOT IN (SELECT transaction_id FROM journal) ORDER BY closed_at",
+    )
+    .fetch_all(&mut *conn)
     .await
     .map_err(db_err)?;
+    problems.extend(unjournaled.into_iter().map(|transaction_id| JournalProblem::NotJournaled { transaction_id }));
 
-    let average_transaction_value = if transaction_count > 0 {
-        total_revenue / transaction_count as f64
-    } else {
-        0.0
-    };
-
-    let top_selling_item = items
-        .iter()
-        .max_by_key(|i| i.quantity_sold)
-        .map(|i| i.item_name.clone());
-
-    let top_revenue_item = items
-        .iter()
-        .max_by(|a, b| a.total_revenue.partial_cmp(&b.total_revenue).unwrap())
-        .map(|i| i.item_name.clone());
+    Ok(JournalVerification { entries: entries.len() as i64, problems })
+}
 
-    Ok(SalesReport {
-        start_date,
-        end_date,
-        items,
-        summary: ReportSummary {
-            total_revenue,
-            total_items_sold,
-            total_transactions: transaction_count,
-            average_transaction_value,
-            top_selling_item,
-            top_revenue_item,
-        },
-    })
+/// Verifies the journal (admin only). See [`verify_journal_db`].
+#[server]
+pub async fn verify_journal() -> Result<JournalVerification, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    verify_journal_db(&pool).await
 }
 
-// ---- Category Server Functions ----
+/// Whether a signer outage has been declared (default: false).
+#[cfg(feature = "ssr")]
+async fn read_fiscal_outage(conn: &mut sqlx::SqliteConnection) -> bool {
+    sqlx::query_scalar::<_, String>("SELECT value FROM config WHERE key = 'fiscal_outage'")
+        .fetch_optional(conn)
+        .await
+        .ok()
+        .flatten()
+        .map(|v| v == "true")
+        .unwrap_or(false)
+}
 
 #[server]
-pub async fn fetch_categories() -> Result<Vec<Category>, ServerFnError> {
+pub async fn get_fiscal_outage() -> Result<bool, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    let categories = sqlx::query_as::<_, Category>("SELECT * FROM categories ORDER BY sort_order, name")
-        .fetch_all(&pool)
-        .await
-        .map_err(db_err)?;
-    Ok(categories)
+    require_admin(&pool).await?;
+    Ok(read_fiscal_outage(&mut *pool.acquire().await.map_err(db_err)?).await)
 }
 
+/// Declares or ends a signer outage. While one is declared, sales the fiscal
+/// signer fails to sign are finished unsigned, with the error stored on them;
+/// otherwise they are refused.
 #[server]
-pub async fn create_category(
-    name: String,
-    description: Option<String>,
-    main_course: Option<bool>,
-) -> Result<Category, ServerFnError> {
+pub async fn set_fiscal_outage(outage: bool) -> Result<(), ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-    let main_course = main_course.unwrap_or(false);
-    // Append new categories at the end of the user-defined order.
-    let next_order: i64 =
-        sqlx::query_scalar("SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories")
-            .fetch_one(&pool)
-            .await
-            .map_err(db_err)?;
-    let category = sqlx::query_as::<_, Category>(
-        "INSERT INTO categories (id, name, description, main_course, sort_order, created_at, updated_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    require_admin(&pool).await?;
+    sqlx::query(
+        "INSERT INTO config (key, value) VALUES ('fiscal_outage', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
     )
-    .bind(id)
-    .bind(&name)
-    .bind(&description)
-    .bind(main_course)
-    .bind(next_order)
-    .bind(now)
-    .bind(now)
-    .fetch_one(&pool)
+    .bind(if outage { "true" } else { "false" })
+    .execute(&pool)
     .await
     .map_err(db_err)?;
-    Ok(category)
+    Ok(())
 }
 
-/// Moves a category one position up or down in the user-defined order by
-/// swapping its `sort_order` with its nearest neighbour. No-op at the ends.
-#[server]
-pub async fn move_category(id: Uuid, up: bool) -> Result<(), ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-
-    let current = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = ?")
-        .bind(id)
-        .fetch_optional(&pool)
+/// Recomputes a sale's deposit lines, promotions, the shares of its manual
+/// discounts and its total from its lines, adding tax on top when the sale
+/// uses exclusive tax. Gift card top-ups are added untaxed.
+#[cfg(feature = "ssr")]
+async fn recompute_transaction_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction_id: Uuid,
+) -> Result<(), ServerFnError> {
+    sync_deposit_lines_db(&mut *conn, transaction_id).await?;
+    apply_promotions_db(&mut *conn, transaction_id).await?;
+    apply_manual_discounts_db(&mut *conn, transaction_id).await?;
+    let inclusive: bool = sqlx::query_scalar("SELECT tax_inclusive FROM transactions WHERE id = ?")
+        .bind(transaction_id)
+        .fetch_one(&mut *conn)
         .await
-        .map_err(db_err)?
-        .ok_or_else(|| not_found("Category not found"))?;
-
-    // Find the adjacent category in (sort_order, name) order, using the same
-    // tie-break as fetch_categories so the swap matches the displayed order.
-    let neighbor = if up {
-        sqlx::query_as::<_, Category>(
-            "SELECT * FROM categories
-             WHERE sort_order < ? OR (sort_order = ? AND name < ?)
-             ORDER BY sort_order DESC, name DESC LIMIT 1",
-        )
-    } else {
-        sqlx::query_as::<_, Category>(
-            "SELECT * FROM categories
-             WHERE sort_order > ? OR (sort_order = ? AND name > ?)
-             ORDER BY sort_order ASC, name ASC LIMIT 1",
-        )
-    }
-    .bind(current.sort_order)
-    .bind(current.sort_order)
-    .bind(&current.name)
-    .fetch_optional(&pool)
+        .map_err(db_err)?;
+    let lines = transaction_tax_lines_db(&mut *conn, transaction_id).await?;
+    let subtotal: Money = lines.iter().map(|(_, amount)| amount).sum();
+    let top_ups: Money = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0) FROM gift_card_loads WHERE transaction_id = ?",
+    )
+    .bind(transaction_id)
+    .fetch_one(&mut *conn)
     .await
     .map_err(db_err)?;
+    let total = subtotal + added_tax(&tax_breakdown(&lines, inclusive), inclusive) + top_ups;
+    sqlx::query("UPDATE transactions SET total = ?, updated_at = ? WHERE id = ?")
+        .bind(total)
+        .bind(Utc::now())
+        .bind(transaction_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    Ok(())
+}
 
-    let Some(neighbor) = neighbor else {
-        return Ok(()); // already at the top/bottom
-    };
+/// [`recompute_transaction_db`] in a transaction of its own.
+#[cfg(feature = "ssr")]
+async fn update_transaction_total_db(
+    pool: &sqlx::SqlitePool,
+    transaction_id: Uuid,
+) -> Result<(), ServerFnError> {
+    let mut tx = begin_write_db(pool).await?;
+    recompute_transaction_db(&mut tx, transaction_id).await?;
+    tx.commit().await.map_err(db_err)
+}
 
-    // Swap the two order values. If they happen to be equal (legacy data),
-    // nudge them apart so the move still takes effect.
-    let (cur_new, nb_new) = if current.sort_order == neighbor.sort_order {
-        if up {
-            (neighbor.sort_order - 1, neighbor.sort_order)
-        } else {
-            (neighbor.sort_order + 1, neighbor.sort_order)
-        }
-    } else {
-        (neighbor.sort_order, current.sort_order)
-    };
+/// Starts a transaction that takes the database's write lock right away.
+/// One that reads first and writes later is refused the lock, rather than
+/// made to wait, when another write is under way.
+#[cfg(feature = "ssr")]
+async fn begin_write_db(pool: &sqlx::SqlitePool) -> Result<sqlx::Transaction<'static, sqlx::Sqlite>, ServerFnError> {
+    pool.begin_with("BEGIN IMMEDIATE").await.map_err(db_err)
+}
 
-    sqlx::query("UPDATE categories SET sort_order = ? WHERE id = ?")
-        .bind(cur_new)
-        .bind(current.id)
-        .execute(&pool)
+/// Keeps the deposit lines of an open sale in step with the lines they are
+/// charged with: every line of an item sold by the piece with a deposit item
+/// gets a deposit line of the same quantity right after it. Deposits whose line was removed
+/// go with it; those whose line moved to another sale are dropped here and
+/// added again there.
+#[cfg(feature = "ssr")]
+async fn sync_deposit_lines_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction_id: Uuid,
+) -> Result<(), ServerFnError> {
+    let open: bool = sqlx::query_scalar("SELECT COUNT(*) > 0 FROM transactions WHERE id = ? AND status = 'open'")
+        .bind(transaction_id)
+        .fetch_one(&mut *conn)
         .await
         .map_err(db_err)?;
-    sqlx::query("UPDATE categories SET sort_order = ? WHERE id = ?")
-        .bind(nb_new)
-        .bind(neighbor.id)
-        .execute(&pool)
+    if !open {
+        return Ok(());
+    }
+    sqlx::query(
+        "DELETE FROM transaction_items WHERE transaction_id = ? AND deposit_for IS NOT NULL
+           AND deposit_for NOT IN (SELECT id FROM transaction_items WHERE transaction_id = ?)",
+    )
+    .bind(transaction_id)
+    .bind(transaction_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    sqlx::query(
+        "UPDATE transaction_items
+         SET quantity = (SELECT p.quantity FROM transaction_items p WHERE p.id = transaction_items.deposit_for),
+             total_price = unit_price * (SELECT p.quantity FROM transaction_items p WHERE p.id = transaction_items.deposit_for) / 1000
+         WHERE transaction_id = ? AND deposit_for IS NOT NULL",
+    )
+    .bind(transaction_id)
+    .execute(&mut *conn)
+    .await
+    .map_err(db_err)?;
+
+    let missing = sqlx::query_as::<_, (Uuid, Quantity, Uuid, DateTime<Utc>)>(
+        "SELECT ti.id, ti.quantity, i.deposit_item_id, ti.created_at
+         FROM transaction_items ti
+         JOIN items i ON ti.item_id = i.id
+         WHERE ti.transaction_id = ? AND i.deposit_item_id IS NOT NULL
+           AND NOT ti.is_bundle AND NOT ti.is_deposit AND ti.unit IS NULL AND ti.quantity > 0
+           AND NOT EXISTS (SELECT 1 FROM transaction_items d WHERE d.deposit_for = ti.id)",
+    )
+    .bind(transaction_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    for (line_id, quantity, deposit_item_id, created_at) in missing {
+        let Some(deposit) = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+            .bind(deposit_item_id)
+            .fetch_optional(&mut *conn)
+            .await
+            .map_err(db_err)?
+        else {
+            continue;
+        };
+        let tax_rate = effective_tax_rate_db(&mut *conn, &deposit).await?;
+        sqlx::query(
+            "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price,
+             tax_rate, is_deposit, deposit_for, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, 1, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(transaction_id)
+        .bind(deposit.id)
+        .bind(quantity)
+        .bind(deposit.price)
+        .bind(deposit.price * quantity)
+        .bind(tax_rate)
+        .bind(line_id)
+        // Listed right after the line it is charged with.
+        .bind(created_at + chrono::Duration::milliseconds(1))
+        .execute(&mut *conn)
         .await
         .map_err(db_err)?;
+    }
     Ok(())
 }
 
-#[server]
-pub async fn update_category(
-    id: Uuid,
-    name: Option<String>,
-    description: Option<String>,
-    main_course: Option<bool>,
-) -> Result<Category, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let mut category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = ?")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(db_err)?
-        .ok_or_else(|| not_found("Category not found"))?;
-
-    if let Some(n) = name { category.name = n; }
-    if let Some(d) = description { category.description = Some(d); }
-    if let Some(mc) = main_course { category.main_course = mc; }
-    category.updated_at = Utc::now();
-
-    let updated = sqlx::query_as::<_, Category>(
-        "UPDATE categories SET name = ?, description = ?, main_course = ?, updated_at = ? WHERE id = ? RETURNING *",
+/// Replaces a sale's promotion discounts with those the active promotions
+/// give on its current lines. Bundle lines are left out: a bundle already
+/// has a price of its own. So are deposits, which are not for sale.
+#[cfg(feature = "ssr")]
+async fn apply_promotions_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction_id: Uuid,
+) -> Result<(), ServerFnError> {
+    let promotions = sqlx::query_as::<_, Promotion>(
+        "SELECT * FROM promotions WHERE active = 1 ORDER BY created_at",
     )
-    .bind(&category.name)
-    .bind(&category.description)
-    .bind(category.main_course)
-    .bind(category.updated_at)
-    .bind(id)
-    .fetch_one(&pool)
+    .fetch_all(&mut *conn)
     .await
     .map_err(db_err)?;
-    Ok(updated)
-}
+    let group_id: Option<Uuid> =
+        sqlx::query_scalar("SELECT customer_group_id FROM transactions WHERE id = ?")
+            .bind(transaction_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(db_err)?;
+    let lines: Vec<PromotionLine> = sqlx::query_as::<_, (Uuid, Uuid, Uuid, Quantity, Money, Money, Option<String>, DateTime<Utc>)>(
+        "SELECT ti.id, ti.item_id, i.category_id, ti.quantity, ti.unit_price, ti.total_price, ti.unit, ti.created_at
+         FROM transaction_items ti
+         JOIN items i ON ti.item_id = i.id
+         WHERE ti.transaction_id = ? AND NOT ti.is_bundle AND ti.bundle_id IS NULL AND NOT ti.is_deposit
+         ORDER BY ti.created_at",
+    )
+    .bind(transaction_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_err)?
+    .into_iter()
+    .map(|(line_id, item_id, category_id, quantity, unit_price, total_price, unit, created_at)| {
+        // A weighed line counts as one unit at the price of its weight.
+        let (quantity, unit_price) = match unit {
+            Some(_) => (1, total_price),
+            None => (quantity.whole_units() as i32, unit_price),
+        };
+        PromotionLine {
+            line_id,
+            item_id,
+            category_id,
+            quantity,
+            unit_price,
+            ordered_at: created_at.with_timezone(&chrono::Local).naive_local(),
+        }
+    })
+    .collect();
 
-#[server]
-pub async fn delete_category(id: Uuid) -> Result<(), ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let result = sqlx::query("DELETE FROM categories WHERE id = ?")
-        .bind(id)
-        .execute(&pool)
+    sqlx::query("DELETE FROM transaction_discounts WHERE transaction_id = ? AND promotion_id IS NOT NULL")
+        .bind(transaction_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    let currency = currency_db(&mut *conn).await?;
+    let now = Utc::now();
+    for d in apply_promotions(&promotions, &lines, group_id, &currency) {
+        let name = promotions.iter().find(|p| p.id == d.promotion_id).map(|p| p.name.clone()).unwrap_or_default();
+        sqlx::query(
+            "INSERT INTO transaction_discounts (id, transaction_id, transaction_item_id, promotion_id, name, amount, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(transaction_id)
+        .bind(d.line_id)
+        .bind(d.promotion_id)
+        .bind(name)
+        .bind(d.amount)
+        .bind(now)
+        .execute(&mut *conn)
         .await
         .map_err(db_err)?;
-    if result.rows_affected() == 0 {
-        return Err(not_found("Category not found"));
     }
     Ok(())
 }
 
-// ---- Customer Group Server Functions ----
-
-/// Lists all customer groups. Available to any signed-in role so cashiers can
-/// pick a group when closing a sale.
-#[server]
-pub async fn fetch_customer_groups() -> Result<Vec<CustomerGroup>, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let groups = sqlx::query_as::<_, CustomerGroup>("SELECT * FROM customer_groups ORDER BY name")
-        .fetch_all(&pool)
+/// Shares a sale's manual discounts out over its lines again, after its
+/// promotions; deposit lines get no share. See [`apply_manual_discounts`].
+#[cfg(feature = "ssr")]
+async fn apply_manual_discounts_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction_id: Uuid,
+) -> Result<(), ServerFnError> {
+    sqlx::query("DELETE FROM transaction_discounts WHERE transaction_id = ? AND manual_discount_id IS NOT NULL")
+        .bind(transaction_id)
+        .execute(&mut *conn)
         .await
         .map_err(db_err)?;
-    Ok(groups)
-}
-
-#[server]
-pub async fn create_customer_group(name: String) -> Result<CustomerGroup, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    require_admin(&pool).await?;
-    let name = name.trim().to_string();
-    if name.is_empty() {
-        return Err(not_found("Group name must not be empty"));
+    let discounts = sqlx::query_as::<_, ManualDiscount>(
+        "SELECT * FROM manual_discounts WHERE transaction_id = ? ORDER BY created_at",
+    )
+    .bind(transaction_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    if discounts.is_empty() {
+        return Ok(());
     }
-    let id = Uuid::new_v4();
+    let lines: Vec<DiscountableLine> = transaction_lines_db(&mut *conn, transaction_id)
+        .await?
+        .iter()
+        .filter(|l| !l.is_deposit)
+        .map(|l| DiscountableLine { line_id: l.id, bundle_id: l.bundle_id, net: l.net_total() })
+        .collect();
+    let currency = currency_db(&mut *conn).await?;
     let now = Utc::now();
-    let group = sqlx::query_as::<_, CustomerGroup>(
-        "INSERT INTO customer_groups (id, name, created_at, updated_at)
-         VALUES (?, ?, ?, ?) RETURNING *",
+    for share in apply_manual_discounts(&discounts, &lines, &currency) {
+        let Some(d) = discounts.iter().find(|d| d.id == share.manual_discount_id) else {
+            continue;
+        };
+        sqlx::query(
+            "INSERT INTO transaction_discounts (id, transaction_id, transaction_item_id, manual_discount_id,
+             reason, user_name, name, amount, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(transaction_id)
+        .bind(share.line_id)
+        .bind(d.id)
+        .bind(&d.reason)
+        .bind(&d.user_name)
+        .bind(d.label())
+        .bind(share.amount)
+        .bind(now)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+/// The lines of a sale with their modifiers and discounts, in the order they
+/// were added.
+#[cfg(feature = "ssr")]
+async fn transaction_lines_db(
+    conn: &mut sqlx::SqliteConnection,
+    transaction_id: Uuid,
+) -> Result<Vec<TransactionItemDetail>, ServerFnError> {
+    let mut lines = sqlx::query_as::<_, TransactionItemDetail>(
+        "SELECT ti.id, ti.item_id, i.name as item_name, ti.quantity,
+         ti.unit_price, ti.total_price, ti.tax_rate, ti.note,
+         ti.unit, ti.is_bundle, ti.bundle_id,
+         ti.is_deposit, ti.deposit_for
+         FROM transaction_items ti
+         JOIN items i ON ti.item_id = i.id
+         WHERE ti.transaction_id = ?
+         ORDER BY ti.created_at",
     )
-    .bind(id)
-    .bind(&name)
-    .bind(now)
-    .bind(now)
-    .fetch_one(&pool)
+    .bind(transaction_id)
+    .fetch_all(&mut *conn)
     .await
     .map_err(db_err)?;
-    Ok(group)
-}
 
-#[server]
-pub async fn update_customer_group(id: Uuid, name: String) -> Result<CustomerGroup, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    require_admin(&pool).await?;
-    let name = name.trim().to_string();
-    if name.is_empty() {
-        return Err(not_found("Group name must not be empty"));
+    let modifiers = sqlx::query_as::<_, LineModifier>(
+        "SELECT m.transaction_item_id, m.modifier_id, m.group_name, m.name, m.price_delta
+         FROM transaction_item_modifiers m
+         JOIN transaction_items ti ON m.transaction_item_id = ti.id
+         WHERE ti.transaction_id = ?
+         ORDER BY m.rowid",
+    )
+    .bind(transaction_id)
+    .fetch_all(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    for m in modifiers {
+        if let Some(line) = lines.iter_mut().find(|l| l.id == m.transaction_item_id) {
+            line.modifiers.push(m);
+        }
     }
-    let group = sqlx::query_as::<_, CustomerGroup>(
-        "UPDATE customer_groups SET name = ?, updated_at = ? WHERE id = ? RETURNING *",
+
+    let discounts = sqlx::query_as::<_, TransactionDiscount>(
+        "SELECT transaction_item_id, promotion_id, manual_discount_id, reason, user_name, name, amount
+         FROM transaction_discounts
+         WHERE transaction_id = ?
+         ORDER BY rowid",
     )
-    .bind(&name)
-    .bind(Utc::now())
-    .bind(id)
-    .fetch_optional(&pool)
+    .bind(transaction_id)
+    .fetch_all(&mut *conn)
     .await
-    .map_err(db_err)?
-    .ok_or_else(|| not_found("Customer group not found"))?;
-    Ok(group)
+    .map_err(db_err)?;
+    for d in discounts {
+        if let Some(line) = lines.iter_mut().find(|l| l.id == d.transaction_item_id) {
+            line.discounts.push(d);
+        }
+    }
+    Ok(lines)
 }
 
-/// Deletes a customer group. Its sales are transferred back to "regular
-/// customers" (their `customer_group_id` is cleared) so no statistics are lost.
-#[server]
-pub async fn delete_customer_group(id: Uuid) -> Result<(), ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    require_admin(&pool).await?;
-
-    sqlx::query("UPDATE transactions SET customer_group_id = NULL WHERE customer_group_id = ?")
-        .bind(id)
-        .execute(&pool)
+/// Starts a new `price_history` period for an item if its price changed.
+#[cfg(feature = "ssr")]
+async fn record_price_db(
+    pool: &sqlx::SqlitePool,
+    item_id: Uuid,
+    price: Money,
+    now: DateTime<Utc>,
+) -> Result<(), ServerFnError> {
+    let current: Option<Money> =
+        sqlx::query_scalar("SELECT price FROM price_history WHERE item_id = ? AND valid_to IS NULL")
+            .bind(item_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(db_err)?;
+    if current == Some(price) {
+        return Ok(());
+    }
+    sqlx::query("UPDATE price_history SET valid_to = ? WHERE item_id = ? AND valid_to IS NULL")
+        .bind(now)
+        .bind(item_id)
+        .execute(pool)
         .await
         .map_err(db_err)?;
-
-    let result = sqlx::query("DELETE FROM customer_groups WHERE id = ?")
-        .bind(id)
-        .execute(&pool)
+    sqlx::query("INSERT INTO price_history (id, item_id, price, valid_from) VALUES (?, ?, ?, ?)")
+        .bind(Uuid::new_v4())
+        .bind(item_id)
+        .bind(price)
+        .bind(now)
+        .execute(pool)
         .await
         .map_err(db_err)?;
-    if result.rows_affected() == 0 {
-        return Err(not_found("Customer group not found"));
-    }
     Ok(())
 }
 
-/// Generates a printable PDF menu sheet and returns it base64-encoded.
-///
-/// The sheet shows the logo and `title`, then the available items of every
-/// "main course" category with images and prices, followed by the remaining
-/// categories as image-less sections. Only items that are in stock are listed,
-/// and empty categories are skipped.
-#[server]
-pub async fn generate_menu_pdf(title: String) -> Result<String, ServerFnError> {
-    use base64::Engine;
-    use crate::menu_pdf::{build_menu_pdf, MenuItem, MenuSection};
-
-    let pool = expect_context::<sqlx::SqlitePool>();
-    require_admin(&pool).await?;
-
-    // User-defined order; the PDF builder still groups main courses first,
-    // preserving this order within each group.
-    let categories = sqlx::query_as::<_, Category>(
-        "SELECT * FROM categories ORDER BY sort_order, name",
+/// All modifier groups with their options and attachments.
+#[cfg(feature = "ssr")]
+async fn modifier_groups_db(pool: &sqlx::SqlitePool) -> Result<Vec<ModifierGroupDetail>, ServerFnError> {
+    let groups = sqlx::query_as::<_, ModifierGroup>("SELECT * FROM modifier_groups ORDER BY name")
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?;
+    let modifiers = sqlx::query_as::<_, Modifier>("SELECT * FROM modifiers ORDER BY sort_order, name")
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?;
+    let links = sqlx::query_as::<_, (Uuid, Option<Uuid>, Option<Uuid>)>(
+        "SELECT group_id, item_id, category_id FROM modifier_group_links",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    Ok(groups
+        .into_iter()
+        .map(|group| ModifierGroupDetail {
+            modifiers: modifiers.iter().filter(|m| m.group_id == group.id).cloned().collect(),
+            item_ids: links.iter().filter(|l| l.0 == group.id).filter_map(|l| l.1).collect(),
+            category_ids: links.iter().filter(|l| l.0 == group.id).filter_map(|l| l.2).collect(),
+            group,
+        })
+        .collect())
+}
+
+/// `(tax rate, line total after discounts)` for each line of a sale, as fed
+/// to [`tax_breakdown`].
+#[cfg(feature = "ssr")]
+async fn transaction_tax_lines_db(
+    db: impl sqlx::SqliteExecutor<'_>,
+    transaction_id: Uuid,
+) -> Result<Vec<(Option<f64>, Money)>, ServerFnError> {
+    sqlx::query_as::<_, (Option<f64>, Money)>(
+        "SELECT ti.tax_rate, ti.total_price
+         + COALESCE((SELECT SUM(d.amount) FROM transaction_discounts d WHERE d.transaction_item_id = ti.id), 0)
+         FROM transaction_items ti WHERE ti.transaction_id = ?",
+    )
+    .bind(transaction_id)
+    .fetch_all(db)
+    .await
+    .map_err(db_err)
+}
+
+/// The tax rate in percent that applies to an item: its own override if set,
+/// otherwise its category's. `None` if neither has a rate.
+#[cfg(feature = "ssr")]
+async fn effective_tax_rate_db(
+    db: impl sqlx::SqliteExecutor<'_>,
+    item: &Item,
+) -> Result<Option<f64>, ServerFnError> {
+    sqlx::query_scalar::<_, f64>(
+        "SELECT rate FROM tax_rates WHERE id = COALESCE(?, (SELECT tax_rate_id FROM categories WHERE id = ?))",
+    )
+    .bind(item.tax_rate_id)
+    .bind(item.category_id)
+    .fetch_optional(db)
+    .await
+    .map_err(db_err)
+}
+
+/// Receipt lines for a sale line's modifiers, e.g. "Oat milk +0.50", or for
+/// the components of a bundle line folded by [`group_bundles`].
+#[cfg(feature = "ssr")]
+fn receipt_modifier_labels(line: &TransactionItemDetail, currency: &str) -> Vec<String> {
+    line.components
+        .iter()
+        .cloned()
+        .chain(line.modifiers.iter().map(|m| {
+            if m.price_delta.is_zero() {
+                m.name.clone()
+            } else {
+                let sign = if m.price_delta.is_negative() { "" } else { "+" };
+                format!("{} {}{}", m.name, sign, m.price_delta.format(currency))
+            }
+        }))
+        .collect()
+}
+
+/// `quantity` of a sale line as an entry of a receipt's items. Goods sold by
+/// weight are one entry at the price of the weight, with [`receipt_weight`]
+/// printed under it.
+#[cfg(feature = "ssr")]
+fn receipt_item(line: &TransactionItemDetail, quantity: Quantity) -> (String, u32, i64) {
+    match line.unit {
+        Some(_) => (line.item_name.clone(), 1, (line.unit_price * quantity).0),
+        None => (line.item_name.clone(), quantity.whole_units() as u32, line.unit_price.0),
+    }
+}
+
+/// Receipt weight details for `quantity` of a weighed sale line: kg, unit
+/// and price per unit.
+#[cfg(feature = "ssr")]
+fn receipt_weight(line: &TransactionItemDetail, quantity: Quantity) -> Option<(f32, String, i64)> {
+    let unit = line.unit.as_deref()?;
+    Some((quantity.0 as f32 / Quantity::ONE.0 as f32, unit.to_string(), line.unit_price.0))
+}
+
+#[cfg(feature = "ssr")]
+fn tax_summaries(taxes: &[TaxLine]) -> Vec<rustpos_common::protocol::TaxSummary> {
+    taxes
+        .iter()
+        .map(|t| rustpos_common::protocol::TaxSummary {
+            rate: t.rate as f32,
+            net: t.net.0,
+            tax: t.tax.0,
+            gross: t.gross.0,
+        })
+        .collect()
+}
+
+/// How much of each line of a sale is not yet returned by an earlier refund.
+/// Bundles are refunded through their component lines.
+#[cfg(feature = "ssr")]
+async fn refundable_lines_db(
+    db: impl sqlx::SqliteExecutor<'_>,
+    transaction_id: Uuid,
+) -> Result<Vec<RefundLine>, ServerFnError> {
+    // Refund lines carry negative quantities, so adding them up nets out what
+    // has already been returned.
+    let rows = sqlx::query_as::<_, (Uuid, Quantity)>(
+        "SELECT ti.id, ti.quantity + COALESCE((
+             SELECT SUM(r.quantity) FROM transaction_items r WHERE r.refund_of_item_id = ti.id
+         ), 0)
+         FROM transaction_items ti WHERE ti.transaction_id = ? AND NOT ti.is_bundle",
+    )
+    .bind(transaction_id)
+    .fetch_all(db)
+    .await
+    .map_err(db_err)?;
+    Ok(rows
+        .into_iter()
+        .map(|(id, qty)| RefundLine {
+            transaction_item_id: id,
+            quantity: qty.max(Quantity::ZERO),
+        })
+        .collect())
+}
+
+/// Builds the SQL fragment that restricts a query to a customer group.
+///
+/// `alias` is the table alias used for `transactions` in the surrounding query
+/// (e.g. `"t"` or `"transactions"`); it is a hard-coded identifier, never user
+/// input. sqlx stores `Uuid` columns as 16-byte BLOBs, so the group id is
+/// inlined as a SQLite blob literal (`X'<32 hex>'`) — a text literal would never
+/// match the stored blob. The hex form has no SQL-special characters, so this is
+/// injection-safe and avoids variadic binding.
+#[cfg(feature = "ssr")]
+fn group_filter_clause(filter: &GroupFilter, alias: &str) -> String {
+    match filter {
+        GroupFilter::All => String::new(),
+        GroupFilter::Regular => format!(" AND {alias}.customer_group_id IS NULL"),
+        GroupFilter::Group(id) => {
+            format!(" AND {alias}.customer_group_id = X'{}'", id.simple())
+        }
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn generate_sales_report_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+) -> Result<SalesReport, ServerFnError> {
+    if end_date <= start_date {
+        return Err(not_found("End date must be after start date"));
+    }
+
+    // Bundle lines carry no revenue of their own; it is booked on their
+    // components. Deposits are not revenue and are reported on their own.
+    let items = sqlx::query_as::<_, ItemSalesReport>(&format!(
+        "SELECT i.id as item_id, i.name as item_name, c.name as category_name,
+         SUM(ti.quantity) as quantity_sold, SUM(ti.total_price) as total_revenue,
+         CAST(ROUND(AVG(ti.unit_price)) AS INTEGER) as average_price, COUNT(DISTINCT ti.transaction_id) as transaction_count,
+         MAX(ti.unit) as unit
+         FROM transaction_items ti
+         JOIN items i ON ti.item_id = i.id
+         JOIN categories c ON i.category_id = c.id
+         JOIN transactions t ON ti.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ? AND NOT ti.is_bundle AND NOT ti.is_deposit{}
+         GROUP BY i.id, i.name, c.name ORDER BY total_revenue DESC",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    // Item revenue is at list price; discounts come off the total.
+    let total_discount = sqlx::query_scalar::<_, Money>(&format!(
+        "SELECT COALESCE(SUM(d.amount), 0)
+         FROM transaction_discounts d
+         JOIN transactions t ON d.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)?;
+
+    let total_revenue: Money = items.iter().map(|i| i.total_revenue).sum::<Money>() + total_discount;
+    // Pieces sold; goods sold by weight are counted in kg on their own rows.
+    let total_items_sold: i64 =
+        items.iter().filter(|i| i.unit.is_none()).map(|i| i.quantity_sold.whole_units()).sum();
+
+    let transaction_count = sqlx::query_scalar::<_, i64>(&format!(
+        "SELECT COUNT(DISTINCT id) FROM transactions
+         WHERE status = 'closed' AND refund_of IS NULL AND closed_at >= ? AND closed_at < ?{}",
+        group_filter_clause(filter, "transactions"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)?;
+
+    let average_transaction_value = if transaction_count > 0 {
+        total_revenue.mul_div(1, transaction_count)
+    } else {
+        Money::ZERO
+    };
+
+    let top_selling_item = items
+        .iter()
+        .filter(|i| i.unit.is_none())
+        .max_by_key(|i| i.quantity_sold)
+        .map(|i| i.item_name.clone());
+
+    let top_revenue_item = items
+        .iter()
+        .max_by_key(|i| i.total_revenue)
+        .map(|i| i.item_name.clone());
+
+    let taxes = tax_totals_db(pool, start_date, end_date, filter).await?;
+
+    // Modifier revenue is already part of the item totals above. Each line's
+    // share is rounded half away from zero, as `Money * Quantity` does.
+    let modifiers = sqlx::query_as::<_, ModifierSalesReport>(&format!(
+        "SELECT m.group_name, m.name as modifier_name, SUM(ti.quantity) / 1000 as quantity_sold,
+         SUM(CAST(ROUND(m.price_delta * ti.quantity / 1000.0) AS INTEGER)) as total_revenue
+         FROM transaction_item_modifiers m
+         JOIN transaction_items ti ON m.transaction_item_id = ti.id
+         JOIN transactions t ON ti.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         GROUP BY m.group_name, m.name ORDER BY quantity_sold DESC",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    // Each line is matched to the list price in effect when it was rung up.
+    let prices = sqlx::query_as::<_, ItemPriceReport>(&format!(
+        "SELECT i.name as item_name, ph.price as list_price, ph.valid_from, ph.valid_to,
+         SUM(ti.quantity) as quantity_sold, SUM(ti.total_price) as total_revenue
+         FROM transaction_items ti
+         JOIN items i ON ti.item_id = i.id
+         JOIN transactions t ON ti.transaction_id = t.id
+         JOIN price_history ph ON ph.item_id = ti.item_id AND ph.valid_from <= ti.created_at
+              AND (ph.valid_to IS NULL OR ph.valid_to > ti.created_at)
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ? AND NOT ti.is_bundle AND NOT ti.is_deposit{}
+         GROUP BY ph.id, i.name, ph.price, ph.valid_from, ph.valid_to
+         ORDER BY i.name, ph.valid_from",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    // Refunds give promotion discounts back, so they net out here; only the
+    // sales themselves are counted.
+    let promotions = sqlx::query_as::<_, PromotionSalesReport>(&format!(
+        "SELECT d.name,
+         COUNT(DISTINCT CASE WHEN t.refund_of IS NULL THEN d.transaction_id END) as transaction_count,
+         SUM(d.amount) as total_discount
+         FROM transaction_discounts d
+         JOIN transactions t ON d.transaction_id = t.id
+         WHERE d.promotion_id IS NOT NULL AND t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         GROUP BY d.promotion_id, d.name ORDER BY total_discount",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    let discount_reasons = manual_discount_totals_db(pool, start_date, end_date, filter, "reason").await?;
+    let discount_users = manual_discount_totals_db(pool, start_date, end_date, filter, "user_name").await?;
+    let gift_cards = gift_card_totals_db(pool, start_date, end_date, filter).await?;
+    let deposits = deposit_totals_db(pool, start_date, end_date, filter).await?;
+    let tips = tip_totals_db(pool, start_date, end_date, filter).await?;
+
+    Ok(SalesReport {
+        start_date,
+        end_date,
+        items,
+        taxes,
+        modifiers,
+        prices,
+        promotions,
+        discount_reasons,
+        discount_users,
+        gift_cards,
+        deposits,
+        tips,
+        summary: ReportSummary {
+            total_revenue,
+            total_items_sold,
+            total_transactions: transaction_count,
+            average_transaction_value,
+            top_selling_item,
+            top_revenue_item,
+        },
+    })
+}
+
+/// Manual discounts in the period summed per reason code or per cashier
+/// (`column` is "reason" or "user_name"). As for promotions, refunds net out
+/// and only the sales themselves are counted.
+#[cfg(feature = "ssr")]
+async fn manual_discount_totals_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+    column: &str,
+) -> Result<Vec<DiscountSalesReport>, ServerFnError> {
+    sqlx::query_as::<_, DiscountSalesReport>(&format!(
+        "SELECT COALESCE(d.{column}, '') as name,
+         COUNT(DISTINCT CASE WHEN t.refund_of IS NULL THEN d.transaction_id END) as transaction_count,
+         SUM(d.amount) as total_discount
+         FROM transaction_discounts d
+         JOIN transactions t ON d.transaction_id = t.id
+         WHERE d.reason IS NOT NULL AND t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         GROUP BY d.{column} ORDER BY total_discount",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Gift card money topped up, redeemed and refunded in the period, and the
+/// balance left on all cards. Balance refunds belong to no sale, so a group
+/// filter leaves them out unless it selects regular customers.
+#[cfg(feature = "ssr")]
+async fn gift_card_totals_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+) -> Result<GiftCardReport, ServerFnError> {
+    let rows = sqlx::query_as::<_, (String, Money)>(&format!(
+        "SELECT e.kind, SUM(e.amount)
+         FROM gift_card_entries e
+         LEFT JOIN transactions t ON e.transaction_id = t.id
+         WHERE e.created_at >= ? AND e.created_at < ?{}
+         GROUP BY e.kind",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let outstanding: Money = sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM gift_card_entries")
+        .fetch_one(pool)
+        .await
+        .map_err(db_err)?;
+
+    let sum = |kind: &str| rows.iter().find(|(k, _)| k == kind).map(|(_, a)| *a).unwrap_or(Money::ZERO);
+    Ok(GiftCardReport {
+        topped_up: sum("top_up"),
+        redeemed: -sum("redeem"),
+        refunded: -sum("refund"),
+        outstanding,
+    })
+}
+
+/// Deposits charged and paid back in closed sales in the period, per
+/// deposit item.
+#[cfg(feature = "ssr")]
+async fn deposit_totals_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+) -> Result<DepositReport, ServerFnError> {
+    let items = sqlx::query_as::<_, DepositItemReport>(&format!(
+        "SELECT i.name as item_name,
+         COALESCE(SUM(CASE WHEN ti.total_price > 0 THEN ti.quantity END), 0) / 1000 as charged_quantity,
+         COALESCE(SUM(CASE WHEN ti.total_price > 0 THEN ti.total_price END), 0) as charged,
+         COALESCE(-SUM(CASE WHEN ti.total_price < 0 THEN ti.quantity END), 0) / 1000 as returned_quantity,
+         COALESCE(-SUM(CASE WHEN ti.total_price < 0 THEN ti.total_price END), 0) as returned
+         FROM transaction_items ti
+         JOIN items i ON ti.item_id = i.id
+         JOIN transactions t ON ti.transaction_id = t.id
+         WHERE ti.is_deposit AND t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         GROUP BY i.id, i.name ORDER BY i.name",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(DepositReport::from_items(items))
+}
+
+/// Tips taken with closed sales in the period, per local day and per user
+/// who closed the sale.
+#[cfg(feature = "ssr")]
+async fn tip_totals_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+) -> Result<TipReport, ServerFnError> {
+    let rows = sqlx::query_as::<_, (DateTime<Utc>, Option<String>, Money)>(&format!(
+        "SELECT closed_at, closed_by, tip_amount FROM transactions
+         WHERE status = 'closed' AND tip_amount > 0 AND closed_at >= ? AND closed_at < ?{}",
+        group_filter_clause(filter, "transactions"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let tips: Vec<(String, String, Money)> = rows
+        .into_iter()
+        .map(|(closed_at, user_name, amount)| {
+            let day = closed_at.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string();
+            (day, user_name.unwrap_or_default(), amount)
+        })
+        .collect();
+    Ok(TipReport::from_tips(&tips))
+}
+
+/// Net, tax and gross per rate for closed sales in the period. Each sale is
+/// broken down on its own, as on its receipt, and the results are summed.
+#[cfg(feature = "ssr")]
+async fn tax_totals_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+) -> Result<Vec<TaxLine>, ServerFnError> {
+    let rows = sqlx::query_as::<_, (Uuid, bool, Option<f64>, Money)>(&format!(
+        "SELECT t.id, t.tax_inclusive, ti.tax_rate, ti.total_price
+         + COALESCE((SELECT SUM(d.amount) FROM transaction_discounts d WHERE d.transaction_item_id = ti.id), 0)
+         FROM transaction_items ti
+         JOIN transactions t ON ti.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         ORDER BY t.id",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    let mut totals: Vec<TaxLine> = Vec::new();
+    for sale in rows.chunk_by(|a, b| a.0 == b.0) {
+        let lines: Vec<(Option<f64>, Money)> = sale.iter().map(|r| (r.2, r.3)).collect();
+        for line in tax_breakdown(&lines, sale[0].1) {
+            match totals.iter_mut().find(|t| t.rate == line.rate) {
+                Some(t) => {
+                    t.net += line.net;
+                    t.tax += line.tax;
+                    t.gross += line.gross;
+                }
+                None => totals.push(line),
+            }
+        }
+    }
+    totals.sort_by(|a, b| a.rate.partial_cmp(&b.rate).unwrap());
+    Ok(totals)
+}
+
+/// Revenue per tender type for closed sales in the period, in [`TENDERS`]
+/// order. Change is subtracted from cash, so the amounts add up to revenue
+/// plus the gift card top-ups sold, the tips taken and the cash rounding.
+#[cfg(feature = "ssr")]
+async fn tender_totals_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+) -> Result<Vec<TenderTotal>, ServerFnError> {
+    let rows = sqlx::query_as::<_, (String, i64, Money)>(&format!(
+        "SELECT p.tender, COUNT(DISTINCT p.transaction_id), SUM(p.amount)
+         FROM payments p
+         JOIN transactions t ON p.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         GROUP BY p.tender",
+        group_filter_clause(filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+
+    let total_change = sqlx::query_scalar::<_, Money>(&format!(
+        "SELECT COALESCE(SUM(change_amount), 0) FROM transactions
+         WHERE status = 'closed' AND closed_at >= ? AND closed_at < ?{}",
+        group_filter_clause(filter, "transactions"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)?;
+
+    Ok(TENDERS
+        .iter()
+        .filter_map(|tender| {
+            let (_, count, amount) = rows.iter().find(|(t, _, _)| t == tender)?;
+            let amount = if *tender == "cash" { *amount - total_change } else { *amount };
+            Some(TenderTotal {
+                tender: tender.to_string(),
+                transaction_count: *count,
+                amount,
+            })
+        })
+        .collect())
+}
+
+/// The cash rounding differences of closed sales and refunds in the period,
+/// summed; see [`CashRounding`].
+#[cfg(feature = "ssr")]
+async fn cash_rounding_total_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: &GroupFilter,
+) -> Result<Money, ServerFnError> {
+    sqlx::query_scalar::<_, Money>(&format!(
+        "SELECT COALESCE(SUM(rounding_amount), 0) FROM transactions
+         WHERE status = 'closed' AND closed_at >= ? AND closed_at < ?{}",
+        group_filter_clause(filter, "transactions"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)
+}
+
+// ---- Category Server Functions ----
+
+#[server]
+pub async fn fetch_categories() -> Result<Vec<Category>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let categories = sqlx::query_as::<_, Category>("SELECT * FROM categories ORDER BY sort_order, name")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)?;
+    Ok(categories)
+}
+
+#[server]
+pub async fn create_category(
+    name: String,
+    description: Option<String>,
+    main_course: Option<bool>,
+    tax_rate_id: Option<Uuid>,
+) -> Result<Category, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let main_course = main_course.unwrap_or(false);
+    // Append new categories at the end of the user-defined order.
+    let next_order: i64 =
+        sqlx::query_scalar("SELECT COALESCE(MAX(sort_order), 0) + 1 FROM categories")
+            .fetch_one(&pool)
+            .await
+            .map_err(db_err)?;
+    let category = sqlx::query_as::<_, Category>(
+        "INSERT INTO categories (id, name, description, main_course, sort_order, tax_rate_id, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(id)
+    .bind(&name)
+    .bind(&description)
+    .bind(main_course)
+    .bind(next_order)
+    .bind(tax_rate_id)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(category)
+}
+
+/// Moves a category one position up or down in the user-defined order by
+/// swapping its `sort_order` with its nearest neighbour. No-op at the ends.
+#[server]
+pub async fn move_category(id: Uuid, up: bool) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+
+    let current = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Category not found"))?;
+
+    // Find the adjacent category in (sort_order, name) order, using the same
+    // tie-break as fetch_categories so the swap matches the displayed order.
+    let neighbor = if up {
+        sqlx::query_as::<_, Category>(
+            "SELECT * FROM categories
+             WHERE sort_order < ? OR (sort_order = ? AND name < ?)
+             ORDER BY sort_order DESC, name DESC LIMIT 1",
+        )
+    } else {
+        sqlx::query_as::<_, Category>(
+            "SELECT * FROM categories
+             WHERE sort_order > ? OR (sort_order = ? AND name > ?)
+             ORDER BY sort_order ASC, name ASC LIMIT 1",
+        )
+    }
+    .bind(current.sort_order)
+    .bind(current.sort_order)
+    .bind(&current.name)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?;
+
+    let Some(neighbor) = neighbor else {
+        return Ok(()); // already at the top/bottom
+    };
+
+    // Swap the two order values. If they happen to be equal (legacy data),
+    // nudge them apart so the move still takes effect.
+    let (cur_new, nb_new) = if current.sort_order == neighbor.sort_order {
+        if up {
+            (neighbor.sort_order - 1, neighbor.sort_order)
+        } else {
+            (neighbor.sort_order + 1, neighbor.sort_order)
+        }
+    } else {
+        (neighbor.sort_order, current.sort_order)
+    };
+
+    sqlx::query("UPDATE categories SET sort_order = ? WHERE id = ?")
+        .bind(cur_new)
+        .bind(current.id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    sqlx::query("UPDATE categories SET sort_order = ? WHERE id = ?")
+        .bind(nb_new)
+        .bind(neighbor.id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    Ok(())
+}
+
+/// Updates a category. Unlike the other fields, `tax_rate_id` is always
+/// applied: `None` removes the category's tax rate.
+#[server]
+pub async fn update_category(
+    id: Uuid,
+    name: Option<String>,
+    description: Option<String>,
+    main_course: Option<bool>,
+    tax_rate_id: Option<Uuid>,
+) -> Result<Category, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let mut category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Category not found"))?;
+
+    if let Some(n) = name { category.name = n; }
+    if let Some(d) = description { category.description = Some(d); }
+    if let Some(mc) = main_course { category.main_course = mc; }
+    category.tax_rate_id = tax_rate_id;
+    category.updated_at = Utc::now();
+
+    let updated = sqlx::query_as::<_, Category>(
+        "UPDATE categories SET name = ?, description = ?, main_course = ?, tax_rate_id = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&category.name)
+    .bind(&category.description)
+    .bind(category.main_course)
+    .bind(category.tax_rate_id)
+    .bind(category.updated_at)
+    .bind(id)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(updated)
+}
+
+#[server]
+pub async fn delete_category(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let result = sqlx::query("DELETE FROM categories WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Category not found"));
+    }
+    Ok(())
+}
+
+// ---- Customer Group Server Functions ----
+
+/// Lists all customer groups. Available to any signed-in role so cashiers can
+/// pick a group when closing a sale.
+#[server]
+pub async fn fetch_customer_groups() -> Result<Vec<CustomerGroup>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let groups = sqlx::query_as::<_, CustomerGroup>("SELECT * FROM customer_groups ORDER BY name")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)?;
+    Ok(groups)
+}
+
+#[server]
+pub async fn create_customer_group(name: String) -> Result<CustomerGroup, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(not_found("Group name must not be empty"));
+    }
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let group = sqlx::query_as::<_, CustomerGroup>(
+        "INSERT INTO customer_groups (id, name, created_at, updated_at)
+         VALUES (?, ?, ?, ?) RETURNING *",
+    )
+    .bind(id)
+    .bind(&name)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(group)
+}
+
+#[server]
+pub async fn update_customer_group(id: Uuid, name: String) -> Result<CustomerGroup, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(not_found("Group name must not be empty"));
+    }
+    let group = sqlx::query_as::<_, CustomerGroup>(
+        "UPDATE customer_groups SET name = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&name)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Customer group not found"))?;
+    Ok(group)
+}
+
+/// Deletes a customer group. Its sales are transferred back to "regular
+/// customers" (their `customer_group_id` is cleared) so no statistics are lost.
+#[server]
+pub async fn delete_customer_group(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    if !account_outstanding_db(&pool, AccountRef::Group(id)).await?.is_zero() {
+        return Err(not_found("The group's house account must be settled first"));
+    }
+
+    sqlx::query("UPDATE transactions SET customer_group_id = NULL WHERE customer_group_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+
+    let result = sqlx::query("DELETE FROM customer_groups WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Customer group not found"));
+    }
+    Ok(())
+}
+
+// ---- Customer Server Functions ----
+
+/// Selects customers with their points balance; append a WHERE clause.
+#[cfg(feature = "ssr")]
+const CUSTOMER_SELECT: &str = "SELECT c.*,
+    (SELECT COALESCE(SUM(e.points), 0) FROM loyalty_entries e WHERE e.customer_id = c.id) as points
+    FROM customers c";
+
+#[cfg(feature = "ssr")]
+async fn customer_db(pool: &sqlx::SqlitePool, id: Uuid) -> Result<Customer, ServerFnError> {
+    sqlx::query_as::<_, Customer>(&format!("{CUSTOMER_SELECT} WHERE c.id = ?"))
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Customer not found"))
+}
+
+/// Trims the input, dropping empty optional fields, and checks it.
+#[cfg(feature = "ssr")]
+fn clean_customer_input(input: CustomerInput) -> Result<CustomerInput, ServerFnError> {
+    let opt = |v: Option<String>| v.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
+    let name = input.name.trim().to_string();
+    if name.is_empty() {
+        return Err(not_found("Customer name must not be empty"));
+    }
+    let card_code = match opt(input.card_code) {
+        Some(code) => Some(normalize_card_code(&code).ok_or_else(|| not_found("Invalid loyalty card code"))?),
+        None => None,
+    };
+    Ok(CustomerInput {
+        name,
+        phone: opt(input.phone),
+        email: opt(input.email),
+        notes: opt(input.notes),
+        card_code,
+    })
+}
+
+#[server]
+pub async fn fetch_customers() -> Result<Vec<Customer>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    sqlx::query_as::<_, Customer>(&format!("{CUSTOMER_SELECT} ORDER BY c.name"))
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)
+}
+
+/// Finds up to ten customers whose name, phone or email contains `query`, or
+/// whose loyalty card code is `query`, for the sale page's customer field.
+#[server]
+pub async fn search_customers(query: String) -> Result<Vec<Customer>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    let query = query.trim();
+    if query.is_empty() {
+        return Ok(Vec::new());
+    }
+    let pattern = format!("%{}%", query.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_"));
+    sqlx::query_as::<_, Customer>(&format!(
+        "{CUSTOMER_SELECT}
+         WHERE c.name LIKE ? ESCAPE '\\' OR c.phone LIKE ? ESCAPE '\\' OR c.email LIKE ? ESCAPE '\\'
+            OR c.card_code = ?
+         ORDER BY c.name LIMIT 10"
+    ))
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(&pattern)
+    .bind(query.to_uppercase())
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Registers a customer. Cashiers may do this too, e.g. when handing out a
+/// loyalty card at the till.
+#[server]
+pub async fn create_customer(input: CustomerInput) -> Result<Customer, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    let input = clean_customer_input(input)?;
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    sqlx::query(
+        "INSERT INTO customers (id, name, phone, email, notes, card_code, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(id)
+    .bind(&input.name)
+    .bind(&input.phone)
+    .bind(&input.email)
+    .bind(&input.notes)
+    .bind(&input.card_code)
+    .bind(now)
+    .bind(now)
+    .execute(&pool)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("UNIQUE") {
+            not_found("Another customer already has this loyalty card")
+        } else {
+            db_err(e)
+        }
+    })?;
+    customer_db(&pool, id).await
+}
+
+#[server]
+pub async fn update_customer(id: Uuid, input: CustomerInput) -> Result<Customer, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let input = clean_customer_input(input)?;
+    let result = sqlx::query(
+        "UPDATE customers SET name = ?, phone = ?, email = ?, notes = ?, card_code = ?, updated_at = ?
+         WHERE id = ?",
+    )
+    .bind(&input.name)
+    .bind(&input.phone)
+    .bind(&input.email)
+    .bind(&input.notes)
+    .bind(&input.card_code)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(&pool)
+    .await
+    .map_err(|e| {
+        if e.to_string().contains("UNIQUE") {
+            not_found("Another customer already has this loyalty card")
+        } else {
+            db_err(e)
+        }
+    })?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Customer not found"));
+    }
+    customer_db(&pool, id).await
+}
+
+/// Deletes a customer and their points. Their sales are kept, no longer
+/// linked to anyone.
+#[server]
+pub async fn delete_customer(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    if !account_outstanding_db(&pool, AccountRef::Customer(id)).await?.is_zero() {
+        return Err(not_found("The customer's house account must be settled first"));
+    }
+
+    sqlx::query("UPDATE transactions SET customer_id = NULL WHERE customer_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+
+    let result = sqlx::query("DELETE FROM customers WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Customer not found"));
+    }
+    Ok(())
+}
+
+/// A customer with their closed sales (refunds included), newest first, and
+/// what they spent over all of them.
+#[server]
+pub async fn fetch_customer_detail(id: Uuid) -> Result<CustomerDetail, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let customer = customer_db(&pool, id).await?;
+    let visits = sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions WHERE customer_id = ? AND status = 'closed' ORDER BY closed_at DESC",
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
+    let visit_count = visits.iter().filter(|t| t.refund_of.is_none()).count() as i64;
+    let lifetime_spend = visits.iter().map(|t| t.total).sum();
+    Ok(CustomerDetail { customer, visit_count, lifetime_spend, visits })
+}
+
+#[cfg(feature = "ssr")]
+async fn read_loyalty_settings(pool: &sqlx::SqlitePool) -> LoyaltySettings {
+    let read = |key: &'static str| async move {
+        sqlx::query_scalar::<_, String>("SELECT value FROM config WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|v| v.parse::<f64>().ok())
+    };
+    let defaults = LoyaltySettings::default();
+    LoyaltySettings {
+        points_per_unit: read("loyalty_points_per_unit").await.unwrap_or(defaults.points_per_unit),
+        point_value: read("loyalty_point_value").await.unwrap_or(defaults.point_value),
+    }
+}
+
+/// How many points a currency unit earns and what a point is worth (by
+/// default 1 point per unit, each worth 0.01).
+#[server]
+pub async fn get_loyalty_settings() -> Result<LoyaltySettings, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    Ok(read_loyalty_settings(&pool).await)
+}
+
+#[server]
+pub async fn set_loyalty_settings(settings: LoyaltySettings) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    if !(settings.points_per_unit >= 0.0 && settings.point_value >= 0.0) {
+        return Err(not_found("Loyalty settings must not be negative"));
+    }
+    for (key, value) in [
+        ("loyalty_points_per_unit", settings.points_per_unit),
+        ("loyalty_point_value", settings.point_value),
+    ] {
+        sqlx::query(
+            "INSERT INTO config (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(key)
+        .bind(value.to_string())
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn add_loyalty_entry_db(
+    conn: &mut sqlx::SqliteConnection,
+    customer_id: Uuid,
+    transaction_id: Uuid,
+    points: i64,
+) -> Result<(), ServerFnError> {
+    if points == 0 {
+        return Ok(());
+    }
+    sqlx::query(
+        "INSERT INTO loyalty_entries (id, customer_id, transaction_id, points, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(customer_id)
+    .bind(transaction_id)
+    .bind(points)
+    .bind(Utc::now())
+    .execute(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    Ok(())
+}
+
+/// Links an open sale to a customer, taking over their name, or unlinks it
+/// with `None`. Unlinking drops any points redeemed on the sale.
+#[server]
+pub async fn set_transaction_customer(
+    transaction_id: Uuid,
+    customer_id: Option<Uuid>,
+) -> Result<Option<Customer>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let transaction = open_transaction_db(&pool, transaction_id).await?;
+    let customer = match customer_id {
+        Some(id) => Some(customer_db(&pool, id).await?),
+        None => None,
+    };
+    if transaction.customer_id != customer_id {
+        sqlx::query("DELETE FROM manual_discounts WHERE transaction_id = ? AND loyalty_points IS NOT NULL")
+            .bind(transaction_id)
+            .execute(&pool)
+            .await
+            .map_err(db_err)?;
+    }
+    sqlx::query("UPDATE transactions SET customer_id = ?, customer_name = ?, updated_at = ? WHERE id = ?")
+        .bind(customer_id)
+        .bind(customer.as_ref().map(|c| c.name.clone()).or(transaction.customer_name))
+        .bind(Utc::now())
+        .bind(transaction_id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    update_transaction_total_db(&pool, transaction_id).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(customer)
+}
+
+/// Links an open sale to the customer whose loyalty card was scanned.
+#[server]
+pub async fn link_customer_card(transaction_id: Uuid, code: String) -> Result<Customer, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let code = normalize_card_code(&code).ok_or_else(|| not_found("Invalid loyalty card code"))?;
+    let id: Uuid = sqlx::query_scalar("SELECT id FROM customers WHERE card_code = ?")
+        .bind(&code)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Unknown loyalty card"))?;
+    set_transaction_customer(transaction_id, Some(id))
+        .await?
+        .ok_or_else(|| not_found("Customer not found"))
+}
+
+/// Redeems some of the linked customer's points as a discount on the whole
+/// sale. The points are taken off their balance when the sale closes.
+#[server]
+pub async fn redeem_loyalty_points(transaction_id: Uuid, points: i64) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let user = get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    let transaction = open_transaction_db(&pool, transaction_id).await?;
+    let customer_id = transaction
+        .customer_id
+        .ok_or_else(|| not_found("Link a customer to the sale first"))?;
+    if points <= 0 {
+        return Err(not_found("Enter a number of points above zero"));
+    }
+    let customer = customer_db(&pool, customer_id).await?;
+    let pending: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(loyalty_points), 0) FROM manual_discounts WHERE transaction_id = ?",
+    )
+    .bind(transaction_id)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    if points > customer.points - pending {
+        return Err(not_found(&format!("{} only has {} points", customer.name, customer.points - pending)));
+    }
+
+    let currency = currency_db(&pool).await?;
+    let value = read_loyalty_settings(&pool).await.redemption_value(points, &currency);
+    let base: Money = transaction_lines_db(&mut *pool.acquire().await.map_err(db_err)?, transaction_id)
+        .await?
+        .iter()
+        .map(|l| l.net_total())
+        .sum();
+    if !value.is_positive() {
+        return Err(not_found("Loyalty points have no value set"));
+    }
+    if value > base {
+        return Err(not_found(&format!("The discount can be at most {}", base.format(&currency))));
+    }
+
+    sqlx::query(
+        "INSERT INTO manual_discounts (id, transaction_id, transaction_item_id, discount_type, discount_value,
+         reason, user_name, loyalty_points, created_at)
+         VALUES (?, ?, NULL, 'amount', ?, 'Loyalty points', ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(transaction_id)
+    .bind(value.to_major(&currency))
+    .bind(&user.username)
+    .bind(points)
+    .bind(Utc::now())
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    update_transaction_total_db(&pool, transaction_id).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+// ---- House Account Server Functions ----
+
+/// The name an account goes by: its customer's or its customer group's.
+#[cfg(feature = "ssr")]
+async fn account_name_db(pool: &sqlx::SqlitePool, account: AccountRef) -> Result<String, ServerFnError> {
+    let (sql, id) = match account {
+        AccountRef::Customer(id) => ("SELECT name FROM customers WHERE id = ?", id),
+        AccountRef::Group(id) => ("SELECT name FROM customer_groups WHERE id = ?", id),
+    };
+    sqlx::query_scalar(sql)
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Account not found"))
+}
+
+/// SQL condition selecting `account`'s rows of a table with `customer_id`
+/// and `customer_group_id` columns; bind the account's id once.
+#[cfg(feature = "ssr")]
+fn account_clause(account: AccountRef, alias: &str) -> String {
+    match account {
+        AccountRef::Customer(_) => format!("{alias}.customer_id = ?"),
+        AccountRef::Group(_) => format!("{alias}.customer_id IS NULL AND {alias}.customer_group_id = ?"),
+    }
+}
+
+#[cfg(feature = "ssr")]
+fn account_id(account: AccountRef) -> Uuid {
+    match account {
+        AccountRef::Customer(id) | AccountRef::Group(id) => id,
+    }
+}
+
+#[cfg(feature = "ssr")]
+async fn add_account_charge_db(
+    conn: &mut sqlx::SqliteConnection,
+    account: AccountRef,
+    transaction_id: Uuid,
+    amount: Money,
+) -> Result<(), ServerFnError> {
+    let (customer_id, customer_group_id) = account.ids();
+    sqlx::query(
+        "INSERT INTO account_charges (id, transaction_id, customer_id, customer_group_id, amount, created_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(transaction_id)
+    .bind(customer_id)
+    .bind(customer_group_id)
+    .bind(amount)
+    .bind(Utc::now())
+    .execute(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    Ok(())
+}
+
+/// What is still owed on an account, over all periods.
+#[cfg(feature = "ssr")]
+async fn account_outstanding_db(pool: &sqlx::SqlitePool, account: AccountRef) -> Result<Money, ServerFnError> {
+    sqlx::query_scalar(&format!(
+        "SELECT COALESCE(SUM(a.amount), 0) FROM account_charges a
+         WHERE a.settlement_id IS NULL AND {}",
+        account_clause(account, "a"),
+    ))
+    .bind(account_id(account))
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)
+}
+
+#[cfg(feature = "ssr")]
+async fn account_statement_db(
+    pool: &sqlx::SqlitePool,
+    account: AccountRef,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+) -> Result<AccountStatement, ServerFnError> {
+    if end_date <= start_date {
+        return Err(not_found("End date must be after start date"));
+    }
+    let name = account_name_db(pool, account).await?;
+    let charges = sqlx::query_as::<_, AccountCharge>(&format!(
+        "SELECT a.*, t.customer_name FROM account_charges a
+         JOIN transactions t ON a.transaction_id = t.id
+         WHERE a.created_at >= ? AND a.created_at < ? AND {}
+         ORDER BY a.created_at",
+        account_clause(account, "a"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .bind(account_id(account))
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let settlements = sqlx::query_as::<_, AccountSettlement>(&format!(
+        "SELECT * FROM account_settlements s
+         WHERE s.created_at >= ? AND s.created_at < ? AND {}
+         ORDER BY s.created_at",
+        account_clause(account, "s"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .bind(account_id(account))
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let unpaid = AccountStatement::unpaid_of(&charges);
+    let outstanding = account_outstanding_db(pool, account).await?;
+    Ok(AccountStatement { account, name, start_date, end_date, charges, settlements, unpaid, outstanding })
+}
+
+/// Every house account that was ever charged, with what is owed on it,
+/// largest balance first.
+#[server]
+pub async fn fetch_accounts() -> Result<Vec<AccountBalance>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let rows = sqlx::query_as::<_, (Option<Uuid>, Option<Uuid>, Money)>(
+        "SELECT customer_id, customer_group_id,
+         COALESCE(SUM(CASE WHEN settlement_id IS NULL THEN amount ELSE 0 END), 0)
+         FROM account_charges GROUP BY customer_id, customer_group_id",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
+    let mut accounts = Vec::new();
+    for (customer_id, customer_group_id, outstanding) in rows {
+        let Some(account) = AccountRef::of(customer_id, customer_group_id) else { continue };
+        let name = account_name_db(&pool, account).await?;
+        accounts.push(AccountBalance { account, name, outstanding });
+    }
+    accounts.sort_by(|a, b| b.outstanding.cmp(&a.outstanding).then_with(|| a.name.cmp(&b.name)));
+    Ok(accounts)
+}
+
+#[server]
+pub async fn fetch_account_statement(
+    account: AccountRef,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+) -> Result<AccountStatement, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    account_statement_db(&pool, account, start_date, end_date).await
+}
+
+/// Records a settlement payment for the given unpaid charges of an account
+/// and marks them paid. The payment is for exactly what they add up to.
+#[server]
+pub async fn settle_account(
+    account: AccountRef,
+    charge_ids: Vec<Uuid>,
+    tender: String,
+) -> Result<AccountSettlement, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let admin = require_admin(&pool).await?;
+
+    if !TENDERS.contains(&tender.as_str()) || tender == "account" || tender == "gift_card" {
+        return Err(not_found(&format!("Cannot settle an account by {}", tender)));
+    }
+    if charge_ids.is_empty() {
+        return Err(not_found("Nothing to settle"));
+    }
+    let mut amount = Money::ZERO;
+    for charge_id in &charge_ids {
+        let charge: Money = sqlx::query_scalar(&format!(
+            "SELECT a.amount FROM account_charges a WHERE a.id = ? AND a.settlement_id IS NULL AND {}",
+            account_clause(account, "a"),
+        ))
+        .bind(charge_id)
+        .bind(account_id(account))
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("A charge is already settled or not on this account"))?;
+        amount += charge;
+    }
+
+    let (customer_id, customer_group_id) = account.ids();
+    let settlement = sqlx::query_as::<_, AccountSettlement>(
+        "INSERT INTO account_settlements (id, customer_id, customer_group_id, tender, amount, user_name, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(Uuid::new_v4())
+    .bind(customer_id)
+    .bind(customer_group_id)
+    .bind(&tender)
+    .bind(amount)
+    .bind(&admin.username)
+    .bind(Utc::now())
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    for charge_id in &charge_ids {
+        sqlx::query("UPDATE account_charges SET settlement_id = ? WHERE id = ?")
+            .bind(settlement.id)
+            .bind(charge_id)
+            .execute(&pool)
+            .await
+            .map_err(db_err)?;
+    }
+    Ok(settlement)
+}
+
+/// Generates an A4 statement of an account for a period and returns it
+/// base64-encoded. Dates are printed in the server's local time.
+#[server]
+pub async fn generate_account_statement_pdf(
+    account: AccountRef,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    labels: StatementLabels,
+) -> Result<String, ServerFnError> {
+    use base64::Engine;
+    use crate::statement_pdf::{build_statement_pdf, StatementPayment, StatementRow, StatementSheet};
+
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let statement = account_statement_db(&pool, account, start_date, end_date).await?;
+    let currency = currency_db(&pool).await?;
+
+    let day = |d: DateTime<Utc>| d.with_timezone(&chrono::Local).format("%Y-%m-%d").to_string();
+    let tender_name = |t: &str| {
+        labels.tenders.iter().find(|(code, _)| code == t).map(|(_, name)| name.clone()).unwrap_or_else(|| t.to_string())
+    };
+    let sheet = StatementSheet {
+        account_name: statement.name.clone(),
+        period: format!("{} – {}", day(start_date), day(end_date - chrono::Duration::seconds(1))),
+        rows: statement
+            .charges
+            .iter()
+            .map(|c| StatementRow {
+                date: day(c.created_at),
+                description: c.customer_name.clone().unwrap_or_else(|| c.transaction_id.to_string()[..8].to_string()),
+                amount: c.amount,
+                paid: c.settlement_id.is_some(),
+            })
+            .collect(),
+        payments: statement
+            .settlements
+            .iter()
+            .map(|s| StatementPayment { date: day(s.created_at), tender: tender_name(&s.tender), amount: s.amount })
+            .collect(),
+        unpaid: statement.unpaid,
+        outstanding: statement.outstanding,
+    };
+
+    // Same logo as the menu sheet.
+    let logo_path = if std::path::Path::new("site/logo_site.png").exists() {
+        "site/logo_site.png"
+    } else {
+        "data/logo_receipt.png"
+    };
+
+    let pdf_bytes = tokio::task::spawn_blocking(move || {
+        build_statement_pdf(&labels, &currency, logo_path, &sheet)
+    })
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .map_err(ServerFnError::new)?;
+
+    Ok(base64::engine::general_purpose::STANDARD.encode(&pdf_bytes))
+}
+
+// ---- Floor Plan Server Functions ----
+
+/// Lists all areas with their tables. Available to any signed-in role so the
+/// sale screen can show the table view.
+#[server]
+pub async fn fetch_floor_plan() -> Result<Vec<FloorAreaDetail>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let areas = sqlx::query_as::<_, FloorArea>("SELECT * FROM floor_areas ORDER BY sort_order, name")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)?;
+    let mut tables = sqlx::query_as::<_, FloorTable>("SELECT * FROM floor_tables ORDER BY sort_order, name")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)?;
+    Ok(areas
+        .into_iter()
+        .map(|area| {
+            let (own, rest): (Vec<_>, Vec<_>) = tables.drain(..).partition(|t| t.area_id == area.id);
+            tables = rest;
+            FloorAreaDetail { area, tables: own }
+        })
+        .collect())
+}
+
+/// What is currently open at each occupied table.
+#[server]
+pub async fn fetch_table_status() -> Result<Vec<TableStatus>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let status = sqlx::query_as::<_, TableStatus>(
+        "SELECT t.table_id AS table_id,
+                COUNT(*) AS open_count,
+                COALESCE(SUM(t.total), 0) AS open_total,
+                COALESCE(
+                    (SELECT MIN(ti.created_at) FROM transaction_items ti
+                     JOIN transactions o ON o.id = ti.transaction_id
+                     WHERE o.table_id = t.table_id AND o.status = 'open'),
+                    MIN(t.created_at)) AS first_order_at
+         FROM transactions t
+         WHERE t.status = 'open' AND t.table_id IS NOT NULL
+         GROUP BY t.table_id",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(status)
+}
+
+#[server]
+pub async fn create_floor_area(name: String) -> Result<FloorArea, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(not_found("Area name must not be empty"));
+    }
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let area = sqlx::query_as::<_, FloorArea>(
+        "INSERT INTO floor_areas (id, name, sort_order, created_at, updated_at)
+         VALUES (?, ?, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM floor_areas), ?, ?) RETURNING *",
+    )
+    .bind(id)
+    .bind(&name)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(area)
+}
+
+#[server]
+pub async fn update_floor_area(id: Uuid, name: String) -> Result<FloorArea, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(not_found("Area name must not be empty"));
+    }
+    let area = sqlx::query_as::<_, FloorArea>(
+        "UPDATE floor_areas SET name = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&name)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Area not found"))?;
+    Ok(area)
+}
+
+/// Deletes an area together with its tables. Sales that were seated at one of
+/// them keep their items but are no longer assigned to a table.
+#[server]
+pub async fn delete_floor_area(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+
+    sqlx::query(
+        "UPDATE transactions SET table_id = NULL
+         WHERE table_id IN (SELECT id FROM floor_tables WHERE area_id = ?)",
+    )
+    .bind(id)
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+
+    let result = sqlx::query("DELETE FROM floor_areas WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Area not found"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+fn validate_floor_table(name: &str, seats: i32) -> Result<(), ServerFnError> {
+    if name.is_empty() {
+        return Err(not_found("Table name must not be empty"));
+    }
+    if seats < 0 {
+        return Err(not_found("Seats must not be negative"));
+    }
+    Ok(())
+}
+
+#[server]
+pub async fn create_floor_table(area_id: Uuid, name: String, seats: i32) -> Result<FloorTable, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    validate_floor_table(&name, seats)?;
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let table = sqlx::query_as::<_, FloorTable>(
+        "INSERT INTO floor_tables (id, area_id, name, seats, sort_order, created_at, updated_at)
+         VALUES (?, ?, ?, ?, (SELECT COALESCE(MAX(sort_order), 0) + 1 FROM floor_tables WHERE area_id = ?), ?, ?)
+         RETURNING *",
+    )
+    .bind(id)
+    .bind(area_id)
+    .bind(&name)
+    .bind(seats)
+    .bind(area_id)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(table)
+}
+
+#[server]
+pub async fn update_floor_table(
+    id: Uuid,
+    area_id: Uuid,
+    name: String,
+    seats: i32,
+) -> Result<FloorTable, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    validate_floor_table(&name, seats)?;
+    let table = sqlx::query_as::<_, FloorTable>(
+        "UPDATE floor_tables SET area_id = ?, name = ?, seats = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(area_id)
+    .bind(&name)
+    .bind(seats)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Table not found"))?;
+    Ok(table)
+}
+
+/// Deletes a table. Sales seated at it are no longer assigned to a table.
+#[server]
+pub async fn delete_floor_table(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+
+    sqlx::query("UPDATE transactions SET table_id = NULL WHERE table_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+
+    let result = sqlx::query("DELETE FROM floor_tables WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Table not found"));
+    }
+    Ok(())
+}
+
+/// Seats an open sale at a table, or takes it off its table with `None`.
+/// Several sales may share a table.
+#[server]
+pub async fn assign_transaction_table(
+    transaction_id: Uuid,
+    table_id: Option<Uuid>,
+) -> Result<Transaction, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let updated = sqlx::query_as::<_, Transaction>(
+        "UPDATE transactions SET table_id = ?, updated_at = ? WHERE id = ? AND status = 'open' RETURNING *",
+    )
+    .bind(table_id)
+    .bind(Utc::now())
+    .bind(transaction_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+
+    broadcast_sale_update(transaction_id);
+    Ok(updated)
+}
+
+// ---- Tax Rate Server Functions ----
+
+/// Lists all tax rates, lowest first.
+#[server]
+pub async fn fetch_tax_rates() -> Result<Vec<TaxRate>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let rates = sqlx::query_as::<_, TaxRate>("SELECT * FROM tax_rates ORDER BY rate, name")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)?;
+    Ok(rates)
+}
+
+#[cfg(feature = "ssr")]
+fn validate_tax_rate(name: &str, rate: f64) -> Result<(), ServerFnError> {
+    if name.is_empty() {
+        return Err(not_found("Tax rate name must not be empty"));
+    }
+    if !(0.0..=100.0).contains(&rate) {
+        return Err(not_found("Tax rate must be between 0 and 100 percent"));
+    }
+    Ok(())
+}
+
+#[server]
+pub async fn create_tax_rate(name: String, rate: f64) -> Result<TaxRate, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    validate_tax_rate(&name, rate)?;
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let tax_rate = sqlx::query_as::<_, TaxRate>(
+        "INSERT INTO tax_rates (id, name, rate, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(id)
+    .bind(&name)
+    .bind(rate)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(tax_rate)
+}
+
+/// Renames or changes a tax rate. Lines already sold keep the rate they were
+/// sold at; open sales pick up the new rate when their lines next change.
+#[server]
+pub async fn update_tax_rate(id: Uuid, name: String, rate: f64) -> Result<TaxRate, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    validate_tax_rate(&name, rate)?;
+    let tax_rate = sqlx::query_as::<_, TaxRate>(
+        "UPDATE tax_rates SET name = ?, rate = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&name)
+    .bind(rate)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Tax rate not found"))?;
+    Ok(tax_rate)
+}
+
+/// Deletes a tax rate. Categories and items using it are left without a rate;
+/// past sales are unaffected as their lines store the rate itself.
+#[server]
+pub async fn delete_tax_rate(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+
+    sqlx::query("UPDATE categories SET tax_rate_id = NULL WHERE tax_rate_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    sqlx::query("UPDATE items SET tax_rate_id = NULL WHERE tax_rate_id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+
+    let result = sqlx::query("DELETE FROM tax_rates WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Tax rate not found"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn read_tax_inclusive(pool: &sqlx::SqlitePool) -> bool {
+    sqlx::query_scalar::<_, String>("SELECT value FROM config WHERE key = 'tax_mode'")
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .map(|v| v != "exclusive")
+        .unwrap_or(true)
+}
+
+/// Whether item prices include tax (`true`, the default) or tax is added on
+/// top at checkout.
+#[server]
+pub async fn get_tax_inclusive() -> Result<bool, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    Ok(read_tax_inclusive(&pool).await)
+}
+
+/// Sets the tax mode for sales started from now on.
+#[server]
+pub async fn set_tax_inclusive(inclusive: bool) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    sqlx::query(
+        "INSERT INTO config (key, value) VALUES ('tax_mode', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(if inclusive { "inclusive" } else { "exclusive" })
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn read_cash_rounding(pool: &sqlx::SqlitePool) -> CashRounding {
+    let read = |key: &'static str| async move {
+        sqlx::query_scalar::<_, String>("SELECT value FROM config WHERE key = ?")
+            .bind(key)
+            .fetch_optional(pool)
+            .await
+            .ok()
+            .flatten()
+    };
+    let defaults = CashRounding::default();
+    // The increment is kept in the currency's major unit, e.g. "0.05".
+    let currency = read("currency").await.unwrap_or_default();
+    CashRounding {
+        increment: read("cash_rounding_increment")
+            .await
+            .and_then(|v| Money::parse(&v, &currency))
+            .unwrap_or(defaults.increment),
+        mode: read("cash_rounding_mode")
+            .await
+            .filter(|m| ROUNDING_MODES.contains(&m.as_str()))
+            .unwrap_or(defaults.mode),
+    }
+}
+
+/// The rounding rule for cash payments (by default none).
+#[server]
+pub async fn get_cash_rounding() -> Result<CashRounding, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    Ok(read_cash_rounding(&pool).await)
+}
+
+#[server]
+pub async fn set_cash_rounding(rounding: CashRounding) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    if rounding.increment.is_negative() {
+        return Err(not_found("The rounding increment must not be negative"));
+    }
+    let currency = currency_db(&pool).await?;
+    if !ROUNDING_MODES.contains(&rounding.mode.as_str()) {
+        return Err(not_found(&format!("Unknown rounding mode: {}", rounding.mode)));
+    }
+    for (key, value) in [
+        ("cash_rounding_increment", rounding.increment.format(&currency)),
+        ("cash_rounding_mode", rounding.mode),
+    ] {
+        sqlx::query(
+            "INSERT INTO config (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(key)
+        .bind(value)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn read_business_details(pool: &sqlx::SqlitePool) -> Result<BusinessDetails, ServerFnError> {
+    let rows = sqlx::query_as::<_, (String, String)>("SELECT key, value FROM config WHERE key LIKE 'business_%'")
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?;
+    let read = |key: &str| rows.iter().find(|(k, _)| k == key).map(|(_, v)| v.clone()).unwrap_or_default();
+    Ok(BusinessDetails {
+        name: read("business_name"),
+        street: read("business_street"),
+        postcode: read("business_postcode"),
+        city: read("business_city"),
+        country: read("business_country"),
+        tax_number: read("business_tax_number"),
+        vat_id: read("business_vat_id"),
+        register_id: read("business_register_id"),
+    })
+}
+
+#[server]
+pub async fn get_business_details() -> Result<BusinessDetails, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    read_business_details(&pool).await
+}
+
+#[server]
+pub async fn set_business_details(details: BusinessDetails) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    for (key, value) in [
+        ("business_name", details.name),
+        ("business_street", details.street),
+        ("business_postcode", details.postcode),
+        ("business_city", details.city),
+        ("business_country", details.country.to_uppercase()),
+        ("business_tax_number", details.tax_number),
+        ("business_vat_id", details.vat_id),
+        ("business_register_id", details.register_id),
+    ] {
+        sqlx::query(
+            "INSERT INTO config (key, value) VALUES (?, ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+        )
+        .bind(key)
+        .bind(value.trim())
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+// ---- Modifier Group Server Functions ----
+
+/// Lists all modifier groups with their options and attachments.
+#[server]
+pub async fn fetch_modifier_groups() -> Result<Vec<ModifierGroupDetail>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    modifier_groups_db(&pool).await
+}
+
+#[cfg(feature = "ssr")]
+fn validate_modifier_group(input: &ModifierGroupInput) -> Result<(), ServerFnError> {
+    if input.name.trim().is_empty() {
+        return Err(not_found("Modifier group name must not be empty"));
+    }
+    if input.min_picks < 0 {
+        return Err(not_found("Minimum picks must not be negative"));
+    }
+    if input.max_picks.is_some_and(|max| max < input.min_picks.max(1)) {
+        return Err(not_found("Maximum picks must be at least the minimum and at least 1"));
+    }
+    if input.modifiers.iter().any(|m| m.name.trim().is_empty()) {
+        return Err(not_found("Modifier name must not be empty"));
+    }
+    if (input.min_picks as usize) > input.modifiers.len() {
+        return Err(not_found("Minimum picks exceeds the number of options"));
+    }
+    Ok(())
+}
+
+/// Writes a group's options and attachments: options with a known id are
+/// updated, new ones inserted and the rest deleted. Links are replaced.
+#[cfg(feature = "ssr")]
+async fn save_modifier_group_parts_db(
+    pool: &sqlx::SqlitePool,
+    group_id: Uuid,
+    input: &ModifierGroupInput,
+) -> Result<(), ServerFnError> {
+    let mut keep: Vec<Uuid> = Vec::with_capacity(input.modifiers.len());
+    for (i, m) in input.modifiers.iter().enumerate() {
+        let updated = match m.id {
+            Some(id) => {
+                let result = sqlx::query(
+                    "UPDATE modifiers SET name = ?, price_delta = ?, sort_order = ? WHERE id = ? AND group_id = ?",
+                )
+                .bind(m.name.trim())
+                .bind(m.price_delta)
+                .bind(i as i64)
+                .bind(id)
+                .bind(group_id)
+                .execute(pool)
+                .await
+                .map_err(db_err)?;
+                (result.rows_affected() > 0).then_some(id)
+            }
+            None => None,
+        };
+        let id = match updated {
+            Some(id) => id,
+            None => {
+                let id = Uuid::new_v4();
+                sqlx::query(
+                    "INSERT INTO modifiers (id, group_id, name, price_delta, sort_order) VALUES (?, ?, ?, ?, ?)",
+                )
+                .bind(id)
+                .bind(group_id)
+                .bind(m.name.trim())
+                .bind(m.price_delta)
+                .bind(i as i64)
+                .execute(pool)
+                .await
+                .map_err(db_err)?;
+                id
+            }
+        };
+        keep.push(id);
+    }
+
+    let existing = sqlx::query_scalar::<_, Uuid>("SELECT id FROM modifiers WHERE group_id = ?")
+        .bind(group_id)
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?;
+    for id in existing.into_iter().filter(|id| !keep.contains(id)) {
+        sqlx::query("DELETE FROM modifiers WHERE id = ?")
+            .bind(id)
+            .execute(pool)
+            .await
+            .map_err(db_err)?;
+    }
+
+    sqlx::query("DELETE FROM modifier_group_links WHERE group_id = ?")
+        .bind(group_id)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+    for item_id in &input.item_ids {
+        sqlx::query("INSERT INTO modifier_group_links (group_id, item_id) VALUES (?, ?)")
+            .bind(group_id)
+            .bind(item_id)
+            .execute(pool)
+            .await
+            .map_err(db_err)?;
+    }
+    for category_id in &input.category_ids {
+        sqlx::query("INSERT INTO modifier_group_links (group_id, category_id) VALUES (?, ?)")
+            .bind(group_id)
+            .bind(category_id)
+            .execute(pool)
+            .await
+            .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+#[server]
+pub async fn create_modifier_group(input: ModifierGroupInput) -> Result<ModifierGroup, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    validate_modifier_group(&input)?;
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let group = sqlx::query_as::<_, ModifierGroup>(
+        "INSERT INTO modifier_groups (id, name, min_picks, max_picks, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(id)
+    .bind(input.name.trim())
+    .bind(input.min_picks)
+    .bind(input.max_picks)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    save_modifier_group_parts_db(&pool, id, &input).await?;
+    Ok(group)
+}
+
+/// Updates a modifier group. Sale lines keep the modifier names and prices
+/// they were sold with.
+#[server]
+pub async fn update_modifier_group(
+    id: Uuid,
+    input: ModifierGroupInput,
+) -> Result<ModifierGroup, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    validate_modifier_group(&input)?;
+    let group = sqlx::query_as::<_, ModifierGroup>(
+        "UPDATE modifier_groups SET name = ?, min_picks = ?, max_picks = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(input.name.trim())
+    .bind(input.min_picks)
+    .bind(input.max_picks)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Modifier group not found"))?;
+    save_modifier_group_parts_db(&pool, id, &input).await?;
+    Ok(group)
+}
+
+#[server]
+pub async fn delete_modifier_group(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let result = sqlx::query("DELETE FROM modifier_groups WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Modifier group not found"));
+    }
+    Ok(())
+}
+
+/// Generates a printable PDF menu sheet and returns it base64-encoded.
+///
+/// The sheet shows the logo and `title`, then the available items of every
+/// "main course" category with images and prices, followed by the remaining
+/// categories as image-less sections. Only items that are in stock are listed,
+/// and empty categories are skipped.
+#[server]
+pub async fn generate_menu_pdf(title: String) -> Result<String, ServerFnError> {
+    use base64::Engine;
+    use crate::menu_pdf::{build_menu_pdf, MenuItem, MenuSection};
+
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+
+    // User-defined order; the PDF builder still groups main courses first,
+    // preserving this order within each group.
+    let categories = sqlx::query_as::<_, Category>(
+        "SELECT * FROM categories ORDER BY sort_order, name",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
+
+    let currency = currency_db(&pool).await?;
+
+    let mut sections: Vec<MenuSection> = Vec::new();
+    for category in &categories {
+        let items = sqlx::query_as::<_, Item>(
+            "SELECT * FROM items WHERE category_id = ? AND in_stock = 1 ORDER BY name",
+        )
+        .bind(category.id)
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)?;
+
+        if items.is_empty() {
+            continue;
+        }
+
+        sections.push(MenuSection {
+            name: category.name.clone(),
+            main_course: category.main_course,
+            items: items
+                .into_iter()
+                .map(|i| MenuItem {
+                    price_suffix: i.price_suffix(),
+                    name: i.name,
+                    price: i.price,
+                    description: i.description,
+                    // `image_path` is a web URL ("/item_images/..."); map it to
+                    // the on-disk path served from the data directory.
+                    image_path: i.image_path.map(|p| format!("data{}", p)),
+                })
+                .collect(),
+        });
+    }
+
+    // Prefer the site logo (bundled into the `site` root as /logo_site.png),
+    // falling back to the receipt logo if it isn't present.
+    let logo_path = if std::path::Path::new("site/logo_site.png").exists() {
+        "site/logo_site.png"
+    } else {
+        "data/logo_receipt.png"
+    };
+
+    let pdf_bytes = tokio::task::spawn_blocking(move || {
+        build_menu_pdf(&title, &currency, logo_path, &sections)
+    })
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .map_err(ServerFnError::new)?;
+
+    Ok(base64::engine::general_purpose::STANDARD.encode(&pdf_bytes))
+}
+
+// ---- Promotion Server Functions ----
+
+#[server]
+pub async fn fetch_promotions() -> Result<Vec<Promotion>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    sqlx::query_as::<_, Promotion>("SELECT * FROM promotions ORDER BY created_at")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)
+}
+
+#[cfg(feature = "ssr")]
+fn validate_promotion(input: &PromotionInput) -> Result<(), ServerFnError> {
+    let is_time = |t: &str| chrono::NaiveTime::parse_from_str(t, "%H:%M").is_ok() && t.len() == 5;
+    let is_date = |d: &str| chrono::NaiveDate::parse_from_str(d, "%Y-%m-%d").is_ok() && d.len() == 10;
+    if input.name.trim().is_empty() {
+        return Err(not_found("Promotion name must not be empty"));
+    }
+    if (input.item_id.is_some() && input.category_id.is_some())
+        || (input.trigger_item_id.is_some() && input.trigger_category_id.is_some())
+    {
+        return Err(not_found("Choose either an item or a category, not both"));
+    }
+    if input.buy_quantity < 1 || input.get_quantity < 1 {
+        return Err(not_found("Quantities must be at least 1"));
+    }
+    let has_trigger = input.trigger_item_id.is_some() || input.trigger_category_id.is_some();
+    if !has_trigger && input.get_quantity > input.buy_quantity {
+        return Err(not_found("Discounted quantity must not exceed the quantity bought"));
+    }
+    if !DISCOUNT_TYPES.contains(&input.discount_type.as_str()) {
+        return Err(not_found(&format!("Unknown discount type: {}", input.discount_type)));
+    }
+    if input.discount_value <= 0.0 || (input.discount_type == "percent" && input.discount_value > 100.0) {
+        return Err(not_found("Discount must be above 0 and at most 100%"));
+    }
+    if !input.weekdays.chars().all(|c| ('1'..='7').contains(&c)) {
+        return Err(not_found("Weekdays must be numbers from 1 (Monday) to 7 (Sunday)"));
+    }
+    if [&input.start_time, &input.end_time].into_iter().flatten().any(|t| !is_time(t)) {
+        return Err(not_found("Times must be given as HH:MM"));
+    }
+    if [&input.valid_from, &input.valid_to].into_iter().flatten().any(|d| !is_date(d)) {
+        return Err(not_found("Dates must be given as YYYY-MM-DD"));
+    }
+    if let (Some(from), Some(to)) = (&input.valid_from, &input.valid_to)
+        && to < from
+    {
+        return Err(not_found("The promotion must end after it starts"));
+    }
+    Ok(())
+}
+
+/// Recomputes the promotions of every open sale, after a promotion changed.
+#[cfg(feature = "ssr")]
+async fn reapply_promotions_db(pool: &sqlx::SqlitePool) -> Result<(), ServerFnError> {
+    let open: Vec<Uuid> = sqlx::query_scalar("SELECT id FROM transactions WHERE status = 'open'")
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?;
+    for transaction_id in open {
+        update_transaction_total_db(pool, transaction_id).await?;
+        broadcast_lines_changed(transaction_id);
+    }
+    Ok(())
+}
+
+#[server]
+pub async fn create_promotion(input: PromotionInput) -> Result<Promotion, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    validate_promotion(&input)?;
+    let now = Utc::now();
+    let promotion = sqlx::query_as::<_, Promotion>(
+        "INSERT INTO promotions (id, name, active, item_id, category_id, trigger_item_id, trigger_category_id,
+         buy_quantity, get_quantity, discount_type, discount_value, customer_group_id, weekdays,
+         start_time, end_time, valid_from, valid_to, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(Uuid::new_v4())
+    .bind(input.name.trim())
+    .bind(input.active)
+    .bind(input.item_id)
+    .bind(input.category_id)
+    .bind(input.trigger_item_id)
+    .bind(input.trigger_category_id)
+    .bind(input.buy_quantity)
+    .bind(input.get_quantity)
+    .bind(&input.discount_type)
+    .bind(input.discount_value)
+    .bind(input.customer_group_id)
+    .bind(&input.weekdays)
+    .bind(&input.start_time)
+    .bind(&input.end_time)
+    .bind(&input.valid_from)
+    .bind(&input.valid_to)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    reapply_promotions_db(&pool).await?;
+    Ok(promotion)
+}
+
+/// Updates a promotion. Closed sales keep the discounts they were given.
+#[server]
+pub async fn update_promotion(id: Uuid, input: PromotionInput) -> Result<Promotion, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    validate_promotion(&input)?;
+    let promotion = sqlx::query_as::<_, Promotion>(
+        "UPDATE promotions SET name = ?, active = ?, item_id = ?, category_id = ?, trigger_item_id = ?,
+         trigger_category_id = ?, buy_quantity = ?, get_quantity = ?, discount_type = ?, discount_value = ?,
+         customer_group_id = ?, weekdays = ?, start_time = ?, end_time = ?, valid_from = ?, valid_to = ?,
+         updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(input.name.trim())
+    .bind(input.active)
+    .bind(input.item_id)
+    .bind(input.category_id)
+    .bind(input.trigger_item_id)
+    .bind(input.trigger_category_id)
+    .bind(input.buy_quantity)
+    .bind(input.get_quantity)
+    .bind(&input.discount_type)
+    .bind(input.discount_value)
+    .bind(input.customer_group_id)
+    .bind(&input.weekdays)
+    .bind(&input.start_time)
+    .bind(&input.end_time)
+    .bind(&input.valid_from)
+    .bind(&input.valid_to)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Promotion not found"))?;
+    reapply_promotions_db(&pool).await?;
+    Ok(promotion)
+}
+
+#[server]
+pub async fn delete_promotion(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let result = sqlx::query("DELETE FROM promotions WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Promotion not found"));
+    }
+    reapply_promotions_db(&pool).await
+}
+
+// ---- Manual Discount Server Functions ----
+
+/// Lists the reason codes for manual discounts. Available to any signed-in
+/// role so cashiers can pick one.
+#[server]
+pub async fn fetch_discount_reasons() -> Result<Vec<DiscountReason>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    sqlx::query_as::<_, DiscountReason>("SELECT * FROM discount_reasons ORDER BY name")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)
+}
+
+#[server]
+pub async fn create_discount_reason(name: String) -> Result<DiscountReason, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(not_found("Reason must not be empty"));
+    }
+    let now = Utc::now();
+    sqlx::query_as::<_, DiscountReason>(
+        "INSERT INTO discount_reasons (id, name, created_at, updated_at)
+         VALUES (?, ?, ?, ?) RETURNING *",
+    )
+    .bind(Uuid::new_v4())
+    .bind(&name)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Renames a reason code. Discounts already given keep the name they were
+/// given under.
+#[server]
+pub async fn update_discount_reason(id: Uuid, name: String) -> Result<DiscountReason, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        return Err(not_found("Reason must not be empty"));
+    }
+    sqlx::query_as::<_, DiscountReason>(
+        "UPDATE discount_reasons SET name = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&name)
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Discount reason not found"))
+}
+
+#[server]
+pub async fn delete_discount_reason(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let result = sqlx::query("DELETE FROM discount_reasons WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Discount reason not found"));
+    }
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+async fn read_max_cashier_discount(pool: &sqlx::SqlitePool) -> f64 {
+    sqlx::query_scalar::<_, String>("SELECT value FROM config WHERE key = 'max_cashier_discount'")
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(10.0)
+}
+
+/// The largest discount, in percent of what it is taken off, a cashier may
+/// give without an admin's PIN (default 10%).
+#[server]
+pub async fn get_max_cashier_discount() -> Result<f64, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    Ok(read_max_cashier_discount(&pool).await)
+}
+
+#[server]
+pub async fn set_max_cashier_discount(percent: f64) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    if !(0.0..=100.0).contains(&percent) {
+        return Err(not_found("The limit must be between 0 and 100%"));
+    }
+    sqlx::query(
+        "INSERT INTO config (key, value) VALUES ('max_cashier_discount', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(percent.to_string())
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(())
+}
+
+/// Gives a discount by hand on a line of an open sale or on the whole sale.
+/// It is checked against what the line or sale comes to after the discounts
+/// it already has; above the cashier limit, a cashier needs an admin's PIN.
+#[server]
+pub async fn add_manual_discount(transaction_id: Uuid, input: ManualDiscountInput) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let user = get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    open_transaction_db(&pool, transaction_id).await?;
+    if !DISCOUNT_TYPES.contains(&input.discount_type.as_str()) {
+        return Err(not_found(&format!("Unknown discount type: {}", input.discount_type)));
+    }
+    if input.discount_value <= 0.0 || (input.discount_type == "percent" && input.discount_value > 100.0) {
+        return Err(not_found("Discount must be above 0 and at most 100%"));
+    }
+    let reason: String = sqlx::query_scalar("SELECT name FROM discount_reasons WHERE id = ?")
+        .bind(input.reason_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Pick a reason for the discount"))?;
+
+    let lines = transaction_lines_db(&mut *pool.acquire().await.map_err(db_err)?, transaction_id).await?;
+    if let Some(line_id) = input.transaction_item_id {
+        let line = lines
+            .iter()
+            .find(|l| l.id == line_id && l.bundle_id.is_none())
+            .ok_or_else(|| not_found("Line not found in this transaction"))?;
+        if line.is_deposit {
+            return Err(not_found("Deposits cannot be discounted"));
+        }
+    }
+    let currency = currency_db(&pool).await?;
+    let base: Money = lines
+        .iter()
+        .filter(|l| !l.is_deposit)
+        .filter(|l| input.transaction_item_id.is_none_or(|id| l.id == id || l.bundle_id == Some(id)))
+        .map(|l| l.net_total())
+        .sum();
+    if !base.is_positive() {
+        return Err(not_found("Nothing left to discount"));
+    }
+    if input.discount_type == "amount" && Money::from_major(input.discount_value, &currency) > base {
+        return Err(not_found(&format!("The discount can be at most {}", base.format(&currency))));
+    }
+
+    let approved_by = if user.role != "admin"
+        && discount_percent(&input.discount_type, input.discount_value, base.to_major(&currency))
+            > read_max_cashier_discount(&pool).await
+    {
+        let pin = input
+            .manager_pin
+            .filter(|p| !p.is_empty())
+            .ok_or_else(|| not_found("Manager approval required"))?;
+        let manager: String =
+            sqlx::query_scalar("SELECT username FROM users WHERE role = 'admin' AND pin_hash = ?")
+                .bind(hash_pin(&pin))
+                .fetch_optional(&pool)
+                .await
+                .map_err(db_err)?
+                .ok_or_else(|| not_found("Invalid manager PIN"))?;
+        Some(manager)
+    } else {
+        None
+    };
+
+    sqlx::query(
+        "INSERT INTO manual_discounts (id, transaction_id, transaction_item_id, discount_type, discount_value,
+         reason, user_name, approved_by, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(transaction_id)
+    .bind(input.transaction_item_id)
+    .bind(&input.discount_type)
+    .bind(input.discount_value)
+    .bind(&reason)
+    .bind(&user.username)
+    .bind(&approved_by)
+    .bind(Utc::now())
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    update_transaction_total_db(&pool, transaction_id).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+/// Takes a manual discount off an open sale again.
+#[server]
+pub async fn remove_manual_discount(transaction_id: Uuid, id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    open_transaction_db(&pool, transaction_id).await?;
+    let result = sqlx::query("DELETE FROM manual_discounts WHERE id = ? AND transaction_id = ?")
+        .bind(id)
+        .bind(transaction_id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Discount not found in this transaction"));
+    }
+    update_transaction_total_db(&pool, transaction_id).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+// ---- Gift Card Server Functions ----
+
+#[cfg(feature = "ssr")]
+async fn gift_card_by_code_db(db: impl sqlx::SqliteExecutor<'_>, code: &str) -> Result<Option<GiftCard>, ServerFnError> {
+    sqlx::query_as::<_, GiftCard>("SELECT * FROM gift_cards WHERE code = ?")
+        .bind(code)
+        .fetch_optional(db)
+        .await
+        .map_err(db_err)
+}
+
+#[cfg(feature = "ssr")]
+async fn gift_card_balance_db(db: impl sqlx::SqliteExecutor<'_>, card_id: Uuid) -> Result<Money, ServerFnError> {
+    sqlx::query_scalar("SELECT COALESCE(SUM(amount), 0) FROM gift_card_entries WHERE gift_card_id = ?")
+        .bind(card_id)
+        .fetch_one(db)
+        .await
+        .map_err(db_err)
+}
+
+/// The gift card top-ups rung up on a sale, oldest first.
+#[cfg(feature = "ssr")]
+async fn gift_card_loads_db(
+    db: impl sqlx::SqliteExecutor<'_>,
+    transaction_id: Uuid,
+) -> Result<Vec<GiftCardLoad>, ServerFnError> {
+    sqlx::query_as::<_, GiftCardLoad>(
+        "SELECT l.id, l.transaction_id, l.gift_card_id, g.code, l.amount, l.created_at
+         FROM gift_card_loads l JOIN gift_cards g ON l.gift_card_id = g.id
+         WHERE l.transaction_id = ? ORDER BY l.created_at",
+    )
+    .bind(transaction_id)
+    .fetch_all(db)
+    .await
+    .map_err(db_err)
+}
+
+#[cfg(feature = "ssr")]
+async fn add_gift_card_entry_db(
+    conn: &mut sqlx::SqliteConnection,
+    card_id: Uuid,
+    transaction_id: Option<Uuid>,
+    kind: &str,
+    amount: Money,
+    user_name: Option<&str>,
+) -> Result<(), ServerFnError> {
+    let now = Utc::now();
+    sqlx::query(
+        "INSERT INTO gift_card_entries (id, gift_card_id, transaction_id, kind, amount, user_name, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(card_id)
+    .bind(transaction_id)
+    .bind(kind)
+    .bind(amount)
+    .bind(user_name)
+    .bind(now)
+    .execute(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    sqlx::query("UPDATE gift_cards SET updated_at = ? WHERE id = ?")
+        .bind(now)
+        .bind(card_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    Ok(())
+}
+
+/// Takes `amount` off a gift card, unless that would leave it below zero.
+/// The balance is checked by the insert itself, so two tills spending from
+/// the same card cannot both succeed. Returns whether the entry was written.
+#[cfg(feature = "ssr")]
+async fn take_from_gift_card_db(
+    conn: &mut sqlx::SqliteConnection,
+    card_id: Uuid,
+    transaction_id: Option<Uuid>,
+    kind: &str,
+    amount: Money,
+    user_name: Option<&str>,
+) -> Result<bool, ServerFnError> {
+    let now = Utc::now();
+    let inserted = sqlx::query(
+        "INSERT INTO gift_card_entries (id, gift_card_id, transaction_id, kind, amount, user_name, created_at)
+         SELECT ?, ?, ?, ?, ?, ?, ?
+         WHERE (SELECT COALESCE(SUM(amount), 0) FROM gift_card_entries WHERE gift_card_id = ?) >= ?",
+    )
+    .bind(Uuid::new_v4())
+    .bind(card_id)
+    .bind(transaction_id)
+    .bind(kind)
+    .bind(-amount)
+    .bind(user_name)
+    .bind(now)
+    .bind(card_id)
+    .bind(amount)
+    .execute(&mut *conn)
+    .await
+    .map_err(db_err)?;
+    if inserted.rows_affected() == 0 {
+        return Ok(false);
+    }
+    sqlx::query("UPDATE gift_cards SET updated_at = ? WHERE id = ?")
+        .bind(now)
+        .bind(card_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    Ok(true)
+}
+
+#[cfg(feature = "ssr")]
+async fn gift_card_detail_db(pool: &sqlx::SqlitePool, card: GiftCard) -> Result<GiftCardDetail, ServerFnError> {
+    let entries = sqlx::query_as::<_, GiftCardEntry>(
+        "SELECT * FROM gift_card_entries WHERE gift_card_id = ? ORDER BY created_at DESC",
+    )
+    .bind(card.id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let balance = gift_card_balance_db(pool, card.id).await?;
+    Ok(GiftCardDetail { card, balance, entries })
+}
+
+/// Looks up a gift card by its code, for a balance check.
+#[server]
+pub async fn fetch_gift_card(code: String) -> Result<GiftCardDetail, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let code = normalize_card_code(&code).ok_or_else(|| not_found("Invalid gift card code"))?;
+    let card = gift_card_by_code_db(&pool, &code)
+        .await?
+        .ok_or_else(|| not_found("Unknown gift card"))?;
+    gift_card_detail_db(&pool, card).await
+}
+
+/// Sells a top-up of `amount` onto a gift card with an open sale. Without a
+/// code a new card is issued with a random 16-digit code, printed on the
+/// receipt. A code not seen before (e.g. from pre-printed card stock) starts
+/// a new card under that code.
+#[server]
+pub async fn add_gift_card_top_up(
+    transaction_id: Uuid,
+    code: Option<String>,
+    amount: Money,
+) -> Result<GiftCardLoad, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    open_transaction_db(&pool, transaction_id).await?;
+    if !amount.is_positive() {
+        return Err(not_found("Top-up amount must be positive"));
+    }
+    let code = match code.filter(|c| !c.trim().is_empty()) {
+        Some(code) => normalize_card_code(&code).ok_or_else(|| not_found("Invalid gift card code"))?,
+        None => format!("{:016}", Uuid::new_v4().as_u128() % 10u128.pow(16)),
+    };
+
+    let now = Utc::now();
+    let card = match gift_card_by_code_db(&pool, &code).await? {
+        Some(card) => card,
+        None => sqlx::query_as::<_, GiftCard>(
+            "INSERT INTO gift_cards (id, code, created_at, updated_at) VALUES (?, ?, ?, ?) RETURNING *",
+        )
+        .bind(Uuid::new_v4())
+        .bind(&code)
+        .bind(now)
+        .bind(now)
+        .fetch_one(&pool)
+        .await
+        .map_err(db_err)?,
+    };
+    let load_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO gift_card_loads (id, transaction_id, gift_card_id, amount, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(load_id)
+    .bind(transaction_id)
+    .bind(card.id)
+    .bind(amount)
+    .bind(now)
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    update_transaction_total_db(&pool, transaction_id).await?;
+    broadcast_lines_changed(transaction_id);
+
+    Ok(GiftCardLoad {
+        id: load_id,
+        transaction_id,
+        gift_card_id: card.id,
+        code: card.code,
+        amount,
+        created_at: now,
+    })
+}
+
+/// Takes a gift card top-up off an open sale again.
+#[server]
+pub async fn remove_gift_card_top_up(transaction_id: Uuid, id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    open_transaction_db(&pool, transaction_id).await?;
+    let result = sqlx::query("DELETE FROM gift_card_loads WHERE id = ? AND transaction_id = ?")
+        .bind(id)
+        .bind(transaction_id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Top-up not found"));
+    }
+    update_transaction_total_db(&pool, transaction_id).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+/// Pays out what is left on a gift card in cash, leaving it at zero, and
+/// prints a refund slip.
+#[server]
+pub async fn refund_gift_card(code: String) -> Result<GiftCardDetail, ServerFnError> {
+    use crate::printer::{find_printer, open_cash_drawer, print_refund_receipt};
+
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let admin = require_admin(&pool).await?;
+    let code = normalize_card_code(&code).ok_or_else(|| not_found("Invalid gift card code"))?;
+    let card = gift_card_by_code_db(&pool, &code)
+        .await?
+        .ok_or_else(|| not_found("Unknown gift card"))?;
+    let balance = gift_card_balance_db(&pool, card.id).await?;
+    if !balance.is_positive() {
+        return Err(not_found("The gift card has no balance left"));
+    }
+    let currency = currency_db(&pool).await?;
+    let taken = take_from_gift_card_db(
+        &mut *pool.acquire().await.map_err(db_err)?,
+        card.id,
+        None,
+        "refund",
+        balance,
+        Some(&admin.username),
+    )
+    .await?;
+    if !taken {
+        return Err(not_found("The gift card's balance changed, please try again"));
+    }
+
+    let local_now = chrono::Local::now();
+    let label = format!("Gift card {}", mask_card_code(&card.code));
+    let job = rustpos_common::protocol::PrintReceiptJob {
+        items: vec![(label.clone(), 1, balance.0)],
+        modifiers: vec![Vec::new()],
+        weights: vec![None],
+        discounts: Vec::new(),
+        payments: vec![("cash".to_string(), -balance.0)],
+        paid_amount: -balance.0,
+        change: 0,
+        tip: 0,
+        rounding: 0,
+        decimals: currency_decimals(&currency),
+        datetime: local_now.format("%Y-%m-%d %H:%M:%S").to_string(),
+        refund_of: Some(label),
+        taxes: Vec::new(),
+        tax_exclusive: false,
+        gift_cards: Vec::new(),
+        loyalty_points: None,
+        journal: None,
+        fiscal: None,
+        fiscal_error: None,
+    };
+    if let Some(printer_tx) = use_context::<tokio::sync::broadcast::Sender<
+        rustpos_common::protocol::PrintReceiptJob,
+    >>() {
+        let _ = printer_tx.send(job.clone());
+    }
+    let local_printing_disabled = read_disable_local_printing(&pool).await;
+    let _ = tokio::task::spawn_blocking(move || {
+        if let Ok((_, mut printer)) = find_printer() {
+            if local_printing_disabled {
+                let _ = open_cash_drawer(&mut printer);
+            } else {
+                let _ = print_refund_receipt(
+                    &mut printer,
+                    &job,
+                    local_now,
+                    Some("data/logo_receipt.png"),
+                );
+            }
+        }
+    })
+    .await;
+
+    gift_card_detail_db(&pool, card).await
+}
+
+// ---- Bundle Server Functions ----
+
+#[cfg(feature = "ssr")]
+async fn bundle_components_db(
+    pool: &sqlx::SqlitePool,
+    bundle_item_id: Uuid,
+) -> Result<Vec<BundleComponent>, ServerFnError> {
+    sqlx::query_as::<_, BundleComponent>(
+        "SELECT * FROM bundle_components WHERE bundle_item_id = ? ORDER BY sort_order",
+    )
+    .bind(bundle_item_id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Lists the components of all bundle items, in order per bundle.
+#[server]
+pub async fn fetch_bundle_components() -> Result<Vec<BundleComponent>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    sqlx::query_as::<_, BundleComponent>(
+        "SELECT * FROM bundle_components ORDER BY bundle_item_id, sort_order",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)
+}
+
+/// Replaces the components of an item, making it a bundle (or a plain item
+/// again with an empty list). Bundles cannot contain other bundles or goods
+/// sold by weight.
+#[server]
+pub async fn set_bundle_components(
+    item_id: Uuid,
+    components: Vec<BundleComponentInput>,
+) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+
+    if !components.is_empty() {
+        let used_in: Option<String> = sqlx::query_scalar(
+            "SELECT i.name FROM bundle_components bc JOIN items i ON bc.bundle_item_id = i.id
+             WHERE bc.item_id = ? LIMIT 1",
+        )
+        .bind(item_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?;
+        if let Some(name) = used_in {
+            return Err(not_found(&format!("This item is part of the bundle {}", name)));
+        }
+    }
+    for c in &components {
+        if c.quantity < 1 {
+            return Err(not_found("Component quantity must be at least 1"));
+        }
+        match (c.item_id, c.category_id) {
+            (Some(id), None) => {
+                if id == item_id {
+                    return Err(not_found("A bundle cannot contain itself"));
+                }
+                let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+                    .bind(id)
+                    .fetch_optional(&pool)
+                    .await
+                    .map_err(db_err)?
+                    .ok_or_else(|| not_found("Component item not found"))?;
+                if item.is_weighed() {
+                    return Err(not_found(&format!("{} is sold by weight", item.name)));
+                }
+                if !bundle_components_db(&pool, id).await?.is_empty() {
+                    return Err(not_found(&format!("{} is a bundle itself", item.name)));
+                }
+            }
+            (None, Some(_)) => {}
+            _ => return Err(not_found("A component is either an item or a choice from a category")),
+        }
+    }
+
+    sqlx::query("DELETE FROM bundle_components WHERE bundle_item_id = ?")
+        .bind(item_id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    for (i, c) in components.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO bundle_components (id, bundle_item_id, item_id, category_id, quantity, sort_order)
+             VALUES (?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(item_id)
+        .bind(c.item_id)
+        .bind(c.category_id)
+        .bind(c.quantity)
+        .bind(i as i64)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    }
+    Ok(())
+}
+
+/// Adds one bundle to an open sale: a bundle line without a price of its own
+/// and a line per component, which share the bundle price in proportion to
+/// the components' list prices. `choices` holds the picked item for each
+/// category component, in order.
+#[cfg(feature = "ssr")]
+async fn add_bundle_db(
+    pool: &sqlx::SqlitePool,
+    transaction_id: Uuid,
+    bundle: &Item,
+    choices: &[Uuid],
+) -> Result<(), ServerFnError> {
+    open_transaction_db(pool, transaction_id).await?;
+    if !bundle.in_stock {
+        return Err(not_found("Item is out of stock"));
+    }
+    let components = bundle_components_db(pool, bundle.id).await?;
+    if components.is_empty() {
+        return Err(not_found(&format!("{} is not a bundle", bundle.name)));
+    }
+
+    // Resolve each component to its item, taking the choices in order.
+    let mut choices = choices.iter();
+    let mut parts: Vec<(Item, i32)> = Vec::new();
+    for c in &components {
+        let item_id = match (c.item_id, c.category_id) {
+            (Some(id), _) => id,
+            (None, Some(category_id)) => {
+                let id = *choices.next().ok_or_else(|| not_found("Pick an item for every choice"))?;
+                let in_category: bool =
+                    sqlx::query_scalar("SELECT COUNT(*) > 0 FROM items WHERE id = ? AND category_id = ?")
+                        .bind(id)
+                        .bind(category_id)
+                        .fetch_one(pool)
+                        .await
+                        .map_err(db_err)?;
+                if !in_category {
+                    return Err(not_found("Picked item is not one of the choices"));
+                }
+                id
+            }
+            (None, None) => continue,
+        };
+        let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(db_err)?
+            .ok_or_else(|| not_found("Item not found"))?;
+        if item.is_weighed() {
+            return Err(not_found(&format!("{} is sold by weight", item.name)));
+        }
+        match parts.iter_mut().find(|(p, _)| p.id == item.id) {
+            Some((_, qty)) => *qty += c.quantity,
+            None => parts.push((item, c.quantity)),
+        }
+    }
+    for (item, qty) in &parts {
+        check_stock_db(pool, transaction_id, item, Quantity::units(*qty as i64)).await?;
+    }
+
+    let unit_prices: Vec<Money> = parts
+        .iter()
+        .flat_map(|(item, qty)| std::iter::repeat_n(item.price, *qty as usize))
+        .collect();
+    let mut shares = allocate_bundle_price(bundle.price, &unit_prices).into_iter();
+
+    let now = Utc::now();
+    let bundle_line_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price,
+         is_bundle, created_at)
+         VALUES (?, ?, ?, 1000, 0, 0, 1, ?)",
+    )
+    .bind(bundle_line_id)
+    .bind(transaction_id)
+    .bind(bundle.id)
+    .bind(now)
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+
+    for (item, qty) in &parts {
+        let tax_rate = effective_tax_rate_db(pool, item).await?;
+        // Units of one component normally get the same share; the rounding
+        // cent can make one differ, which then gets a line of its own.
+        let item_shares: Vec<Money> = shares.by_ref().take(*qty as usize).collect();
+        for group in item_shares.chunk_by(|a, b| a == b) {
+            let quantity = Quantity::units(group.len() as i64);
+            sqlx::query(
+                "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price,
+                 tax_rate, bundle_id, is_deposit, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(transaction_id)
+            .bind(item.id)
+            .bind(quantity)
+            .bind(group[0])
+            .bind(group[0] * quantity)
+            .bind(tax_rate)
+            .bind(bundle_line_id)
+            .bind(item.is_deposit)
+            .bind(now)
+            .execute(pool)
+            .await
+            .map_err(db_err)?;
+        }
+    }
+
+    update_transaction_total_db(pool, transaction_id).await
+}
+
+/// See [`add_bundle_db`].
+#[server]
+pub async fn add_bundle_to_transaction(
+    transaction_id: Uuid,
+    item_id: Uuid,
+    choices: Vec<Uuid>,
+) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let bundle = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+        .bind(item_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Item not found"))?;
+    add_bundle_db(&pool, transaction_id, &bundle, &choices).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+// ---- Item Server Functions ----
+
+/// Trims a SKU, treating a blank one as none. SKUs are what the barcode
+/// scanner looks items up by, so no two items may share one.
+#[cfg(feature = "ssr")]
+async fn check_sku_db(
+    pool: &sqlx::SqlitePool,
+    sku: Option<String>,
+    item_id: Uuid,
+) -> Result<Option<String>, ServerFnError> {
+    let Some(sku) = sku.map(|s| s.trim().to_string()).filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    let taken_by: Option<String> = sqlx::query_scalar("SELECT name FROM items WHERE sku = ? AND id != ?")
+        .bind(&sku)
+        .bind(item_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)?;
+    if let Some(name) = taken_by {
+        return Err(not_found(&format!("SKU {} is already used by {}", sku, name)));
+    }
+    Ok(Some(sku))
+}
+
+#[cfg(feature = "ssr")]
+fn check_price_unit(price_unit: String) -> Result<String, ServerFnError> {
+    if !PRICE_UNITS.contains(&price_unit.as_str()) {
+        return Err(not_found(&format!("Unknown price unit: {}", price_unit)));
+    }
+    Ok(price_unit)
+}
+
+/// Checks an item's deposit settings: the linked item must be a deposit
+/// item, deposit items carry no deposit of their own, and an item stays a
+/// deposit item while other items are linked to it.
+#[cfg(feature = "ssr")]
+async fn check_deposit_db(
+    pool: &sqlx::SqlitePool,
+    item_id: Uuid,
+    is_deposit: bool,
+    deposit_item_id: Option<Uuid>,
+) -> Result<(), ServerFnError> {
+    if let Some(deposit_id) = deposit_item_id {
+        if is_deposit {
+            return Err(not_found("A deposit item cannot have a deposit of its own"));
+        }
+        let linked_is_deposit: bool = sqlx::query_scalar("SELECT is_deposit FROM items WHERE id = ?")
+            .bind(deposit_id)
+            .fetch_optional(pool)
+            .await
+            .map_err(db_err)?
+            .ok_or_else(|| not_found("Deposit item not found"))?;
+        if !linked_is_deposit || deposit_id == item_id {
+            return Err(not_found("Pick a deposit item as the deposit"));
+        }
+    }
+    if !is_deposit {
+        let linked: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM items WHERE deposit_item_id = ?")
+            .bind(item_id)
+            .fetch_one(pool)
+            .await
+            .map_err(db_err)?;
+        if linked > 0 {
+            return Err(not_found(&format!("{} items use this item as their deposit", linked)));
+        }
+    }
+    Ok(())
+}
+
+#[server]
+pub async fn fetch_items() -> Result<Vec<Item>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let items = sqlx::query_as::<_, Item>("SELECT * FROM items ORDER BY name")
+        .fetch_all(&pool)
+        .await
+        .map_err(db_err)?;
+    Ok(items)
+}
+
+#[server]
+pub async fn create_item(
+    name: String,
+    description: Option<String>,
+    price: Money,
+    category_id: Uuid,
+    sku: Option<String>,
+    in_stock: Option<bool>,
+    stock_quantity: Option<i32>,
+    kitchen_item: Option<bool>,
+    tax_rate_id: Option<Uuid>,
+    price_unit: Option<String>,
+    is_deposit: Option<bool>,
+    deposit_item_id: Option<Uuid>,
+) -> Result<Item, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let id = Uuid::new_v4();
+    let sku = check_sku_db(&pool, sku, id).await?;
+    let price_unit = check_price_unit(price_unit.unwrap_or_else(|| "each".to_string()))?;
+    let is_deposit = is_deposit.unwrap_or(false);
+    check_deposit_db(&pool, id, is_deposit, deposit_item_id).await?;
+    let now = Utc::now();
+    let in_stock = in_stock.unwrap_or(true);
+    let kitchen_item = kitchen_item.unwrap_or(false);
+    let item = sqlx::query_as::<_, Item>(
+        "INSERT INTO items (id, name, description, price, category_id, sku, in_stock, stock_quantity, kitchen_item, tax_rate_id, price_unit,
+         is_deposit, deposit_item_id, created_at, updated_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(id)
+    .bind(&name)
+    .bind(&description)
+    .bind(price)
+    .bind(category_id)
+    .bind(&sku)
+    .bind(in_stock)
+    .bind(stock_quantity)
+    .bind(kitchen_item)
+    .bind(tax_rate_id)
+    .bind(&price_unit)
+    .bind(is_deposit)
+    .bind(deposit_item_id)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    record_price_db(&pool, id, item.price, now).await?;
+    Ok(item)
+}
+
+/// Updates an item. Unlike the other fields, `tax_rate_id` and
+/// `deposit_item_id` are always applied: `None` makes the item inherit its
+/// category's rate again, or takes its deposit off.
+#[server]
+pub async fn update_item(
+    id: Uuid,
+    name: Option<String>,
+    description: Option<String>,
+    price: Option<Money>,
+    category_id: Option<Uuid>,
+    sku: Option<String>,
+    in_stock: Option<bool>,
+    stock_quantity: Option<i32>,
+    track_stock: Option<bool>,
+    kitchen_item: Option<bool>,
+    tax_rate_id: Option<Uuid>,
+    price_unit: Option<String>,
+    is_deposit: Option<bool>,
+    deposit_item_id: Option<Uuid>,
+) -> Result<Item, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let mut item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+        .bind(id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Item not found"))?;
+
+    if let Some(n) = name { item.name = n; }
+    if let Some(d) = description { item.description = Some(d); }
+    if let Some(p) = price { item.price = p; }
+    if let Some(c) = category_id { item.category_id = c; }
+    if let Some(s) = sku { item.sku = Some(s); }
+    item.sku = check_sku_db(&pool, item.sku.take(), id).await?;
+    if let Some(s) = in_stock { item.in_stock = s; }
+    if let Some(k) = kitchen_item { item.kitchen_item = k; }
+    if let Some(u) = price_unit { item.price_unit = check_price_unit(u)?; }
+    item.tax_rate_id = tax_rate_id;
+    if let Some(d) = is_deposit { item.is_deposit = d; }
+    item.deposit_item_id = deposit_item_id;
+    check_deposit_db(&pool, id, item.is_deposit, item.deposit_item_id).await?;
+    // track_stock=Some(false) means "endless" -> set stock_quantity to None
+    if let Some(track) = track_stock {
+        if track {
+            if let Some(qty) = stock_quantity { item.stock_quantity = Some(qty); }
+        } else {
+            item.stock_quantity = None;
+        }
+    } else if let Some(qty) = stock_quantity {
+        item.stock_quantity = Some(qty);
+    }
+    item.updated_at = Utc::now();
+
+    let updated = sqlx::query_as::<_, Item>(
+        "UPDATE items SET name = ?, description = ?, price = ?, category_id = ?,
+         sku = ?, in_stock = ?, stock_quantity = ?, kitchen_item = ?, tax_rate_id = ?, price_unit = ?,
+         is_deposit = ?, deposit_item_id = ?, updated_at = ? WHERE id = ? RETURNING *",
+    )
+    .bind(&item.name)
+    .bind(&item.description)
+    .bind(item.price)
+    .bind(item.category_id)
+    .bind(&item.sku)
+    .bind(item.in_stock)
+    .bind(item.stock_quantity)
+    .bind(item.kitchen_item)
+    .bind(item.tax_rate_id)
+    .bind(&item.price_unit)
+    .bind(item.is_deposit)
+    .bind(item.deposit_item_id)
+    .bind(item.updated_at)
+    .bind(id)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    // Open sales keep the price their lines were rung up at; see
+    // `reprice_open_transactions`.
+    record_price_db(&pool, id, updated.price, updated.updated_at).await?;
+    Ok(updated)
+}
+
+#[server]
+pub async fn delete_item(id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let result = sqlx::query("DELETE FROM items WHERE id = ?")
+        .bind(id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Item not found"));
+    }
+    Ok(())
+}
+
+// ---- Item Image Server Functions ----
+
+#[server]
+pub async fn upload_item_image(
+    item_id: Uuid,
+    image_data: String,
+) -> Result<String, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+
+    sqlx::query("SELECT id FROM items WHERE id = ?")
+        .bind(item_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Item not found"))?;
+
+    let (mime, b64) = image_data
+        .strip_prefix("data:")
+        .and_then(|s| s.split_once(','))
+        .ok_or_else(|| not_found("Invalid image data"))?;
+
+    let ext = if mime.starts_with("image/png") { "png" }
+        else if mime.starts_with("image/jpeg") { "jpg" }
+        else if mime.starts_with("image/webp") { "webp" }
+        else { "png" };
+
+    use std::io::Write;
+    let bytes = base64_decode(b64).map_err(|e| db_err(e))?;
+
+    let dir = "data/item_images";
+    std::fs::create_dir_all(dir).map_err(|e| db_err(e))?;
+
+    let filename = format!("{}.{}", item_id, ext);
+    let filepath = format!("{}/{}", dir, filename);
+
+    let mut file = std::fs::File::create(&filepath).map_err(|e| db_err(e))?;
+    file.write_all(&bytes).map_err(|e| db_err(e))?;
+
+    let url_path = format!("/item_images/{}", filename);
+
+    sqlx::query("UPDATE items SET image_path = ?, updated_at = ? WHERE id = ?")
+        .bind(&url_path)
+        .bind(Utc::now())
+        .bind(item_id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+
+    Ok(url_path)
+}
+
+#[server]
+pub async fn remove_item_image(item_id: Uuid) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+
+    let image_path: Option<String> =
+        sqlx::query_scalar("SELECT image_path FROM items WHERE id = ?")
+            .bind(item_id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(db_err)?
+            .flatten();
+
+    if let Some(ref url_path) = image_path {
+        let fs_path = format!("data{}", url_path);
+        let _ = std::fs::remove_file(&fs_path);
+    }
+
+    sqlx::query("UPDATE items SET image_path = NULL, updated_at = ? WHERE id = ?")
+        .bind(Utc::now())
+        .bind(item_id)
+        .execute(&pool)
+        .await
+        .map_err(db_err)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "ssr")]
+fn base64_decode(input: &str) -> Result<Vec<u8>, String> {
+    use std::collections::HashMap;
+    let table: HashMap<u8, u8> = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (c, i as u8))
+        .collect();
+
+    let input: Vec<u8> = input.bytes().filter(|&b| b != b'\n' && b != b'\r' && b != b' ').collect();
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+
+    for chunk in input.chunks(4) {
+        let mut buf = [0u8; 4];
+        let mut len = 0;
+        for (i, &b) in chunk.iter().enumerate() {
+            if b == b'=' { break; }
+            buf[i] = *table.get(&b).ok_or_else(|| format!("Invalid base64 char: {}", b as char))?;
+            len = i + 1;
+        }
+        if len >= 2 { out.push((buf[0] << 2) | (buf[1] >> 4)); }
+        if len >= 3 { out.push((buf[1] << 4) | (buf[2] >> 2)); }
+        if len >= 4 { out.push((buf[2] << 6) | buf[3]); }
+    }
+    Ok(out)
+}
+
+// ---- Transaction Server Functions ----
+
+#[server]
+pub async fn fetch_all_transactions() -> Result<Vec<Transaction>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let transactions = sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions ORDER BY created_at DESC",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(transactions)
+}
+
+#[server]
+pub async fn fetch_open_transactions() -> Result<Vec<Transaction>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let transactions = sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions WHERE status = 'open' ORDER BY created_at DESC",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(transactions)
+}
+
+#[server]
+pub async fn fetch_transaction_details(
+    id: Uuid,
+) -> Result<TransactionDetailsResponse, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let transaction =
+        sqlx::query_as::<_, Transaction>("SELECT * FROM transactions WHERE id = ?")
+            .bind(id)
+            .fetch_optional(&pool)
+            .await
+            .map_err(db_err)?
+            .ok_or_else(|| not_found("Transaction not found"))?;
+
+    let items = transaction_lines_db(&mut *pool.acquire().await.map_err(db_err)?, id).await?;
+
+    let payments = sqlx::query_as::<_, Payment>(
+        "SELECT * FROM payments WHERE transaction_id = ? ORDER BY created_at",
+    )
+    .bind(id)
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
+
+    let lines: Vec<(Option<f64>, Money)> =
+        items.iter().map(|it| (it.tax_rate, it.net_total())).collect();
+    let taxes = tax_breakdown(&lines, transaction.tax_inclusive);
+    let gift_card_loads = gift_card_loads_db(&pool, id).await?;
+    let customer = match transaction.customer_id {
+        Some(customer_id) => customer_db(&pool, customer_id).await.ok(),
+        None => None,
+    };
+
+    Ok(TransactionDetailsResponse { transaction, items, payments, taxes, gift_card_loads, customer })
+}
+
+/// Starts a new open sale, optionally seated at a table of the floor plan.
+#[server]
+pub async fn create_transaction(
+    customer_name: Option<String>,
+    table_id: Option<Uuid>,
+) -> Result<Transaction, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let id = Uuid::new_v4();
+    let now = Utc::now();
+    let tax_inclusive = read_tax_inclusive(&pool).await;
+    let transaction = sqlx::query_as::<_, Transaction>(
+        "INSERT INTO transactions (id, customer_name, status, total, tax_inclusive, table_id, created_at, updated_at)
+         VALUES (?, ?, 'open', 0, ?, ?, ?, ?) RETURNING *",
+    )
+    .bind(id)
+    .bind(&customer_name)
+    .bind(tax_inclusive)
+    .bind(table_id)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+
+    // Notify other sale clients about the new open transaction
+    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
+        let _ = sb.0.send(format!("update:{}", id));
+    }
+
+    Ok(transaction)
+}
+
+#[server]
+pub async fn update_transaction_details(
+    id: Uuid,
+    customer_name: Option<String>,
+    customer_group_id: Option<Uuid>,
+) -> Result<Transaction, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let current = sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions WHERE id = ? AND status = 'open'",
+    )
+    .bind(id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+
+    sqlx::query(
+        "UPDATE transactions SET customer_name = ?, customer_group_id = ?, updated_at = ? WHERE id = ?",
+    )
+    .bind(&customer_name)
+    .bind(customer_group_id)
+    .bind(Utc::now())
+    .bind(id)
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    // Group-only promotions may now apply, or no longer.
+    if current.customer_group_id != customer_group_id {
+        update_transaction_total_db(&pool, id).await?;
+        broadcast_lines_changed(id);
+    }
+
+    let updated = sqlx::query_as::<_, Transaction>("SELECT * FROM transactions WHERE id = ?")
+        .bind(id)
+        .fetch_one(&pool)
+        .await
+        .map_err(db_err)?;
+    Ok(updated)
+}
+
+/// Adds `quantity` units of an item with the given modifiers to an open sale
+/// (or takes them off, for a negative quantity). Units with the same
+/// modifiers are kept on one line; different modifiers get their own line.
+#[cfg(feature = "ssr")]
+async fn add_item_db(
+    pool: &sqlx::SqlitePool,
+    transaction_id: Uuid,
+    item_id: Uuid,
+    quantity: i32,
+    modifier_ids: Vec<Uuid>,
+) -> Result<(), ServerFnError> {
+    sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions WHERE id = ? AND status = 'open'",
+    )
+    .bind(transaction_id)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+
+    let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+        .bind(item_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Item not found"))?;
+
+    if item.is_weighed() {
+        return Err(not_found(&format!("{} is sold by weight", item.name)));
+    }
+    if !bundle_components_db(pool, item.id).await?.is_empty() {
+        return Err(not_found(&format!("{} is a bundle", item.name)));
+    }
+    let quantity = Quantity::units(quantity as i64);
+    check_stock_db(pool, transaction_id, &item, quantity).await?;
+
+    let all_groups = modifier_groups_db(pool).await?;
+    let groups: Vec<&ModifierGroupDetail> =
+        all_groups.iter().filter(|g| g.applies_to(&item)).collect();
+    check_modifier_picks(&groups, &modifier_ids).map_err(|e| not_found(&e))?;
+    let mut chosen: Vec<(&ModifierGroupDetail, &Modifier)> = groups
+        .iter()
+        .flat_map(|g| g.modifiers.iter().map(move |m| (*g, m)))
+        .filter(|(_, m)| modifier_ids.contains(&m.id))
+        .collect();
+    chosen.sort_by_key(|(_, m)| m.id);
+    let modifier_key = chosen
+        .iter()
+        .map(|(_, m)| m.id.simple().to_string())
+        .collect::<Vec<_>>()
+        .join(",");
+    let unit_price = item.price + chosen.iter().map(|(_, m)| m.price_delta).sum::<Money>();
+
+    // Only merge into a line with the same modifiers and no note, so noted
+    // lines ("no onions") stay separate. Lines keep the price they were rung
+    // up at, so after a price change new units start a line of their own;
+    // units taken off come from the newest line. Deposits charged with an
+    // item and deposit returns are lines of their own.
+    let existing = sqlx::query_as::<_, (Uuid, Quantity, Money)>(
+        "SELECT id, quantity, unit_price FROM transaction_items
+         WHERE transaction_id = ? AND item_id = ? AND modifier_key = ? AND note IS NULL
+           AND NOT price_embedded AND unit IS NULL AND NOT is_bundle AND bundle_id IS NULL
+           AND deposit_for IS NULL AND quantity > 0 AND (? OR unit_price = ?)
+         ORDER BY created_at DESC LIMIT 1",
+    )
+    .bind(transaction_id)
+    .bind(item_id)
+    .bind(&modifier_key)
+    .bind(quantity.is_negative())
+    .bind(unit_price)
+    .fetch_optional(pool)
+    .await
+    .map_err(db_err)?;
+
+    let new_quantity = existing.map(|(_, q, _)| q).unwrap_or_default() + quantity;
+    let tax_rate = effective_tax_rate_db(pool, &item).await?;
+
+    match existing {
+        Some((line_id, _, _)) if !new_quantity.is_positive() => {
+            sqlx::query("DELETE FROM transaction_items WHERE id = ?")
+                .bind(line_id)
+                .execute(pool)
+                .await
+                .map_err(db_err)?;
+        }
+        Some((line_id, _, line_price)) => {
+            sqlx::query("UPDATE transaction_items SET quantity = ?, total_price = ? WHERE id = ?")
+                .bind(new_quantity)
+                .bind(line_price * new_quantity)
+                .bind(line_id)
+                .execute(pool)
+                .await
+                .map_err(db_err)?;
+        }
+        None if new_quantity.is_positive() => {
+            let id = Uuid::new_v4();
+            let total_price = unit_price * new_quantity;
+            let now = Utc::now();
+            sqlx::query(
+                "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price,
+                 tax_rate, modifier_key, is_deposit, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(id)
+            .bind(transaction_id)
+            .bind(item_id)
+            .bind(new_quantity)
+            .bind(unit_price)
+            .bind(total_price)
+            .bind(tax_rate)
+            .bind(&modifier_key)
+            .bind(item.is_deposit)
+            .bind(now)
+            .execute(pool)
+            .await
+            .map_err(db_err)?;
+
+            for (group, modifier) in &chosen {
+                sqlx::query(
+                    "INSERT INTO transaction_item_modifiers (id, transaction_item_id, modifier_id, group_name, name, price_delta)
+                     VALUES (?, ?, ?, ?, ?, ?)",
+                )
+                .bind(Uuid::new_v4())
+                .bind(id)
+                .bind(modifier.id)
+                .bind(&group.group.name)
+                .bind(&modifier.name)
+                .bind(modifier.price_delta)
+                .execute(pool)
+                .await
+                .map_err(db_err)?;
+            }
+        }
+        None => {}
+    }
+
+    update_transaction_total_db(pool, transaction_id).await
+}
+
+/// Checks that `quantity` more of `item` can be sold in an open sale, counting
+/// all of the sale's lines of the item against its tracked stock. Stock is
+/// counted in pieces, so goods sold by weight only have to be in stock.
+#[cfg(feature = "ssr")]
+async fn check_stock_db(
+    pool: &sqlx::SqlitePool,
+    transaction_id: Uuid,
+    item: &Item,
+    quantity: Quantity,
+) -> Result<(), ServerFnError> {
+    if !item.in_stock {
+        return Err(not_found("Item is out of stock"));
+    }
+    if let Some(stock_qty) = item.stock_quantity.filter(|_| !item.is_weighed()) {
+        let existing_in_transaction = sqlx::query_scalar::<_, Quantity>(
+            "SELECT COALESCE(SUM(quantity), 0) FROM transaction_items
+             WHERE transaction_id = ? AND item_id = ? AND unit IS NULL",
+        )
+        .bind(transaction_id)
+        .bind(item.id)
+        .fetch_one(pool)
+        .await
+        .map_err(db_err)?;
+
+        let stock = Quantity::units(stock_qty as i64);
+        if existing_in_transaction + quantity > stock {
+            return Err(not_found(&format!("Only {} left in stock", stock - existing_in_transaction)));
+        }
+    }
+    Ok(())
+}
+
+/// Adds a line of `item` at a price of its own, which is never merged with
+/// others: `weight` kg of goods sold by weight at `unit_price` per kg, or
+/// without a weight a single unit at a price read from an in-store barcode.
+#[cfg(feature = "ssr")]
+async fn add_priced_line_db(
+    pool: &sqlx::SqlitePool,
+    transaction_id: Uuid,
+    item: &Item,
+    unit_price: Money,
+    weight: Option<Quantity>,
+) -> Result<(), ServerFnError> {
+    sqlx::query("SELECT id FROM transactions WHERE id = ? AND status = 'open'")
+        .bind(transaction_id)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Transaction not found or not open"))?;
+    let quantity = weight.unwrap_or(Quantity::ONE);
+    check_stock_db(pool, transaction_id, item, quantity).await?;
+
+    let tax_rate = effective_tax_rate_db(pool, item).await?;
+    sqlx::query(
+        "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price,
+         tax_rate, unit, price_embedded, is_deposit, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(transaction_id)
+    .bind(item.id)
+    .bind(quantity)
+    .bind(unit_price)
+    .bind(unit_price * quantity)
+    .bind(tax_rate)
+    .bind(weight.map(|_| "kg"))
+    .bind(weight.is_none())
+    .bind(item.is_deposit)
+    .bind(Utc::now())
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+
+    update_transaction_total_db(pool, transaction_id).await
+}
+
+/// Adds `weight_kg` of an item sold by weight to an open sale, priced at the
+/// item's current price per kg or 100 g.
+#[server]
+pub async fn add_weighed_item(
+    transaction_id: Uuid,
+    item_id: Uuid,
+    weight_kg: f64,
+) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+        .bind(item_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Item not found"))?;
+    if !item.is_weighed() {
+        return Err(not_found(&format!("{} is not sold by weight", item.name)));
+    }
+    let weight = Quantity::from_decimal(weight_kg);
+    if !(weight.is_positive() && weight < Quantity::units(1000)) {
+        return Err(not_found("Weight must be more than 0 kg"));
+    }
+    let price = kg_price(item.price, &item.price_unit);
+    add_priced_line_db(&pool, transaction_id, &item, price, Some(weight)).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+/// Notifies the customer display and the other sale clients that the lines
+/// of an open sale changed.
+#[cfg(feature = "ssr")]
+fn broadcast_lines_changed(transaction_id: Uuid) {
+    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
+        let _ = tx.send(format!("update:{}", transaction_id));
+    }
+    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
+        let _ = sb.0.send(format!("update:{}", transaction_id));
+    }
+}
+
+/// See [`add_item_db`].
+#[server]
+pub async fn add_item_to_transaction(
+    transaction_id: Uuid,
+    item_id: Uuid,
+    quantity: i32,
+    modifier_ids: Vec<Uuid>,
+) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    add_item_db(&pool, transaction_id, item_id, quantity, modifier_ids).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+/// Looks up an item by its SKU/EAN, as read by a barcode scanner.
+#[cfg(feature = "ssr")]
+async fn item_by_code_db(pool: &sqlx::SqlitePool, code: &str) -> Result<Item, ServerFnError> {
+    let code = code.trim();
+    if code.is_empty() {
+        return Err(not_found("Empty barcode"));
+    }
+    sqlx::query_as::<_, Item>("SELECT * FROM items WHERE sku = ?")
+        .bind(code)
+        .fetch_optional(pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found(&format!("Unknown barcode: {}", code)))
+}
+
+/// Adds one unit of the item with the scanned SKU/EAN to an open sale.
+/// Items with modifier groups are only looked up (`added` is false) so the
+/// sale screen can ask for the options first, as are items sold by weight
+/// so it can ask for the weight.
+///
+/// In-store EAN-13s (see [`InStoreCode`]) that match no SKU are looked up by
+/// their item part and added with the weight or price they carry.
+#[server]
+pub async fn scan_item_code(transaction_id: Uuid, code: String) -> Result<ScanResult, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let item = match item_by_code_db(&pool, &code).await {
+        Ok(item) => item,
+        Err(e) => {
+            let Some(in_store) = InStoreCode::decode(code.trim()) else { return Err(e) };
+            let item = item_by_code_db(&pool, &in_store.item_code).await?;
+            if item.is_weighed() {
+                // The code carries the weight in grams.
+                let weight = Quantity(in_store.value.into());
+                let price = kg_price(item.price, &item.price_unit);
+                add_priced_line_db(&pool, transaction_id, &item, price, Some(weight)).await?;
+            } else {
+                add_priced_line_db(&pool, transaction_id, &item, Money(in_store.value.into()), None).await?;
+            }
+            broadcast_lines_changed(transaction_id);
+            return Ok(ScanResult { item, added: true });
+        }
+    };
+    if item.is_weighed() {
+        return Ok(ScanResult { item, added: false });
+    }
+    let components = bundle_components_db(&pool, item.id).await?;
+    if !components.is_empty() {
+        if components.iter().any(|c| c.category_id.is_some()) {
+            return Ok(ScanResult { item, added: false });
+        }
+        add_bundle_db(&pool, transaction_id, &item, &[]).await?;
+        broadcast_lines_changed(transaction_id);
+        return Ok(ScanResult { item, added: true });
+    }
+    let has_modifiers = modifier_groups_db(&pool).await?.iter().any(|g| g.applies_to(&item));
+    if has_modifiers {
+        return Ok(ScanResult { item, added: false });
+    }
+    add_item_db(&pool, transaction_id, item.id, 1, vec![]).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(ScanResult { item, added: true })
+}
+
+/// Adds `quantity` returned units of a deposit item to an open sale: a
+/// line with a negative quantity that pays the deposit back. Returns of the
+/// same deposit at the same price share a line.
+#[server]
+pub async fn return_deposit(transaction_id: Uuid, item_id: Uuid, quantity: i32) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    open_transaction_db(&pool, transaction_id).await?;
+    if !(1..=999).contains(&quantity) {
+        return Err(not_found("Return between 1 and 999 units"));
+    }
+    let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
+        .bind(item_id)
+        .fetch_optional(&pool)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Item not found"))?;
+    if !item.is_deposit {
+        return Err(not_found(&format!("{} is not a deposit", item.name)));
+    }
+    let quantity = Quantity::units(quantity as i64);
+
+    let existing = sqlx::query_as::<_, (Uuid, Quantity)>(
+        "SELECT id, quantity FROM transaction_items
+         WHERE transaction_id = ? AND item_id = ? AND is_deposit AND deposit_for IS NULL
+           AND quantity < 0 AND unit_price = ?",
+    )
+    .bind(transaction_id)
+    .bind(item_id)
+    .bind(item.price)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?;
+    match existing {
+        Some((line_id, returned)) => {
+            let new_qty = returned - quantity;
+            sqlx::query("UPDATE transaction_items SET quantity = ?, total_price = ? WHERE id = ?")
+                .bind(new_qty)
+                .bind(item.price * new_qty)
+                .bind(line_id)
+                .execute(&pool)
+                .await
+                .map_err(db_err)?;
+        }
+        None => {
+            let tax_rate = effective_tax_rate_db(&pool, &item).await?;
+            sqlx::query(
+                "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price,
+                 tax_rate, is_deposit, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, 1, ?)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(transaction_id)
+            .bind(item_id)
+            .bind(-quantity)
+            .bind(item.price)
+            .bind(-item.price * quantity)
+            .bind(tax_rate)
+            .bind(Utc::now())
+            .execute(&pool)
+            .await
+            .map_err(db_err)?;
+        }
+    }
+
+    update_transaction_total_db(&pool, transaction_id).await?;
+    broadcast_lines_changed(transaction_id);
+    Ok(())
+}
+
+/// Takes one unit off a line of an open sale, removing the line at zero.
+/// For a deposit return, one returned unit is taken off. A weighed line is
+/// removed whole.
+#[server]
+pub async fn remove_item_from_transaction(
+    transaction_id: Uuid,
+    transaction_item_id: Uuid,
+) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+
+    sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions WHERE id = ? AND status = 'open'",
     )
-    .fetch_all(&pool)
+    .bind(transaction_id)
+    .fetch_optional(&pool)
     .await
-    .map_err(db_err)?;
-
-    let currency: String = sqlx::query_scalar("SELECT value FROM config WHERE key = 'currency'")
-        .fetch_optional(&pool)
-        .await
-        .ok()
-        .flatten()
-        .unwrap_or_default();
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Transaction not found or not open"))?;
 
-    let mut sections: Vec<MenuSection> = Vec::new();
-    for category in &categories {
-        let items = sqlx::query_as::<_, Item>(
-            "SELECT * FROM items WHERE category_id = ? AND in_stock = 1 ORDER BY name",
-        )
-        .bind(category.id)
-        .fetch_all(&pool)
-        .await
-        .map_err(db_err)?;
+    let line = sqlx::query_as::<_, (Quantity, Money, Option<Uuid>, Option<Uuid>, Option<String>)>(
+        "SELECT quantity, unit_price, bundle_id, deposit_for, unit FROM transaction_items WHERE id = ? AND transaction_id = ?",
+    )
+    .bind(transaction_item_id)
+    .bind(transaction_id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?;
+    if matches!(line, Some((_, _, Some(_), _, _))) {
+        return Err(not_found("Remove the whole bundle"));
+    }
+    if matches!(line, Some((_, _, _, Some(_), _))) {
+        return Err(not_found("The deposit goes with its item"));
+    }
 
-        if items.is_empty() {
-            continue;
+    // Bundle lines have a quantity of 1; deleting one takes its components
+    // with it, and deleting a line takes its deposit.
+    if let Some((qty, unit_price, _, _, unit)) = line {
+        if unit.is_none() && qty.0.abs() > Quantity::ONE.0 {
+            // The unit price already includes the line's modifiers.
+            let new_qty = qty - Quantity::units(qty.0.signum());
+            sqlx::query("UPDATE transaction_items SET quantity = ?, total_price = ? WHERE id = ?")
+                .bind(new_qty)
+                .bind(unit_price * new_qty)
+                .bind(transaction_item_id)
+                .execute(&pool)
+                .await
+                .map_err(db_err)?;
+        } else {
+            sqlx::query("DELETE FROM transaction_items WHERE id = ?")
+                .bind(transaction_item_id)
+                .execute(&pool)
+                .await
+                .map_err(db_err)?;
         }
-
-        sections.push(MenuSection {
-            name: category.name.clone(),
-            main_course: category.main_course,
-            items: items
-                .into_iter()
-                .map(|i| MenuItem {
-                    name: i.name,
-                    price: i.price,
-                    description: i.description,
-                    // `image_path` is a web URL ("/item_images/..."); map it to
-                    // the on-disk path served from the data directory.
-                    image_path: i.image_path.map(|p| format!("data{}", p)),
-                })
-                .collect(),
-        });
     }
 
-    // Prefer the site logo (bundled into the `site` root as /logo_site.png),
-    // falling back to the receipt logo if it isn't present.
-    let logo_path = if std::path::Path::new("site/logo_site.png").exists() {
-        "site/logo_site.png"
-    } else {
-        "data/logo_receipt.png"
-    };
+    update_transaction_total_db(&pool, transaction_id).await?;
 
-    let pdf_bytes = tokio::task::spawn_blocking(move || {
-        build_menu_pdf(&title, &currency, logo_path, &sections)
-    })
-    .await
-    .map_err(|e| ServerFnError::new(e.to_string()))?
-    .map_err(ServerFnError::new)?;
+    // Notify customer display
+    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
+        let _ = tx.send(format!("update:{}", transaction_id));
+    }
+    // Notify other sale clients
+    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
+        let _ = sb.0.send(format!("update:{}", transaction_id));
+    }
 
-    Ok(base64::engine::general_purpose::STANDARD.encode(&pdf_bytes))
+    Ok(())
 }
 
-// ---- Item Server Functions ----
-
+/// Sets or clears the kitchen note of a line of an open sale. Blank notes
+/// are stored as no note.
 #[server]
-pub async fn fetch_items() -> Result<Vec<Item>, ServerFnError> {
+pub async fn set_line_note(
+    transaction_id: Uuid,
+    transaction_item_id: Uuid,
+    note: Option<String>,
+) -> Result<(), ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    let items = sqlx::query_as::<_, Item>("SELECT * FROM items ORDER BY name")
-        .fetch_all(&pool)
-        .await
-        .map_err(db_err)?;
-    Ok(items)
+
+    let note = note.map(|n| n.trim().to_string()).filter(|n| !n.is_empty());
+    if note.as_ref().is_some_and(|n| n.chars().count() > 200) {
+        return Err(not_found("Note must be at most 200 characters"));
+    }
+
+    let result = sqlx::query(
+        "UPDATE transaction_items SET note = ?
+         WHERE id = ? AND transaction_id = ?
+           AND transaction_id IN (SELECT id FROM transactions WHERE status = 'open')",
+    )
+    .bind(&note)
+    .bind(transaction_item_id)
+    .bind(transaction_id)
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    if result.rows_affected() == 0 {
+        return Err(not_found("Line not found or sale not open"));
+    }
+
+    // Notify customer display
+    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
+        let _ = tx.send(format!("update:{}", transaction_id));
+    }
+    // Notify other sale clients
+    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
+        let _ = sb.0.send(format!("update:{}", transaction_id));
+    }
+
+    Ok(())
 }
 
+/// Brings the lines of all open sales up to the items' current prices.
+/// Lines otherwise keep the price they were rung up at. The modifier price
+/// changes recorded on each line are kept, weighed lines get the current
+/// price of a kg, and lines priced by an in-store barcode or sold in a bundle
+/// are left alone. Returns the number of lines whose price changed.
 #[server]
-pub async fn create_item(
-    name: String,
-    description: Option<String>,
-    price: f64,
-    category_id: Uuid,
-    sku: Option<String>,
-    in_stock: Option<bool>,
-    stock_quantity: Option<i32>,
-    kitchen_item: Option<bool>,
-) -> Result<Item, ServerFnError> {
+pub async fn reprice_open_transactions() -> Result<u64, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-    let in_stock = in_stock.unwrap_or(true);
-    let kitchen_item = kitchen_item.unwrap_or(false);
-    let item = sqlx::query_as::<_, Item>(
-        "INSERT INTO items (id, name, description, price, category_id, sku, in_stock, stock_quantity, kitchen_item, created_at, updated_at)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+    require_admin(&pool).await?;
+
+    let lines = sqlx::query_as::<_, (Uuid, Uuid, Quantity, Money, Money, Option<String>, Money, String)>(
+        "SELECT ti.id, ti.transaction_id, ti.quantity, ti.unit_price,
+                i.price + COALESCE((SELECT SUM(m.price_delta) FROM transaction_item_modifiers m
+                                    WHERE m.transaction_item_id = ti.id), 0),
+                ti.unit, i.price, i.price_unit
+         FROM transaction_items ti
+         JOIN items i ON ti.item_id = i.id
+         JOIN transactions t ON ti.transaction_id = t.id
+         WHERE t.status = 'open' AND NOT ti.price_embedded AND NOT ti.is_bundle AND ti.bundle_id IS NULL",
     )
-    .bind(id)
-    .bind(&name)
-    .bind(&description)
-    .bind(price)
-    .bind(category_id)
-    .bind(&sku)
-    .bind(in_stock)
-    .bind(stock_quantity)
-    .bind(kitchen_item)
-    .bind(now)
-    .bind(now)
-    .fetch_one(&pool)
+    .fetch_all(&pool)
     .await
     .map_err(db_err)?;
-    Ok(item)
+
+    let mut repriced = 0;
+    let mut touched: Vec<Uuid> = Vec::new();
+    for (line_id, transaction_id, quantity, unit_price, current_price, unit, list_price, price_unit) in lines {
+        let current_price = match unit {
+            // Items no longer sold by weight have no price per kg.
+            Some(_) if price_unit == "each" => continue,
+            Some(_) => kg_price(list_price, &price_unit),
+            // Nor have items now sold by weight a price per piece.
+            None if price_unit != "each" => continue,
+            None => current_price,
+        };
+        if unit_price == current_price {
+            continue;
+        }
+        sqlx::query("UPDATE transaction_items SET unit_price = ?, total_price = ? WHERE id = ?")
+            .bind(current_price)
+            .bind(current_price * quantity)
+            .bind(line_id)
+            .execute(&pool)
+            .await
+            .map_err(db_err)?;
+        repriced += 1;
+        if !touched.contains(&transaction_id) {
+            touched.push(transaction_id);
+        }
+    }
+
+    for transaction_id in touched {
+        update_transaction_total_db(&pool, transaction_id).await?;
+        if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
+            let _ = tx.send(format!("update:{}", transaction_id));
+        }
+        broadcast_sale_update(transaction_id);
+    }
+    Ok(repriced)
 }
 
+/// Closes an open sale, settling it with one or more payments.
+///
+/// `tip` is paid on top of the total and kept out of it. Non-cash tenders may
+/// cover at most the total and the tip; any overpayment must come from the
+/// cash portion, which is the only one change is given from. The cash portion
+/// is rounded by the cash rounding rule, and the difference is recorded on
+/// the sale.
 #[server]
-pub async fn update_item(
+pub async fn close_transaction(
     id: Uuid,
-    name: Option<String>,
-    description: Option<String>,
-    price: Option<f64>,
-    category_id: Option<Uuid>,
-    sku: Option<String>,
-    in_stock: Option<bool>,
-    stock_quantity: Option<i32>,
-    track_stock: Option<bool>,
-    kitchen_item: Option<bool>,
-) -> Result<Item, ServerFnError> {
+    payments: Vec<PaymentInput>,
+    tip: Money,
+) -> Result<CloseTransactionResponse, ServerFnError> {
+    use crate::printer::{find_printer, open_cash_drawer, print_receipt};
+
     let pool = expect_context::<sqlx::SqlitePool>();
-    let mut item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
-        .bind(id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(db_err)?
-        .ok_or_else(|| not_found("Item not found"))?;
+    let user_name = get_authenticated_user(&pool).await?.map(|u| u.username);
+    let (response, job) = close_transaction_db(&pool, id, &payments, tip, user_name.as_deref()).await?;
+
+    // Send to remote printer clients via WebSocket
+    if let Some(printer_tx) = use_context::<tokio::sync::broadcast::Sender<
+        rustpos_common::protocol::PrintReceiptJob,
+    >>() {
+        let _ = printer_tx.send(job.clone());
+    }
 
-    if let Some(n) = name { item.name = n; }
-    if let Some(d) = description { item.description = Some(d); }
-    if let Some(p) = price { item.price = p; }
-    if let Some(c) = category_id { item.category_id = c; }
-    if let Some(s) = sku { item.sku = Some(s); }
-    if let Some(s) = in_stock { item.in_stock = s; }
-    if let Some(k) = kitchen_item { item.kitchen_item = k; }
-    // track_stock=Some(false) means "endless" -> set stock_quantity to None
-    if let Some(track) = track_stock {
-        if track {
-            if let Some(qty) = stock_quantity { item.stock_quantity = Some(qty); }
-        } else {
-            item.stock_quantity = None;
+    // Local print (unless disabled in settings — e.g. printing handled by a
+    // remote client). Even when local printing is disabled, we still open the
+    // cash drawer if a printer is physically connected, so cash sales work
+    // without printing a local receipt.
+    let local_printing_disabled = read_disable_local_printing(&pool).await;
+    let local_now = chrono::Local::now();
+    let _ = tokio::task::spawn_blocking(move || {
+        if let Ok((_, mut printer)) = find_printer() {
+            if local_printing_disabled {
+                let _ = open_cash_drawer(&mut printer);
+            } else {
+                let _ = print_receipt(
+                    &mut printer,
+                    &job,
+                    local_now,
+                    Some("data/logo_receipt.png"),
+                );
+            }
         }
-    } else if let Some(qty) = stock_quantity {
-        item.stock_quantity = Some(qty);
+    })
+    .await;
+
+    // Notify kitchen displays via WebSocket
+    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<()>>() {
+        let _ = tx.send(());
     }
-    item.updated_at = Utc::now();
 
-    let updated = sqlx::query_as::<_, Item>(
-        "UPDATE items SET name = ?, description = ?, price = ?, category_id = ?,
-         sku = ?, in_stock = ?, stock_quantity = ?, kitchen_item = ?, updated_at = ? WHERE id = ? RETURNING *",
-    )
-    .bind(&item.name)
-    .bind(&item.description)
-    .bind(item.price)
-    .bind(item.category_id)
-    .bind(&item.sku)
-    .bind(item.in_stock)
-    .bind(item.stock_quantity)
-    .bind(item.kitchen_item)
-    .bind(item.updated_at)
-    .bind(id)
-    .fetch_one(&pool)
-    .await
-    .map_err(db_err)?;
-    Ok(updated)
+    // Notify customer display — transaction closed, keep showing briefly
+    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
+        let _ = tx.send(format!("closed:{}", id));
+    }
+    // Notify other sale clients
+    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
+        let _ = sb.0.send(format!("closed:{}", id));
+    }
+
+    Ok(response)
 }
 
-#[server]
-pub async fn delete_item(id: Uuid) -> Result<(), ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let result = sqlx::query("DELETE FROM items WHERE id = ?")
-        .bind(id)
-        .execute(&pool)
+/// Closes the sale for [`close_transaction`] and returns its receipt. The sale,
+/// its lines and the gift card balances are read and every write happens in
+/// one write transaction, so no line edit or redemption on another till can
+/// come in between, a sale is closed once even when two tills try at the same
+/// time, and a failure part way leaves it open as it was.
+#[cfg(feature = "ssr")]
+async fn close_transaction_db(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    payments: &[PaymentInput],
+    tip: Money,
+    user_name: Option<&str>,
+) -> Result<(CloseTransactionResponse, rustpos_common::protocol::PrintReceiptJob), ServerFnError> {
+    // Settings are read first: the write transaction holds the database.
+    let cash_rounding = read_cash_rounding(pool).await;
+    let currency = currency_db(pool).await?;
+    let loyalty = read_loyalty_settings(pool).await;
+
+    let mut tx = begin_write_db(pool).await?;
+    let transaction = open_transaction_db(&mut *tx, id).await?;
+
+    // A sale that only pays out deposits needs no payment; the amount is
+    // given back as change.
+    if payments.is_empty() && !transaction.total.is_negative() {
+        return Err(not_found("No payment given"));
+    }
+    if tip.is_negative() {
+        return Err(not_found("The tip cannot be negative"));
+    }
+    if tip.is_positive() && transaction.total.is_negative() {
+        return Err(not_found("A payout cannot take a tip"));
+    }
+    for p in payments {
+        if !TENDERS.contains(&p.tender.as_str()) {
+            return Err(not_found(&format!("Unknown tender type: {}", p.tender)));
+        }
+        if !p.amount.is_positive() {
+            return Err(not_found("Payment amounts must be positive"));
+        }
+    }
+
+    let paid_amount: Money = payments.iter().map(|p| p.amount).sum();
+    let non_cash: Money = payments
+        .iter()
+        .filter(|p| p.tender != "cash")
+        .map(|p| p.amount)
+        .sum();
+    let due = transaction.total + tip;
+    if non_cash > due {
+        return Err(not_found("Non-cash payments cannot exceed the total"));
+    }
+    // What is settled in cash is rounded to the smallest coin. A payout
+    // without payments is made in cash too.
+    let rounding = if payments.is_empty() || payments.iter().any(|p| p.tender == "cash") {
+        let cash_due = due - non_cash;
+        cash_rounding.round(cash_due) - cash_due
+    } else {
+        Money::ZERO
+    };
+    let due = due + rounding;
+    if paid_amount < due {
+        return Err(not_found("Insufficient payment amount"));
+    }
+
+    // What goes on the house account: the customer's, else the group's.
+    let on_account: Money = payments.iter().filter(|p| p.tender == "account").map(|p| p.amount).sum();
+    let account = AccountRef::of(transaction.customer_id, transaction.customer_group_id);
+    if on_account.is_positive() && account.is_none() {
+        return Err(not_found("On-account sales need a customer or a customer group"));
+    }
+
+    // What is taken off each gift card, which must hold that much.
+    let mut redemptions: Vec<(GiftCard, Money)> = Vec::new();
+    for p in payments.iter().filter(|p| p.tender == "gift_card") {
+        let code = p
+            .card_code
+            .as_deref()
+            .and_then(normalize_card_code)
+            .ok_or_else(|| not_found("A gift card payment needs the card's code"))?;
+        let card = gift_card_by_code_db(&mut *tx, &code)
+            .await?
+            .ok_or_else(|| not_found("Unknown gift card"))?;
+        match redemptions.iter_mut().find(|(c, _)| c.id == card.id) {
+            Some((_, amount)) => *amount += p.amount,
+            None => redemptions.push((card, p.amount)),
+        }
+    }
+    for (card, amount) in &redemptions {
+        let balance = gift_card_balance_db(&mut *tx, card.id).await?;
+        if *amount > balance {
+            return Err(not_found(&format!(
+                "Gift card {} only holds {}",
+                mask_card_code(&card.code),
+                balance.format(&currency)
+            )));
+        }
+    }
+
+    let change = (paid_amount - due).max(Money::ZERO);
+    let now = Utc::now();
+    let trans_items = transaction_lines_db(&mut tx, id).await?;
+    let table_name: Option<String> = sqlx::query_scalar("SELECT name FROM floor_tables WHERE id = ?")
+        .bind(transaction.table_id)
+        .fetch_optional(&mut *tx)
         .await
         .map_err(db_err)?;
-    if result.rows_affected() == 0 {
-        return Err(not_found("Item not found"));
-    }
-    Ok(())
-}
+    let loads = gift_card_loads_db(&mut *tx, id).await?;
+    let redeemed_points: i64 = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(loyalty_points), 0) FROM manual_discounts WHERE transaction_id = ?",
+    )
+    .bind(id)
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(db_err)?;
 
-// ---- Item Image Server Functions ----
+    let transaction = sqlx::query_as::<_, Transaction>(
+        "UPDATE transactions SET status = 'closed', paid_amount = ?, change_amount = ?,
+         tip_amount = ?, rounding_amount = ?, closed_by = ?, closed_at = ?, updated_at = ?
+         WHERE id = ? AND status = 'open' RETURNING *",
+    )
+    .bind(paid_amount)
+    .bind(change)
+    .bind(tip)
+    .bind(rounding)
+    .bind(user_name)
+    .bind(now)
+    .bind(now)
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Transaction not found or not open"))?;
 
-#[server]
-pub async fn upload_item_image(
-    item_id: Uuid,
-    image_data: String,
-) -> Result<String, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
+    for ti in &trans_items {
+        // Decrement stock_quantity for tracked items. Stock is counted in
+        // pieces, so goods sold by weight leave it alone.
+        if ti.unit.is_none() {
+            sqlx::query(
+                "UPDATE items SET stock_quantity = stock_quantity - ?
+                 WHERE id = ? AND stock_quantity IS NOT NULL",
+            )
+            .bind(ti.quantity.whole_units())
+            .bind(ti.item_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
 
-    sqlx::query("SELECT id FROM items WHERE id = ?")
-        .bind(item_id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(db_err)?
-        .ok_or_else(|| not_found("Item not found"))?;
+            // Auto-mark out of stock if stock_quantity reaches 0
+            sqlx::query(
+                "UPDATE items SET in_stock = 0 WHERE id = ? AND stock_quantity IS NOT NULL AND stock_quantity <= 0",
+            )
+            .bind(ti.item_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        }
 
-    let (mime, b64) = image_data
-        .strip_prefix("data:")
-        .and_then(|s| s.split_once(','))
-        .ok_or_else(|| not_found("Invalid image data"))?;
+        // Create kitchen order items for kitchen items. A bundle reaches the
+        // kitchen through its component lines.
+        let is_kitchen: bool = sqlx::query_scalar("SELECT kitchen_item FROM items WHERE id = ?")
+            .bind(ti.item_id)
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(db_err)?;
 
-    let ext = if mime.starts_with("image/png") { "png" }
-        else if mime.starts_with("image/jpeg") { "jpg" }
-        else if mime.starts_with("image/webp") { "webp" }
-        else { "png" };
+        if is_kitchen && !ti.is_bundle {
+            let ko_id = Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO kitchen_order_items (id, transaction_id, transaction_item_id, item_id, item_name, quantity, customer_name, table_name, modifiers, note, completed, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, 0, ?)",
+            )
+            .bind(ko_id)
+            .bind(id)
+            .bind(ti.id)
+            .bind(ti.item_id)
+            .bind(&ti.item_name)
+            .bind(ti.quantity)
+            .bind(&transaction.customer_name)
+            .bind(&table_name)
+            .bind(ti.modifier_names())
+            .bind(&ti.note)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        }
+    }
 
-    use std::io::Write;
-    let bytes = base64_decode(b64).map_err(|e| db_err(e))?;
+    for p in payments {
+        sqlx::query(
+            "INSERT INTO payments (id, transaction_id, tender, amount, created_at)
+             VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(id)
+        .bind(&p.tender)
+        .bind(p.amount)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    }
 
-    let dir = "data/item_images";
-    std::fs::create_dir_all(dir).map_err(|e| db_err(e))?;
+    if let Some(account) = account.filter(|_| on_account.is_positive()) {
+        add_account_charge_db(&mut tx, account, id, on_account).await?;
+    }
 
-    let filename = format!("{}.{}", item_id, ext);
-    let filepath = format!("{}/{}", dir, filename);
+    // Credit the top-ups sold and take the redemptions off their cards.
+    for load in &loads {
+        add_gift_card_entry_db(&mut tx, load.gift_card_id, Some(id), "top_up", load.amount, user_name).await?;
+    }
+    // An entry is only written while its card still holds the amount.
+    for (card, amount) in &redemptions {
+        if !take_from_gift_card_db(&mut tx, card.id, Some(id), "redeem", *amount, user_name).await? {
+            return Err(not_found(&format!(
+                "Gift card {} no longer holds {}",
+                mask_card_code(&card.code),
+                amount.format(&currency)
+            )));
+        }
+    }
 
-    let mut file = std::fs::File::create(&filepath).map_err(|e| db_err(e))?;
-    file.write_all(&bytes).map_err(|e| db_err(e))?;
+    // Loyalty points: those redeemed come off the customer's balance, and
+    // what was spent, gift card top-ups and deposits aside, earns new ones.
+    let mut earned_points = None;
+    if let Some(customer_id) = transaction.customer_id {
+        add_loyalty_entry_db(&mut tx, customer_id, id, -redeemed_points).await?;
+        let deposits: Money = trans_items.iter().filter(|l| l.is_deposit).map(|l| l.total_price).sum();
+        let spent = transaction.total - loads.iter().map(|l| l.amount).sum::<Money>() - deposits;
+        let earned = loyalty.points_for(spent, &currency).max(0);
+        add_loyalty_entry_db(&mut tx, customer_id, id, earned).await?;
+        earned_points = Some((customer_id, earned));
+    }
+    let journal_entry = append_journal_db(&mut tx, id, "sale").await?;
+    let (transaction, fiscal) = sign_transaction_db(&mut tx, transaction, &journal_entry).await?;
+    tx.commit().await.map_err(db_err)?;
+
+    let mut receipt_loyalty = None;
+    if let Some((customer_id, earned)) = earned_points {
+        let balance = customer_db(pool, customer_id).await?.points;
+        receipt_loyalty = Some((earned, balance));
+    }
+    let mut receipt_gift_cards = Vec::new();
+    for (card_id, code, print_code) in loads
+        .iter()
+        .map(|l| (l.gift_card_id, l.code.clone(), true))
+        .chain(redemptions.iter().map(|(c, _)| (c.id, c.code.clone(), false)))
+    {
+        if receipt_gift_cards.iter().any(|(id, _, _)| *id == card_id) {
+            continue;
+        }
+        receipt_gift_cards.push((card_id, code, print_code));
+    }
+    let mut gift_card_summaries = Vec::with_capacity(receipt_gift_cards.len());
+    for (card_id, code, print_code) in receipt_gift_cards {
+        gift_card_summaries.push(rustpos_common::protocol::GiftCardSummary {
+            code,
+            balance: gift_card_balance_db(pool, card_id).await?.0,
+            print_code,
+        });
+    }
 
-    let url_path = format!("/item_images/{}", filename);
+    let lines: Vec<(Option<f64>, Money)> =
+        trans_items.iter().map(|it| (it.tax_rate, it.net_total())).collect();
+    let taxes = tax_breakdown(&lines, transaction.tax_inclusive);
+    let receipt_discounts = discount_lines(&trans_items)
+        .into_iter()
+        .map(|d| (d.name, d.amount.0))
+        .collect();
+    let receipt_lines = group_bundles(&trans_items);
+    let mut receipt_modifiers: Vec<Vec<String>> =
+        receipt_lines.iter().map(|it| receipt_modifier_labels(it, &currency)).collect();
+    let mut receipt_weights: Vec<Option<(f32, String, i64)>> =
+        receipt_lines.iter().map(|it| receipt_weight(it, it.quantity)).collect();
+    let mut receipt_items: Vec<(String, u32, i64)> =
+        receipt_lines.iter().map(|it| receipt_item(it, it.quantity)).collect();
+    for load in &loads {
+        receipt_items.push((format!("Gift card {}", mask_card_code(&load.code)), 1, load.amount.0));
+        receipt_modifiers.push(Vec::new());
+        receipt_weights.push(None);
+    }
 
-    sqlx::query("UPDATE items SET image_path = ?, updated_at = ? WHERE id = ?")
-        .bind(&url_path)
-        .bind(Utc::now())
-        .bind(item_id)
-        .execute(&pool)
-        .await
-        .map_err(db_err)?;
+    let job = rustpos_common::protocol::PrintReceiptJob {
+        items: receipt_items,
+        modifiers: receipt_modifiers,
+        weights: receipt_weights,
+        discounts: receipt_discounts,
+        payments: payments
+            .iter()
+            .map(|p| (p.tender.clone(), p.amount.0))
+            .collect(),
+        paid_amount: paid_amount.0,
+        change: change.0,
+        tip: tip.0,
+        rounding: rounding.0,
+        decimals: currency_decimals(&currency),
+        datetime: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        refund_of: None,
+        taxes: tax_summaries(&taxes),
+        tax_exclusive: !transaction.tax_inclusive,
+        gift_cards: gift_card_summaries,
+        loyalty_points: receipt_loyalty,
+        journal: Some((journal_entry.seq, journal_entry.short_hash().to_string())),
+        fiscal,
+        fiscal_error: transaction.fiscal_error.clone(),
+    };
 
-    Ok(url_path)
+    let response = CloseTransactionResponse {
+        transaction,
+        change_amount: change,
+        tip_amount: tip,
+    };
+    Ok((response, job))
 }
 
 #[server]
-pub async fn remove_item_image(item_id: Uuid) -> Result<(), ServerFnError> {
+pub async fn cancel_transaction(id: Uuid) -> Result<Transaction, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    let transaction = sqlx::query_as::<_, Transaction>(
+        "UPDATE transactions SET status = 'cancelled', updated_at = ?
+         WHERE id = ? AND status = 'open' RETURNING *",
+    )
+    .bind(Utc::now())
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+    let journal_entry = append_journal_db(&mut tx, id, "cancel").await?;
+    let (transaction, _) = sign_transaction_db(&mut tx, transaction, &journal_entry).await?;
+    tx.commit().await.map_err(db_err)?;
 
-    let image_path: Option<String> =
-        sqlx::query_scalar("SELECT image_path FROM items WHERE id = ?")
-            .bind(item_id)
-            .fetch_optional(&pool)
-            .await
-            .map_err(db_err)?
-            .flatten();
+    // Notify customer display — clear immediately
+    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
+        let _ = tx.send("clear".to_string());
+    }
+    // Notify other sale clients
+    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
+        let _ = sb.0.send(format!("cancelled:{}", id));
+    }
 
-    if let Some(ref url_path) = image_path {
-        let fs_path = format!("data{}", url_path);
-        let _ = std::fs::remove_file(&fs_path);
+    Ok(transaction)
+}
+
+// ---- Tab Operation Server Functions ----
+
+/// Tells the other sale terminals that an open sale changed.
+#[cfg(feature = "ssr")]
+fn broadcast_sale_update(transaction_id: Uuid) {
+    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
+        let _ = sb.0.send(format!("update:{}", transaction_id));
     }
+}
 
-    sqlx::query("UPDATE items SET image_path = NULL, updated_at = ? WHERE id = ?")
-        .bind(Utc::now())
-        .bind(item_id)
-        .execute(&pool)
+#[cfg(feature = "ssr")]
+async fn open_transaction_db(db: impl sqlx::SqliteExecutor<'_>, id: Uuid) -> Result<Transaction, ServerFnError> {
+    sqlx::query_as::<_, Transaction>("SELECT * FROM transactions WHERE id = ? AND status = 'open'")
+        .bind(id)
+        .fetch_optional(db)
         .await
-        .map_err(db_err)?;
-
-    Ok(())
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Transaction not found or not open"))
 }
 
+/// Moves `quantity` of a line of sale `from_id` to sale `to_id`. Units of
+/// items sold by the piece join a line there with the same item, modifiers,
+/// price and note if there is one; otherwise they become a new line with the
+/// same modifiers. A weighed line only moves whole. Discounts given on the
+/// line go with the units moved: such a line never joins another, and when
+/// only part of it moves the new line takes its share of each discount.
 #[cfg(feature = "ssr")]
-fn base64_decode(input: &str) -> Result<Vec<u8>, String> {
-    use std::collections::HashMap;
-    let table: HashMap<u8, u8> = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"
-        .iter()
-        .enumerate()
-        .map(|(i, &c)| (c, i as u8))
-        .collect();
+async fn move_line_db(
+    conn: &mut sqlx::SqliteConnection,
+    from_id: Uuid,
+    line_id: Uuid,
+    to_id: Uuid,
+    quantity: Quantity,
+) -> Result<(), ServerFnError> {
+    let (item_id, line_qty, unit_price, modifier_key, note, unit, price_embedded, is_bundle, bundle_id, deposit_for) =
+        sqlx::query_as::<_, (Uuid, Quantity, Money, String, Option<String>, Option<String>, bool, bool, Option<Uuid>, Option<Uuid>)>(
+            "SELECT item_id, quantity, unit_price, modifier_key, note, unit, price_embedded, is_bundle, bundle_id,
+             deposit_for
+             FROM transaction_items WHERE id = ? AND transaction_id = ?",
+        )
+        .bind(line_id)
+        .bind(from_id)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_err)?
+        .ok_or_else(|| not_found("Line not found in this transaction"))?;
+    if unit.is_some() && quantity != line_qty {
+        return Err(not_found("A weighed line moves as a whole"));
+    }
+    if !quantity.is_positive() || quantity > line_qty || (unit.is_none() && !quantity.is_whole()) {
+        return Err(not_found(&format!("Can move between 1 and {} of this line", line_qty)));
+    }
+    if bundle_id.is_some() {
+        return Err(not_found("Move the whole bundle"));
+    }
+    // The deposit is charged again in the sale its item moves to.
+    if deposit_for.is_some() {
+        return Err(not_found("The deposit moves with its item"));
+    }
+    if is_bundle {
+        // A bundle moves as a whole, component lines included.
+        sqlx::query("UPDATE transaction_items SET transaction_id = ? WHERE id = ? OR bundle_id = ?")
+            .bind(to_id)
+            .bind(line_id)
+            .bind(line_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_err)?;
+        sqlx::query(
+            "UPDATE transaction_discounts SET transaction_id = ? WHERE transaction_item_id IN
+             (SELECT id FROM transaction_items WHERE id = ? OR bundle_id = ?)",
+        )
+        .bind(to_id)
+        .bind(line_id)
+        .bind(line_id)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_err)?;
+        sqlx::query("UPDATE manual_discounts SET transaction_id = ? WHERE transaction_item_id = ?")
+            .bind(to_id)
+            .bind(line_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_err)?;
+        return Ok(());
+    }
 
-    let input: Vec<u8> = input.bytes().filter(|&b| b != b'\n' && b != b'\r' && b != b' ').collect();
-    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let discounts = sqlx::query_as::<_, ManualDiscount>("SELECT * FROM manual_discounts WHERE transaction_item_id = ?")
+        .bind(line_id)
+        .fetch_all(&mut *conn)
+        .await
+        .map_err(db_err)?;
 
-    for chunk in input.chunks(4) {
-        let mut buf = [0u8; 4];
-        let mut len = 0;
-        for (i, &b) in chunk.iter().enumerate() {
-            if b == b'=' { break; }
-            buf[i] = *table.get(&b).ok_or_else(|| format!("Invalid base64 char: {}", b as char))?;
-            len = i + 1;
+    // Each weighed line is a weighing of its own and is never merged, and a
+    // discount given on a line covers that line's units only.
+    let target = match unit {
+        Some(_) => None,
+        None if !discounts.is_empty() => None,
+        None => sqlx::query_as::<_, (Uuid, Quantity)>(
+            "SELECT id, quantity FROM transaction_items ti
+             WHERE transaction_id = ? AND item_id = ? AND modifier_key = ? AND unit_price = ? AND note IS ?
+               AND unit IS NULL AND price_embedded = ? AND NOT is_bundle AND bundle_id IS NULL
+               AND deposit_for IS NULL AND quantity > 0
+               AND NOT EXISTS (SELECT 1 FROM manual_discounts m WHERE m.transaction_item_id = ti.id)",
+        )
+        .bind(to_id)
+        .bind(item_id)
+        .bind(&modifier_key)
+        .bind(unit_price)
+        .bind(&note)
+        .bind(price_embedded)
+        .fetch_optional(&mut *conn)
+        .await
+        .map_err(db_err)?,
+    };
+
+    match target {
+        Some((target_id, target_qty)) => {
+            let new_qty = target_qty + quantity;
+            sqlx::query("UPDATE transaction_items SET quantity = ?, total_price = ? WHERE id = ?")
+                .bind(new_qty)
+                .bind(unit_price * new_qty)
+                .bind(target_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(db_err)?;
+        }
+        None if quantity == line_qty => {
+            // The whole line moves as it is, modifiers and discounts included.
+            sqlx::query("UPDATE transaction_items SET transaction_id = ? WHERE id = ?")
+                .bind(to_id)
+                .bind(line_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(db_err)?;
+            sqlx::query("UPDATE transaction_discounts SET transaction_id = ? WHERE transaction_item_id = ?")
+                .bind(to_id)
+                .bind(line_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(db_err)?;
+            sqlx::query("UPDATE manual_discounts SET transaction_id = ? WHERE transaction_item_id = ?")
+                .bind(to_id)
+                .bind(line_id)
+                .execute(&mut *conn)
+                .await
+                .map_err(db_err)?;
+            return Ok(());
+        }
+        None => {
+            let new_id = Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price,
+                 tax_rate, modifier_key, note, unit, price_embedded, is_deposit, created_at)
+                 SELECT ?, ?, item_id, ?, unit_price, ?, tax_rate, modifier_key, note,
+                        unit, price_embedded, is_deposit, ?
+                 FROM transaction_items WHERE id = ?",
+            )
+            .bind(new_id)
+            .bind(to_id)
+            .bind(quantity)
+            .bind(unit_price * quantity)
+            .bind(Utc::now())
+            .bind(line_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_err)?;
+            sqlx::query(
+                "INSERT INTO transaction_item_modifiers (id, transaction_item_id, modifier_id, group_name, name, price_delta)
+                 SELECT randomblob(16), ?, modifier_id, group_name, name, price_delta
+                 FROM transaction_item_modifiers WHERE transaction_item_id = ? ORDER BY rowid",
+            )
+            .bind(new_id)
+            .bind(line_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_err)?;
+            split_line_discounts_db(&mut *conn, &discounts, to_id, new_id, quantity, line_qty).await?;
         }
-        if len >= 2 { out.push((buf[0] << 2) | (buf[1] >> 4)); }
-        if len >= 3 { out.push((buf[1] << 4) | (buf[2] >> 2)); }
-        if len >= 4 { out.push((buf[2] << 6) | buf[3]); }
     }
-    Ok(out)
-}
 
-// ---- Transaction Server Functions ----
+    if quantity == line_qty {
+        sqlx::query("DELETE FROM transaction_items WHERE id = ?")
+            .bind(line_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_err)?;
+    } else {
+        let remaining = line_qty - quantity;
+        sqlx::query("UPDATE transaction_items SET quantity = ?, total_price = ? WHERE id = ?")
+            .bind(remaining)
+            .bind(unit_price * remaining)
+            .bind(line_id)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_err)?;
+    }
+    Ok(())
+}
 
-#[server]
-pub async fn fetch_all_transactions() -> Result<Vec<Transaction>, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let transactions = sqlx::query_as::<_, Transaction>(
-        "SELECT * FROM transactions ORDER BY created_at DESC",
-    )
-    .fetch_all(&pool)
-    .await
-    .map_err(db_err)?;
-    Ok(transactions)
+/// Gives `new_line_id` in sale `to_id` the share of `discounts` that comes
+/// with `quantity` of the `line_qty` units they were given on. A percentage
+/// applies to both lines as it is; amounts and redeemed loyalty points are
+/// shared out by quantity, the old line keeping the rest.
+#[cfg(feature = "ssr")]
+async fn split_line_discounts_db(
+    conn: &mut sqlx::SqliteConnection,
+    discounts: &[ManualDiscount],
+    to_id: Uuid,
+    new_line_id: Uuid,
+    quantity: Quantity,
+    line_qty: Quantity,
+) -> Result<(), ServerFnError> {
+    let currency = currency_db(&mut *conn).await?;
+    for d in discounts {
+        let (moved_value, kept_value) = match d.discount_type.as_str() {
+            "percent" => (d.discount_value, d.discount_value),
+            _ => {
+                let amount = Money::from_major(d.discount_value, &currency);
+                let moved = amount.mul_div(quantity.0, line_qty.0);
+                (moved.to_major(&currency), (amount - moved).to_major(&currency))
+            }
+        };
+        let moved_points = d.loyalty_points.map(|p| p * quantity.0 / line_qty.0);
+        sqlx::query("UPDATE manual_discounts SET discount_value = ?, loyalty_points = ? WHERE id = ?")
+            .bind(kept_value)
+            .bind(d.loyalty_points.zip(moved_points).map(|(p, moved)| p - moved))
+            .bind(d.id)
+            .execute(&mut *conn)
+            .await
+            .map_err(db_err)?;
+        sqlx::query(
+            "INSERT INTO manual_discounts (id, transaction_id, transaction_item_id, discount_type, discount_value,
+             reason, user_name, approved_by, loyalty_points, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(to_id)
+        .bind(new_line_id)
+        .bind(&d.discount_type)
+        .bind(moved_value)
+        .bind(&d.reason)
+        .bind(&d.user_name)
+        .bind(&d.approved_by)
+        .bind(moved_points)
+        .bind(d.created_at)
+        .execute(&mut *conn)
+        .await
+        .map_err(db_err)?;
+    }
+    Ok(())
 }
 
+/// Moves units of lines from one open sale to another, e.g. when a guest
+/// changes tables or pays for part of a tab separately.
 #[server]
-pub async fn fetch_open_transactions() -> Result<Vec<Transaction>, ServerFnError> {
+pub async fn move_transaction_items(
+    from_id: Uuid,
+    to_id: Uuid,
+    moves: Vec<LineMove>,
+) -> Result<(), ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    let transactions = sqlx::query_as::<_, Transaction>(
-        "SELECT * FROM transactions WHERE status = 'open' ORDER BY created_at DESC",
-    )
-    .fetch_all(&pool)
-    .await
-    .map_err(db_err)?;
-    Ok(transactions)
+    move_transaction_items_db(&pool, from_id, to_id, &moves).await?;
+    broadcast_sale_update(from_id);
+    broadcast_sale_update(to_id);
+    Ok(())
 }
 
-#[server]
-pub async fn fetch_transaction_details(
-    id: Uuid,
-) -> Result<TransactionDetailsResponse, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let transaction =
-        sqlx::query_as::<_, Transaction>("SELECT * FROM transactions WHERE id = ?")
-            .bind(id)
-            .fetch_optional(&pool)
-            .await
-            .map_err(db_err)?
-            .ok_or_else(|| not_found("Transaction not found"))?;
+/// Does the move for [`move_transaction_items`]. Either every line moves or,
+/// if one cannot, none does.
+#[cfg(feature = "ssr")]
+async fn move_transaction_items_db(
+    pool: &sqlx::SqlitePool,
+    from_id: Uuid,
+    to_id: Uuid,
+    moves: &[LineMove],
+) -> Result<(), ServerFnError> {
+    if from_id == to_id {
+        return Err(not_found("Pick a different transaction to move to"));
+    }
+    let mut tx = begin_write_db(pool).await?;
+    open_transaction_db(&mut *tx, from_id).await?;
+    open_transaction_db(&mut *tx, to_id).await?;
 
-    let items = sqlx::query_as::<_, TransactionItemDetail>(
-        "SELECT ti.id, ti.item_id, i.name as item_name, ti.quantity,
-         ti.unit_price, ti.total_price
-         FROM transaction_items ti
-         JOIN items i ON ti.item_id = i.id
-         WHERE ti.transaction_id = ?",
-    )
-    .bind(id)
-    .fetch_all(&pool)
-    .await
-    .map_err(db_err)?;
+    for m in moves.iter().filter(|m| m.quantity.is_positive()) {
+        move_line_db(&mut tx, from_id, m.transaction_item_id, to_id, m.quantity).await?;
+    }
 
-    Ok(TransactionDetailsResponse { transaction, items })
+    recompute_transaction_db(&mut tx, from_id).await?;
+    recompute_transaction_db(&mut tx, to_id).await?;
+    tx.commit().await.map_err(db_err)
 }
 
+/// Moves everything from open sale `from_id` onto `into_id` and removes the
+/// emptied sale. The merged tab keeps its own customer name, or takes the
+/// other one's if it has none.
 #[server]
-pub async fn create_transaction(
-    customer_name: Option<String>,
-) -> Result<Transaction, ServerFnError> {
+pub async fn merge_transactions(from_id: Uuid, into_id: Uuid) -> Result<Transaction, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    let id = Uuid::new_v4();
-    let now = Utc::now();
-    let transaction = sqlx::query_as::<_, Transaction>(
-        "INSERT INTO transactions (id, customer_name, status, total, created_at, updated_at)
-         VALUES (?, ?, 'open', 0.0, ?, ?) RETURNING *",
-    )
-    .bind(id)
-    .bind(&customer_name)
-    .bind(now)
-    .bind(now)
-    .fetch_one(&pool)
-    .await
-    .map_err(db_err)?;
+    let merged = merge_transactions_db(&pool, from_id, into_id).await?;
 
-    // Notify other sale clients about the new open transaction
+    // Terminals showing the merged-away tab drop it like a cancelled one.
     if let Some(sb) = use_context::<crate::SaleBroadcast>() {
-        let _ = sb.0.send(format!("update:{}", id));
+        let _ = sb.0.send(format!("cancelled:{}", from_id));
     }
-
-    Ok(transaction)
+    broadcast_sale_update(into_id);
+    Ok(merged)
 }
 
-#[server]
-pub async fn update_transaction_details(
-    id: Uuid,
-    customer_name: Option<String>,
-    customer_group_id: Option<Uuid>,
+/// Does the merge for [`merge_transactions`] and returns the merged tab.
+#[cfg(feature = "ssr")]
+async fn merge_transactions_db(
+    pool: &sqlx::SqlitePool,
+    from_id: Uuid,
+    into_id: Uuid,
 ) -> Result<Transaction, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    sqlx::query_as::<_, Transaction>(
-        "SELECT * FROM transactions WHERE id = ? AND status = 'open'",
-    )
-    .bind(id)
-    .fetch_optional(&pool)
-    .await
-    .map_err(db_err)?
-    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+    if from_id == into_id {
+        return Err(not_found("Cannot merge a transaction into itself"));
+    }
+    let mut tx = begin_write_db(pool).await?;
+    let from = open_transaction_db(&mut *tx, from_id).await?;
+    let into = open_transaction_db(&mut *tx, into_id).await?;
 
-    let updated = sqlx::query_as::<_, Transaction>(
-        "UPDATE transactions SET customer_name = ?, customer_group_id = ?, updated_at = ? WHERE id = ? RETURNING *",
+    // Deposit returns move as they are; deposits follow their items.
+    for line in transaction_lines_db(&mut tx, from_id).await? {
+        if line.bundle_id.is_some() || line.deposit_for.is_some() {
+            continue;
+        }
+        if line.quantity.is_negative() {
+            sqlx::query("UPDATE transaction_items SET transaction_id = ? WHERE id = ?")
+                .bind(into_id)
+                .bind(line.id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+            sqlx::query("UPDATE transaction_discounts SET transaction_id = ? WHERE transaction_item_id = ?")
+                .bind(into_id)
+                .bind(line.id)
+                .execute(&mut *tx)
+                .await
+                .map_err(db_err)?;
+            continue;
+        }
+        move_line_db(&mut tx, from_id, line.id, into_id, line.quantity).await?;
+    }
+    sqlx::query("UPDATE gift_card_loads SET transaction_id = ? WHERE transaction_id = ?")
+        .bind(into_id)
+        .bind(from_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+
+    sqlx::query(
+        "UPDATE transactions SET customer_name = ?, customer_group_id = ?, customer_id = ?, table_id = ?, updated_at = ? WHERE id = ?",
     )
-    .bind(&customer_name)
-    .bind(customer_group_id)
+    .bind(into.customer_name.or(from.customer_name))
+    .bind(into.customer_group_id.or(from.customer_group_id))
+    .bind(into.customer_id.or(from.customer_id))
+    .bind(into.table_id.or(from.table_id))
     .bind(Utc::now())
-    .bind(id)
-    .fetch_one(&pool)
+    .bind(into_id)
+    .execute(&mut *tx)
     .await
     .map_err(db_err)?;
-    Ok(updated)
+    // The emptied sale's discounts would go with it: those on the whole sale
+    // and on lines moved as they are. Points redeemed on it stay only if the
+    // merged tab is still for the same customer.
+    if into.customer_id.is_some() && into.customer_id != from.customer_id {
+        sqlx::query("DELETE FROM manual_discounts WHERE transaction_id = ? AND loyalty_points IS NOT NULL")
+            .bind(from_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+    }
+    sqlx::query("UPDATE manual_discounts SET transaction_id = ? WHERE transaction_id = ?")
+        .bind(into_id)
+        .bind(from_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    sqlx::query("DELETE FROM transactions WHERE id = ?")
+        .bind(from_id)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+    recompute_transaction_db(&mut tx, into_id).await?;
+    let merged = open_transaction_db(&mut *tx, into_id).await?;
+    tx.commit().await.map_err(db_err)?;
+    Ok(merged)
 }
 
+/// Splits an open sale into `parts` tabs with totals as even as whole units
+/// allow (see [`split_units`]). The original sale becomes the first tab and
+/// all tabs get its customer name numbered "(1/3)", "(2/3)" and so on.
+/// Returns all tabs, original first.
 #[server]
-pub async fn add_item_to_transaction(
-    transaction_id: Uuid,
-    item_id: Uuid,
-    quantity: i32,
-) -> Result<(), ServerFnError> {
+pub async fn split_transaction(id: Uuid, parts: i32) -> Result<Vec<Transaction>, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-
-    sqlx::query_as::<_, Transaction>(
-        "SELECT * FROM transactions WHERE id = ? AND status = 'open'",
-    )
-    .bind(transaction_id)
-    .fetch_optional(&pool)
-    .await
-    .map_err(db_err)?
-    .ok_or_else(|| not_found("Transaction not found or not open"))?;
-
-    let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
-        .bind(item_id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(db_err)?
-        .ok_or_else(|| not_found("Item not found"))?;
-
-    if !item.in_stock {
-        return Err(not_found("Item is out of stock"));
+    let tabs = split_transaction_db(&pool, id, parts).await?;
+    for tab in &tabs {
+        broadcast_sale_update(tab.id);
     }
+    Ok(tabs)
+}
 
-    // Check stock quantity if tracked
-    if let Some(stock_qty) = item.stock_quantity {
-        let existing_in_transaction = sqlx::query_scalar::<_, i32>(
-            "SELECT quantity FROM transaction_items WHERE transaction_id = ? AND item_id = ?",
-        )
-        .bind(transaction_id)
-        .bind(item_id)
-        .fetch_optional(&pool)
-        .await
-        .map_err(db_err)?
-        .unwrap_or(0);
-
-        if existing_in_transaction + quantity > stock_qty {
-            return Err(not_found(&format!("Only {} left in stock", stock_qty - existing_in_transaction)));
-        }
+/// Does the split for [`split_transaction`], all tabs or none.
+#[cfg(feature = "ssr")]
+async fn split_transaction_db(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    parts: i32,
+) -> Result<Vec<Transaction>, ServerFnError> {
+    let mut tx = begin_write_db(pool).await?;
+    let original = open_transaction_db(&mut *tx, id).await?;
+    // Bundles are split as whole units at the bundle price. Deposits follow
+    // their items and deposit returns stay on the first tab.
+    let lines: Vec<TransactionItemDetail> = group_bundles(&transaction_lines_db(&mut tx, id).await?)
+        .into_iter()
+        .filter(|l| l.deposit_for.is_none() && l.quantity.is_positive())
+        .collect();
+    // A weighed line goes to one tab, as one unit at the price of its weight.
+    let shares: Vec<(Uuid, i32, Money)> = lines
+        .iter()
+        .map(|l| match l.unit {
+            Some(_) => (l.id, l.split_units(), l.total_price),
+            None => (l.id, l.split_units(), l.unit_price),
+        })
+        .collect();
+    let units: i32 = shares.iter().map(|(_, qty, _)| qty).sum();
+    if parts < 2 || parts > units {
+        return Err(not_found(&format!("Can split into 2 to {} tabs", units.max(2))));
     }
 
-    let existing_qty = sqlx::query_scalar::<_, i32>(
-        "SELECT quantity FROM transaction_items WHERE transaction_id = ? AND item_id = ?",
-    )
-    .bind(transaction_id)
-    .bind(item_id)
-    .fetch_optional(&pool)
-    .await
-    .map_err(db_err)?;
-
-    let new_quantity = existing_qty.unwrap_or(0) + quantity;
+    let tabs = split_units(&shares, parts as usize);
+    let base_name = original.customer_name.clone().unwrap_or_default();
+    let now = Utc::now();
 
-    if new_quantity <= 0 {
-        sqlx::query("DELETE FROM transaction_items WHERE transaction_id = ? AND item_id = ?")
-            .bind(transaction_id)
-            .bind(item_id)
-            .execute(&pool)
-            .await
-            .map_err(db_err)?;
-    } else if existing_qty.is_some() {
-        let total_price = item.price * new_quantity as f64;
-        sqlx::query(
-            "UPDATE transaction_items SET quantity = ?, unit_price = ?, total_price = ?
-             WHERE transaction_id = ? AND item_id = ?",
+    let mut result = vec![original.clone()];
+    for (i, share) in tabs.iter().enumerate().skip(1) {
+        let name = format!("{} ({}/{})", base_name, i + 1, parts).trim().to_string();
+        let tab = sqlx::query_as::<_, Transaction>(
+            "INSERT INTO transactions (id, customer_name, customer_group_id, customer_id, status, total, tax_inclusive, table_id, created_at, updated_at)
+             VALUES (?, ?, ?, ?, 'open', 0, ?, ?, ?, ?) RETURNING *",
         )
-        .bind(new_quantity)
-        .bind(item.price)
-        .bind(total_price)
-        .bind(transaction_id)
-        .bind(item_id)
-        .execute(&pool)
+        .bind(Uuid::new_v4())
+        .bind(&name)
+        .bind(original.customer_group_id)
+        .bind(original.customer_id)
+        .bind(original.tax_inclusive)
+        .bind(original.table_id)
+        .bind(now)
+        .bind(now)
+        .fetch_one(&mut *tx)
         .await
         .map_err(db_err)?;
-    } else {
-        let id = Uuid::new_v4();
-        let total_price = item.price * new_quantity as f64;
-        let now = Utc::now();
-        sqlx::query(
-            "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price, created_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
-        )
+        for &(line_id, qty) in share {
+            let quantity = match lines.iter().find(|l| l.id == line_id) {
+                Some(l) if l.unit.is_some() => l.quantity,
+                _ => Quantity::units(qty as i64),
+            };
+            move_line_db(&mut tx, id, line_id, tab.id, quantity).await?;
+        }
+        recompute_transaction_db(&mut tx, tab.id).await?;
+        result.push(tab);
+    }
+
+    let first_name = format!("{} (1/{})", base_name, parts).trim().to_string();
+    sqlx::query("UPDATE transactions SET customer_name = ? WHERE id = ?")
+        .bind(&first_name)
         .bind(id)
-        .bind(transaction_id)
-        .bind(item_id)
-        .bind(new_quantity)
-        .bind(item.price)
-        .bind(total_price)
-        .bind(now)
-        .execute(&pool)
+        .execute(&mut *tx)
         .await
         .map_err(db_err)?;
+    recompute_transaction_db(&mut tx, id).await?;
+
+    // Re-read so names and totals are current.
+    let mut tabs = Vec::with_capacity(result.len());
+    for t in result {
+        tabs.push(open_transaction_db(&mut *tx, t.id).await?);
     }
+    tx.commit().await.map_err(db_err)?;
+    Ok(tabs)
+}
 
-    update_transaction_total_db(&pool, transaction_id).await?;
+/// How many units of each line of a closed sale can still be refunded.
+#[server]
+pub async fn fetch_refundable_lines(id: Uuid) -> Result<Vec<RefundLine>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    refundable_lines_db(&pool, id).await
+}
 
-    // Notify customer display
-    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
-        let _ = tx.send(format!("update:{}", transaction_id));
+/// Refunds selected lines of a closed sale.
+///
+/// Creates a closed transaction linked to the original with negative lines and
+/// total, pays the amount back on `tender`, restocks tracked items and prints
+/// a refund receipt.
+#[server]
+pub async fn refund_transaction(
+    id: Uuid,
+    lines: Vec<RefundLine>,
+    tender: String,
+) -> Result<Transaction, ServerFnError> {
+    use crate::printer::{find_printer, open_cash_drawer, print_refund_receipt};
+
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let admin = require_admin(&pool).await?;
+    let (refund, job) = refund_transaction_db(&pool, id, lines, &tender, &admin.username).await?;
+
+    if let Some(printer_tx) = use_context::<tokio::sync::broadcast::Sender<
+        rustpos_common::protocol::PrintReceiptJob,
+    >>() {
+        let _ = printer_tx.send(job.clone());
     }
-    // Notify other sale clients
+    let local_printing_disabled = read_disable_local_printing(&pool).await;
+    let local_now = chrono::Local::now();
+    let _ = tokio::task::spawn_blocking(move || {
+        if let Ok((_, mut printer)) = find_printer() {
+            if local_printing_disabled {
+                let _ = open_cash_drawer(&mut printer);
+            } else {
+                let _ = print_refund_receipt(
+                    &mut printer,
+                    &job,
+                    local_now,
+                    Some("data/logo_receipt.png"),
+                );
+            }
+        }
+    })
+    .await;
+
+    // Sale clients reload item stock when a transaction closes
     if let Some(sb) = use_context::<crate::SaleBroadcast>() {
-        let _ = sb.0.send(format!("update:{}", transaction_id));
+        let _ = sb.0.send(format!("closed:{}", refund.id));
     }
 
-    Ok(())
+    Ok(refund)
 }
 
-#[server]
-pub async fn remove_item_from_transaction(
-    transaction_id: Uuid,
-    item_id: Uuid,
-) -> Result<(), ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
+/// Does the refund for [`refund_transaction`] and returns its receipt. What
+/// is left to refund is checked in the same database transaction that writes
+/// the refund, so two refunds of one sale cannot both give back the same
+/// units.
+#[cfg(feature = "ssr")]
+async fn refund_transaction_db(
+    pool: &sqlx::SqlitePool,
+    id: Uuid,
+    lines: Vec<RefundLine>,
+    tender: &str,
+    user_name: &str,
+) -> Result<(Transaction, rustpos_common::protocol::PrintReceiptJob), ServerFnError> {
+    if !TENDERS.contains(&tender) {
+        return Err(not_found(&format!("Unknown tender type: {}", tender)));
+    }
+    if tender == "gift_card" {
+        return Err(not_found("Refunds cannot be paid onto a gift card"));
+    }
+    let lines: Vec<RefundLine> = lines.into_iter().filter(|l| l.quantity.is_positive()).collect();
+    if lines.is_empty() {
+        return Err(not_found("Nothing selected to refund"));
+    }
+    let rounding_rule = read_cash_rounding(pool).await;
+    let loyalty = read_loyalty_settings(pool).await;
+    let currency = currency_db(pool).await?;
 
-    sqlx::query_as::<_, Transaction>(
-        "SELECT * FROM transactions WHERE id = ? AND status = 'open'",
+    let mut tx = begin_write_db(pool).await?;
+    let original = sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions WHERE id = ? AND status = 'closed'",
     )
-    .bind(transaction_id)
-    .fetch_optional(&pool)
+    .bind(id)
+    .fetch_optional(&mut *tx)
     .await
     .map_err(db_err)?
-    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+    .ok_or_else(|| not_found("Transaction not found or not closed"))?;
+
+    // A refund "on account" is credited to the sale's house account.
+    let refund_account = match tender {
+        "account" => Some(
+            AccountRef::of(original.customer_id, original.customer_group_id)
+                .ok_or_else(|| not_found("Only sales to a customer or customer group can be refunded on account"))?,
+        ),
+        _ => None,
+    };
+
+    if original.refund_of.is_some() {
+        return Err(not_found("A refund cannot be refunded"));
+    }
+
+    let original_items = transaction_lines_db(&mut tx, id).await?;
+    let refundable = refundable_lines_db(&mut *tx, id).await?;
+
+    let mut refund_items = Vec::with_capacity(lines.len());
+    for line in &lines {
+        let item = original_items
+            .iter()
+            .find(|it| it.id == line.transaction_item_id)
+            .ok_or_else(|| not_found("Line does not belong to this transaction"))?;
+        let available = refundable
+            .iter()
+            .find(|r| r.transaction_item_id == line.transaction_item_id)
+            .map(|r| r.quantity)
+            .unwrap_or_default();
+        if line.quantity > available {
+            return Err(not_found(&format!(
+                "Only {} of {} can be refunded",
+                available,
+                item.item_name
+            )));
+        }
+        // Units are refunded one by one, a weighing only as a whole.
+        if item.unit.is_some() && line.quantity != available {
+            return Err(not_found(&format!("{} is refunded as a whole", item.item_name)));
+        }
+        if item.unit.is_none() && !line.quantity.is_whole() {
+            return Err(not_found(&format!("{} is refunded in whole units", item.item_name)));
+        }
+        // Discounts on the line are given back for the returned units.
+        let discounts = item.refund_discounts(item.quantity - available, line.quantity);
+        refund_items.push((item, line.quantity, discounts));
+    }
+
+    // Refund lines are taxed at the rates and in the mode of the original sale.
+    let lines: Vec<(Option<f64>, Money)> = refund_items
+        .iter()
+        .map(|(it, qty, discounts)| {
+            let given_back: Money = discounts.iter().map(|d| d.amount).sum();
+            (it.tax_rate, -it.unit_price * *qty + given_back)
+        })
+        .collect();
+    let taxes = tax_breakdown(&lines, original.tax_inclusive);
+    let total: Money = lines.iter().map(|(_, amount)| amount).sum::<Money>()
+        + added_tax(&taxes, original.tax_inclusive);
+    // Cash is paid back in whole coins, as it is taken.
+    let paid_back = match tender {
+        "cash" => rounding_rule.round(total),
+        _ => total,
+    };
+    let rounding = paid_back - total;
+    let refund_id = Uuid::new_v4();
+    let now = Utc::now();
 
-    let current_quantity = sqlx::query_scalar::<_, i32>(
-        "SELECT quantity FROM transaction_items WHERE transaction_id = ? AND item_id = ?",
+    let refund = sqlx::query_as::<_, Transaction>(
+        "INSERT INTO transactions (id, customer_name, status, total, paid_amount, change_amount, rounding_amount,
+         customer_group_id, customer_id, refund_of, tax_inclusive, closed_by, created_at, updated_at, closed_at)
+         VALUES (?, ?, 'closed', ?, ?, 0, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
     )
-    .bind(transaction_id)
-    .bind(item_id)
-    .fetch_optional(&pool)
+    .bind(refund_id)
+    .bind(&original.customer_name)
+    .bind(total)
+    .bind(paid_back)
+    .bind(rounding)
+    .bind(original.customer_group_id)
+    .bind(original.customer_id)
+    .bind(id)
+    .bind(original.tax_inclusive)
+    .bind(user_name)
+    .bind(now)
+    .bind(now)
+    .bind(now)
+    .fetch_one(&mut *tx)
     .await
     .map_err(db_err)?;
 
-    if let Some(qty) = current_quantity {
-        if qty > 1 {
-            let item = sqlx::query_as::<_, Item>("SELECT * FROM items WHERE id = ?")
-                .bind(item_id)
-                .fetch_one(&pool)
-                .await
-                .map_err(db_err)?;
-            let new_qty = qty - 1;
-            let total_price = item.price * new_qty as f64;
+    for (item, qty, discounts) in &refund_items {
+        let line_id = Uuid::new_v4();
+        sqlx::query(
+            "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price,
+             total_price, tax_rate, unit, is_deposit, refund_of_item_id, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        )
+        .bind(line_id)
+        .bind(refund_id)
+        .bind(item.item_id)
+        .bind(-*qty)
+        .bind(item.unit_price)
+        .bind(-item.unit_price * *qty)
+        .bind(item.tax_rate)
+        .bind(&item.unit)
+        .bind(item.is_deposit)
+        .bind(item.id)
+        .bind(now)
+        .execute(&mut *tx)
+        .await
+        .map_err(db_err)?;
+
+        // Carry the modifiers over so modifier sales net out in the reports.
+        for m in &item.modifiers {
+            sqlx::query(
+                "INSERT INTO transaction_item_modifiers (id, transaction_item_id, modifier_id, group_name, name, price_delta)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(line_id)
+            .bind(m.modifier_id)
+            .bind(&m.group_name)
+            .bind(&m.name)
+            .bind(m.price_delta)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        }
+        for d in discounts {
+            sqlx::query(
+                "INSERT INTO transaction_discounts (id, transaction_id, transaction_item_id, promotion_id,
+                 reason, user_name, name, amount, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(refund_id)
+            .bind(line_id)
+            .bind(d.promotion_id)
+            .bind(&d.reason)
+            .bind(&d.user_name)
+            .bind(&d.name)
+            .bind(d.amount)
+            .bind(now)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+        }
+
+        // Put returned units back into tracked stock, and undo the automatic
+        // out-of-stock flag if this brings the item back above zero. Goods
+        // sold by weight took none off.
+        if item.unit.is_none() {
             sqlx::query(
-                "UPDATE transaction_items SET quantity = ?, unit_price = ?, total_price = ?
-                 WHERE transaction_id = ? AND item_id = ?",
+                "UPDATE items SET stock_quantity = stock_quantity + ?
+                 WHERE id = ? AND stock_quantity IS NOT NULL",
             )
-            .bind(new_qty)
-            .bind(item.price)
-            .bind(total_price)
-            .bind(transaction_id)
-            .bind(item_id)
-            .execute(&pool)
+            .bind(qty.whole_units())
+            .bind(item.item_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+            sqlx::query(
+                "UPDATE items SET in_stock = 1
+                 WHERE id = ? AND stock_quantity IS NOT NULL AND stock_quantity > 0 AND stock_quantity - ? <= 0",
+            )
+            .bind(item.item_id)
+            .bind(qty.whole_units())
+            .execute(&mut *tx)
             .await
             .map_err(db_err)?;
-        } else {
-            sqlx::query("DELETE FROM transaction_items WHERE transaction_id = ? AND item_id = ?")
-                .bind(transaction_id)
-                .bind(item_id)
-                .execute(&pool)
-                .await
-                .map_err(db_err)?;
         }
     }
 
-    update_transaction_total_db(&pool, transaction_id).await?;
+    sqlx::query(
+        "INSERT INTO payments (id, transaction_id, tender, amount, created_at)
+         VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(refund_id)
+    .bind(tender)
+    .bind(paid_back)
+    .bind(now)
+    .execute(&mut *tx)
+    .await
+    .map_err(db_err)?;
+    if let Some(account) = refund_account {
+        add_account_charge_db(&mut tx, account, refund_id, total).await?;
+    }
 
-    // Notify customer display
-    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
-        let _ = tx.send(format!("update:{}", transaction_id));
+    // Take back the loyalty points the refunded amount earned. Deposits
+    // earned none.
+    if let Some(customer_id) = original.customer_id {
+        let deposits: Money = refund_items
+            .iter()
+            .filter(|(it, _, _)| it.is_deposit)
+            .map(|(it, qty, _)| -it.unit_price * *qty)
+            .sum();
+        let points = loyalty.points_for(total - deposits, &currency);
+        add_loyalty_entry_db(&mut tx, customer_id, refund_id, points).await?;
     }
-    // Notify other sale clients
-    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
-        let _ = sb.0.send(format!("update:{}", transaction_id));
+    // Signing uses up a signature counter, so it comes last.
+    let journal_entry = append_journal_db(&mut tx, refund_id, "refund").await?;
+    let (refund, fiscal) = sign_transaction_db(&mut tx, refund, &journal_entry).await?;
+    tx.commit().await.map_err(db_err)?;
+
+    // The refund receipt
+    let original_ref = format!(
+        "{} #{}",
+        original
+            .closed_at
+            .unwrap_or(original.created_at)
+            .with_timezone(&chrono::Local)
+            .format("%Y-%m-%d %H:%M"),
+        &id.simple().to_string()[..8],
+    );
+    let mut receipt_discounts: Vec<(String, i64)> = Vec::new();
+    for d in refund_items.iter().flat_map(|(_, _, discounts)| discounts) {
+        match receipt_discounts.iter_mut().find(|(name, _)| *name == d.name) {
+            Some((_, amount)) => *amount += d.amount.0,
+            None => receipt_discounts.push((d.name.clone(), d.amount.0)),
+        }
     }
+    let job = rustpos_common::protocol::PrintReceiptJob {
+        items: refund_items.iter().map(|(it, qty, _)| receipt_item(it, *qty)).collect(),
+        modifiers: refund_items.iter().map(|(it, _, _)| receipt_modifier_labels(it, &currency)).collect(),
+        weights: refund_items.iter().map(|(it, qty, _)| receipt_weight(it, *qty)).collect(),
+        discounts: receipt_discounts,
+        payments: vec![(tender.to_string(), paid_back.0)],
+        paid_amount: paid_back.0,
+        change: 0,
+        tip: 0,
+        rounding: rounding.0,
+        decimals: currency_decimals(&currency),
+        datetime: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        refund_of: Some(original_ref),
+        taxes: tax_summaries(&taxes),
+        tax_exclusive: !original.tax_inclusive,
+        gift_cards: Vec::new(),
+        loyalty_points: None,
+        journal: Some((journal_entry.seq, journal_entry.short_hash().to_string())),
+        fiscal,
+        fiscal_error: refund.fiscal_error.clone(),
+    };
+    Ok((refund, job))
+}
 
+/// Called by the sale page to tell the customer display which transaction is active.
+#[server]
+pub async fn set_display_transaction(id: Option<Uuid>) -> Result<(), ServerFnError> {
+    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
+        let msg = match id {
+            Some(id) => format!("update:{}", id),
+            None => "clear".to_string(),
+        };
+        let _ = tx.send(msg);
+    }
     Ok(())
 }
 
+// ---- Report Server Functions ----
+
+#[server]
+pub async fn fetch_sales_report(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: GroupFilter,
+) -> Result<SalesReport, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    generate_sales_report_db(&pool, start_date, end_date, &filter).await
+}
+
 #[server]
-pub async fn close_transaction(
-    id: Uuid,
-    paid_amount: f64,
-) -> Result<CloseTransactionResponse, ServerFnError> {
-    use crate::printer::{find_printer, open_cash_drawer, print_receipt};
+pub async fn fetch_daily_report(filter: GroupFilter) -> Result<SalesReport, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let end_date = Utc::now();
+    let start_date = end_date - chrono::Duration::days(1);
+    generate_sales_report_db(&pool, start_date, end_date, &filter).await
+}
 
+#[server]
+pub async fn fetch_monthly_report(filter: GroupFilter) -> Result<SalesReport, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
+    let end_date = Utc::now();
+    let start_date = end_date - chrono::Duration::days(30);
+    generate_sales_report_db(&pool, start_date, end_date, &filter).await
+}
 
-    let transaction = sqlx::query_as::<_, Transaction>(
-        "SELECT * FROM transactions WHERE id = ? AND status = 'open'",
-    )
-    .bind(id)
-    .fetch_optional(&pool)
-    .await
-    .map_err(db_err)?
-    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+#[server]
+pub async fn fetch_item_sales_timeseries(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    top_n: i64,
+    filter: GroupFilter,
+) -> Result<ItemSalesTimeseries, ServerFnError> {
+    use chrono::{Datelike, TimeZone, Timelike};
 
-    if paid_amount < transaction.total {
-        return Err(not_found("Insufficient payment amount"));
+    if end_date <= start_date {
+        return Err(not_found("End date must be after start date"));
     }
+    let pool = expect_context::<sqlx::SqlitePool>();
 
-    let change = paid_amount - transaction.total;
-    let now = Utc::now();
+    // Determine bucket size: hourly for short periods, daily otherwise.
+    let duration = end_date.signed_duration_since(start_date);
+    let use_hourly = duration <= chrono::Duration::hours(48);
 
-    // Decrement stock quantities for tracked items
-    let trans_items = sqlx::query_as::<_, TransactionItemDetail>(
-        "SELECT ti.id, ti.item_id, i.name as item_name, ti.quantity,
-         ti.unit_price, ti.total_price
+    // Top N items by quantity within the period. A weighed line counts as one.
+    #[derive(sqlx::FromRow)]
+    struct TopRow {
+        item_id: Uuid,
+        item_name: String,
+    }
+    let top_items = sqlx::query_as::<_, TopRow>(&format!(
+        "SELECT i.id as item_id, i.name as item_name
          FROM transaction_items ti
          JOIN items i ON ti.item_id = i.id
-         WHERE ti.transaction_id = ?",
-    )
-    .bind(id)
+         JOIN transactions t ON ti.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         GROUP BY i.id, i.name
+         ORDER BY SUM(CASE WHEN ti.unit IS NULL THEN ti.quantity ELSE 1000 END) DESC
+         LIMIT ?",
+        group_filter_clause(&filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .bind(top_n)
     .fetch_all(&pool)
     .await
     .map_err(db_err)?;
 
-    for ti in &trans_items {
-        // Decrement stock_quantity for tracked items
-        sqlx::query(
-            "UPDATE items SET stock_quantity = stock_quantity - ?
-             WHERE id = ? AND stock_quantity IS NOT NULL",
-        )
-        .bind(ti.quantity)
-        .bind(ti.item_id)
-        .execute(&pool)
-        .await
-        .map_err(db_err)?;
+    let item_ids: Vec<Uuid> = top_items.iter().map(|r| r.item_id).collect();
+    let item_names: Vec<String> = top_items.iter().map(|r| r.item_name.clone()).collect();
+    let item_index: std::collections::HashMap<Uuid, usize> = item_ids
+        .iter()
+        .enumerate()
+        .map(|(i, id)| (*id, i))
+        .collect();
 
-        // Auto-mark out of stock if stock_quantity reaches 0
-        sqlx::query(
-            "UPDATE items SET in_stock = 0 WHERE id = ? AND stock_quantity IS NOT NULL AND stock_quantity <= 0",
-        )
-        .bind(ti.item_id)
-        .execute(&pool)
-        .await
-        .map_err(db_err)?;
+    // Pre-compute buckets covering the full requested range so empty periods still appear.
+    let mut buckets: Vec<TimeseriesBucket> = Vec::new();
+    let n = item_ids.len();
+    if use_hourly {
+        let mut t = chrono::Utc
+            .with_ymd_and_hms(
+                start_date.year(),
+                start_date.month(),
+                start_date.day(),
+                start_date.hour(),
+                0,
+                0,
+            )
+            .single()
+            .unwrap_or(start_date);
+        while t < end_date {
+            buckets.push(TimeseriesBucket {
+                bucket_start: t,
+                label: t.format("%H:%M").to_string(),
+                quantities: vec![0i64; n],
+            });
+            t = t + chrono::Duration::hours(1);
+        }
+    } else {
+        let mut d = start_date.date_naive();
+        let end_d = end_date.date_naive();
+        while d <= end_d {
+            let ts = d
+                .and_hms_opt(0, 0, 0)
+                .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
+                .unwrap_or(start_date);
+            buckets.push(TimeseriesBucket {
+                bucket_start: ts,
+                label: d.format("%m-%d").to_string(),
+                quantities: vec![0i64; n],
+            });
+            d = d.succ_opt().unwrap_or(d);
+        }
+    }
 
-        // Create kitchen order items for kitchen items
-        let is_kitchen: bool = sqlx::query_scalar("SELECT kitchen_item FROM items WHERE id = ?")
-            .bind(ti.item_id)
-            .fetch_one(&pool)
-            .await
-            .map_err(db_err)?;
+    if n == 0 || buckets.is_empty() {
+        return Ok(ItemSalesTimeseries {
+            item_ids,
+            item_names,
+            buckets,
+            bucket_unit: if use_hourly { "hour".into() } else { "day".into() },
+        });
+    }
 
-        if is_kitchen {
-            let ko_id = Uuid::new_v4();
-            sqlx::query(
-                "INSERT INTO kitchen_order_items (id, transaction_id, transaction_item_id, item_id, item_name, quantity, customer_name, completed, created_at)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, 0, ?)",
+    // Pull raw rows for the top items and aggregate.
+    let placeholders = vec!["?"; item_ids.len()].join(",");
+    let sql = format!(
+        "SELECT ti.item_id as item_id, CASE WHEN ti.unit IS NULL THEN ti.quantity / 1000 ELSE 1 END as quantity,
+         t.closed_at as closed_at
+         FROM transaction_items ti
+         JOIN transactions t ON ti.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+           AND ti.item_id IN ({})",
+        group_filter_clause(&filter, "t"),
+        placeholders
+    );
+    let mut q = sqlx::query_as::<_, (Uuid, i64, DateTime<Utc>)>(&sql)
+        .bind(start_date)
+        .bind(end_date);
+    for id in &item_ids {
+        q = q.bind(id);
+    }
+    let rows = q.fetch_all(&pool).await.map_err(db_err)?;
+
+    for (item_id, qty, closed_at) in rows {
+        let Some(&idx) = item_index.get(&item_id) else { continue };
+        // Find bucket index by linear scan with simple math
+        let bucket_idx = if use_hourly {
+            let diff = closed_at.signed_duration_since(buckets[0].bucket_start);
+            let h = diff.num_hours();
+            if h < 0 { continue; }
+            h as usize
+        } else {
+            let diff = closed_at.date_naive().signed_duration_since(buckets[0].bucket_start.date_naive());
+            let d = diff.num_days();
+            if d < 0 { continue; }
+            d as usize
+        };
+        if let Some(b) = buckets.get_mut(bucket_idx) {
+            b.quantities[idx] += qty;
+        }
+    }
+
+    Ok(ItemSalesTimeseries {
+        item_ids,
+        item_names,
+        buckets,
+        bucket_unit: if use_hourly { "hour".into() } else { "day".into() },
+    })
+}
+
+#[server]
+pub async fn fetch_revenue_timeseries(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: GroupFilter,
+) -> Result<RevenueTimeseries, ServerFnError> {
+    use chrono::{Datelike, TimeZone, Timelike};
+    if end_date <= start_date {
+        return Err(not_found("End date must be after start date"));
+    }
+    let pool = expect_context::<sqlx::SqlitePool>();
+
+    let duration = end_date.signed_duration_since(start_date);
+    let use_hourly = duration <= chrono::Duration::hours(48);
+
+    let mut buckets: Vec<RevenueBucket> = Vec::new();
+    if use_hourly {
+        let mut t = chrono::Utc
+            .with_ymd_and_hms(
+                start_date.year(),
+                start_date.month(),
+                start_date.day(),
+                start_date.hour(),
+                0,
+                0,
             )
-            .bind(ko_id)
-            .bind(id)
-            .bind(ti.id)
-            .bind(ti.item_id)
-            .bind(&ti.item_name)
-            .bind(ti.quantity)
-            .bind(&transaction.customer_name)
-            .bind(now)
-            .execute(&pool)
-            .await
-            .map_err(db_err)?;
+            .single()
+            .unwrap_or(start_date);
+        while t < end_date {
+            buckets.push(RevenueBucket {
+                bucket_start: t,
+                label: t.format("%H:%M").to_string(),
+                revenue: Money::ZERO,
+            });
+            t = t + chrono::Duration::hours(1);
+        }
+    } else {
+        let mut d = start_date.date_naive();
+        let end_d = end_date.date_naive();
+        while d <= end_d {
+            let ts = d
+                .and_hms_opt(0, 0, 0)
+                .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
+                .unwrap_or(start_date);
+            buckets.push(RevenueBucket {
+                bucket_start: ts,
+                label: d.format("%m-%d").to_string(),
+                revenue: Money::ZERO,
+            });
+            d = d.succ_opt().unwrap_or(d);
         }
     }
 
-    let transaction = sqlx::query_as::<_, Transaction>(
-        "UPDATE transactions SET status = 'closed', paid_amount = ?, change_amount = ?,
-         closed_at = ?, updated_at = ? WHERE id = ? RETURNING *",
-    )
-    .bind(paid_amount)
-    .bind(change)
-    .bind(now)
-    .bind(now)
-    .bind(id)
-    .fetch_one(&pool)
+    let rows = sqlx::query_as::<_, (Money, DateTime<Utc>)>(&format!(
+        "SELECT total, closed_at FROM transactions
+         WHERE status = 'closed' AND closed_at >= ? AND closed_at < ?{}",
+        group_filter_clause(&filter, "transactions"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(&pool)
     .await
     .map_err(db_err)?;
 
-    // Print receipt
-    if transaction.status == "closed" {
-        let receipt_items: Vec<(String, u32, f32)> = trans_items
-            .into_iter()
-            .map(|it| (it.item_name, it.quantity as u32, it.unit_price as f32))
-            .collect();
-
-        let local_now = chrono::Local::now();
-
-        // Send to remote printer clients via WebSocket
-        if let Some(printer_tx) = use_context::<tokio::sync::broadcast::Sender<
-            rustpos_common::protocol::PrintReceiptJob,
-        >>() {
-            let job = rustpos_common::protocol::PrintReceiptJob {
-                items: receipt_items.clone(),
-                paid_amount: paid_amount as f32,
-                change: change as f32,
-                datetime: local_now.format("%Y-%m-%d %H:%M:%S").to_string(),
+    let mut total_revenue = Money::ZERO;
+    if !buckets.is_empty() {
+        for (total, closed_at) in rows {
+            total_revenue += total;
+            let idx = if use_hourly {
+                let h = closed_at.signed_duration_since(buckets[0].bucket_start).num_hours();
+                if h < 0 { continue; }
+                h as usize
+            } else {
+                let d = closed_at.date_naive().signed_duration_since(buckets[0].bucket_start.date_naive()).num_days();
+                if d < 0 { continue; }
+                d as usize
             };
-            let _ = printer_tx.send(job);
-        }
-
-        // Local print (unless disabled in settings — e.g. printing handled by a
-        // remote client). Even when local printing is disabled, we still open the
-        // cash drawer if a printer is physically connected, so cash sales work
-        // without printing a local receipt.
-        let local_printing_disabled = read_disable_local_printing(&pool).await;
-        let _ = tokio::task::spawn_blocking(move || {
-            if let Ok((_, mut printer)) = find_printer() {
-                if local_printing_disabled {
-                    let _ = open_cash_drawer(&mut printer);
-                } else {
-                    let _ = print_receipt(
-                        &mut printer,
-                        receipt_items,
-                        paid_amount as f32,
-                        change as f32,
-                        local_now,
-                        Some("data/logo_receipt.png"),
-                    );
-                }
+            if let Some(b) = buckets.get_mut(idx) {
+                b.revenue += total;
             }
-        })
-        .await;
-    }
-
-    // Notify kitchen displays via WebSocket
-    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<()>>() {
-        let _ = tx.send(());
-    }
-
-    // Notify customer display — transaction closed, keep showing briefly
-    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
-        let _ = tx.send(format!("closed:{}", id));
-    }
-    // Notify other sale clients
-    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
-        let _ = sb.0.send(format!("closed:{}", id));
+        }
     }
 
-    Ok(CloseTransactionResponse {
-        transaction,
-        change_amount: change,
+    Ok(RevenueTimeseries {
+        buckets,
+        bucket_unit: if use_hourly { "hour".into() } else { "day".into() },
+        total_revenue,
     })
 }
 
 #[server]
-pub async fn cancel_transaction(id: Uuid) -> Result<Transaction, ServerFnError> {
+pub async fn fetch_basket_size_distribution(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: GroupFilter,
+) -> Result<BasketSizeDistribution, ServerFnError> {
+    if end_date <= start_date {
+        return Err(not_found("End date must be after start date"));
+    }
     let pool = expect_context::<sqlx::SqlitePool>();
-    let transaction = sqlx::query_as::<_, Transaction>(
-        "UPDATE transactions SET status = 'cancelled', updated_at = ?
-         WHERE id = ? AND status = 'open' RETURNING *",
-    )
-    .bind(Utc::now())
-    .bind(id)
-    .fetch_optional(&pool)
+
+    let rows = sqlx::query_as::<_, (Uuid, i64)>(&format!(
+        "SELECT t.id, COALESCE(SUM(CASE WHEN ti.unit IS NULL THEN ti.quantity ELSE 1000 END), 0) / 1000 as items_count
+         FROM transactions t
+         LEFT JOIN transaction_items ti ON ti.transaction_id = t.id
+         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+         GROUP BY t.id",
+        group_filter_clause(&filter, "t"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(&pool)
     .await
-    .map_err(db_err)?
-    .ok_or_else(|| not_found("Transaction not found or not open"))?;
+    .map_err(db_err)?;
 
-    // Notify customer display — clear immediately
-    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
-        let _ = tx.send("clear".to_string());
-    }
-    // Notify other sale clients
-    if let Some(sb) = use_context::<crate::SaleBroadcast>() {
-        let _ = sb.0.send(format!("cancelled:{}", id));
+    // Bucket boundaries (inclusive lower / inclusive upper, last is open-ended).
+    let edges: [(i64, i64, &str); 6] = [
+        (1, 1, "1"),
+        (2, 2, "2"),
+        (3, 3, "3"),
+        (4, 5, "4-5"),
+        (6, 9, "6-9"),
+        (10, i64::MAX, "10+"),
+    ];
+    let mut counts = [0i64; 6];
+    let mut total_items: i64 = 0;
+    let mut total_baskets: i64 = 0;
+    for (_id, qty) in &rows {
+        if *qty <= 0 { continue; }
+        total_baskets += 1;
+        total_items += qty;
+        for (i, (lo, hi, _)) in edges.iter().enumerate() {
+            if *qty >= *lo && *qty <= *hi {
+                counts[i] += 1;
+                break;
+            }
+        }
     }
 
-    Ok(transaction)
-}
+    let buckets: Vec<BasketSizeBucket> = edges
+        .iter()
+        .enumerate()
+        .map(|(i, (_, _, label))| BasketSizeBucket {
+            label: (*label).to_string(),
+            count: counts[i],
+        })
+        .collect();
 
-/// Called by the sale page to tell the customer display which transaction is active.
-#[server]
-pub async fn set_display_transaction(id: Option<Uuid>) -> Result<(), ServerFnError> {
-    if let Some(tx) = use_context::<tokio::sync::broadcast::Sender<String>>() {
-        let msg = match id {
-            Some(id) => format!("update:{}", id),
-            None => "clear".to_string(),
-        };
-        let _ = tx.send(msg);
-    }
-    Ok(())
-}
+    let average_items = if total_baskets > 0 {
+        total_items as f64 / total_baskets as f64
+    } else {
+        0.0
+    };
 
-// ---- Report Server Functions ----
+    Ok(BasketSizeDistribution {
+        buckets,
+        total_transactions: total_baskets,
+        average_items,
+    })
+}
 
 #[server]
-pub async fn fetch_sales_report(
+pub async fn fetch_payment_analysis(
     start_date: DateTime<Utc>,
     end_date: DateTime<Utc>,
     filter: GroupFilter,
-) -> Result<SalesReport, ServerFnError> {
+) -> Result<PaymentAnalysis, ServerFnError> {
+    if end_date <= start_date {
+        return Err(not_found("End date must be after start date"));
+    }
     let pool = expect_context::<sqlx::SqlitePool>();
-    generate_sales_report_db(&pool, start_date, end_date, &filter).await
-}
 
-#[server]
-pub async fn fetch_daily_report(filter: GroupFilter) -> Result<SalesReport, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let end_date = Utc::now();
-    let start_date = end_date - chrono::Duration::days(1);
-    generate_sales_report_db(&pool, start_date, end_date, &filter).await
-}
+    let rows = sqlx::query_as::<_, (Option<Money>, Option<Money>)>(&format!(
+        "SELECT paid_amount, change_amount FROM transactions
+         WHERE status = 'closed' AND refund_of IS NULL AND closed_at >= ? AND closed_at < ?{}",
+        group_filter_clause(&filter, "transactions"),
+    ))
+    .bind(start_date)
+    .bind(end_date)
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?;
 
-#[server]
-pub async fn fetch_monthly_report(filter: GroupFilter) -> Result<SalesReport, ServerFnError> {
-    let pool = expect_context::<sqlx::SqlitePool>();
-    let end_date = Utc::now();
-    let start_date = end_date - chrono::Duration::days(30);
-    generate_sales_report_db(&pool, start_date, end_date, &filter).await
+    // Bucket edges in whole currency units.
+    let edges: [(i64, Option<i64>, &str); 6] = [
+        (0, Some(0), "exact"),
+        (0, Some(1), "<1"),
+        (1, Some(5), "1-5"),
+        (5, Some(10), "5-10"),
+        (10, Some(20), "10-20"),
+        (20, None, "20+"),
+    ];
+    let unit = Money::unit(&currency_db(&pool).await?);
+    let mut counts = [0i64; 6];
+    let mut total_paid = Money::ZERO;
+    let mut total_change = Money::ZERO;
+    let mut exact_count = 0i64;
+    let mut tx_count = 0i64;
+
+    for (paid, change) in rows {
+        let paid = paid.unwrap_or_default();
+        let change = change.unwrap_or_default();
+        total_paid += paid;
+        total_change += change;
+        tx_count += 1;
+        if !change.is_positive() {
+            exact_count += 1;
+        }
+        for (i, (lo, hi, _)) in edges.iter().enumerate() {
+            // First bucket is "exact" (change == 0); others are ranges.
+            if i == 0 {
+                if !change.is_positive() {
+                    counts[0] += 1;
+                    break;
+                }
+            } else if change.0 >= lo * unit && hi.is_none_or(|hi| change.0 < hi * unit) {
+                counts[i] += 1;
+                break;
+            }
+        }
+    }
+
+    let average_change = if tx_count > 0 {
+        total_change.mul_div(1, tx_count)
+    } else {
+        Money::ZERO
+    };
+
+    let change_distribution: Vec<ChangeBucket> = edges
+        .iter()
+        .enumerate()
+        .map(|(i, (_, _, label))| ChangeBucket {
+            label: (*label).to_string(),
+            count: counts[i],
+        })
+        .collect();
+
+    let tenders = tender_totals_db(&pool, start_date, end_date, &filter).await?;
+    let cash_rounding = cash_rounding_total_db(&pool, start_date, end_date, &filter).await?;
+
+    Ok(PaymentAnalysis {
+        transaction_count: tx_count,
+        total_paid,
+        total_change,
+        average_change,
+        exact_payment_count: exact_count,
+        change_distribution,
+        tenders,
+        cash_rounding,
+    })
 }
 
 #[server]
-pub async fn fetch_item_sales_timeseries(
+pub async fn fetch_underperforming_items(
     start_date: DateTime<Utc>,
     end_date: DateTime<Utc>,
-    top_n: i64,
+    limit: i64,
     filter: GroupFilter,
-) -> Result<ItemSalesTimeseries, ServerFnError> {
-    use chrono::{Datelike, TimeZone, Timelike};
-
+) -> Result<Vec<UnderperformingItem>, ServerFnError> {
     if end_date <= start_date {
         return Err(not_found("End date must be after start date"));
     }
     let pool = expect_context::<sqlx::SqlitePool>();
 
-    // Determine bucket size: hourly for short periods, daily otherwise.
-    let duration = end_date.signed_duration_since(start_date);
-    let use_hourly = duration <= chrono::Duration::hours(48);
-
-    // Top N items by quantity within the period.
-    #[derive(sqlx::FromRow)]
-    struct TopRow {
-        item_id: Uuid,
-        item_name: String,
-    }
-    let top_items = sqlx::query_as::<_, TopRow>(&format!(
-        "SELECT i.id as item_id, i.name as item_name
-         FROM transaction_items ti
-         JOIN items i ON ti.item_id = i.id
-         JOIN transactions t ON ti.transaction_id = t.id
-         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
-         GROUP BY i.id, i.name
-         ORDER BY SUM(ti.quantity) DESC
+    let items = sqlx::query_as::<_, UnderperformingItem>(&format!(
+        "SELECT i.id as item_id, i.name as item_name,
+                COALESCE(c.name, '') as category_name,
+                i.price as price,
+                CAST(0 AS INTEGER) as quantity_sold,
+                CAST(0 AS INTEGER) as revenue,
+                i.created_at as created_at
+         FROM items i
+         LEFT JOIN categories c ON i.category_id = c.id
+         WHERE i.created_at < ?
+           AND i.id NOT IN (
+               SELECT DISTINCT ti.item_id FROM transaction_items ti
+               JOIN transactions t ON ti.transaction_id = t.id
+               WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
+           )
+         ORDER BY i.created_at ASC, i.name ASC
          LIMIT ?",
         group_filter_clause(&filter, "t"),
     ))
+    .bind(end_date)
     .bind(start_date)
     .bind(end_date)
-    .bind(top_n)
+    .bind(limit)
     .fetch_all(&pool)
     .await
     .map_err(db_err)?;
 
-    let item_ids: Vec<Uuid> = top_items.iter().map(|r| r.item_id).collect();
-    let item_names: Vec<String> = top_items.iter().map(|r| r.item_name.clone()).collect();
-    let item_index: std::collections::HashMap<Uuid, usize> = item_ids
-        .iter()
-        .enumerate()
-        .map(|(i, id)| (*id, i))
-        .collect();
+    Ok(items)
+}
 
-    // Pre-compute buckets covering the full requested range so empty periods still appear.
-    let mut buckets: Vec<TimeseriesBucket> = Vec::new();
-    let n = item_ids.len();
-    if use_hourly {
-        let mut t = chrono::Utc
-            .with_ymd_and_hms(
-                start_date.year(),
-                start_date.month(),
-                start_date.day(),
-                start_date.hour(),
-                0,
-                0,
-            )
-            .single()
-            .unwrap_or(start_date);
-        while t < end_date {
-            buckets.push(TimeseriesBucket {
-                bucket_start: t,
-                label: t.format("%H:%M").to_string(),
-                quantities: vec![0i64; n],
-            });
-            t = t + chrono::Duration::hours(1);
-        }
-    } else {
-        let mut d = start_date.date_naive();
-        let end_d = end_date.date_naive();
-        while d <= end_d {
-            let ts = d
-                .and_hms_opt(0, 0, 0)
-                .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
-                .unwrap_or(start_date);
-            buckets.push(TimeseriesBucket {
-                bucket_start: ts,
-                label: d.format("%m-%d").to_string(),
-                quantities: vec![0i64; n],
-            });
-            d = d.succ_opt().unwrap_or(d);
-        }
+#[server]
+pub async fn export_report_csv(
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+    filter: GroupFilter,
+) -> Result<String, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let report = generate_sales_report_db(&pool, start_date, end_date, &filter).await?;
+    let currency = currency_db(&pool).await?;
+    let money = |amount: Money| amount.format(&currency);
+
+    let mut csv = String::from("Item,Category,Quantity Sold,Revenue,Avg Price,Transactions,Unit\n");
+    for item in &report.items {
+        csv.push_str(&format!(
+            "\"{}\",\"{}\",{},{},{},{},{}\n",
+            item.item_name.replace('"', "\"\""),
+            item.category_name.replace('"', "\"\""),
+            item.quantity_sold,
+            money(item.total_revenue),
+            money(item.average_price),
+            item.transaction_count,
+            item.unit.as_deref().unwrap_or_default(),
+        ));
     }
+    csv.push_str(&format!(
+        "\nTotal,,{},{},,{}\n",
+        report.summary.total_items_sold,
+        money(report.summary.total_revenue),
+        report.summary.total_transactions,
+    ));
+    csv.push_str(&format!("Average Transaction Value,,,,{},\n", money(report.summary.average_transaction_value)));
 
-    if n == 0 || buckets.is_empty() {
-        return Ok(ItemSalesTimeseries {
-            item_ids,
-            item_names,
-            buckets,
-            bucket_unit: if use_hourly { "hour".into() } else { "day".into() },
-        });
+    let tenders = tender_totals_db(&pool, start_date, end_date, &filter).await?;
+    csv.push_str("\nTender,Transactions,Revenue\n");
+    for t in &tenders {
+        csv.push_str(&format!("{},{},{}\n", t.tender, t.transaction_count, money(t.amount)));
+    }
+    let cash_rounding = cash_rounding_total_db(&pool, start_date, end_date, &filter).await?;
+    if !cash_rounding.is_zero() {
+        csv.push_str(&format!("Cash rounding,,{}\n", money(cash_rounding)));
     }
 
-    // Pull raw rows for the top items and aggregate.
-    let placeholders = vec!["?"; item_ids.len()].join(",");
-    let sql = format!(
-        "SELECT ti.item_id as item_id, ti.quantity as quantity, t.closed_at as closed_at
-         FROM transaction_items ti
-         JOIN transactions t ON ti.transaction_id = t.id
-         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
-           AND ti.item_id IN ({})",
-        group_filter_clause(&filter, "t"),
-        placeholders
-    );
-    let mut q = sqlx::query_as::<_, (Uuid, i64, DateTime<Utc>)>(&sql)
-        .bind(start_date)
-        .bind(end_date);
-    for id in &item_ids {
-        q = q.bind(id);
+    csv.push_str("\nTax Rate,Net,Tax,Gross\n");
+    for t in &report.taxes {
+        csv.push_str(&format!("{}%,{},{},{}\n", t.rate, money(t.net), money(t.tax), money(t.gross)));
     }
-    let rows = q.fetch_all(&pool).await.map_err(db_err)?;
 
-    for (item_id, qty, closed_at) in rows {
-        let Some(&idx) = item_index.get(&item_id) else { continue };
-        // Find bucket index by linear scan with simple math
-        let bucket_idx = if use_hourly {
-            let diff = closed_at.signed_duration_since(buckets[0].bucket_start);
-            let h = diff.num_hours();
-            if h < 0 { continue; }
-            h as usize
-        } else {
-            let diff = closed_at.date_naive().signed_duration_since(buckets[0].bucket_start.date_naive());
-            let d = diff.num_days();
-            if d < 0 { continue; }
-            d as usize
-        };
-        if let Some(b) = buckets.get_mut(bucket_idx) {
-            b.quantities[idx] += qty;
+    if !report.modifiers.is_empty() {
+        csv.push_str("\nModifier Group,Modifier,Quantity Sold,Revenue\n");
+        for m in &report.modifiers {
+            csv.push_str(&format!(
+                "\"{}\",\"{}\",{},{}\n",
+                m.group_name.replace('"', "\"\""),
+                m.modifier_name.replace('"', "\"\""),
+                m.quantity_sold,
+                money(m.total_revenue),
+            ));
         }
     }
 
-    Ok(ItemSalesTimeseries {
-        item_ids,
-        item_names,
-        buckets,
-        bucket_unit: if use_hourly { "hour".into() } else { "day".into() },
-    })
-}
+    if !report.prices.is_empty() {
+        csv.push_str("\nItem,List Price,Valid From,Valid To,Quantity Sold,Revenue\n");
+        for p in &report.prices {
+            csv.push_str(&format!(
+                "\"{}\",{},{},{},{},{}\n",
+                p.item_name.replace('"', "\"\""),
+                money(p.list_price),
+                p.valid_from.to_rfc3339(),
+                p.valid_to.map(|t| t.to_rfc3339()).unwrap_or_default(),
+                p.quantity_sold,
+                money(p.total_revenue),
+            ));
+        }
+    }
 
-#[server]
-pub async fn fetch_revenue_timeseries(
-    start_date: DateTime<Utc>,
-    end_date: DateTime<Utc>,
-    filter: GroupFilter,
-) -> Result<RevenueTimeseries, ServerFnError> {
-    use chrono::{Datelike, TimeZone, Timelike};
-    if end_date <= start_date {
-        return Err(not_found("End date must be after start date"));
+    if !report.promotions.is_empty() {
+        csv.push_str("\nPromotion,Transactions,Discount\n");
+        for p in &report.promotions {
+            csv.push_str(&format!(
+                "\"{}\",{},{}\n",
+                p.name.replace('"', "\"\""),
+                p.transaction_count,
+                money(p.total_discount),
+            ));
+        }
     }
-    let pool = expect_context::<sqlx::SqlitePool>();
 
-    let duration = end_date.signed_duration_since(start_date);
-    let use_hourly = duration <= chrono::Duration::hours(48);
+    for (heading, rows) in [("Discount reason", &report.discount_reasons), ("Discount user", &report.discount_users)] {
+        if rows.is_empty() {
+            continue;
+        }
+        csv.push_str(&format!("\n{},Transactions,Discount\n", heading));
+        for d in rows {
+            csv.push_str(&format!(
+                "\"{}\",{},{}\n",
+                d.name.replace('"', "\"\""),
+                d.transaction_count,
+                money(d.total_discount),
+            ));
+        }
+    }
 
-    let mut buckets: Vec<RevenueBucket> = Vec::new();
-    if use_hourly {
-        let mut t = chrono::Utc
-            .with_ymd_and_hms(
-                start_date.year(),
-                start_date.month(),
-                start_date.day(),
-                start_date.hour(),
-                0,
-                0,
-            )
-            .single()
-            .unwrap_or(start_date);
-        while t < end_date {
-            buckets.push(RevenueBucket {
-                bucket_start: t,
-                label: t.format("%H:%M").to_string(),
-                revenue: 0.0,
-            });
-            t = t + chrono::Duration::hours(1);
+    let g = &report.gift_cards;
+    if [g.topped_up, g.redeemed, g.refunded, g.outstanding].iter().any(|a| !a.is_zero()) {
+        csv.push_str("\nGift Cards,Amount\n");
+        csv.push_str(&format!("Topped up,{}\n", money(g.topped_up)));
+        csv.push_str(&format!("Redeemed,{}\n", money(g.redeemed)));
+        csv.push_str(&format!("Refunded,{}\n", money(g.refunded)));
+        csv.push_str(&format!("Outstanding,{}\n", money(g.outstanding)));
+    }
+
+    let d = &report.deposits;
+    if !d.items.is_empty() {
+        csv.push_str("\nDeposit,Charged Qty,Charged,Returned Qty,Returned\n");
+        for row in &d.items {
+            csv.push_str(&format!(
+                "\"{}\",{},{},{},{}\n",
+                row.item_name.replace('"', "\"\""),
+                row.charged_quantity,
+                money(row.charged),
+                row.returned_quantity,
+                money(row.returned),
+            ));
         }
-    } else {
-        let mut d = start_date.date_naive();
-        let end_d = end_date.date_naive();
-        while d <= end_d {
-            let ts = d
-                .and_hms_opt(0, 0, 0)
-                .map(|dt| DateTime::<Utc>::from_naive_utc_and_offset(dt, Utc))
-                .unwrap_or(start_date);
-            buckets.push(RevenueBucket {
-                bucket_start: ts,
-                label: d.format("%m-%d").to_string(),
-                revenue: 0.0,
-            });
-            d = d.succ_opt().unwrap_or(d);
+        csv.push_str(&format!("Net deposit liability,,,,{}\n", money(d.net_liability)));
+    }
+
+    let t = &report.tips;
+    if !t.days.is_empty() {
+        csv.push_str("\nTip day,User,Transactions,Tips\n");
+        for share in &t.days {
+            csv.push_str(&format!(
+                "{},\"{}\",{},{}\n",
+                share.day,
+                share.user_name.replace('"', "\"\""),
+                share.transaction_count,
+                money(share.amount),
+            ));
+        }
+        for share in &t.users {
+            csv.push_str(&format!(
+                "Total,\"{}\",{},{}\n",
+                share.user_name.replace('"', "\"\""),
+                share.transaction_count,
+                money(share.amount),
+            ));
         }
+        csv.push_str(&format!("Total,,,{}\n", money(t.total)));
     }
+    Ok(csv)
+}
 
-    let rows = sqlx::query_as::<_, (f64, DateTime<Utc>)>(&format!(
-        "SELECT total, closed_at FROM transactions
-         WHERE status = 'closed' AND closed_at >= ? AND closed_at < ?{}",
-        group_filter_clause(&filter, "transactions"),
-    ))
-    .bind(start_date)
-    .bind(end_date)
-    .fetch_all(&pool)
+/// Gathers the sales finished between the dates for the DSFinV-K export,
+/// grouped by the Z-closing whose period they were finished in, under its
+/// number, as on the printed Z-report. Sales not closed with a Z-closing yet
+/// are left out until they are.
+#[cfg(feature = "ssr")]
+async fn dsfinvk_export_db(
+    pool: &sqlx::SqlitePool,
+    start_date: DateTime<Utc>,
+    end_date: DateTime<Utc>,
+) -> Result<crate::dsfinvk::Export, ServerFnError> {
+    use crate::dsfinvk::{Closing, Export, Receipt, Reference};
+    use std::collections::HashMap;
+
+    let numbers: HashMap<Uuid, i64> = sqlx::query_as::<_, (Uuid, i64)>("SELECT transaction_id, seq FROM journal")
+        .fetch_all(pool)
+        .await
+        .map_err(db_err)?
+        .into_iter()
+        .collect();
+    let z_closings: Vec<ZClosing> = sqlx::query_as::<_, (i64, String, String, DateTime<Utc>)>(
+        "SELECT number, closed_by, totals, created_at FROM z_closings ORDER BY number",
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?
+    .into_iter()
+    .map(z_closing_from_row)
+    .collect::<Result<_, _>>()?;
+    let finished = sqlx::query_as::<_, Transaction>(
+        "SELECT * FROM transactions WHERE status IN ('closed', 'cancelled')",
+    )
+    .fetch_all(pool)
     .await
     .map_err(db_err)?;
-
-    let mut total_revenue = 0.0_f64;
-    if !buckets.is_empty() {
-        for (total, closed_at) in rows {
-            total_revenue += total;
-            let idx = if use_hourly {
-                let h = closed_at.signed_duration_since(buckets[0].bucket_start).num_hours();
-                if h < 0 { continue; }
-                h as usize
-            } else {
-                let d = closed_at.date_naive().signed_duration_since(buckets[0].bucket_start.date_naive()).num_days();
-                if d < 0 { continue; }
-                d as usize
+    let finished_at = |t: &Transaction| t.closed_at.unwrap_or(t.updated_at);
+    // The Z-closing a sale was counted in, as `open_closing_totals_db` counts.
+    let closed_in = |t: &Transaction| {
+        let at = finished_at(t);
+        z_closings
+            .iter()
+            .find(|z| z.totals.period_start <= at && at < z.totals.period_end)
+    };
+    let originals: HashMap<Uuid, Reference> = finished
+        .iter()
+        .map(|t| {
+            let reference = Reference {
+                closing: closed_in(t).map_or(0, |z| z.number),
+                transaction_id: t.id,
+                finished_at: finished_at(t),
             };
-            if let Some(b) = buckets.get_mut(idx) {
-                b.revenue += total;
-            }
+            (t.id, reference)
+        })
+        .collect();
+
+    let mut closings: Vec<Closing> = Vec::new();
+    let mut in_period: Vec<Transaction> = finished
+        .iter()
+        .filter(|t| finished_at(t) >= start_date && finished_at(t) < end_date)
+        .cloned()
+        .collect();
+    in_period.sort_by_key(|t| (numbers.get(&t.id).copied().unwrap_or(i64::MAX), finished_at(t)));
+    for transaction in in_period {
+        let Some(z) = closed_in(&transaction) else {
+            continue;
+        };
+        let id = transaction.id;
+        let payments = sqlx::query_as::<_, Payment>("SELECT * FROM payments WHERE transaction_id = ? ORDER BY created_at")
+            .bind(id)
+            .fetch_all(pool)
+            .await
+            .map_err(db_err)?;
+        let lines = transaction_lines_db(&mut *pool.acquire().await.map_err(db_err)?, id).await?;
+        let receipt = Receipt {
+            number: numbers.get(&id).copied().unwrap_or_default(),
+            lines,
+            payments,
+            gift_card_loads: gift_card_loads_db(pool, id).await?,
+            refund_of: transaction.refund_of.and_then(|original| originals.get(&original)).cloned(),
+            transaction,
+        };
+        if !closings.iter().any(|c| c.number == z.number) {
+            // The business day is that of the last sale in the closing.
+            let last_sale = finished
+                .iter()
+                .map(finished_at)
+                .filter(|at| z.totals.period_start <= *at && *at < z.totals.period_end)
+                .max()
+                .unwrap_or(z.created_at);
+            closings.push(Closing {
+                number: z.number,
+                business_day: last_sale.with_timezone(&chrono::Local).date_naive(),
+                created_at: z.created_at,
+                receipts: Vec::new(),
+            });
+        }
+        if let Some(closing) = closings.iter_mut().find(|c| c.number == z.number) {
+            closing.receipts.push(receipt);
         }
     }
+    closings.sort_by_key(|c| c.number);
 
-    Ok(RevenueTimeseries {
-        buckets,
-        bucket_unit: if use_hourly { "hour".into() } else { "day".into() },
-        total_revenue,
+    // The signers of the exported receipts. Only the one in use can tell its
+    // algorithm and public key.
+    let current = use_context::<crate::fiscal::FiscalSignerHandle>().map(|signer| signer.0.info());
+    let mut signers: Vec<crate::fiscal::SignerInfo> = Vec::new();
+    for serial in closings
+        .iter()
+        .flat_map(|c| &c.receipts)
+        .filter_map(|r| r.transaction.fiscal_signer.clone())
+    {
+        if signers.iter().any(|s| s.serial == serial) {
+            continue;
+        }
+        signers.push(match &current {
+            Some(info) if info.serial == serial => info.clone(),
+            _ => crate::fiscal::SignerInfo { serial, ..Default::default() },
+        });
+    }
+
+    Ok(Export {
+        business: read_business_details(pool).await?,
+        currency: currency_db(pool).await?,
+        software_version: env!("CARGO_PKG_VERSION").to_string(),
+        signers,
+        closings,
     })
 }
 
+/// Builds the DSFinV-K export of the sales finished between the dates for
+/// tax auditors and returns the ZIP archive base64-encoded. See
+/// [`crate::dsfinvk`].
 #[server]
-pub async fn fetch_basket_size_distribution(
+pub async fn export_dsfinvk(
     start_date: DateTime<Utc>,
     end_date: DateTime<Utc>,
-    filter: GroupFilter,
-) -> Result<BasketSizeDistribution, ServerFnError> {
-    if end_date <= start_date {
-        return Err(not_found("End date must be after start date"));
-    }
+) -> Result<String, ServerFnError> {
+    use base64::Engine;
+
     let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let export = dsfinvk_export_db(&pool, start_date, end_date).await?;
+    let archive = tokio::task::spawn_blocking(move || crate::dsfinvk::build_archive(&export))
+        .await
+        .map_err(|e| ServerFnError::new(e.to_string()))?
+        .map_err(ServerFnError::new)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(&archive))
+}
 
-    let rows = sqlx::query_as::<_, (Uuid, i64)>(&format!(
-        "SELECT t.id, COALESCE(SUM(ti.quantity), 0) as items_count
-         FROM transactions t
-         LEFT JOIN transaction_items ti ON ti.transaction_id = t.id
-         WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
-         GROUP BY t.id",
-        group_filter_clause(&filter, "t"),
-    ))
-    .bind(start_date)
-    .bind(end_date)
-    .fetch_all(&pool)
+// ---- Closing Server Functions ----
+
+/// The last Z-closing, if the day was ever closed.
+#[cfg(feature = "ssr")]
+async fn last_z_closing_db(pool: &sqlx::SqlitePool) -> Result<Option<ZClosing>, ServerFnError> {
+    let row = sqlx::query_as::<_, (i64, String, String, DateTime<Utc>)>(
+        "SELECT number, closed_by, totals, created_at FROM z_closings ORDER BY number DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
     .await
     .map_err(db_err)?;
+    row.map(z_closing_from_row).transpose()
+}
 
-    // Bucket boundaries (inclusive lower / inclusive upper, last is open-ended).
-    let edges: [(i64, i64, &str); 6] = [
-        (1, 1, "1"),
-        (2, 2, "2"),
-        (3, 3, "3"),
-        (4, 5, "4-5"),
-        (6, 9, "6-9"),
-        (10, i64::MAX, "10+"),
-    ];
-    let mut counts = [0i64; 6];
-    let mut total_items: i64 = 0;
-    let mut total_baskets: i64 = 0;
-    for (_id, qty) in &rows {
-        if *qty <= 0 { continue; }
-        total_baskets += 1;
-        total_items += qty;
-        for (i, (lo, hi, _)) in edges.iter().enumerate() {
-            if *qty >= *lo && *qty <= *hi {
-                counts[i] += 1;
-                break;
-            }
-        }
-    }
+#[cfg(feature = "ssr")]
+fn z_closing_from_row(
+    (number, closed_by, totals, created_at): (i64, String, String, DateTime<Utc>),
+) -> Result<ZClosing, ServerFnError> {
+    let totals = serde_json::from_str(&totals).map_err(db_err)?;
+    Ok(ZClosing { number, closed_by, created_at, totals })
+}
 
-    let buckets: Vec<BasketSizeBucket> = edges
-        .iter()
-        .enumerate()
-        .map(|(i, (_, _, label))| BasketSizeBucket {
-            label: (*label).to_string(),
-            count: counts[i],
-        })
-        .collect();
+/// The totals of the sales finished since the last Z-closing, up to `end`.
+/// Before the first closing, every sale counts.
+#[cfg(feature = "ssr")]
+async fn open_closing_totals_db(
+    pool: &sqlx::SqlitePool,
+    end: DateTime<Utc>,
+) -> Result<ClosingTotals, ServerFnError> {
+    let start = match last_z_closing_db(pool).await? {
+        Some(closing) => closing.totals.period_end,
+        None => sqlx::query_scalar::<_, Option<DateTime<Utc>>>("SELECT MIN(created_at) FROM transactions")
+            .fetch_one(pool)
+            .await
+            .map_err(db_err)?
+            .unwrap_or(end),
+    };
+    let sales = sqlx::query_as::<_, ClosingSale>(
+        "SELECT status, refund_of, closed_by, total, tip_amount, rounding_amount FROM transactions
+         WHERE (status = 'closed' AND closed_at >= ? AND closed_at < ?)
+            OR (status = 'cancelled' AND updated_at >= ? AND updated_at < ?)",
+    )
+    .bind(start)
+    .bind(end)
+    .bind(start)
+    .bind(end)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let tenders = tender_totals_db(pool, start, end, &GroupFilter::All).await?;
+    let taxes = tax_totals_db(pool, start, end, &GroupFilter::All).await?;
+    Ok(ClosingTotals::from_sales(start, end, &sales, tenders, taxes))
+}
 
-    let average_items = if total_baskets > 0 {
-        total_items as f64 / total_baskets as f64
-    } else {
-        0.0
+/// The X-report: the totals since the last Z-closing so far, without
+/// closing anything (admin only).
+#[server]
+pub async fn fetch_x_report() -> Result<ClosingTotals, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    open_closing_totals_db(&pool, Utc::now()).await
+}
+
+/// Closes the day: freezes the totals since the last Z-closing under the
+/// next number (admin only). Sales finished from now on count towards the
+/// next closing.
+#[server]
+pub async fn create_z_closing() -> Result<ZClosing, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let user = require_admin(&pool).await?;
+    create_z_closing_db(&pool, user.username).await
+}
+
+#[cfg(feature = "ssr")]
+async fn create_z_closing_db(pool: &sqlx::SqlitePool, closed_by: String) -> Result<ZClosing, ServerFnError> {
+    let number = last_z_closing_db(pool).await?.map_or(1, |c| c.number + 1);
+    let now = Utc::now();
+    let totals = open_closing_totals_db(pool, now).await?;
+    // A closing made at the same time takes the number first; the primary
+    // key then refuses this one instead of leaving two over the same sales.
+    sqlx::query("INSERT INTO z_closings (number, closed_by, totals, created_at) VALUES (?, ?, ?, ?)")
+        .bind(number)
+        .bind(&closed_by)
+        .bind(serde_json::to_string(&totals).map_err(db_err)?)
+        .bind(now)
+        .execute(pool)
+        .await
+        .map_err(db_err)?;
+    Ok(ZClosing { number, closed_by, created_at: now, totals })
+}
+
+/// Every Z-closing, the latest first (admin only).
+#[server]
+pub async fn fetch_z_closings() -> Result<Vec<ZClosing>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    sqlx::query_as::<_, (i64, String, String, DateTime<Utc>)>(
+        "SELECT number, closed_by, totals, created_at FROM z_closings ORDER BY number DESC",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)?
+    .into_iter()
+    .map(z_closing_from_row)
+    .collect()
+}
+
+/// Prints closing totals on the local receipt printer under `title`.
+#[cfg(feature = "ssr")]
+async fn print_closing_db(
+    pool: &sqlx::SqlitePool,
+    title: String,
+    closed_by: Option<String>,
+    totals: &ClosingTotals,
+    open_drawer: bool,
+) -> Result<(), ServerFnError> {
+    use crate::printer::{find_printer, print_closing, ClosingSummary};
+
+    let currency = currency_db(pool).await?;
+    let local = |t: DateTime<Utc>| t.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M").to_string();
+    let summary = ClosingSummary {
+        title,
+        period: format!("{} to {}", local(totals.period_start), local(totals.period_end)),
+        closed_by,
+        decimals: currency_decimals(&currency),
+        currency,
+        revenue: totals.revenue.0,
+        transaction_count: totals.transaction_count,
+        refund_count: totals.refund_count,
+        refunds: totals.refunds.0,
+        cancellation_count: totals.cancellation_count,
+        cancellations: totals.cancellations.0,
+        tips: totals.tips.0,
+        rounding: totals.rounding.0,
+        tenders: totals.tenders.iter().map(|t| (t.tender.clone(), t.transaction_count, t.amount.0)).collect(),
+        taxes: tax_summaries(&totals.taxes),
+        users: totals.users.iter().map(|u| (u.user_name.clone(), u.transaction_count, u.amount.0)).collect(),
     };
+    let now = chrono::Local::now();
 
-    Ok(BasketSizeDistribution {
-        buckets,
-        total_transactions: total_baskets,
-        average_items,
+    let result: Result<(), String> = tokio::task::spawn_blocking(move || {
+        let (_, mut printer) = find_printer().map_err(|e| e.to_string())?;
+        print_closing(&mut printer, &summary, open_drawer, now, Some("data/logo_receipt.png"))
+            .map_err(|e| e.to_string())
     })
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?;
+    result.map_err(ServerFnError::new)
 }
 
+/// Prints the X-report and opens the cash drawer for counting (admin only).
 #[server]
-pub async fn fetch_payment_analysis(
-    start_date: DateTime<Utc>,
-    end_date: DateTime<Utc>,
-    filter: GroupFilter,
-) -> Result<PaymentAnalysis, ServerFnError> {
-    if end_date <= start_date {
-        return Err(not_found("End date must be after start date"));
-    }
+pub async fn print_x_report() -> Result<(), ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let totals = open_closing_totals_db(&pool, Utc::now()).await?;
+    print_closing_db(&pool, "X-Report".to_string(), None, &totals, true).await
+}
 
-    let rows = sqlx::query_as::<_, (Option<f64>, Option<f64>)>(&format!(
-        "SELECT paid_amount, change_amount FROM transactions
-         WHERE status = 'closed' AND closed_at >= ? AND closed_at < ?{}",
-        group_filter_clause(&filter, "transactions"),
-    ))
-    .bind(start_date)
-    .bind(end_date)
-    .fetch_all(&pool)
+/// Prints a Z-closing, when it is made or again later (admin only).
+#[server]
+pub async fn print_z_closing(number: i64) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    let row = sqlx::query_as::<_, (i64, String, String, DateTime<Utc>)>(
+        "SELECT number, closed_by, totals, created_at FROM z_closings WHERE number = ?",
+    )
+    .bind(number)
+    .fetch_optional(&pool)
+    .await
+    .map_err(db_err)?
+    .ok_or_else(|| not_found("Z-closing not found"))?;
+    let closing = z_closing_from_row(row)?;
+    let title = format!("Z-Report #{}", closing.number);
+    print_closing_db(&pool, title, Some(closing.closed_by), &closing.totals, false).await
+}
+
+// ---- Cash Drawer Server Functions ----
+
+/// The drawer session that has not been counted yet, if any.
+#[cfg(feature = "ssr")]
+async fn open_drawer_session_db(pool: &sqlx::SqlitePool) -> Result<Option<DrawerSession>, ServerFnError> {
+    sqlx::query_as::<_, DrawerSession>(
+        "SELECT * FROM drawer_sessions WHERE closed_at IS NULL ORDER BY opened_at DESC LIMIT 1",
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(db_err)
+}
+
+/// A drawer session with its movements and the cash taken and paid out
+/// while it was open, up to now if it still is.
+#[cfg(feature = "ssr")]
+async fn drawer_summary_db(pool: &sqlx::SqlitePool, session: DrawerSession) -> Result<DrawerSummary, ServerFnError> {
+    let start = session.opened_at;
+    let end = session.closed_at.unwrap_or_else(Utc::now);
+    let movements = sqlx::query_as::<_, CashMovement>(
+        "SELECT * FROM cash_movements WHERE session_id = ? ORDER BY created_at",
+    )
+    .bind(session.id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    let cash_sales = tender_totals_db(pool, start, end, &GroupFilter::All)
+        .await?
+        .into_iter()
+        .find(|t| t.tender == "cash")
+        .map_or(Money::ZERO, |t| t.amount);
+    let cash_settlements: Money = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0) FROM account_settlements
+         WHERE tender = 'cash' AND created_at >= ? AND created_at < ?",
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)
+    .await
+    .map_err(db_err)?;
+    // Balance refunds are paid out in cash; see `refund_gift_card`.
+    let gift_card_payouts: Money = sqlx::query_scalar(
+        "SELECT COALESCE(SUM(amount), 0) FROM gift_card_entries
+         WHERE kind = 'refund' AND created_at >= ? AND created_at < ?",
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)
     .await
     .map_err(db_err)?;
+    let counts = sqlx::query_as::<_, DenominationCount>(
+        "SELECT denomination, quantity FROM drawer_counts WHERE session_id = ? ORDER BY denomination DESC",
+    )
+    .bind(session.id)
+    .fetch_all(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(DrawerSummary { session, movements, cash_sales, cash_settlements, gift_card_payouts, counts })
+}
 
-    let edges: [(f64, f64, &str); 6] = [
-        (0.0, 0.0, "exact"),
-        (0.01, 1.0, "<1"),
-        (1.0, 5.0, "1-5"),
-        (5.0, 10.0, "5-10"),
-        (10.0, 20.0, "10-20"),
-        (20.0, f64::INFINITY, "20+"),
-    ];
-    let mut counts = [0i64; 6];
-    let mut total_paid = 0.0_f64;
-    let mut total_change = 0.0_f64;
-    let mut exact_count = 0i64;
-    let mut tx_count = 0i64;
+/// Opens the cash drawer on the local printer, if one is attached.
+#[cfg(feature = "ssr")]
+async fn kick_cash_drawer() {
+    use crate::printer::{find_printer, open_cash_drawer};
 
-    for (paid, change) in rows {
-        let paid = paid.unwrap_or(0.0);
-        let change = change.unwrap_or(0.0);
-        total_paid += paid;
-        total_change += change;
-        tx_count += 1;
-        if change <= 0.001 {
-            exact_count += 1;
-        }
-        for (i, (lo, hi, _)) in edges.iter().enumerate() {
-            // First bucket is "exact" (change == 0); others are ranges.
-            if i == 0 {
-                if change <= 0.001 {
-                    counts[0] += 1;
-                    break;
-                }
-            } else if change >= *lo && (change < *hi || hi.is_infinite()) {
-                counts[i] += 1;
-                break;
-            }
+    let _ = tokio::task::spawn_blocking(|| {
+        if let Ok((_, mut printer)) = find_printer() {
+            let _ = open_cash_drawer(&mut printer);
         }
-    }
+    })
+    .await;
+}
 
-    let average_change = if tx_count > 0 {
-        total_change / tx_count as f64
-    } else {
-        0.0
-    };
+#[cfg(feature = "ssr")]
+async fn insert_cash_movement_db(
+    pool: &sqlx::SqlitePool,
+    session_id: Uuid,
+    kind: &str,
+    amount: Money,
+    reason: Option<String>,
+    user_name: &str,
+) -> Result<(), ServerFnError> {
+    sqlx::query(
+        "INSERT INTO cash_movements (id, session_id, kind, amount, reason, user_name, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(session_id)
+    .bind(kind)
+    .bind(amount)
+    .bind(reason)
+    .bind(user_name)
+    .bind(Utc::now())
+    .execute(pool)
+    .await
+    .map_err(db_err)?;
+    Ok(())
+}
 
-    let change_distribution: Vec<ChangeBucket> = edges
-        .iter()
-        .enumerate()
-        .map(|(i, (_, _, label))| ChangeBucket {
-            label: (*label).to_string(),
-            count: counts[i],
-        })
-        .collect();
+/// The open drawer session and what the drawer should hold now, or `None`
+/// if the drawer was counted and not opened again since.
+#[server]
+pub async fn fetch_drawer() -> Result<Option<DrawerSummary>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    match open_drawer_session_db(&pool).await? {
+        Some(session) => Ok(Some(drawer_summary_db(&pool, session).await?)),
+        None => Ok(None),
+    }
+}
 
-    Ok(PaymentAnalysis {
-        transaction_count: tx_count,
-        total_paid,
-        total_change,
-        average_change,
-        exact_payment_count: exact_count,
-        change_distribution,
-    })
+/// Starts a drawer session with the opening float put into the drawer.
+#[server]
+pub async fn open_drawer_session(float: Money) -> Result<DrawerSummary, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let user = get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    if float.is_negative() {
+        return Err(not_found("The float cannot be negative"));
+    }
+    if open_drawer_session_db(&pool).await?.is_some() {
+        return Err(not_found("The drawer is already open; count it first"));
+    }
+    let session = sqlx::query_as::<_, DrawerSession>(
+        "INSERT INTO drawer_sessions (id, opened_by, opened_at) VALUES (?, ?, ?) RETURNING *",
+    )
+    .bind(Uuid::new_v4())
+    .bind(&user.username)
+    .bind(Utc::now())
+    .fetch_one(&pool)
+    .await
+    .map_err(db_err)?;
+    insert_cash_movement_db(&pool, session.id, "float", float, None, &user.username).await?;
+    kick_cash_drawer().await;
+    drawer_summary_db(&pool, session).await
 }
 
+/// Records cash put into or taken out of the open drawer: a pay-in, a
+/// pay-out (which needs a reason) or a safe drop.
 #[server]
-pub async fn fetch_underperforming_items(
-    start_date: DateTime<Utc>,
-    end_date: DateTime<Utc>,
-    limit: i64,
-    filter: GroupFilter,
-) -> Result<Vec<UnderperformingItem>, ServerFnError> {
-    if end_date <= start_date {
-        return Err(not_found("End date must be after start date"));
-    }
+pub async fn add_cash_movement(
+    kind: String,
+    amount: Money,
+    reason: Option<String>,
+) -> Result<DrawerSummary, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
+    let user = get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    if !CASH_MOVEMENT_KINDS.contains(&kind.as_str()) || kind == "float" {
+        return Err(not_found(&format!("Unknown cash movement: {}", kind)));
+    }
+    if !amount.is_positive() {
+        return Err(not_found("Enter an amount above zero"));
+    }
+    let reason = reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+    if kind == "pay_out" && reason.is_none() {
+        return Err(not_found("Enter what the cash is paid out for"));
+    }
+    let session = open_drawer_session_db(&pool)
+        .await?
+        .ok_or_else(|| not_found("Open the drawer with a float first"))?;
+    insert_cash_movement_db(&pool, session.id, &kind, amount, reason, &user.username).await?;
+    kick_cash_drawer().await;
+    drawer_summary_db(&pool, session).await
+}
 
-    let items = sqlx::query_as::<_, UnderperformingItem>(&format!(
-        "SELECT i.id as item_id, i.name as item_name,
-                COALESCE(c.name, '') as category_name,
-                i.price as price,
-                CAST(0 AS INTEGER) as quantity_sold,
-                CAST(0 AS REAL) as revenue,
-                i.created_at as created_at
-         FROM items i
-         LEFT JOIN categories c ON i.category_id = c.id
-         WHERE i.created_at < ?
-           AND i.id NOT IN (
-               SELECT DISTINCT ti.item_id FROM transaction_items ti
-               JOIN transactions t ON ti.transaction_id = t.id
-               WHERE t.status = 'closed' AND t.closed_at >= ? AND t.closed_at < ?{}
-           )
-         ORDER BY i.created_at ASC, i.name ASC
-         LIMIT ?",
-        group_filter_clause(&filter, "t"),
-    ))
-    .bind(end_date)
-    .bind(start_date)
-    .bind(end_date)
-    .bind(limit)
-    .fetch_all(&pool)
+/// Counts the open drawer and closes its session, recording what it
+/// should have held, what was counted and the variance between them.
+#[server]
+pub async fn count_drawer(counts: Vec<DenominationCount>) -> Result<DrawerSummary, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let user = get_authenticated_user(&pool).await?
+        .ok_or_else(|| not_found("Not authenticated"))?;
+    if counts.iter().any(|c| c.quantity < 0 || !c.denomination.is_positive()) {
+        return Err(not_found("Counts cannot be negative"));
+    }
+    let mut session = open_drawer_session_db(&pool)
+        .await?
+        .ok_or_else(|| not_found("The drawer is not open"))?;
+    let now = Utc::now();
+    session.closed_at = Some(now);
+    let expected = drawer_summary_db(&pool, session.clone()).await?.expected();
+    let counted = counted_total(&counts);
+
+    let mut tx = pool.begin().await.map_err(db_err)?;
+    let closed = sqlx::query(
+        "UPDATE drawer_sessions SET closed_by = ?, closed_at = ?, expected = ?, counted = ?, variance = ?
+         WHERE id = ? AND closed_at IS NULL",
+    )
+    .bind(&user.username)
+    .bind(now)
+    .bind(expected)
+    .bind(counted)
+    .bind(counted - expected)
+    .bind(session.id)
+    .execute(&mut *tx)
     .await
     .map_err(db_err)?;
+    if closed.rows_affected() == 0 {
+        return Err(not_found("The drawer was counted already"));
+    }
+    for count in counts.iter().filter(|c| c.quantity > 0) {
+        sqlx::query("INSERT INTO drawer_counts (session_id, denomination, quantity) VALUES (?, ?, ?)")
+            .bind(session.id)
+            .bind(count.denomination)
+            .bind(count.quantity)
+            .execute(&mut *tx)
+            .await
+            .map_err(db_err)?;
+    }
+    tx.commit().await.map_err(db_err)?;
 
-    Ok(items)
+    let session = sqlx::query_as::<_, DrawerSession>("SELECT * FROM drawer_sessions WHERE id = ?")
+        .bind(session.id)
+        .fetch_one(&pool)
+        .await
+        .map_err(db_err)?;
+    drawer_summary_db(&pool, session).await
 }
 
+/// Counted drawer sessions with their variance, the latest first (admin
+/// only).
 #[server]
-pub async fn export_report_csv(
-    start_date: DateTime<Utc>,
-    end_date: DateTime<Utc>,
-    filter: GroupFilter,
-) -> Result<String, ServerFnError> {
+pub async fn fetch_drawer_sessions() -> Result<Vec<DrawerSession>, ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    let report = generate_sales_report_db(&pool, start_date, end_date, &filter).await?;
-
-    let mut csv = String::from("Item,Category,Quantity Sold,Revenue,Avg Price,Transactions\n");
-    for item in &report.items {
-        csv.push_str(&format!(
-            "\"{}\",\"{}\",{},{:.2},{:.2},{}\n",
-            item.item_name.replace('"', "\"\""),
-            item.category_name.replace('"', "\"\""),
-            item.quantity_sold,
-            item.total_revenue,
-            item.average_price,
-            item.transaction_count,
-        ));
-    }
-    csv.push_str(&format!(
-        "\nTotal,,{},{:.2},,{}\n",
-        report.summary.total_items_sold,
-        report.summary.total_revenue,
-        report.summary.total_transactions,
-    ));
-    csv.push_str(&format!("Average Transaction Value,,,,{:.2},\n", report.summary.average_transaction_value));
-    Ok(csv)
+    require_admin(&pool).await?;
+    sqlx::query_as::<_, DrawerSession>(
+        "SELECT * FROM drawer_sessions WHERE closed_at IS NOT NULL ORDER BY closed_at DESC",
+    )
+    .fetch_all(&pool)
+    .await
+    .map_err(db_err)
 }
 
 // ---- Kitchen Server Functions ----
@@ -1727,8 +7351,11 @@ pub async fn fetch_kitchen_orders() -> Result<Vec<KitchenOrder>, ServerFnError>
         transaction_id: Uuid,
         transaction_item_id: Uuid,
         item_name: String,
-        quantity: i32,
+        quantity: Quantity,
         customer_name: Option<String>,
+        table_name: Option<String>,
+        modifiers: Option<String>,
+        note: Option<String>,
         completed: bool,
         created_at: DateTime<Utc>,
     }
@@ -1759,6 +7386,8 @@ pub async fn fetch_kitchen_orders() -> Result<Vec<KitchenOrder>, ServerFnError>
             transaction_item_id: row.transaction_item_id,
             item_name: row.item_name,
             quantity: row.quantity,
+            modifiers: row.modifiers,
+            note: row.note,
             completed: row.completed,
         };
         if let Some(order) = existing {
@@ -1767,6 +7396,7 @@ pub async fn fetch_kitchen_orders() -> Result<Vec<KitchenOrder>, ServerFnError>
             orders.push(KitchenOrder {
                 transaction_id: row.transaction_id,
                 customer_name: row.customer_name,
+                table_name: row.table_name,
                 created_at: row.created_at,
                 items: vec![item],
             });
@@ -1824,8 +7454,11 @@ pub async fn fetch_completed_kitchen_orders() -> Result<Vec<KitchenOrder>, Serve
         transaction_id: Uuid,
         transaction_item_id: Uuid,
         item_name: String,
-        quantity: i32,
+        quantity: Quantity,
         customer_name: Option<String>,
+        table_name: Option<String>,
+        modifiers: Option<String>,
+        note: Option<String>,
         completed: bool,
         created_at: DateTime<Utc>,
     }
@@ -1849,6 +7482,8 @@ pub async fn fetch_completed_kitchen_orders() -> Result<Vec<KitchenOrder>, Serve
             transaction_item_id: row.transaction_item_id,
             item_name: row.item_name,
             quantity: row.quantity,
+            modifiers: row.modifiers,
+            note: row.note,
             completed: row.completed,
         };
         if let Some(order) = existing {
@@ -1857,6 +7492,7 @@ pub async fn fetch_completed_kitchen_orders() -> Result<Vec<KitchenOrder>, Serve
             orders.push(KitchenOrder {
                 transaction_id: row.transaction_id,
                 customer_name: row.customer_name,
+                table_name: row.table_name,
                 created_at: row.created_at,
                 items: vec![item],
             });
@@ -2239,28 +7875,14 @@ pub async fn get_config_currency() -> Result<Option<String>, ServerFnError> {
 #[server]
 pub async fn set_config_currency(currency: String) -> Result<(), ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
-    sqlx::query(
-        "INSERT INTO config (key, value) VALUES ('currency', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-    )
-    .bind(&currency)
-    .execute(&pool)
-    .await
-    .map_err(db_err)?;
-    Ok(())
+    store_currency_db(&pool, &currency).await
 }
 
 #[server]
 pub async fn set_currency_admin(currency: String) -> Result<(), ServerFnError> {
     let pool = expect_context::<sqlx::SqlitePool>();
     require_admin(&pool).await?;
-    sqlx::query(
-        "INSERT INTO config (key, value) VALUES ('currency', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
-    )
-    .bind(&currency)
-    .execute(&pool)
-    .await
-    .map_err(db_err)?;
-    Ok(())
+    store_currency_db(&pool, &currency).await
 }
 
 #[server]
@@ -2372,6 +7994,71 @@ pub async fn set_disable_local_printing(disabled: bool) -> Result<(), ServerFnEr
     Ok(())
 }
 
+/// The serial port the scale is attached to, if one is set up.
+#[cfg(feature = "ssr")]
+async fn read_scale_port(pool: &sqlx::SqlitePool) -> Option<String> {
+    sqlx::query_scalar::<_, String>("SELECT value FROM config WHERE key = 'scale_port'")
+        .fetch_optional(pool)
+        .await
+        .ok()
+        .flatten()
+        .filter(|v| !v.is_empty())
+}
+
+#[server]
+pub async fn get_scale_port() -> Result<Option<String>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    Ok(read_scale_port(&pool).await)
+}
+
+/// Sets the scale's serial port; an empty name removes the scale.
+#[server]
+pub async fn set_scale_port(port: String) -> Result<(), ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    sqlx::query(
+        "INSERT INTO config (key, value) VALUES ('scale_port', ?) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+    )
+    .bind(port.trim())
+    .execute(&pool)
+    .await
+    .map_err(db_err)?;
+    Ok(())
+}
+
+/// Serial ports found on the server, to pick the scale's port from.
+#[server]
+pub async fn list_serial_ports() -> Result<Vec<String>, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    require_admin(&pool).await?;
+    Ok(tokio::task::spawn_blocking(rustpos_common::scale::list_ports)
+        .await
+        .unwrap_or_default())
+}
+
+/// Whether a scale is set up, so the sale screen can offer to read it.
+#[server]
+pub async fn has_scale() -> Result<bool, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    Ok(read_scale_port(&pool).await.is_some())
+}
+
+/// Reads the current weight in kg from the scale attached to the server.
+#[server]
+pub async fn read_scale_weight() -> Result<f64, ServerFnError> {
+    let pool = expect_context::<sqlx::SqlitePool>();
+    let port = read_scale_port(&pool)
+        .await
+        .ok_or_else(|| not_found("No scale is set up"))?;
+    tokio::task::spawn_blocking(move || {
+        rustpos_common::scale::read_weight(&port).map_err(|e| e.to_string())
+    })
+    .await
+    .map_err(|e| ServerFnError::new(e.to_string()))?
+    .map_err(|e| not_found(&format!("Scale: {}", e)))
+}
+
 /// Print a sales breakdown for the given period on the local printer:
 /// per-item quantity sold and total sale value, plus period totals.
 #[server]
@@ -2380,46 +8067,37 @@ pub async fn print_sales_report(
     end_date: DateTime<Utc>,
     filter: GroupFilter,
 ) -> Result<(), ServerFnError> {
-    use crate::printer::{find_printer, print_sales_report as print_sr};
+    use crate::printer::{find_printer, print_sales_report as print_sr, SalesReportSummary};
 
     let pool = expect_context::<sqlx::SqlitePool>();
     require_admin(&pool).await?;
 
     let report = generate_sales_report_db(&pool, start_date, end_date, &filter).await?;
-    let currency: String =
-        sqlx::query_scalar("SELECT value FROM config WHERE key = 'currency'")
-            .fetch_optional(&pool)
-            .await
-            .ok()
-            .flatten()
-            .unwrap_or_default();
-
-    let items: Vec<(String, u32, f32)> = report
-        .items
-        .iter()
-        .map(|i| (i.item_name.clone(), i.quantity_sold as u32, i.total_revenue as f32))
-        .collect();
-    let total_items_sold = report.summary.total_items_sold as u32;
-    let total_revenue = report.summary.total_revenue as f32;
-    let period = format!(
-        "{} to {}",
-        start_date.format("%Y-%m-%d"),
-        end_date.format("%Y-%m-%d")
-    );
+    let currency = currency_db(&pool).await?;
+    let decimals = currency_decimals(&currency);
+
+    let summary = SalesReportSummary {
+        period: format!(
+            "{} to {}",
+            start_date.format("%Y-%m-%d"),
+            end_date.format("%Y-%m-%d")
+        ),
+        currency,
+        decimals,
+        items: report
+            .items
+            .iter()
+            .map(|i| (i.item_name.clone(), i.quantity_sold.whole_units() as u32, i.total_revenue.0))
+            .collect(),
+        total_items_sold: report.summary.total_items_sold as u32,
+        total_revenue: report.summary.total_revenue.0,
+        taxes: tax_summaries(&report.taxes),
+    };
     let now = chrono::Local::now();
 
     let result: Result<(), String> = tokio::task::spawn_blocking(move || {
         let (_, mut printer) = find_printer().map_err(|e| e.to_string())?;
-        print_sr(
-            &mut printer,
-            &period,
-            &currency,
-            items,
-            total_items_sold,
-            total_revenue,
-            now,
-            Some("data/logo_receipt.png"),
-        )
+        print_sr(&mut printer, &summary, now, Some("data/logo_receipt.png"))
         .map_err(|e| e.to_string())
     })
     .await
@@ -2458,3 +8136,426 @@ pub async fn get_system_ip_addresses() -> Result<Vec<String>, ServerFnError> {
     addrs.sort();
     Ok(addrs.into_iter().map(|(_, s)| s).collect())
 }
+
+#[cfg(all(test, feature = "ssr"))]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use sqlx::SqlitePool;
+
+    /// A fresh in-memory database. It has a single connection, so anything
+    /// reaching for the pool while a transaction holds it times out.
+    async fn memory_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(std::time::Duration::from_secs(2))
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        crate::db::migrate(&pool).await;
+        pool
+    }
+
+    /// A fresh database in a file, for tests that need several connections.
+    async fn file_pool() -> (SqlitePool, std::path::PathBuf) {
+        let path = std::env::temp_dir().join(format!("rustpos-test-{}.db", Uuid::new_v4()));
+        let pool = SqlitePoolOptions::new()
+            .max_connections(4)
+            .connect(&format!("sqlite:{}?mode=rwc", path.display()))
+            .await
+            .unwrap();
+        crate::db::migrate(&pool).await;
+        (pool, path)
+    }
+
+    /// Opens a sale with one line per `(unit price, quantity)`.
+    async fn open_sale(pool: &SqlitePool, lines: &[(i64, i32)]) -> Uuid {
+        let now = Utc::now();
+        let category_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO categories (id, name, created_at, updated_at) VALUES (?, 'Test', ?, ?)")
+            .bind(category_id)
+            .bind(now)
+            .bind(now)
+            .execute(pool)
+            .await
+            .unwrap();
+        let id = Uuid::new_v4();
+        sqlx::query("INSERT INTO transactions (id, status, created_at, updated_at) VALUES (?, 'open', ?, ?)")
+            .bind(id)
+            .bind(now)
+            .bind(now)
+            .execute(pool)
+            .await
+            .unwrap();
+        for (n, (price, quantity)) in lines.iter().enumerate() {
+            let item_id = Uuid::new_v4();
+            sqlx::query(
+                "INSERT INTO items (id, name, price, category_id, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?)",
+            )
+            .bind(item_id)
+            .bind(format!("Item {n}"))
+            .bind(price)
+            .bind(category_id)
+            .bind(now)
+            .bind(now)
+            .execute(pool)
+            .await
+            .unwrap();
+            sqlx::query(
+                "INSERT INTO transaction_items (id, transaction_id, item_id, quantity, unit_price, total_price, created_at)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )
+            .bind(Uuid::new_v4())
+            .bind(id)
+            .bind(item_id)
+            .bind(Quantity::units(*quantity as i64))
+            .bind(price)
+            .bind(price * *quantity as i64)
+            .bind(now + chrono::Duration::milliseconds(n as i64))
+            .execute(pool)
+            .await
+            .unwrap();
+        }
+        update_transaction_total_db(pool, id).await.unwrap();
+        id
+    }
+
+    fn cash(amount: i64) -> Vec<PaymentInput> {
+        vec![PaymentInput { tender: "cash".to_string(), amount: Money(amount), card_code: None }]
+    }
+
+    async fn count(pool: &SqlitePool, sql: &str, id: Uuid) -> i64 {
+        sqlx::query_scalar(sql).bind(id).fetch_one(pool).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn close_settles_the_sale() {
+        let pool = memory_pool().await;
+        let id = open_sale(&pool, &[(250, 2), (100, 1)]).await;
+        let (response, job) = close_transaction_db(&pool, id, &cash(1000), Money::ZERO, None).await.unwrap();
+        assert_eq!(response.transaction.status, "closed");
+        assert_eq!(response.transaction.total, Money(600));
+        assert_eq!(response.change_amount, Money(400));
+        assert_eq!(job.items.len(), 2);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM payments WHERE transaction_id = ?", id).await, 1);
+    }
+
+    #[tokio::test]
+    async fn a_sale_is_journaled_once() {
+        let pool = memory_pool().await;
+        let id = open_sale(&pool, &[(250, 2)]).await;
+        let (_, job) = close_transaction_db(&pool, id, &cash(500), Money::ZERO, None).await.unwrap();
+        assert_eq!(job.journal.map(|(seq, _)| seq), Some(1));
+        let mut tx = pool.begin().await.unwrap();
+        assert!(append_journal_db(&mut tx, id, "sale").await.is_err());
+        drop(tx);
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM journal WHERE transaction_id = ?", id).await, 1);
+        assert!(verify_journal_db(&pool).await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn a_sale_is_closed_once() {
+        let (pool, path) = file_pool().await;
+        let id = open_sale(&pool, &[(250, 2)]).await;
+        let payments = cash(500);
+        let (first, second) = tokio::join!(
+            close_transaction_db(&pool, id, &payments, Money::ZERO, None),
+            close_transaction_db(&pool, id, &payments, Money::ZERO, None),
+        );
+        assert!(first.is_ok() != second.is_ok());
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM payments WHERE transaction_id = ?", id).await, 1);
+        assert!(close_transaction_db(&pool, id, &payments, Money::ZERO, None).await.is_err());
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM payments WHERE transaction_id = ?", id).await, 1);
+        pool.close().await;
+        std::fs::remove_file(path).ok();
+    }
+
+    /// Closes a sale with one line of `quantity` units at `price` and
+    /// returns it with the id of its line.
+    async fn sold(pool: &SqlitePool, price: i64, quantity: i32) -> (Uuid, Uuid) {
+        let id = open_sale(pool, &[(price, quantity)]).await;
+        close_transaction_db(pool, id, &cash(price * quantity as i64), Money::ZERO, None).await.unwrap();
+        let line_id = sqlx::query_scalar("SELECT id FROM transaction_items WHERE transaction_id = ?")
+            .bind(id)
+            .fetch_one(pool)
+            .await
+            .unwrap();
+        (id, line_id)
+    }
+
+    fn units(line_id: Uuid, quantity: i32) -> Vec<RefundLine> {
+        vec![RefundLine { transaction_item_id: line_id, quantity: Quantity::units(quantity as i64) }]
+    }
+
+    #[tokio::test]
+    async fn refunding_more_than_was_sold_is_refused() {
+        let pool = memory_pool().await;
+        let (id, line_id) = sold(&pool, 250, 2).await;
+        assert!(refund_transaction_db(&pool, id, units(line_id, 3), "cash", "admin").await.is_err());
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM transactions WHERE refund_of = ?", id).await, 0);
+    }
+
+    #[tokio::test]
+    async fn partial_refunds_give_back_what_was_sold() {
+        let pool = memory_pool().await;
+        let (id, line_id) = sold(&pool, 250, 3).await;
+        let (first, job) = refund_transaction_db(&pool, id, units(line_id, 1), "cash", "admin").await.unwrap();
+        assert_eq!(first.total, Money(-250));
+        assert_eq!(job.items, vec![("Item 0".to_string(), 1, 250)]);
+        let (second, _) = refund_transaction_db(&pool, id, units(line_id, 1), "cash", "admin").await.unwrap();
+        assert_eq!(second.total, Money(-250));
+        assert!(refund_transaction_db(&pool, id, units(line_id, 2), "cash", "admin").await.is_err());
+        refund_transaction_db(&pool, id, units(line_id, 1), "cash", "admin").await.unwrap();
+        assert!(refund_transaction_db(&pool, id, units(line_id, 1), "cash", "admin").await.is_err());
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM transactions WHERE refund_of = ?", id).await, 3);
+        assert_eq!(
+            count(&pool, "SELECT SUM(total) FROM transactions WHERE refund_of = ?", id).await,
+            -750
+        );
+    }
+
+    #[tokio::test]
+    async fn units_are_refunded_once() {
+        let (pool, path) = file_pool().await;
+        let (id, line_id) = sold(&pool, 250, 2).await;
+        let (first, second) = tokio::join!(
+            refund_transaction_db(&pool, id, units(line_id, 2), "cash", "admin"),
+            refund_transaction_db(&pool, id, units(line_id, 2), "cash", "admin"),
+        );
+        assert!(first.is_ok() != second.is_ok());
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM transactions WHERE refund_of = ?", id).await, 1);
+        pool.close().await;
+        std::fs::remove_file(path).ok();
+    }
+
+    #[tokio::test]
+    async fn the_export_follows_the_z_closings() {
+        let pool = memory_pool().await;
+        let start = Utc::now() - chrono::Duration::hours(1);
+        let (first, _) = sold(&pool, 250, 1).await;
+        create_z_closing_db(&pool, "admin".to_string()).await.unwrap();
+        let (second, _) = sold(&pool, 300, 1).await;
+        create_z_closing_db(&pool, "admin".to_string()).await.unwrap();
+        sold(&pool, 400, 1).await;
+
+        let export = dsfinvk_export_db(&pool, start, Utc::now() + chrono::Duration::hours(1)).await.unwrap();
+        let closings: Vec<(i64, Vec<Uuid>)> = export
+            .closings
+            .iter()
+            .map(|c| (c.number, c.receipts.iter().map(|r| r.transaction.id).collect()))
+            .collect();
+        assert_eq!(closings, vec![(1, vec![first]), (2, vec![second])]);
+    }
+
+    #[tokio::test]
+    async fn a_weighed_line_keeps_its_weight_as_the_quantity() {
+        let pool = memory_pool().await;
+        let id = open_sale(&pool, &[]).await;
+        let item = sqlx::query_as::<_, Item>(
+            "INSERT INTO items (id, name, price, category_id, created_at, updated_at)
+             SELECT ?, 'Cheese', 2990, id, created_at, created_at FROM categories RETURNING *",
+        )
+        .bind(Uuid::new_v4())
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        add_priced_line_db(&pool, id, &item, Money(2990), Some(Quantity(535))).await.unwrap();
+        add_priced_line_db(&pool, id, &item, Money(2990), Some(Quantity(250))).await.unwrap();
+
+        let lines = transaction_lines_db(&mut pool.acquire().await.unwrap(), id).await.unwrap();
+        assert_eq!(lines.len(), 2);
+        assert_eq!((lines[0].quantity, lines[0].unit.as_deref()), (Quantity(535), Some("kg")));
+        assert_eq!(lines[0].total_price, Money(1600));
+        let total = open_transaction_db(&pool, id).await.unwrap().total;
+        assert_eq!(total, Money(2348));
+        close_transaction_db(&pool, id, &cash(total.0), Money::ZERO, None).await.unwrap();
+
+        let part = vec![RefundLine { transaction_item_id: lines[0].id, quantity: Quantity(500) }];
+        assert!(refund_transaction_db(&pool, id, part, "cash", "admin").await.is_err());
+        let whole = vec![RefundLine { transaction_item_id: lines[0].id, quantity: Quantity(535) }];
+        let (refund, job) = refund_transaction_db(&pool, id, whole, "cash", "admin").await.unwrap();
+        assert_eq!(refund.total, Money(-1600));
+        assert_eq!(job.items, vec![("Cheese".to_string(), 1, 1600)]);
+        assert_eq!(job.weights, vec![Some((0.535, "kg".to_string(), 2990))]);
+    }
+
+    /// Gives a sale an amount off the whole of it, in major units.
+    async fn discount_sale(pool: &SqlitePool, id: Uuid, amount: f64) {
+        sqlx::query(
+            "INSERT INTO manual_discounts (id, transaction_id, discount_type, discount_value, reason, created_at)
+             VALUES (?, ?, 'amount', ?, 'Goodwill', ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(id)
+        .bind(amount)
+        .bind(Utc::now())
+        .execute(pool)
+        .await
+        .unwrap();
+        update_transaction_total_db(pool, id).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn merging_keeps_the_discount_on_the_merged_tab() {
+        let pool = memory_pool().await;
+        let from = open_sale(&pool, &[(500, 2)]).await;
+        let into = open_sale(&pool, &[(300, 1)]).await;
+        discount_sale(&pool, from, 1.5).await;
+        assert_eq!(open_transaction_db(&pool, from).await.unwrap().total, Money(850));
+
+        let merged = merge_transactions_db(&pool, from, into).await.unwrap();
+        assert_eq!(merged.total, Money(1150));
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM manual_discounts WHERE transaction_id = ?", into).await, 1);
+        assert!(open_transaction_db(&pool, from).await.is_err());
+    }
+
+    async fn quantities(pool: &SqlitePool, id: Uuid) -> Vec<i32> {
+        sqlx::query_scalar("SELECT quantity / 1000 FROM transaction_items WHERE transaction_id = ? ORDER BY created_at")
+            .bind(id)
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn a_partial_move_splits_the_line() {
+        let pool = memory_pool().await;
+        let from = open_sale(&pool, &[(250, 3), (100, 1)]).await;
+        let to = open_sale(&pool, &[(300, 1)]).await;
+        let lines = transaction_lines_db(&mut pool.acquire().await.unwrap(), from).await.unwrap();
+        let moves = [LineMove { transaction_item_id: lines[0].id, quantity: Quantity::units(2) }];
+
+        move_transaction_items_db(&pool, from, to, &moves).await.unwrap();
+        assert_eq!(quantities(&pool, from).await, vec![1, 1]);
+        assert_eq!(quantities(&pool, to).await, vec![1, 2]);
+        assert_eq!(open_transaction_db(&pool, from).await.unwrap().total, Money(350));
+        assert_eq!(open_transaction_db(&pool, to).await.unwrap().total, Money(800));
+    }
+
+    #[tokio::test]
+    async fn a_move_that_fails_part_way_moves_nothing() {
+        let pool = memory_pool().await;
+        let from = open_sale(&pool, &[(250, 3), (100, 1)]).await;
+        let to = open_sale(&pool, &[(300, 1)]).await;
+        let lines = transaction_lines_db(&mut pool.acquire().await.unwrap(), from).await.unwrap();
+        let moves = [
+            LineMove { transaction_item_id: lines[0].id, quantity: Quantity::units(2) },
+            LineMove { transaction_item_id: lines[1].id, quantity: Quantity::units(5) },
+        ];
+
+        assert!(move_transaction_items_db(&pool, from, to, &moves).await.is_err());
+        assert_eq!(quantities(&pool, from).await, vec![3, 1]);
+        assert_eq!(quantities(&pool, to).await, vec![1]);
+        assert_eq!(open_transaction_db(&pool, from).await.unwrap().total, Money(850));
+    }
+
+    #[tokio::test]
+    async fn a_line_discount_moves_with_the_units() {
+        let pool = memory_pool().await;
+        let from = open_sale(&pool, &[(250, 3)]).await;
+        let to = open_sale(&pool, &[(300, 1)]).await;
+        let line_id: Uuid = sqlx::query_scalar("SELECT id FROM transaction_items WHERE transaction_id = ?")
+            .bind(from)
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO manual_discounts (id, transaction_id, transaction_item_id, discount_type, discount_value, reason, created_at)
+             VALUES (?, ?, ?, 'amount', 1.5, 'Goodwill', ?)",
+        )
+        .bind(Uuid::new_v4())
+        .bind(from)
+        .bind(line_id)
+        .bind(Utc::now())
+        .execute(&pool)
+        .await
+        .unwrap();
+        update_transaction_total_db(&pool, from).await.unwrap();
+        assert_eq!(open_transaction_db(&pool, from).await.unwrap().total, Money(600));
+
+        let part = [LineMove { transaction_item_id: line_id, quantity: Quantity::units(2) }];
+        move_transaction_items_db(&pool, from, to, &part).await.unwrap();
+        assert_eq!(open_transaction_db(&pool, from).await.unwrap().total, Money(200));
+        assert_eq!(open_transaction_db(&pool, to).await.unwrap().total, Money(700));
+
+        // The rest does not join the moved units' line, which has its own discount.
+        let rest = [LineMove { transaction_item_id: line_id, quantity: Quantity::ONE }];
+        move_transaction_items_db(&pool, from, to, &rest).await.unwrap();
+        assert_eq!(quantities(&pool, to).await, vec![1, 1, 2]);
+        assert_eq!(open_transaction_db(&pool, to).await.unwrap().total, Money(900));
+    }
+
+    #[tokio::test]
+    async fn a_split_keeps_the_total() {
+        let pool = memory_pool().await;
+        let id = open_sale(&pool, &[(250, 3), (100, 1)]).await;
+        let tabs = split_transaction_db(&pool, id, 2).await.unwrap();
+        assert_eq!(tabs.len(), 2);
+        assert_eq!(tabs.iter().map(|t| t.total).sum::<Money>(), Money(850));
+        assert!(tabs.iter().all(|t| t.total.is_positive()));
+    }
+
+    struct BrokenSigner;
+
+    impl crate::fiscal::FiscalSigner for BrokenSigner {
+        fn sign(&self, _: &crate::fiscal::SignRequest) -> Result<crate::fiscal::FiscalSignature, String> {
+            Err("no signer attached".to_string())
+        }
+
+        fn info(&self) -> crate::fiscal::SignerInfo {
+            unreachable!()
+        }
+    }
+
+    #[tokio::test]
+    async fn an_unsigned_sale_is_refused_unless_the_signer_is_out() {
+        let pool = memory_pool().await;
+        let owner = Owner::new();
+        owner.set();
+        provide_context(crate::fiscal::FiscalSignerHandle(std::sync::Arc::new(BrokenSigner)));
+        let id = open_sale(&pool, &[(250, 2)]).await;
+
+        assert!(close_transaction_db(&pool, id, &cash(500), Money::ZERO, None).await.is_err());
+        assert_eq!(open_transaction_db(&pool, id).await.unwrap().status, "open");
+        assert_eq!(count(&pool, "SELECT COUNT(*) FROM journal WHERE transaction_id = ?", id).await, 0);
+
+        sqlx::query("INSERT INTO config (key, value) VALUES ('fiscal_outage', 'true')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        let (response, job) = close_transaction_db(&pool, id, &cash(500), Money::ZERO, None).await.unwrap();
+        assert_eq!(response.transaction.fiscal_error.as_deref(), Some("no signer attached"));
+        assert_eq!(job.fiscal_error.as_deref(), Some("no signer attached"));
+    }
+
+    #[tokio::test]
+    async fn a_gift_card_is_not_spent_twice() {
+        let (pool, path) = file_pool().await;
+        let card_id = Uuid::new_v4();
+        sqlx::query("INSERT INTO gift_cards (id, code, created_at, updated_at) VALUES (?, 'GC12345678', ?, ?)")
+            .bind(card_id)
+            .bind(Utc::now())
+            .bind(Utc::now())
+            .execute(&pool)
+            .await
+            .unwrap();
+        add_gift_card_entry_db(&mut pool.acquire().await.unwrap(), card_id, None, "top_up", Money(500), None)
+            .await
+            .unwrap();
+        let first_sale = open_sale(&pool, &[(400, 1)]).await;
+        let second_sale = open_sale(&pool, &[(400, 1)]).await;
+        let payments = vec![PaymentInput {
+            tender: "gift_card".to_string(),
+            amount: Money(400),
+            card_code: Some("GC12345678".to_string()),
+        }];
+        let (first, second) = tokio::join!(
+            close_transaction_db(&pool, first_sale, &payments, Money::ZERO, None),
+            close_transaction_db(&pool, second_sale, &payments, Money::ZERO, None),
+        );
+        assert!(first.is_ok() != second.is_ok());
+        assert_eq!(gift_card_balance_db(&pool, card_id).await.unwrap(), Money(100));
+        pool.close().await;
+        std::fs::remove_file(path).ok();
+    }
+}
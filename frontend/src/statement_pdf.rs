@@ -0,0 +1,180 @@
+//! Server-side generation of an A4 house account statement.
+//!
+//! Shows the logo, the statement title, the account and the period, then one
+//! row per on-account sale (or refund credited back) in the period with its
+//! date, description, amount and whether it is paid, the settlements received
+//! in the period, and at the bottom what of the period is unpaid and what is
+//! owed on the account overall. Rows flow onto new pages, each repeating the
+//! column headings. Drawing uses the page primitives of `menu_pdf`.
+
+use crate::menu_pdf::{black, ellipsize, gray, load_image_rgb, Pdf, BOTTOM_LIMIT, CONTENT_W, MARGIN, PAGE_W, PT_TO_MM};
+use crate::models::{Money, StatementLabels};
+
+/// An on-account sale or credit on the statement.
+pub struct StatementRow {
+    pub date: String,
+    pub description: String,
+    pub amount: Money,
+    pub paid: bool,
+}
+
+/// A settlement payment received in the period.
+pub struct StatementPayment {
+    pub date: String,
+    pub tender: String,
+    pub amount: Money,
+}
+
+/// Everything printed on one account's statement.
+pub struct StatementSheet {
+    pub account_name: String,
+    pub period: String,
+    pub rows: Vec<StatementRow>,
+    pub payments: Vec<StatementPayment>,
+    pub unpaid: Money,
+    pub outstanding: Money,
+}
+
+const TITLE: f32 = 22.0;
+const TEXT: f32 = 10.5;
+const ROW_H: f32 = TEXT * PT_TO_MM + 2.5;
+
+// Column left edges; amounts are right-aligned against `AMOUNT_RIGHT`.
+const DATE_X: f32 = MARGIN;
+const DESC_X: f32 = MARGIN + 34.0;
+const AMOUNT_RIGHT: f32 = PAGE_W - MARGIN - 26.0;
+const STATUS_X: f32 = PAGE_W - MARGIN - 22.0;
+
+fn heading_row(pdf: &mut Pdf, labels: &StatementLabels) {
+    pdf.text(&labels.date, TEXT, DATE_X, pdf.y, true, gray());
+    pdf.text(&labels.description, TEXT, DESC_X, pdf.y, true, gray());
+    let w = Pdf::text_width(&labels.amount, TEXT);
+    pdf.text(&labels.amount, TEXT, AMOUNT_RIGHT - w, pdf.y, true, gray());
+    pdf.text(&labels.status, TEXT, STATUS_X, pdf.y, true, gray());
+    pdf.y += ROW_H;
+    pdf.hline(MARGIN, PAGE_W - MARGIN, pdf.y - 1.2);
+}
+
+/// Draws `label` on the left and `amount` right-aligned, in bold.
+fn total_row(pdf: &mut Pdf, label: &str, amount: &str) {
+    pdf.ensure(ROW_H);
+    pdf.text(label, TEXT, DESC_X, pdf.y, true, black());
+    let w = Pdf::text_width(amount, TEXT);
+    pdf.text(amount, TEXT, AMOUNT_RIGHT - w, pdf.y, true, black());
+    pdf.y += ROW_H;
+}
+
+/// Builds the statement PDF and returns the raw bytes.
+pub fn build_statement_pdf(
+    labels: &StatementLabels,
+    currency: &str,
+    logo_path: &str,
+    sheet: &StatementSheet,
+) -> Result<Vec<u8>, String> {
+    let mut pdf = Pdf::new(&labels.title)?;
+    let money = |amount: Money| format!("{} {}", currency, amount.format(currency));
+
+    // --- Header: logo on the left, title and account below ---
+    if let Some((rgb, w, h)) = load_image_rgb(logo_path) {
+        let box_w = 60.0_f32.min(CONTENT_W);
+        let box_h = (box_w / (w as f32 / h as f32)).min(20.0);
+        pdf.image(rgb, w, h, MARGIN, pdf.y, box_w, box_h);
+        pdf.y += box_h + 6.0;
+    }
+    pdf.text(&labels.title, TITLE, MARGIN, pdf.y, true, black());
+    pdf.y += TITLE * PT_TO_MM * 1.4;
+    pdf.text(&sheet.account_name, TEXT + 2.0, MARGIN, pdf.y, true, black());
+    pdf.y += ROW_H + 1.0;
+    pdf.text(&format!("{}: {}", labels.period, sheet.period), TEXT, MARGIN, pdf.y, false, black());
+    pdf.y += ROW_H + 5.0;
+
+    // --- Charges ---
+    heading_row(&mut pdf, labels);
+    for row in &sheet.rows {
+        if pdf.y + ROW_H > BOTTOM_LIMIT {
+            pdf.new_page();
+            heading_row(&mut pdf, labels);
+        }
+        pdf.text(&row.date, TEXT, DATE_X, pdf.y, false, black());
+        let desc = ellipsize(&row.description, TEXT, AMOUNT_RIGHT - DESC_X - 28.0);
+        pdf.text(&desc, TEXT, DESC_X, pdf.y, false, black());
+        let amount = money(row.amount);
+        let w = Pdf::text_width(&amount, TEXT);
+        pdf.text(&amount, TEXT, AMOUNT_RIGHT - w, pdf.y, false, black());
+        let (status, color) = if row.paid { (&labels.paid, gray()) } else { (&labels.open, black()) };
+        pdf.text(status, TEXT, STATUS_X, pdf.y, !row.paid, color);
+        pdf.y += ROW_H;
+    }
+
+    // --- Settlements received in the period ---
+    if !sheet.payments.is_empty() {
+        pdf.ensure(ROW_H * 3.0);
+        pdf.y += 4.0;
+        pdf.text(&labels.settlements, TEXT, MARGIN, pdf.y, true, gray());
+        pdf.y += ROW_H;
+        for payment in &sheet.payments {
+            pdf.ensure(ROW_H);
+            pdf.text(&payment.date, TEXT, DATE_X, pdf.y, false, black());
+            pdf.text(&payment.tender, TEXT, DESC_X, pdf.y, false, black());
+            let amount = money(payment.amount);
+            let w = Pdf::text_width(&amount, TEXT);
+            pdf.text(&amount, TEXT, AMOUNT_RIGHT - w, pdf.y, false, black());
+            pdf.y += ROW_H;
+        }
+    }
+
+    // --- Totals ---
+    pdf.ensure(ROW_H * 2.0 + 6.0);
+    pdf.y += 3.0;
+    pdf.hline(DESC_X, AMOUNT_RIGHT, pdf.y);
+    pdf.y += 3.0;
+    total_row(&mut pdf, &labels.unpaid, &money(sheet.unpaid));
+    total_row(&mut pdf, &labels.outstanding, &money(sheet.outstanding));
+
+    pdf.save()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels() -> StatementLabels {
+        StatementLabels {
+            title: "Statement".into(),
+            period: "Period".into(),
+            date: "Date".into(),
+            description: "Sale".into(),
+            amount: "Amount".into(),
+            status: "Status".into(),
+            paid: "Paid".into(),
+            open: "Open".into(),
+            settlements: "Payments received".into(),
+            unpaid: "Unpaid in this period".into(),
+            outstanding: "Outstanding balance".into(),
+            tenders: vec![("card".into(), "Card".into())],
+        }
+    }
+
+    #[test]
+    fn builds_a_paginated_statement() {
+        // Enough rows to overflow a single page and repeat the headings.
+        let rows: Vec<StatementRow> = (0..90)
+            .map(|i| StatementRow {
+                date: "2026-03-01".into(),
+                description: format!("Sale {i} – Jane Doe"),
+                amount: Money(1250),
+                paid: i % 3 == 0,
+            })
+            .collect();
+        let sheet = StatementSheet {
+            account_name: "Staff".into(),
+            period: "2026-03-01 – 2026-03-31".into(),
+            rows,
+            payments: vec![StatementPayment { date: "2026-03-31".into(), tender: "Card".into(), amount: Money(37500) }],
+            unpaid: Money(75000),
+            outstanding: Money(75000),
+        };
+        let bytes = build_statement_pdf(&labels(), "€", "does/not/exist.png", &sheet).unwrap();
+        assert!(bytes.starts_with(b"%PDF"), "output should be a PDF");
+    }
+}
@@ -1,5 +1,5 @@
 use futures_util::{SinkExt, StreamExt};
-use rustpos_common::printer::{find_printer, print_receipt, set_codepage};
+use rustpos_common::printer::{find_printer, print_receipt, print_refund_receipt, set_codepage};
 use rustpos_common::protocol::*;
 use tokio_tungstenite::tungstenite::Message;
 
@@ -114,16 +114,16 @@ async fn connect_and_run(
             match serde_json::from_str::<ServerMessage>(&text) {
                 Ok(ServerMessage::PrintReceipt(job)) => {
                     println!(
-                        "Received print job: {} items, total {:.2}",
+                        "Received print job: {} items, total {}",
                         job.items.len(),
-                        job.items
-                            .iter()
-                            .map(|(_, q, p)| *q as f32 * p)
-                            .sum::<f32>()
+                        format_amount(
+                            job.items.iter().map(|(_, q, p)| *q as i64 * p).sum::<i64>(),
+                            job.decimals
+                        )
                     );
                     let logo_ref = logo_path.clone();
                     let result =
-                        tokio::task::spawn_blocking(move || print_job(job, logo_ref.as_deref()))
+                        tokio::task::spawn_blocking(move || print_job(*job, logo_ref.as_deref()))
                             .await?;
 
                     let response = match result {
@@ -197,14 +197,11 @@ fn print_job(
         })
         .unwrap_or_else(|_| chrono::Local::now());
 
-    print_receipt(
-        &mut printer,
-        job.items,
-        job.paid_amount,
-        job.change,
-        datetime,
-        logo_path,
-    )
-    .map_err(|e| e.to_string())?;
+    let result = if job.refund_of.is_some() {
+        print_refund_receipt(&mut printer, &job, datetime, logo_path)
+    } else {
+        print_receipt(&mut printer, &job, datetime, logo_path)
+    };
+    result.map_err(|e| e.to_string())?;
     Ok(())
 }